@@ -201,17 +201,8 @@ export const PaymentRecordingForm: React.FC<PaymentRecordingFormProps> = ({
         recordedBy: appUser.id,
       });
 
-      // Update fee assignments with payment allocations
+      // The satellite applies confirmed payments to the fee assignment
       try {
-        await studentFeeAssignmentService.recordPayment(
-          mainAssignment.id,
-          values.amount,
-          paymentAllocations.map((alloc) => ({
-            categoryId: alloc.categoryId,
-            amount: alloc.amount,
-          })),
-        );
-
         // Recalculate student totals
         await studentFeeAssignmentService.recalculateStudentTotals(student.id);
       } catch (assignmentError) {
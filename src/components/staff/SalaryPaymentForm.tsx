@@ -47,6 +47,7 @@ import {
   type StaffPenalty,
 } from "@/services/staffFinancialService";
 import { useSchool } from "@/contexts/SchoolContext";
+import { useAuth } from "@/contexts/AuthContext";
 
 interface SalaryPaymentFormProps {
   staffId?: string;
@@ -73,6 +74,7 @@ export default function SalaryPaymentForm({
 }: SalaryPaymentFormProps) {
   const { toast } = useToast();
   const { config } = useSchool();
+  const { appUser } = useAuth();
   const [loading, setLoading] = useState(false);
   const [staffLoading, setStaffLoading] = useState(false);
   const [selectedStaff, setSelectedStaff] = useState<StaffMember | null>(null);
@@ -588,6 +590,15 @@ export default function SalaryPaymentForm({
       return;
     }
 
+    if (!appUser) {
+      toast({
+        title: "Authentication Required",
+        description: "Please sign in to record salary payments",
+        variant: "destructive",
+      });
+      return;
+    }
+
     setLoading(true);
     try {
       const paymentPayload = {
@@ -601,7 +612,7 @@ export default function SalaryPaymentForm({
         deductions: deductions as PaymentDeduction[],
         paymentMethod: formData.paymentMethod,
         paymentDate: formData.paymentDate,
-        recordedBy: "current-user", // Replace with actual user ID
+        recordedBy: appUser.id,
       };
 
       if (paymentData) {
@@ -1,91 +1,1099 @@
 //! Main entry point for the Satellite canister
 
 use junobuild_macros::{
-    assert_delete_asset, assert_delete_doc, assert_set_doc, assert_upload_asset,
+    assert_delete_asset, assert_delete_doc, assert_set_doc, assert_upload_asset, on_delete_doc, on_init,
+    on_post_upgrade, on_set_doc,
 };
 use junobuild_satellite::{
     include_satellite, AssertDeleteAssetContext, AssertDeleteDocContext, AssertSetDocContext,
-    AssertUploadAssetContext,
+    AssertUploadAssetContext, OnDeleteDocContext, OnSetDocContext,
 };
+use ic_cdk::management_canister::{HttpRequestResult, TransformArgs};
+use junobuild_utils::decode_doc_data;
 
 // Import modules
 pub mod modules {
+    pub mod archival;
+    pub mod audit;
     pub mod banking;
+    pub mod budgets;
+    pub mod cash_transit;
+    pub mod category_usage;
+    pub mod charges;
+    pub mod classes;
+    pub mod close;
+    pub mod devices;
+    pub mod disbursements;
+    pub mod documents;
+    pub mod duty_claims;
+    pub mod enrollment;
     pub mod expenses;
+    pub mod family_invoices;
     pub mod fees;
+    pub mod fx;
+    pub mod garnishments;
+    pub mod guardians;
+    pub mod health;
+    pub mod history;
+    pub mod gateway;
+    pub mod icrc_payments;
+    pub mod id_cards;
+    pub mod insurance;
+    pub mod inventory;
+    pub mod investments;
+    pub mod jobs;
+    pub mod late_fees;
+    pub mod ledger;
+    pub mod maintenance;
+    pub mod migrations;
+    pub mod notifications;
     pub mod payments;
+    pub mod payroll;
+    pub mod petty_cash;
+    pub mod procurement;
+    pub mod pta;
+    pub mod receipts;
+    pub mod reconciliation;
+    pub mod remittances;
+    pub mod replication;
+    pub mod reports;
+    pub mod results;
+    pub mod roles;
+    pub mod rules;
+    pub mod settings;
+    pub mod simulation;
+    pub mod sponsors;
     pub mod staff;
     pub mod students;
+    pub mod terms;
+    pub mod tips;
+    pub mod utilities;
     pub mod utils;
+    pub mod verification;
 }
 
 use modules::{
+    archival::{validate_archival, validate_archival_delete},
+    audit::{
+        record_doc_delete, record_doc_set,
+        sequences::{
+            audit_sequences as sequence_audit, validate_voided_number_delete, validate_voided_number_document,
+            SequenceAudit,
+        },
+        validate_audit_log_delete, validate_audit_log_document, AUDITED_COLLECTIONS,
+    },
     banking::{validate_bank_transaction, validate_transfer, validate_bank_account},
-    expenses::{validate_expense_document, validate_expense_category_document},
-    fees::{validate_student_fee_assignment, validate_scholarship},
-    payments::validate_payment_document,
-    staff::{validate_staff_document, validate_salary_payment_document},
-    students::validate_student_document,
+    budgets::{
+        encumbrances::{get_budget_lines_availability, validate_encumbrance_document, BudgetLineAvailability},
+        record_budget_spending, validate_budget_document,
+        virements::{on_budget_virement_saved, validate_budget_virement_document, BudgetVirementData},
+    },
+    cash_transit::{list_transit_alerts, validate_cash_movement_document, CashTransitAlert},
+    category_usage::{
+        get_category_usage as category_usage, rebuild_category_usage as recount_category_usage,
+        record_expense_usage, record_fee_assignment_usage, validate_category_usage_document, CategoryUsage,
+    },
+    charges::{on_student_charge_saved, validate_student_charge_document, StudentChargeData},
+    classes::{validate_class_delete, validate_class_document},
+    close::{
+        close_period, get_close_readiness, validate_period_close_delete, validate_period_close_document,
+        validate_period_open, validate_period_open_on_delete, CloseReadiness, CloseWaiver,
+    },
+    devices::validate_cashier_device_document,
+    disbursements::{
+        import_acknowledgments,
+        retries::{export_retry_file, validate_disbursement_retry_document, DisbursementFileEntry},
+        vendors::{build_vendor_payment_file, VendorPaymentFile},
+        validate_bank_acknowledgment_document, AcknowledgmentBatch, AcknowledgmentResult,
+    },
+    documents::{
+        get_payslip_render_data as payslip_render_data, get_receipt_render_data as receipt_render_data,
+        DocumentRenderData,
+    },
+    duty_claims::{
+        list_payable_claims, validate_duty_claim_document, validate_duty_rate_document,
+        PayableDutyClaim,
+    },
+    enrollment::{
+        billing::{assign_fees_to_class as bill_class, ClassBillingSummary},
+        promote_students as promote_class_students, validate_promotion_delete, validate_promotion_document,
+        PromotedStudent,
+    },
+    expenses::{
+        policies::validate_expense_policy_document, splits::post_expense_splits, validate_expense_category_delete,
+        validate_expense_category_document, validate_expense_document, ExpenseData,
+    },
+    family_invoices::{
+        generate_family_invoice as issue_family_invoice, on_family_payment_saved, validate_family_invoice_document,
+        validate_family_payment_document, FamilyInvoice, FamilyPaymentData,
+    },
+    fees::{
+        validate_fee_category, validate_fee_structure_document, validate_student_fee_assignment, validate_scholarship,
+        StudentFeeAssignmentData,
+    },
+    fx::{validate_exchange_rate_delete, validate_exchange_rate_document},
+    garnishments::validate_court_order_document,
+    guardians::{validate_guardian_delete, validate_guardian_document},
+    health::{health as system_health, HealthStatus},
+    history::{
+        get_document_history as document_history, record_doc_history, record_doc_history_delete,
+        validate_history_delete, validate_history_document, DocumentRevision,
+    },
+    gateway::{
+        on_online_payment_saved, transform_response, validate_gateway_settings_document,
+        validate_gateway_verification_document, verify_payment, GatewayVerification,
+    },
+    icrc_payments::{
+        on_crypto_payment_saved, validate_icrc_ledger_document, verify_crypto_payment as verify_crypto_transfer,
+        CryptoVerification,
+    },
+    id_cards::{issue_id_card, validate_id_card_issuance_delete, validate_id_card_issuance_document, IdCardIssuance},
+    insurance::{
+        get_claims_recovery_report, validate_insurance_claim_document,
+        validate_insurance_policy_document, ClaimRecoveryReportItem,
+    },
+    inventory::{
+        get_stock_levels as stock_levels, on_stock_movement_saved, validate_stock_item_delete,
+        validate_stock_item_document, validate_stock_movement_delete, validate_stock_movement_document, StockLevel,
+        StockMovementData,
+    },
+    investments::validate_investment_document,
+    jobs::schedule_jobs,
+    ledger::{
+        prepayments::{
+            get_prepaid_balances as prepaid_balances, on_prepayment_saved, validate_prepayment_delete,
+            validate_prepayment_document, PrepaidBalance, PrepaymentData,
+        },
+        recurring::{validate_recurring_journal_delete, validate_recurring_journal_document},
+    },
+    maintenance::{get_asset_maintenance_costs, validate_work_order_document, AssetMaintenanceCost},
+    migrations::{
+        import_legacy_documents as import_legacy, is_bypassed, list_validation_bypasses as validation_bypasses_page,
+        validate_validation_bypass_delete, validate_validation_bypass_document, LegacyDocument, ValidationBypassPage,
+    },
+    notifications::{
+        broadcasts::{queue_broadcast as queue_fee_broadcast, BroadcastFilter, BroadcastSummary},
+        delivery::{transform_response as transform_provider_response, validate_notification_settings_document},
+        fee_messages::queue_invoice_message,
+        validate_notification_document,
+    },
+    payments::{
+        batch::{record_payments_batch as record_payment_batch, BatchResult, PaymentInput},
+        on_payment_saved,
+        teller::{get_teller_prefill as teller_prefill, TellerPrefill},
+        unapplied::{list_unapplied_payments as unapplied_payments_page, UnappliedPaymentsPage},
+        validate_payment_document, PaymentData,
+    },
+    payroll::{run_payroll, validate_payroll_run_document, PayrollRunSummary},
+    petty_cash::{
+        floats::{retire_float as retire_petty_cash_float, validate_petty_cash_float_document, FloatRetirement},
+        validate_petty_cash_topup_document,
+        vouchers::validate_petty_cash_voucher_document,
+    },
+    procurement::{
+        record_purchase_order_payment, validate_purchase_order_document,
+        vendors::{validate_vendor_delete, validate_vendor_document},
+    },
+    pta::{get_pta_report, validate_fund_settings_document, PtaFundReport},
+    remittances::{
+        get_remittance_schedule, get_unremitted_deductions, validate_deduction_body_document,
+        validate_deduction_remittance_document, RemittanceScheduleItem,
+    },
+    receipts::{validate_receipt_delete, validate_receipt_document},
+    reconciliation::{
+        codes::{get_reference_match_reviews as reference_match_reviews, ReferenceMatchReview},
+        import_bank_statement as reconcile_bank_statement,
+        suspense::{get_suspense_report, validate_suspense_item_document, SuspenseReport},
+        validate_reconciliation_report_document, ReconciliationSummary, StatementRow,
+    },
+    replication::{
+        get_replication_status as replication_status, record_replication_event, sync_replication as push_replication,
+        validate_replication_outbox_delete, validate_replication_outbox_document,
+        validate_replication_settings_document, validate_replication_state_document, ReplicationStatus,
+        REPLICABLE_COLLECTIONS,
+    },
+    reports::{
+        get_comparative_report,
+        debtors::{list_debtors as debtors_page, DebtorsPage},
+        quality::{get_data_quality as data_quality, DataQualityReport},
+        statement::{get_student_statement as student_statement, StudentStatement},
+        summary::{get_financial_summary as financial_summary, FinancialSummary},
+        validate_report_rollup_document, ComparativeReport,
+    },
+    results::{
+        check_exam_entry as exam_entry_status, check_result_release as result_release_status,
+        policies::{
+            record_clearance_policy_change, validate_clearance_policy_delete, validate_clearance_policy_document,
+            validate_clearance_policy_history_document, ClearancePolicyData,
+        },
+        validate_result_release_settings_document, ClearanceStatus,
+    },
+    roles::{
+        rejections::report_rejected_write as record_reported_rejection,
+        validate_user_role_document,
+        working_hours::{validate_working_hours_document, validate_write_window},
+    },
+    rules::{get_rule_violation_metrics as rule_violation_metrics, validate_rule_violation_document, RuleMetric},
+    settings::validate_school_settings_document,
+    simulation::{simulate_transition as simulate_write, SimulatedDocument, TransitionSimulation},
+    sponsors::{
+        create_sponsor_invoice as issue_sponsor_invoice, get_sponsor_statement as sponsor_statement,
+        on_sponsor_payment_saved, validate_sponsor_document, validate_sponsor_invoice_document,
+        validate_sponsor_payment_document, SponsorInvoiceSummary, SponsorPaymentData, SponsorStatement,
+    },
+    staff::{
+        loans::{validate_staff_loan_delete, validate_staff_loan_document},
+        payslips::{validate_payslip_delete, validate_payslip_upload, PAYSLIPS_COLLECTION},
+        log_salary_hold_changes, on_salary_payment_saved, validate_staff_delete, validate_staff_document, validate_salary_payment_document,
+        SalaryPaymentData, StaffMemberData,
+    },
+    students::{
+        assets::{validate_student_asset_upload, STUDENT_ASSETS_COLLECTION},
+        validate_student_delete, validate_student_document,
+    },
+    terms::{
+        close_term as close_academic_term,
+        optional_fees::{validate_optional_fee_rollover_delete, validate_optional_fee_rollover_document},
+        validate_academic_term_delete, validate_academic_term_document, validate_term_open,
+        validate_term_open_on_delete, validate_term_summary_document, TermClassSummary,
+    },
+    tips::{file_tip, list_tips, validate_tip_delete, validate_tip_document, TipRecord},
+    utilities::{
+        fuel::{get_fuel_variance_report, validate_fuel_log_document, validate_generator_document, FuelVarianceItem},
+        list_cost_anomalies, validate_meter_reading_document, validate_utility_meter_document,
+        UtilityCostAnomaly,
+    },
+    utils::{
+        cache::invalidate as invalidate_cache,
+        counters::validate_counter_document,
+        doc_utils::Pagination,
+        idempotency::{validate_idempotency_key_delete, validate_idempotency_key_document},
+    },
+    verification::{self_test as run_self_test, SelfTestReport},
 };
 
 #[assert_set_doc(collections = [
     "bank_accounts",
     "bank_transactions",
+    "chart_of_accounts",
     "inter_account_transfers",
     "expenses", 
     "expense_categories", 
+    "expense_policies",
     "budgets", 
+    "budget_virements",
+    "encumbrances",
+    "purchase_orders",
+    "vendors",
     "students", 
+    "guardians",
     "payments", 
     "fee_categories", 
+    "fee_structures",
     "student_fee_assignments",
     "scholarships",
     "scholarship_applications",
+    "student_charges",
     "staff",
+    "staff_loans",
     "salary_payments",
-    "classes"
+    "duty_rates",
+    "duty_claims",
+    "court_orders",
+    "deduction_bodies",
+    "deduction_remittances",
+    "bank_acknowledgments",
+    "disbursement_retries",
+    "user_roles",
+    "petty_cash_topups",
+    "petty_cash_floats",
+    "petty_cash_vouchers",
+    "cash_movements",
+    "insurance_policies",
+    "insurance_claims",
+    "utility_meters",
+    "meter_readings",
+    "generators",
+    "fuel_logs",
+    "work_orders",
+    "stock_items",
+    "stock_movements",
+    "fund_settings",
+    "investments",
+    "recurring_journals",
+    "prepayments",
+    "classes",
+    "report_rollups",
+    "audit_logs",
+    "expenses_history",
+    "payments_history",
+    "staff_history",
+    "student_fee_assignments_history",
+    "voided_numbers",
+    "working_hours",
+    "cashier_devices",
+    "receipts",
+    "counters",
+    "idempotency_keys",
+    "tips",
+    "payroll_runs",
+    "period_closes",
+    "reconciliation_reports",
+    "suspense_items",
+    "id_card_issuances",
+    "gateway_settings",
+    "gateway_verifications",
+    "icrc_ledgers",
+    "result_release_settings",
+    "school_settings",
+    "exchange_rates",
+    "academic_terms",
+    "term_summaries",
+    "optional_fee_rollovers",
+    "clearance_policies",
+    "clearance_policy_history",
+    "sponsors",
+    "sponsor_invoices",
+    "sponsor_payments",
+    "family_invoices",
+    "family_payments",
+    "notification_queue",
+    "notification_settings",
+    "validation_bypasses",
+    "rule_violations",
+    "promotions",
+    "replication_settings",
+    "replication_outbox",
+    "replication_state",
+    "category_usage"
 ])]
 fn assert_set_doc(context: AssertSetDocContext) -> Result<(), String> {
+    // Financial writes are limited to working hours where a policy is configured
+    if AUDITED_COLLECTIONS.contains(&context.data.collection.as_str()) && !is_bypassed(&context, "write_window") {
+        validate_write_window(&context)?;
+    }
+    // Entries dated in a closed month or term are locked
+    if !is_bypassed(&context, "period_lock") {
+        validate_period_open(&context)?;
+    }
+    if !is_bypassed(&context, "term_lock") {
+        validate_term_open(&context)?;
+    }
+    // Settled documents are archived rather than removed, and archived ones are frozen
+    validate_archival(&context)?;
+    // Legacy imports may skip the collection's own rules
+    if is_bypassed(&context, "document") {
+        invalidate_cache(&context.data.collection);
+        return Ok(());
+    }
+
+    let validated = validate_collection_document(&context);
+    // Accepted writes to lookup collections are reloaded on the next read
+    if validated.is_ok() {
+        invalidate_cache(&context.data.collection);
+    }
+    validated
+}
+
+// The collection's own document rules, also run by `simulate_transition`
+fn validate_collection_document(context: &AssertSetDocContext) -> Result<(), String> {
     match context.data.collection.as_str() {
         // Banking Module
-        "bank_accounts" => validate_bank_account(&context),
-        "bank_transactions" => validate_bank_transaction(&context),
-        "inter_account_transfers" => validate_transfer(&context),
+        "bank_accounts" => validate_bank_account(context),
+        "bank_transactions" => validate_bank_transaction(context),
+        "inter_account_transfers" => validate_transfer(context),
         // Expenses Module
-        "expenses" => validate_expense_document(&context),
-        "expense_categories" => validate_expense_category_document(&context),
+        "expenses" => validate_expense_document(context),
+        "expense_categories" => validate_expense_category_document(context),
+        "expense_policies" => validate_expense_policy_document(context),
+        // Budgets Module
+        "budgets" => validate_budget_document(context),
+        "budget_virements" => validate_budget_virement_document(context),
+        "encumbrances" => validate_encumbrance_document(context),
+        // Procurement
+        "purchase_orders" => validate_purchase_order_document(context),
+        "vendors" => validate_vendor_document(context),
         // Students Module
-        "students" => validate_student_document(&context),
+        "students" => validate_student_document(context),
+        "guardians" => validate_guardian_document(context),
+        "classes" => validate_class_document(context),
+        "promotions" => validate_promotion_document(context),
         // Payments Module
-        "payments" => validate_payment_document(&context),
+        "payments" => validate_payment_document(context),
         // Fee & Scholarship Module
-        "student_fee_assignments" => validate_student_fee_assignment(&context),
-        "scholarships" => validate_scholarship(&context),
+        "student_fee_assignments" => validate_student_fee_assignment(context),
+        "scholarships" => validate_scholarship(context),
+        "fee_categories" => validate_fee_category(context),
+        "fee_structures" => validate_fee_structure_document(context),
+        "student_charges" => validate_student_charge_document(context),
+        // Sponsors
+        "sponsors" => validate_sponsor_document(context),
+        "sponsor_invoices" => validate_sponsor_invoice_document(context),
+        "sponsor_payments" => validate_sponsor_payment_document(context),
+        "family_invoices" => validate_family_invoice_document(context),
+        "family_payments" => validate_family_payment_document(context),
         // Staff & Payroll Module
-        "staff" => validate_staff_document(&context),
-        "salary_payments" => validate_salary_payment_document(&context),
+        "staff" => validate_staff_document(context),
+        "staff_loans" => validate_staff_loan_document(context),
+        "salary_payments" => validate_salary_payment_document(context),
+        "duty_rates" => validate_duty_rate_document(context),
+        "duty_claims" => validate_duty_claim_document(context),
+        "court_orders" => validate_court_order_document(context),
+        // Deduction Remittances
+        "deduction_bodies" => validate_deduction_body_document(context),
+        "deduction_remittances" => validate_deduction_remittance_document(context),
+        // Disbursements
+        "bank_acknowledgments" => validate_bank_acknowledgment_document(context),
+        "disbursement_retries" => validate_disbursement_retry_document(context),
+        // Cash Handling
+        "petty_cash_topups" => validate_petty_cash_topup_document(context),
+        "petty_cash_floats" => validate_petty_cash_float_document(context),
+        "petty_cash_vouchers" => validate_petty_cash_voucher_document(context),
+        "cash_movements" => validate_cash_movement_document(context),
+        // Insurance
+        "insurance_policies" => validate_insurance_policy_document(context),
+        "insurance_claims" => validate_insurance_claim_document(context),
+        // Utilities
+        "utility_meters" => validate_utility_meter_document(context),
+        "meter_readings" => validate_meter_reading_document(context),
+        "generators" => validate_generator_document(context),
+        "fuel_logs" => validate_fuel_log_document(context),
+        // Maintenance
+        "work_orders" => validate_work_order_document(context),
+        // Inventory
+        "stock_items" => validate_stock_item_document(context),
+        "stock_movements" => validate_stock_movement_document(context),
+        // PTA Fund
+        "fund_settings" => validate_fund_settings_document(context),
+        // Investments
+        "investments" => validate_investment_document(context),
+        // Ledger
+        "recurring_journals" => validate_recurring_journal_document(context),
+        "prepayments" => validate_prepayment_document(context),
+        // Reports
+        "report_rollups" => validate_report_rollup_document(context),
+        // Audit Trail
+        "audit_logs" => validate_audit_log_document(context),
+        "expenses_history" | "payments_history" | "staff_history" | "student_fee_assignments_history" => {
+            validate_history_document(context)
+        }
+        "voided_numbers" => validate_voided_number_document(context),
+        // Access Control
+        "user_roles" => validate_user_role_document(context),
+        "working_hours" => validate_working_hours_document(context),
+        "cashier_devices" => validate_cashier_device_document(context),
+        // Receipts
+        "receipts" => validate_receipt_document(context),
+        "counters" => validate_counter_document(context),
+        "idempotency_keys" => validate_idempotency_key_document(context),
+        // Whistleblower tips
+        "tips" => validate_tip_document(context),
+        // Payroll
+        "payroll_runs" => validate_payroll_run_document(context),
+        // Period close
+        "period_closes" => validate_period_close_document(context),
+        // Bank reconciliation
+        "reconciliation_reports" => validate_reconciliation_report_document(context),
+        "suspense_items" => validate_suspense_item_document(context),
+        // Student ID cards
+        "id_card_issuances" => validate_id_card_issuance_document(context),
+        // Payment gateways
+        "gateway_settings" => validate_gateway_settings_document(context),
+        "gateway_verifications" => validate_gateway_verification_document(context),
+        "icrc_ledgers" => validate_icrc_ledger_document(context),
+        // Result release
+        "result_release_settings" => validate_result_release_settings_document(context),
+        "clearance_policies" => validate_clearance_policy_document(context),
+        "clearance_policy_history" => validate_clearance_policy_history_document(context),
+        // School configuration
+        "school_settings" => validate_school_settings_document(context),
+        "exchange_rates" => validate_exchange_rate_document(context),
+        // Academic terms
+        "academic_terms" => validate_academic_term_document(context),
+        "term_summaries" => validate_term_summary_document(context),
+        "optional_fee_rollovers" => validate_optional_fee_rollover_document(context),
+        // Notifications
+        "notification_queue" => validate_notification_document(context),
+        "notification_settings" => validate_notification_settings_document(context),
+        // Migrations
+        "validation_bypasses" => validate_validation_bypass_document(context),
+        // Validation rule rollout
+        "rule_violations" => validate_rule_violation_document(context),
+        // Analytics replication
+        "replication_settings" => validate_replication_settings_document(context),
+        "replication_outbox" => validate_replication_outbox_document(context),
+        "replication_state" => validate_replication_state_document(context),
+        // Category usage counters
+        "category_usage" => validate_category_usage_document(context),
         // TODO: Implement remaining validations
-        "budgets" => Ok(()),
-        "fee_categories" => Ok(()),
         "scholarship_applications" => Ok(()),
-        "classes" => Ok(()),
         _ => Ok(()), // Allow unknown collections for now
     }
 }
 
+#[on_set_doc(collections = [
+    "budget_virements",
+    "clearance_policies",
+    "expenses",
+    "family_payments",
+    "inter_account_transfers",
+    "payments",
+    "prepayments",
+    "salary_payments",
+    "sponsor_payments",
+    "staff",
+    "stock_movements",
+    "student_charges",
+    "student_fee_assignments",
+    "students"
+])]
+async fn on_set_doc(context: OnSetDocContext) -> Result<(), String> {
+    if AUDITED_COLLECTIONS.contains(&context.data.collection.as_str()) {
+        record_doc_set(&context)?;
+    }
+    record_doc_history(&context)?;
+    if REPLICABLE_COLLECTIONS.contains(&context.data.collection.as_str()) {
+        record_replication_event(&context.data.collection, &context.data.key, Some(&context.data.data.after))?;
+    }
+
+    match context.data.collection.as_str() {
+        "staff" => {
+            let before: Option<StaffMemberData> = match context.data.data.before {
+                Some(ref doc) => Some(decode_doc_data(&doc.data)?),
+                None => None,
+            };
+            let after: StaffMemberData = decode_doc_data(&context.data.data.after.data)?;
+            log_salary_hold_changes(&context.data.key, before.as_ref(), &after)
+        }
+        "clearance_policies" => {
+            let policy: ClearancePolicyData = decode_doc_data(&context.data.data.after.data)?;
+            record_clearance_policy_change(
+                &context.data.key,
+                context.data.data.before.is_none(),
+                &policy,
+                &context.caller.to_text(),
+            )
+        }
+        "budget_virements" => {
+            let virement: BudgetVirementData = decode_doc_data(&context.data.data.after.data)?;
+            on_budget_virement_saved(&context.data.key, &virement)
+        }
+        "payments" => {
+            let before: Option<PaymentData> = match context.data.data.before {
+                Some(ref doc) => Some(decode_doc_data(&doc.data)?),
+                None => None,
+            };
+            let payment: PaymentData = decode_doc_data(&context.data.data.after.data)?;
+            on_payment_saved(&context.data.key, before.as_ref(), &payment)?;
+            on_online_payment_saved(&context.data.key, &payment).await?;
+            on_crypto_payment_saved(&context.data.key, &payment).await
+        }
+        "salary_payments" => {
+            let salary: SalaryPaymentData = decode_doc_data(&context.data.data.after.data)?;
+            on_salary_payment_saved(&context.data.key, &salary)
+        }
+        "family_payments" => {
+            let before: Option<FamilyPaymentData> = match context.data.data.before {
+                Some(ref doc) => Some(decode_doc_data(&doc.data)?),
+                None => None,
+            };
+            let payment: FamilyPaymentData = decode_doc_data(&context.data.data.after.data)?;
+            on_family_payment_saved(&context.data.key, before.as_ref(), &payment)
+        }
+        "sponsor_payments" => {
+            let before: Option<SponsorPaymentData> = match context.data.data.before {
+                Some(ref doc) => Some(decode_doc_data(&doc.data)?),
+                None => None,
+            };
+            let payment: SponsorPaymentData = decode_doc_data(&context.data.data.after.data)?;
+            on_sponsor_payment_saved(&context.data.key, before.as_ref(), &payment)
+        }
+        "student_charges" => {
+            let before: Option<StudentChargeData> = match context.data.data.before {
+                Some(ref doc) => Some(decode_doc_data(&doc.data)?),
+                None => None,
+            };
+            let charge: StudentChargeData = decode_doc_data(&context.data.data.after.data)?;
+            on_student_charge_saved(&context.data.key, before.as_ref(), &charge)
+        }
+        "stock_movements" => {
+            let movement: StockMovementData = decode_doc_data(&context.data.data.after.data)?;
+            on_stock_movement_saved(&context.data.key, &movement)
+        }
+        "student_fee_assignments" => {
+            let before: Option<StudentFeeAssignmentData> = match context.data.data.before {
+                Some(ref doc) => Some(decode_doc_data(&doc.data)?),
+                None => None,
+            };
+            let assignment: StudentFeeAssignmentData = decode_doc_data(&context.data.data.after.data)?;
+            record_fee_assignment_usage(before.as_ref(), Some(&assignment))?;
+            if before.is_none() {
+                queue_invoice_message(&context.data.key, &assignment)?;
+            }
+            Ok(())
+        }
+        "expenses" => {
+            let before: Option<ExpenseData> = match context.data.data.before {
+                Some(ref doc) => Some(decode_doc_data(&doc.data)?),
+                None => None,
+            };
+            let expense: ExpenseData = decode_doc_data(&context.data.data.after.data)?;
+            record_expense_usage(before.as_ref(), Some(&expense))?;
+            record_budget_spending(before.as_ref(), Some(&expense))?;
+            post_expense_splits(&context.data.key, &expense)?;
+            record_purchase_order_payment(before.as_ref(), &expense)
+        }
+        "prepayments" => {
+            let before: Option<PrepaymentData> = match context.data.data.before {
+                Some(ref doc) => Some(decode_doc_data(&doc.data)?),
+                None => None,
+            };
+            let prepayment: PrepaymentData = decode_doc_data(&context.data.data.after.data)?;
+            on_prepayment_saved(&context.data.key, before.as_ref(), &prepayment)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[on_init]
+fn on_init() {
+    schedule_jobs();
+}
+
+#[on_post_upgrade]
+fn on_post_upgrade() {
+    schedule_jobs();
+}
+
+#[on_delete_doc(collections = [
+    "expenses",
+    "inter_account_transfers",
+    "payments",
+    "salary_payments",
+    "staff",
+    "student_fee_assignments",
+    "students"
+])]
+async fn on_delete_doc(context: OnDeleteDocContext) -> Result<(), String> {
+    if AUDITED_COLLECTIONS.contains(&context.data.collection.as_str()) {
+        record_doc_delete(&context)?;
+    }
+    record_doc_history_delete(&context)?;
+    if REPLICABLE_COLLECTIONS.contains(&context.data.collection.as_str()) {
+        record_replication_event(&context.data.collection, &context.data.key, None)?;
+    }
+
+    match context.data.collection.as_str() {
+        "student_fee_assignments" => {
+            let deleted: Option<StudentFeeAssignmentData> = match context.data.data {
+                Some(ref doc) => Some(decode_doc_data(&doc.data)?),
+                None => None,
+            };
+            record_fee_assignment_usage(deleted.as_ref(), None)
+        }
+        "expenses" => {
+            let deleted: Option<ExpenseData> = match context.data.data {
+                Some(ref doc) => Some(decode_doc_data(&doc.data)?),
+                None => None,
+            };
+            record_expense_usage(deleted.as_ref(), None)?;
+            record_budget_spending(deleted.as_ref(), None)
+        }
+        _ => Ok(()),
+    }
+}
+
 #[assert_delete_doc]
-fn assert_delete_doc(_context: AssertDeleteDocContext) -> Result<(), String> {
-    Ok(())
+fn assert_delete_doc(context: AssertDeleteDocContext) -> Result<(), String> {
+    validate_period_open_on_delete(&context)?;
+    validate_term_open_on_delete(&context)?;
+    validate_archival_delete(&context)?;
+
+    let validated = match context.data.collection.as_str() {
+        "audit_logs" => validate_audit_log_delete(),
+        "expenses_history" | "payments_history" | "staff_history" | "student_fee_assignments_history" => {
+            validate_history_delete()
+        }
+        "voided_numbers" => validate_voided_number_delete(),
+        "receipts" => validate_receipt_delete(),
+        "tips" => validate_tip_delete(),
+        "period_closes" => validate_period_close_delete(),
+        "academic_terms" => validate_academic_term_delete(&context),
+        "clearance_policies" => validate_clearance_policy_delete(),
+        "id_card_issuances" => validate_id_card_issuance_delete(),
+        "validation_bypasses" => validate_validation_bypass_delete(),
+        "promotions" => validate_promotion_delete(),
+        "optional_fee_rollovers" => validate_optional_fee_rollover_delete(),
+        "exchange_rates" => validate_exchange_rate_delete(),
+        "replication_outbox" => validate_replication_outbox_delete(&context.caller),
+        "idempotency_keys" => validate_idempotency_key_delete(&context.caller),
+        "classes" => validate_class_delete(&context.data.key),
+        "expense_categories" => validate_expense_category_delete(&context.data.key),
+        "vendors" => validate_vendor_delete(&context.data.key),
+        "stock_items" => validate_stock_item_delete(&context.data.key),
+        "stock_movements" => validate_stock_movement_delete(),
+        "recurring_journals" => validate_recurring_journal_delete(&context.data.key),
+        "prepayments" => validate_prepayment_delete(),
+        "staff" => validate_staff_delete(&context.data.key),
+        "staff_loans" => validate_staff_loan_delete(&context.data.key),
+        "students" => validate_student_delete(&context.data.key),
+        "guardians" => validate_guardian_delete(&context.data.key),
+        _ => Ok(()),
+    };
+    if validated.is_ok() {
+        invalidate_cache(&context.data.collection);
+    }
+    validated
 }
 
 #[assert_upload_asset]
-fn assert_upload_asset(_context: AssertUploadAssetContext) -> Result<(), String> {
-    Ok(())
+fn assert_upload_asset(context: AssertUploadAssetContext) -> Result<(), String> {
+    match context.data.batch.key.collection.as_str() {
+        STUDENT_ASSETS_COLLECTION => validate_student_asset_upload(&context),
+        PAYSLIPS_COLLECTION => validate_payslip_upload(&context),
+        _ => Ok(()),
+    }
 }
 
 #[assert_delete_asset]
-fn assert_delete_asset(_context: AssertDeleteAssetContext) -> Result<(), String> {
-    Ok(())
+fn assert_delete_asset(context: AssertDeleteAssetContext) -> Result<(), String> {
+    match context.data.key.collection.as_str() {
+        PAYSLIPS_COLLECTION => validate_payslip_delete(&context),
+        _ => Ok(()),
+    }
+}
+
+// Custom endpoints
+
+#[ic_cdk::query]
+fn list_payable_duty_claims(staff_id: String) -> Result<Vec<PayableDutyClaim>, String> {
+    list_payable_claims(&staff_id)
+}
+
+#[ic_cdk::query]
+fn get_deduction_remittance_schedule(period: String) -> Result<Vec<RemittanceScheduleItem>, String> {
+    get_remittance_schedule(&period)
+}
+
+#[ic_cdk::query]
+fn list_unremitted_deductions() -> Result<Vec<RemittanceScheduleItem>, String> {
+    get_unremitted_deductions()
+}
+
+#[ic_cdk::update]
+fn import_payment_acknowledgments(batch: AcknowledgmentBatch) -> Result<Vec<AcknowledgmentResult>, String> {
+    import_acknowledgments(batch)
+}
+
+#[ic_cdk::update]
+fn export_disbursement_retry_file(batch_reference: String) -> Result<Vec<DisbursementFileEntry>, String> {
+    export_retry_file(batch_reference)
+}
+
+// An update rather than a query: exporting stamps each expense with its batch
+#[ic_cdk::update]
+fn export_vendor_payment_file(date: String) -> Result<VendorPaymentFile, String> {
+    build_vendor_payment_file(date)
+}
+
+#[ic_cdk::query]
+fn list_cash_in_transit_alerts() -> Result<Vec<CashTransitAlert>, String> {
+    list_transit_alerts()
+}
+
+#[ic_cdk::query]
+fn get_insurance_claims_report() -> Result<Vec<ClaimRecoveryReportItem>, String> {
+    get_claims_recovery_report()
+}
+
+#[ic_cdk::query]
+fn list_utility_cost_anomalies() -> Result<Vec<UtilityCostAnomaly>, String> {
+    list_cost_anomalies()
+}
+
+#[ic_cdk::query]
+fn get_generator_fuel_variance(period: Option<String>) -> Result<Vec<FuelVarianceItem>, String> {
+    get_fuel_variance_report(period)
+}
+
+#[ic_cdk::query]
+fn get_asset_maintenance_cost_report(asset_id: Option<String>) -> Result<Vec<AssetMaintenanceCost>, String> {
+    get_asset_maintenance_costs(asset_id)
+}
+
+#[ic_cdk::query]
+fn get_budget_availability(budget_id: String) -> Result<Vec<BudgetLineAvailability>, String> {
+    get_budget_lines_availability(&budget_id)
+}
+
+#[ic_cdk::query]
+fn get_pta_fund_report(period: Option<String>) -> Result<PtaFundReport, String> {
+    get_pta_report(period)
+}
+
+#[ic_cdk::query]
+fn get_comparatives(metric: String, periods: Vec<String>) -> Result<ComparativeReport, String> {
+    get_comparative_report(metric, periods)
+}
+
+#[ic_cdk::update]
+fn file_transaction_tip(reference: String, description: String) -> Result<u64, String> {
+    file_tip(reference, description)
+}
+
+#[ic_cdk::update]
+fn create_payroll_run(
+    period_start: String,
+    period_end: String,
+    idempotency_key: Option<String>,
+) -> Result<PayrollRunSummary, String> {
+    run_payroll(period_start, period_end, idempotency_key)
+}
+
+#[ic_cdk::query]
+fn get_period_close_readiness(period: String) -> Result<CloseReadiness, String> {
+    get_close_readiness(period)
+}
+
+#[ic_cdk::update]
+fn close_accounting_period(period: String, waivers: Vec<CloseWaiver>) -> Result<CloseReadiness, String> {
+    close_period(period, waivers)
+}
+
+#[ic_cdk::update]
+fn import_bank_statement(account_id: String, rows: Vec<StatementRow>) -> Result<ReconciliationSummary, String> {
+    reconcile_bank_statement(account_id, rows)
+}
+
+#[ic_cdk::query]
+fn get_outstanding_suspense_items() -> Result<SuspenseReport, String> {
+    get_suspense_report()
+}
+
+#[ic_cdk::query]
+fn get_reference_match_reviews() -> Result<Vec<ReferenceMatchReview>, String> {
+    reference_match_reviews()
+}
+
+#[ic_cdk::query]
+fn list_transaction_tips() -> Result<Vec<TipRecord>, String> {
+    list_tips()
+}
+
+#[ic_cdk::update]
+fn issue_student_id_card(
+    student_id: String,
+    reprint_reason: Option<String>,
+    fee_assignment_id: Option<String>,
+) -> Result<IdCardIssuance, String> {
+    issue_id_card(student_id, reprint_reason, fee_assignment_id)
+}
+
+#[ic_cdk::update]
+async fn verify_gateway_payment(payment_id: String) -> Result<GatewayVerification, String> {
+    verify_payment(payment_id).await
+}
+
+#[ic_cdk::update]
+async fn verify_crypto_payment(payment_id: String) -> Result<CryptoVerification, String> {
+    verify_crypto_transfer(payment_id).await
+}
+
+#[ic_cdk::query]
+fn transform_gateway_response(args: TransformArgs) -> HttpRequestResult {
+    transform_response(args)
+}
+
+#[ic_cdk::query]
+fn transform_notification_response(args: TransformArgs) -> HttpRequestResult {
+    transform_provider_response(args)
+}
+
+#[ic_cdk::query]
+fn check_result_release(student_id: String, term: String) -> Result<ClearanceStatus, String> {
+    result_release_status(student_id, term)
+}
+
+#[ic_cdk::query]
+fn check_exam_entry(student_id: String, term: String) -> Result<ClearanceStatus, String> {
+    exam_entry_status(student_id, term)
+}
+
+#[ic_cdk::update]
+fn close_term(term_id: String) -> Result<Vec<TermClassSummary>, String> {
+    close_academic_term(term_id)
+}
+
+#[ic_cdk::query]
+fn get_financial_summary(from: String, to: String) -> Result<FinancialSummary, String> {
+    financial_summary(from, to)
+}
+
+#[ic_cdk::update]
+fn create_sponsor_invoice(sponsor_id: String, academic_year: String, term: String) -> Result<SponsorInvoiceSummary, String> {
+    issue_sponsor_invoice(sponsor_id, academic_year, term)
+}
+
+#[ic_cdk::query]
+fn get_sponsor_statement(sponsor_id: String) -> Result<SponsorStatement, String> {
+    sponsor_statement(sponsor_id)
+}
+
+#[ic_cdk::update]
+fn generate_family_invoice(guardian_id: String, term: String) -> Result<FamilyInvoice, String> {
+    issue_family_invoice(guardian_id, term)
+}
+
+#[ic_cdk::query]
+fn get_student_statement(student_id: String) -> Result<StudentStatement, String> {
+    student_statement(student_id)
+}
+
+#[ic_cdk::query]
+fn list_debtors(class_id: Option<String>, min_balance: f64, page: Pagination) -> Result<DebtorsPage, String> {
+    debtors_page(class_id, min_balance, page)
+}
+
+#[ic_cdk::query]
+fn list_unapplied_payments(page: Pagination) -> Result<UnappliedPaymentsPage, String> {
+    unapplied_payments_page(page)
+}
+
+#[ic_cdk::update]
+fn queue_broadcast(filter: BroadcastFilter, message: String) -> Result<BroadcastSummary, String> {
+    queue_fee_broadcast(filter, message)
+}
+
+#[ic_cdk::query]
+fn get_data_quality(collection: String) -> Result<DataQualityReport, String> {
+    data_quality(collection)
+}
+
+#[ic_cdk::update]
+fn import_legacy_documents(
+    collection: String,
+    documents: Vec<LegacyDocument>,
+    rule_sets: Vec<String>,
+    migration_id: String,
+    reason: String,
+) -> Result<Vec<String>, String> {
+    import_legacy(collection, documents, rule_sets, migration_id, reason)
+}
+
+#[ic_cdk::query]
+fn list_validation_bypasses(collection: Option<String>, page: Pagination) -> Result<ValidationBypassPage, String> {
+    validation_bypasses_page(collection, page)
+}
+
+#[ic_cdk::query]
+fn get_rule_violation_metrics() -> Result<Vec<RuleMetric>, String> {
+    rule_violation_metrics()
+}
+
+#[ic_cdk::update]
+fn promote_students(
+    from_class_id: String,
+    to_class_id: String,
+    student_ids: Vec<String>,
+) -> Result<Vec<PromotedStudent>, String> {
+    promote_class_students(from_class_id, to_class_id, student_ids)
+}
+
+#[ic_cdk::update]
+fn assign_fees_to_class(class_id: String, fee_structure_id: String, term: String) -> Result<ClassBillingSummary, String> {
+    bill_class(class_id, fee_structure_id, term)
+}
+
+#[ic_cdk::query]
+fn self_test() -> SelfTestReport {
+    run_self_test()
+}
+
+#[ic_cdk::update]
+async fn sync_replication() -> Result<ReplicationStatus, String> {
+    push_replication().await
+}
+
+#[ic_cdk::query]
+fn get_replication_status() -> Result<ReplicationStatus, String> {
+    replication_status()
+}
+
+#[ic_cdk::query]
+fn get_teller_prefill(student_id: String, amount: f64) -> Result<TellerPrefill, String> {
+    teller_prefill(&student_id, amount)
+}
+
+#[ic_cdk::query]
+fn get_category_usage() -> Result<Vec<CategoryUsage>, String> {
+    category_usage()
+}
+
+#[ic_cdk::update]
+fn rebuild_category_usage(cursor: Option<String>) -> Result<Option<String>, String> {
+    recount_category_usage(cursor)
+}
+
+#[ic_cdk::update]
+fn retire_float(float_id: String) -> Result<FloatRetirement, String> {
+    retire_petty_cash_float(&float_id)
+}
+
+#[ic_cdk::query]
+fn get_receipt_render_data(receipt_number: String) -> Result<DocumentRenderData, String> {
+    receipt_render_data(&receipt_number)
+}
+
+#[ic_cdk::query]
+fn get_payslip_render_data(salary_payment_id: String) -> Result<DocumentRenderData, String> {
+    payslip_render_data(&salary_payment_id)
+}
+
+#[ic_cdk::query]
+fn audit_sequences(scheme: String, period: String) -> Result<SequenceAudit, String> {
+    sequence_audit(&scheme, &period)
+}
+
+#[ic_cdk::query]
+fn get_stock_levels() -> Result<Vec<StockLevel>, String> {
+    stock_levels()
+}
+
+#[ic_cdk::query]
+fn get_prepaid_balances() -> Result<Vec<PrepaidBalance>, String> {
+    prepaid_balances()
+}
+
+#[ic_cdk::query]
+fn health() -> Result<HealthStatus, String> {
+    system_health()
+}
+
+#[ic_cdk::query]
+fn get_document_history(collection: String, key: String) -> Result<Vec<DocumentRevision>, String> {
+    document_history(collection, key)
+}
+
+#[ic_cdk::query]
+fn simulate_transition(
+    collection: String,
+    current_doc: Option<SimulatedDocument>,
+    proposed_doc: SimulatedDocument,
+) -> Result<TransitionSimulation, String> {
+    simulate_write(collection, current_doc, proposed_doc, validate_collection_document)
+}
+
+#[ic_cdk::update]
+async fn record_payments_batch(
+    payments: Vec<PaymentInput>,
+    idempotency_key: Option<String>,
+) -> Result<Vec<BatchResult>, String> {
+    record_payment_batch(payments, idempotency_key, on_set_doc).await
+}
+
+#[ic_cdk::update]
+fn report_rejected_write(collection: String, key: String, amount: Option<f64>) -> Result<String, String> {
+    record_reported_rejection(collection, key, amount)
 }
 
 include_satellite!();
@@ -0,0 +1,112 @@
+//! Archival Module - Soft Deletion of Financial Documents
+//!
+//! Settled money movements are never removed: a paid expense, a confirmed payment or a
+//! paid salary payment cannot be deleted, whoever asks. Such a document is archived
+//! instead, by setting `archived` on it together with an `archivedReason` and
+//! `archivedBy` (the caller). The archiving write may change nothing else, and an
+//! archived document is frozen: it cannot be changed, unarchived or deleted.
+//!
+//! Archiving hides a document from the app's working lists only. The satellite keeps
+//! counting it (balances, ledgers, reports), so the audit trail stays intact.
+
+use junobuild_satellite::{AssertDeleteDocContext, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde_json::Value;
+
+use super::roles::{caller_has_any_role, Role};
+
+const MIN_ARCHIVED_REASON_LENGTH: usize = 10;
+
+// Collections under the archival model and the statuses that may only be archived
+const ARCHIVABLE_STATUSES: [(&str, &[&str]); 3] =
+    [("expenses", &["paid"]), ("payments", &["confirmed"]), ("salary_payments", &["paid"])];
+
+// Archive fields, as stored (camelCase)
+const ARCHIVE_FIELDS: [&str; 3] = ["archived", "archivedReason", "archivedBy"];
+
+/// Archiving rules for writes to archivable collections.
+pub fn validate_archival(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_archivable(&context.data.collection) {
+        return Ok(());
+    }
+
+    let proposed: Value =
+        decode_doc_data(&context.data.data.proposed.data).map_err(|e| format!("Invalid document data: {}", e))?;
+    let current: Option<Value> = match context.data.data.current {
+        Some(ref doc) => {
+            Some(decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous document data: {}", e))?)
+        }
+        None => None,
+    };
+
+    let current = match current {
+        Some(current) if is_archived(&current) => {
+            return Err("AUDIT: Archived documents cannot be changed".to_string());
+        }
+        Some(current) => current,
+        None if is_archived(&proposed) => {
+            return Err("AUDIT: A document is archived after it is recorded, not created archived".to_string());
+        }
+        None => return Ok(()),
+    };
+    if !is_archived(&proposed) {
+        return Ok(());
+    }
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+        return Err("SECURITY: Only a bursar, accountant or administrator can archive documents".to_string());
+    }
+    let reason = proposed.get("archivedReason").and_then(Value::as_str).map(str::trim).unwrap_or("");
+    if reason.len() < MIN_ARCHIVED_REASON_LENGTH {
+        return Err(format!("Archived reason must be at least {} characters", MIN_ARCHIVED_REASON_LENGTH));
+    }
+    let caller = context.caller.to_text();
+    if proposed.get("archivedBy").and_then(Value::as_str) != Some(caller.as_str()) {
+        return Err(format!("SECURITY: archivedBy must be the caller's own identity ({})", caller));
+    }
+    if without_archive_fields(&proposed) != without_archive_fields(&current) {
+        return Err("AUDIT: Archiving a document cannot change anything else on it".to_string());
+    }
+
+    Ok(())
+}
+
+/// Paid and confirmed documents, and archived ones, cannot be hard-deleted.
+pub fn validate_archival_delete(context: &AssertDeleteDocContext) -> Result<(), String> {
+    let statuses = match ARCHIVABLE_STATUSES.iter().find(|(c, _)| *c == context.data.collection) {
+        Some((_, statuses)) => *statuses,
+        None => return Ok(()),
+    };
+    let current: Value = match context.data.data.current {
+        Some(ref doc) => decode_doc_data(&doc.data).map_err(|e| format!("Invalid document data: {}", e))?,
+        None => return Ok(()),
+    };
+
+    if is_archived(&current) {
+        return Err("AUDIT: Archived documents cannot be deleted".to_string());
+    }
+    match current.get("status").and_then(Value::as_str) {
+        Some(status) if statuses.contains(&status) => {
+            Err(format!("AUDIT: A {} document cannot be deleted; archive it with a reason instead", status))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn is_archivable(collection: &str) -> bool {
+    ARCHIVABLE_STATUSES.iter().any(|(c, _)| *c == collection)
+}
+
+fn is_archived(data: &Value) -> bool {
+    data.get("archived").and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn without_archive_fields(data: &Value) -> Value {
+    let mut data = data.clone();
+    if let Some(fields) = data.as_object_mut() {
+        for field in ARCHIVE_FIELDS {
+            fields.remove(field);
+        }
+    }
+    data
+}
@@ -0,0 +1,153 @@
+//! Audit Module - Immutable Trail of Financial Mutations
+//!
+//! Every create, update and delete of a financial document (expenses, payments, salary
+//! payments, inter-account transfers) is recorded in `audit_logs` from the on-set and
+//! on-delete hooks: who made the change, which document, and the status before and after.
+//! Only the satellite writes audit entries, each entry is written once, and entries can
+//! never be deleted.
+//!
+//! Writes rejected by a policy check are recorded too, as `rejected` entries carrying the
+//! reason. Those entries persist when the satellite makes the write on the caller's
+//! behalf (e.g. batch payment recording). A rejected direct client write is rolled back
+//! together with everything else done during the call, so the frontend reports it
+//! afterwards through `report_rejected_write` (see `roles::rejections`).
+
+pub mod sequences;
+
+use junobuild_satellite::{AssertSetDocContext, Doc, OnDeleteDocContext, OnSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::utils::doc_utils::*;
+
+pub const AUDIT_LOGS_COLLECTION: &str = "audit_logs";
+
+/// Collections whose mutations are recorded.
+pub const AUDITED_COLLECTIONS: [&str; 4] = ["expenses", "inter_account_transfers", "payments", "salary_payments"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLogData {
+    pub caller: String,
+    pub collection: String,
+    pub doc_key: String,
+    pub action: String,
+    pub old_status: Option<String>,
+    pub new_status: Option<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+    pub timestamp: u64,
+}
+
+/// Audit Log Validation
+///
+/// Checks:
+/// - Only the satellite writes audit entries (no client-side writes)
+/// - Entries are written once and never modified
+pub fn validate_audit_log_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: The audit log is written by the satellite only".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Audit log entries cannot be modified".to_string());
+    }
+    Ok(())
+}
+
+/// Audit entries can never be deleted.
+pub fn validate_audit_log_delete() -> Result<(), String> {
+    Err("AUDIT: Audit log entries cannot be deleted".to_string())
+}
+
+/// Called from the on-set hook for audited collections.
+pub fn record_doc_set(context: &OnSetDocContext) -> Result<(), String> {
+    let before = context.data.data.before.as_ref();
+    let action = if before.is_some() { "update" } else { "create" };
+
+    record(
+        &context.caller.to_text(),
+        &context.data.collection,
+        &context.data.key,
+        action,
+        before.and_then(doc_status),
+        doc_status(&context.data.data.after),
+    )
+}
+
+/// Called from the on-delete hook for audited collections.
+pub fn record_doc_delete(context: &OnDeleteDocContext) -> Result<(), String> {
+    record(
+        &context.caller.to_text(),
+        &context.data.collection,
+        &context.data.key,
+        "delete",
+        context.data.data.as_ref().and_then(doc_status),
+        None,
+    )
+}
+
+/// Record a change the satellite makes on its own, e.g. from a scheduled job.
+pub fn record_system_change(
+    collection: &str,
+    doc_key: &str,
+    action: &str,
+    old_status: Option<String>,
+    new_status: Option<String>,
+) -> Result<(), String> {
+    record(&junobuild_satellite::id().to_text(), collection, doc_key, action, old_status, new_status)
+}
+
+/// Record a write rejected by a policy check, e.g. a role limit violation.
+pub fn record_rejected_write(caller: &str, collection: &str, doc_key: &str, reason: &str) -> Result<(), String> {
+    write_entry(rejected_entry(caller, collection, doc_key, reason, ic_cdk::api::time()))
+}
+
+/// The `rejected` entry recorded for a write refused by a policy check.
+pub fn rejected_entry(caller: &str, collection: &str, doc_key: &str, reason: &str, timestamp: u64) -> AuditLogData {
+    AuditLogData {
+        caller: caller.to_string(),
+        collection: collection.to_string(),
+        doc_key: doc_key.to_string(),
+        action: "rejected".to_string(),
+        old_status: None,
+        new_status: None,
+        reason: Some(reason.to_string()),
+        timestamp,
+    }
+}
+
+fn record(
+    caller: &str,
+    collection: &str,
+    doc_key: &str,
+    action: &str,
+    old_status: Option<String>,
+    new_status: Option<String>,
+) -> Result<(), String> {
+    write_entry(AuditLogData {
+        caller: caller.to_string(),
+        collection: collection.to_string(),
+        doc_key: doc_key.to_string(),
+        action: action.to_string(),
+        old_status,
+        new_status,
+        reason: None,
+        timestamp: ic_cdk::api::time(),
+    })
+}
+
+fn write_entry(entry: AuditLogData) -> Result<(), String> {
+    // Time first so keys sort chronologically
+    let key = format!("{}_{}_{}", entry.timestamp, entry.collection, entry.doc_key);
+    let description = format!("collection={};doc_key={};", entry.collection, entry.doc_key);
+    set_doc_data(AUDIT_LOGS_COLLECTION, &key, &entry, Some(description), None)?;
+    Ok(())
+}
+
+fn doc_status(doc: &Doc) -> Option<String> {
+    decode_doc_data::<serde_json::Value>(&doc.data)
+        .ok()?
+        .get("status")?
+        .as_str()
+        .map(str::to_string)
+}
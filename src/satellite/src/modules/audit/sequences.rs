@@ -0,0 +1,286 @@
+//! Numbering gap audit.
+//!
+//! Receipts, invoices, ID cards and satellite-issued references are numbered from the
+//! counters in `counters`, so every number from 1 to the counter should belong to exactly
+//! one document. `audit_sequences` compares the numbers found in a scheme's documents
+//! with its counter for a period and reports:
+//! - gaps: numbers issued by the counter that no document carries and nobody voided
+//! - duplicates: numbers carried by more than one document
+//! - numbers beyond the counter, which were not issued by the satellite
+//! - voided numbers, with their reasons
+//!
+//! A number is voided in `voided_numbers` (key `{scheme}:{period}:{number}`): by the
+//! satellite when it skips a reference already in use, or by a bursar or administrator
+//! explaining a number that was issued but never used. Voids are written once and never
+//! deleted. A document that is itself voided (status `voided`, such as a spoilt receipt)
+//! keeps its number and is reported as voided with the document's reason.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, id, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::counters::{CounterData, COUNTERS_COLLECTION};
+use crate::modules::utils::doc_utils::*;
+
+pub const VOIDED_NUMBERS_COLLECTION: &str = "voided_numbers";
+
+// A numbering scheme: where its numbers are kept and how they are formatted
+struct Scheme {
+    name: &'static str,
+    // Period format: a year (YYYY) or a month (YYYY-MM)
+    monthly: bool,
+    counter_prefix: &'static str,
+    collection: &'static str,
+    field: &'static str,
+    // Number prefix before the period, and between the period and the digits
+    prefix: &'static str,
+    infix: &'static str,
+}
+
+const SCHEMES: [Scheme; 7] = [
+    Scheme { name: "receipts", monthly: false, counter_prefix: "receipts", collection: "receipts", field: "receiptNumber", prefix: "RCP", infix: "" },
+    Scheme { name: "sponsor_invoices", monthly: false, counter_prefix: "sponsor-invoices", collection: "sponsor_invoices", field: "invoiceNumber", prefix: "SPI", infix: "" },
+    Scheme { name: "family_invoices", monthly: false, counter_prefix: "family-invoices", collection: "family_invoices", field: "invoiceNumber", prefix: "FINV", infix: "" },
+    Scheme { name: "id_cards", monthly: false, counter_prefix: "id-cards", collection: "id_card_issuances", field: "cardNumber", prefix: "ID", infix: "" },
+    Scheme { name: "salary", monthly: true, counter_prefix: "salary", collection: "salary_payments", field: "reference", prefix: "SAL", infix: "" },
+    Scheme { name: "bank_credits", monthly: false, counter_prefix: "bank-credit", collection: "payments", field: "reference", prefix: "PAY", infix: "B" },
+    Scheme { name: "petty_cash", monthly: false, counter_prefix: "petty-cash", collection: "expenses", field: "reference", prefix: "EXP", infix: "PC" },
+];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoidedNumberData {
+    pub scheme: String,
+    pub period: String,
+    pub number: u64,
+    pub reason: String,
+    pub voided_by: String,
+    pub voided_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct SequenceDuplicate {
+    pub number: u64,
+    pub keys: Vec<String>,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct VoidedSequenceNumber {
+    pub number: u64,
+    pub reason: String,
+    pub voided_by: String,
+    pub voided_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct SequenceAudit {
+    pub scheme: String,
+    pub period: String,
+    // Last number issued by the counter
+    pub counter: u64,
+    pub issued: u32,
+    pub gaps: Vec<u64>,
+    pub duplicates: Vec<SequenceDuplicate>,
+    pub beyond_counter: Vec<u64>,
+    pub voided: Vec<VoidedSequenceNumber>,
+}
+
+/// Voided Number Validation
+///
+/// Checks:
+/// - Recorded by the satellite, a bursar or an administrator, as the caller
+/// - Known scheme, period in the scheme's format, key `{scheme}:{period}:{number}`
+/// - The number was issued by the counter and, unless voided by the satellite, no
+///   document carries it
+/// - A reason of at least 10 characters; voids are never modified
+pub fn validate_voided_number_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: VoidedNumberData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid voided number data format: {}", e))?;
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can void numbers".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Voided numbers cannot be modified".to_string());
+    }
+    if !is_satellite_caller(&context.caller) && data.voided_by != context.caller.to_text() {
+        return Err("voidedBy must be the principal voiding the number".to_string());
+    }
+    if data.reason.trim().len() < 10 {
+        return Err("A void must give a reason of at least 10 characters".to_string());
+    }
+
+    let scheme = scheme(&data.scheme)?;
+    validate_period(scheme, &data.period)?;
+    if context.data.key != void_key(&data.scheme, &data.period, data.number) {
+        return Err(format!(
+            "Voided numbers are keyed {{scheme}}:{{period}}:{{number}}; expected '{}'",
+            void_key(&data.scheme, &data.period, data.number)
+        ));
+    }
+
+    let counter = counter_value(scheme, &data.period)?;
+    if data.number == 0 || data.number > counter {
+        return Err(format!("Number {} has not been issued (the counter is at {})", data.number, counter));
+    }
+    // The satellite voids numbers it skipped because a document already carries them
+    if !is_satellite_caller(&context.caller) {
+        if let Some(keys) = issued_numbers(scheme, &data.period)?.get(&data.number) {
+            return Err(format!("Number {} is in use by '{}' and cannot be voided", data.number, keys.join("', '")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Voided numbers can never be deleted.
+pub fn validate_voided_number_delete() -> Result<(), String> {
+    Err("AUDIT: Voided numbers cannot be deleted".to_string())
+}
+
+/// Record a number skipped by the satellite.
+pub fn void_number(scheme: &str, period: &str, number: u64, reason: &str) -> Result<(), String> {
+    let void = VoidedNumberData {
+        scheme: scheme.to_string(),
+        period: period.to_string(),
+        number,
+        reason: reason.to_string(),
+        voided_by: id().to_text(),
+        voided_at: ic_cdk::api::time(),
+    };
+    set_doc_data(VOIDED_NUMBERS_COLLECTION, &void_key(scheme, period, number), &void, None, None)?;
+    Ok(())
+}
+
+/// Gaps, duplicates, numbers beyond the counter and voids in a numbering scheme for a
+/// period (YYYY, or YYYY-MM for salary references).
+pub fn audit_sequences(scheme_name: &str, period: &str) -> Result<SequenceAudit, String> {
+    if !caller_has_any_role(&caller(), &[Role::SuperAdmin, Role::Bursar, Role::Accountant, Role::Auditor]) {
+        return Err("SECURITY: Not authorised to audit numbering sequences".to_string());
+    }
+    let scheme = scheme(scheme_name)?;
+    validate_period(scheme, period)?;
+
+    let counter = counter_value(scheme, period)?;
+    let issued = issued_numbers(scheme, period)?;
+    let mut voided: BTreeMap<u64, VoidedSequenceNumber> = voided_documents(scheme, period)?;
+    for (_, _, v) in list_doc_data::<VoidedNumberData>(VOIDED_NUMBERS_COLLECTION, None)? {
+        if v.scheme == scheme.name && v.period == period {
+            voided.insert(
+                v.number,
+                VoidedSequenceNumber { number: v.number, reason: v.reason, voided_by: v.voided_by, voided_at: v.voided_at },
+            );
+        }
+    }
+
+    let gaps = (1..=counter)
+        .filter(|n| !issued.contains_key(n) && !voided.contains_key(n))
+        .collect();
+    let duplicates = issued
+        .iter()
+        .filter(|(_, keys)| keys.len() > 1)
+        .map(|(number, keys)| SequenceDuplicate { number: *number, keys: keys.clone() })
+        .collect();
+    let beyond_counter = issued.keys().copied().filter(|n| *n > counter).collect();
+
+    Ok(SequenceAudit {
+        scheme: scheme.name.to_string(),
+        period: period.to_string(),
+        counter,
+        issued: issued.len() as u32,
+        gaps,
+        duplicates,
+        beyond_counter,
+        voided: voided.into_values().collect(),
+    })
+}
+
+fn scheme(name: &str) -> Result<&'static Scheme, String> {
+    SCHEMES.iter().find(|s| s.name == name).ok_or_else(|| {
+        format!(
+            "Unknown numbering scheme '{}'. Must be one of: {}",
+            name,
+            SCHEMES.iter().map(|s| s.name).collect::<Vec<_>>().join(", ")
+        )
+    })
+}
+
+fn validate_period(scheme: &Scheme, period: &str) -> Result<(), String> {
+    let valid = if scheme.monthly {
+        period.len() == 7
+            && period.as_bytes()[4] == b'-'
+            && period[..4].chars().chain(period[5..].chars()).all(|c| c.is_ascii_digit())
+    } else {
+        period.len() == 4 && period.chars().all(|c| c.is_ascii_digit())
+    };
+    if !valid {
+        return Err(format!(
+            "The {} scheme is numbered per {}; period must be {}",
+            scheme.name,
+            if scheme.monthly { "month" } else { "year" },
+            if scheme.monthly { "YYYY-MM" } else { "YYYY" }
+        ));
+    }
+    Ok(())
+}
+
+fn void_key(scheme: &str, period: &str, number: u64) -> String {
+    format!("{}:{}:{}", scheme, period, number)
+}
+
+fn counter_value(scheme: &Scheme, period: &str) -> Result<u64, String> {
+    let name = format!("{}-{}", scheme.counter_prefix, period);
+    Ok(get_doc_data::<CounterData>(COUNTERS_COLLECTION, &name)?
+        .map(|(_, counter)| counter.last_number)
+        .unwrap_or(0))
+}
+
+// Number → keys of the documents carrying it (voided or not), for the scheme's documents
+// in the period
+fn issued_numbers(scheme: &Scheme, period: &str) -> Result<BTreeMap<u64, Vec<String>>, String> {
+    let mut issued: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+    for (number, key, _) in numbered_documents(scheme, period)? {
+        issued.entry(number).or_default().push(key);
+    }
+    for keys in issued.values_mut() {
+        keys.sort();
+    }
+    Ok(issued)
+}
+
+// Numbers carried by documents voided in place, with the document's void details
+fn voided_documents(scheme: &Scheme, period: &str) -> Result<BTreeMap<u64, VoidedSequenceNumber>, String> {
+    let field = |doc: &serde_json::Value, name: &str| doc.get(name).cloned().unwrap_or_default();
+    Ok(numbered_documents(scheme, period)?
+        .into_iter()
+        .filter(|(_, _, doc)| doc.get("status").and_then(|v| v.as_str()) == Some("voided"))
+        .map(|(number, _, doc)| {
+            let void = VoidedSequenceNumber {
+                number,
+                reason: field(&doc, "voidReason").as_str().unwrap_or_default().to_string(),
+                voided_by: field(&doc, "voidedBy").as_str().unwrap_or_default().to_string(),
+                voided_at: field(&doc, "voidedAt").as_u64().unwrap_or_default(),
+            };
+            (number, void)
+        })
+        .collect())
+}
+
+fn numbered_documents(scheme: &Scheme, period: &str) -> Result<Vec<(u64, String, serde_json::Value)>, String> {
+    let prefix = format!("{}-{}-{}", scheme.prefix, period, scheme.infix);
+    Ok(list_doc_data::<serde_json::Value>(scheme.collection, None)?
+        .into_iter()
+        .filter_map(|(key, _, doc)| {
+            let number = doc
+                .get(scheme.field)
+                .and_then(|v| v.as_str())
+                .and_then(|v| v.strip_prefix(prefix.as_str()))
+                .filter(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+                .and_then(|digits| digits.parse::<u64>().ok())?;
+            Some((number, key, doc))
+        })
+        .collect())
+}
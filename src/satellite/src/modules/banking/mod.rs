@@ -8,18 +8,55 @@
 //!
 //! Note: Basic input validation (required fields, formats) is handled on frontend.
 
+pub mod signatories;
+
 use junobuild_satellite::AssertSetDocContext;
 use junobuild_utils::decode_doc_data;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::fx::{is_currency_code, validate_foreign_amount};
+use super::settings::school_settings;
+use super::utils::doc_utils::{get_doc_data, list_doc_data};
+use super::utils::identity::validate_not_self_approval;
+use super::utils::money::Money;
+use signatories::{
+    is_authorised_signatory, validate_signatory_approval, validate_signatory_changes, AccountSignatory, PendingMandate,
+};
 
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BankTransactionData {
-    pub debit_amount: f64,
-    pub credit_amount: f64,
-    pub balance: f64,
+    pub debit_amount: Money,
+    pub credit_amount: Money,
+    pub balance: Money,
     pub status: String,
     pub is_reconciled: Option<bool>,
+    #[serde(default)]
+    pub bank_account_id: String,
+    #[serde(default)]
+    pub transaction_date: String,
+    #[serde(default)]
+    pub transaction_type: String,
+    #[serde(default)]
+    pub reference: Option<String>,
+    #[serde(default)]
+    pub statement_id: Option<String>,
+    #[serde(default)]
+    pub reconciled_by: Option<String>,
+    #[serde(default)]
+    pub reconciled_at: Option<u64>,
+    // Currency of a domiciliary account's transactions, with the rate and the amount in
+    // the base currency (see `fx`)
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub fx_rate: Option<f64>,
+    #[serde(default)]
+    pub base_amount: Option<Money>,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -27,23 +64,43 @@ pub struct BankTransactionData {
 pub struct InterAccountTransferData {
     pub from_account_id: String,
     pub to_account_id: String,
-    pub amount: f64,
+    pub amount: Money,
     pub status: String,
     pub approved_by: Option<String>,
     pub approved_at: Option<u64>,
+    #[serde(default)]
+    pub approvals: Vec<TransferApproval>,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferApproval {
+    pub approved_by: String,
+    pub approved_at: u64,
 }
 
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BankAccountData {
+    pub bank_name: String,
+    pub account_number: String,
     pub account_type: String,
-    pub balance: f64,
+    pub balance: Money,
+    // Bank mandate: see `signatories`
+    #[serde(default)]
+    pub signatories: Vec<AccountSignatory>,
+    #[serde(default)]
+    pub pending_signatories: Option<PendingMandate>,
+    #[serde(default)]
+    pub mandate_approved_by: Option<String>,
+    // Foreign currency held by a domiciliary account; the base currency when not set
+    #[serde(default)]
+    pub currency: Option<String>,
 }
 
 // Security Constants
-const MAX_SINGLE_TRANSACTION: f64 = 1_000_000_000.0; // ₦1B - Suspicious transaction threshold
-const MAX_TRANSFER_WITHOUT_APPROVAL: f64 = 5_000_000.0; // ₦5M - Requires approval above this
-const OVERDRAFT_ALERT_THRESHOLD: f64 = -10_000_000.0; // ₦10M negative - Alert on excessive overdraft
+const MAX_SINGLE_TRANSACTION: Money = Money::from_kobo(100_000_000_000); // ₦1B - Suspicious transaction threshold
+const OVERDRAFT_ALERT_THRESHOLD: Money = Money::from_kobo(-1_000_000_000); // ₦10M negative - Alert on excessive overdraft
 
 /// Bank Transaction Validation - Security & Business Rules Only
 ///
@@ -51,6 +108,7 @@ const OVERDRAFT_ALERT_THRESHOLD: f64 = -10_000_000.0; // ₦10M negative - Alert
 /// - Amount integrity (non-negative, no double-entry)
 /// - Fraud detection (unreasonable amounts)
 /// - Balance consistency (detect suspicious overdrafts)
+/// - Currency of the account, with the rate and base amount when foreign
 pub fn validate_bank_transaction(context: &AssertSetDocContext) -> Result<(), String> {
     let data: BankTransactionData = decode_doc_data(&context.data.data.proposed.data)
         .map_err(|e| format!("Invalid bank transaction data format: {}", e))?;
@@ -58,17 +116,17 @@ pub fn validate_bank_transaction(context: &AssertSetDocContext) -> Result<(), St
     let debit = data.debit_amount;
     let credit = data.credit_amount;
     
-    if debit < 0.0 || credit < 0.0 {
+    if debit.is_negative() || credit.is_negative() {
         return Err("SECURITY: Transaction amounts cannot be negative".to_string());
     }
     
     // CRITICAL: Transaction must have either debit OR credit, not both (double-entry integrity)
-    if debit > 0.0 && credit > 0.0 {
+    if debit.is_positive() && credit.is_positive() {
         return Err("SECURITY: Transaction cannot have both debit and credit amounts".to_string());
     }
     
     // CRITICAL: Transaction must have at least one non-zero amount
-    if debit == 0.0 && credit == 0.0 {
+    if debit.is_zero() && credit.is_zero() {
         return Err("SECURITY: Transaction must have a non-zero amount".to_string());
     }
     
@@ -76,7 +134,7 @@ pub fn validate_bank_transaction(context: &AssertSetDocContext) -> Result<(), St
     let transaction_amount = debit.max(credit);
     if transaction_amount > MAX_SINGLE_TRANSACTION {
         return Err(format!(
-            "FRAUD ALERT: Transaction amount ₦{:.2} exceeds maximum limit of ₦{:.2}. Contact administrator.",
+            "FRAUD ALERT: Transaction amount ₦{} exceeds maximum limit of ₦{}. Contact administrator.",
             transaction_amount, MAX_SINGLE_TRANSACTION
         ));
     }
@@ -84,11 +142,29 @@ pub fn validate_bank_transaction(context: &AssertSetDocContext) -> Result<(), St
     // FRAUD DETECTION: Alert on excessive overdrafts
     if data.balance < OVERDRAFT_ALERT_THRESHOLD {
         return Err(format!(
-            "FRAUD ALERT: Account balance ₦{:.2} exceeds reasonable overdraft limit. Verify account status.",
+            "FRAUD ALERT: Account balance ₦{} exceeds reasonable overdraft limit. Verify account status.",
             data.balance
         ));
     }
     
+    // Domiciliary account transactions are in the account's currency
+    if !data.bank_account_id.is_empty() {
+        if let Some((_, account)) = get_doc_data::<BankAccountData>("bank_accounts", &data.bank_account_id)? {
+            let base_currency = school_settings()?.currency;
+            let account_currency = account.currency.unwrap_or_else(|| base_currency.clone());
+            if data.currency.as_deref().unwrap_or(&base_currency) != account_currency {
+                return Err(format!("Transactions on this account must be in {}", account_currency));
+            }
+        }
+    }
+    validate_foreign_amount(
+        data.currency.as_deref(),
+        transaction_amount,
+        data.fx_rate,
+        data.base_amount,
+        &data.transaction_date,
+    )?;
+    
     // AUDIT: Ensure status transitions are valid
     let valid_statuses = ["pending", "cleared", "reconciled"];
     if !valid_statuses.contains(&data.status.as_str()) {
@@ -123,14 +199,14 @@ pub fn validate_transfer(context: &AssertSetDocContext) -> Result<(), String> {
     }
     
     // CRITICAL: Validate amount is positive
-    if data.amount <= 0.0 {
+    if !data.amount.is_positive() {
         return Err("Transfer amount must be greater than 0".to_string());
     }
     
     // FRAUD DETECTION: Check for unreasonably large transfers
     if data.amount > MAX_SINGLE_TRANSACTION {
         return Err(format!(
-            "FRAUD ALERT: Transfer amount ₦{:.2} exceeds maximum limit. Contact administrator.",
+            "FRAUD ALERT: Transfer amount ₦{} exceeds maximum limit. Contact administrator.",
             data.amount
         ));
     }
@@ -141,14 +217,17 @@ pub fn validate_transfer(context: &AssertSetDocContext) -> Result<(), String> {
         return Err(format!("Invalid status '{}'", data.status));
     }
     
+    // Approval and dual-signatory thresholds are set per school
+    let settings = school_settings()?;
+
     // CRITICAL: Transfers over threshold require approval
-    if data.amount > MAX_TRANSFER_WITHOUT_APPROVAL {
+    if data.amount > settings.transfer_approval_threshold {
         if data.status == "completed" {
             // Must have approvedBy and approvedAt
             if data.approved_by.is_none() || data.approved_by.as_ref().unwrap().trim().is_empty() {
                 return Err(format!(
                     "APPROVAL REQUIRED: Transfers over ₦{:.2} require approval before completion",
-                    MAX_TRANSFER_WITHOUT_APPROVAL
+                    settings.transfer_approval_threshold
                 ));
             }
             
@@ -157,16 +236,110 @@ pub fn validate_transfer(context: &AssertSetDocContext) -> Result<(), String> {
             }
         }
     }
+
+    validate_transfer_approvals(context, &data)?;
+    validate_transfer_not_self_approved(context, &data)?;
+
+    // MANDATE: The approver must sign for the paying account
+    if data.amount > settings.transfer_approval_threshold && data.status == "completed" {
+        validate_signatory_approval(&data.from_account_id, data.approved_by.as_deref(), data.amount)?;
+    }
+
+    // MANDATE: Large transfers need two distinct signatories of the paying account
+    if data.amount > settings.transfer_dual_signatory_threshold && data.status == "completed" {
+        validate_dual_signatories(&data, settings.transfer_dual_signatory_threshold)?;
+    }
     
     Ok(())
 }
 
+/// Signatory approvals are append-only, and each new approval is the caller's own.
+fn validate_transfer_approvals(context: &AssertSetDocContext, data: &InterAccountTransferData) -> Result<(), String> {
+    let previous = match context.data.data.current {
+        Some(ref before_doc) => {
+            let before: InterAccountTransferData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous transfer data: {}", e))?;
+            before.approvals
+        }
+        None => Vec::new(),
+    };
+
+    if data.approvals.len() < previous.len() || data.approvals[..previous.len()] != previous[..] {
+        return Err("AUDIT: Recorded transfer approvals cannot be changed or removed".to_string());
+    }
+
+    let added = &data.approvals[previous.len()..];
+    if added.len() > 1 {
+        return Err("Approvals must be recorded one signatory at a time".to_string());
+    }
+    if let Some(approval) = added.first() {
+        if approval.approved_by != context.caller.to_text() {
+            return Err("SECURITY: Signatories can only record their own approval".to_string());
+        }
+        if previous.iter().any(|a| a.approved_by == approval.approved_by) {
+            return Err("This signatory has already approved the transfer".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// The initiator of a transfer cannot approve it, whether by moving it to approved,
+/// setting approvedBy or recording a signatory approval.
+fn validate_transfer_not_self_approved(context: &AssertSetDocContext, data: &InterAccountTransferData) -> Result<(), String> {
+    let approving = match context.data.data.current {
+        Some(ref before_doc) => {
+            let before: InterAccountTransferData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous transfer data: {}", e))?;
+            (data.status == "approved" && before.status != "approved")
+                || (data.approved_by.is_some() && data.approved_by != before.approved_by)
+                || data.approvals.len() > before.approvals.len()
+        }
+        None => data.status == "approved" || data.approved_by.is_some() || !data.approvals.is_empty(),
+    };
+    if approving {
+        validate_not_self_approval(context, None, "transfers")?;
+    }
+    Ok(())
+}
+
+fn validate_dual_signatories(data: &InterAccountTransferData, threshold: Money) -> Result<(), String> {
+    let (_, account) = get_doc_data::<BankAccountData>("bank_accounts", &data.from_account_id)?
+        .ok_or_else(|| format!("Bank account '{}' not found", data.from_account_id))?;
+
+    if account.signatories.is_empty() {
+        return Err(format!(
+            "MANDATE: Transfers over ₦{:.2} require signatories to be registered on the paying account",
+            threshold
+        ));
+    }
+
+    let signatories: HashSet<&str> = data
+        .approvals
+        .iter()
+        .filter(|a| is_authorised_signatory(&account, &a.approved_by, data.amount))
+        .map(|a| a.approved_by.as_str())
+        .collect();
+
+    if signatories.len() < 2 {
+        return Err(format!(
+            "MANDATE: Transfers over ₦{:.2} require approval by two different account signatories; {} recorded",
+            threshold,
+            signatories.len()
+        ));
+    }
+
+    Ok(())
+}
+
 /// Bank Account Validation - Critical Account Integrity Checks
 ///
 /// Security Checks:
 /// - Unique account numbers (prevent duplicates)
 /// - Balance integrity (detect suspicious balances)
 /// - Account type validation
+/// - Currency code of a domiciliary account
+/// - Signatory mandate changes (four-eyes)
 pub fn validate_bank_account(context: &AssertSetDocContext) -> Result<(), String> {
     let data: BankAccountData = decode_doc_data(&context.data.data.proposed.data)
         .map_err(|e| format!("Invalid bank account data format: {}", e))?;
@@ -176,11 +349,41 @@ pub fn validate_bank_account(context: &AssertSetDocContext) -> Result<(), String
     if !valid_types.contains(&data.account_type.as_str()) {
         return Err(format!("Invalid accountType '{}'. Must be: current or savings", data.account_type));
     }
+    if let Some(ref currency) = data.currency {
+        if !is_currency_code(currency) {
+            return Err(format!("Invalid currency '{}'. Must be a three-letter ISO code, e.g. USD", currency));
+        }
+    }
+
+    let before: Option<BankAccountData> = match context.data.data.current {
+        Some(ref before_doc) => Some(
+            decode_doc_data(&before_doc.data).map_err(|e| format!("Invalid previous bank account data: {}", e))?,
+        ),
+        None => None,
+    };
+    validate_signatory_changes(&context.caller, before.as_ref(), &data)?;
+
+    // CRITICAL: One account record per bank account number
+    let account_number = data.account_number.trim();
+    if account_number.is_empty() {
+        return Err("accountNumber is required".to_string());
+    }
+    let existing = list_doc_data::<BankAccountData>("bank_accounts", None)?;
+    if let Some((_, _, other)) = existing.iter().find(|(key, _, other)| {
+        key != &context.data.key
+            && other.account_number.trim() == account_number
+            && other.bank_name.trim().eq_ignore_ascii_case(data.bank_name.trim())
+    }) {
+        return Err(format!(
+            "SECURITY: Account number {} at {} is already registered",
+            account_number, other.bank_name
+        ));
+    }
     
     // FRAUD DETECTION: Alert on unreasonably negative balances
-    if data.balance < -50_000_000.0 {
+    if data.balance < Money::from_kobo(-5_000_000_000) {
         return Err(format!(
-            "FRAUD ALERT: Account balance ₦{:.2} is unreasonably negative. Verify account integrity.",
+            "FRAUD ALERT: Account balance ₦{} is unreasonably negative. Verify account integrity.",
             data.balance
         ));
     }
@@ -0,0 +1,143 @@
+//! Bank account signatory registry.
+//!
+//! Each bank account carries the mandate held by the bank: who may sign (principal),
+//! in what capacity (application role) and up to what amount. Mandate changes follow
+//! the four-eyes rule: one officer proposes the new list (`pendingSignatories`) and a
+//! different officer applies it. Transfer and cheque approvals are checked against the
+//! registry.
+
+use candid::Principal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::BankAccountData;
+use crate::modules::roles::{caller_has_any_role, get_caller_role, Role};
+use crate::modules::utils::doc_utils::get_doc_data;
+use crate::modules::utils::money::Money;
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSignatory {
+    pub principal: String,
+    pub name: String,
+    pub role: String,
+    pub limit: Option<Money>,
+    pub is_active: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingMandate {
+    pub signatories: Vec<AccountSignatory>,
+    pub requested_by: String,
+    pub requested_at: u64,
+}
+
+/// Signatory Registry Validation
+///
+/// Checks:
+/// - Only bursars and administrators touch the mandate
+/// - Each signatory is a valid principal with an application role and a positive limit
+/// - The active list only changes by applying a pending change proposed by someone else
+pub fn validate_signatory_changes(
+    caller: &Principal,
+    before: Option<&BankAccountData>,
+    data: &BankAccountData,
+) -> Result<(), String> {
+    let (before_signatories, before_pending) = match before {
+        Some(b) => (b.signatories.clone(), b.pending_signatories.clone()),
+        None => (Vec::new(), None),
+    };
+
+    if before_signatories == data.signatories && before_pending == data.pending_signatories {
+        return Ok(());
+    }
+
+    if !caller_has_any_role(caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can change account signatories".to_string());
+    }
+
+    if let Some(ref pending) = data.pending_signatories {
+        validate_signatory_list(&pending.signatories)?;
+        if before_pending.as_ref() != Some(pending) && pending.requested_by != caller.to_text() {
+            return Err("requestedBy must be the principal proposing the mandate change".to_string());
+        }
+    }
+
+    if before_signatories != data.signatories {
+        let pending = before_pending
+            .as_ref()
+            .ok_or("MANDATE: Signatories can only be changed by approving a pending mandate change")?;
+        if pending.signatories != data.signatories || data.pending_signatories.is_some() {
+            return Err("MANDATE: The approved signatories must be exactly the pending mandate change".to_string());
+        }
+        if pending.requested_by == caller.to_text() {
+            return Err("SECURITY: A mandate change must be approved by someone other than its requester".to_string());
+        }
+        if data.mandate_approved_by.as_deref() != Some(caller.to_text().as_str()) {
+            return Err("mandateApprovedBy must be the principal approving the mandate change".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_signatory_list(signatories: &[AccountSignatory]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for signatory in signatories.iter() {
+        Principal::from_text(&signatory.principal)
+            .map_err(|_| format!("Signatory '{}' is not a valid principal", signatory.principal))?;
+        if !seen.insert(signatory.principal.as_str()) {
+            return Err(format!("{} is listed more than once as a signatory", signatory.name));
+        }
+        if signatory.name.trim().is_empty() {
+            return Err("Each signatory must have a name".to_string());
+        }
+        if Role::parse(&signatory.role).is_none() {
+            return Err(format!("Invalid signatory role '{}' for {}", signatory.role, signatory.name));
+        }
+        if signatory.limit.map(|l| !l.is_positive()).unwrap_or(false) {
+            return Err(format!("Signing limit for {} must be greater than 0", signatory.name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Active signatory on the account who still holds the mandated role and may sign
+/// for the amount.
+pub fn is_authorised_signatory(account: &BankAccountData, principal: &str, amount: Money) -> bool {
+    let signatory = match account.signatories.iter().find(|s| s.principal == principal && s.is_active) {
+        Some(s) => s,
+        None => return false,
+    };
+
+    let holds_role = Principal::from_text(principal)
+        .ok()
+        .and_then(|p| get_caller_role(&p))
+        .map(|role| Some(role) == Role::parse(&signatory.role))
+        .unwrap_or(false);
+
+    holds_role && signatory.limit.map(|limit| amount <= limit).unwrap_or(true)
+}
+
+/// Cheques and transfers drawn on an account with a registered mandate must be approved
+/// by one of its signatories within their limit.
+pub fn validate_signatory_approval(account_id: &str, approved_by: Option<&str>, amount: Money) -> Result<(), String> {
+    let (_, account) = get_doc_data::<BankAccountData>("bank_accounts", account_id)?
+        .ok_or_else(|| format!("Bank account '{}' not found", account_id))?;
+
+    if account.signatories.is_empty() {
+        return Ok(());
+    }
+
+    let approver = approved_by.ok_or("Payments from this account must be approved by an account signatory")?;
+    if !is_authorised_signatory(&account, approver, amount) {
+        return Err(format!(
+            "MANDATE: Approver is not a signatory on this account for ₦{}",
+            amount
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,276 @@
+//! Commitment accounting (encumbrances).
+//!
+//! Approved requisitions and purchase orders are recorded as encumbrances
+//! (`encumbrances`) against a budget line before any cash is spent. Expenses recorded
+//! against an encumbrance liquidate it, and closing or cancelling the purchase order
+//! releases whatever is left. Budget enforcement works on
+//! `available = allocation − actuals − outstanding encumbrances`.
+
+use candid::CandidType;
+use junobuild_satellite::{AssertSetDocContext, Doc};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{covers_category, BudgetData, BudgetItemData, BUDGETS_COLLECTION};
+use crate::modules::expenses::splits::expense_allocations;
+use crate::modules::expenses::ExpenseData;
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::utils::validation_utils::academic_year_for_date;
+
+pub const ENCUMBRANCES_COLLECTION: &str = "encumbrances";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncumbranceData {
+    pub budget_id: String,
+    pub category_id: String,
+    pub source_type: String,
+    pub source_reference: String,
+    pub description: String,
+    pub amount: Money,
+    pub status: String,
+    pub raised_by: String,
+    pub closed_by: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct BudgetLineAvailability {
+    pub category_id: String,
+    pub category_name: String,
+    pub allocated: f64,
+    pub actuals: f64,
+    pub encumbered: f64,
+    pub available: f64,
+}
+
+/// Encumbrance Validation
+///
+/// Checks:
+/// - Raised by a bursar, accountant or administrator against an approved/active budget line
+/// - The line has enough available budget for the commitment
+/// - Amount and budget line are fixed; open → closed/cancelled releases the commitment
+pub fn validate_encumbrance_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: EncumbranceData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid encumbrance data format: {}", e))?;
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+        return Err("SECURITY: Only finance staff can record budget commitments".to_string());
+    }
+
+    let valid_sources = ["requisition", "purchase_order"];
+    if !valid_sources.contains(&data.source_type.as_str()) {
+        return Err(format!(
+            "Invalid source type '{}'. Must be one of: {}",
+            data.source_type,
+            valid_sources.join(", ")
+        ));
+    }
+    if data.source_reference.trim().is_empty() {
+        return Err("sourceReference (requisition or PO number) is required".to_string());
+    }
+    if !data.amount.is_positive() {
+        return Err("Encumbrance amount must be greater than 0".to_string());
+    }
+
+    let before_doc = match context.data.data.current {
+        Some(ref doc) => doc,
+        None => {
+            if data.status != "open" {
+                return Err("New encumbrances must have status 'open'".to_string());
+            }
+            if data.raised_by != context.caller.to_text() {
+                return Err("raisedBy must be the principal recording the commitment".to_string());
+            }
+
+            let (_, budget) = get_doc_data::<BudgetData>(BUDGETS_COLLECTION, &data.budget_id)?
+                .ok_or_else(|| format!("Budget '{}' not found", data.budget_id))?;
+            if budget.status != "approved" && budget.status != "active" {
+                return Err(format!("Commitments can only be made against an approved budget; this one is {}", budget.status));
+            }
+            let line = budget
+                .budget_items
+                .iter()
+                .find(|i| i.category_id == data.category_id)
+                .ok_or("Budget line not found for this category")?;
+
+            let available = Money::from_naira(line_availability(&data.budget_id, line, Some(&context.data.key))?.available);
+            if data.amount > available {
+                return Err(format!(
+                    "BUDGET: Only ₦{} is available on '{}'; cannot commit ₦{}",
+                    available.max(Money::ZERO),
+                    line.category_name,
+                    data.amount
+                ));
+            }
+            return Ok(());
+        }
+    };
+
+    let before: EncumbranceData = decode_doc_data(&before_doc.data)
+        .map_err(|e| format!("Invalid previous encumbrance data: {}", e))?;
+
+    if before.budget_id != data.budget_id
+        || before.category_id != data.category_id
+        || before.amount != data.amount
+        || before.source_reference != data.source_reference
+        || before.raised_by != data.raised_by
+    {
+        return Err("AUDIT: Commitments cannot be changed; cancel and raise a new one".to_string());
+    }
+
+    if before.status != data.status {
+        let valid_transitions = HashMap::from([
+            ("open", vec!["closed", "cancelled"]),
+            ("closed", vec![]),
+            ("cancelled", vec![]),
+        ]);
+        let allowed = valid_transitions.get(before.status.as_str()).cloned().unwrap_or_default();
+        if !allowed.contains(&data.status.as_str()) {
+            return Err(format!(
+                "Invalid encumbrance status transition from '{}' to '{}'. Allowed: [{}]",
+                before.status,
+                data.status,
+                allowed.join(", ")
+            ));
+        }
+        if data.closed_by.as_deref() != Some(context.caller.to_text().as_str()) {
+            return Err("closedBy must be the principal closing the commitment".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// New expenses in a budgeted category, and expenses being approved, must fit within the
+/// available budget for the period of the payment date, each split line on its own
+/// category. An expense against an encumbrance draws on that commitment first.
+pub fn validate_expense_budget_availability(
+    context: &AssertSetDocContext,
+    expense: &ExpenseData,
+) -> Result<(), String> {
+    if is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+    if let Some(ref doc) = context.data.data.current {
+        let before: ExpenseData = decode_doc_data(&doc.data)
+            .map_err(|e| format!("Invalid previous expense data: {}", e))?;
+        if expense.status != "approved" || before.status == "approved" {
+            return Ok(());
+        }
+    }
+
+    let allocations = expense_allocations(expense);
+
+    // Commitment drawn on, and the category it is on
+    let mut drawn_commitment: Option<(String, Money)> = None;
+    if let Some(ref encumbrance_id) = expense.encumbrance_id {
+        let (_, encumbrance) = get_doc_data::<EncumbranceData>(ENCUMBRANCES_COLLECTION, encumbrance_id)?
+            .ok_or_else(|| format!("Encumbrance '{}' not found", encumbrance_id))?;
+        if encumbrance.status != "open" {
+            return Err(format!("Commitment {} is {}", encumbrance.source_reference, encumbrance.status));
+        }
+        let line_amount = allocations
+            .iter()
+            .find(|(category_id, _)| category_id == &encumbrance.category_id)
+            .map(|(_, amount)| *amount)
+            .ok_or("Expense category does not match the commitment's budget line")?;
+        let expenses = list_doc_data::<ExpenseData>("expenses", None)?;
+        drawn_commitment = Some((
+            encumbrance.category_id.clone(),
+            outstanding(encumbrance_id, &encumbrance, &expenses).min(line_amount),
+        ));
+    }
+
+    let year = match academic_year_for_date(&expense.payment_date) {
+        Some(year) => year,
+        None => return Ok(()),
+    };
+
+    let budgets = list_doc_data::<BudgetData>(BUDGETS_COLLECTION, None)?;
+    for (category_id, amount) in allocations.iter() {
+        let mut budgeted: Option<String> = None;
+        let mut available = Money::ZERO;
+        for (budget_id, _, budget) in budgets.iter() {
+            if !covers_category(budget, &expense.payment_date, category_id) {
+                continue;
+            }
+            if let Some(line) = budget.budget_items.iter().find(|i| &i.category_id == category_id) {
+                budgeted = Some(line.category_name.clone());
+                available += Money::from_naira(line_availability(budget_id, line, None)?.available);
+            }
+        }
+        let drawn = match drawn_commitment {
+            Some((ref committed_category, drawn)) if committed_category == category_id => drawn,
+            _ => Money::ZERO,
+        };
+
+        if let Some(category_name) = budgeted {
+            if *amount > available + drawn {
+                return Err(format!(
+                    "BUDGET: ₦{} exceeds the ₦{} available for {} in {}",
+                    amount,
+                    (available + drawn).max(Money::ZERO),
+                    category_name,
+                    year
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Allocation, actuals, outstanding commitments and availability for each line of a budget.
+pub fn get_budget_lines_availability(budget_id: &str) -> Result<Vec<BudgetLineAvailability>, String> {
+    let (_, budget) = get_doc_data::<BudgetData>(BUDGETS_COLLECTION, budget_id)?
+        .ok_or_else(|| format!("Budget '{}' not found", budget_id))?;
+
+    budget
+        .budget_items
+        .iter()
+        .map(|line| line_availability(budget_id, line, None))
+        .collect()
+}
+
+fn line_availability(
+    budget_id: &str,
+    line: &BudgetItemData,
+    exclude_key: Option<&str>,
+) -> Result<BudgetLineAvailability, String> {
+    let expenses = list_doc_data::<ExpenseData>("expenses", None)?;
+    let mut encumbered = Money::ZERO;
+    for (key, _, encumbrance) in list_doc_data::<EncumbranceData>(ENCUMBRANCES_COLLECTION, None)? {
+        if encumbrance.budget_id == budget_id
+            && encumbrance.category_id == line.category_id
+            && encumbrance.status == "open"
+            && Some(key.as_str()) != exclude_key
+        {
+            encumbered += outstanding(&key, &encumbrance, &expenses);
+        }
+    }
+
+    Ok(BudgetLineAvailability {
+        category_id: line.category_id.clone(),
+        category_name: line.category_name.clone(),
+        allocated: line.allocated_amount.naira(),
+        actuals: line.spent_amount.naira(),
+        encumbered: encumbered.naira(),
+        available: (line.allocated_amount - line.spent_amount - encumbered).naira(),
+    })
+}
+
+/// Commitment not yet liquidated by approved or paid expenses recorded against it.
+fn outstanding(key: &str, encumbrance: &EncumbranceData, expenses: &[(String, Doc, ExpenseData)]) -> Money {
+    let liquidated: Money = expenses
+        .iter()
+        .filter(|(_, _, e)| e.encumbrance_id.as_deref() == Some(key) && (e.status == "approved" || e.status == "paid"))
+        .map(|(_, _, e)| e.amount)
+        .sum();
+
+    (encumbrance.amount - liquidated).max(Money::ZERO)
+}
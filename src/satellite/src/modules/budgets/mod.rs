@@ -0,0 +1,309 @@
+//! Budgets Module - Budget Validation
+//!
+//! Budgets allocate spending per expense category for an academic (fiscal) year and,
+//! optionally, a term. This module enforces:
+//! - Fiscal year format and term values
+//! - Positive allocations on per-category line items that sum to the budget total
+//! - Spent and balance figures that are consistent with the allocations
+//! - One budget line per category per period across all budgets
+//!
+//! Line items' spent amounts are kept by the satellite: an expense adds to its category's
+//! line when it is approved, in the term budget for its payment date if there is one and
+//! otherwise the year's.
+//!
+//! Reallocations between budget lines go through [`virements`]; commitments from
+//! requisitions and purchase orders are tracked in [`encumbrances`].
+
+pub mod encumbrances;
+pub mod virements;
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::expenses::splits::expense_allocations;
+use super::expenses::{ExpenseCategoryData, ExpenseData};
+use super::utils::cache::cached_doc_data;
+use super::utils::doc_utils::*;
+use super::utils::identity::validate_caller_identity;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const BUDGETS_COLLECTION: &str = "budgets";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetItemData {
+    pub category_id: String,
+    pub category_name: String,
+    pub allocated_amount: Money,
+    pub spent_amount: Money,
+    pub balance: Money,
+    pub notes: Option<String>,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetData {
+    pub academic_year: String,
+    pub term: Option<String>,
+    pub budget_items: Vec<BudgetItemData>,
+    pub total_budget: Money,
+    pub total_spent: Money,
+    pub balance: Money,
+    pub status: String,
+    pub created_by: String,
+    pub approved_by: Option<String>,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Budget Validation
+///
+/// Checks:
+/// - academicYear is YYYY/YYYY (consecutive years), term is first/second/third
+/// - Line items have positive allocations, no repeated category, existing categories
+/// - totalBudget, totalSpent and balance agree with the line items
+/// - Status workflow draft → approved → active → closed
+/// - No other budget for the same period already covers a category
+pub fn validate_budget_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: BudgetData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid budget data format: {}", e))?;
+
+    // approvedBy must name the approving caller
+    validate_caller_identity(context)?;
+
+    if !is_valid_academic_year(&data.academic_year) {
+        return Err("academicYear must be in format YYYY/YYYY (e.g. 2024/2025)".to_string());
+    }
+    if let Some(ref term) = data.term {
+        if !["first", "second", "third"].contains(&term.as_str()) {
+            return Err("term must be 'first', 'second', or 'third'".to_string());
+        }
+    }
+
+    validate_budget_items(&data)?;
+    validate_budget_totals(&data)?;
+    validate_budget_status(context, &data)?;
+    validate_budget_period_uniqueness(context, &data)?;
+
+    Ok(())
+}
+
+fn validate_budget_items(data: &BudgetData) -> Result<(), String> {
+    if data.budget_items.is_empty() {
+        return Err("Budget must have at least one line item".to_string());
+    }
+
+    let mut seen = HashSet::new();
+    for item in data.budget_items.iter() {
+        if item.category_id.trim().is_empty() {
+            return Err("Each budget item must have a categoryId".to_string());
+        }
+        if !seen.insert(item.category_id.as_str()) {
+            return Err(format!(
+                "Category '{}' appears more than once in the budget",
+                item.category_name
+            ));
+        }
+        if !item.allocated_amount.is_positive() {
+            return Err(format!(
+                "Allocation for '{}' must be greater than 0",
+                item.category_name
+            ));
+        }
+        if item.spent_amount.is_negative() {
+            return Err(format!("Spent amount for '{}' cannot be negative", item.category_name));
+        }
+        if item.balance != item.allocated_amount - item.spent_amount {
+            return Err(format!(
+                "Balance for '{}' must equal allocated minus spent",
+                item.category_name
+            ));
+        }
+        if cached_doc_data::<ExpenseCategoryData>("expense_categories", &item.category_id)?.is_none() {
+            return Err(format!("Expense category '{}' not found", item.category_id));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_budget_totals(data: &BudgetData) -> Result<(), String> {
+    let allocated: Money = data.budget_items.iter().map(|i| i.allocated_amount).sum();
+    let spent: Money = data.budget_items.iter().map(|i| i.spent_amount).sum();
+
+    if data.total_budget != allocated {
+        return Err(format!(
+            "totalBudget ₦{} must equal the sum of line item allocations ₦{}",
+            data.total_budget, allocated
+        ));
+    }
+    if data.total_spent != spent {
+        return Err(format!(
+            "totalSpent ₦{} must equal the sum of line item spending ₦{}",
+            data.total_spent, spent
+        ));
+    }
+    if data.balance != data.total_budget - data.total_spent {
+        return Err("Budget balance must equal totalBudget minus totalSpent".to_string());
+    }
+
+    Ok(())
+}
+
+fn validate_budget_status(context: &AssertSetDocContext, data: &BudgetData) -> Result<(), String> {
+    let valid_statuses = ["draft", "approved", "active", "closed"];
+    if !valid_statuses.contains(&data.status.as_str()) {
+        return Err(format!(
+            "Invalid budget status '{}'. Must be one of: {}",
+            data.status,
+            valid_statuses.join(", ")
+        ));
+    }
+
+    if data.status != "draft" && data.approved_by.as_ref().map(|a| a.trim().is_empty()).unwrap_or(true) {
+        return Err("Approved budgets must have approvedBy set".to_string());
+    }
+
+    let before_doc = match context.data.data.current {
+        Some(ref doc) => doc,
+        None => {
+            if data.status != "draft" {
+                return Err("New budgets must be created as 'draft'".to_string());
+            }
+            return Ok(());
+        }
+    };
+
+    let before: BudgetData = decode_doc_data(&before_doc.data)
+        .map_err(|e| format!("Invalid previous budget data: {}", e))?;
+
+    if before.status != data.status {
+        let valid_transitions = HashMap::from([
+            ("draft", vec!["approved"]),
+            ("approved", vec!["active", "draft"]),
+            ("active", vec!["closed"]),
+            ("closed", vec![]),
+        ]);
+
+        let allowed = valid_transitions.get(before.status.as_str()).cloned().unwrap_or_default();
+        if !allowed.contains(&data.status.as_str()) {
+            return Err(format!(
+                "Invalid budget status transition from '{}' to '{}'. Allowed: [{}]",
+                before.status,
+                data.status,
+                allowed.join(", ")
+            ));
+        }
+    }
+
+    if before.status == "closed" {
+        return Err("AUDIT: Closed budgets cannot be modified".to_string());
+    }
+
+    // Once approved, allocations only change by sending the budget back to draft (or
+    // through an approved virement, applied by the satellite)
+    if before.status != "draft" && data.status != "draft" && !is_satellite_caller(&context.caller) {
+        for item in data.budget_items.iter() {
+            let original = before.budget_items.iter().find(|b| b.category_id == item.category_id);
+            if original.map(|b| b.allocated_amount != item.allocated_amount).unwrap_or(true) {
+                return Err("Allocations of an approved budget cannot be changed; return it to draft first".to_string());
+            }
+        }
+        if data.budget_items.len() != before.budget_items.len() {
+            return Err("Line items of an approved budget cannot be added or removed".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_budget_period_uniqueness(context: &AssertSetDocContext, data: &BudgetData) -> Result<(), String> {
+    let budgets = list_doc_data::<BudgetData>(BUDGETS_COLLECTION, None)?;
+
+    for (key, _, other) in budgets.iter() {
+        if key == &context.data.key || other.academic_year != data.academic_year || other.term != data.term {
+            continue;
+        }
+
+        if let Some(item) = data
+            .budget_items
+            .iter()
+            .find(|i| other.budget_items.iter().any(|o| o.category_id == i.category_id))
+        {
+            return Err(format!(
+                "A budget for '{}' already exists for {}{}",
+                item.category_name,
+                data.academic_year,
+                data.term.as_ref().map(|t| format!(" ({} term)", t)).unwrap_or_default()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether an approved or active budget covers an expense category for the academic
+/// year and term of a payment date. Budgets without a term cover the whole year.
+pub fn covers_category(budget: &BudgetData, payment_date: &str, category_id: &str) -> bool {
+    (budget.status == "approved" || budget.status == "active")
+        && Some(budget.academic_year.as_str()) == academic_year_for_date(payment_date).as_deref()
+        && (budget.term.is_none() || budget.term.as_deref() == term_for_date(payment_date))
+        && budget.budget_items.iter().any(|i| i.category_id == category_id)
+}
+
+/// Called from the `expenses` on-set and on-delete hooks: moves approved and paid
+/// expenses into (and out of) their budget lines' spent amounts, line by line for split
+/// expenses.
+pub fn record_budget_spending(before: Option<&ExpenseData>, after: Option<&ExpenseData>) -> Result<(), String> {
+    fn spent(expense: Option<&ExpenseData>) -> Option<&ExpenseData> {
+        expense.filter(|e| e.status == "approved" || e.status == "paid")
+    }
+    let (before, after) = (spent(before), spent(after));
+    if let (Some(b), Some(a)) = (before, after) {
+        if expense_allocations(b) == expense_allocations(a) && b.payment_date == a.payment_date {
+            return Ok(());
+        }
+    }
+
+    if let Some(expense) = before {
+        for (category_id, amount) in expense_allocations(expense) {
+            adjust_budget_spending(&expense.payment_date, &category_id, -amount)?;
+        }
+    }
+    if let Some(expense) = after {
+        for (category_id, amount) in expense_allocations(expense) {
+            adjust_budget_spending(&expense.payment_date, &category_id, amount)?;
+        }
+    }
+    Ok(())
+}
+
+// Charge the term budget covering the category, else the year's; unbudgeted spending is
+// left alone
+fn adjust_budget_spending(payment_date: &str, category_id: &str, delta: Money) -> Result<(), String> {
+    let mut budgets: Vec<_> = list_doc_data::<BudgetData>(BUDGETS_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, budget)| covers_category(budget, payment_date, category_id))
+        .collect();
+    budgets.sort_by_key(|(_, _, budget)| budget.term.is_none());
+    let Some((budget_id, doc, mut budget)) = budgets.into_iter().next() else {
+        return Ok(());
+    };
+
+    if let Some(item) = budget.budget_items.iter_mut().find(|i| i.category_id == category_id) {
+        item.spent_amount += delta;
+        item.balance = item.allocated_amount - item.spent_amount;
+    }
+    budget.total_spent = budget.budget_items.iter().map(|i| i.spent_amount).sum();
+    budget.balance = budget.total_budget - budget.total_spent;
+
+    set_doc_data(BUDGETS_COLLECTION, &budget_id, &budget, doc.description.clone(), doc.version)?;
+    Ok(())
+}
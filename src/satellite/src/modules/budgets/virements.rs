@@ -0,0 +1,265 @@
+//! Budget virements: moving allocation between budget lines.
+//!
+//! A virement (`budget_virements`) requests moving an amount from one budget line to
+//! another within the same academic year. Policy limits how much of a line may be
+//! moved out in a year. Once approved by someone other than the requester, the
+//! satellite adjusts both budget documents and records a memorandum entry in the
+//! journal.
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{BudgetData, BUDGETS_COLLECTION};
+use crate::modules::ledger::{record_memo_entry, JournalLineData};
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::utils::validation_utils::current_date;
+
+pub const BUDGET_VIREMENTS_COLLECTION: &str = "budget_virements";
+
+// Policy: at most this share of a line's allocation may be moved out in a year
+const VIREMENT_LIMIT_PERCENT: f64 = 25.0;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetVirementData {
+    pub from_budget_id: String,
+    pub from_category_id: String,
+    pub to_budget_id: String,
+    pub to_category_id: String,
+    pub amount: Money,
+    pub reason: String,
+    pub status: String,
+    pub requested_by: String,
+    pub approved_by: Option<String>,
+    pub approved_at: Option<u64>,
+    #[serde(default)]
+    pub applied_at: Option<u64>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Budget Virement Validation
+///
+/// Checks:
+/// - Different source and target lines, both in approved/active budgets of the same year
+/// - Source line has headroom (unspent, not already promised to pending virements)
+/// - Cumulative virements out of the line stay within the policy limit
+/// - pending → approved/rejected by a bursar/admin who did not request it
+pub fn validate_budget_virement_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: BudgetVirementData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid budget virement data format: {}", e))?;
+
+    if !data.amount.is_positive() {
+        return Err("Virement amount must be greater than 0".to_string());
+    }
+    if data.reason.trim().len() < 10 {
+        return Err("Virements must include a reason of at least 10 characters".to_string());
+    }
+    if data.from_budget_id == data.to_budget_id && data.from_category_id == data.to_category_id {
+        return Err("Source and target budget lines must be different".to_string());
+    }
+
+    match context.data.data.current {
+        Some(ref before_doc) => {
+            let before: BudgetVirementData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous virement data: {}", e))?;
+            validate_virement_update(context, &before, &data)
+        }
+        None => {
+            if data.status != "pending" {
+                return Err("New virements must have status 'pending'".to_string());
+            }
+            if data.requested_by != context.caller.to_text() {
+                return Err("requestedBy must be the principal requesting the virement".to_string());
+            }
+            validate_virement_headroom(context, &data)
+        }
+    }
+}
+
+fn validate_virement_update(
+    context: &AssertSetDocContext,
+    before: &BudgetVirementData,
+    data: &BudgetVirementData,
+) -> Result<(), String> {
+    if before.from_budget_id != data.from_budget_id
+        || before.from_category_id != data.from_category_id
+        || before.to_budget_id != data.to_budget_id
+        || before.to_category_id != data.to_category_id
+        || before.amount != data.amount
+        || before.requested_by != data.requested_by
+    {
+        return Err("AUDIT: Virement lines and amount cannot be changed once requested".to_string());
+    }
+
+    if before.status == data.status {
+        if is_satellite_caller(&context.caller) {
+            return Ok(());
+        }
+        return Err(format!("Virement is already {}", before.status));
+    }
+
+    let valid_transitions = HashMap::from([
+        ("pending", vec!["approved", "rejected"]),
+        ("approved", vec![]),
+        ("rejected", vec![]),
+    ]);
+    let allowed = valid_transitions.get(before.status.as_str()).cloned().unwrap_or_default();
+    if !allowed.contains(&data.status.as_str()) {
+        return Err(format!(
+            "Invalid virement status transition from '{}' to '{}'. Allowed: [{}]",
+            before.status,
+            data.status,
+            allowed.join(", ")
+        ));
+    }
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can decide on virements".to_string());
+    }
+    if data.requested_by == context.caller.to_text() {
+        return Err("SECURITY: Virements cannot be approved or rejected by their requester".to_string());
+    }
+    if data.approved_by.as_deref() != Some(context.caller.to_text().as_str()) || data.approved_at.is_none() {
+        return Err("approvedBy and approvedAt must record the principal deciding on the virement".to_string());
+    }
+
+    if data.status == "approved" {
+        validate_virement_headroom(context, data)?;
+    }
+
+    Ok(())
+}
+
+fn validate_virement_headroom(context: &AssertSetDocContext, data: &BudgetVirementData) -> Result<(), String> {
+    let (_, source) = get_doc_data::<BudgetData>(BUDGETS_COLLECTION, &data.from_budget_id)?
+        .ok_or_else(|| format!("Budget '{}' not found", data.from_budget_id))?;
+    let (_, target) = get_doc_data::<BudgetData>(BUDGETS_COLLECTION, &data.to_budget_id)?
+        .ok_or_else(|| format!("Budget '{}' not found", data.to_budget_id))?;
+
+    for budget in [&source, &target] {
+        if budget.status != "approved" && budget.status != "active" {
+            return Err(format!(
+                "Virements only move funds between approved or active budgets; a {} budget is {}",
+                budget.academic_year, budget.status
+            ));
+        }
+    }
+    if source.academic_year != target.academic_year {
+        return Err("Virements must stay within the same academic year".to_string());
+    }
+
+    let line = source
+        .budget_items
+        .iter()
+        .find(|i| i.category_id == data.from_category_id)
+        .ok_or("Source budget line not found")?;
+    if !target.budget_items.iter().any(|i| i.category_id == data.to_category_id) {
+        return Err("Target budget line not found".to_string());
+    }
+
+    let virements = list_doc_data::<BudgetVirementData>(BUDGET_VIREMENTS_COLLECTION, None)?;
+    let from_line = |v: &&(String, _, BudgetVirementData)| {
+        v.0 != context.data.key && v.2.from_budget_id == data.from_budget_id && v.2.from_category_id == data.from_category_id
+    };
+
+    let promised: Money = virements.iter().filter(from_line).filter(|v| v.2.status == "pending").map(|v| v.2.amount).sum();
+    let headroom = line.balance - promised;
+    if data.amount > headroom {
+        return Err(format!(
+            "'{}' has only ₦{} unspent and uncommitted; cannot move ₦{}",
+            line.category_name, headroom.max(Money::ZERO), data.amount
+        ));
+    }
+
+    // Applied virements have already reduced the allocation; add them back for the base
+    let moved_out: Money = virements.iter().filter(from_line).filter(|v| v.2.status == "approved").map(|v| v.2.amount).sum();
+    let limit = (line.allocated_amount + moved_out).percent(VIREMENT_LIMIT_PERCENT);
+    if moved_out + data.amount > limit {
+        return Err(format!(
+            "Virement policy allows at most {}% (₦{}) of '{}' to be moved out; ₦{} already moved",
+            VIREMENT_LIMIT_PERCENT, limit, line.category_name, moved_out
+        ));
+    }
+
+    Ok(())
+}
+
+/// Called from the `budget_virements` on-set hook: applies an approved virement to both
+/// budget documents and records a memorandum entry in the journal.
+pub fn on_budget_virement_saved(key: &str, virement: &BudgetVirementData) -> Result<(), String> {
+    if virement.status != "approved" || virement.applied_at.is_some() {
+        return Ok(());
+    }
+
+    adjust_budget_line(&virement.from_budget_id, &virement.from_category_id, -virement.amount)?;
+    adjust_budget_line(&virement.to_budget_id, &virement.to_category_id, virement.amount)?;
+
+    let (doc, mut applied) = get_doc_data::<BudgetVirementData>(BUDGET_VIREMENTS_COLLECTION, key)?
+        .ok_or_else(|| format!("Virement '{}' not found", key))?;
+    let now = ic_cdk::api::time();
+    applied.applied_at = Some(now);
+    applied.updated_at = now;
+    set_doc_data(BUDGET_VIREMENTS_COLLECTION, key, &applied, doc.description.clone(), doc.version)?;
+
+    record_virement_memo(key, virement)
+}
+
+fn adjust_budget_line(budget_id: &str, category_id: &str, delta: Money) -> Result<(), String> {
+    let (doc, mut budget) = get_doc_data::<BudgetData>(BUDGETS_COLLECTION, budget_id)?
+        .ok_or_else(|| format!("Budget '{}' not found", budget_id))?;
+
+    let item = budget
+        .budget_items
+        .iter_mut()
+        .find(|i| i.category_id == category_id)
+        .ok_or_else(|| format!("Budget line '{}' not found", category_id))?;
+    item.allocated_amount += delta;
+    item.balance = item.allocated_amount - item.spent_amount;
+
+    budget.total_budget = budget.budget_items.iter().map(|i| i.allocated_amount).sum();
+    budget.balance = budget.total_budget - budget.total_spent;
+
+    set_doc_data(BUDGETS_COLLECTION, budget_id, &budget, doc.description.clone(), doc.version)?;
+    Ok(())
+}
+
+fn record_virement_memo(key: &str, virement: &BudgetVirementData) -> Result<(), String> {
+    let line_name = |budget_id: &str, category_id: &str| -> Result<String, String> {
+        Ok(get_doc_data::<BudgetData>(BUDGETS_COLLECTION, budget_id)?
+            .and_then(|(_, b)| b.budget_items.into_iter().find(|i| i.category_id == category_id))
+            .map(|i| i.category_name)
+            .unwrap_or_else(|| category_id.to_string()))
+    };
+    let from_name = line_name(&virement.from_budget_id, &virement.from_category_id)?;
+    let to_name = line_name(&virement.to_budget_id, &virement.to_category_id)?;
+
+    let memo_line = |category_id: &str, name: &str, debit: Money, credit: Money| JournalLineData {
+        account_id: category_id.to_string(),
+        account_name: format!("Budget: {}", name),
+        account_code: String::new(),
+        debit,
+        credit,
+        description: Some(virement.reason.clone()),
+    };
+
+    let today = current_date();
+    record_memo_entry(
+        &format!("budget-virement-{}", key),
+        &format!("VIR-{}", key),
+        &today,
+        &format!("Budget virement of ₦{} from {} to {}", virement.amount, from_name, to_name),
+        "adjustment",
+        Some(key.to_string()),
+        vec![
+            memo_line(&virement.to_category_id, &to_name, virement.amount, Money::ZERO),
+            memo_line(&virement.from_category_id, &from_name, Money::ZERO, virement.amount),
+        ],
+    )?;
+
+    Ok(())
+}
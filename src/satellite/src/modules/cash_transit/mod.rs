@@ -0,0 +1,209 @@
+//! Cash Transit Module - Custody of Physical Cash Movements
+//!
+//! Cash physically moving between the cashier, the bank and campus (`cash_movements`)
+//! is a common loss vector. This module enforces a custody chain:
+//! - The dispatcher names the principal who will receive the cash
+//! - Only that principal can confirm receipt, recording the amount actually counted
+//! - Shortfalls are recorded as `disputed` with an explanation
+//! - Movements still in transit after a day are reported as alerts
+
+use candid::{CandidType, Principal};
+use junobuild_satellite::{caller, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+
+pub const CASH_MOVEMENTS_COLLECTION: &str = "cash_movements";
+
+const ONE_HOUR_NS: u64 = 3_600_000_000_000;
+const TRANSIT_ALERT_HOURS: u64 = 24;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CashMovementData {
+    pub movement_type: String,
+    pub amount: Money,
+    pub from_location: String,
+    pub to_location: String,
+    pub carrier_name: String,
+    pub dispatched_by: String,
+    pub dispatched_at: u64,
+    pub receiving_principal: String,
+    pub status: String,
+    pub received_by: Option<String>,
+    pub received_at: Option<u64>,
+    pub received_amount: Option<Money>,
+    pub bank_transaction_id: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct CashTransitAlert {
+    pub movement_id: String,
+    pub movement_type: String,
+    pub amount: f64,
+    pub from_location: String,
+    pub to_location: String,
+    pub carrier_name: String,
+    pub receiving_principal: String,
+    pub hours_in_transit: u64,
+}
+
+/// Cash Movement Validation
+///
+/// Security Checks:
+/// - Dispatcher is the caller and cannot also be the receiver
+/// - Movement details are fixed once dispatched
+/// - Only the named receiving principal can confirm or dispute custody
+/// - Received amount must match, otherwise the movement is disputed with notes
+pub fn validate_cash_movement_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: CashMovementData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid cash movement data format: {}", e))?;
+
+    let valid_types = ["cashier_to_bank", "bank_to_campus", "campus_transfer"];
+    if !valid_types.contains(&data.movement_type.as_str()) {
+        return Err(format!(
+            "Invalid movement type '{}'. Must be one of: {}",
+            data.movement_type,
+            valid_types.join(", ")
+        ));
+    }
+    if !data.amount.is_positive() {
+        return Err("Cash movement amount must be greater than 0".to_string());
+    }
+    if data.from_location.trim().is_empty() || data.to_location.trim().is_empty() {
+        return Err("Origin and destination are required".to_string());
+    }
+    if data.carrier_name.trim().is_empty() {
+        return Err("The name of the person carrying the cash is required".to_string());
+    }
+    Principal::from_text(&data.receiving_principal)
+        .map_err(|_| "receivingPrincipal must be the receiver's principal".to_string())?;
+
+    match context.data.data.current {
+        Some(ref before_doc) => {
+            let before: CashMovementData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous cash movement data: {}", e))?;
+            validate_custody_handover(context, &before, &data)
+        }
+        None => {
+            if data.status != "in_transit" {
+                return Err("New cash movements must have status 'in_transit'".to_string());
+            }
+            if data.dispatched_by != context.caller.to_text() {
+                return Err("dispatchedBy must be the principal dispatching the cash".to_string());
+            }
+            // A future dispatch time would hold back the in-transit alert
+            if data.dispatched_at > ic_cdk::api::time() + ONE_HOUR_NS {
+                return Err("dispatchedAt cannot be in the future".to_string());
+            }
+            if data.receiving_principal == data.dispatched_by {
+                return Err("SECURITY: Cash cannot be dispatched to oneself; name a different receiver".to_string());
+            }
+            if data.received_by.is_some() || data.received_at.is_some() || data.received_amount.is_some() {
+                return Err("Receipt details are recorded by the receiver on arrival".to_string());
+            }
+            Ok(())
+        }
+    }
+}
+
+fn validate_custody_handover(
+    context: &AssertSetDocContext,
+    before: &CashMovementData,
+    data: &CashMovementData,
+) -> Result<(), String> {
+    if before.movement_type != data.movement_type
+        || before.amount != data.amount
+        || before.from_location != data.from_location
+        || before.to_location != data.to_location
+        || before.carrier_name != data.carrier_name
+        || before.dispatched_by != data.dispatched_by
+        || before.dispatched_at != data.dispatched_at
+        || before.receiving_principal != data.receiving_principal
+    {
+        return Err("AUDIT: Cash movement details cannot be changed after dispatch".to_string());
+    }
+
+    if before.status == data.status {
+        if before.status != "in_transit" {
+            return Err("AUDIT: Completed cash movements cannot be modified".to_string());
+        }
+        return Ok(());
+    }
+
+    let valid_transitions = HashMap::from([
+        ("in_transit", vec!["received", "disputed"]),
+        ("received", vec![]),
+        ("disputed", vec![]),
+    ]);
+
+    let allowed = valid_transitions.get(before.status.as_str()).cloned().unwrap_or_default();
+    if !allowed.contains(&data.status.as_str()) {
+        return Err(format!(
+            "Invalid cash movement status transition from '{}' to '{}'. Allowed: [{}]",
+            before.status,
+            data.status,
+            allowed.join(", ")
+        ));
+    }
+
+    let caller_text = context.caller.to_text();
+    if caller_text != data.receiving_principal {
+        return Err("SECURITY: Only the named receiver can confirm custody of this cash".to_string());
+    }
+    if data.received_by.as_deref() != Some(caller_text.as_str()) || data.received_at.is_none() {
+        return Err("Receipt must record receivedBy (the receiver) and receivedAt".to_string());
+    }
+
+    let received_amount = data
+        .received_amount
+        .ok_or("receivedAmount (the amount counted on arrival) is required")?;
+
+    match data.status.as_str() {
+        "received" if received_amount != data.amount => Err(format!(
+            "Counted amount ₦{} differs from the dispatched ₦{}; record the movement as disputed",
+            received_amount, data.amount
+        )),
+        "disputed" if data.notes.as_ref().map(|n| n.trim().len() < 10).unwrap_or(true) => {
+            Err("Disputed movements must explain the discrepancy in notes (at least 10 characters)".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Movements still awaiting custody confirmation more than a day after dispatch.
+pub fn list_transit_alerts() -> Result<Vec<CashTransitAlert>, String> {
+    if !caller_has_any_role(&caller(), &[Role::SuperAdmin, Role::Bursar, Role::Accountant, Role::Auditor]) {
+        return Err("SECURITY: Not authorised to view cash movements".to_string());
+    }
+
+    let now = ic_cdk::api::time();
+    let movements = list_doc_data::<CashMovementData>(CASH_MOVEMENTS_COLLECTION, None)?;
+
+    let mut alerts: Vec<CashTransitAlert> = movements
+        .into_iter()
+        .filter(|(_, _, m)| m.status == "in_transit")
+        .map(|(key, _, m)| (key, now.saturating_sub(m.dispatched_at) / ONE_HOUR_NS, m))
+        .filter(|(_, hours, _)| *hours >= TRANSIT_ALERT_HOURS)
+        .map(|(key, hours, m)| CashTransitAlert {
+            movement_id: key,
+            movement_type: m.movement_type,
+            amount: m.amount.naira(),
+            from_location: m.from_location,
+            to_location: m.to_location,
+            carrier_name: m.carrier_name,
+            receiving_principal: m.receiving_principal,
+            hours_in_transit: hours,
+        })
+        .collect();
+
+    alerts.sort_by_key(|a| std::cmp::Reverse(a.hours_in_transit));
+    Ok(alerts)
+}
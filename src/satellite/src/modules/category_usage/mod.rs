@@ -0,0 +1,285 @@
+//! Category Usage Module - Usage Counters for Fee and Expense Categories
+//!
+//! One document per category in `category_usage` (key `fee:<categoryId>` or
+//! `expense:<categoryId>`) counts what is recorded against it:
+//! - Fee categories: fee assignments listing the category, and those still owing on it
+//!   (open, not closed out)
+//! - Expense categories: expenses in the category, and those not yet settled (pending or
+//!   approved but unpaid)
+//!
+//! The counters are kept by the satellite from the `student_fee_assignments` and
+//! `expenses` hooks, so validators can read them without scanning either collection. A
+//! category with open assignments or unsettled expenses cannot be deactivated.
+//! `rebuild_category_usage` recounts from scratch, for data recorded before the counters
+//! existed.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::expenses::{ExpenseCategoryData, ExpenseData};
+use super::fees::{FeeCategoryData, StudentFeeAssignmentData};
+use super::roles::{require_role, Role};
+use super::utils::cache::cached_list_doc_data;
+use super::utils::doc_utils::*;
+use super::utils::scan::scan_collection;
+
+pub const CATEGORY_USAGE_COLLECTION: &str = "category_usage";
+
+// Expense statuses still committing spend against their category
+const UNSETTLED_EXPENSE_STATUSES: [&str; 2] = ["pending", "approved"];
+
+#[derive(Deserialize, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryUsageData {
+    pub kind: String,
+    pub category_id: String,
+    pub fee_assignments: u32,
+    pub open_fee_assignments: u32,
+    pub expenses: u32,
+    pub pending_expenses: u32,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct CategoryUsage {
+    // "fee" or "expense"
+    pub kind: String,
+    pub category_id: String,
+    pub category_name: String,
+    pub is_active: bool,
+    pub fee_assignments: u32,
+    pub open_fee_assignments: u32,
+    pub expenses: u32,
+    pub pending_expenses: u32,
+}
+
+// Changes to apply per usage document: total and open/pending counts
+type UsageDeltas = HashMap<(&'static str, String), (i64, i64)>;
+
+/// Usage counters are maintained by the satellite only.
+pub fn validate_category_usage_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Category usage is maintained by the satellite".to_string());
+    }
+    Ok(())
+}
+
+/// Called from the `student_fee_assignments` on-set and on-delete hooks.
+pub fn record_fee_assignment_usage(
+    before: Option<&StudentFeeAssignmentData>,
+    after: Option<&StudentFeeAssignmentData>,
+) -> Result<(), String> {
+    let mut deltas = UsageDeltas::new();
+    if let Some(assignment) = before {
+        add_assignment_usage(&mut deltas, assignment, -1);
+    }
+    if let Some(assignment) = after {
+        add_assignment_usage(&mut deltas, assignment, 1);
+    }
+    apply_deltas(deltas)
+}
+
+/// Called from the `expenses` on-set and on-delete hooks.
+pub fn record_expense_usage(before: Option<&ExpenseData>, after: Option<&ExpenseData>) -> Result<(), String> {
+    let mut deltas = UsageDeltas::new();
+    if let Some(expense) = before {
+        add_expense_usage(&mut deltas, expense, -1);
+    }
+    if let Some(expense) = after {
+        add_expense_usage(&mut deltas, expense, 1);
+    }
+    apply_deltas(deltas)
+}
+
+/// A fee category cannot be deactivated while fee assignments still owe on it.
+pub fn validate_fee_category_deactivation(key: &str) -> Result<(), String> {
+    let blockers = category_usage("fee", key)?.open_fee_assignments;
+    if blockers > 0 {
+        return Err(format!(
+            "Cannot deactivate fee category '{}': {} open fee assignment(s) still owe on it",
+            key, blockers
+        ));
+    }
+    Ok(())
+}
+
+/// An expense category cannot be deactivated while expenses in it are pending or
+/// approved but unpaid.
+pub fn validate_expense_category_deactivation(key: &str) -> Result<(), String> {
+    let blockers = category_usage("expense", key)?.pending_expenses;
+    if blockers > 0 {
+        return Err(format!(
+            "Cannot deactivate expense category '{}': {} expense(s) still pending",
+            key, blockers
+        ));
+    }
+    Ok(())
+}
+
+/// Usage of every fee and expense category, including unused ones.
+pub fn get_category_usage() -> Result<Vec<CategoryUsage>, String> {
+    let counted: HashMap<String, CategoryUsageData> = list_doc_data::<CategoryUsageData>(CATEGORY_USAGE_COLLECTION, None)?
+        .into_iter()
+        .map(|(key, _, usage)| (key, usage))
+        .collect();
+    let usage_of = |kind: &str, category_id: &str| counted.get(&usage_key(kind, category_id));
+
+    let mut usage = Vec::new();
+    for (key, _, category) in cached_list_doc_data::<FeeCategoryData>("fee_categories")? {
+        let counts = usage_of("fee", &key);
+        usage.push(CategoryUsage {
+            kind: "fee".to_string(),
+            category_name: category.name,
+            is_active: category.is_active,
+            fee_assignments: counts.map(|c| c.fee_assignments).unwrap_or(0),
+            open_fee_assignments: counts.map(|c| c.open_fee_assignments).unwrap_or(0),
+            expenses: 0,
+            pending_expenses: 0,
+            category_id: key,
+        });
+    }
+    for (key, _, category) in cached_list_doc_data::<ExpenseCategoryData>("expense_categories")? {
+        let counts = usage_of("expense", &key);
+        usage.push(CategoryUsage {
+            kind: "expense".to_string(),
+            category_name: category.name,
+            is_active: category.is_active,
+            fee_assignments: 0,
+            open_fee_assignments: 0,
+            expenses: counts.map(|c| c.expenses).unwrap_or(0),
+            pending_expenses: counts.map(|c| c.pending_expenses).unwrap_or(0),
+            category_id: key,
+        });
+    }
+    Ok(usage)
+}
+
+/// Recount category usage from the fee assignments and expenses (SuperAdmin). Starting
+/// without a cursor clears the counters; a returned cursor means the recount stopped at
+/// the instruction budget and is resumed by calling again with it.
+pub fn rebuild_category_usage(cursor: Option<String>) -> Result<Option<String>, String> {
+    require_role(&caller(), Role::SuperAdmin)?;
+
+    if cursor.is_none() {
+        for (key, doc, _) in list_doc_data::<CategoryUsageData>(CATEGORY_USAGE_COLLECTION, None)? {
+            delete_doc_data(CATEGORY_USAGE_COLLECTION, &key, doc.version)?;
+        }
+    }
+
+    let mut deltas = UsageDeltas::new();
+    let mut cursor = cursor;
+    if !cursor.as_deref().is_some_and(|c| c.starts_with("expenses:")) {
+        let progress = scan_collection::<StudentFeeAssignmentData>("student_fee_assignments", cursor, |_, _, assignment| {
+            add_assignment_usage(&mut deltas, &assignment, 1);
+            Ok(())
+        })?;
+        if progress.next_cursor.is_some() {
+            apply_deltas(deltas)?;
+            return Ok(progress.next_cursor);
+        }
+        cursor = None;
+    }
+    let progress = scan_collection::<ExpenseData>("expenses", cursor, |_, _, expense| {
+        add_expense_usage(&mut deltas, &expense, 1);
+        Ok(())
+    })?;
+    apply_deltas(deltas)?;
+    Ok(progress.next_cursor)
+}
+
+/// Number of usage counters that differ from a recount of the fee assignments and
+/// expenses (counters missing or left over included). Non-zero means the counters have
+/// drifted and `rebuild_category_usage` should be run.
+pub fn count_usage_drift() -> Result<u32, String> {
+    let mut deltas = UsageDeltas::new();
+    for (_, _, assignment) in list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)? {
+        add_assignment_usage(&mut deltas, &assignment, 1);
+    }
+    for (_, _, expense) in list_doc_data::<ExpenseData>("expenses", None)? {
+        add_expense_usage(&mut deltas, &expense, 1);
+    }
+    let mut recounted: HashMap<String, (i64, i64)> =
+        deltas.into_iter().map(|((kind, category_id), counts)| (usage_key(kind, &category_id), counts)).collect();
+
+    let mut drifted = 0;
+    for (key, _, usage) in list_doc_data::<CategoryUsageData>(CATEGORY_USAGE_COLLECTION, None)? {
+        let stored = match usage.kind.as_str() {
+            "fee" => (usage.fee_assignments as i64, usage.open_fee_assignments as i64),
+            _ => (usage.expenses as i64, usage.pending_expenses as i64),
+        };
+        if recounted.remove(&key).unwrap_or((0, 0)) != stored {
+            drifted += 1;
+        }
+    }
+    drifted += recounted.values().filter(|counts| **counts != (0, 0)).count() as u32;
+    Ok(drifted)
+}
+
+fn usage_key(kind: &str, category_id: &str) -> String {
+    format!("{}:{}", kind, category_id)
+}
+
+fn category_usage(kind: &str, category_id: &str) -> Result<CategoryUsageData, String> {
+    Ok(get_doc_data::<CategoryUsageData>(CATEGORY_USAGE_COLLECTION, &usage_key(kind, category_id))?
+        .map(|(_, usage)| usage)
+        .unwrap_or_default())
+}
+
+// An assignment counts once per category it lists, and is open on a category while the
+// item still has a balance and the assignment has not been closed out
+fn add_assignment_usage(deltas: &mut UsageDeltas, assignment: &StudentFeeAssignmentData, sign: i64) {
+    let mut open_by_category: HashMap<&str, bool> = HashMap::new();
+    for item in assignment.fee_items.iter() {
+        let open = item.balance.is_positive() && assignment.closed_out_at.is_none();
+        *open_by_category.entry(item.category_id.as_str()).or_insert(false) |= open;
+    }
+    for (category_id, open) in open_by_category {
+        let entry = deltas.entry(("fee", category_id.to_string())).or_insert((0, 0));
+        entry.0 += sign;
+        if open {
+            entry.1 += sign;
+        }
+    }
+}
+
+fn add_expense_usage(deltas: &mut UsageDeltas, expense: &ExpenseData, sign: i64) {
+    let entry = deltas.entry(("expense", expense.category_id.clone())).or_insert((0, 0));
+    entry.0 += sign;
+    if UNSETTLED_EXPENSE_STATUSES.contains(&expense.status.as_str()) {
+        entry.1 += sign;
+    }
+}
+
+fn apply_deltas(deltas: UsageDeltas) -> Result<(), String> {
+    let adjust = |count: u32, delta: i64| (count as i64 + delta).max(0) as u32;
+
+    for ((kind, category_id), (total, open)) in deltas {
+        if total == 0 && open == 0 {
+            continue;
+        }
+        let key = usage_key(kind, &category_id);
+        let (version, mut usage) = match get_doc_data::<CategoryUsageData>(CATEGORY_USAGE_COLLECTION, &key)? {
+            Some((doc, usage)) => (doc.version, usage),
+            None => (
+                None,
+                CategoryUsageData {
+                    kind: kind.to_string(),
+                    category_id: category_id.clone(),
+                    ..Default::default()
+                },
+            ),
+        };
+        if kind == "fee" {
+            usage.fee_assignments = adjust(usage.fee_assignments, total);
+            usage.open_fee_assignments = adjust(usage.open_fee_assignments, open);
+        } else {
+            usage.expenses = adjust(usage.expenses, total);
+            usage.pending_expenses = adjust(usage.pending_expenses, open);
+        }
+        usage.updated_at = ic_cdk::api::time();
+        set_doc_data(CATEGORY_USAGE_COLLECTION, &key, &usage, None, version)?;
+    }
+    Ok(())
+}
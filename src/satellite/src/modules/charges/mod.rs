@@ -0,0 +1,229 @@
+//! Charges Module - Ad-hoc Student Charges
+//!
+//! Small charges such as library fines or a lost ID card are recorded in `student_charges`
+//! and posted onto the student's fee assignment as their own fee item
+//! (category id `charge:<charge key>`). ID card reprint charges are posted by the ID card
+//! issuance (see [`super::id_cards`]). Because they live on the assignment, they appear
+//! in statements and receipts alongside term fees. This module enforces:
+//! - A known charge type and an amount within that type's cap
+//! - The fee assignment belongs to the charged student
+//! - Charges are immutable; they can only be waived, and only while unpaid
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::fees::{fee_assignment_status, FeeItemData, StudentFeeAssignmentData};
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const STUDENT_CHARGES_COLLECTION: &str = "student_charges";
+
+/// Maximum amount per charge, by charge type
+const CHARGE_TYPE_CAPS: [(&str, Money); 7] = [
+    ("library_fine", Money::from_kobo(500_000)),
+    ("lost_id_card", Money::from_kobo(300_000)),
+    ("id_card_reprint", Money::from_kobo(300_000)),
+    ("lost_book", Money::from_kobo(2_000_000)),
+    ("damaged_property", Money::from_kobo(5_000_000)),
+    ("late_registration", Money::from_kobo(1_000_000)),
+    ("other", Money::from_kobo(1_000_000)),
+];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StudentChargeData {
+    pub student_id: String,
+    pub fee_assignment_id: String,
+    pub charge_type: String,
+    pub description: String,
+    pub amount: Money,
+    pub charge_date: String,
+    pub status: String,
+    pub waived_by: Option<String>,
+    pub waiver_reason: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Student Charge Validation
+///
+/// Checks:
+/// - Charge type is known and the amount is within its cap
+/// - Student and fee assignment exist and match
+/// - New charges are `posted`; the only change allowed is `posted` → `waived`
+/// - Waivers are by a bursar/admin, with a reason, before anything is paid on the charge
+pub fn validate_student_charge_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: StudentChargeData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid student charge data format: {}", e))?;
+
+    let cap = CHARGE_TYPE_CAPS
+        .iter()
+        .find(|(charge_type, _)| *charge_type == data.charge_type)
+        .map(|(_, cap)| *cap)
+        .ok_or_else(|| {
+            format!(
+                "Invalid charge type '{}'. Must be one of: {}",
+                data.charge_type,
+                CHARGE_TYPE_CAPS.iter().map(|(t, _)| *t).collect::<Vec<_>>().join(", ")
+            )
+        })?;
+
+    if !data.amount.is_positive() {
+        return Err("Charge amount must be greater than 0".to_string());
+    }
+    if data.amount > cap {
+        return Err(format!(
+            "A {} charge cannot exceed ₦{}",
+            data.charge_type.replace('_', " "),
+            cap
+        ));
+    }
+    if data.description.trim().len() < 5 {
+        return Err("Charge description must be at least 5 characters".to_string());
+    }
+    if !is_valid_date_format(&data.charge_date) {
+        return Err("Invalid charge date format. Must be YYYY-MM-DD".to_string());
+    }
+
+    let before_doc = match context.data.data.current {
+        Some(ref doc) => doc,
+        None => {
+            if data.status != "posted" {
+                return Err("New charges must have status 'posted'".to_string());
+            }
+            if data.recorded_by != context.caller.to_text() && !is_satellite_caller(&context.caller) {
+                return Err("recordedBy must be the principal recording the charge".to_string());
+            }
+            if !doc_exists("students", &data.student_id)? {
+                return Err(format!("Student '{}' not found", data.student_id));
+            }
+
+            let (_, assignment) =
+                get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", &data.fee_assignment_id)?
+                    .ok_or_else(|| format!("Fee assignment '{}' not found", data.fee_assignment_id))?;
+            if assignment.student_id != data.student_id {
+                return Err("Fee assignment does not belong to the charged student".to_string());
+            }
+            return Ok(());
+        }
+    };
+
+    let before: StudentChargeData = decode_doc_data(&before_doc.data)
+        .map_err(|e| format!("Invalid previous charge data: {}", e))?;
+
+    if before.student_id != data.student_id
+        || before.fee_assignment_id != data.fee_assignment_id
+        || before.charge_type != data.charge_type
+        || before.amount != data.amount
+        || before.recorded_by != data.recorded_by
+    {
+        return Err("AUDIT: Posted charges cannot be changed; waive and re-post instead".to_string());
+    }
+
+    if before.status == data.status {
+        return Err(format!("Charge is already {}", before.status));
+    }
+    if before.status != "posted" || data.status != "waived" {
+        return Err(format!(
+            "Invalid charge status transition from '{}' to '{}'. Allowed: [waived]",
+            before.status, data.status
+        ));
+    }
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can waive charges".to_string());
+    }
+    if data.waived_by.as_deref() != Some(context.caller.to_text().as_str()) {
+        return Err("waivedBy must be the principal waiving the charge".to_string());
+    }
+    if data.waiver_reason.as_ref().map(|r| r.trim().len() < 10).unwrap_or(true) {
+        return Err("Waivers must include a reason of at least 10 characters".to_string());
+    }
+
+    let (_, assignment) =
+        get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", &data.fee_assignment_id)?
+            .ok_or_else(|| format!("Fee assignment '{}' not found", data.fee_assignment_id))?;
+    let item_id = charge_item_id(&context.data.key);
+    if assignment
+        .fee_items
+        .iter()
+        .any(|item| item.category_id == item_id && item.amount_paid.is_positive())
+    {
+        return Err("Charges that have been (partly) paid cannot be waived".to_string());
+    }
+
+    Ok(())
+}
+
+/// Called from the `student_charges` on-set hook: posts a new charge onto the fee
+/// assignment, or removes a waived charge from it.
+pub fn on_student_charge_saved(
+    key: &str,
+    before: Option<&StudentChargeData>,
+    charge: &StudentChargeData,
+) -> Result<(), String> {
+    let posting = before.is_none() && charge.status == "posted";
+    let waiving = before.map(|b| b.status == "posted").unwrap_or(false) && charge.status == "waived";
+    if !posting && !waiving {
+        return Ok(());
+    }
+
+    let (doc, mut assignment) =
+        get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", &charge.fee_assignment_id)?
+            .ok_or_else(|| format!("Fee assignment '{}' not found", charge.fee_assignment_id))?;
+
+    let item_id = charge_item_id(key);
+    let delta = if posting {
+        if assignment.fee_items.iter().any(|item| item.category_id == item_id) {
+            return Ok(());
+        }
+        assignment.fee_items.push(FeeItemData {
+            category_id: item_id,
+            category_name: charge.description.clone(),
+            fee_type: "other".to_string(),
+            amount: charge.amount,
+            amount_paid: Money::ZERO,
+            balance: charge.amount,
+            is_mandatory: true,
+            is_optional: Some(false),
+            is_selected: None,
+            extra: serde_json::Map::from_iter([(
+                "chargeType".to_string(),
+                serde_json::Value::String(charge.charge_type.clone()),
+            )]),
+        });
+        charge.amount
+    } else {
+        let before_len = assignment.fee_items.len();
+        assignment.fee_items.retain(|item| item.category_id != item_id);
+        if assignment.fee_items.len() == before_len {
+            return Ok(());
+        }
+        -charge.amount
+    };
+
+    assignment.total_amount += delta;
+    if let Some(original) = assignment.original_amount {
+        assignment.original_amount = Some(original + delta);
+    }
+    assignment.balance = assignment.total_amount - assignment.amount_paid;
+    assignment.status = fee_assignment_status(assignment.amount_paid, assignment.balance).to_string();
+
+    set_doc_data(
+        "student_fee_assignments",
+        &charge.fee_assignment_id,
+        &assignment,
+        doc.description.clone(),
+        doc.version,
+    )?;
+
+    Ok(())
+}
+
+fn charge_item_id(charge_key: &str) -> String {
+    format!("charge:{}", charge_key)
+}
@@ -0,0 +1,150 @@
+//! Classes Module - Class Validation
+//!
+//! Classes (`classes`) are set up per academic year, e.g. "JSS 2" section "A" for 2024/2025.
+//! This module enforces:
+//! - Academic year format, school level and optional term
+//! - Non-negative capacity, with enrollment never above it
+//! - One class per name and section per academic year
+//! - The class teacher is an active member of staff
+//! - Classes with enrolled students cannot be deleted
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::staff::StaffMemberData;
+use super::utils::doc_utils::*;
+use super::utils::validation_utils::*;
+
+pub const CLASSES_COLLECTION: &str = "classes";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassData {
+    pub name: String,
+    pub section: Option<String>,
+    pub level: String,
+    pub academic_year: String,
+    pub term: Option<String>,
+    pub capacity: Option<i64>,
+    #[serde(default)]
+    pub current_enrollment: i64,
+    pub class_teacher_id: Option<String>,
+    pub room: Option<String>,
+    pub description: Option<String>,
+    pub is_active: bool,
+}
+
+/// Class Validation
+///
+/// Checks:
+/// - Name is present; level is nursery/primary/jss/sss
+/// - academicYear is YYYY/YYYY, term (if set) is first/second/third
+/// - Capacity and enrollment are non-negative, enrollment within capacity
+/// - Name + section is unique within the academic year
+/// - classTeacherId references an active staff member
+pub fn validate_class_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: ClassData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid class data format: {}", e))?;
+
+    if data.name.trim().is_empty() {
+        return Err("Class name is required".to_string());
+    }
+
+    let valid_levels = ["nursery", "primary", "jss", "sss"];
+    if !valid_levels.contains(&data.level.as_str()) {
+        return Err(format!(
+            "Invalid class level '{}'. Must be one of: {}",
+            data.level,
+            valid_levels.join(", ")
+        ));
+    }
+
+    if !is_valid_academic_year(&data.academic_year) {
+        return Err("academicYear must be in format YYYY/YYYY (e.g. 2024/2025)".to_string());
+    }
+    if let Some(ref term) = data.term {
+        if !["first", "second", "third"].contains(&term.as_str()) {
+            return Err("term must be 'first', 'second', or 'third'".to_string());
+        }
+    }
+
+    if data.current_enrollment < 0 {
+        return Err("currentEnrollment cannot be negative".to_string());
+    }
+    if let Some(capacity) = data.capacity {
+        if capacity < 0 {
+            return Err("Class capacity cannot be negative".to_string());
+        }
+        if data.current_enrollment > capacity {
+            return Err(format!(
+                "Class capacity ({}) cannot be below current enrollment ({})",
+                capacity, data.current_enrollment
+            ));
+        }
+    }
+
+    validate_class_name_uniqueness(context, &data)?;
+    validate_class_teacher(context, &data)?;
+
+    Ok(())
+}
+
+fn validate_class_name_uniqueness(context: &AssertSetDocContext, data: &ClassData) -> Result<(), String> {
+    let name = data.name.trim().to_lowercase();
+    let section = section_key(&data.section);
+
+    let classes = list_doc_data::<ClassData>(CLASSES_COLLECTION, None)?;
+    if classes.iter().any(|(key, _, other)| {
+        key != &context.data.key
+            && other.academic_year == data.academic_year
+            && other.name.trim().to_lowercase() == name
+            && section_key(&other.section) == section
+    }) {
+        return Err(format!(
+            "Class '{}{}' already exists for {}",
+            data.name.trim(),
+            data.section.as_ref().map(|s| format!(" {}", s.trim())).unwrap_or_default(),
+            data.academic_year
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_class_teacher(context: &AssertSetDocContext, data: &ClassData) -> Result<(), String> {
+    let teacher_id = match data.class_teacher_id {
+        Some(ref id) if !id.trim().is_empty() => id,
+        _ => return Ok(()),
+    };
+
+    // Only re-check when the teacher is assigned or changed, so a teacher leaving
+    // does not block unrelated edits to the class
+    if let Some(ref before_doc) = context.data.data.current {
+        let before: ClassData = decode_doc_data(&before_doc.data)
+            .map_err(|e| format!("Invalid previous class data: {}", e))?;
+        if before.class_teacher_id.as_ref() == Some(teacher_id) {
+            return Ok(());
+        }
+    }
+
+    let (_, teacher) = get_doc_data::<StaffMemberData>("staff", teacher_id)?
+        .ok_or_else(|| format!("Class teacher '{}' not found in staff", teacher_id))?;
+    if !teacher.is_active {
+        return Err(format!(
+            "{} {} is not an active staff member and cannot be class teacher",
+            teacher.firstname, teacher.surname
+        ));
+    }
+
+    Ok(())
+}
+
+fn section_key(section: &Option<String>) -> String {
+    section.as_ref().map(|s| s.trim().to_lowercase()).unwrap_or_default()
+}
+
+/// A class cannot be deleted while students are enrolled in it.
+pub fn validate_class_delete(key: &str) -> Result<(), String> {
+    deny_if_referenced("class", key, &[("students", referencing_keys("students", "classId", key)?)])
+}
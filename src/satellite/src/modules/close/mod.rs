@@ -0,0 +1,271 @@
+//! Close Module - Month-End Close Checklist and Period Locks
+//!
+//! Before a month (YYYY-MM) is closed its books must be complete. `get_close_readiness`
+//! evaluates the checklist:
+//! - Cash sessions closed: no cash movement dispatched by month end is still in transit
+//! - Bank accounts reconciled: every bank transaction of the month is reconciled
+//! - Payroll posted: a payroll run covers the month and no salary payment for a period
+//!   ending in the month is unpaid
+//! - Suspense cleared: no open suspense item dated by month end, and the suspense
+//!   clearing account (1190) is nil at month end
+//!
+//! `close_period` locks the month in `period_closes` only when every check passes or a
+//! failing check is waived with a reason. Once closed, payments, expenses, salary
+//! payments and bank transactions dated in the month can no longer be created, changed
+//! or deleted.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertDeleteDocContext, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::banking::BankTransactionData;
+use super::cash_transit::{CashMovementData, CASH_MOVEMENTS_COLLECTION};
+use super::ledger::{JournalEntryData, JOURNAL_ENTRIES_COLLECTION};
+use super::payroll::{PayrollRunData, PAYROLL_RUNS_COLLECTION};
+use super::reconciliation::suspense::open_suspense_items;
+use super::roles::{require_role, Role};
+use super::staff::SalaryPaymentData;
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const PERIOD_CLOSES_COLLECTION: &str = "period_closes";
+
+const SUSPENSE_ACCOUNT_CODE: &str = "1190";
+const MIN_WAIVER_REASON_LENGTH: usize = 10;
+
+// Locked collections and the date field that places a document in a period
+const LOCKED_DATE_FIELDS: [(&str, &str); 4] = [
+    ("bank_transactions", "transactionDate"),
+    ("expenses", "paymentDate"),
+    ("payments", "paymentDate"),
+    ("salary_payments", "paymentDate"),
+];
+
+#[derive(CandidType, Deserialize, Serialize, Clone)]
+pub struct CloseWaiver {
+    pub check: String,
+    pub reason: String,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodCloseData {
+    pub period: String,
+    pub waivers: Vec<CloseWaiver>,
+    pub closed_by: String,
+    pub closed_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct CloseCheck {
+    pub check: String,
+    pub label: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct CloseReadiness {
+    pub period: String,
+    pub checks: Vec<CloseCheck>,
+    pub ready: bool,
+    pub closed: bool,
+}
+
+/// Period closes are recorded by `close_period` only and are final.
+pub fn validate_period_close_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Periods are closed through the close_period endpoint".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: A closed period cannot be modified".to_string());
+    }
+    Ok(())
+}
+
+/// Closed periods can never be reopened by deleting the close.
+pub fn validate_period_close_delete() -> Result<(), String> {
+    Err("AUDIT: A closed period cannot be reopened".to_string())
+}
+
+/// Evaluate the month-end checklist for a period (YYYY-MM).
+pub fn get_close_readiness(period: String) -> Result<CloseReadiness, String> {
+    if !is_valid_period(&period) {
+        return Err("Invalid period. Must be in format YYYY-MM".to_string());
+    }
+
+    let checks = evaluate_checks(&period)?;
+    Ok(CloseReadiness {
+        ready: checks.iter().all(|c| c.passed),
+        closed: doc_exists(PERIOD_CLOSES_COLLECTION, &period)?,
+        period,
+        checks,
+    })
+}
+
+/// Lock a period. Every failing check must be waived with a reason.
+pub fn close_period(period: String, waivers: Vec<CloseWaiver>) -> Result<CloseReadiness, String> {
+    let caller = caller();
+    require_role(&caller, Role::Bursar)?;
+
+    if !is_valid_period(&period) {
+        return Err("Invalid period. Must be in format YYYY-MM".to_string());
+    }
+    if doc_exists(PERIOD_CLOSES_COLLECTION, &period)? {
+        return Err(format!("Period {} is already closed", period));
+    }
+    if period.as_str() >= &current_date()[0..7] {
+        return Err(format!("Period {} has not ended yet", period));
+    }
+
+    let checks = evaluate_checks(&period)?;
+    for waiver in waivers.iter() {
+        let check = checks
+            .iter()
+            .find(|c| c.check == waiver.check)
+            .ok_or_else(|| format!("Unknown close check '{}'", waiver.check))?;
+        if check.passed {
+            return Err(format!("Check '{}' passes and does not need a waiver", check.check));
+        }
+        if waiver.reason.trim().len() < MIN_WAIVER_REASON_LENGTH {
+            return Err(format!(
+                "Waiver reason for '{}' must be at least {} characters",
+                check.check, MIN_WAIVER_REASON_LENGTH
+            ));
+        }
+    }
+    let outstanding: Vec<String> = checks
+        .iter()
+        .filter(|c| !c.passed && !waivers.iter().any(|w| w.check == c.check))
+        .map(|c| format!("{} ({})", c.label, c.detail))
+        .collect();
+    if !outstanding.is_empty() {
+        return Err(format!(
+            "Period {} cannot be closed until these checks pass or are waived: {}",
+            period,
+            outstanding.join("; ")
+        ));
+    }
+
+    let close = PeriodCloseData {
+        period: period.clone(),
+        waivers,
+        closed_by: caller.to_text(),
+        closed_at: ic_cdk::api::time(),
+    };
+    set_doc_data(PERIOD_CLOSES_COLLECTION, &period, &close, None, None)?;
+
+    Ok(CloseReadiness {
+        ready: checks.iter().all(|c| c.passed),
+        closed: true,
+        period,
+        checks,
+    })
+}
+
+/// Reject writes to documents dated in a closed period, whether the document is being
+/// created there, changed there, or moved out of it.
+pub fn validate_period_open(context: &AssertSetDocContext) -> Result<(), String> {
+    if is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+    check_period_open(&context.data.collection, context.data.data.current.as_ref().map(|d| &d.data))?;
+    check_period_open(&context.data.collection, Some(&context.data.data.proposed.data))
+}
+
+/// Reject deletion of documents dated in a closed period.
+pub fn validate_period_open_on_delete(context: &AssertDeleteDocContext) -> Result<(), String> {
+    check_period_open(&context.data.collection, context.data.data.current.as_ref().map(|d| &d.data))
+}
+
+fn check_period_open(collection: &str, data: Option<&Vec<u8>>) -> Result<(), String> {
+    let field = match LOCKED_DATE_FIELDS.iter().find(|(c, _)| *c == collection) {
+        Some((_, field)) => *field,
+        None => return Ok(()),
+    };
+    let date = match data.and_then(|d| decode_doc_data::<serde_json::Value>(d).ok()) {
+        Some(data) => data.get(field).and_then(|v| v.as_str()).map(str::to_string),
+        None => None,
+    };
+
+    match date.as_deref().and_then(|d| d.get(0..7)) {
+        Some(period) if doc_exists(PERIOD_CLOSES_COLLECTION, period)? => {
+            Err(format!("PERIOD_CLOSED: {} is closed; entries dated in it cannot be changed", period))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn evaluate_checks(period: &str) -> Result<Vec<CloseCheck>, String> {
+    let in_or_before = |date: &str| date.get(0..7).map(|p| p <= period).unwrap_or(false);
+
+    let in_transit = list_doc_data::<CashMovementData>(CASH_MOVEMENTS_COLLECTION, None)?
+        .iter()
+        .filter(|(_, _, m)| m.status == "in_transit" && in_or_before(&date_from_timestamp(m.dispatched_at)))
+        .count();
+
+    let unreconciled = list_doc_data::<BankTransactionData>("bank_transactions", None)?
+        .iter()
+        .filter(|(_, _, t)| t.transaction_date.starts_with(period) && t.status != "reconciled")
+        .count();
+
+    let unpaid_salaries = list_doc_data::<SalaryPaymentData>("salary_payments", None)?
+        .iter()
+        .filter(|(_, _, s)| {
+            s.payment_period_end.starts_with(period) && (s.status == "pending" || s.status == "approved")
+        })
+        .count();
+
+    let payroll_run = list_doc_data::<PayrollRunData>(PAYROLL_RUNS_COLLECTION, None)?
+        .iter()
+        .any(|(_, _, r)| {
+            r.period_start.get(0..7).map(|p| p <= period).unwrap_or(false)
+                && r.period_end.get(0..7).map(|p| p >= period).unwrap_or(false)
+        });
+
+    let suspense_balance: Money = list_doc_data::<JournalEntryData>(JOURNAL_ENTRIES_COLLECTION, None)?
+        .iter()
+        .filter(|(_, _, e)| e.status == "posted" && in_or_before(&e.entry_date))
+        .flat_map(|(_, _, e)| e.lines.iter())
+        .filter(|l| l.account_code == SUSPENSE_ACCOUNT_CODE)
+        .map(|l| l.debit - l.credit)
+        .sum();
+
+    let open_suspense = open_suspense_items(&format!("{}-31", period))?.len();
+
+    Ok(vec![
+        CloseCheck {
+            check: "cash_sessions_closed".to_string(),
+            label: "All cash sessions closed".to_string(),
+            passed: in_transit == 0,
+            detail: format!("{} cash movement(s) still in transit", in_transit),
+        },
+        CloseCheck {
+            check: "bank_reconciled".to_string(),
+            label: "Bank accounts reconciled".to_string(),
+            passed: unreconciled == 0,
+            detail: format!("{} bank transaction(s) not reconciled", unreconciled),
+        },
+        CloseCheck {
+            check: "payroll_posted".to_string(),
+            label: "Payroll posted".to_string(),
+            passed: payroll_run && unpaid_salaries == 0,
+            detail: if payroll_run {
+                format!("{} salary payment(s) pending or approved but unpaid", unpaid_salaries)
+            } else {
+                format!("No payroll run covers {}; waive the check if no payroll is due", period)
+            },
+        },
+        CloseCheck {
+            check: "suspense_cleared".to_string(),
+            label: "Suspense items cleared".to_string(),
+            passed: open_suspense == 0 && suspense_balance.is_zero(),
+            detail: format!(
+                "{} open suspense item(s); suspense account balance ₦{}",
+                open_suspense, suspense_balance
+            ),
+        },
+    ])
+}
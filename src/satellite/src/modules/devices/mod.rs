@@ -0,0 +1,90 @@
+//! Devices Module - Cashier Station Registration
+//!
+//! Cash is taken only at known front-desk terminals. Each terminal is registered in
+//! `cashier_devices` by an administrator (document key = device id) with the public key
+//! it generated and the station it sits at. Cash payments must carry the id of an active
+//! registered device, so cash cannot be entered from an arbitrary browser.
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+
+pub const CASHIER_DEVICES_COLLECTION: &str = "cashier_devices";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CashierDeviceData {
+    pub name: String,
+    // Station the terminal is installed at, e.g. "Main gate bursary window"
+    pub location: String,
+    pub device_key: String,
+    pub is_active: bool,
+    pub registered_by: String,
+    pub registered_at: u64,
+}
+
+/// Cashier Device Validation
+///
+/// Checks:
+/// - Registered and revoked by super admins only
+/// - Name, location and device key present; a device key registers one device only
+/// - Device key and registration details are fixed once registered
+pub fn validate_cashier_device_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: CashierDeviceData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid cashier device data format: {}", e))?;
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only super admins can register cashier devices".to_string());
+    }
+
+    if data.name.trim().is_empty() || data.location.trim().is_empty() {
+        return Err("Device name and location are required".to_string());
+    }
+    if data.device_key.trim().len() < 32 {
+        return Err("Device key must be the terminal's public key".to_string());
+    }
+
+    match context.data.data.current {
+        Some(ref before_doc) => {
+            let before: CashierDeviceData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous cashier device data: {}", e))?;
+            if before.device_key != data.device_key
+                || before.registered_by != data.registered_by
+                || before.registered_at != data.registered_at
+            {
+                return Err("Device key and registration details cannot be changed; register a new device".to_string());
+            }
+        }
+        None => {
+            if data.registered_by != context.caller.to_text() {
+                return Err("registeredBy must be the principal registering the device".to_string());
+            }
+        }
+    }
+
+    let duplicate = list_doc_data::<CashierDeviceData>(CASHIER_DEVICES_COLLECTION, None)?
+        .into_iter()
+        .find(|(key, _, device)| key != &context.data.key && device.device_key == data.device_key);
+    if let Some((key, _, _)) = duplicate {
+        return Err(format!("SECURITY: This device key is already registered as device '{}'", key));
+    }
+
+    Ok(())
+}
+
+/// Cash must be recorded from an active registered cashier device.
+pub fn validate_cash_entry_device(device_id: Option<&str>) -> Result<(), String> {
+    let device_id = match device_id {
+        Some(id) if !id.trim().is_empty() => id,
+        _ => return Err("SECURITY: Cash payments must be recorded from a registered cashier device".to_string()),
+    };
+
+    match get_doc_data::<CashierDeviceData>(CASHIER_DEVICES_COLLECTION, device_id)? {
+        Some((_, device)) if device.is_active => Ok(()),
+        Some(_) => Err(format!("SECURITY: Cashier device '{}' has been deactivated", device_id)),
+        None => Err(format!("SECURITY: Cashier device '{}' is not registered", device_id)),
+    }
+}
@@ -0,0 +1,222 @@
+//! Disbursements Module - Bank Payment File Acknowledgments
+//!
+//! After a salary or vendor payment file is uploaded to the bank, the bank returns an
+//! acknowledgment file listing which credits succeeded and which failed. This module:
+//! - Imports acknowledgment batches exactly once (`bank_acknowledgments`)
+//! - Marks approved salary payments `paid` or `failed` per the bank's response
+//! - Marks batched vendor expenses `paid` (see [`vendors`])
+//! - Routes failed items into the retry queue (`disbursement_retries`, see [`retries`])
+
+pub mod retries;
+pub mod vendors;
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext};
+use serde::{Deserialize, Serialize};
+
+use super::audit::record_system_change;
+use super::roles::{caller_has_any_role, Role};
+use super::staff::{on_salary_payment_saved, SalaryPaymentData};
+use super::utils::doc_utils::*;
+use super::utils::validation_utils::*;
+use retries::{find_resubmitted_retry, queue_retry, settle_retry};
+
+pub const BANK_ACKNOWLEDGMENTS_COLLECTION: &str = "bank_acknowledgments";
+
+const MAX_ACKNOWLEDGMENTS_PER_BATCH: usize = 500;
+
+#[derive(CandidType, Deserialize)]
+pub struct PaymentAcknowledgment {
+    pub reference: String,
+    pub status: String,
+    pub bank_reference: Option<String>,
+    pub failure_reason: Option<String>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct AcknowledgmentBatch {
+    pub batch_reference: String,
+    pub value_date: String,
+    pub acknowledgments: Vec<PaymentAcknowledgment>,
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone)]
+pub struct AcknowledgmentResult {
+    pub reference: String,
+    pub outcome: String,
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankAcknowledgmentData {
+    pub batch_reference: String,
+    pub value_date: String,
+    pub imported_by: String,
+    pub imported_at: u64,
+    pub results: Vec<AcknowledgmentResult>,
+}
+
+/// Import a bank acknowledgment batch for salary and vendor payments.
+///
+/// Each acknowledgment is applied independently; items that cannot be applied are
+/// reported as `skipped` with a message rather than failing the whole batch.
+pub fn import_acknowledgments(batch: AcknowledgmentBatch) -> Result<Vec<AcknowledgmentResult>, String> {
+    let caller = caller();
+    if !caller_has_any_role(&caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can import bank acknowledgments".to_string());
+    }
+
+    if batch.batch_reference.trim().is_empty() {
+        return Err("Batch reference is required".to_string());
+    }
+    if !is_valid_date_format(&batch.value_date) {
+        return Err("Invalid value date format. Must be YYYY-MM-DD".to_string());
+    }
+    if batch.acknowledgments.is_empty() {
+        return Err("Acknowledgment batch is empty".to_string());
+    }
+    if batch.acknowledgments.len() > MAX_ACKNOWLEDGMENTS_PER_BATCH {
+        return Err(format!(
+            "Acknowledgment batch cannot exceed {} items",
+            MAX_ACKNOWLEDGMENTS_PER_BATCH
+        ));
+    }
+
+    // A bank return file is applied exactly once
+    let batch_key = batch.batch_reference.trim().to_string();
+    if get_doc_data::<BankAcknowledgmentData>(BANK_ACKNOWLEDGMENTS_COLLECTION, &batch_key)?.is_some() {
+        return Err(format!("Acknowledgment batch '{}' has already been imported", batch_key));
+    }
+
+    let results: Vec<AcknowledgmentResult> = batch
+        .acknowledgments
+        .iter()
+        .map(|ack| {
+            apply_acknowledgment(&batch_key, &batch.value_date, ack).unwrap_or_else(|e| AcknowledgmentResult {
+                reference: ack.reference.clone(),
+                outcome: "skipped".to_string(),
+                message: Some(e),
+            })
+        })
+        .collect();
+
+    let record = BankAcknowledgmentData {
+        batch_reference: batch_key.clone(),
+        value_date: batch.value_date.clone(),
+        imported_by: caller.to_text(),
+        imported_at: ic_cdk::api::time(),
+        results: results.clone(),
+    };
+    set_doc_data(
+        BANK_ACKNOWLEDGMENTS_COLLECTION,
+        &batch_key,
+        &record,
+        Some(format!("value_date={};", batch.value_date)),
+        None,
+    )?;
+
+    Ok(results)
+}
+
+fn apply_acknowledgment(
+    batch_reference: &str,
+    value_date: &str,
+    ack: &PaymentAcknowledgment,
+) -> Result<AcknowledgmentResult, String> {
+    if ack.status != "paid" && ack.status != "failed" {
+        return Err(format!("Unknown acknowledgment status '{}'. Must be paid or failed", ack.status));
+    }
+
+    let reason = ack
+        .failure_reason
+        .clone()
+        .filter(|r| !r.trim().is_empty())
+        .unwrap_or_else(|| "Rejected by bank (no reason given)".to_string());
+
+    // Vendor payments carry the expense reference
+    if ack.reference.starts_with("EXP-") {
+        return vendors::apply_vendor_acknowledgment(batch_reference, ack, &reason);
+    }
+
+    let (key, doc, mut salary) = list_doc_data::<SalaryPaymentData>("salary_payments", None)?
+    .into_iter()
+    .find(|(_, _, s)| s.reference == ack.reference)
+    .ok_or_else(|| format!("Salary payment '{}' not found", ack.reference))?;
+
+    // A failed payment that was resubmitted through the retry queue
+    if salary.status == "failed" {
+        let (retry_key, retry_doc, retry) = find_resubmitted_retry(&salary.reference)?
+            .ok_or_else(|| "Salary payment failed previously and has no resubmitted retry".to_string())?;
+
+        settle_retry(&retry_key, &retry_doc, retry, &ack.status, &reason, batch_reference)?;
+
+        if ack.status == "failed" {
+            return Ok(AcknowledgmentResult {
+                reference: ack.reference.clone(),
+                outcome: "failed".to_string(),
+                message: Some(reason),
+            });
+        }
+    } else if salary.status == ack.status {
+        return Err(format!("Salary payment is already {}", salary.status));
+    } else if salary.status != "approved" {
+        return Err(format!(
+            "Only approved salary payments can be acknowledged (current status: {})",
+            salary.status
+        ));
+    }
+
+    let old_status = salary.status.clone();
+    let now = ic_cdk::api::time();
+    salary.status = ack.status.clone();
+    salary.bank_reference = ack.bank_reference.clone();
+    salary.updated_at = now;
+
+    if ack.status == "paid" {
+        salary.payment_date = value_date.to_string();
+    } else {
+        salary.failure_reason = Some(reason.clone());
+
+        queue_retry(
+            "salary_payments",
+            &key,
+            &salary.reference,
+            &salary.staff_name,
+            salary.net_salary,
+            &reason,
+            batch_reference,
+        )?;
+    }
+
+    set_doc_data(
+        "salary_payments",
+        &key,
+        &salary,
+        doc.description.clone(),
+        doc.version,
+    )?;
+    // Satellite writes skip the on-set hook, so the audit entry is recorded here
+    record_system_change("salary_payments", &key, "acknowledgment", Some(old_status), Some(salary.status.clone()))?;
+
+    if salary.status == "paid" {
+        on_salary_payment_saved(&key, &salary)?;
+    }
+
+    Ok(AcknowledgmentResult {
+        reference: ack.reference.clone(),
+        outcome: ack.status.clone(),
+        message: salary.failure_reason.clone().filter(|_| ack.status == "failed"),
+    })
+}
+
+/// Acknowledgment records are written by the import endpoint only.
+pub fn validate_bank_acknowledgment_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Bank acknowledgments can only be recorded through the import endpoint".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Bank acknowledgment records cannot be modified".to_string());
+    }
+    Ok(())
+}
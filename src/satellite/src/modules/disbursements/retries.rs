@@ -0,0 +1,347 @@
+//! Retry queue for failed bank disbursements (salaries, vendor payments).
+//!
+//! Lifecycle: `queued` → `corrected` → `resubmitted` → `succeeded`, or back to `queued`
+//! when the bank rejects the retry again. Entries can be `cancelled` while not in flight.
+//! The original payment document is never rewritten with new bank details; every attempt
+//! is kept in the retry's `attempts` history.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext, Doc};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::modules::expenses::ExpenseData;
+use crate::modules::procurement::vendors::{VendorData, VENDORS_COLLECTION};
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::utils::validation_utils::*;
+
+pub const DISBURSEMENT_RETRIES_COLLECTION: &str = "disbursement_retries";
+
+const MAX_RETRY_ATTEMPTS: usize = 3;
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryAttempt {
+    pub batch_reference: String,
+    pub outcome: String,
+    pub failure_reason: Option<String>,
+    pub recorded_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisbursementRetryData {
+    pub source_collection: String,
+    pub source_key: String,
+    pub reference: String,
+    pub payee_name: String,
+    pub amount: Money,
+    pub failure_reason: String,
+    pub status: String,
+    pub bank_name: Option<String>,
+    pub account_number: Option<String>,
+    pub account_name: Option<String>,
+    pub corrected_by: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub attempts: Vec<RetryAttempt>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone)]
+pub struct DisbursementFileEntry {
+    pub reference: String,
+    pub payee_name: String,
+    pub bank_name: String,
+    pub account_number: String,
+    pub amount: f64,
+    pub narration: String,
+}
+
+/// Add a failed disbursement to the retry queue. The failed batch is recorded as the
+/// first attempt.
+pub fn queue_retry(
+    source_collection: &str,
+    source_key: &str,
+    reference: &str,
+    payee_name: &str,
+    amount: Money,
+    failure_reason: &str,
+    batch_reference: &str,
+) -> Result<(), String> {
+    let now = ic_cdk::api::time();
+    let retry = DisbursementRetryData {
+        source_collection: source_collection.to_string(),
+        source_key: source_key.to_string(),
+        reference: reference.to_string(),
+        payee_name: payee_name.to_string(),
+        amount,
+        failure_reason: failure_reason.to_string(),
+        status: "queued".to_string(),
+        bank_name: None,
+        account_number: None,
+        account_name: None,
+        corrected_by: None,
+        notes: None,
+        attempts: vec![RetryAttempt {
+            batch_reference: batch_reference.to_string(),
+            outcome: "failed".to_string(),
+            failure_reason: Some(failure_reason.to_string()),
+            recorded_at: now,
+        }],
+        created_at: now,
+        updated_at: now,
+    };
+
+    set_doc_data(
+        DISBURSEMENT_RETRIES_COLLECTION,
+        &format!("{}-{}", reference, now),
+        &retry,
+        Some(retry_description(&retry)),
+        None,
+    )?;
+
+    Ok(())
+}
+
+/// The retry currently in flight with the bank for a payment reference.
+pub fn find_resubmitted_retry(reference: &str) -> Result<Option<(String, Doc, DisbursementRetryData)>, String> {
+    Ok(list_doc_data::<DisbursementRetryData>(
+        DISBURSEMENT_RETRIES_COLLECTION,
+        Some(format!("reference={};status=resubmitted;", reference)),
+    )?
+    .into_iter()
+    .find(|(_, _, r)| r.reference == reference && r.status == "resubmitted"))
+}
+
+/// Apply the bank's response to a resubmitted retry.
+pub fn settle_retry(
+    key: &str,
+    doc: &Doc,
+    mut retry: DisbursementRetryData,
+    outcome: &str,
+    failure_reason: &str,
+    batch_reference: &str,
+) -> Result<(), String> {
+    let now = ic_cdk::api::time();
+
+    retry.attempts.push(RetryAttempt {
+        batch_reference: batch_reference.to_string(),
+        outcome: outcome.to_string(),
+        failure_reason: if outcome == "failed" { Some(failure_reason.to_string()) } else { None },
+        recorded_at: now,
+    });
+
+    if outcome == "paid" {
+        retry.status = "succeeded".to_string();
+    } else {
+        retry.status = "queued".to_string();
+        retry.failure_reason = failure_reason.to_string();
+    }
+    retry.updated_at = now;
+
+    set_doc_data(
+        DISBURSEMENT_RETRIES_COLLECTION,
+        key,
+        &retry,
+        Some(retry_description(&retry)),
+        doc.version,
+    )?;
+
+    Ok(())
+}
+
+/// Build the bank file entries for all corrected retries and mark them resubmitted
+/// under the given batch reference.
+pub fn export_retry_file(batch_reference: String) -> Result<Vec<DisbursementFileEntry>, String> {
+    if !caller_has_any_role(&caller(), &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can export disbursement files".to_string());
+    }
+    if batch_reference.trim().is_empty() {
+        return Err("Batch reference is required".to_string());
+    }
+
+    let corrected = list_doc_data::<DisbursementRetryData>(
+        DISBURSEMENT_RETRIES_COLLECTION,
+        Some("status=corrected;".to_string()),
+    )?;
+
+    let mut entries = Vec::new();
+    for (key, doc, mut retry) in corrected.into_iter().filter(|(_, _, r)| r.status == "corrected") {
+        let (bank_name, account_number) = match (&retry.bank_name, &retry.account_number) {
+            (Some(bank), Some(account)) => (bank.clone(), account.clone()),
+            _ => continue,
+        };
+
+        entries.push(DisbursementFileEntry {
+            reference: retry.reference.clone(),
+            payee_name: retry.account_name.clone().unwrap_or_else(|| retry.payee_name.clone()),
+            bank_name,
+            account_number,
+            amount: retry.amount.naira(),
+            narration: format!("{} RETRY {}", retry.reference, retry.attempts.len()),
+        });
+
+        retry.status = "resubmitted".to_string();
+        retry.attempts.push(RetryAttempt {
+            batch_reference: batch_reference.clone(),
+            outcome: "resubmitted".to_string(),
+            failure_reason: None,
+            recorded_at: ic_cdk::api::time(),
+        });
+        retry.updated_at = ic_cdk::api::time();
+
+        set_doc_data(
+            DISBURSEMENT_RETRIES_COLLECTION,
+            &key,
+            &retry,
+            Some(retry_description(&retry)),
+            doc.version,
+        )?;
+    }
+
+    Ok(entries)
+}
+
+/// Disbursement Retry Validation
+///
+/// Security Checks:
+/// - Entries are only created by the satellite from failed disbursements
+/// - Payment amount, payee and attempt history are immutable
+/// - Corrected bank details are revalidated (format and payee master record)
+/// - Resubmission and settlement happen only through the satellite
+pub fn validate_disbursement_retry_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let is_satellite = is_satellite_caller(&context.caller);
+
+    let before_doc = match context.data.data.current {
+        Some(ref doc) => doc,
+        None if is_satellite => return Ok(()),
+        None => {
+            return Err("SECURITY: Retry queue entries are created automatically from failed disbursements".to_string());
+        }
+    };
+
+    let data: DisbursementRetryData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid retry data format: {}", e))?;
+    let before: DisbursementRetryData = decode_doc_data(&before_doc.data)
+        .map_err(|e| format!("Invalid previous retry data: {}", e))?;
+
+    if is_satellite {
+        return Ok(());
+    }
+
+    if data.source_collection != before.source_collection
+        || data.source_key != before.source_key
+        || data.reference != before.reference
+        || data.amount != before.amount
+        || data.attempts != before.attempts
+    {
+        return Err("AUDIT: Retry payment details and attempt history cannot be modified".to_string());
+    }
+
+    let valid_transitions = HashMap::from([
+        ("queued", vec!["corrected", "cancelled"]),
+        ("corrected", vec!["corrected", "cancelled"]),
+        ("resubmitted", vec![]),
+        ("succeeded", vec![]),
+        ("cancelled", vec![]),
+    ]);
+
+    let allowed = valid_transitions.get(before.status.as_str()).cloned().unwrap_or_default();
+    if !allowed.contains(&data.status.as_str()) {
+        return Err(format!(
+            "Invalid retry status transition from '{}' to '{}'. Allowed: [{}]",
+            before.status,
+            data.status,
+            allowed.join(", ")
+        ));
+    }
+
+    match data.status.as_str() {
+        "corrected" => validate_corrected_bank_details(context, &data),
+        "cancelled" => {
+            if data.notes.as_ref().map(|n| n.trim().len() < 10).unwrap_or(true) {
+                return Err("Cancelled retries must include a reason of at least 10 characters in notes".to_string());
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_corrected_bank_details(
+    context: &AssertSetDocContext,
+    retry: &DisbursementRetryData,
+) -> Result<(), String> {
+    if retry.attempts.len() >= MAX_RETRY_ATTEMPTS * 2 {
+        return Err(format!(
+            "Disbursement {} has exhausted its {} retry attempts; cancel it and pay manually",
+            retry.reference, MAX_RETRY_ATTEMPTS
+        ));
+    }
+
+    let bank_name = retry.bank_name.as_ref().filter(|b| !b.trim().is_empty())
+        .ok_or("Corrected retries must include the bank name")?;
+    let account_number = retry.account_number.as_ref()
+        .ok_or("Corrected retries must include the account number")?;
+
+    if !is_valid_account_number(account_number) {
+        return Err("Account number must be 10 digits".to_string());
+    }
+    if retry.corrected_by.as_deref() != Some(context.caller.to_text().as_str()) {
+        return Err("correctedBy must be the principal correcting the bank details".to_string());
+    }
+
+    // Revalidate against the payee master record so the correction is not a one-off
+    if retry.source_collection == "salary_payments" {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct SalaryStaff {
+            staff_id: String,
+        }
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct StaffBankDetails {
+            bank_name: Option<String>,
+            account_number: Option<String>,
+        }
+
+        let (_, salary) = get_doc_data::<SalaryStaff>("salary_payments", &retry.source_key)?
+            .ok_or_else(|| format!("Salary payment '{}' not found", retry.source_key))?;
+        let (_, staff) = get_doc_data::<StaffBankDetails>("staff", &salary.staff_id)?
+            .ok_or_else(|| format!("Staff member '{}' not found", salary.staff_id))?;
+
+        if staff.account_number.as_deref() != Some(account_number.as_str())
+            || staff.bank_name.as_deref().map(|b| b.eq_ignore_ascii_case(bank_name)) != Some(true)
+        {
+            return Err("Corrected bank details must match the staff record. Update the staff bank details first".to_string());
+        }
+    }
+
+    if retry.source_collection == "expenses" {
+        let (_, expense) = get_doc_data::<ExpenseData>("expenses", &retry.source_key)?
+            .ok_or_else(|| format!("Expense '{}' not found", retry.source_key))?;
+        let vendor_id = expense
+            .vendor_id
+            .filter(|id| !id.trim().is_empty())
+            .ok_or("Corrected vendor payments must be for a registered vendor (vendorId)")?;
+        let (_, vendor) = get_doc_data::<VendorData>(VENDORS_COLLECTION, &vendor_id)?
+            .ok_or_else(|| format!("Vendor '{}' not found", vendor_id))?;
+
+        if vendor.account_number.as_deref() != Some(account_number.as_str())
+            || vendor.bank_name.as_deref().map(|b| b.eq_ignore_ascii_case(bank_name)) != Some(true)
+        {
+            return Err("Corrected bank details must match the vendor register. Update the vendor's bank details first".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+fn retry_description(retry: &DisbursementRetryData) -> String {
+    format!("reference={};status={};", retry.reference, retry.status)
+}
@@ -0,0 +1,160 @@
+//! Vendor payment files for approved bank-transfer expenses.
+//!
+//! Mirrors the payroll flow: approved, unpaid `bank_transfer` expenses are bundled into a
+//! batch, each expense records the batch it was sent in, and the expense only becomes
+//! `paid` when the bank acknowledgment for it is imported. A failed vendor payment goes to
+//! the retry queue, where corrected bank details must match the vendor register.
+
+use candid::CandidType;
+use junobuild_satellite::caller;
+use serde::{Deserialize, Serialize};
+
+use super::retries::{find_resubmitted_retry, queue_retry, settle_retry, DisbursementFileEntry};
+use super::{AcknowledgmentResult, PaymentAcknowledgment};
+use crate::modules::audit::record_system_change;
+use crate::modules::expenses::ExpenseData;
+use crate::modules::procurement::record_purchase_order_payment;
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::utils::validation_utils::*;
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct VendorPaymentFile {
+    pub batch_reference: String,
+    pub value_date: String,
+    pub total_amount: f64,
+    pub entries: Vec<DisbursementFileEntry>,
+}
+
+/// Bundle every approved, unbatched bank-transfer expense due on or before `date` into a
+/// new vendor payment batch. Expenses without vendor bank details are left out.
+pub fn build_vendor_payment_file(date: String) -> Result<VendorPaymentFile, String> {
+    if !caller_has_any_role(&caller(), &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can export disbursement files".to_string());
+    }
+    if !is_valid_date_format(&date) {
+        return Err("Invalid date format. Must be YYYY-MM-DD".to_string());
+    }
+
+    let now = ic_cdk::api::time();
+    let batch_reference = format!("VND-{}-{}", date.replace('-', ""), now % 1_000_000);
+
+    let expenses = list_doc_data::<ExpenseData>("expenses", None)?;
+
+    let mut entries = Vec::new();
+    let mut total = Money::ZERO;
+    for (key, doc, mut expense) in expenses.into_iter() {
+        if expense.status != "approved"
+            || expense.payment_method != "bank_transfer"
+            || expense.payment_batch.is_some()
+            || expense.payment_date > date
+        {
+            continue;
+        }
+
+        let (bank_name, account_number) = match (&expense.vendor_bank_name, &expense.vendor_account_number) {
+            (Some(bank), Some(account)) if !bank.trim().is_empty() && is_valid_account_number(account) => {
+                (bank.clone(), account.clone())
+            }
+            _ => continue,
+        };
+
+        entries.push(DisbursementFileEntry {
+            reference: expense.reference.clone(),
+            payee_name: expense.vendor_name.clone().unwrap_or_else(|| expense.category_name.clone()),
+            bank_name,
+            account_number,
+            amount: expense.amount.naira(),
+            narration: expense.reference.clone(),
+        });
+        total += expense.amount;
+
+        expense.payment_batch = Some(batch_reference.clone());
+        expense.updated_at = now;
+
+        set_doc_data("expenses", &key, &expense, doc.description.clone(), doc.version)?;
+    }
+
+    if entries.is_empty() {
+        return Err(format!("No approved bank transfer expenses are awaiting payment as of {}", date));
+    }
+
+    Ok(VendorPaymentFile {
+        total_amount: total.naira(),
+        batch_reference,
+        value_date: date,
+        entries,
+    })
+}
+
+/// Apply one bank acknowledgment line to a batched expense.
+pub(super) fn apply_vendor_acknowledgment(
+    batch_reference: &str,
+    ack: &PaymentAcknowledgment,
+    reason: &str,
+) -> Result<AcknowledgmentResult, String> {
+    let (key, doc, mut expense) = list_doc_data::<ExpenseData>("expenses", None)?
+    .into_iter()
+    .find(|(_, _, e)| e.reference == ack.reference)
+    .ok_or_else(|| format!("Expense '{}' not found", ack.reference))?;
+
+    if expense.status != "approved" {
+        return Err(format!(
+            "Only approved expenses can be acknowledged (current status: {})",
+            expense.status
+        ));
+    }
+    if expense.payment_batch.is_none() {
+        return Err("Expense has not been included in a vendor payment file".to_string());
+    }
+
+    // A failed payment that was resubmitted through the retry queue
+    if expense.failure_reason.is_some() {
+        let (retry_key, retry_doc, retry) = find_resubmitted_retry(&expense.reference)?
+            .ok_or_else(|| "Expense payment failed previously and has no resubmitted retry".to_string())?;
+
+        settle_retry(&retry_key, &retry_doc, retry, &ack.status, reason, batch_reference)?;
+
+        if ack.status == "failed" {
+            return Ok(AcknowledgmentResult {
+                reference: ack.reference.clone(),
+                outcome: "failed".to_string(),
+                message: Some(reason.to_string()),
+            });
+        }
+    }
+
+    let old_status = expense.status.clone();
+    if ack.status == "paid" {
+        expense.status = "paid".to_string();
+        expense.bank_reference = ack.bank_reference.clone();
+    } else {
+        expense.failure_reason = Some(reason.to_string());
+
+        queue_retry(
+            "expenses",
+            &key,
+            &expense.reference,
+            expense.vendor_name.as_deref().unwrap_or(&expense.category_name),
+            expense.amount,
+            reason,
+            batch_reference,
+        )?;
+    }
+    expense.updated_at = ic_cdk::api::time();
+
+    set_doc_data("expenses", &key, &expense, doc.description.clone(), doc.version)?;
+    // Satellite writes skip the on-set hook, so the audit entry is recorded and a purchase
+    // order paid off is marked here
+    record_system_change("expenses", &key, "acknowledgment", Some(old_status), Some(expense.status.clone()))?;
+    if ack.status == "paid" {
+        record_purchase_order_payment(None, &expense)?;
+    }
+
+    Ok(AcknowledgmentResult {
+        reference: ack.reference.clone(),
+        outcome: ack.status.clone(),
+        message: if ack.status == "failed" { Some(reason.to_string()) } else { None },
+    })
+}
@@ -0,0 +1,268 @@
+//! Documents Module - Receipt and Payslip PDFs
+//!
+//! The satellite renders receipts and payslips as PDF assets itself, so the documents
+//! exist whatever the frontend does:
+//! - A receipt when a confirmed payment's receipt is issued, at
+//!   `/receipt_pdfs/{receipt number}.pdf`
+//! - A payslip when a salary payment is paid, at
+//!   `/payslips/salary_payments/{key}/payslip.pdf` (see [`super::staff::payslips`])
+//!
+//! Both are laid out from the render data served by `get_receipt_render_data` and
+//! `get_payslip_render_data` (a title, a reference and labelled lines in sections) at
+//! fixed positions by the [`pdf`] writer, so the frontend can show the same content and
+//! the same data always gives the same file. A PDF that is already stored, such as a
+//! payslip uploaded by payroll, is left alone.
+
+pub mod pdf;
+
+use candid::CandidType;
+use junobuild_satellite::{caller, error, get_asset_store, id, set_asset_handler};
+use junobuild_storage::http::types::HeaderField;
+use junobuild_storage::types::store::AssetKey;
+use serde::{Deserialize, Serialize};
+
+use super::payments::PaymentData;
+use super::receipts::{ReceiptData, RECEIPTS_COLLECTION};
+use super::roles::{caller_has_any_role, Role};
+use super::staff::payslips::PAYSLIPS_COLLECTION;
+use super::staff::SalaryPaymentData;
+use super::utils::doc_utils::get_doc_data;
+use super::utils::money::Money;
+use super::utils::validation_utils::date_from_timestamp;
+use pdf::{Font, PdfWriter, PAGE_HEIGHT, PAGE_WIDTH};
+
+pub const RECEIPT_PDFS_COLLECTION: &str = "receipt_pdfs";
+
+// Layout, in points
+const MARGIN: i32 = 56;
+const TOP: i32 = PAGE_HEIGHT - 72;
+const BOTTOM: i32 = 72;
+const VALUE_X: i32 = 260;
+const LINE_HEIGHT: i32 = 16;
+const SECTION_GAP: i32 = 24;
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct RenderLine {
+    pub label: String,
+    pub value: String,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct RenderSection {
+    pub heading: String,
+    pub lines: Vec<RenderLine>,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct DocumentRenderData {
+    pub title: String,
+    pub reference: String,
+    pub sections: Vec<RenderSection>,
+}
+
+/// Render data for an issued receipt.
+pub fn get_receipt_render_data(receipt_number: &str) -> Result<DocumentRenderData, String> {
+    let (_, receipt) = get_doc_data::<ReceiptData>(RECEIPTS_COLLECTION, receipt_number)?
+        .ok_or_else(|| format!("Receipt '{}' not found", receipt_number))?;
+    let payment = get_doc_data::<PaymentData>("payments", &receipt.payment_id)?.map(|(_, p)| p);
+    Ok(receipt_render_data(&receipt, payment.as_ref()))
+}
+
+/// Render data for a salary payment's payslip (payroll and HR staff).
+pub fn get_payslip_render_data(salary_payment_id: &str) -> Result<DocumentRenderData, String> {
+    if !caller_has_any_role(&caller(), &[Role::SuperAdmin, Role::Bursar, Role::Accountant, Role::Hr]) {
+        return Err("SECURITY: Only payroll and HR staff can view payslips".to_string());
+    }
+    let (_, salary) = get_doc_data::<SalaryPaymentData>("salary_payments", salary_payment_id)?
+        .ok_or_else(|| format!("Salary payment '{}' not found", salary_payment_id))?;
+    Ok(payslip_render_data(&salary))
+}
+
+/// Called when a receipt is issued. A PDF that cannot be stored is logged rather than
+/// undoing the receipt.
+pub fn store_receipt_pdf(receipt: &ReceiptData, payment: &PaymentData) {
+    let full_path = format!("/{}/{}.pdf", RECEIPT_PDFS_COLLECTION, receipt.receipt_number);
+    let data = receipt_render_data(receipt, Some(payment));
+    if let Err(e) = store_pdf(RECEIPT_PDFS_COLLECTION, &full_path, &data) {
+        let _ = error(format!("Receipt PDF {} not stored: {}", full_path, e));
+    }
+}
+
+/// Called when a salary payment is saved; stores the payslip once it is paid. A PDF
+/// that cannot be stored is logged rather than failing the payment update.
+pub fn store_payslip_pdf(salary_key: &str, salary: &SalaryPaymentData) {
+    if salary.status != "paid" {
+        return;
+    }
+    let full_path = format!("/{}/salary_payments/{}/payslip.pdf", PAYSLIPS_COLLECTION, salary_key);
+    if let Err(e) = store_pdf(PAYSLIPS_COLLECTION, &full_path, &payslip_render_data(salary)) {
+        let _ = error(format!("Payslip PDF {} not stored: {}", full_path, e));
+    }
+}
+
+fn receipt_render_data(receipt: &ReceiptData, payment: Option<&PaymentData>) -> DocumentRenderData {
+    let mut sections = vec![
+        RenderSection {
+            heading: "Payment".to_string(),
+            lines: vec![
+                line("Amount", amount(receipt.amount)),
+                line("Payment date", receipt.payment_date.clone()),
+                line("Payment method", receipt.payment_method.replace('_', " ")),
+                line("Payment reference", receipt.reference.clone()),
+                line("Issued on", date_from_timestamp(receipt.issued_at)),
+            ],
+        },
+        RenderSection {
+            heading: "Student".to_string(),
+            lines: vec![
+                line("Name", receipt.student_name.clone()),
+                line("Student ID", receipt.student_id.clone()),
+            ],
+        },
+    ];
+
+    if let Some(payment) = payment {
+        sections[1].lines.push(line("Class", payment.class_name.clone()));
+        if !payment.fee_allocations.is_empty() {
+            sections.push(RenderSection {
+                heading: "Allocation".to_string(),
+                lines: payment
+                    .fee_allocations
+                    .iter()
+                    .map(|a| line(&a.category_name, amount(a.amount)))
+                    .collect(),
+            });
+        }
+    }
+
+    if receipt.status == "voided" {
+        sections.push(RenderSection {
+            heading: "Voided".to_string(),
+            lines: vec![
+                line("Reason", receipt.void_reason.clone().unwrap_or_default()),
+                line("Voided on", receipt.voided_at.map(date_from_timestamp).unwrap_or_default()),
+            ],
+        });
+    }
+
+    DocumentRenderData {
+        title: if receipt.status == "voided" { "Payment Receipt (VOID)" } else { "Payment Receipt" }.to_string(),
+        reference: receipt.receipt_number.clone(),
+        sections,
+    }
+}
+
+fn payslip_render_data(salary: &SalaryPaymentData) -> DocumentRenderData {
+    let allowances: Money = salary.allowances.iter().map(|a| a.amount).sum();
+    let deductions: Money = salary.deductions.iter().map(|d| d.amount).sum();
+
+    let mut earnings = vec![line("Basic salary", amount(salary.basic_salary))];
+    earnings.extend(salary.allowances.iter().map(|a| line(&a.name, amount(a.amount))));
+
+    let mut sections = vec![
+        RenderSection {
+            heading: "Employee".to_string(),
+            lines: vec![
+                line("Name", salary.staff_name.clone()),
+                line("Staff number", salary.staff_number.clone()),
+                line(
+                    "Pay period",
+                    format!("{} to {}", salary.payment_period_start, salary.payment_period_end),
+                ),
+                line("Payment date", salary.payment_date.clone()),
+                line("Payment method", salary.payment_method.replace('_', " ")),
+            ],
+        },
+        RenderSection { heading: "Earnings".to_string(), lines: earnings },
+    ];
+    if !salary.deductions.is_empty() {
+        sections.push(RenderSection {
+            heading: "Deductions".to_string(),
+            lines: salary.deductions.iter().map(|d| line(&d.name, amount(d.amount))).collect(),
+        });
+    }
+    sections.push(RenderSection {
+        heading: "Summary".to_string(),
+        lines: vec![
+            line("Gross pay", amount(salary.basic_salary + allowances)),
+            line("Total deductions", amount(deductions)),
+            line("Net pay", amount(salary.net_salary)),
+        ],
+    });
+
+    DocumentRenderData {
+        title: "Payslip".to_string(),
+        reference: salary.reference.clone(),
+        sections,
+    }
+}
+
+fn render_pdf(data: &DocumentRenderData) -> Vec<u8> {
+    let mut pdf = PdfWriter::new();
+    let mut y = TOP;
+    pdf.text(MARGIN, y, Font::Bold, 18, &data.title);
+    y -= 20;
+    pdf.text(MARGIN, y, Font::Regular, 10, &data.reference);
+    y -= 12;
+    pdf.rule(MARGIN, PAGE_WIDTH - MARGIN, y);
+
+    for section in data.sections.iter() {
+        // Keep a heading with at least its first line
+        if y - SECTION_GAP - LINE_HEIGHT < BOTTOM {
+            pdf.new_page();
+            y = TOP + SECTION_GAP;
+        }
+        y -= SECTION_GAP;
+        pdf.text(MARGIN, y, Font::Bold, 12, &section.heading);
+        for line in section.lines.iter() {
+            y -= LINE_HEIGHT;
+            if y < BOTTOM {
+                pdf.new_page();
+                y = TOP;
+            }
+            pdf.text(MARGIN, y, Font::Regular, 10, &line.label);
+            pdf.text(VALUE_X, y, Font::Regular, 10, &line.value);
+        }
+    }
+
+    pdf.finish()
+}
+
+fn store_pdf(collection: &str, full_path: &str, data: &DocumentRenderData) -> Result<(), String> {
+    if get_asset_store(id(), &collection.to_string(), full_path.to_string())?.is_some() {
+        return Ok(());
+    }
+
+    let key = AssetKey {
+        name: full_path.rsplit('/').next().unwrap_or(full_path).to_string(),
+        full_path: full_path.to_string(),
+        token: None,
+        collection: collection.to_string(),
+        owner: id(),
+        description: None,
+    };
+    let headers = [HeaderField("Content-Type".to_string(), "application/pdf".to_string())];
+    set_asset_handler(&key, &render_pdf(data), &headers)
+}
+
+fn line(label: &str, value: String) -> RenderLine {
+    RenderLine { label: label.to_string(), value }
+}
+
+// NGN 1,234,567.89 (the naira sign is not in the standard PDF fonts)
+fn amount(value: Money) -> String {
+    let plain = value.to_string();
+    let (sign, digits) = match plain.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", plain.as_str()),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, "00"));
+    let mut grouped = String::new();
+    for (i, c) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    format!("NGN {}{}.{}", sign, grouped, fraction)
+}
@@ -0,0 +1,120 @@
+//! Minimal PDF writer.
+//!
+//! Just enough of PDF 1.4 for text documents: A4 pages, the standard Helvetica and
+//! Helvetica-Bold fonts (not embedded), text and horizontal rules. The output depends
+//! only on what is drawn (no timestamps or document ids), so the same content always
+//! gives the same bytes.
+
+pub const PAGE_WIDTH: i32 = 595;
+pub const PAGE_HEIGHT: i32 = 842;
+
+#[derive(Clone, Copy)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+pub struct PdfWriter {
+    // Content stream of each page
+    pages: Vec<String>,
+}
+
+impl Default for PdfWriter {
+    fn default() -> Self {
+        PdfWriter { pages: vec![String::new()] }
+    }
+}
+
+impl PdfWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn new_page(&mut self) {
+        self.pages.push(String::new());
+    }
+
+    /// Text with its baseline starting at (x, y), in points from the bottom left.
+    pub fn text(&mut self, x: i32, y: i32, font: Font, size: i32, text: &str) {
+        let font = match font {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        };
+        self.current_page()
+            .push_str(&format!("BT /{} {} Tf {} {} Td ({}) Tj ET\n", font, size, x, y, escape(text)));
+    }
+
+    /// A thin horizontal line from x1 to x2 at height y.
+    pub fn rule(&mut self, x1: i32, x2: i32, y: i32) {
+        self.current_page()
+            .push_str(&format!("0.5 w {} {} m {} {} l S\n", x1, y, x2, y));
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        // Objects: 1 catalog, 2 page tree, 3 and 4 fonts, then a page and its content
+        // stream for each page
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..self.pages.len()).map(|i| format!("{} 0 R", 5 + 2 * i)).collect::<Vec<_>>().join(" "),
+                self.pages.len()
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_string(),
+        ];
+        for (i, content) in self.pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                6 + 2 * i
+            ));
+            objects.push(format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content));
+        }
+
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+        }
+
+        let xref = out.len();
+        out.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+        for offset in offsets {
+            out.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        out.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        ));
+        out.into_bytes()
+    }
+
+    fn current_page(&mut self) -> &mut String {
+        if self.pages.is_empty() {
+            self.pages.push(String::new());
+        }
+        let last = self.pages.len() - 1;
+        &mut self.pages[last]
+    }
+}
+
+// PDF string literal content; the standard fonts only cover printable ASCII here, so
+// anything else is replaced
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
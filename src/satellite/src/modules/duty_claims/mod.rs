@@ -0,0 +1,451 @@
+//! Duty Claims Module - Overtime & Extra-Duty Allowances
+//!
+//! Staff claim extra-duty pay (exam invigilation, weekend lessons, ...) against a
+//! rate catalog kept in `duty_rates`. This module enforces:
+//! - Claim amounts priced exactly from the active catalog rate
+//! - Approval workflow (pending → approved/rejected, approved → paid)
+//! - Salary payment allowances that reference a claim match it exactly
+//! - Each claim is paid through at most one salary payment (traceability)
+
+use candid::CandidType;
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::staff::{PaymentAllowanceItem, SalaryPaymentData};
+use super::utils::doc_utils::*;
+use super::utils::identity::validate_caller_identity;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const DUTY_CLAIMS_COLLECTION: &str = "duty_claims";
+pub const DUTY_RATES_COLLECTION: &str = "duty_rates";
+
+const MAX_UNITS_PER_CLAIM: f64 = 200.0;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DutyRateData {
+    pub name: String,
+    pub duty_type: String,
+    pub unit: String,
+    pub rate: Money,
+    pub max_units_per_claim: Option<f64>,
+    pub is_active: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DutyClaimData {
+    pub staff_id: String,
+    pub staff_number: String,
+    pub rate_id: String,
+    pub duty_type: String,
+    pub duty_date: String,
+    pub units: f64,
+    pub rate: Money,
+    pub amount: Money,
+    pub description: String,
+    pub status: String,
+    pub approved_by: Option<String>,
+    pub approved_at: Option<u64>,
+    pub salary_payment_id: Option<String>,
+    pub notes: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct PayableDutyClaim {
+    pub claim_id: String,
+    pub duty_type: String,
+    pub duty_date: String,
+    pub units: f64,
+    pub rate: f64,
+    pub amount: f64,
+}
+
+/// Duty Rate Catalog Validation
+///
+/// Checks:
+/// - Known duty type and unit
+/// - Positive rate and sensible unit cap
+/// - One active rate per duty type
+pub fn validate_duty_rate_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: DutyRateData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid duty rate data format: {}", e))?;
+
+    if data.name.trim().is_empty() {
+        return Err("Duty rate name is required".to_string());
+    }
+
+    let valid_duty_types = [
+        "exam_invigilation", "weekend_lesson", "extra_lesson", "sports_coaching",
+        "excursion", "boarding_duty", "other",
+    ];
+    if !valid_duty_types.contains(&data.duty_type.as_str()) {
+        return Err(format!(
+            "Invalid duty type '{}'. Must be one of: {}",
+            data.duty_type,
+            valid_duty_types.join(", ")
+        ));
+    }
+
+    let valid_units = ["session", "hour", "day"];
+    if !valid_units.contains(&data.unit.as_str()) {
+        return Err(format!("Invalid unit '{}'. Must be one of: {}", data.unit, valid_units.join(", ")));
+    }
+
+    if !data.rate.is_positive() || !is_valid_amount(data.rate.naira()) {
+        return Err("Duty rate must be greater than zero and not exceed ₦1,000,000".to_string());
+    }
+
+    if let Some(max_units) = data.max_units_per_claim {
+        if max_units <= 0.0 || max_units > MAX_UNITS_PER_CLAIM {
+            return Err(format!("maxUnitsPerClaim must be between 0 and {}", MAX_UNITS_PER_CLAIM));
+        }
+    }
+
+    // Only one active rate per duty type, otherwise claims become ambiguous
+    if data.is_active {
+        let duplicate = list_doc_data::<DutyRateData>(DUTY_RATES_COLLECTION, None)?
+            .into_iter()
+            .any(|(key, _, rate)| key != context.data.key && rate.is_active && rate.duty_type == data.duty_type);
+        if duplicate {
+            return Err(format!("An active rate for duty type '{}' already exists", data.duty_type));
+        }
+    }
+
+    Ok(())
+}
+
+/// Duty Claim Validation - Pricing & Approval Workflow
+///
+/// Checks:
+/// - Staff member exists and is active
+/// - Claim is priced from the active catalog rate (units × rate)
+/// - Status transitions and approval fields
+/// - Payment linkage can only be set by the satellite (salary payment hook)
+pub fn validate_duty_claim_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: DutyClaimData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid duty claim data format: {}", e))?;
+
+    // recordedBy and approvedBy must name the caller that set them
+    validate_caller_identity(context)?;
+
+    let before: Option<DutyClaimData> = match context.data.data.current {
+        Some(ref doc) => Some(decode_doc_data(&doc.data)
+            .map_err(|e| format!("Invalid previous duty claim data: {}", e))?),
+        None => None,
+    };
+
+    validate_duty_claim_fields(&data)?;
+    validate_duty_claim_status_transition(context, before.as_ref(), &data)?;
+
+    // Pricing is only re-checked while the claim is still editable
+    if data.status == "pending" {
+        validate_duty_claim_pricing(&data)?;
+        validate_duty_claim_staff(&data)?;
+    } else if let Some(ref before) = before {
+        if before.amount != data.amount || before.units != data.units || before.rate != data.rate {
+            return Err("Claim amount, units and rate cannot change after approval".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_duty_claim_fields(claim: &DutyClaimData) -> Result<(), String> {
+    if claim.staff_id.trim().is_empty() {
+        return Err("staffId is required".to_string());
+    }
+    if claim.rate_id.trim().is_empty() {
+        return Err("rateId is required".to_string());
+    }
+    if claim.description.trim().is_empty() {
+        return Err("Claim description is required".to_string());
+    }
+    if !is_valid_date_format(&claim.duty_date) {
+        return Err("Invalid duty date format. Must be YYYY-MM-DD".to_string());
+    }
+    if is_date_in_future(&claim.duty_date) {
+        return Err("Duty date cannot be in the future".to_string());
+    }
+    if claim.units <= 0.0 || claim.units > MAX_UNITS_PER_CLAIM {
+        return Err(format!("Claim units must be between 0 and {}", MAX_UNITS_PER_CLAIM));
+    }
+    Ok(())
+}
+
+fn validate_duty_claim_pricing(claim: &DutyClaimData) -> Result<(), String> {
+    let (_, rate) = get_doc_data::<DutyRateData>(DUTY_RATES_COLLECTION, &claim.rate_id)?
+        .ok_or_else(|| format!("Duty rate '{}' not found", claim.rate_id))?;
+
+    if !rate.is_active {
+        return Err(format!("Duty rate '{}' is no longer active", rate.name));
+    }
+
+    if rate.duty_type != claim.duty_type {
+        return Err(format!(
+            "Claim duty type '{}' does not match rate duty type '{}'",
+            claim.duty_type, rate.duty_type
+        ));
+    }
+
+    if claim.rate != rate.rate {
+        return Err(format!(
+            "Claim rate (₦{}) does not match catalog rate (₦{}) for '{}'",
+            claim.rate, rate.rate, rate.name
+        ));
+    }
+
+    if let Some(max_units) = rate.max_units_per_claim {
+        if claim.units > max_units {
+            return Err(format!("Claim units ({}) exceed the maximum of {} for '{}'", claim.units, max_units, rate.name));
+        }
+    }
+
+    let expected_amount = Money::from_naira(claim.units * rate.rate.naira());
+    if claim.amount != expected_amount {
+        return Err(format!(
+            "Claim amount (₦{}) must equal units × rate (₦{})",
+            claim.amount, expected_amount
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_duty_claim_staff(claim: &DutyClaimData) -> Result<(), String> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct StaffStatus {
+        is_active: bool,
+    }
+
+    let (_, staff) = get_doc_data::<StaffStatus>("staff", &claim.staff_id)?
+        .ok_or_else(|| format!("Staff member '{}' not found", claim.staff_id))?;
+
+    if !staff.is_active {
+        return Err(format!("Staff member '{}' is not active", claim.staff_number));
+    }
+    Ok(())
+}
+
+fn validate_duty_claim_status_transition(
+    context: &AssertSetDocContext,
+    before: Option<&DutyClaimData>,
+    claim: &DutyClaimData,
+) -> Result<(), String> {
+    let valid_statuses = ["pending", "approved", "rejected", "paid"];
+    if !valid_statuses.contains(&claim.status.as_str()) {
+        return Err(format!(
+            "Invalid claim status '{}'. Must be one of: {}",
+            claim.status,
+            valid_statuses.join(", ")
+        ));
+    }
+
+    let is_satellite = is_satellite_caller(&context.caller);
+
+    match before {
+        Some(before) => {
+            let valid_transitions = HashMap::from([
+                ("pending", vec!["approved", "rejected"]),
+                ("approved", vec!["paid"]),
+                ("rejected", vec![]),
+                ("paid", vec![]),
+            ]);
+
+            if before.status != claim.status {
+                if let Some(allowed_next_states) = valid_transitions.get(before.status.as_str()) {
+                    if !allowed_next_states.contains(&claim.status.as_str()) {
+                        return Err(format!(
+                            "Invalid status transition from '{}' to '{}'. Allowed: [{}]",
+                            before.status,
+                            claim.status,
+                            allowed_next_states.join(", ")
+                        ));
+                    }
+                }
+            }
+
+            // Payment linkage is owned by the salary payment hook
+            if !is_satellite {
+                if claim.status == "paid" && before.status != "paid" {
+                    return Err("Duty claims are marked paid automatically when the salary payment is paid".to_string());
+                }
+                if claim.salary_payment_id != before.salary_payment_id {
+                    return Err("salaryPaymentId is set automatically when the claim is included in payroll".to_string());
+                }
+            }
+        }
+        None => {
+            if claim.status != "pending" {
+                return Err("New duty claims must have status 'pending'".to_string());
+            }
+            if claim.salary_payment_id.is_some() {
+                return Err("New duty claims cannot reference a salary payment".to_string());
+            }
+        }
+    }
+
+    match claim.status.as_str() {
+        "approved" | "paid" => {
+            let approver = claim.approved_by.as_ref()
+                .filter(|a| !a.trim().is_empty())
+                .ok_or("Approved duty claims must have approvedBy set")?;
+            if claim.approved_at.is_none() {
+                return Err("Approved duty claims must have approvedAt timestamp".to_string());
+            }
+            if approver == &claim.recorded_by {
+                return Err("Staff cannot approve their own duty claims".to_string());
+            }
+        }
+        "rejected" if claim.notes.as_ref().map(|n| n.trim().is_empty()).unwrap_or(true) => {
+            return Err("Rejected duty claims must include rejection reason in notes".to_string());
+        }
+        _ => {}
+    }
+
+    if claim.status == "paid" && claim.salary_payment_id.is_none() {
+        return Err("Paid duty claims must reference the salary payment".to_string());
+    }
+
+    Ok(())
+}
+
+/// Salary payment allowances that carry a `claimId` must match an approved claim for the
+/// same staff member that has not been included in a different salary payment.
+pub fn validate_salary_claim_allowances(
+    salary_key: &str,
+    salary: &SalaryPaymentData,
+) -> Result<(), String> {
+    let mut seen_claims = HashSet::new();
+
+    for allowance in salary.allowances.iter() {
+        let claim_id = match allowance.claim_id {
+            Some(ref claim_id) if !claim_id.trim().is_empty() => claim_id,
+            _ => continue,
+        };
+
+        if !seen_claims.insert(claim_id.clone()) {
+            return Err(format!("Duty claim '{}' is included more than once", claim_id));
+        }
+
+        let (_, claim) = get_doc_data::<DutyClaimData>(DUTY_CLAIMS_COLLECTION, claim_id)?
+            .ok_or_else(|| format!("Duty claim '{}' not found", claim_id))?;
+
+        validate_claim_allowance(salary_key, salary, allowance, claim_id, &claim)?;
+    }
+
+    Ok(())
+}
+
+fn validate_claim_allowance(
+    salary_key: &str,
+    salary: &SalaryPaymentData,
+    allowance: &PaymentAllowanceItem,
+    claim_id: &str,
+    claim: &DutyClaimData,
+) -> Result<(), String> {
+    if claim.staff_id != salary.staff_id {
+        return Err(format!("Duty claim '{}' belongs to a different staff member", claim_id));
+    }
+
+    let already_linked_here = claim.salary_payment_id.as_deref() == Some(salary_key);
+    match claim.status.as_str() {
+        "approved" => {}
+        "paid" if already_linked_here => {}
+        _ => {
+            return Err(format!(
+                "Duty claim '{}' is '{}' and cannot be paid through payroll",
+                claim_id, claim.status
+            ));
+        }
+    }
+
+    if let Some(ref linked) = claim.salary_payment_id {
+        if linked != salary_key {
+            return Err(format!(
+                "Duty claim '{}' is already included in salary payment '{}'",
+                claim_id, linked
+            ));
+        }
+    }
+
+    if allowance.amount != claim.amount {
+        return Err(format!(
+            "Allowance '{}' (₦{}) must equal duty claim amount (₦{})",
+            allowance.name, allowance.amount, claim.amount
+        ));
+    }
+
+    Ok(())
+}
+
+/// Called from the `salary_payments` on-set hook: links every referenced claim to the
+/// salary payment and marks the claims paid once the salary is paid.
+pub fn sync_claims_with_salary_payment(salary_key: &str, salary: &SalaryPaymentData) -> Result<(), String> {
+    for allowance in salary.allowances.iter() {
+        let claim_id = match allowance.claim_id {
+            Some(ref claim_id) if !claim_id.trim().is_empty() => claim_id,
+            _ => continue,
+        };
+
+        let (doc, mut claim) = match get_doc_data::<DutyClaimData>(DUTY_CLAIMS_COLLECTION, claim_id)? {
+            Some(found) => found,
+            None => continue,
+        };
+
+        let target_status = if salary.status == "paid" { "paid" } else { claim.status.as_str() }.to_string();
+        let already_synced = claim.salary_payment_id.as_deref() == Some(salary_key) && claim.status == target_status;
+        if already_synced {
+            continue;
+        }
+
+        claim.salary_payment_id = Some(salary_key.to_string());
+        claim.status = target_status;
+        claim.updated_at = ic_cdk::api::time();
+
+        set_doc_data(
+            DUTY_CLAIMS_COLLECTION,
+            claim_id,
+            &claim,
+            Some(duty_claim_description(&claim)),
+            doc.version,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Approved claims for a staff member that have not yet been included in payroll.
+pub fn list_payable_claims(staff_id: &str) -> Result<Vec<PayableDutyClaim>, String> {
+    let claims = list_doc_data::<DutyClaimData>(DUTY_CLAIMS_COLLECTION, None)?;
+
+    let mut payable: Vec<PayableDutyClaim> = claims
+        .into_iter()
+        .filter(|(_, _, claim)| claim.staff_id == staff_id && claim.status == "approved" && claim.salary_payment_id.is_none())
+        .map(|(key, _, claim)| PayableDutyClaim {
+            claim_id: key,
+            duty_type: claim.duty_type,
+            duty_date: claim.duty_date,
+            units: claim.units,
+            rate: claim.rate.naira(),
+            amount: claim.amount.naira(),
+        })
+        .collect();
+
+    payable.sort_by(|a, b| a.duty_date.cmp(&b.duty_date));
+    Ok(payable)
+}
+
+fn duty_claim_description(claim: &DutyClaimData) -> String {
+    format!("staff_id={};status={};", claim.staff_id, claim.status)
+}
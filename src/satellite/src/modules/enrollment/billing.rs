@@ -0,0 +1,283 @@
+//! Class billing.
+//!
+//! `assign_fees_to_class` raises a fee assignment from a fee structure for every active
+//! student in its class, instead of one write per student from the browser:
+//! - Mandatory items are billed; optional items are left for parents to opt into
+//! - A student's scholarship is applied when one covers them: the active, current
+//!   scholarship for the student or class with the largest discount, within its
+//!   beneficiary limit. The discount comes off the items it covers.
+//! - Students who already have fees for the term are skipped, so the call can be repeated
+//!   for students who join later
+//!
+//! Every check runs before anything is written, so a rejected call bills nobody.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, Doc};
+use serde::{Deserialize, Serialize};
+
+use super::full_name;
+use crate::modules::category_usage::record_fee_assignment_usage;
+use crate::modules::classes::{ClassData, CLASSES_COLLECTION};
+use crate::modules::fees::{
+    check_scholarship_applies, fee_assignment_status, FeeItemData, FeeStructureData, ScholarshipData,
+    StudentFeeAssignmentData,
+};
+use crate::modules::notifications::fee_messages::queue_invoice_message;
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::terms::{AcademicTermData, ACADEMIC_TERMS_COLLECTION};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+
+const MAX_CLASS_STUDENTS: usize = 200;
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ClassFeeAssignment {
+    pub student_id: String,
+    pub assignment_id: String,
+    pub total_amount: f64,
+    pub scholarship_id: Option<String>,
+    pub discount_amount: f64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ClassBillingSummary {
+    pub assigned: Vec<ClassFeeAssignment>,
+    // Students who already had fees for the term
+    pub skipped_student_ids: Vec<String>,
+}
+
+// A student to bill, with the scholarship applied to them
+struct PlannedAssignment {
+    student_id: String,
+    student_name: String,
+    scholarship: Option<(String, Money)>,
+}
+
+/// Bill every active student in `class_id` for `term` from the fee structure, applying
+/// their scholarships.
+pub fn assign_fees_to_class(
+    class_id: String,
+    fee_structure_id: String,
+    term: String,
+) -> Result<ClassBillingSummary, String> {
+    let caller = caller();
+    if !caller_has_any_role(&caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can assign fees to a class".to_string());
+    }
+
+    let (_, structure) = get_doc_data::<FeeStructureData>("fee_structures", &fee_structure_id)?
+        .ok_or_else(|| format!("Fee structure '{}' not found", fee_structure_id))?;
+    if !structure.is_active {
+        return Err(format!("Fee structure '{}' is not active", fee_structure_id));
+    }
+    if structure.class_id != class_id {
+        return Err(format!(
+            "Fee structure '{}' is for class '{}', not '{}'",
+            fee_structure_id, structure.class_id, class_id
+        ));
+    }
+    if structure.term != term {
+        return Err(format!(
+            "Fee structure '{}' is for the {} term, not the {} term",
+            fee_structure_id, structure.term, term
+        ));
+    }
+    let (_, class) = get_doc_data::<ClassData>(CLASSES_COLLECTION, &class_id)?
+        .ok_or_else(|| format!("Class '{}' not found", class_id))?;
+    if !class.is_active {
+        return Err(format!("Class '{}' is not active", class.name));
+    }
+
+    let academic_term = list_doc_data::<AcademicTermData>(ACADEMIC_TERMS_COLLECTION, None)?
+        .into_iter()
+        .find(|(_, _, t)| t.academic_year == structure.academic_year && t.term == term)
+        .map(|(_, _, t)| t);
+    if let Some(ref t) = academic_term {
+        if t.status == "closed" {
+            return Err(format!("TERM_CLOSED: The {} {} term is closed", t.academic_year, t.term));
+        }
+    }
+    let due_date = academic_term.map(|t| t.start_date);
+
+    let fee_items = billed_items(&structure);
+    if fee_items.is_empty() {
+        return Err(format!("Fee structure '{}' has no mandatory items to bill", fee_structure_id));
+    }
+
+    let students: Vec<(String, Doc, serde_json::Value)> = list_doc_data::<serde_json::Value>("students", None)?
+        .into_iter()
+        .filter(|(_, _, s)| s.get("classId").and_then(|c| c.as_str()) == Some(class_id.as_str()))
+        .filter(|(_, _, s)| s.get("isActive").and_then(|a| a.as_bool()) != Some(false))
+        .collect();
+    if students.len() > MAX_CLASS_STUDENTS {
+        return Err(format!("Classes of more than {} students are billed per student", MAX_CLASS_STUDENTS));
+    }
+
+    let assignments = list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)?;
+    let mut scholarships: Vec<(String, Doc, ScholarshipData)> = list_doc_data::<ScholarshipData>("scholarships", None)?
+        .into_iter()
+        .filter(|(_, _, s)| s.academic_year.as_ref().is_none_or(|year| *year == structure.academic_year))
+        .filter(|(_, _, s)| s.terms.as_ref().is_none_or(|terms| terms.is_empty() || terms.contains(&term)))
+        .collect();
+
+    let mut planned = Vec::new();
+    let mut skipped_student_ids = Vec::new();
+    for (student_id, _, student) in students.iter() {
+        if assignments.iter().any(|(_, _, a)| {
+            &a.student_id == student_id && a.academic_year == structure.academic_year && a.term == term
+        }) {
+            skipped_student_ids.push(student_id.clone());
+            continue;
+        }
+        let scholarship = scholarships
+            .iter_mut()
+            .filter(|(_, _, s)| check_scholarship_applies(s, student_id, &class_id).is_ok())
+            .filter(|(_, _, s)| match (s.max_beneficiaries, s.current_beneficiaries) {
+                (Some(max), current) => current.unwrap_or(0) < max,
+                (None, _) => true,
+            })
+            .map(|(key, _, s)| (key.clone(), s.discount_on(&fee_items), s))
+            .filter(|(_, discount, _)| discount.is_positive())
+            .max_by_key(|(_, discount, _)| *discount)
+            .map(|(key, discount, s)| {
+                s.current_beneficiaries = Some(s.current_beneficiaries.unwrap_or(0) + 1);
+                (key, discount)
+            });
+        planned.push(PlannedAssignment {
+            student_id: student_id.clone(),
+            student_name: full_name(student),
+            scholarship,
+        });
+    }
+
+    let original_amount: Money = fee_items.iter().map(|item| item.amount).sum();
+    let mut billed = Vec::new();
+    for PlannedAssignment { student_id, student_name, scholarship } in planned {
+        let mut assignment = StudentFeeAssignmentData {
+            student_id: student_id.clone(),
+            student_name,
+            class_id: class_id.clone(),
+            fee_structure_id: fee_structure_id.clone(),
+            academic_year: structure.academic_year.clone(),
+            term: term.clone(),
+            fee_items: billed_items(&structure),
+            original_amount: None,
+            total_amount: original_amount,
+            amount_paid: Money::ZERO,
+            balance: original_amount,
+            status: fee_assignment_status(Money::ZERO, original_amount).to_string(),
+            due_date: due_date.clone(),
+            scholarship_id: None,
+            scholarship_name: None,
+            scholarship_type: None,
+            scholarship_value: None,
+            discount_amount: None,
+            applied_payment_ids: Vec::new(),
+            closed_out_at: None,
+            extra: serde_json::Map::from_iter([(
+                "className".to_string(),
+                serde_json::Value::String(structure.class_name.clone()),
+            )]),
+        };
+        if let Some((scholarship_id, discount)) = scholarship {
+            if let Some((_, _, s)) = scholarships.iter().find(|(key, _, _)| *key == scholarship_id) {
+                apply_scholarship(&mut assignment, scholarship_id, s, discount);
+            }
+        }
+        let assignment_id = format!("{}_{}_{}", student_id, structure.academic_year.replace('/', "-"), term);
+        billed.push((assignment_id, assignment));
+    }
+
+    // The assignments are still validated as they are written; a rejection undoes the
+    // whole call rather than leaving the class part-billed
+    let assigned = match write_assignments(billed, &scholarships) {
+        Ok(assigned) => assigned,
+        Err(e) => ic_cdk::trap(format!("Fees were not assigned to class '{}': {}", class.name, e)),
+    };
+
+    Ok(ClassBillingSummary { assigned, skipped_student_ids })
+}
+
+fn apply_scholarship(
+    assignment: &mut StudentFeeAssignmentData,
+    scholarship_id: String,
+    scholarship: &ScholarshipData,
+    discount: Money,
+) {
+    // The discount comes off the covered items in order
+    let mut remaining = discount;
+    for item in assignment.fee_items.iter_mut().filter(|item| scholarship.covers_fee_type(&item.fee_type)) {
+        let share = remaining.min(item.balance);
+        item.balance -= share;
+        remaining -= share;
+    }
+    assignment.original_amount = Some(assignment.total_amount);
+    assignment.total_amount -= discount;
+    assignment.balance = assignment.total_amount;
+    assignment.status = fee_assignment_status(Money::ZERO, assignment.total_amount).to_string();
+    assignment.scholarship_id = Some(scholarship_id);
+    assignment.scholarship_name = Some(scholarship.name.clone());
+    assignment.scholarship_type = Some(scholarship.assignment_type());
+    assignment.scholarship_value = match scholarship.scholarship_type.as_str() {
+        "percentage" => scholarship.percentage_off,
+        _ => scholarship.fixed_amount_off.map(Money::naira),
+    };
+    assignment.discount_amount = Some(discount);
+}
+
+fn write_assignments(
+    billed: Vec<(String, StudentFeeAssignmentData)>,
+    scholarships: &[(String, Doc, ScholarshipData)],
+) -> Result<Vec<ClassFeeAssignment>, String> {
+    let mut assigned = Vec::new();
+    for (assignment_id, assignment) in billed {
+        set_doc_data(
+            "student_fee_assignments",
+            &assignment_id,
+            &assignment,
+            Some(format!("studentId={};", assignment.student_id)),
+            None,
+        )?;
+        // Written by the satellite, so the on-set hook does not run
+        record_fee_assignment_usage(None, Some(&assignment))?;
+        queue_invoice_message(&assignment_id, &assignment)?;
+
+        assigned.push(ClassFeeAssignment {
+            student_id: assignment.student_id,
+            assignment_id,
+            total_amount: assignment.total_amount.naira(),
+            scholarship_id: assignment.scholarship_id,
+            discount_amount: assignment.discount_amount.unwrap_or(Money::ZERO).naira(),
+        });
+    }
+
+    // Beneficiary counts of the scholarships applied
+    for (key, doc, scholarship) in scholarships.iter() {
+        if assigned.iter().any(|a| a.scholarship_id.as_ref() == Some(key)) {
+            set_doc_data("scholarships", key, scholarship, doc.description.clone(), doc.version)?;
+        }
+    }
+
+    Ok(assigned)
+}
+
+// The structure's items billed to every student: all but the optional ones
+fn billed_items(structure: &FeeStructureData) -> Vec<FeeItemData> {
+    structure
+        .fee_items
+        .iter()
+        .filter(|item| item.is_mandatory || item.is_optional != Some(true))
+        .map(|item| FeeItemData {
+            category_id: item.category_id.clone(),
+            category_name: item.category_name.clone(),
+            fee_type: item.fee_type.clone(),
+            amount: item.amount,
+            amount_paid: Money::ZERO,
+            balance: item.amount,
+            is_mandatory: item.is_mandatory,
+            is_optional: item.is_optional,
+            is_selected: None,
+            extra: serde_json::Map::new(),
+        })
+        .collect()
+}
@@ -0,0 +1,328 @@
+//! Enrollment Module - Student Promotion and Class Transfer
+//!
+//! `promote_students` moves a group of students from one class to another at the end of
+//! the current term (the school settings' academic year and active term):
+//! - Each student's current-term fee assignments are closed out; any balance stays owed
+//!   and is settled as arrears by later payments
+//! - A next-term fee assignment is raised from the destination class's fee structure,
+//!   with the mandatory items and the optional items the student had selected
+//! - The student and both classes' enrollment counts are updated
+//! - A `promotions` document records the move for each student; it is never changed
+//!
+//! Every check runs before anything is written, so a rejected promotion moves nobody.
+
+pub mod billing;
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext, Doc};
+use serde::{Deserialize, Serialize};
+
+use super::classes::{ClassData, CLASSES_COLLECTION};
+use super::fees::{fee_assignment_status, FeeItemData, FeeStructureData, StudentFeeAssignmentData};
+use super::notifications::fee_messages::queue_invoice_message;
+use super::roles::{caller_has_any_role, Role};
+use super::settings::school_settings;
+use super::terms::{AcademicTermData, ACADEMIC_TERMS_COLLECTION};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+
+pub const PROMOTIONS_COLLECTION: &str = "promotions";
+
+const TERMS: [&str; 3] = ["first", "second", "third"];
+const MAX_PROMOTION_STUDENTS: usize = 200;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromotionData {
+    pub student_id: String,
+    pub from_class_id: String,
+    pub to_class_id: String,
+    pub from_academic_year: String,
+    pub from_term: String,
+    pub to_academic_year: String,
+    pub to_term: String,
+    pub closed_assignment_ids: Vec<String>,
+    // Balance left on the closed assignments, owed as arrears
+    pub carried_balance: Money,
+    pub new_assignment_id: String,
+    pub promoted_by: String,
+    pub promoted_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct PromotedStudent {
+    pub student_id: String,
+    pub promotion_id: String,
+    pub new_assignment_id: String,
+    pub carried_balance: f64,
+}
+
+// A student ready to move, with the documents the promotion rewrites
+struct PlannedMove {
+    student_id: String,
+    student: (Doc, serde_json::Value),
+    student_name: String,
+    // Current-term fee assignments to close out
+    closing: Vec<String>,
+}
+
+/// Promotion Validation
+///
+/// Checks:
+/// - Promotions are recorded by `promote_students` only and never modified
+pub fn validate_promotion_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Promotions are recorded through the promote_students endpoint".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Promotions cannot be modified".to_string());
+    }
+    Ok(())
+}
+
+/// Promotions are part of the students' history and cannot be deleted.
+pub fn validate_promotion_delete() -> Result<(), String> {
+    Err("AUDIT: Promotions cannot be deleted".to_string())
+}
+
+/// Move students from one class to another into the next term, closing out their
+/// current-term fees and billing the next term from the destination class's fee
+/// structure.
+pub fn promote_students(
+    from_class_id: String,
+    to_class_id: String,
+    student_ids: Vec<String>,
+) -> Result<Vec<PromotedStudent>, String> {
+    let caller = caller();
+    if !caller_has_any_role(&caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can promote students".to_string());
+    }
+    if from_class_id == to_class_id {
+        return Err("Students must move to a different class".to_string());
+    }
+    if student_ids.is_empty() || student_ids.len() > MAX_PROMOTION_STUDENTS {
+        return Err(format!("A promotion must include between 1 and {} students", MAX_PROMOTION_STUDENTS));
+    }
+    if (1..student_ids.len()).any(|i| student_ids[..i].contains(&student_ids[i])) {
+        return Err("Each student can only be listed once".to_string());
+    }
+
+    let settings = school_settings()?;
+    let (from_year, from_term) = match (settings.current_academic_year, settings.active_term) {
+        (Some(year), Some(term)) => (year, term),
+        _ => return Err("Set the current academic year and active term in school settings first".to_string()),
+    };
+    let (to_year, to_term) = next_term(&from_year, &from_term)?;
+
+    let (from_class_doc, from_class) = get_doc_data::<serde_json::Value>(CLASSES_COLLECTION, &from_class_id)?
+        .ok_or_else(|| format!("Class '{}' not found", from_class_id))?;
+    let (to_class_doc, to_class) = get_doc_data::<serde_json::Value>(CLASSES_COLLECTION, &to_class_id)?
+        .ok_or_else(|| format!("Class '{}' not found", to_class_id))?;
+    let destination: ClassData = serde_json::from_value(to_class.clone())
+        .map_err(|e| format!("Invalid classes data for '{}': {}", to_class_id, e))?;
+    if !destination.is_active {
+        return Err(format!("Class '{}' is not active", destination.name));
+    }
+    if let Some(capacity) = destination.capacity {
+        if destination.current_enrollment + student_ids.len() as i64 > capacity {
+            return Err(format!(
+                "Class '{}' has room for {} more students, not {}",
+                destination.name,
+                (capacity - destination.current_enrollment).max(0),
+                student_ids.len()
+            ));
+        }
+    }
+
+    let (fee_structure_id, fee_structure) = list_doc_data::<FeeStructureData>("fee_structures", None)?
+        .into_iter()
+        .find(|(_, _, s)| s.is_active && s.class_id == to_class_id && s.academic_year == to_year && s.term == to_term)
+        .map(|(key, _, s)| (key, s))
+        .ok_or_else(|| format!("No active fee structure for class '{}' in the {} {} term", destination.name, to_year, to_term))?;
+
+    let due_date = list_doc_data::<AcademicTermData>(ACADEMIC_TERMS_COLLECTION, None)?
+        .into_iter()
+        .find(|(_, _, t)| t.academic_year == to_year && t.term == to_term)
+        .map(|(_, _, t)| t.start_date);
+
+    let assignments = list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)?;
+    let mut moves = Vec::new();
+    for student_id in student_ids.iter() {
+        let (student_doc, student) = get_doc_data::<serde_json::Value>("students", student_id)?
+            .ok_or_else(|| format!("Student '{}' not found", student_id))?;
+        if student.get("classId").and_then(|c| c.as_str()) != Some(from_class_id.as_str()) {
+            return Err(format!("Student '{}' is not in class '{}'", student_id, from_class_id));
+        }
+        if assignments.iter().any(|(_, _, a)| &a.student_id == student_id && a.academic_year == to_year && a.term == to_term) {
+            return Err(format!("Student '{}' already has fees assigned for the {} {} term", student_id, to_year, to_term));
+        }
+        let current: Vec<&(String, Doc, StudentFeeAssignmentData)> = assignments
+            .iter()
+            .filter(|(_, _, a)| &a.student_id == student_id && a.academic_year == from_year && a.term == from_term)
+            .collect();
+        let student_name = current
+            .first()
+            .map(|(_, _, a)| a.student_name.clone())
+            .unwrap_or_else(|| full_name(&student));
+        let closing = current.iter().map(|(key, _, _)| key.clone()).collect();
+        moves.push(PlannedMove { student_id: student_id.clone(), student: (student_doc, student), student_name, closing });
+    }
+
+    let now = ic_cdk::api::time();
+    let promoted_by = caller.to_text();
+    let mut promoted = Vec::new();
+    for PlannedMove { student_id, student: (student_doc, mut student), student_name, closing } in moves {
+        let mut closed_assignment_ids = Vec::new();
+        let mut carried_balance = Money::ZERO;
+        for key in closing {
+            let (doc, mut assignment) = get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", &key)?
+                .ok_or_else(|| format!("Fee assignment '{}' not found", key))?;
+            if assignment.closed_out_at.is_none() {
+                assignment.closed_out_at = Some(now);
+                set_doc_data("student_fee_assignments", &key, &assignment, doc.description, doc.version)?;
+            }
+            carried_balance += assignment.balance;
+            closed_assignment_ids.push(key);
+        }
+
+        let fee_items: Vec<FeeItemData> = fee_structure
+            .fee_items
+            .iter()
+            .filter(|item| item.is_mandatory || optional_item_kept(&closed_assignment_ids, &assignments, &item.category_id))
+            .map(|item| FeeItemData {
+                category_id: item.category_id.clone(),
+                category_name: item.category_name.clone(),
+                fee_type: item.fee_type.clone(),
+                amount: item.amount,
+                amount_paid: Money::ZERO,
+                balance: item.amount,
+                is_mandatory: item.is_mandatory,
+                is_optional: item.is_optional,
+                is_selected: if item.is_mandatory { None } else { Some(true) },
+                extra: serde_json::Map::new(),
+            })
+            .collect();
+        let total_amount: Money = fee_items.iter().map(|item| item.amount).sum();
+        let new_assignment_id = format!("{}_{}_{}", student_id, to_year.replace('/', "-"), to_term);
+        let assignment = StudentFeeAssignmentData {
+            student_id: student_id.clone(),
+            student_name,
+            class_id: to_class_id.clone(),
+            fee_structure_id: fee_structure_id.clone(),
+            academic_year: to_year.clone(),
+            term: to_term.clone(),
+            fee_items,
+            original_amount: None,
+            total_amount,
+            amount_paid: Money::ZERO,
+            balance: total_amount,
+            status: fee_assignment_status(Money::ZERO, total_amount).to_string(),
+            due_date: due_date.clone(),
+            scholarship_id: None,
+            scholarship_name: None,
+            scholarship_type: None,
+            scholarship_value: None,
+            discount_amount: None,
+            applied_payment_ids: Vec::new(),
+            closed_out_at: None,
+            extra: serde_json::Map::from_iter([(
+                "className".to_string(),
+                serde_json::Value::String(fee_structure.class_name.clone()),
+            )]),
+        };
+        set_doc_data(
+            "student_fee_assignments",
+            &new_assignment_id,
+            &assignment,
+            Some(format!("studentId={};", student_id)),
+            None,
+        )?;
+        // Written by the satellite, so the on-set hook that sends invoices does not run
+        queue_invoice_message(&new_assignment_id, &assignment)?;
+
+        student["classId"] = serde_json::Value::String(to_class_id.clone());
+        student["className"] = serde_json::Value::String(fee_structure.class_name.clone());
+        set_doc_data("students", &student_id, &student, student_doc.description, student_doc.version)?;
+
+        let promotion_id = format!("{}_{}_{}", student_id, from_year.replace('/', "-"), from_term);
+        let promotion = PromotionData {
+            student_id: student_id.clone(),
+            from_class_id: from_class_id.clone(),
+            to_class_id: to_class_id.clone(),
+            from_academic_year: from_year.clone(),
+            from_term: from_term.clone(),
+            to_academic_year: to_year.clone(),
+            to_term: to_term.clone(),
+            closed_assignment_ids,
+            carried_balance,
+            new_assignment_id: new_assignment_id.clone(),
+            promoted_by: promoted_by.clone(),
+            promoted_at: now,
+        };
+        set_doc_data(
+            PROMOTIONS_COLLECTION,
+            &promotion_id,
+            &promotion,
+            Some(format!("student_id={};", student_id)),
+            None,
+        )?;
+
+        promoted.push(PromotedStudent {
+            student_id,
+            promotion_id,
+            new_assignment_id,
+            carried_balance: carried_balance.naira(),
+        });
+    }
+
+    let moved = promoted.len() as i64;
+    update_enrollment(&from_class_id, from_class_doc, from_class, -moved)?;
+    update_enrollment(&to_class_id, to_class_doc, to_class, moved)?;
+
+    Ok(promoted)
+}
+
+// The term after `term` of `academic_year`; the third term rolls into the next year
+fn next_term(academic_year: &str, term: &str) -> Result<(String, String), String> {
+    let index = TERMS
+        .iter()
+        .position(|t| *t == term)
+        .ok_or_else(|| format!("Invalid active term '{}'", term))?;
+    if index + 1 < TERMS.len() {
+        return Ok((academic_year.to_string(), TERMS[index + 1].to_string()));
+    }
+    let start: u32 = academic_year
+        .split('/')
+        .nth(1)
+        .and_then(|year| year.parse().ok())
+        .ok_or_else(|| format!("Invalid academic year '{}'", academic_year))?;
+    Ok((format!("{}/{}", start, start + 1), TERMS[0].to_string()))
+}
+
+// An optional item is kept when the student had it selected on a closed assignment
+fn optional_item_kept(
+    closed_assignment_ids: &[String],
+    assignments: &[(String, Doc, StudentFeeAssignmentData)],
+    category_id: &str,
+) -> bool {
+    assignments
+        .iter()
+        .filter(|(key, _, _)| closed_assignment_ids.contains(key))
+        .flat_map(|(_, _, a)| a.fee_items.iter())
+        .any(|item| item.category_id == category_id && item.is_selected != Some(false))
+}
+
+fn full_name(student: &serde_json::Value) -> String {
+    ["surname", "firstname"]
+        .iter()
+        .filter_map(|field| student.get(*field).and_then(|v| v.as_str()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn update_enrollment(class_id: &str, doc: Doc, mut class: serde_json::Value, change: i64) -> Result<(), String> {
+    let enrollment = class.get("currentEnrollment").and_then(|e| e.as_i64()).unwrap_or(0);
+    class["currentEnrollment"] = serde_json::Value::from((enrollment + change).max(0));
+    set_doc_data(CLASSES_COLLECTION, class_id, &class, doc.description, doc.version)?;
+    Ok(())
+}
@@ -1,7 +1,28 @@
+pub mod policies;
+pub mod splits;
+
 use junobuild_satellite::{AssertSetDocContext, list_docs};
 use junobuild_shared::types::list::{ListParams, ListMatcher};
 use junobuild_utils::decode_doc_data;
 use serde::{Deserialize, Serialize};
+use super::banking::signatories::validate_signatory_approval;
+use super::budgets::encumbrances::validate_expense_budget_availability;
+use super::category_usage::validate_expense_category_deactivation;
+use super::fx::{in_base_currency, validate_foreign_amount};
+use super::insurance::validate_expense_claim_link;
+use super::maintenance::validate_expense_work_order;
+use super::procurement::validate_expense_purchase_order;
+use super::procurement::vendors::validate_expense_vendor;
+use super::pta::validate_expense_fund;
+use super::roles::limits::validate_role_write_limit;
+use super::roles::{caller_has_any_role, require_role, Role};
+use super::settings::school_settings;
+use policies::validate_expense_policy;
+use splits::{validate_expense_splits, ExpenseSplitLine};
+use super::utils::cache::cached_doc_exists;
+use super::utils::doc_utils::{deny_if_referenced, is_satellite_caller, referencing_keys};
+use super::utils::identity::{validate_caller_identity, validate_not_self_approval};
+use super::utils::money::Money;
 use super::utils::validation_utils::*;
 use std::collections::HashMap;
 
@@ -11,7 +32,7 @@ pub struct ExpenseData {
     pub category_id: String,
     pub category_name: String,
     pub category: String,
-    pub amount: f64,
+    pub amount: Money,
     pub description: String,
     pub purpose: Option<String>,
     pub payment_method: String,
@@ -27,6 +48,56 @@ pub struct ExpenseData {
     pub recorded_by: String,
     pub created_at: u64,
     pub updated_at: u64,
+    #[serde(default)]
+    pub vendor_bank_name: Option<String>,
+    #[serde(default)]
+    pub vendor_account_number: Option<String>,
+    #[serde(default)]
+    pub payment_batch: Option<String>,
+    #[serde(default)]
+    pub bank_reference: Option<String>,
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+    #[serde(default)]
+    pub insurance_claim_id: Option<String>,
+    #[serde(default)]
+    pub work_order_id: Option<String>,
+    #[serde(default)]
+    pub fund: Option<String>,
+    #[serde(default)]
+    pub cheque_account_id: Option<String>,
+    #[serde(default)]
+    pub encumbrance_id: Option<String>,
+    #[serde(default)]
+    pub vendor_id: Option<String>,
+    #[serde(default)]
+    pub purchase_order_id: Option<String>,
+    #[serde(default)]
+    pub split_lines: Vec<ExpenseSplitLine>,
+    #[serde(default)]
+    pub void_reason: Option<String>,
+    #[serde(default)]
+    pub voided_by: Option<String>,
+    #[serde(default)]
+    pub voided_at: Option<u64>,
+    // Currency paid in when not the base currency, with the rate and the amount in the
+    // base currency (see `fx`)
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub fx_rate: Option<f64>,
+    #[serde(default)]
+    pub base_amount: Option<Money>,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ExpenseData {
+    /// The amount in the base currency, as it is charged to budgets and reported.
+    pub fn amount_in_base(&self) -> Money {
+        in_base_currency(self.amount, self.base_amount)
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -45,6 +116,9 @@ pub fn validate_expense_document(context: &AssertSetDocContext) -> Result<(), St
         let expense_data: ExpenseData = decode_doc_data(&context.data.data.proposed.data)
             .map_err(|e| format!("Invalid expense data format: {}", e))?;
 
+        // Recorder and approver must be the callers that set them, not another staff member
+        validate_caller_identity(context)?;
+
         // Core expense validation (keep only minimal server-side checks)
         validate_expense_basic_fields(&expense_data)?;
         
@@ -59,17 +133,82 @@ pub fn validate_expense_document(context: &AssertSetDocContext) -> Result<(), St
         
         // Format validation (only core: enums and id/reference/date format)
         validate_expense_formats(&expense_data)?;
+
+        // Foreign-currency amounts carry their rate and base amount
+        validate_foreign_amount(
+            expense_data.currency.as_deref(),
+            expense_data.amount,
+            expense_data.fx_rate,
+            expense_data.base_amount,
+            &expense_data.payment_date,
+        )?;
         
         // Approval workflow validation
         validate_expense_approval_workflow(context, &expense_data)?;
 
+        // Vendor bank transfers are settled through payment files
+        validate_vendor_disbursement_fields(context, &expense_data)?;
+
+        // Invoices spanning departments are split across categories
+        validate_expense_splits(context, &expense_data)?;
+
+        // Vendors come from the vendor register
+        validate_expense_vendor(context, &expense_data)?;
+
+        // Purchase order spending stays within the PO
+        validate_expense_purchase_order(context, &expense_data)?;
+
+        // Insurable incident costs must link to a live claim
+        validate_expense_claim_link(&expense_data)?;
+
+        // Maintenance spending must be backed by completed work
+        validate_expense_work_order(context, &expense_data)?;
+
+        // PTA spending is approved by the PTA's own signatories
+        validate_expense_fund(context, &expense_data)?;
+
+        // Cheques are approved by a signatory of the drawing account
+        validate_expense_cheque_signatory(context, &expense_data)?;
+
+        // Per-category spending policy (limits, payment methods, receipts)
+        validate_expense_policy(context, &expense_data)?;
+
+        // Amount within the recording user's role limit
+        let before_amount = match context.data.data.current {
+            Some(ref doc) => Some(decode_doc_data::<ExpenseData>(&doc.data)
+                .map_err(|e| format!("Invalid previous expense data: {}", e))?
+                .amount_in_base()),
+            None => None,
+        };
+        validate_role_write_limit(context, expense_data.amount_in_base(), before_amount)?;
+
+        // Spending must fit the budget after commitments
+        validate_expense_budget_availability(context, &expense_data)?;
+
 
         Ok(())
     }
     
+    fn validate_expense_cheque_signatory(context: &AssertSetDocContext, expense_data: &ExpenseData) -> Result<(), String> {
+        let account_id = match expense_data.cheque_account_id {
+            Some(ref id) if expense_data.payment_method == "cheque" && !id.trim().is_empty() => id,
+            _ => return Ok(()),
+        };
+
+        if let Some(ref before_doc) = context.data.data.current {
+            let before: ExpenseData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous expense data: {}", e))?;
+            if before.approved_by == expense_data.approved_by && before.cheque_account_id == expense_data.cheque_account_id {
+                return Ok(());
+            }
+        }
+
+        validate_signatory_approval(account_id, expense_data.approved_by.as_deref(), expense_data.amount)
+    }
+
     fn validate_expense_basic_fields(expense_data: &ExpenseData) -> Result<(), String> {
         // Only core authoritative checks
-        if expense_data.amount <= 0.0 {
+        if !expense_data.amount.is_positive() {
             return Err("Expense amount must be greater than 0".to_string());
         }
         Ok(())
@@ -111,7 +250,7 @@ pub fn validate_expense_document(context: &AssertSetDocContext) -> Result<(), St
         Ok(())
     }
     
-    fn validate_expense_approval_workflow(_context: &AssertSetDocContext, expense_data: &ExpenseData) -> Result<(), String> {
+    fn validate_expense_approval_workflow(context: &AssertSetDocContext, expense_data: &ExpenseData) -> Result<(), String> {
         match expense_data.status.as_str() {
             "pending" => {
                 // New pending expenses should not have approval fields set
@@ -131,16 +270,16 @@ pub fn validate_expense_document(context: &AssertSetDocContext) -> Result<(), St
                     return Err("Approved expenses must have approved_at timestamp".to_string());
                 }
                 
-                // Validate approver is not the same as recorder (no self-approval)
-                // TODO: Re-enable in production
-                // TEMPORARILY DISABLED FOR DEVELOPMENT/TESTING
-                /*
-                if let Some(ref approver) = expense_data.approved_by {
-                    if approver == &expense_data.recorded_by {
-                        return Err("Users cannot approve their own expenses".to_string());
-                    }
+                // No self-approval: the approving caller cannot be the recorder
+                let newly_approved = match context.data.data.current {
+                    Some(ref doc) => decode_doc_data::<ExpenseData>(&doc.data)
+                        .map_err(|e| format!("Invalid previous expense data: {}", e))?
+                        .status != "approved",
+                    None => true,
+                };
+                if newly_approved {
+                    validate_not_self_approval(context, Some("recordedBy"), "expenses")?;
                 }
-                */
                 
                 // Validate approval timestamp is reasonable
                 if let Some(approved_at) = expense_data.approved_at {
@@ -148,7 +287,7 @@ pub fn validate_expense_document(context: &AssertSetDocContext) -> Result<(), St
                 }
                 
                 // High-value approval validation
-                validate_high_value_approval_requirements(expense_data)?;
+                validate_high_value_approval_requirements(context, expense_data)?;
             },
             "rejected" => {
                 // Rejected expenses must have rejection reason
@@ -177,6 +316,10 @@ pub fn validate_expense_document(context: &AssertSetDocContext) -> Result<(), St
                 // Validate payment method for paid expenses
                 validate_paid_expense_requirements(expense_data)?;
             },
+            "voided" => {
+                // Spoilt or cancelled cheques are voided, keeping the expense and its reference
+                validate_cheque_void(context, expense_data)?;
+            },
             _ => {
                 return Err(format!("Invalid expense status: '{}'", expense_data.status));
             }
@@ -195,11 +338,16 @@ pub fn validate_expense_document(context: &AssertSetDocContext) -> Result<(), St
 
             let valid_transitions = HashMap::from([
                 ("pending", vec!["approved", "rejected"]),
-                ("approved", vec!["paid"]),
+                ("approved", vec!["paid", "voided"]),
                 ("rejected", vec![]),
-                ("paid", vec![]),
+                ("paid", vec!["voided"]),
+                ("voided", vec![]),
             ]);
 
+            if before_data.status == "voided" {
+                return Err("AUDIT: Voided expenses cannot be modified".to_string());
+            }
+
             let current_status = &before_data.status;
             let new_status = &proposed.status;
 
@@ -259,17 +407,57 @@ pub fn validate_expense_document(context: &AssertSetDocContext) -> Result<(), St
         Ok(())
     }
 
-    fn validate_expense_category_exists(category_id: &str) -> Result<(), String> {
-        let params = ListParams {
-            matcher: Some(ListMatcher {
-                key: Some(category_id.to_string()),
-                ..Default::default()
-            }),
-            ..Default::default()
+    fn validate_vendor_disbursement_fields(context: &AssertSetDocContext, proposed: &ExpenseData) -> Result<(), String> {
+        if let Some(ref account) = proposed.vendor_account_number {
+            if !account.trim().is_empty() && !is_valid_account_number(account) {
+                return Err("Vendor account number must be 10 digits".to_string());
+            }
+        }
+
+        if is_satellite_caller(&context.caller) {
+            return Ok(());
+        }
+
+        let before_data: Option<ExpenseData> = match context.data.data.current {
+            Some(ref before_doc) => Some(
+                decode_doc_data(&before_doc.data)
+                    .map_err(|e| format!("Invalid previous expense data: {}", e))?,
+            ),
+            None => None,
         };
 
-        let categories = list_docs(String::from("expense_categories"), params);
-        if categories.items.is_empty() {
+        // Batch, bank reference and failure are recorded by the satellite only
+        let (batch, bank_reference, failure_reason) = match before_data {
+            Some(ref before) => (&before.payment_batch, &before.bank_reference, &before.failure_reason),
+            None => (&None, &None, &None),
+        };
+        if &proposed.payment_batch != batch
+            || &proposed.bank_reference != bank_reference
+            || &proposed.failure_reason != failure_reason
+        {
+            return Err("SECURITY: Payment batch and bank outcome fields are set by the disbursement workflow".to_string());
+        }
+
+        let was_paid = before_data.as_ref().map(|b| b.status == "paid").unwrap_or(false);
+        if proposed.payment_method == "bank_transfer" && proposed.status == "paid" && !was_paid {
+            return Err("Bank transfer expenses are marked paid only when the bank acknowledgment is imported".to_string());
+        }
+
+        if let Some(ref before) = before_data {
+            if before.payment_batch.is_some()
+                && (proposed.vendor_bank_name != before.vendor_bank_name
+                    || proposed.vendor_account_number != before.vendor_account_number
+                    || proposed.amount != before.amount)
+            {
+                return Err("Expenses already sent to the bank cannot change amount or vendor bank details".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_expense_category_exists(category_id: &str) -> Result<(), String> {
+        if !cached_doc_exists("expense_categories", category_id)? {
             return Err(format!("Expense category '{}' not found", category_id));
         }
         Ok(())
@@ -302,7 +490,7 @@ pub fn validate_expense_document(context: &AssertSetDocContext) -> Result<(), St
     fn validate_potential_duplicate_expense(context: &AssertSetDocContext, expense_data: &ExpenseData, vendor: &str) -> Result<(), String> {
         // Check for potential duplicate: same vendor, same amount, same date
         let search_pattern = format!("vendor_name={}*amount={}*payment_date={};", 
-            vendor.to_lowercase(), expense_data.amount, expense_data.payment_date);
+            vendor.to_lowercase(), expense_data.amount.naira(), expense_data.payment_date);
         
         let similar_expenses = list_docs(
             String::from("expenses"),
@@ -346,8 +534,51 @@ pub fn validate_expense_document(context: &AssertSetDocContext) -> Result<(), St
         Ok(())
     }
 
-    fn validate_high_value_approval_requirements(_expense_data: &ExpenseData) -> Result<(), String> {
-        // Moved to frontend - only status/approval workflow enforced here
+    // Expenses above the school's threshold are approved by an administrator only
+    fn validate_high_value_approval_requirements(context: &AssertSetDocContext, expense_data: &ExpenseData) -> Result<(), String> {
+        let threshold = school_settings()?.expense_approval_threshold;
+        if expense_data.amount <= threshold {
+            return Ok(());
+        }
+        if let Some(ref before_doc) = context.data.data.current {
+            let before: ExpenseData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous expense data: {}", e))?;
+            if before.status == expense_data.status && before.amount == expense_data.amount {
+                return Ok(());
+            }
+        }
+        require_role(&context.caller, Role::SuperAdmin)
+            .map_err(|_| format!("SECURITY: Expenses above ₦{} must be approved by an administrator", threshold))
+    }
+
+    // Only cheque expenses are voided: by a bursar or administrator other than the recorder,
+    // with a reason, and without changing what was paid
+    fn validate_cheque_void(context: &AssertSetDocContext, expense_data: &ExpenseData) -> Result<(), String> {
+        let before: ExpenseData = match context.data.data.current {
+            Some(ref doc) => decode_doc_data(&doc.data)
+                .map_err(|e| format!("Invalid previous expense data: {}", e))?,
+            None => return Err("New expenses cannot be recorded as voided".to_string()),
+        };
+        if before.status == "voided" {
+            return Ok(());
+        }
+
+        if expense_data.payment_method != "cheque" || before.payment_method != "cheque" {
+            return Err("Only cheque expenses can be voided".to_string());
+        }
+        if expense_data.amount != before.amount || expense_data.reference != before.reference {
+            return Err("AUDIT: Voiding a cheque cannot change its amount or reference".to_string());
+        }
+        if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+            return Err("SECURITY: Only a bursar or administrator can void cheques".to_string());
+        }
+        validate_not_self_approval(context, Some("recordedBy"), "cheque voids")?;
+        if expense_data.voided_by.as_deref() != Some(context.caller.to_text().as_str()) || expense_data.voided_at.is_none() {
+            return Err("Voided cheques must record voidedBy (the approving caller) and voidedAt".to_string());
+        }
+        if expense_data.void_reason.as_ref().map(|r| r.trim().len() < 10).unwrap_or(true) {
+            return Err("Voiding a cheque requires a reason of at least 10 characters".to_string());
+        }
         Ok(())
     }
 
@@ -403,5 +634,19 @@ pub fn validate_expense_document(context: &AssertSetDocContext) -> Result<(), St
             }
         }
 
+        // Deactivation is blocked while expenses in the category are unsettled
+        if let Some(ref before_doc) = context.data.data.current {
+            let before_data: ExpenseCategoryData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous expense category data: {}", e))?;
+            if before_data.is_active && !category_data.is_active {
+                validate_expense_category_deactivation(&context.data.key)?;
+            }
+        }
+
         Ok(())
     }
+
+/// An expense category cannot be deleted while expenses are recorded against it.
+pub fn validate_expense_category_delete(key: &str) -> Result<(), String> {
+    deny_if_referenced("expense category", key, &[("expenses", referencing_keys("expenses", "categoryId", key)?)])
+}
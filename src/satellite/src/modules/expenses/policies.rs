@@ -0,0 +1,172 @@
+//! Expense policy rules per category.
+//!
+//! Each expense category may have a policy document in `expense_policies`, keyed by the
+//! category id: a maximum single amount, a monthly cap, the payment methods allowed and
+//! the amount from which a receipt must be attached. The policy keyed `default` applies
+//! to categories without their own. Categories with no applicable policy are unrestricted.
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::{ExpenseCategoryData, ExpenseData};
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::cache::cached_doc_data;
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+
+pub const EXPENSE_POLICIES_COLLECTION: &str = "expense_policies";
+
+// Key of the policy that applies to categories without their own
+const DEFAULT_POLICY_KEY: &str = "default";
+
+const PAYMENT_METHODS: [&str; 5] = ["cash", "bank_transfer", "cheque", "pos", "online"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpensePolicyData {
+    pub max_single_amount: Option<Money>,
+    pub monthly_cap: Option<Money>,
+    // Empty means any payment method
+    #[serde(default)]
+    pub allowed_payment_methods: Vec<String>,
+    pub receipt_required_above: Option<Money>,
+    pub is_active: bool,
+    pub updated_by: String,
+}
+
+/// Expense Policy Validation
+///
+/// Checks:
+/// - Maintained by a bursar or administrator
+/// - Keyed by an existing expense category id, or `default`
+/// - Positive limits, a monthly cap no lower than the single-amount limit, known payment methods
+pub fn validate_expense_policy_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: ExpensePolicyData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid expense policy data format: {}", e))?;
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can change expense policies".to_string());
+    }
+    if data.updated_by != context.caller.to_text() {
+        return Err("updatedBy must be the principal changing the policy".to_string());
+    }
+
+    if context.data.key != DEFAULT_POLICY_KEY
+        && cached_doc_data::<ExpenseCategoryData>("expense_categories", &context.data.key)?.is_none()
+    {
+        return Err(format!(
+            "Expense policies are keyed by category id (or '{}'); category '{}' not found",
+            DEFAULT_POLICY_KEY, context.data.key
+        ));
+    }
+
+    for (field, limit) in [
+        ("maxSingleAmount", data.max_single_amount),
+        ("monthlyCap", data.monthly_cap),
+        ("receiptRequiredAbove", data.receipt_required_above),
+    ] {
+        if limit.map(|l| !l.is_positive()).unwrap_or(false) {
+            return Err(format!("{} must be greater than 0", field));
+        }
+    }
+    if let (Some(single), Some(cap)) = (data.max_single_amount, data.monthly_cap) {
+        if cap < single {
+            return Err("monthlyCap cannot be less than maxSingleAmount".to_string());
+        }
+    }
+    if let Some(invalid) = data.allowed_payment_methods.iter().find(|m| !PAYMENT_METHODS.contains(&m.as_str())) {
+        return Err(format!(
+            "Invalid payment method '{}'. Must be one of: {}",
+            invalid,
+            PAYMENT_METHODS.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Evaluate the category's policy for a new expense, or an expense whose amount,
+/// category, payment method or receipt changed.
+pub fn validate_expense_policy(context: &AssertSetDocContext, expense: &ExpenseData) -> Result<(), String> {
+    if is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+    if let Some(ref before_doc) = context.data.data.current {
+        let before: ExpenseData = decode_doc_data(&before_doc.data)
+            .map_err(|e| format!("Invalid previous expense data: {}", e))?;
+        if before.amount == expense.amount
+            && before.category_id == expense.category_id
+            && before.payment_method == expense.payment_method
+            && before.invoice_url == expense.invoice_url
+        {
+            return Ok(());
+        }
+    }
+
+    let policy = match applicable_policy(&expense.category_id)? {
+        Some(policy) => policy,
+        None => return Ok(()),
+    };
+
+    if let Some(max) = policy.max_single_amount {
+        if expense.amount > max {
+            return Err(format!(
+                "POLICY: {} expenses are limited to ₦{} each; ₦{} requested",
+                expense.category_name, max, expense.amount
+            ));
+        }
+    }
+
+    if !policy.allowed_payment_methods.is_empty() && !policy.allowed_payment_methods.contains(&expense.payment_method) {
+        return Err(format!(
+            "POLICY: {} expenses must be paid by {}",
+            expense.category_name,
+            policy.allowed_payment_methods.join(" or ")
+        ));
+    }
+
+    if let Some(threshold) = policy.receipt_required_above {
+        let has_receipt = expense.invoice_url.as_ref().map(|u| !u.trim().is_empty()).unwrap_or(false);
+        if expense.amount > threshold && !has_receipt {
+            return Err(format!(
+                "POLICY: {} expenses above ₦{} must have a receipt attached",
+                expense.category_name, threshold
+            ));
+        }
+    }
+
+    if let Some(cap) = policy.monthly_cap {
+        let month = expense.payment_date.get(0..7).unwrap_or_default();
+        let spent: Money = list_doc_data::<ExpenseData>("expenses", None)?
+            .iter()
+            .filter(|(key, _, e)| {
+                key != &context.data.key
+                    && e.category_id == expense.category_id
+                    && (e.status == "approved" || e.status == "paid")
+                    && e.payment_date.starts_with(month)
+            })
+            .map(|(_, _, e)| e.amount)
+            .sum();
+        if spent + expense.amount > cap {
+            return Err(format!(
+                "POLICY: {} spending for {} would exceed the monthly cap of ₦{} (₦{} already spent)",
+                expense.category_name, month, cap, spent
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The category's own active policy, else the active default policy.
+fn applicable_policy(category_id: &str) -> Result<Option<ExpensePolicyData>, String> {
+    for key in [category_id, DEFAULT_POLICY_KEY] {
+        if let Some((_, policy)) = get_doc_data::<ExpensePolicyData>(EXPENSE_POLICIES_COLLECTION, key)? {
+            if policy.is_active {
+                return Ok(Some(policy));
+            }
+        }
+    }
+    Ok(None)
+}
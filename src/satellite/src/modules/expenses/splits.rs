@@ -0,0 +1,224 @@
+//! Split expenses.
+//!
+//! An invoice that spans departments (shared generator fuel, a joint supplier bill) is
+//! recorded as one expense with `splitLines`, each charging part of the amount to an
+//! expense category and its budget code, instead of forcing the whole invoice onto one
+//! category. The expense's own category is the line that carries the largest share.
+//!
+//! Budgets are charged line by line (see [`expense_allocations`]). The frontend posts
+//! single-category expenses to the ledger; split expenses are posted here, one debit per
+//! line against the category's mapped expense account, and reversed if the expense is
+//! voided.
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::{ExpenseCategoryData, ExpenseData};
+use crate::modules::ledger::{find_account, journal_line, post_journal_entry};
+use crate::modules::utils::cache::cached_doc_data;
+use crate::modules::utils::doc_utils::list_doc_data;
+use crate::modules::utils::money::Money;
+
+const ACCOUNT_MAPPINGS_COLLECTION: &str = "account_mappings";
+
+// Fallback accounts, as in the frontend's auto-posting
+const OTHER_EXPENSE_ACCOUNT_CODE: &str = "5900";
+const CASH_ACCOUNT_CODE: &str = "1110";
+const BANK_ACCOUNT_CODE: &str = "1120";
+
+// Percentages are given to two decimal places
+const PERCENTAGE_TOLERANCE: f64 = 0.005;
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpenseSplitLine {
+    pub category_id: String,
+    pub budget_code: Option<String>,
+    // Share of the expense, when split by percentage
+    pub percentage: Option<f64>,
+    pub amount: Money,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountMappingRef {
+    mapping_type: String,
+    source_type: String,
+    account_code: String,
+    is_active: bool,
+}
+
+/// Category and amount charged by an expense: one per split line, or the whole amount to
+/// the expense's category.
+pub fn expense_allocations(expense: &ExpenseData) -> Vec<(String, Money)> {
+    if expense.split_lines.is_empty() {
+        return vec![(expense.category_id.clone(), expense.amount_in_base())];
+    }
+    expense.split_lines.iter().map(|l| (l.category_id.clone(), l.amount)).collect()
+}
+
+/// Split Line Validation
+///
+/// Checks:
+/// - At least two lines, each in a different active expense category, with the category's
+///   budget code and a positive amount
+/// - Lines given by percentage are that share of the total, and percentages add up to 100
+/// - Line amounts add up to the expense amount; the expense's category is the largest line
+/// - Expenses in a foreign currency are not split
+/// - Lines are fixed once the expense is recorded, since they have been posted
+pub fn validate_expense_splits(context: &AssertSetDocContext, expense: &ExpenseData) -> Result<(), String> {
+    if let Some(ref before_doc) = context.data.data.current {
+        let before: ExpenseData = decode_doc_data(&before_doc.data)
+            .map_err(|e| format!("Invalid previous expense data: {}", e))?;
+        if before.split_lines != expense.split_lines || (!before.split_lines.is_empty() && before.amount != expense.amount) {
+            return Err("AUDIT: Split lines and the amount of a split expense cannot be changed once recorded".to_string());
+        }
+        return Ok(());
+    }
+
+    let lines = &expense.split_lines;
+    if lines.is_empty() {
+        return Ok(());
+    }
+    if lines.len() < 2 {
+        return Err("A split expense must have at least two lines".to_string());
+    }
+    if expense.base_amount.is_some() {
+        return Err("A foreign-currency expense cannot be split".to_string());
+    }
+
+    for (i, line) in lines.iter().enumerate() {
+        if lines[..i].iter().any(|l| l.category_id == line.category_id) {
+            return Err(format!("Expense category '{}' appears on more than one split line", line.category_id));
+        }
+        if !line.amount.is_positive() {
+            return Err("Each split line amount must be greater than 0".to_string());
+        }
+
+        let (_, category) = cached_doc_data::<ExpenseCategoryData>("expense_categories", &line.category_id)?
+            .ok_or_else(|| format!("Expense category '{}' not found", line.category_id))?;
+        if !category.is_active {
+            return Err(format!("Expense category '{}' is inactive", category.name));
+        }
+        if line.budget_code.as_deref().filter(|c| !c.is_empty()) != category.budget_code.as_deref().filter(|c| !c.is_empty()) {
+            return Err(format!(
+                "Split line for '{}' must carry its budget code ({})",
+                category.name,
+                category.budget_code.as_deref().unwrap_or("none")
+            ));
+        }
+
+        if let Some(percentage) = line.percentage {
+            if percentage <= 0.0 || percentage > 100.0 {
+                return Err(format!("Split line for '{}': percentage must be between 0 and 100", category.name));
+            }
+            // Within a kobo of the share, since shares are rounded
+            if (line.amount - expense.amount.percent(percentage)).kobo().abs() > 1 {
+                return Err(format!(
+                    "Split line for '{}': ₦{} is not {}% of ₦{}",
+                    category.name, line.amount, percentage, expense.amount
+                ));
+            }
+        }
+    }
+
+    if lines.iter().any(|l| l.percentage.is_some()) {
+        if lines.iter().any(|l| l.percentage.is_none()) {
+            return Err("Split lines must all be given by percentage, or all by amount".to_string());
+        }
+        let total: f64 = lines.iter().filter_map(|l| l.percentage).sum();
+        if (total - 100.0).abs() > PERCENTAGE_TOLERANCE {
+            return Err(format!("Split percentages add up to {:.2}%, not 100%", total));
+        }
+    }
+
+    let lines_total: Money = lines.iter().map(|l| l.amount).sum();
+    if lines_total != expense.amount {
+        return Err(format!(
+            "Split lines add up to ₦{}, not the expense amount of ₦{}",
+            lines_total, expense.amount
+        ));
+    }
+
+    let own_share = lines.iter().find(|l| l.category_id == expense.category_id).map(|l| l.amount);
+    if own_share.is_none() || own_share < lines.iter().map(|l| l.amount).max() {
+        return Err("The expense's category must be its largest split line".to_string());
+    }
+
+    Ok(())
+}
+
+/// Called when an expense is saved: posts a split expense to the ledger when it is
+/// approved or paid, and reverses the posting when it is voided. Posting is keyed by the
+/// expense, so saving it again does not post twice.
+pub fn post_expense_splits(key: &str, expense: &ExpenseData) -> Result<(), String> {
+    if expense.split_lines.is_empty() {
+        return Ok(());
+    }
+    let posted = expense.status == "approved" || expense.status == "paid";
+    if !posted && expense.status != "voided" {
+        return Ok(());
+    }
+
+    let payment_account = find_account(if expense.payment_method == "cash" { CASH_ACCOUNT_CODE } else { BANK_ACCOUNT_CODE })?;
+    let mut debits = Vec::with_capacity(expense.split_lines.len());
+    for line in expense.split_lines.iter() {
+        let (_, category) = cached_doc_data::<ExpenseCategoryData>("expense_categories", &line.category_id)?
+            .ok_or_else(|| format!("Expense category '{}' not found", line.category_id))?;
+        let description = format!(
+            "{}{} - {}",
+            category.name,
+            line.budget_code.as_ref().map(|c| format!(" ({})", c)).unwrap_or_default(),
+            line.description.as_deref().unwrap_or(&expense.description)
+        );
+        debits.push((find_account(&expense_account_code(&category.category)?)?, line.amount, description));
+    }
+
+    let payee = expense.vendor_name.as_deref().unwrap_or(&expense.category_name);
+    let credit_description = format!("Payment to {}", payee);
+    let mut lines = Vec::with_capacity(debits.len() + 1);
+
+    if posted {
+        for (account, amount, description) in debits.iter() {
+            lines.push(journal_line(account, *amount, Money::ZERO, description));
+        }
+        lines.push(journal_line(&payment_account, Money::ZERO, expense.amount, &credit_description));
+        post_journal_entry(
+            &format!("expense-split-{}", key),
+            &format!("JE-{}", expense.reference),
+            &expense.payment_date,
+            &format!("Split expense {} - {}", expense.reference, expense.description),
+            "expense",
+            Some(key.to_string()),
+            lines,
+        )?;
+    } else {
+        for (account, amount, description) in debits.iter() {
+            lines.push(journal_line(account, Money::ZERO, *amount, description));
+        }
+        lines.push(journal_line(&payment_account, expense.amount, Money::ZERO, &credit_description));
+        post_journal_entry(
+            &format!("expense-split-void-{}", key),
+            &format!("JE-{}-V", expense.reference),
+            &expense.payment_date,
+            &format!("Reversal of voided split expense {}", expense.reference),
+            "expense",
+            Some(key.to_string()),
+            lines,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The ledger account mapped to an expense category type, else other expenses.
+pub fn expense_account_code(category: &str) -> Result<String, String> {
+    let category = category.to_lowercase();
+    Ok(list_doc_data::<AccountMappingRef>(ACCOUNT_MAPPINGS_COLLECTION, None)?
+        .into_iter()
+        .find(|(_, _, m)| m.mapping_type == "expense" && m.source_type == category && m.is_active)
+        .map(|(_, _, m)| m.account_code)
+        .unwrap_or_else(|| OTHER_EXPENSE_ACCOUNT_CODE.to_string()))
+}
@@ -0,0 +1,363 @@
+//! Family Invoices Module - One Invoice per Guardian
+//!
+//! Parents with several children at the school want one bill. `generate_family_invoice`
+//! bills a guardian for everything their children (see [`super::guardians`]) still owe up
+//! to a term of the current academic year, as one numbered invoice (`FINV-YYYY-NNNNNN`)
+//! in `family_invoices` with a line per child and fee assignment.
+//!
+//! Payments against an invoice are recorded in `family_payments`. When one is confirmed,
+//! the on-set hook allocates it across the lines in priority order:
+//! - Arrears from earlier terms first, oldest first
+//! - Then the term's fees, child by child in the guardian's order
+//!
+//! Within a line, fee items are paid in the school's allocation priority order.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::devices::validate_cash_entry_device;
+use super::fees::{allocate_to_fee_items, apply_payment_to_assignment, StudentFeeAssignmentData};
+use super::guardians::{GuardianData, GUARDIANS_COLLECTION};
+use super::roles::{caller_has_any_role, require_role, Role};
+use super::settings::school_settings;
+use super::utils::counters::next_number;
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const FAMILY_INVOICES_COLLECTION: &str = "family_invoices";
+pub const FAMILY_PAYMENTS_COLLECTION: &str = "family_payments";
+
+const TERMS: [&str; 3] = ["first", "second", "third"];
+const PAYMENT_METHODS: [&str; 5] = ["cash", "bank_transfer", "pos", "online", "cheque"];
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FamilyInvoiceLine {
+    pub student_id: String,
+    pub student_name: String,
+    pub fee_assignment_id: String,
+    pub academic_year: String,
+    pub term: String,
+    pub is_arrears: bool,
+    pub amount: Money,
+    pub amount_paid: Money,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FamilyInvoiceData {
+    pub guardian_id: String,
+    pub invoice_number: String,
+    pub academic_year: String,
+    pub term: String,
+    // In allocation priority order
+    pub lines: Vec<FamilyInvoiceLine>,
+    pub total: Money,
+    pub amount_paid: Money,
+    // Paid by the family but no longer owed on any line
+    pub unapplied: Money,
+    pub status: String,
+    pub issued_by: String,
+    pub issued_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FamilyPaymentData {
+    pub guardian_id: String,
+    pub invoice_id: String,
+    pub amount: Money,
+    pub payment_method: String,
+    pub payment_date: String,
+    pub reference: String,
+    pub status: String,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub device_id: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct FamilyInvoiceItem {
+    pub student_id: String,
+    pub student_name: String,
+    pub academic_year: String,
+    pub term: String,
+    pub is_arrears: bool,
+    pub amount: f64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct FamilyInvoice {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    pub academic_year: String,
+    pub term: String,
+    pub lines: Vec<FamilyInvoiceItem>,
+    pub total: f64,
+}
+
+/// Family invoices are issued by `generate_family_invoice` and updated by the payment
+/// allocation only.
+pub fn validate_family_invoice_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Family invoices are issued through the generate_family_invoice endpoint".to_string());
+    }
+    Ok(())
+}
+
+/// Family Payment Validation
+///
+/// Checks:
+/// - The invoice exists and was issued to the guardian
+/// - Amount is positive and within the invoice balance; valid method, date and reference
+/// - Cash is entered at a registered cashier terminal
+/// - New payments are pending or confirmed; pending → confirmed/cancelled only
+/// - Only bursars confirm; confirmed payments cannot be changed
+pub fn validate_family_payment_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: FamilyPaymentData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid family payment data format: {}", e))?;
+
+    let before: Option<FamilyPaymentData> = match context.data.data.current {
+        Some(ref doc) => Some(
+            decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous family payment data: {}", e))?,
+        ),
+        None => None,
+    };
+
+    match before {
+        None => {
+            if data.status != "pending" && data.status != "confirmed" {
+                return Err("New family payments must have status 'pending' or 'confirmed'".to_string());
+            }
+            if data.recorded_by != context.caller.to_text() {
+                return Err("recordedBy must be the principal recording the payment".to_string());
+            }
+            if data.payment_method == "cash" {
+                validate_cash_entry_device(data.device_id.as_deref())?;
+            }
+        }
+        Some(ref before) => {
+            if before.status != "pending" {
+                return Err(format!("AUDIT: A {} family payment cannot be changed", before.status));
+            }
+            if before.guardian_id != data.guardian_id
+                || before.invoice_id != data.invoice_id
+                || before.amount != data.amount
+                || before.payment_method != data.payment_method
+                || before.recorded_by != data.recorded_by
+            {
+                return Err("Only the status and notes of a pending family payment can change".to_string());
+            }
+            if data.status != "pending" && data.status != "confirmed" && data.status != "cancelled" {
+                return Err(format!(
+                    "Invalid status transition from 'pending' to '{}'. Allowed: [confirmed, cancelled]",
+                    data.status
+                ));
+            }
+            if data.status == "cancelled" && data.notes.as_ref().map(|n| n.trim().is_empty()).unwrap_or(true) {
+                return Err("Cancelled family payments must include the reason in notes".to_string());
+            }
+        }
+    }
+
+    if data.status == "confirmed" {
+        require_role(&context.caller, Role::Bursar)?;
+    }
+    if !data.amount.is_positive() {
+        return Err("Family payment amount must be greater than zero".to_string());
+    }
+    if !PAYMENT_METHODS.contains(&data.payment_method.as_str()) {
+        return Err(format!(
+            "Invalid payment method '{}'. Must be one of: {}",
+            data.payment_method,
+            PAYMENT_METHODS.join(", ")
+        ));
+    }
+    if !is_valid_date_format(&data.payment_date) {
+        return Err("Invalid payment date format. Must be YYYY-MM-DD".to_string());
+    }
+    if data.reference.trim().is_empty() {
+        return Err("Family payments must include a reference".to_string());
+    }
+
+    let (_, invoice) = get_doc_data::<FamilyInvoiceData>(FAMILY_INVOICES_COLLECTION, &data.invoice_id)?
+        .ok_or_else(|| format!("Family invoice '{}' not found", data.invoice_id))?;
+    if invoice.guardian_id != data.guardian_id {
+        return Err("The invoice was not issued to this guardian".to_string());
+    }
+    let outstanding = invoice.total - invoice.amount_paid;
+    if data.status != "cancelled" && data.amount > outstanding {
+        return Err(format!(
+            "Payment of ₦{} exceeds the invoice's outstanding balance of ₦{}",
+            data.amount, outstanding
+        ));
+    }
+
+    Ok(())
+}
+
+/// Issue one invoice covering everything a guardian's children owe up to a term of the
+/// current academic year.
+pub fn generate_family_invoice(guardian_id: String, term: String) -> Result<FamilyInvoice, String> {
+    let caller = caller();
+    if !caller_has_any_role(&caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can generate family invoices".to_string());
+    }
+    let term_index = TERMS
+        .iter()
+        .position(|t| *t == term)
+        .ok_or_else(|| format!("Invalid term '{}'. Must be one of: {}", term, TERMS.join(", ")))?;
+
+    let (_, guardian) = get_doc_data::<GuardianData>(GUARDIANS_COLLECTION, &guardian_id)?
+        .ok_or_else(|| format!("Guardian '{}' not found", guardian_id))?;
+    if guardian.student_ids.is_empty() {
+        return Err("The guardian has no children linked".to_string());
+    }
+
+    let academic_year = match school_settings()?.current_academic_year {
+        Some(year) => year,
+        None => academic_year_for_date(&current_date()).ok_or("Could not determine the current academic year")?,
+    };
+    let invoice_period = (academic_year.clone(), term_index);
+    let period_of = |a: &StudentFeeAssignmentData| {
+        (a.academic_year.clone(), TERMS.iter().position(|t| *t == a.term).unwrap_or(0))
+    };
+
+    let assignments = list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)?;
+    let mut lines: Vec<(usize, FamilyInvoiceLine)> = Vec::new();
+    for (child_index, student_id) in guardian.student_ids.iter().enumerate() {
+        for (key, _, assignment) in assignments.iter() {
+            if &assignment.student_id != student_id || !assignment.balance.is_positive() {
+                continue;
+            }
+            let period = period_of(assignment);
+            if period > invoice_period {
+                continue;
+            }
+            lines.push((
+                child_index,
+                FamilyInvoiceLine {
+                    student_id: student_id.clone(),
+                    student_name: assignment.student_name.clone(),
+                    fee_assignment_id: key.clone(),
+                    academic_year: assignment.academic_year.clone(),
+                    term: assignment.term.clone(),
+                    is_arrears: period < invoice_period,
+                    amount: assignment.balance,
+                    amount_paid: Money::ZERO,
+                },
+            ));
+        }
+    }
+    if lines.is_empty() {
+        return Err("None of the guardian's children owe fees".to_string());
+    }
+
+    // Allocation priority: arrears oldest first, then the term's fees child by child
+    lines.sort_by(|(a_child, a), (b_child, b)| {
+        let a_key = (!a.is_arrears, a.academic_year.as_str(), TERMS.iter().position(|t| *t == a.term), *a_child);
+        let b_key = (!b.is_arrears, b.academic_year.as_str(), TERMS.iter().position(|t| *t == b.term), *b_child);
+        a_key.cmp(&b_key)
+    });
+    let lines: Vec<FamilyInvoiceLine> = lines.into_iter().map(|(_, line)| line).collect();
+
+    let year = &current_date()[0..4];
+    let invoice_number = format!("FINV-{}-{:06}", year, next_number(&format!("family-invoices-{}", year))?);
+    let invoice = FamilyInvoiceData {
+        guardian_id: guardian_id.clone(),
+        invoice_number: invoice_number.clone(),
+        academic_year: academic_year.clone(),
+        term: term.clone(),
+        total: lines.iter().map(|l| l.amount).sum(),
+        lines,
+        amount_paid: Money::ZERO,
+        unapplied: Money::ZERO,
+        status: "issued".to_string(),
+        issued_by: caller.to_text(),
+        issued_at: ic_cdk::api::time(),
+    };
+    set_doc_data(
+        FAMILY_INVOICES_COLLECTION,
+        &invoice_number,
+        &invoice,
+        Some(format!("guardian_id={};", guardian_id)),
+        None,
+    )?;
+
+    Ok(FamilyInvoice {
+        invoice_id: invoice_number.clone(),
+        invoice_number,
+        academic_year,
+        term,
+        lines: invoice
+            .lines
+            .iter()
+            .map(|l| FamilyInvoiceItem {
+                student_id: l.student_id.clone(),
+                student_name: l.student_name.clone(),
+                academic_year: l.academic_year.clone(),
+                term: l.term.clone(),
+                is_arrears: l.is_arrears,
+                amount: l.amount.naira(),
+            })
+            .collect(),
+        total: invoice.total.naira(),
+    })
+}
+
+/// Called from the `family_payments` on-set hook: when a family payment is confirmed,
+/// allocate it across the invoice lines in priority order.
+pub fn on_family_payment_saved(
+    key: &str,
+    before: Option<&FamilyPaymentData>,
+    payment: &FamilyPaymentData,
+) -> Result<(), String> {
+    let was_confirmed = before.map(|b| b.status == "confirmed").unwrap_or(false);
+    if payment.status != "confirmed" || was_confirmed {
+        return Ok(());
+    }
+
+    let (doc, mut invoice) = get_doc_data::<FamilyInvoiceData>(FAMILY_INVOICES_COLLECTION, &payment.invoice_id)?
+        .ok_or_else(|| format!("Family invoice '{}' not found", payment.invoice_id))?;
+
+    let priority = school_settings()?.allocation_priority;
+    let mut remaining = payment.amount;
+    for line in invoice.lines.iter_mut() {
+        if !remaining.is_positive() {
+            break;
+        }
+        let (_, assignment) =
+            match get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", &line.fee_assignment_id)? {
+                Some(found) => found,
+                None => continue,
+            };
+        let due = (line.amount - line.amount_paid).min(assignment.balance);
+        if !due.is_positive() {
+            continue;
+        }
+
+        let share = remaining.min(due);
+        let allocations = allocate_to_fee_items(&assignment, share, &priority);
+        apply_payment_to_assignment(
+            &line.fee_assignment_id,
+            &format!("{}:{}", key, line.fee_assignment_id),
+            share,
+            &allocations,
+        )?;
+        line.amount_paid += share;
+        remaining -= share;
+    }
+
+    invoice.amount_paid += payment.amount;
+    invoice.unapplied += remaining;
+    invoice.status = if invoice.amount_paid >= invoice.total { "paid" } else { "partial" }.to_string();
+    set_doc_data(FAMILY_INVOICES_COLLECTION, &payment.invoice_id, &invoice, doc.description, doc.version)?;
+
+    Ok(())
+}
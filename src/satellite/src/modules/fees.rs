@@ -1,9 +1,49 @@
 //! Fee assignment and scholarship validation module
+//!
+//! Fee structures (`fee_structures`) are the per-class templates for a term's fees: one
+//! structure per class, academic year and term, listing fee categories with their
+//! amounts. Fee assignments raised from a structure carry its items, with optional items
+//! free to be left out (rolled out as the `fee_structure_match` rule).
 
 use junobuild_satellite::AssertSetDocContext;
 use junobuild_utils::decode_doc_data;
 use serde::{Deserialize, Serialize};
 
+use super::category_usage::validate_fee_category_deactivation;
+use super::rules::check_rule;
+use super::settings::school_settings;
+use super::utils::cache::{cached_doc_data, cached_doc_exists, cached_list_doc_data};
+use super::utils::doc_utils::{doc_exists, get_doc_data, is_satellite_caller, list_doc_data, set_doc_data};
+use super::utils::money::Money;
+use super::verification::balance_after;
+use super::utils::validation_utils::{
+    calendar_date, current_date, days_between, is_valid_academic_year, is_valid_category_name, is_valid_date_format,
+};
+
+pub const VALID_FEE_TYPES: [&str; 14] = [
+    "tuition", "uniform", "feeding", "transport", "books", "sports", "development",
+    "examination", "pta", "computer", "library", "laboratory", "lesson", "other",
+];
+
+// Payment allocation rules besides fee types: earlier terms' balances, and mandatory or
+// optional items
+pub const ALLOCATION_RULES: [&str; 3] = ["arrears", "mandatory", "optional"];
+
+// Upper bound for a single fee category amount (₦10M)
+const MAX_FEE_CATEGORY_AMOUNT: Money = Money::from_kobo(1_000_000_000);
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeCategoryData {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub fee_type: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub default_amount: Option<Money>,
+    pub is_active: bool,
+}
+
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StudentFeeAssignmentData {
@@ -14,17 +54,27 @@ pub struct StudentFeeAssignmentData {
     pub academic_year: String,
     pub term: String,
     pub fee_items: Vec<FeeItemData>,
-    pub original_amount: Option<f64>,
-    pub total_amount: f64,
-    pub amount_paid: f64,
-    pub balance: f64,
+    pub original_amount: Option<Money>,
+    pub total_amount: Money,
+    pub amount_paid: Money,
+    pub balance: Money,
     pub status: String,
     pub due_date: Option<String>,
     pub scholarship_id: Option<String>,
     pub scholarship_name: Option<String>,
     pub scholarship_type: Option<String>,
     pub scholarship_value: Option<f64>,
-    pub discount_amount: Option<f64>,
+    pub discount_amount: Option<Money>,
+    // Payments already applied by the satellite, so a payment is never counted twice
+    #[serde(default)]
+    pub applied_payment_ids: Vec<String>,
+    // Closed out when the student was promoted (see `enrollment`); any balance is carried
+    // as arrears
+    #[serde(default)]
+    pub closed_out_at: Option<u64>,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -34,12 +84,42 @@ pub struct FeeItemData {
     pub category_name: String,
     #[serde(rename = "type")]
     pub fee_type: String,
-    pub amount: f64,
-    pub amount_paid: f64,
-    pub balance: f64,
+    pub amount: Money,
+    pub amount_paid: Money,
+    pub balance: Money,
     pub is_mandatory: bool,
     pub is_optional: Option<bool>,
     pub is_selected: Option<bool>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeStructureData {
+    pub class_id: String,
+    pub class_name: String,
+    pub academic_year: String,
+    pub term: String,
+    pub fee_items: Vec<FeeStructureItemData>,
+    pub total_amount: Money,
+    pub is_active: bool,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeStructureItemData {
+    pub category_id: String,
+    pub category_name: String,
+    #[serde(rename = "type")]
+    pub fee_type: String,
+    pub amount: Money,
+    pub is_mandatory: bool,
+    pub is_optional: Option<bool>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -49,7 +129,7 @@ pub struct ScholarshipData {
     #[serde(rename = "type")]
     pub scholarship_type: String,
     pub percentage_off: Option<f64>,
-    pub fixed_amount_off: Option<f64>,
+    pub fixed_amount_off: Option<Money>,
     pub applicable_to: String,
     pub class_ids: Option<Vec<String>>,
     pub student_ids: Option<Vec<String>>,
@@ -59,6 +139,292 @@ pub struct ScholarshipData {
     pub created_by: String,
     pub max_beneficiaries: Option<i64>,
     pub current_beneficiaries: Option<i64>,
+    // Fee types discounted (all when not set) and fee types never discounted
+    #[serde(default)]
+    pub applicable_to_fee_types: Option<Vec<String>>,
+    #[serde(default)]
+    pub excluded_fee_types: Option<Vec<String>>,
+    // Academic year and terms the scholarship is limited to, when set
+    #[serde(default)]
+    pub academic_year: Option<String>,
+    #[serde(default)]
+    pub terms: Option<Vec<String>>,
+    #[serde(default)]
+    pub max_discount_per_student: Option<Money>,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ScholarshipData {
+    /// Whether the scholarship discounts fees of this type.
+    pub fn covers_fee_type(&self, fee_type: &str) -> bool {
+        !self.excluded_fee_types.as_ref().is_some_and(|types| types.iter().any(|t| t == fee_type))
+            && self
+                .applicable_to_fee_types
+                .as_ref()
+                .is_none_or(|types| types.is_empty() || types.iter().any(|t| t == fee_type))
+    }
+
+    /// The discount on a student's fee items: the scholarship's reduction of the items it
+    /// covers, up to the per-student maximum.
+    pub fn discount_on(&self, fee_items: &[FeeItemData]) -> Money {
+        let covered: Money =
+            fee_items.iter().filter(|item| self.covers_fee_type(&item.fee_type)).map(|item| item.amount).sum();
+        let discount = match self.scholarship_type.as_str() {
+            "full_waiver" => covered,
+            "percentage" => covered.percent(self.percentage_off.unwrap_or(0.0)),
+            "fixed_amount" => self.fixed_amount_off.unwrap_or(Money::ZERO).min(covered),
+            _ => Money::ZERO,
+        };
+        match self.max_discount_per_student {
+            Some(max) => discount.min(max),
+            None => discount,
+        }
+    }
+
+    /// The type recorded on a fee assignment (`waiver` for a full waiver).
+    pub fn assignment_type(&self) -> String {
+        match self.scholarship_type.as_str() {
+            "full_waiver" => "waiver".to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// Payment status implied by the amount paid and the outstanding balance
+pub fn fee_assignment_status(amount_paid: Money, balance: Money) -> &'static str {
+    if amount_paid.is_zero() {
+        "unpaid"
+    } else if balance.is_negative() {
+        "overpaid"
+    } else if balance.is_zero() {
+        "paid"
+    } else {
+        "partial"
+    }
+}
+
+/// The student's current fee assignment: the latest by academic year and term that has
+/// not been closed out. Payments for the student are recorded against it.
+pub fn current_fee_assignment(student_id: &str) -> Result<Option<(String, StudentFeeAssignmentData)>, String> {
+    let term_order = |term: &str| ["first", "second", "third"].iter().position(|t| *t == term).unwrap_or(0);
+    Ok(list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)?
+        .into_iter()
+        .filter(|(_, _, a)| a.student_id == student_id && a.closed_out_at.is_none())
+        .max_by_key(|(_, _, a)| (a.academic_year.clone(), term_order(&a.term)))
+        .map(|(key, _, a)| (key, a)))
+}
+
+/// Apply a confirmed payment to its fee assignment: each allocation is added to the
+/// matching fee item, then the assignment's amount paid, balance and status are
+/// recomputed. Payments already applied to the assignment are ignored.
+pub fn apply_payment_to_assignment(
+    assignment_id: &str,
+    payment_id: &str,
+    amount: Money,
+    allocations: &[(String, Money)],
+) -> Result<(), String> {
+    let (doc, mut assignment) = get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", assignment_id)?
+        .ok_or_else(|| format!("Fee assignment '{}' not found", assignment_id))?;
+
+    if assignment.applied_payment_ids.iter().any(|id| id == payment_id) {
+        return Ok(());
+    }
+
+    for (category_id, allocated) in allocations.iter() {
+        if let Some(item) = assignment.fee_items.iter_mut().find(|i| &i.category_id == category_id) {
+            item.amount_paid += *allocated;
+            item.balance = item.amount - item.amount_paid;
+        }
+    }
+
+    assignment.amount_paid += amount;
+    let (balance, status) = balance_after(assignment.total_amount, assignment.amount_paid);
+    assignment.balance = balance;
+    assignment.status = status.to_string();
+    assignment.applied_payment_ids.push(payment_id.to_string());
+
+    set_doc_data(
+        "student_fee_assignments",
+        assignment_id,
+        &assignment,
+        doc.description.clone(),
+        doc.version,
+    )?;
+
+    Ok(())
+}
+
+/// Position of a fee item in an allocation priority: the first rule naming its fee type
+/// or matching it as mandatory/optional. Items no rule matches come last.
+pub fn allocation_rank(item: &FeeItemData, priority: &[String]) -> usize {
+    priority
+        .iter()
+        .position(|rule| match rule.as_str() {
+            "mandatory" => item.is_mandatory,
+            "optional" => !item.is_mandatory,
+            fee_type => item.fee_type == fee_type,
+        })
+        .unwrap_or(priority.len())
+}
+
+/// Spread an amount over the assignment's unpaid fee items in allocation priority order
+pub fn allocate_to_fee_items(
+    assignment: &StudentFeeAssignmentData,
+    amount: Money,
+    priority: &[String],
+) -> Vec<(String, Money)> {
+    allocate_to_items(&assignment.fee_items, amount, priority)
+}
+
+/// Spread an amount over unpaid fee items in allocation priority order
+pub fn allocate_to_items(fee_items: &[FeeItemData], amount: Money, priority: &[String]) -> Vec<(String, Money)> {
+    let mut items: Vec<_> = fee_items.iter().filter(|i| i.balance.is_positive()).collect();
+    items.sort_by_key(|i| allocation_rank(i, priority));
+
+    let mut remaining = amount;
+    let mut allocations = Vec::new();
+    for item in items {
+        if !remaining.is_positive() {
+            break;
+        }
+        let share = remaining.min(item.balance);
+        allocations.push((item.category_id.clone(), share));
+        remaining -= share;
+    }
+    allocations
+}
+
+/// Validate fee category document
+pub fn validate_fee_category(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: FeeCategoryData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid fee category data format: {}", e))?;
+
+    if !is_valid_category_name(&data.name) {
+        return Err("Category name must be 3-100 characters and contain only letters, numbers, spaces, and basic punctuation".to_string());
+    }
+
+    if !VALID_FEE_TYPES.contains(&data.fee_type.as_str()) {
+        return Err(format!(
+            "Invalid fee type '{}'. Must be one of: {}",
+            data.fee_type,
+            VALID_FEE_TYPES.join(", ")
+        ));
+    }
+
+    if let Some(amount) = data.default_amount {
+        if !amount.is_positive() || amount > MAX_FEE_CATEGORY_AMOUNT {
+            return Err(format!(
+                "defaultAmount must be greater than 0 and not exceed ₦{}",
+                MAX_FEE_CATEGORY_AMOUNT
+            ));
+        }
+    }
+
+    if let Some(ref desc) = data.description {
+        if desc.len() > 1000 {
+            return Err(format!(
+                "Category description cannot exceed 1000 characters (current length: {})",
+                desc.len()
+            ));
+        }
+    }
+
+    // Name uniqueness (case-insensitive)
+    let existing = cached_list_doc_data::<FeeCategoryData>("fee_categories")?;
+    for (doc_key, _, other) in existing.iter() {
+        if doc_key == &context.data.key {
+            continue;
+        }
+        if other.name.trim().eq_ignore_ascii_case(data.name.trim()) {
+            return Err(format!("Fee category name '{}' is already taken", data.name));
+        }
+    }
+
+    // Deactivation is blocked while fee assignments still owe on the category
+    if let Some(ref before_doc) = context.data.data.current {
+        let before: FeeCategoryData = decode_doc_data(&before_doc.data)
+            .map_err(|e| format!("Invalid previous fee category data: {}", e))?;
+        if before.is_active && !data.is_active {
+            validate_fee_category_deactivation(&context.data.key)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Fee Structure Validation
+///
+/// Checks:
+/// - Class exists; academic year is YYYY/YYYY and term is first/second/third
+/// - One structure per class, academic year and term
+/// - Items reference existing fee categories of the same type, each listed once
+/// - Item amounts are positive and within the fee category cap
+/// - totalAmount is the sum of the items
+pub fn validate_fee_structure_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: FeeStructureData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid fee structure data format: {}", e))?;
+
+    if !cached_doc_exists("classes", &data.class_id)? {
+        return Err(format!("Class '{}' not found", data.class_id));
+    }
+    if !is_valid_academic_year(&data.academic_year) {
+        return Err("academicYear must be in format YYYY/YYYY, e.g. 2024/2025".to_string());
+    }
+    if !["first", "second", "third"].contains(&data.term.as_str()) {
+        return Err("term must be 'first', 'second', or 'third'".to_string());
+    }
+
+    if data.fee_items.is_empty() {
+        return Err("feeItems cannot be empty".to_string());
+    }
+    for (i, item) in data.fee_items.iter().enumerate() {
+        let (_, category) = cached_doc_data::<FeeCategoryData>("fee_categories", &item.category_id)?
+            .ok_or_else(|| format!("Fee category '{}' not found", item.category_id))?;
+        if category.fee_type != item.fee_type {
+            return Err(format!(
+                "Fee item {} has type '{}' but its category is '{}'",
+                item.category_name, item.fee_type, category.fee_type
+            ));
+        }
+        if data.fee_items[..i].iter().any(|other| other.category_id == item.category_id) {
+            return Err(format!("Fee category '{}' is listed twice", item.category_name));
+        }
+        if !item.amount.is_positive() || item.amount > MAX_FEE_CATEGORY_AMOUNT {
+            return Err(format!(
+                "Fee item {} amount must be greater than 0 and not exceed ₦{}",
+                item.category_name, MAX_FEE_CATEGORY_AMOUNT
+            ));
+        }
+        if item.is_mandatory && item.is_optional.unwrap_or(false) {
+            return Err(format!("Fee item {} cannot be both mandatory and optional", item.category_name));
+        }
+    }
+    let items_total: Money = data.fee_items.iter().map(|item| item.amount).sum();
+    if data.total_amount != items_total {
+        return Err(format!(
+            "totalAmount ({}) must equal the sum of the fee items ({})",
+            data.total_amount, items_total
+        ));
+    }
+
+    let duplicate = list_doc_data::<FeeStructureData>("fee_structures", None)?
+        .into_iter()
+        .any(|(key, _, other)| {
+            key != context.data.key
+                && other.class_id == data.class_id
+                && other.academic_year == data.academic_year
+                && other.term == data.term
+        });
+    if duplicate {
+        return Err(format!(
+            "A fee structure already exists for this class in the {} {} term",
+            data.academic_year, data.term
+        ));
+    }
+
+    Ok(())
 }
 
 /// Validate student fee assignment document
@@ -88,6 +454,33 @@ pub fn validate_student_fee_assignment(context: &AssertSetDocContext) -> Result<
         return Err("term must be 'first', 'second', or 'third'".to_string());
     }
 
+    // Referential integrity: student, class and fee structure must exist
+    if !doc_exists("students", &data.student_id)? {
+        return Err(format!("Student '{}' not found", data.student_id));
+    }
+    if !cached_doc_exists("classes", &data.class_id)? {
+        return Err(format!("Class '{}' not found", data.class_id));
+    }
+    if !doc_exists("fee_structures", &data.fee_structure_id)? {
+        return Err(format!("Fee structure '{}' not found", data.fee_structure_id));
+    }
+
+    // Closed-out assignments only take payments, applied by the satellite
+    if !is_satellite_caller(&context.caller) {
+        let before_closed_out_at = match context.data.data.current {
+            Some(ref doc) => decode_doc_data::<StudentFeeAssignmentData>(&doc.data)
+                .map_err(|e| format!("Invalid previous fee assignment data: {}", e))?
+                .closed_out_at,
+            None => None,
+        };
+        if before_closed_out_at.is_some() {
+            return Err("Fee assignment was closed out by a promotion and cannot be changed".to_string());
+        }
+        if data.closed_out_at.is_some() {
+            return Err("Fee assignments are closed out by promote_students only".to_string());
+        }
+    }
+
     // Validate fee items
     if data.fee_items.is_empty() {
         return Err("feeItems cannot be empty".to_string());
@@ -99,7 +492,7 @@ pub fn validate_student_fee_assignment(context: &AssertSetDocContext) -> Result<
             return Err("feeItem must have categoryId".to_string());
         }
 
-        if item.amount < 0.0 {
+        if item.amount.is_negative() {
             return Err(format!("Fee item {} has negative amount", item.category_id));
         }
 
@@ -114,6 +507,11 @@ pub fn validate_student_fee_assignment(context: &AssertSetDocContext) -> Result<
         }
     }
 
+    // Items follow the fee structure; the satellite adds its own (charges, late fees)
+    if !is_satellite_caller(&context.caller) {
+        check_rule(context, "fee_structure_match", || validate_assignment_matches_structure(context, &data))?;
+    }
+
     // Validate amounts
 
     // Validate scholarship data if present
@@ -135,7 +533,7 @@ pub fn validate_student_fee_assignment(context: &AssertSetDocContext) -> Result<
         let discount_amount = data.discount_amount
             .ok_or("discountAmount is required when scholarship is applied")?;
 
-        if discount_amount < 0.0 {
+        if discount_amount.is_negative() {
             return Err("discountAmount cannot be negative".to_string());
         }
 
@@ -159,29 +557,39 @@ pub fn validate_student_fee_assignment(context: &AssertSetDocContext) -> Result<
         }
 
         // Validate total amount calculation with discount
-        let expected_total = orig_amt - discount_amount;
-        let tolerance = 0.01; // Allow small floating point differences
-        if (data.total_amount - expected_total).abs() > tolerance {
+        if data.total_amount != orig_amt - discount_amount {
             return Err(format!(
                 "totalAmount ({}) should equal originalAmount ({}) minus discountAmount ({})",
                 data.total_amount, orig_amt, discount_amount
             ));
         }
+
+        // The scholarship itself must apply when it is attached (or changed), so an
+        // assignment keeps a scholarship that later expires
+        let before_scholarship_id = match context.data.data.current {
+            Some(ref doc) => decode_doc_data::<StudentFeeAssignmentData>(&doc.data)
+                .map_err(|e| format!("Invalid previous fee assignment data: {}", e))?
+                .scholarship_id,
+            None => None,
+        };
+        if before_scholarship_id.as_ref() != Some(scholarship_id) {
+            check_rule(context, "scholarship_applicability", || {
+                validate_scholarship_applicability(scholarship_id, &data)
+            })?;
+        }
     }
 
     // Validate amounts are non-negative
-    if data.total_amount < 0.0 {
+    if data.total_amount.is_negative() {
         return Err("totalAmount cannot be negative".to_string());
     }
 
-    if data.amount_paid < 0.0 {
+    if data.amount_paid.is_negative() {
         return Err("amountPaid cannot be negative".to_string());
     }
 
     // Validate balance calculation
-    let expected_balance = data.total_amount - data.amount_paid;
-    let tolerance = 0.01;
-    if (data.balance - expected_balance).abs() > tolerance {
+    if data.balance != data.total_amount - data.amount_paid {
         return Err(format!(
             "balance ({}) must equal totalAmount ({}) minus amountPaid ({})",
             data.balance, data.total_amount, data.amount_paid
@@ -194,19 +602,19 @@ pub fn validate_student_fee_assignment(context: &AssertSetDocContext) -> Result<
     }
 
     // Validate status matches amounts
-    if data.amount_paid == 0.0 && data.status != "unpaid" {
+    if data.amount_paid.is_zero() && data.status != "unpaid" {
         return Err("status must be 'unpaid' when amountPaid is 0".to_string());
     }
 
-    if data.balance == 0.0 && data.status != "paid" {
+    if data.balance.is_zero() && data.status != "paid" {
         return Err("status must be 'paid' when balance is 0".to_string());
     }
 
-    if data.balance < 0.0 && data.status != "overpaid" {
+    if data.balance.is_negative() && data.status != "overpaid" {
         return Err("status must be 'overpaid' when balance is negative".to_string());
     }
 
-    if data.amount_paid > 0.0 && data.balance > 0.0 && data.status != "partial" {
+    if data.amount_paid.is_positive() && data.balance.is_positive() && data.status != "partial" {
         return Err("status must be 'partial' when partially paid".to_string());
     }
 
@@ -215,6 +623,25 @@ pub fn validate_student_fee_assignment(context: &AssertSetDocContext) -> Result<
         validate_iso_date(due_date)?;
     }
 
+    // Due-date policy applies when an assignment is raised
+    if context.data.data.current.is_none() {
+        let settings = school_settings()?;
+        match data.due_date {
+            None if settings.fee_due_date_required => {
+                return Err("dueDate is required for new fee assignments".to_string());
+            }
+            Some(ref due_date)
+                if days_between(&current_date(), due_date).unwrap_or(0) > settings.fee_due_days as i64 =>
+            {
+                return Err(format!(
+                    "dueDate cannot be more than {} days after the assignment is raised",
+                    settings.fee_due_days
+                ));
+            }
+            _ => {}
+        }
+    }
+
     Ok(())
 }
 
@@ -246,7 +673,7 @@ pub fn validate_scholarship(context: &AssertSetDocContext) -> Result<(), String>
         let fixed_amount_off = data.fixed_amount_off
             .ok_or("fixedAmountOff is required for fixed_amount type")?;
 
-        if fixed_amount_off <= 0.0 {
+        if !fixed_amount_off.is_positive() {
             return Err("fixedAmountOff must be greater than 0".to_string());
         }
     }
@@ -315,47 +742,105 @@ pub fn validate_scholarship(context: &AssertSetDocContext) -> Result<(), String>
     Ok(())
 }
 
-/// Validate ISO date format (YYYY-MM-DD)
-fn validate_iso_date(date_str: &str) -> Result<(), String> {
-    if date_str.len() != 10 {
-        return Err(format!("Invalid date format: {}. Expected YYYY-MM-DD", date_str));
+// The assignment is for the structure's class and term and carries its items at the
+// structure's amounts; optional items may be left out or deselected. Items the satellite
+// posted (charges, late fees) stay as they were.
+fn validate_assignment_matches_structure(
+    context: &AssertSetDocContext,
+    assignment: &StudentFeeAssignmentData,
+) -> Result<(), String> {
+    let (_, structure) = get_doc_data::<FeeStructureData>("fee_structures", &assignment.fee_structure_id)?
+        .ok_or_else(|| format!("Fee structure '{}' not found", assignment.fee_structure_id))?;
+    if structure.class_id != assignment.class_id
+        || structure.academic_year != assignment.academic_year
+        || structure.term != assignment.term
+    {
+        return Err("Fee assignment class and term must match its fee structure".to_string());
+    }
+
+    for template in structure.fee_items.iter() {
+        match assignment.fee_items.iter().find(|i| i.category_id == template.category_id) {
+            Some(item) if item.amount != template.amount || item.fee_type != template.fee_type => {
+                return Err(format!(
+                    "Fee item {} must be ₦{} ({}) as in the fee structure",
+                    template.category_name, template.amount, template.fee_type
+                ));
+            }
+            Some(item) if template.is_mandatory && item.is_selected == Some(false) => {
+                return Err(format!("Mandatory fee item {} cannot be deselected", template.category_name));
+            }
+            None if template.is_mandatory => {
+                return Err(format!("Mandatory fee item {} is missing", template.category_name));
+            }
+            _ => {}
+        }
     }
-    
-    let parts: Vec<&str> = date_str.split('-').collect();
-    if parts.len() != 3 {
-        return Err(format!("Invalid date format: {}. Expected YYYY-MM-DD", date_str));
+
+    let before_items: Vec<FeeItemData> = match context.data.data.current {
+        Some(ref doc) => decode_doc_data::<StudentFeeAssignmentData>(&doc.data)
+            .map_err(|e| format!("Invalid previous fee assignment data: {}", e))?
+            .fee_items,
+        None => Vec::new(),
+    };
+    for item in assignment.fee_items.iter() {
+        if structure.fee_items.iter().any(|t| t.category_id == item.category_id) {
+            continue;
+        }
+        let posted_by_satellite = before_items
+            .iter()
+            .any(|b| b.category_id == item.category_id && b.amount == item.amount);
+        if !posted_by_satellite {
+            return Err(format!("Fee item {} is not part of the fee structure", item.category_name));
+        }
     }
 
-    // Validate year (4 digits)
-    if parts[0].len() != 4 || !parts[0].chars().all(|c| c.is_numeric()) {
-        return Err(format!("Invalid year in date: {}", date_str));
+    Ok(())
+}
+
+// The scholarship is active, current, and covers the assignment's student or class
+fn validate_scholarship_applicability(scholarship_id: &str, assignment: &StudentFeeAssignmentData) -> Result<(), String> {
+    let (_, scholarship) = get_doc_data::<ScholarshipData>("scholarships", scholarship_id)?
+        .ok_or_else(|| format!("Scholarship '{}' not found", scholarship_id))?;
+
+    check_scholarship_applies(&scholarship, &assignment.student_id, &assignment.class_id)
+}
+
+/// The scholarship is active, current, and covers the student or their class.
+pub fn check_scholarship_applies(scholarship: &ScholarshipData, student_id: &str, class_id: &str) -> Result<(), String> {
+    if scholarship.status != "active" {
+        return Err(format!("Scholarship '{}' is {}", scholarship.name, scholarship.status));
     }
-    
-    // Validate month (2 digits)
-    if parts[1].len() != 2 || !parts[1].chars().all(|c| c.is_numeric()) {
-        return Err(format!("Invalid month in date: {}", date_str));
+    let today = current_date();
+    if today < scholarship.start_date {
+        return Err(format!("Scholarship '{}' starts on {}", scholarship.name, scholarship.start_date));
     }
-    
-    // Validate day (2 digits)
-    if parts[2].len() != 2 || !parts[2].chars().all(|c| c.is_numeric()) {
-        return Err(format!("Invalid day in date: {}", date_str));
+    if let Some(ref end_date) = scholarship.end_date {
+        if &today > end_date {
+            return Err(format!("Scholarship '{}' ended on {}", scholarship.name, end_date));
+        }
     }
 
-    let year: i32 = parts[0].parse().unwrap(); // Safe because we validated
-    let month: u32 = parts[1].parse().unwrap();
-    let day: u32 = parts[2].parse().unwrap();
-
-    if year < 1900 || year > 2100 {
-        return Err(format!("Year out of range: {}", year));
+    match scholarship.applicable_to.as_str() {
+        "specific_students" if !scholarship.student_ids.as_ref().is_some_and(|ids| ids.iter().any(|id| id == student_id)) => {
+            return Err(format!("Scholarship '{}' does not cover this student", scholarship.name));
+        }
+        "specific_classes" if !scholarship.class_ids.as_ref().is_some_and(|ids| ids.iter().any(|id| id == class_id)) => {
+            return Err(format!("Scholarship '{}' does not cover class '{}'", scholarship.name, class_id));
+        }
+        _ => {}
     }
 
-    if month < 1 || month > 12 {
-        return Err(format!("Month out of range: {}", month));
-    }
+    Ok(())
+}
 
-    if day < 1 || day > 31 {
-        return Err(format!("Day out of range: {}", day));
+/// Validate ISO date format (YYYY-MM-DD) and that the date exists in the calendar
+fn validate_iso_date(date_str: &str) -> Result<(), String> {
+    if !is_valid_date_format(date_str) {
+        return Err(format!("Invalid date: {}. Expected a calendar date as YYYY-MM-DD", date_str));
     }
 
-    Ok(())
+    match calendar_date(date_str) {
+        Some(date) if (1900..=2100).contains(&date.year()) => Ok(()),
+        _ => Err(format!("Year out of range in date: {}", date_str)),
+    }
 }
@@ -0,0 +1,305 @@
+//! FX Module - Foreign Currency Amounts and Exchange-Rate Snapshots
+//!
+//! Payments, expenses and bank transactions are in the school's base currency (the
+//! `currency` school setting) unless they name another `currency`. A foreign-currency
+//! document also carries the rate it was converted at (`fxRate`, base currency per unit)
+//! and the converted amount (`baseAmount`), so reports add up base amounts whatever the
+//! currency paid in. Fee allocations and budgets are always in the base currency.
+//!
+//! Daily rates are kept in `exchange_rates`, one snapshot per currency and day keyed
+//! `{CURRENCY}_{YYYY-MM-DD}`. A timer fetches the day's rate for each of the school's
+//! `fxCurrencies` from the IC exchange rate canister (XRC); finance staff record a
+//! `manual` rate for a currency the XRC does not quote. Snapshots are never changed. A
+//! document's rate must be within 5% of the latest snapshot on or before its date, when
+//! there is one.
+
+use candid::{CandidType, Principal};
+use ic_cdk::call::Call;
+use junobuild_satellite::{error, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+use super::roles::{caller_has_any_role, Role};
+use super::settings::school_settings;
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::{current_date, is_valid_date_format};
+
+pub const EXCHANGE_RATES_COLLECTION: &str = "exchange_rates";
+
+const XRC_CANISTER_ID: &str = "uf6dk-hyaaa-aaaaq-qaaaq-cai";
+// The XRC charges up to 1B cycles per rate; unused cycles are refunded
+const XRC_CALL_CYCLES: u128 = 1_000_000_000;
+const FX_RATE_TOLERANCE_PERCENT: f64 = 5.0;
+// Converted amounts may be off the amount × rate by a rounding kobo
+const BASE_AMOUNT_TOLERANCE_KOBO: i64 = 1;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeRateData {
+    pub currency: String,
+    pub base_currency: String,
+    // Base currency per unit of `currency`
+    pub rate: f64,
+    pub date: String,
+    // `xrc` or `manual`
+    pub source: String,
+    pub recorded_by: String,
+    pub recorded_at: u64,
+}
+
+// XRC interface (get_exchange_rate), reduced to the fields used here
+#[derive(CandidType)]
+enum AssetClass {
+    FiatCurrency,
+}
+
+#[derive(CandidType)]
+struct Asset {
+    symbol: String,
+    class: AssetClass,
+}
+
+#[derive(CandidType)]
+struct GetExchangeRateRequest {
+    base_asset: Asset,
+    quote_asset: Asset,
+    timestamp: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct ExchangeRateMetadata {
+    decimals: u32,
+}
+
+#[derive(CandidType, Deserialize)]
+struct ExchangeRate {
+    rate: u64,
+    metadata: ExchangeRateMetadata,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+struct OtherError {
+    code: u32,
+    description: String,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+enum ExchangeRateError {
+    AnonymousPrincipalNotAllowed,
+    Pending,
+    CryptoBaseAssetNotFound,
+    CryptoQuoteAssetNotFound,
+    StablecoinRateNotFound,
+    StablecoinRateTooFewRates,
+    StablecoinRateZeroRate,
+    ForexInvalidTimestamp,
+    ForexBaseAssetNotFound,
+    ForexQuoteAssetNotFound,
+    ForexAssetsNotFound,
+    RateLimited,
+    NotEnoughCycles,
+    FailedToAcceptCycles,
+    InconsistentRatesReceived,
+    Other(OtherError),
+}
+
+thread_local! {
+    // A refresh is running; later ticks skip until it is done
+    static REFRESH_IN_FLIGHT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Exchange Rate Validation
+///
+/// Checks:
+/// - Finance staff record manual rates; the satellite records XRC rates
+/// - Keyed by currency and date, for a foreign currency against the base currency
+/// - The rate is positive
+/// - Snapshots are never changed
+pub fn validate_exchange_rate_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+        return Err("SECURITY: Only finance staff can record exchange rates".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Exchange rate snapshots cannot be changed".to_string());
+    }
+
+    let data: ExchangeRateData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid exchange rate data format: {}", e))?;
+
+    let base_currency = school_settings()?.currency;
+    if !is_currency_code(&data.currency) || data.currency == base_currency {
+        return Err(format!("Exchange rates are for a foreign currency code, not {}", base_currency));
+    }
+    if data.base_currency != base_currency {
+        return Err(format!("Exchange rates are quoted in the base currency, {}", base_currency));
+    }
+    if !is_valid_date_format(&data.date) {
+        return Err("Exchange rate date must be in format YYYY-MM-DD".to_string());
+    }
+    if context.data.key != format!("{}_{}", data.currency, data.date) {
+        return Err(format!("Exchange rate must use the key '{}_{}'", data.currency, data.date));
+    }
+    if !data.rate.is_finite() || data.rate <= 0.0 {
+        return Err("Exchange rate must be greater than 0".to_string());
+    }
+    if data.source != "manual" {
+        return Err("Rates recorded by staff must have source 'manual'".to_string());
+    }
+    if data.recorded_by != context.caller.to_text() {
+        return Err("recordedBy must be the principal recording the rate".to_string());
+    }
+
+    Ok(())
+}
+
+/// Exchange rate snapshots back converted amounts and cannot be deleted.
+pub fn validate_exchange_rate_delete() -> Result<(), String> {
+    Err("AUDIT: Exchange rate snapshots cannot be deleted".to_string())
+}
+
+/// Foreign Currency Amount Validation
+///
+/// Checks:
+/// - A document in the base currency (or without a currency) carries no rate
+/// - A foreign-currency document carries its rate and base amount, and the base amount is
+///   the amount at that rate
+/// - The rate is within 5% of the latest snapshot on or before the document's date
+pub fn validate_foreign_amount(
+    currency: Option<&str>,
+    amount: Money,
+    fx_rate: Option<f64>,
+    base_amount: Option<Money>,
+    date: &str,
+) -> Result<(), String> {
+    let base_currency = school_settings()?.currency;
+    let currency = match currency {
+        Some(currency) if currency != base_currency => currency,
+        _ => {
+            if fx_rate.is_some() || base_amount.is_some() {
+                return Err(format!("fxRate and baseAmount are only set for amounts not in {}", base_currency));
+            }
+            return Ok(());
+        }
+    };
+
+    if !is_currency_code(currency) {
+        return Err(format!("Invalid currency '{}'. Must be a three-letter ISO code, e.g. USD", currency));
+    }
+    let (rate, base_amount) = match (fx_rate, base_amount) {
+        (Some(rate), Some(base_amount)) => (rate, base_amount),
+        _ => return Err(format!("Amounts in {} need an fxRate and a baseAmount in {}", currency, base_currency)),
+    };
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err("fxRate must be greater than 0".to_string());
+    }
+    let converted = Money::from_naira(amount.naira() * rate);
+    if (converted - base_amount).kobo().abs() > BASE_AMOUNT_TOLERANCE_KOBO {
+        return Err(format!(
+            "baseAmount {} {} does not match {} {} at {}",
+            base_currency, base_amount, currency, amount, rate
+        ));
+    }
+
+    if let Some(snapshot) = rate_on(currency, date)? {
+        if (rate - snapshot.rate).abs() > snapshot.rate * FX_RATE_TOLERANCE_PERCENT / 100.0 {
+            return Err(format!(
+                "fxRate {} is more than {}% off the {} rate of {} on {}",
+                rate, FX_RATE_TOLERANCE_PERCENT, currency, snapshot.rate, snapshot.date
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The amount in the base currency: the converted amount of a foreign-currency document.
+pub fn in_base_currency(amount: Money, base_amount: Option<Money>) -> Money {
+    base_amount.unwrap_or(amount)
+}
+
+/// The latest snapshot for `currency` on or before `date`.
+pub fn rate_on(currency: &str, date: &str) -> Result<Option<ExchangeRateData>, String> {
+    Ok(list_doc_data::<ExchangeRateData>(EXCHANGE_RATES_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, r)| r.currency == currency && r.date.as_str() <= date)
+        .max_by(|(_, _, a), (_, _, b)| a.date.cmp(&b.date))
+        .map(|(_, _, r)| r))
+}
+
+/// Record today's rate for each of the school's foreign currencies that has none yet.
+/// Run by the exchange rate timer; a currency the XRC cannot quote is logged and skipped.
+pub async fn refresh_exchange_rates() -> Result<(), String> {
+    if REFRESH_IN_FLIGHT.with(|f| f.replace(true)) {
+        return Ok(());
+    }
+    let result = refresh().await;
+    REFRESH_IN_FLIGHT.with(|f| f.set(false));
+    result
+}
+
+async fn refresh() -> Result<(), String> {
+    let settings = school_settings()?;
+    let today = current_date();
+
+    for currency in settings.fx_currencies.iter() {
+        let key = format!("{}_{}", currency, today);
+        if doc_exists(EXCHANGE_RATES_COLLECTION, &key)? {
+            continue;
+        }
+        let rate = match fetch_rate(currency, &settings.currency).await {
+            Ok(rate) => rate,
+            Err(e) => {
+                let _ = error(format!("Exchange rate for {} not refreshed: {}", currency, e));
+                continue;
+            }
+        };
+        let snapshot = ExchangeRateData {
+            currency: currency.clone(),
+            base_currency: settings.currency.clone(),
+            rate,
+            date: today.clone(),
+            source: "xrc".to_string(),
+            recorded_by: junobuild_satellite::id().to_text(),
+            recorded_at: ic_cdk::api::time(),
+        };
+        // Re-check: a manual rate may have been recorded while the call was made
+        if !doc_exists(EXCHANGE_RATES_COLLECTION, &key)? {
+            set_doc_data(EXCHANGE_RATES_COLLECTION, &key, &snapshot, Some(format!("currency={};", currency)), None)?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn fetch_rate(currency: &str, base_currency: &str) -> Result<f64, String> {
+    let xrc = Principal::from_text(XRC_CANISTER_ID).map_err(|e| format!("Invalid XRC canister id: {}", e))?;
+    let request = GetExchangeRateRequest {
+        base_asset: Asset { symbol: currency.to_string(), class: AssetClass::FiatCurrency },
+        quote_asset: Asset { symbol: base_currency.to_string(), class: AssetClass::FiatCurrency },
+        timestamp: None,
+    };
+
+    let result: Result<ExchangeRate, ExchangeRateError> = Call::unbounded_wait(xrc, "get_exchange_rate")
+        .with_arg(request)
+        .with_cycles(XRC_CALL_CYCLES)
+        .await
+        .map_err(|e| format!("XRC could not be reached: {}", e))?
+        .candid()
+        .map_err(|e| format!("Invalid XRC response: {}", e))?;
+    let rate = result.map_err(|e| match e {
+        ExchangeRateError::Other(OtherError { code, description }) => format!("XRC error {}: {}", code, description),
+        other => format!("XRC error: {:?}", other),
+    })?;
+
+    Ok(rate.rate as f64 / 10f64.powi(rate.metadata.decimals as i32))
+}
+
+/// A three-letter ISO 4217 code, e.g. USD.
+pub fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase())
+}
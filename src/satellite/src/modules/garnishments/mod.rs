@@ -0,0 +1,304 @@
+//! Garnishments Module - Court-Order Deduction Compliance
+//!
+//! Court orders (garnishee orders) instruct the school to withhold a fixed amount from a
+//! staff member's salary each period until a total cap is reached. This module enforces:
+//! - Order records are complete (reference, per-period amount, total cap)
+//! - Salary payments apply each active order exactly as ordered
+//! - Deductions stop automatically once the cap is reached
+//! - The running deducted total is maintained by the satellite only
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::staff::{PaymentDeductionItem, SalaryPaymentData};
+use super::utils::doc_utils::*;
+use super::utils::identity::validate_caller_identity;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const COURT_ORDERS_COLLECTION: &str = "court_orders";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CourtOrderData {
+    pub staff_id: String,
+    pub order_reference: String,
+    pub court_name: String,
+    pub beneficiary: String,
+    pub deduction_name: String,
+    pub effective_date: String,
+    pub amount_per_period: Money,
+    pub total_cap: Money,
+    #[serde(default)]
+    pub amount_deducted: Money,
+    pub status: String,
+    pub notes: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Court Order Validation
+///
+/// Checks:
+/// - Unique order reference
+/// - Positive per-period amount not exceeding the total cap
+/// - Running total only moved by the satellite and never above the cap
+/// - Status reflects whether the cap has been reached
+pub fn validate_court_order_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: CourtOrderData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid court order data format: {}", e))?;
+
+    // Court orders are recorded under the caller's own identity
+    validate_caller_identity(context)?;
+
+    if data.staff_id.trim().is_empty() {
+        return Err("staffId is required".to_string());
+    }
+    if data.order_reference.trim().is_empty() {
+        return Err("Court order reference is required".to_string());
+    }
+    if data.court_name.trim().is_empty() || data.beneficiary.trim().is_empty() {
+        return Err("Court name and beneficiary are required".to_string());
+    }
+    if data.deduction_name.trim().is_empty() {
+        return Err("deductionName is required to match salary deductions".to_string());
+    }
+    if !is_valid_date_format(&data.effective_date) {
+        return Err("Invalid effective date format. Must be YYYY-MM-DD".to_string());
+    }
+
+    if !data.amount_per_period.is_positive() {
+        return Err("amountPerPeriod must be greater than zero".to_string());
+    }
+    if data.total_cap < data.amount_per_period {
+        return Err("totalCap cannot be less than amountPerPeriod".to_string());
+    }
+    if data.amount_deducted.is_negative() || data.amount_deducted > data.total_cap {
+        return Err("amountDeducted must be between 0 and totalCap".to_string());
+    }
+
+    let valid_statuses = ["active", "suspended", "satisfied", "revoked"];
+    if !valid_statuses.contains(&data.status.as_str()) {
+        return Err(format!(
+            "Invalid court order status '{}'. Must be one of: {}",
+            data.status,
+            valid_statuses.join(", ")
+        ));
+    }
+
+    let cap_reached = data.amount_deducted >= data.total_cap;
+    if cap_reached && data.status == "active" {
+        return Err("Court order has reached its cap and must be 'satisfied'".to_string());
+    }
+    if !cap_reached && data.status == "satisfied" {
+        return Err("Court order cannot be 'satisfied' before its cap is reached".to_string());
+    }
+    if data.status == "revoked" && data.notes.as_ref().map(|n| n.trim().is_empty()).unwrap_or(true) {
+        return Err("Revoked court orders must include the revocation details in notes".to_string());
+    }
+
+    let is_satellite = is_satellite_caller(&context.caller);
+    match context.data.data.current {
+        Some(ref before_doc) => {
+            let before: CourtOrderData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous court order data: {}", e))?;
+
+            if !is_satellite && before.amount_deducted != data.amount_deducted {
+                return Err("AUDIT: amountDeducted is maintained automatically from paid salaries".to_string());
+            }
+            if before.staff_id != data.staff_id || before.deduction_name != data.deduction_name {
+                return Err("Court order staff and deduction name cannot be changed".to_string());
+            }
+            if before.status == "revoked" && data.status != "revoked" {
+                return Err("Revoked court orders cannot be reinstated".to_string());
+            }
+        }
+        None => {
+            if !data.amount_deducted.is_zero() {
+                return Err("New court orders must start with amountDeducted of 0".to_string());
+            }
+        }
+    }
+
+    let duplicate = list_doc_data::<CourtOrderData>(COURT_ORDERS_COLLECTION, None)?
+        .into_iter()
+        .any(|(key, _, order)| key != context.data.key && order.order_reference == data.order_reference);
+    if duplicate {
+        return Err(format!("Court order '{}' already exists", data.order_reference));
+    }
+
+    Ok(())
+}
+
+/// Every active court order for the staff member must appear in the salary deductions
+/// with exactly the ordered amount (or the remainder of the cap), and orders that are
+/// satisfied, suspended or revoked must not be deducted. Checked when the deductions are
+/// set or changed: an order a paid salary has since satisfied leaves that salary's
+/// deduction standing for later status updates and archiving.
+pub fn validate_salary_court_order_deductions(
+    context: &AssertSetDocContext,
+    salary: &SalaryPaymentData,
+) -> Result<(), String> {
+    if let Some(ref doc) = context.data.data.current {
+        let before: SalaryPaymentData = decode_doc_data(&doc.data)
+            .map_err(|e| format!("Invalid previous salary data: {}", e))?;
+        if before.deductions == salary.deductions {
+            return Ok(());
+        }
+    }
+
+    let salary_key = context.data.key.as_str();
+    let orders = list_doc_data::<CourtOrderData>(COURT_ORDERS_COLLECTION, None)?;
+
+    for (_, _, order) in orders.iter().filter(|(_, _, o)| o.staff_id == salary.staff_id) {
+        let applied = salary
+            .deductions
+            .iter()
+            .find(|d| d.name.eq_ignore_ascii_case(&order.deduction_name));
+
+        let in_force = is_in_force(salary, order);
+        let expected = expected_deduction(salary_key, salary, order)?;
+
+        match applied {
+            Some(deduction) if !expected.is_positive() => {
+                return Err(format!(
+                    "Deduction '{}' must not be applied: court order {} is {}",
+                    deduction.name,
+                    order.order_reference,
+                    if in_force { "fully satisfied" } else { "not in force" }
+                ));
+            }
+            Some(deduction) => {
+                if deduction.amount != expected {
+                    return Err(format!(
+                        "Court order {} requires a deduction of exactly ₦{}, found ₦{}",
+                        order.order_reference, expected, deduction.amount
+                    ));
+                }
+                if !deduction.is_statutory {
+                    return Err(format!(
+                        "Court order deduction '{}' must be marked as statutory",
+                        deduction.name
+                    ));
+                }
+            }
+            None if expected.is_positive() => {
+                return Err(format!(
+                    "Court order {} requires a deduction '{}' of ₦{}",
+                    order.order_reference, order.deduction_name, expected
+                ));
+            }
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Called from the `salary_payments` on-set hook once a salary is paid: recomputes the
+/// running total for each of the staff member's orders and marks them satisfied at the cap.
+pub fn sync_court_orders_with_salary_payment(salary: &SalaryPaymentData) -> Result<(), String> {
+    if salary.status != "paid" {
+        return Ok(());
+    }
+
+    let orders = list_doc_data::<CourtOrderData>(COURT_ORDERS_COLLECTION, None)?;
+
+    let salaries = list_doc_data::<SalaryPaymentData>("salary_payments", None)?;
+
+    for (key, doc, mut order) in orders.into_iter().filter(|(_, _, o)| o.staff_id == salary.staff_id) {
+        let total: Money = salaries
+            .iter()
+            .filter(|(_, _, s)| s.staff_id == order.staff_id && s.status == "paid")
+            .flat_map(|(_, _, s)| s.deductions.iter())
+            .filter(|d| d.name.eq_ignore_ascii_case(&order.deduction_name))
+            .map(|d| d.amount)
+            .sum();
+
+        if total == order.amount_deducted {
+            continue;
+        }
+
+        order.amount_deducted = total.min(order.total_cap);
+        if order.amount_deducted >= order.total_cap && order.status == "active" {
+            order.status = "satisfied".to_string();
+        }
+        order.updated_at = ic_cdk::api::time();
+
+        set_doc_data(
+            COURT_ORDERS_COLLECTION,
+            &key,
+            &order,
+            Some(court_order_description(&order)),
+            doc.version,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Court-order deductions a salary payment must carry, for building salaries server-side.
+pub fn due_court_order_deductions(
+    salary_key: &str,
+    salary: &SalaryPaymentData,
+) -> Result<Vec<PaymentDeductionItem>, String> {
+    let orders = list_doc_data::<CourtOrderData>(COURT_ORDERS_COLLECTION, None)?;
+
+    let mut deductions = Vec::new();
+    for (_, _, order) in orders.iter().filter(|(_, _, o)| o.staff_id == salary.staff_id) {
+        let amount = expected_deduction(salary_key, salary, order)?;
+        if amount.is_positive() {
+            deductions.push(PaymentDeductionItem {
+                name: order.deduction_name.clone(),
+                amount,
+                is_statutory: true,
+            });
+        }
+    }
+
+    Ok(deductions)
+}
+
+fn is_in_force(salary: &SalaryPaymentData, order: &CourtOrderData) -> bool {
+    order.status == "active" && order.effective_date <= salary.payment_period_end
+}
+
+// Ordered amount for this salary, or what remains of the cap after the staff member's
+// other paid or approved salaries
+fn expected_deduction(salary_key: &str, salary: &SalaryPaymentData, order: &CourtOrderData) -> Result<Money, String> {
+    if !is_in_force(salary, order) {
+        return Ok(Money::ZERO);
+    }
+    let deducted_elsewhere = deducted_in_other_payments(salary_key, salary, order)?;
+    Ok((order.total_cap - deducted_elsewhere).clamp(Money::ZERO, order.amount_per_period))
+}
+
+fn deducted_in_other_payments(
+    salary_key: &str,
+    salary: &SalaryPaymentData,
+    order: &CourtOrderData,
+) -> Result<Money, String> {
+    let salaries = list_doc_data::<SalaryPaymentData>("salary_payments", None)?;
+
+    Ok(salaries
+        .iter()
+        .filter(|(key, _, s)| {
+            key != salary_key && s.staff_id == salary.staff_id && (s.status == "paid" || s.status == "approved")
+        })
+        .flat_map(|(_, _, s)| s.deductions.iter())
+        .filter(|d| d.name.eq_ignore_ascii_case(&order.deduction_name))
+        .map(|d| d.amount)
+        .sum())
+}
+
+fn court_order_description(order: &CourtOrderData) -> String {
+    format!(
+        "staff_id={};order_reference={};status={};",
+        order.staff_id, order.order_reference, order.status
+    )
+}
@@ -0,0 +1,316 @@
+//! Gateway Module - Online Payment Verification
+//!
+//! Online payments carry the gateway's transaction id. Before one can be confirmed the
+//! satellite asks the gateway itself whether the transaction succeeded, through an HTTPS
+//! outcall to the provider's verify endpoint:
+//! - Paystack: `https://api.paystack.co/transaction/verify/{transaction_id}`
+//! - Flutterwave: `https://api.flutterwave.com/v3/transactions/{transaction_id}/verify`
+//!
+//! Endpoints and secret keys are configured per provider in `gateway_settings` (create
+//! the collection with controller-only read access: it holds secret keys). Verification
+//! runs when an online payment is saved, and on demand through `verify_payment`; the
+//! outcome is kept in `gateway_verifications`, keyed by transaction id. A payment is
+//! confirmed only against a successful verification of the same amount for that payment.
+
+use candid::CandidType;
+use ic_cdk::management_canister::{
+    http_request, transform_context_from_query, HttpHeader, HttpMethod, HttpRequestArgs, HttpRequestResult,
+    TransformArgs,
+};
+use junobuild_satellite::{caller, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::payments::PaymentData;
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+
+pub const GATEWAY_SETTINGS_COLLECTION: &str = "gateway_settings";
+pub const GATEWAY_VERIFICATIONS_COLLECTION: &str = "gateway_verifications";
+
+// Supported providers, in the order tried when a payment does not name one
+const PROVIDERS: [&str; 2] = ["paystack", "flutterwave"];
+const TRANSACTION_ID_PLACEHOLDER: &str = "{transaction_id}";
+const MAX_RESPONSE_BYTES: u64 = 10_000;
+// Name of the query endpoint in lib.rs that strips responses down to the fields compared
+const TRANSFORM_METHOD: &str = "transform_gateway_response";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewaySettingsData {
+    pub verify_url: String,
+    pub secret_key: String,
+    pub is_active: bool,
+    pub updated_by: String,
+    pub updated_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayVerificationData {
+    pub provider: String,
+    pub transaction_id: String,
+    pub payment_id: String,
+    pub status: String,
+    pub amount: Money,
+    pub currency: Option<String>,
+    pub gateway_reference: Option<String>,
+    pub verified_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct GatewayVerification {
+    pub provider: String,
+    pub transaction_id: String,
+    pub payment_id: String,
+    pub status: String,
+    pub amount: f64,
+    pub verified: bool,
+}
+
+// The provider response after the transform: identical on every replica
+#[derive(Deserialize, Serialize)]
+struct VerifiedTransaction {
+    status: String,
+    amount: i64,
+    currency: Option<String>,
+    reference: Option<String>,
+}
+
+/// Gateway Settings Validation
+///
+/// Checks:
+/// - Only administrators configure gateways
+/// - The key is a supported provider
+/// - The verify URL is HTTPS and contains the `{transaction_id}` placeholder
+/// - A secret key is set
+pub fn validate_gateway_settings_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only administrators can configure payment gateways".to_string());
+    }
+
+    let data: GatewaySettingsData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid gateway settings data format: {}", e))?;
+
+    if !PROVIDERS.contains(&context.data.key.as_str()) {
+        return Err(format!("Unknown payment gateway '{}'. Must be one of: {}", context.data.key, PROVIDERS.join(", ")));
+    }
+    if !data.verify_url.starts_with("https://") || !data.verify_url.contains(TRANSACTION_ID_PLACEHOLDER) {
+        return Err(format!(
+            "Verify URL must be an https:// URL containing {}",
+            TRANSACTION_ID_PLACEHOLDER
+        ));
+    }
+    if data.secret_key.trim().is_empty() {
+        return Err("Gateway secret key is required".to_string());
+    }
+    if data.updated_by != context.caller.to_text() {
+        return Err("updatedBy must be the principal configuring the gateway".to_string());
+    }
+
+    Ok(())
+}
+
+/// Verification outcomes are written by the satellite only.
+pub fn validate_gateway_verification_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Gateway verifications are recorded by the satellite".to_string());
+    }
+    Ok(())
+}
+
+/// An online payment can only be confirmed against a successful gateway verification of
+/// its transaction, for this payment and amount.
+pub fn validate_online_payment_confirmation(payment_id: &str, payment: &PaymentData) -> Result<(), String> {
+    let transaction_id = payment
+        .transaction_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .ok_or("Online payments must include the gateway transaction id")?;
+
+    let (_, verification) =
+        get_doc_data::<GatewayVerificationData>(GATEWAY_VERIFICATIONS_COLLECTION, transaction_id)?.ok_or_else(|| {
+            format!("SECURITY: Transaction '{}' has not been verified with the payment gateway", transaction_id)
+        })?;
+
+    if verification.payment_id != payment_id {
+        return Err(format!(
+            "SECURITY: Transaction '{}' was verified for another payment",
+            transaction_id
+        ));
+    }
+    if verification.status != "success" {
+        return Err(format!(
+            "SECURITY: The gateway reports transaction '{}' as '{}'; only successful transactions can be confirmed",
+            transaction_id, verification.status
+        ));
+    }
+    if verification.amount != payment.amount {
+        return Err(format!(
+            "SECURITY: The gateway reports ₦{} for transaction '{}', but the payment is for ₦{}",
+            verification.amount, transaction_id, payment.amount
+        ));
+    }
+
+    Ok(())
+}
+
+/// Called from the `payments` on-set hook: verifies pending online payments with the
+/// gateway so they are ready to confirm.
+pub async fn on_online_payment_saved(key: &str, payment: &PaymentData) -> Result<(), String> {
+    if payment.payment_method != "online" || payment.status != "pending" || payment.transaction_id.is_none() {
+        return Ok(());
+    }
+    verify_with_gateway(key, payment).await.map(|_| ())
+}
+
+/// Verify an online payment with its gateway, e.g. when the gateway was unreachable at
+/// the time the payment was saved.
+pub async fn verify_payment(payment_id: String) -> Result<GatewayVerification, String> {
+    if !caller_has_any_role(&caller(), &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+        return Err("SECURITY: Only finance officers can verify gateway payments".to_string());
+    }
+
+    let (_, payment) = get_doc_data::<PaymentData>("payments", &payment_id)?
+        .ok_or_else(|| format!("Payment '{}' not found", payment_id))?;
+    if payment.payment_method != "online" {
+        return Err("Only online payments are verified with a gateway".to_string());
+    }
+
+    verify_with_gateway(&payment_id, &payment).await
+}
+
+/// Transform for the verify outcall: keeps the status, amount (in kobo), currency and
+/// reference and drops the headers, so all replicas agree on the response.
+pub fn transform_response(args: TransformArgs) -> HttpRequestResult {
+    let provider = String::from_utf8(args.context).unwrap_or_default();
+    let body = serde_json::from_slice::<serde_json::Value>(&args.response.body)
+        .ok()
+        .and_then(|body| parse_verify_response(&provider, &body))
+        .and_then(|transaction| serde_json::to_vec(&transaction).ok())
+        .unwrap_or_default();
+
+    HttpRequestResult { status: args.response.status, headers: Vec::new(), body }
+}
+
+async fn verify_with_gateway(payment_id: &str, payment: &PaymentData) -> Result<GatewayVerification, String> {
+    let transaction_id = payment
+        .transaction_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .ok_or("Online payments must include the gateway transaction id")?
+        .to_string();
+
+    let existing = get_doc_data::<GatewayVerificationData>(GATEWAY_VERIFICATIONS_COLLECTION, &transaction_id)?;
+    if let Some((_, ref verification)) = existing {
+        if verification.payment_id != payment_id && verification.status == "success" {
+            return Err(format!(
+                "SECURITY: Transaction '{}' was already verified for payment '{}'",
+                transaction_id, verification.payment_id
+            ));
+        }
+    }
+
+    let (provider, settings) = gateway_for(payment)?;
+    let request = HttpRequestArgs {
+        url: settings.verify_url.replace(TRANSACTION_ID_PLACEHOLDER, &transaction_id),
+        max_response_bytes: Some(MAX_RESPONSE_BYTES),
+        method: HttpMethod::GET,
+        headers: vec![
+            HttpHeader { name: "Authorization".to_string(), value: format!("Bearer {}", settings.secret_key) },
+            HttpHeader { name: "Accept".to_string(), value: "application/json".to_string() },
+        ],
+        body: None,
+        transform: Some(transform_context_from_query(TRANSFORM_METHOD.to_string(), provider.as_bytes().to_vec())),
+    };
+
+    let response = http_request(&request)
+        .await
+        .map_err(|e| format!("Payment gateway '{}' could not be reached: {:?}", provider, e))?;
+
+    let (status, amount, currency, reference) = match serde_json::from_slice::<VerifiedTransaction>(&response.body) {
+        Ok(transaction) if response.status == 200u32 => (
+            transaction.status,
+            Money::from_kobo(transaction.amount),
+            transaction.currency,
+            transaction.reference,
+        ),
+        // Unknown transaction ids are answered with 4xx; anything unreadable is not a success
+        _ => ("not_found".to_string(), Money::ZERO, None, None),
+    };
+    let status = if currency.as_deref().map(|c| c != "NGN").unwrap_or(false) {
+        "currency_mismatch".to_string()
+    } else {
+        status
+    };
+
+    let verification = GatewayVerificationData {
+        provider: provider.clone(),
+        transaction_id: transaction_id.clone(),
+        payment_id: payment_id.to_string(),
+        status,
+        amount,
+        currency,
+        gateway_reference: reference,
+        verified_at: ic_cdk::api::time(),
+    };
+    set_doc_data(
+        GATEWAY_VERIFICATIONS_COLLECTION,
+        &transaction_id,
+        &verification,
+        Some(format!("payment_id={};", payment_id)),
+        existing.and_then(|(doc, _)| doc.version),
+    )?;
+
+    Ok(GatewayVerification {
+        verified: verification.status == "success" && verification.amount == payment.amount,
+        provider,
+        transaction_id,
+        payment_id: payment_id.to_string(),
+        status: verification.status,
+        amount: verification.amount.naira(),
+    })
+}
+
+// The payment's own gateway if it names one, otherwise the first active gateway
+fn gateway_for(payment: &PaymentData) -> Result<(String, GatewaySettingsData), String> {
+    let candidates: Vec<&str> = match payment.gateway.as_deref() {
+        Some(provider) => vec![provider],
+        None => PROVIDERS.to_vec(),
+    };
+    for provider in candidates {
+        if let Some((_, settings)) = get_doc_data::<GatewaySettingsData>(GATEWAY_SETTINGS_COLLECTION, provider)? {
+            if settings.is_active {
+                return Ok((provider.to_string(), settings));
+            }
+        }
+    }
+    Err("No active payment gateway is configured".to_string())
+}
+
+// Paystack: { data: { status: "success", amount: <kobo>, currency, reference } }
+// Flutterwave: { data: { status: "successful", amount: <naira>, currency, tx_ref } }
+fn parse_verify_response(provider: &str, body: &serde_json::Value) -> Option<VerifiedTransaction> {
+    let data = body.get("data")?;
+    let status = data.get("status")?.as_str()?;
+    let currency = data.get("currency").and_then(|c| c.as_str()).map(str::to_string);
+
+    match provider {
+        "paystack" => Some(VerifiedTransaction {
+            status: status.to_string(),
+            amount: data.get("amount")?.as_i64()?,
+            currency,
+            reference: data.get("reference").and_then(|r| r.as_str()).map(str::to_string),
+        }),
+        "flutterwave" => Some(VerifiedTransaction {
+            status: if status == "successful" { "success" } else { status }.to_string(),
+            amount: Money::from_naira(data.get("amount")?.as_f64()?).kobo(),
+            currency,
+            reference: data.get("tx_ref").and_then(|r| r.as_str()).map(str::to_string),
+        }),
+        _ => None,
+    }
+}
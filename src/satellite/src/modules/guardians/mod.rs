@@ -0,0 +1,119 @@
+//! Guardians Module - Parents and Guardians
+//!
+//! A guardian document in `guardians` links a parent or guardian to their children
+//! through `studentIds`. Family invoicing reads the linkage to bill all of a guardian's
+//! children together. This module enforces:
+//! - Guardians carry a name, a valid phone number and relationship, and a valid email
+//!   when one is given
+//! - Linked students exist
+//! - An active student always keeps at least one guardian: the last guardian linked to
+//!   an active student cannot be deleted
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::students::StudentData;
+use super::utils::doc_utils::*;
+use super::utils::validation_utils::*;
+
+pub const GUARDIANS_COLLECTION: &str = "guardians";
+
+pub const VALID_RELATIONSHIPS: [&str; 4] = ["father", "mother", "guardian", "other"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardianData {
+    pub surname: String,
+    pub firstname: String,
+    pub phone: String,
+    pub email: Option<String>,
+    pub relationship: String,
+    pub student_ids: Vec<String>,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Guardian Validation
+///
+/// Checks:
+/// - Surname and first name are present
+/// - Phone number is a valid Nigerian number
+/// - Email is valid when present
+/// - Relationship is one of father, mother, guardian, other
+/// - Linked students exist and are listed once
+pub fn validate_guardian_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: GuardianData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid guardian data format: {}", e))?;
+
+    if data.surname.trim().is_empty() || data.firstname.trim().is_empty() {
+        return Err("Guardian surname and first name are required".to_string());
+    }
+    if !is_valid_phone_number(&data.phone) {
+        return Err(format!("Invalid phone number '{}'", data.phone));
+    }
+    if let Some(ref email) = data.email {
+        if !email.trim().is_empty() && !is_valid_email(email) {
+            return Err(format!("Invalid email address '{}'", email));
+        }
+    }
+    if !VALID_RELATIONSHIPS.contains(&data.relationship.as_str()) {
+        return Err(format!(
+            "Invalid relationship '{}'. Must be one of: {}",
+            data.relationship,
+            VALID_RELATIONSHIPS.join(", ")
+        ));
+    }
+
+    // Students already linked were checked when they were added
+    let linked_before: Vec<String> = match context.data.data.current {
+        Some(ref doc) => decode_doc_data::<GuardianData>(&doc.data)
+            .map(|before| before.student_ids)
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    for (i, student_id) in data.student_ids.iter().enumerate() {
+        if data.student_ids[..i].contains(student_id) {
+            return Err(format!("Student '{}' is linked more than once", student_id));
+        }
+        if !linked_before.contains(student_id) && !doc_exists("students", student_id)? {
+            return Err(format!("Student '{}' not found", student_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// A guardian cannot be deleted while they are the only guardian of an active student.
+pub fn validate_guardian_delete(key: &str) -> Result<(), String> {
+    let guardians = list_doc_data::<GuardianData>(GUARDIANS_COLLECTION, None)?;
+    let Some((_, _, guardian)) = guardians.iter().find(|(k, _, _)| k == key) else {
+        return Ok(());
+    };
+
+    let mut sole_contact_for = Vec::new();
+    for student_id in guardian.student_ids.iter() {
+        let has_other_guardian = guardians
+            .iter()
+            .any(|(k, _, other)| k != key && other.student_ids.contains(student_id));
+        if has_other_guardian {
+            continue;
+        }
+        let is_active = get_doc_data::<StudentData>("students", student_id)?
+            .map(|(_, student)| student.is_active.unwrap_or(true))
+            .unwrap_or(false);
+        if is_active {
+            sole_contact_for.push(student_id.clone());
+        }
+    }
+
+    if !sole_contact_for.is_empty() {
+        return Err(format!(
+            "Cannot delete guardian '{}': sole guardian of active students [{}]",
+            key,
+            sole_contact_for.join(", ")
+        ));
+    }
+    Ok(())
+}
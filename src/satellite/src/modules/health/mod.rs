@@ -0,0 +1,108 @@
+//! Health Module - One Status for Operations
+//!
+//! `health` gathers the state of the satellite's moving parts into one answer, so an
+//! operations dashboard can poll a single query:
+//! - The last run of each timer job (see [`super::jobs`])
+//! - The replication outbox backlog and last push error
+//! - Notifications waiting to be sent and those that failed
+//! - Bank transactions not yet reconciled and open suspense items
+//! - Category usage counters that have drifted from a recount
+//! - The canister's cycle balance
+//!
+//! Anything needing attention is listed in `issues`, and the status is then `degraded`.
+
+use candid::CandidType;
+use junobuild_satellite::caller;
+use serde::{Deserialize, Serialize};
+
+use super::banking::BankTransactionData;
+use super::category_usage::count_usage_drift;
+use super::jobs::{job_statuses, JobStatus};
+use super::notifications::{NotificationData, NOTIFICATION_QUEUE_COLLECTION};
+use super::reconciliation::suspense::{SuspenseItemData, SUSPENSE_ITEMS_COLLECTION};
+use super::replication::{get_replication_status, ReplicationStatus};
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::list_doc_data;
+
+// Below this the canister should be topped up (1T cycles)
+const LOW_CYCLES_THRESHOLD: u128 = 1_000_000_000_000;
+// A replication backlog this long means pushes are not keeping up
+const OUTBOX_BACKLOG_THRESHOLD: u32 = 1_000;
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct HealthStatus {
+    // `ok` or `degraded`
+    pub status: String,
+    pub issues: Vec<String>,
+    pub checked_at: u64,
+    pub jobs: Vec<JobStatus>,
+    pub replication: ReplicationStatus,
+    pub notifications_queued: u32,
+    pub notifications_failed: u32,
+    pub unreconciled_bank_transactions: u32,
+    pub open_suspense_items: u32,
+    pub category_usage_drift: u32,
+    pub cycle_balance: u128,
+}
+
+/// The satellite's health: each subsystem's state and the issues found.
+pub fn health() -> Result<HealthStatus, String> {
+    if !caller_has_any_role(&caller(), &[Role::SuperAdmin, Role::Auditor]) {
+        return Err("SECURITY: Only administrators and auditors can view system health".to_string());
+    }
+
+    let jobs = job_statuses();
+    let replication = get_replication_status()?;
+    let notifications = list_doc_data::<NotificationData>(NOTIFICATION_QUEUE_COLLECTION, None)?;
+    let notifications_queued = notifications.iter().filter(|(_, _, n)| n.status == "queued").count() as u32;
+    let notifications_failed = notifications.iter().filter(|(_, _, n)| n.status == "failed").count() as u32;
+    let unreconciled_bank_transactions = list_doc_data::<BankTransactionData>("bank_transactions", None)?
+        .iter()
+        .filter(|(_, _, t)| t.status != "reconciled" && !t.is_reconciled.unwrap_or(false))
+        .count() as u32;
+    let open_suspense_items = list_doc_data::<SuspenseItemData>(SUSPENSE_ITEMS_COLLECTION, None)?
+        .iter()
+        .filter(|(_, _, item)| item.status == "open")
+        .count() as u32;
+    let category_usage_drift = count_usage_drift()?;
+    let cycle_balance = ic_cdk::api::canister_cycle_balance();
+
+    let mut issues = Vec::new();
+    for job in jobs.iter() {
+        if let Some(ref e) = job.last_error {
+            issues.push(format!("Job '{}' failed on its last run: {}", job.job, e));
+        }
+    }
+    if let Some(ref e) = replication.last_error {
+        issues.push(format!("Replication push failed: {}", e));
+    }
+    if replication.pending_events > OUTBOX_BACKLOG_THRESHOLD {
+        issues.push(format!("{} replication events are waiting to be pushed", replication.pending_events));
+    }
+    if notifications_failed > 0 {
+        issues.push(format!("{} notification(s) failed to send", notifications_failed));
+    }
+    if category_usage_drift > 0 {
+        issues.push(format!(
+            "{} category usage counter(s) differ from a recount; run rebuild_category_usage",
+            category_usage_drift
+        ));
+    }
+    if cycle_balance < LOW_CYCLES_THRESHOLD {
+        issues.push(format!("Cycle balance is low: {} cycles", cycle_balance));
+    }
+
+    Ok(HealthStatus {
+        status: if issues.is_empty() { "ok" } else { "degraded" }.to_string(),
+        issues,
+        checked_at: ic_cdk::api::time(),
+        jobs,
+        replication,
+        notifications_queued,
+        notifications_failed,
+        unreconciled_bank_transactions,
+        open_suspense_items,
+        category_usage_drift,
+        cycle_balance,
+    })
+}
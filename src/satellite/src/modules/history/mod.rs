@@ -0,0 +1,160 @@
+//! History Module - Change History of Financial and Staff Records
+//!
+//! The audit log records who changed a document and its status; settling a dispute needs
+//! what the document said before. Whenever an expense, payment, staff record or fee
+//! assignment is changed or deleted, the on-set and on-delete hooks keep the full previous
+//! payload in the collection's history store (`expenses_history`, `payments_history`,
+//! `staff_history`, `student_fee_assignments_history`), with who replaced it and when.
+//! `get_document_history` lists a document's revisions, oldest first.
+//!
+//! Only the satellite writes history, each revision is written once, and revisions can
+//! never be deleted. The satellite's own writes (payment allocations, late fees, charges,
+//! promotions, term rollover) do not pass through the hooks; `set_doc_data` keeps their
+//! replaced versions instead, recorded as replaced by the satellite.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, id, AssertSetDocContext, Doc, OnDeleteDocContext, OnSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+
+/// Collections whose changes are kept, with their history stores.
+pub const HISTORY_COLLECTIONS: [(&str, &str); 4] = [
+    ("expenses", "expenses_history"),
+    ("payments", "payments_history"),
+    ("staff", "staff_history"),
+    ("student_fee_assignments", "student_fee_assignments_history"),
+];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntryData {
+    pub doc_key: String,
+    // Version and payload of the document before the change
+    pub version: Option<u64>,
+    pub data: serde_json::Value,
+    pub description: Option<String>,
+    // When that version was written
+    pub updated_at: u64,
+    // `update` or `delete`
+    pub action: String,
+    pub replaced_by: String,
+    pub replaced_at: u64,
+}
+
+#[derive(CandidType, Serialize)]
+pub struct DocumentRevision {
+    pub version: Option<u64>,
+    // Document data as JSON, in the stored (camelCase) shape
+    pub data: String,
+    pub description: Option<String>,
+    pub updated_at: u64,
+    pub action: String,
+    pub replaced_by: String,
+    pub replaced_at: u64,
+}
+
+/// History Entry Validation
+///
+/// Checks:
+/// - Only the satellite writes history (no client-side writes)
+/// - Revisions are written once and never modified
+pub fn validate_history_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Document history is written by the satellite only".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Document history cannot be modified".to_string());
+    }
+    Ok(())
+}
+
+/// Revisions can never be deleted.
+pub fn validate_history_delete() -> Result<(), String> {
+    Err("AUDIT: Document history cannot be deleted".to_string())
+}
+
+/// Called from the on-set hook: keeps the replaced version of a changed document.
+pub fn record_doc_history(context: &OnSetDocContext) -> Result<(), String> {
+    match context.data.data.before {
+        Some(ref before) => {
+            record(&context.data.collection, &context.data.key, before, "update", &context.caller.to_text())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Called from the on-delete hook: keeps the deleted document.
+pub fn record_doc_history_delete(context: &OnDeleteDocContext) -> Result<(), String> {
+    match context.data.data {
+        Some(ref deleted) => {
+            record(&context.data.collection, &context.data.key, deleted, "delete", &context.caller.to_text())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Called from `set_doc_data` for the satellite's own writes, which skip the on-set hook:
+/// keeps the replaced version of a changed document.
+pub fn record_replaced_version(collection: &str, key: &str, before: &Doc) -> Result<(), String> {
+    record(collection, key, before, "update", &id().to_text())
+}
+
+/// Revisions of a document, oldest first.
+pub fn get_document_history(collection: String, key: String) -> Result<Vec<DocumentRevision>, String> {
+    if !caller_has_any_role(&caller(), &[Role::SuperAdmin, Role::Bursar, Role::Accountant, Role::Auditor, Role::Hr]) {
+        return Err("SECURITY: Only staff with finance or HR access can read document history".to_string());
+    }
+    let store = history_store(&collection).ok_or_else(|| {
+        let collections: Vec<&str> = HISTORY_COLLECTIONS.iter().map(|(c, _)| *c).collect();
+        format!("No history is kept for '{}'. Must be one of: {}", collection, collections.join(", "))
+    })?;
+
+    let mut revisions: Vec<DocumentRevision> =
+        list_doc_data::<HistoryEntryData>(store, Some(format!("docKey={};", key)))?
+            .into_iter()
+            .filter(|(_, _, entry)| entry.doc_key == key)
+            .map(|(_, _, entry)| DocumentRevision {
+                version: entry.version,
+                data: entry.data.to_string(),
+                description: entry.description,
+                updated_at: entry.updated_at,
+                action: entry.action,
+                replaced_by: entry.replaced_by,
+                replaced_at: entry.replaced_at,
+            })
+            .collect();
+    revisions.sort_by_key(|r| (r.replaced_at, r.version));
+
+    Ok(revisions)
+}
+
+fn record(collection: &str, key: &str, doc: &Doc, action: &str, replaced_by: &str) -> Result<(), String> {
+    let store = match history_store(collection) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let data: serde_json::Value = decode_doc_data(&doc.data)?;
+    let now = ic_cdk::api::time();
+    let entry = HistoryEntryData {
+        doc_key: key.to_string(),
+        version: doc.version,
+        data,
+        description: doc.description.clone(),
+        updated_at: doc.updated_at,
+        action: action.to_string(),
+        replaced_by: replaced_by.to_string(),
+        replaced_at: now,
+    };
+    // Versions start again when a deleted key is reused, so revisions are keyed by time
+    let entry_key = format!("{}_{}", key, now);
+
+    set_doc_data(store, &entry_key, &entry, Some(format!("docKey={};", key)), None)?;
+    Ok(())
+}
+
+fn history_store(collection: &str) -> Option<&'static str> {
+    HISTORY_COLLECTIONS.iter().find(|(c, _)| *c == collection).map(|(_, store)| *store)
+}
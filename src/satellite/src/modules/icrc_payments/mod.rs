@@ -0,0 +1,388 @@
+//! ICRC Payments Module - Fee Payments on ICP and ckBTC Ledgers
+//!
+//! Fees can be paid in ICP or ckBTC by an ICRC-1 transfer (or an ICRC-2 `transfer_from`)
+//! to the school's account on the token's ledger. A payment with method `crypto` carries
+//! the transfer (`cryptoTransfer`): the token, the ledger block index, the amount in the
+//! token's smallest unit and the naira rate it was accepted at.
+//!
+//! Before the payment can be confirmed the satellite reads the block from the ledger
+//! (ICRC-3 `icrc3_get_blocks`, following the ledger to its archive for older blocks) and
+//! checks that it is a transfer of that amount to the school's account. The verified
+//! block is recorded on the payment (`verifiedBlock`). Verification runs when a pending
+//! crypto payment is saved, and on demand through `verify_crypto_payment`. A block pays
+//! one payment only.
+//!
+//! Ledgers are configured per token in `icrc_ledgers`, keyed by token symbol (`ICP`,
+//! `ckBTC`), with the account fees are paid to (the satellite's own account when no
+//! owner is given).
+
+use candid::{CandidType, Nat, Principal};
+use ic_cdk::call::Call;
+use icrc_ledger_types::icrc::generic_value::{ICRC3Value, Value};
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc3::blocks::{GetBlocksRequest, GetBlocksResult};
+use junobuild_satellite::{caller, id, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::payments::PaymentData;
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+
+pub const ICRC_LEDGERS_COLLECTION: &str = "icrc_ledgers";
+
+const TOKENS: [&str; 2] = ["ICP", "ckBTC"];
+// Rates are quoted to the naira, so the payment may differ from tokens × rate by this much
+const RATE_TOLERANCE_KOBO: i64 = 100;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IcrcLedgerData {
+    pub ledger_id: String,
+    pub decimals: u8,
+    // Account fees are paid to; the satellite's own account when no owner is given
+    pub receiving_owner: Option<String>,
+    // 32-byte subaccount, hex encoded
+    pub receiving_subaccount: Option<String>,
+    pub is_active: bool,
+    pub updated_by: String,
+    pub updated_at: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CryptoTransfer {
+    pub token: String,
+    pub block_index: u64,
+    // In the token's smallest unit (e8s for ICP, satoshis for ckBTC)
+    pub amount: u64,
+    // Naira per whole token
+    pub naira_rate: f64,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedBlock {
+    pub token: String,
+    pub ledger_id: String,
+    pub block_index: u64,
+    pub from: String,
+    pub to: String,
+    pub amount: u64,
+    // Ledger timestamp of the block (nanoseconds)
+    pub timestamp: u64,
+    pub verified_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct CryptoVerification {
+    pub payment_id: String,
+    pub token: String,
+    pub block_index: u64,
+    pub from: String,
+    pub amount: u64,
+    pub naira_amount: f64,
+}
+
+struct LedgerTransfer {
+    from: Account,
+    to: Account,
+    amount: u64,
+    timestamp: u64,
+}
+
+/// ICRC Ledger Validation
+///
+/// Checks:
+/// - Only administrators configure ledgers
+/// - The key is a supported token and the ledger is a valid principal
+/// - The receiving owner is a valid principal and the subaccount 32 bytes of hex, when given
+pub fn validate_icrc_ledger_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only administrators can configure payment ledgers".to_string());
+    }
+
+    let data: IcrcLedgerData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid ICRC ledger data format: {}", e))?;
+
+    if !TOKENS.contains(&context.data.key.as_str()) {
+        return Err(format!("Unknown token '{}'. Must be one of: {}", context.data.key, TOKENS.join(", ")));
+    }
+    Principal::from_text(&data.ledger_id).map_err(|_| format!("Invalid ledger canister '{}'", data.ledger_id))?;
+    if let Some(ref owner) = data.receiving_owner {
+        Principal::from_text(owner).map_err(|_| format!("Invalid receiving owner '{}'", owner))?;
+    }
+    if let Some(ref subaccount) = data.receiving_subaccount {
+        if subaccount.len() != 64 || !subaccount.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err("Receiving subaccount must be 32 bytes of hex (64 characters)".to_string());
+        }
+    }
+    if data.updated_by != context.caller.to_text() {
+        return Err("updatedBy must be the principal configuring the ledger".to_string());
+    }
+
+    Ok(())
+}
+
+/// Crypto Payment Validation
+///
+/// Checks:
+/// - Crypto payments carry the transfer, on a configured token, worth the payment amount
+///   at the rate given
+/// - A ledger block is recorded on one payment only
+/// - Only the satellite records the verified block, and the transfer cannot change once
+///   verified
+/// - A crypto payment is confirmed only with a verified block matching its transfer
+pub fn validate_crypto_payment(context: &AssertSetDocContext, payment: &PaymentData) -> Result<(), String> {
+    let before: Option<PaymentData> = match context.data.data.current {
+        Some(ref doc) => Some(decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous payment data: {}", e))?),
+        None => None,
+    };
+    let satellite = is_satellite_caller(&context.caller);
+
+    if !satellite {
+        let verified_before = before.as_ref().and_then(|b| b.verified_block.clone());
+        if payment.verified_block != verified_before {
+            return Err("SECURITY: Verified ledger blocks are recorded by the satellite".to_string());
+        }
+        if verified_before.is_some()
+            && before.as_ref().map(|b| b.crypto_transfer != payment.crypto_transfer).unwrap_or(false)
+        {
+            return Err("AUDIT: The transfer of a verified crypto payment cannot be changed".to_string());
+        }
+    }
+
+    if payment.payment_method != "crypto" {
+        if payment.crypto_transfer.is_some() {
+            return Err("Only crypto payments carry a ledger transfer".to_string());
+        }
+        return Ok(());
+    }
+
+    let transfer = payment.crypto_transfer.as_ref().ok_or("Crypto payments must include the ledger transfer")?;
+    let (_, ledger) = active_ledger(&transfer.token)?;
+    if transfer.amount == 0 || transfer.naira_rate <= 0.0 {
+        return Err("Crypto transfer amount and naira rate must be greater than zero".to_string());
+    }
+    let naira_value = Money::from_naira(token_value(transfer.amount, ledger.decimals) * transfer.naira_rate);
+    if (naira_value - payment.amount).kobo().abs() > RATE_TOLERANCE_KOBO {
+        return Err(format!(
+            "{} {} at ₦{} is worth ₦{}, not the payment amount of ₦{}",
+            token_value(transfer.amount, ledger.decimals),
+            transfer.token,
+            transfer.naira_rate,
+            naira_value,
+            payment.amount
+        ));
+    }
+    if let Some(other) = payment_for_block(&transfer.token, transfer.block_index, &context.data.key)? {
+        return Err(format!(
+            "SECURITY: Block {} of the {} ledger is already recorded on payment '{}'",
+            transfer.block_index, transfer.token, other
+        ));
+    }
+
+    let was_confirmed = before.as_ref().map(|b| b.status == "confirmed").unwrap_or(false);
+    if payment.status == "confirmed" && !was_confirmed {
+        let verified = payment.verified_block.as_ref().is_some_and(|block| {
+            block.token == transfer.token
+                && block.block_index == transfer.block_index
+                && block.amount == transfer.amount
+        });
+        if !verified {
+            return Err(format!(
+                "SECURITY: Block {} of the {} ledger has not been verified for this payment (verify_crypto_payment)",
+                transfer.block_index, transfer.token
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Called from the `payments` on-set hook: verifies pending crypto payments with the
+/// ledger so they are ready to confirm.
+pub async fn on_crypto_payment_saved(key: &str, payment: &PaymentData) -> Result<(), String> {
+    if payment.payment_method != "crypto" || payment.status != "pending" || payment.verified_block.is_some() {
+        return Ok(());
+    }
+    verify_and_record(key).await.map(|_| ())
+}
+
+/// Verify a crypto payment's transfer with its ledger, e.g. when the ledger could not be
+/// reached at the time the payment was saved.
+pub async fn verify_crypto_payment(payment_id: String) -> Result<CryptoVerification, String> {
+    if !caller_has_any_role(&caller(), &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+        return Err("SECURITY: Only finance officers can verify crypto payments".to_string());
+    }
+    verify_and_record(&payment_id).await
+}
+
+async fn verify_and_record(payment_id: &str) -> Result<CryptoVerification, String> {
+    let (_, payment) = get_doc_data::<PaymentData>("payments", payment_id)?
+        .ok_or_else(|| format!("Payment '{}' not found", payment_id))?;
+    if payment.payment_method != "crypto" {
+        return Err("Only crypto payments are verified with a ledger".to_string());
+    }
+    let transfer = payment.crypto_transfer.clone().ok_or("Crypto payments must include the ledger transfer")?;
+    let (ledger_id, ledger) = active_ledger(&transfer.token)?;
+
+    let block = fetch_block(ledger_id, transfer.block_index).await?;
+    let ledger_transfer = parse_transfer(block).map_err(|e| {
+        format!("Block {} of the {} ledger is not a transfer: {}", transfer.block_index, transfer.token, e)
+    })?;
+
+    let receiving = receiving_account(&ledger)?;
+    if !same_account(&ledger_transfer.to, &receiving) {
+        return Err(format!(
+            "SECURITY: Block {} transfers to {}, not the school's account {}",
+            transfer.block_index, ledger_transfer.to, receiving
+        ));
+    }
+    if ledger_transfer.amount != transfer.amount {
+        return Err(format!(
+            "SECURITY: Block {} transfers {} {}, but the payment records {}",
+            transfer.block_index,
+            token_value(ledger_transfer.amount, ledger.decimals),
+            transfer.token,
+            token_value(transfer.amount, ledger.decimals)
+        ));
+    }
+    if let Some(other) = payment_for_block(&transfer.token, transfer.block_index, payment_id)? {
+        return Err(format!(
+            "SECURITY: Block {} of the {} ledger is already recorded on payment '{}'",
+            transfer.block_index, transfer.token, other
+        ));
+    }
+
+    let verified = VerifiedBlock {
+        token: transfer.token.clone(),
+        ledger_id: ledger.ledger_id.clone(),
+        block_index: transfer.block_index,
+        from: ledger_transfer.from.to_string(),
+        to: ledger_transfer.to.to_string(),
+        amount: ledger_transfer.amount,
+        timestamp: ledger_transfer.timestamp,
+        verified_at: ic_cdk::api::time(),
+    };
+
+    // Re-read: the payment may have changed while the ledger was queried
+    let (doc, mut current) = get_doc_data::<PaymentData>("payments", payment_id)?
+        .ok_or_else(|| format!("Payment '{}' not found", payment_id))?;
+    if current.crypto_transfer.as_ref() != Some(&transfer) {
+        return Err("The payment's transfer changed while it was being verified; verify it again".to_string());
+    }
+    current.verified_block = Some(verified.clone());
+    current.updated_at = ic_cdk::api::time();
+    set_doc_data("payments", payment_id, &current, doc.description, doc.version)?;
+
+    Ok(CryptoVerification {
+        payment_id: payment_id.to_string(),
+        token: verified.token,
+        block_index: verified.block_index,
+        from: verified.from,
+        amount: verified.amount,
+        naira_amount: current.amount.naira(),
+    })
+}
+
+fn active_ledger(token: &str) -> Result<(Principal, IcrcLedgerData), String> {
+    let (_, ledger) = get_doc_data::<IcrcLedgerData>(ICRC_LEDGERS_COLLECTION, token)?
+        .filter(|(_, l)| l.is_active)
+        .ok_or_else(|| format!("No active ledger is configured for {}", token))?;
+    let ledger_id = Principal::from_text(&ledger.ledger_id)
+        .map_err(|_| format!("Invalid ledger canister '{}'", ledger.ledger_id))?;
+    Ok((ledger_id, ledger))
+}
+
+// Another payment, not cancelled, already carrying the block
+fn payment_for_block(token: &str, block_index: u64, payment_id: &str) -> Result<Option<String>, String> {
+    Ok(list_doc_data::<PaymentData>("payments", None)?
+        .into_iter()
+        .find(|(key, _, p)| {
+            key != payment_id
+                && p.status != "cancelled"
+                && p.crypto_transfer.as_ref().is_some_and(|t| t.token == token && t.block_index == block_index)
+        })
+        .map(|(key, _, _)| key))
+}
+
+// The block, from the ledger or the archive it points to
+async fn fetch_block(ledger_id: Principal, block_index: u64) -> Result<ICRC3Value, String> {
+    let request = vec![GetBlocksRequest { start: Nat::from(block_index), length: Nat::from(1u64) }];
+    let result: GetBlocksResult = Call::unbounded_wait(ledger_id, "icrc3_get_blocks")
+        .with_arg(request)
+        .await
+        .map_err(|e| format!("Ledger {} could not be reached: {}", ledger_id, e))?
+        .candid()
+        .map_err(|e| format!("Invalid ledger response: {}", e))?;
+
+    let wanted = Nat::from(block_index);
+    if let Some(block) = result.blocks.into_iter().find(|b| b.id == wanted) {
+        return Ok(block.block);
+    }
+    for archived in result.archived_blocks {
+        let archive: GetBlocksResult = Call::unbounded_wait(archived.callback.canister_id, &archived.callback.method)
+            .with_arg(archived.args)
+            .await
+            .map_err(|e| format!("Ledger archive {} could not be reached: {}", archived.callback.canister_id, e))?
+            .candid()
+            .map_err(|e| format!("Invalid ledger archive response: {}", e))?;
+        if let Some(block) = archive.blocks.into_iter().find(|b| b.id == wanted) {
+            return Ok(block.block);
+        }
+    }
+    Err(format!("Block {} was not found on ledger {}", block_index, ledger_id))
+}
+
+// ICRC-3 block: { btype?: "1xfer" | "2xfer", ts, tx: { op?: "xfer", from, to, amt } }
+fn parse_transfer(block: ICRC3Value) -> Result<LedgerTransfer, String> {
+    let mut block = Value::from(block).as_map()?;
+    let btype = block.remove("btype").map(|b| b.as_text()).transpose()?;
+    let timestamp = block.remove("ts").map(u64::try_from).transpose()?.unwrap_or(0);
+    let mut tx = block.remove("tx").ok_or("the block has no transaction")?.as_map()?;
+    let op = tx.remove("op").map(|o| o.as_text()).transpose()?;
+
+    let is_transfer = matches!(btype.as_deref(), Some("1xfer") | Some("2xfer")) || op.as_deref() == Some("xfer");
+    if !is_transfer {
+        return Err(format!("it is a {}", btype.or(op).unwrap_or_else(|| "block of unknown type".to_string())));
+    }
+
+    Ok(LedgerTransfer {
+        from: Account::try_from(tx.remove("from").ok_or("the transfer has no sender")?)?,
+        to: Account::try_from(tx.remove("to").ok_or("the transfer has no recipient")?)?,
+        amount: u64::try_from(tx.remove("amt").ok_or("the transfer has no amount")?)?,
+        timestamp,
+    })
+}
+
+fn receiving_account(ledger: &IcrcLedgerData) -> Result<Account, String> {
+    let owner = match ledger.receiving_owner {
+        Some(ref owner) => Principal::from_text(owner).map_err(|_| format!("Invalid receiving owner '{}'", owner))?,
+        None => id(),
+    };
+    let subaccount = match ledger.receiving_subaccount {
+        Some(ref hex) => {
+            let mut bytes = [0u8; 32];
+            for (i, byte) in bytes.iter_mut().enumerate() {
+                *byte = hex
+                    .get(i * 2..i * 2 + 2)
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or("Receiving subaccount must be 32 bytes of hex")?;
+            }
+            Some(bytes)
+        }
+        None => None,
+    };
+    Ok(Account { owner, subaccount })
+}
+
+// No subaccount and the all-zero subaccount are the same (default) account
+fn same_account(a: &Account, b: &Account) -> bool {
+    a.owner == b.owner && a.subaccount.unwrap_or([0; 32]) == b.subaccount.unwrap_or([0; 32])
+}
+
+fn token_value(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
@@ -0,0 +1,183 @@
+//! ID Cards Module - Student ID Card Issuance
+//!
+//! Every card printed for a student is recorded in `id_card_issuances` through
+//! `issue_id_card`. A student's first card is covered by their fees and issued free,
+//! once. Every later card is a reprint: it needs a reason and is billed to the student
+//! through a `student_charges` reprint charge posted onto the fee assignment given, so
+//! it is collected like any other charge. The issuance keeps the charge key; waiving
+//! the charge is done on the charge itself.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::charges::{on_student_charge_saved, StudentChargeData, STUDENT_CHARGES_COLLECTION};
+use super::roles::{caller_has_any_role, Role};
+use super::utils::counters::next_number;
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const ID_CARD_ISSUANCES_COLLECTION: &str = "id_card_issuances";
+
+pub const ID_CARD_REPRINT_FEE: Money = Money::from_kobo(200_000);
+const REPRINT_REASONS: [&str; 3] = ["lost", "damaged", "details_changed"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdCardIssuanceData {
+    pub student_id: String,
+    pub card_number: String,
+    pub issue_type: String,
+    pub reprint_reason: Option<String>,
+    pub charge_id: Option<String>,
+    pub fee_amount: Money,
+    pub issued_by: String,
+    pub issued_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct IdCardIssuance {
+    pub issuance_id: String,
+    pub card_number: String,
+    pub issue_type: String,
+    pub charge_id: Option<String>,
+    pub fee_amount: f64,
+}
+
+/// ID Card Issuance Validation
+///
+/// Checks:
+/// - Issuances are recorded by `issue_id_card` only and never modified
+/// - A student gets one free (initial) card; every other card is a billed reprint
+pub fn validate_id_card_issuance_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: ID cards are issued through the issue_id_card endpoint".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: ID card issuances cannot be modified".to_string());
+    }
+
+    let data: IdCardIssuanceData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid ID card issuance data format: {}", e))?;
+
+    match data.issue_type.as_str() {
+        "initial" => {
+            if !issuances_for(&data.student_id)?.is_empty() {
+                return Err(format!("Student '{}' has already been issued a free ID card", data.student_id));
+            }
+            if data.charge_id.is_some() || !data.fee_amount.is_zero() {
+                return Err("The initial ID card is issued free of charge".to_string());
+            }
+        }
+        "reprint" => {
+            if data.charge_id.is_none() || !data.fee_amount.is_positive() {
+                return Err("ID card reprints must be billed to the student".to_string());
+            }
+        }
+        other => return Err(format!("Invalid issue type '{}'. Must be one of: initial, reprint", other)),
+    }
+
+    Ok(())
+}
+
+/// Issued ID cards are part of the student's billing history and cannot be deleted.
+pub fn validate_id_card_issuance_delete() -> Result<(), String> {
+    Err("AUDIT: ID card issuances cannot be deleted".to_string())
+}
+
+/// Record an ID card printed for a student. The first card is free; reprints need a
+/// reason and the fee assignment the reprint fee is charged to.
+pub fn issue_id_card(
+    student_id: String,
+    reprint_reason: Option<String>,
+    fee_assignment_id: Option<String>,
+) -> Result<IdCardIssuance, String> {
+    let caller = caller();
+    if !caller_has_any_role(&caller, &[Role::SuperAdmin, Role::Bursar, Role::DataEntry]) {
+        return Err("SECURITY: Only administrators, bursars and data entry staff can issue ID cards".to_string());
+    }
+    if !doc_exists("students", &student_id)? {
+        return Err(format!("Student '{}' not found", student_id));
+    }
+
+    let previous = issuances_for(&student_id)?.len();
+    let issuance_id = format!("{}_{}", student_id, previous + 1);
+    let now = ic_cdk::api::time();
+
+    let (issue_type, charge_id, fee_amount) = if previous == 0 {
+        if reprint_reason.is_some() || fee_assignment_id.is_some() {
+            return Err("This is the student's first ID card, which is issued free".to_string());
+        }
+        ("initial", None, Money::ZERO)
+    } else {
+        let reason = reprint_reason.as_deref().unwrap_or_default();
+        if !REPRINT_REASONS.contains(&reason) {
+            return Err(format!(
+                "The student already has an ID card; a reprint reason is required. Must be one of: {}",
+                REPRINT_REASONS.join(", ")
+            ));
+        }
+        let fee_assignment_id = fee_assignment_id
+            .ok_or("Reprints are billed to the student; a fee assignment is required")?;
+
+        let charge_id = format!("id_card_{}", issuance_id);
+        let charge = StudentChargeData {
+            student_id: student_id.clone(),
+            fee_assignment_id,
+            charge_type: "id_card_reprint".to_string(),
+            description: format!("ID card reprint ({})", reason.replace('_', " ")),
+            amount: ID_CARD_REPRINT_FEE,
+            charge_date: current_date(),
+            status: "posted".to_string(),
+            waived_by: None,
+            waiver_reason: None,
+            recorded_by: caller.to_text(),
+            created_at: now,
+            updated_at: now,
+        };
+        // Satellite writes skip the on-set hook, so the charge is posted here
+        set_doc_data(STUDENT_CHARGES_COLLECTION, &charge_id, &charge, None, None)?;
+        on_student_charge_saved(&charge_id, None, &charge)?;
+
+        ("reprint", Some(charge_id), ID_CARD_REPRINT_FEE)
+    };
+
+    let year = &current_date()[0..4];
+    let card_number = format!("ID-{}-{:06}", year, next_number(&format!("id-cards-{}", year))?);
+
+    let issuance = IdCardIssuanceData {
+        student_id: student_id.clone(),
+        card_number: card_number.clone(),
+        issue_type: issue_type.to_string(),
+        reprint_reason,
+        charge_id: charge_id.clone(),
+        fee_amount,
+        issued_by: caller.to_text(),
+        issued_at: now,
+    };
+    set_doc_data(
+        ID_CARD_ISSUANCES_COLLECTION,
+        &issuance_id,
+        &issuance,
+        Some(format!("student_id={};", student_id)),
+        None,
+    )?;
+
+    Ok(IdCardIssuance {
+        issuance_id,
+        card_number,
+        issue_type: issue_type.to_string(),
+        charge_id,
+        fee_amount: fee_amount.naira(),
+    })
+}
+
+fn issuances_for(student_id: &str) -> Result<Vec<IdCardIssuanceData>, String> {
+    Ok(list_doc_data::<IdCardIssuanceData>(ID_CARD_ISSUANCES_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, issuance)| issuance.student_id == student_id)
+        .map(|(_, _, issuance)| issuance)
+        .collect())
+}
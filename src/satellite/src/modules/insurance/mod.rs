@@ -0,0 +1,285 @@
+//! Insurance Module - Policies, Claims and Recoveries
+//!
+//! Expenses caused by an insurable incident (fire, theft, vehicle damage) are tagged with
+//! the claim they are recoverable under. This module:
+//! - Registers policies (`insurance_policies`) with their period of cover
+//! - Validates claims (`insurance_claims`) against the policy and the tagged expenses
+//! - Ensures recovery credits never exceed the expenses claimed
+//! - Reports claim recoveries against incurred costs
+
+use candid::CandidType;
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::expenses::ExpenseData;
+use super::utils::doc_utils::*;
+use super::utils::identity::validate_caller_identity;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const INSURANCE_POLICIES_COLLECTION: &str = "insurance_policies";
+pub const INSURANCE_CLAIMS_COLLECTION: &str = "insurance_claims";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsurancePolicyData {
+    pub insurer: String,
+    pub policy_number: String,
+    pub cover_type: String,
+    pub sum_insured: Money,
+    pub premium: Money,
+    pub start_date: String,
+    pub end_date: String,
+    pub is_active: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimRecovery {
+    pub amount: Money,
+    pub received_date: String,
+    pub reference: String,
+    pub bank_transaction_id: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsuranceClaimData {
+    pub policy_id: String,
+    pub claim_number: String,
+    pub incident_date: String,
+    pub incident_description: String,
+    pub status: String,
+    #[serde(default)]
+    pub recoveries: Vec<ClaimRecovery>,
+    pub notes: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ClaimRecoveryReportItem {
+    pub claim_id: String,
+    pub claim_number: String,
+    pub policy_number: String,
+    pub insurer: String,
+    pub incident_date: String,
+    pub status: String,
+    pub expense_count: u32,
+    pub incurred: f64,
+    pub recovered: f64,
+    pub unrecovered: f64,
+}
+
+/// Insurance Policy Validation
+///
+/// Checks:
+/// - Unique policy number
+/// - Positive sum insured, non-negative premium
+/// - Cover period ends after it starts
+pub fn validate_insurance_policy_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: InsurancePolicyData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid insurance policy data format: {}", e))?;
+
+    if data.insurer.trim().is_empty() || data.policy_number.trim().is_empty() {
+        return Err("Insurer and policy number are required".to_string());
+    }
+    if !data.sum_insured.is_positive() {
+        return Err("Sum insured must be greater than 0".to_string());
+    }
+    if data.premium.is_negative() {
+        return Err("Premium cannot be negative".to_string());
+    }
+    if !is_valid_date_format(&data.start_date) || !is_valid_date_format(&data.end_date) {
+        return Err("Invalid cover dates. Must be YYYY-MM-DD".to_string());
+    }
+    if data.end_date <= data.start_date {
+        return Err("Policy end date must be after the start date".to_string());
+    }
+
+    let policies = list_doc_data::<InsurancePolicyData>(INSURANCE_POLICIES_COLLECTION, None)?;
+    if policies
+        .iter()
+        .any(|(key, _, p)| key != &context.data.key && p.policy_number.eq_ignore_ascii_case(&data.policy_number))
+    {
+        return Err(format!("Policy number '{}' already exists", data.policy_number));
+    }
+
+    Ok(())
+}
+
+/// Insurance Claim Validation
+///
+/// Checks:
+/// - Policy exists and the incident falls within its period of cover
+/// - Unique claim number
+/// - Recovery credits never exceed the expenses tagged to the claim or the sum insured
+/// - Settled/closed claims cannot be reopened
+pub fn validate_insurance_claim_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: InsuranceClaimData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid insurance claim data format: {}", e))?;
+
+    // recordedBy must be the caller
+    validate_caller_identity(context)?;
+
+    if data.claim_number.trim().is_empty() {
+        return Err("Claim number is required".to_string());
+    }
+    if data.incident_description.trim().len() < 10 {
+        return Err("Incident description must be at least 10 characters".to_string());
+    }
+    if !is_valid_date_format(&data.incident_date) {
+        return Err("Invalid incident date format. Must be YYYY-MM-DD".to_string());
+    }
+
+    let valid_statuses = ["open", "submitted", "settled", "rejected", "closed"];
+    if !valid_statuses.contains(&data.status.as_str()) {
+        return Err(format!(
+            "Invalid claim status '{}'. Must be one of: {}",
+            data.status,
+            valid_statuses.join(", ")
+        ));
+    }
+
+    if let Some(ref before_doc) = context.data.data.current {
+        let before: InsuranceClaimData = decode_doc_data(&before_doc.data)
+            .map_err(|e| format!("Invalid previous claim data: {}", e))?;
+        if before.policy_id != data.policy_id {
+            return Err("A claim cannot be moved to a different policy".to_string());
+        }
+        if before.status == "closed" {
+            return Err("AUDIT: Closed claims cannot be modified".to_string());
+        }
+        if data.recoveries.len() < before.recoveries.len() {
+            return Err("AUDIT: Recorded recoveries cannot be removed".to_string());
+        }
+    }
+
+    let (_, policy) = get_doc_data::<InsurancePolicyData>(INSURANCE_POLICIES_COLLECTION, &data.policy_id)?
+        .ok_or_else(|| format!("Insurance policy '{}' not found", data.policy_id))?;
+
+    if data.incident_date < policy.start_date || data.incident_date > policy.end_date {
+        return Err(format!(
+            "Incident date {} is outside the policy cover ({} to {})",
+            data.incident_date, policy.start_date, policy.end_date
+        ));
+    }
+
+    let claims = list_doc_data::<InsuranceClaimData>(INSURANCE_CLAIMS_COLLECTION, None)?;
+    if claims
+        .iter()
+        .any(|(key, _, c)| key != &context.data.key && c.claim_number.eq_ignore_ascii_case(&data.claim_number))
+    {
+        return Err(format!("Claim number '{}' already exists", data.claim_number));
+    }
+
+    for recovery in data.recoveries.iter() {
+        if !recovery.amount.is_positive() {
+            return Err("Recovery amounts must be greater than 0".to_string());
+        }
+        if !is_valid_date_format(&recovery.received_date) {
+            return Err("Invalid recovery date format. Must be YYYY-MM-DD".to_string());
+        }
+        if recovery.reference.trim().is_empty() {
+            return Err("Each recovery must carry the insurer's payment reference".to_string());
+        }
+    }
+
+    let recovered: Money = data.recoveries.iter().map(|r| r.amount).sum();
+    if recovered.is_positive() {
+        let (_, incurred) = claimed_expenses(&context.data.key)?;
+        if recovered > incurred {
+            return Err(format!(
+                "Recoveries of ₦{} exceed the ₦{} of expenses claimed",
+                recovered, incurred
+            ));
+        }
+        if recovered > policy.sum_insured {
+            return Err(format!(
+                "Recoveries of ₦{} exceed the policy sum insured of ₦{}",
+                recovered, policy.sum_insured
+            ));
+        }
+    }
+
+    if data.status == "settled" && data.recoveries.is_empty() {
+        return Err("Settled claims must record at least one recovery".to_string());
+    }
+    if data.status == "rejected" && data.notes.as_ref().map(|n| n.trim().is_empty()).unwrap_or(true) {
+        return Err("Rejected claims must include the insurer's reason in notes".to_string());
+    }
+
+    Ok(())
+}
+
+/// An expense tagged to a claim must reference an existing claim that is still open to
+/// new costs, and the incident must precede the expense.
+pub fn validate_expense_claim_link(expense: &ExpenseData) -> Result<(), String> {
+    let claim_id = match expense.insurance_claim_id {
+        Some(ref id) if !id.trim().is_empty() => id,
+        _ => return Ok(()),
+    };
+
+    let (_, claim) = get_doc_data::<InsuranceClaimData>(INSURANCE_CLAIMS_COLLECTION, claim_id)?
+        .ok_or_else(|| format!("Insurance claim '{}' not found", claim_id))?;
+
+    if claim.status == "closed" || claim.status == "rejected" {
+        return Err(format!(
+            "Insurance claim {} is {} and cannot take further expenses",
+            claim.claim_number, claim.status
+        ));
+    }
+    if expense.payment_date < claim.incident_date {
+        return Err(format!(
+            "Expense dated {} precedes the insured incident on {}",
+            expense.payment_date, claim.incident_date
+        ));
+    }
+
+    Ok(())
+}
+
+/// Claim recoveries against the costs incurred, one line per claim.
+pub fn get_claims_recovery_report() -> Result<Vec<ClaimRecoveryReportItem>, String> {
+    let policies = list_doc_data::<InsurancePolicyData>(INSURANCE_POLICIES_COLLECTION, None)?;
+    let claims = list_doc_data::<InsuranceClaimData>(INSURANCE_CLAIMS_COLLECTION, None)?;
+
+    let mut report = Vec::new();
+    for (claim_id, _, claim) in claims.into_iter() {
+        let (expense_count, incurred) = claimed_expenses(&claim_id)?;
+        let recovered: Money = claim.recoveries.iter().map(|r| r.amount).sum();
+        let policy = policies.iter().find(|(key, _, _)| key == &claim.policy_id).map(|(_, _, p)| p);
+
+        report.push(ClaimRecoveryReportItem {
+            claim_id,
+            claim_number: claim.claim_number,
+            policy_number: policy.map(|p| p.policy_number.clone()).unwrap_or_default(),
+            insurer: policy.map(|p| p.insurer.clone()).unwrap_or_default(),
+            incident_date: claim.incident_date,
+            status: claim.status,
+            expense_count,
+            incurred: incurred.naira(),
+            recovered: recovered.naira(),
+            unrecovered: (incurred - recovered).max(Money::ZERO).naira(),
+        });
+    }
+
+    report.sort_by(|a, b| b.incident_date.cmp(&a.incident_date));
+    Ok(report)
+}
+
+/// Number and total of approved or paid expenses tagged to a claim.
+fn claimed_expenses(claim_id: &str) -> Result<(u32, Money), String> {
+    let expenses = list_doc_data::<ExpenseData>("expenses", None)?;
+
+    let tagged: Vec<Money> = expenses
+        .iter()
+        .filter(|(_, _, e)| e.insurance_claim_id.as_deref() == Some(claim_id))
+        .filter(|(_, _, e)| e.status == "approved" || e.status == "paid")
+        .map(|(_, _, e)| e.amount)
+        .collect();
+
+    Ok((tagged.len() as u32, tagged.iter().sum()))
+}
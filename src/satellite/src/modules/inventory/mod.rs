@@ -0,0 +1,351 @@
+//! Inventory Module - Uniform and Book Store
+//!
+//! Uniforms and books are kept as stock items (`stock_items`), and every change in
+//! quantity is a stock movement (`stock_movements`): deliveries received and returns in,
+//! internal issues and sales to students out, and adjustments after a stock count.
+//! Quantity on hand is the sum of an item's movements. This module enforces:
+//! - Movements out never take an item's quantity on hand below zero
+//! - Adjustments are made by a bursar or administrator, with a reason
+//! - Movements are written once and never changed or deleted
+//! - A sale to a student is posted onto their fee assignment as a fee item
+//!   (category id `stock:<movement key>`) with fee type `uniform` or `books`, so the
+//!   payment allocations that settle it carry that fee type
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::fees::{fee_assignment_status, FeeItemData, StudentFeeAssignmentData};
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const STOCK_ITEMS_COLLECTION: &str = "stock_items";
+pub const STOCK_MOVEMENTS_COLLECTION: &str = "stock_movements";
+
+// Item types, which are also the fee types their sales are posted under
+const ITEM_TYPES: [&str; 2] = ["uniform", "books"];
+
+const INBOUND_MOVEMENTS: [&str; 3] = ["receipt", "return", "adjustment_in"];
+const OUTBOUND_MOVEMENTS: [&str; 3] = ["issue", "sale", "adjustment_out"];
+
+// Store staff: finance staff and data entry clerks
+const STORE_ROLES: [Role; 4] = [Role::SuperAdmin, Role::Bursar, Role::Accountant, Role::DataEntry];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockItemData {
+    pub name: String,
+    pub sku: String,
+    pub item_type: String,
+    pub size: Option<String>,
+    // Selling price to students
+    pub unit_price: Money,
+    pub reorder_level: u32,
+    pub is_active: bool,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockMovementData {
+    pub item_id: String,
+    pub movement_type: String,
+    pub quantity: u32,
+    pub movement_date: String,
+    // Purchase cost of a delivery
+    pub unit_cost: Option<Money>,
+    // Selling price of a sale, the item's price when sold
+    pub unit_price: Option<Money>,
+    pub student_id: Option<String>,
+    pub fee_assignment_id: Option<String>,
+    // Department or staff member an internal issue went to
+    pub issued_to: Option<String>,
+    pub reference: Option<String>,
+    pub reason: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct StockLevel {
+    pub item_id: String,
+    pub name: String,
+    pub sku: String,
+    pub item_type: String,
+    pub size: Option<String>,
+    pub quantity_on_hand: i64,
+    pub reorder_level: u32,
+    pub below_reorder_level: bool,
+    // Quantity on hand at the selling price
+    pub stock_value: f64,
+}
+
+/// Stock Item Validation
+///
+/// Checks:
+/// - Maintained by a bursar, accountant or administrator
+/// - Name set, SKU unique, item type uniform or books
+/// - Selling price positive
+/// - The item type is fixed once movements are recorded against it
+pub fn validate_stock_item_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: StockItemData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid stock item data format: {}", e))?;
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+        return Err("SECURITY: Only finance staff can maintain stock items".to_string());
+    }
+    if data.name.trim().is_empty() || data.sku.trim().is_empty() {
+        return Err("Stock item name and SKU are required".to_string());
+    }
+    if !ITEM_TYPES.contains(&data.item_type.as_str()) {
+        return Err(format!(
+            "Invalid item type '{}'. Must be one of: {}",
+            data.item_type,
+            ITEM_TYPES.join(", ")
+        ));
+    }
+    if !data.unit_price.is_positive() {
+        return Err("Selling price must be greater than 0".to_string());
+    }
+
+    let duplicate = list_doc_data::<StockItemData>(STOCK_ITEMS_COLLECTION, None)?
+        .into_iter()
+        .any(|(key, _, item)| key != context.data.key && item.sku.eq_ignore_ascii_case(data.sku.trim()));
+    if duplicate {
+        return Err(format!("SKU '{}' is already used by another stock item", data.sku));
+    }
+
+    if let Some(ref before_doc) = context.data.data.current {
+        let before: StockItemData = decode_doc_data(&before_doc.data)
+            .map_err(|e| format!("Invalid previous stock item data: {}", e))?;
+        if before.item_type != data.item_type && !item_movements(&context.data.key)?.is_empty() {
+            return Err("The item type cannot be changed once stock has moved".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// A stock item cannot be deleted once stock has moved; deactivate it instead.
+pub fn validate_stock_item_delete(key: &str) -> Result<(), String> {
+    deny_if_referenced(
+        "stock item",
+        key,
+        &[(STOCK_MOVEMENTS_COLLECTION, referencing_keys(STOCK_MOVEMENTS_COLLECTION, "itemId", key)?)],
+    )
+}
+
+/// Stock Movement Validation
+///
+/// Checks:
+/// - Recorded once, by store staff, as the caller; never modified
+/// - Known movement type, positive quantity, valid date, an existing active item
+/// - Deliveries record their unit cost; issues record who received the stock
+/// - Sales are to a student on their own fee assignment, at the item's selling price
+/// - Adjustments by a bursar or administrator with a reason of at least 10 characters
+/// - Movements out cannot take the quantity on hand below zero
+pub fn validate_stock_movement_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: StockMovementData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid stock movement data format: {}", e))?;
+
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Stock movements cannot be modified; record a correcting movement".to_string());
+    }
+    if !caller_has_any_role(&context.caller, &STORE_ROLES) {
+        return Err("SECURITY: Only store and finance staff can record stock movements".to_string());
+    }
+    if data.recorded_by != context.caller.to_text() {
+        return Err("recordedBy must be the principal recording the movement".to_string());
+    }
+
+    let movement_type = data.movement_type.as_str();
+    if !INBOUND_MOVEMENTS.contains(&movement_type) && !OUTBOUND_MOVEMENTS.contains(&movement_type) {
+        return Err(format!(
+            "Invalid movement type '{}'. Must be one of: {}, {}",
+            data.movement_type,
+            INBOUND_MOVEMENTS.join(", "),
+            OUTBOUND_MOVEMENTS.join(", ")
+        ));
+    }
+    if data.quantity == 0 {
+        return Err("Movement quantity must be greater than 0".to_string());
+    }
+    if !is_valid_date_format(&data.movement_date) {
+        return Err("Invalid movement date format. Must be YYYY-MM-DD".to_string());
+    }
+
+    let (_, item) = get_doc_data::<StockItemData>(STOCK_ITEMS_COLLECTION, &data.item_id)?
+        .ok_or_else(|| format!("Stock item '{}' not found", data.item_id))?;
+    if !item.is_active && movement_type != "adjustment_out" {
+        return Err(format!("Stock item '{}' is inactive", item.name));
+    }
+
+    match movement_type {
+        "receipt" if !data.unit_cost.map(|c| c.is_positive()).unwrap_or(false) => {
+            return Err("Deliveries must record a unit cost greater than 0".to_string());
+        }
+        "issue" if data.issued_to.as_ref().map(|t| t.trim().is_empty()).unwrap_or(true) => {
+            return Err("Issues must record who the stock was issued to".to_string());
+        }
+        "sale" => validate_sale(&data, &item)?,
+        "adjustment_in" | "adjustment_out" => {
+            if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+                return Err("SECURITY: Only a bursar or administrator can adjust stock".to_string());
+            }
+            if data.reason.as_ref().map(|r| r.trim().len() < 10).unwrap_or(true) {
+                return Err("Stock adjustments must include a reason of at least 10 characters".to_string());
+            }
+        }
+        _ => {}
+    }
+
+    if OUTBOUND_MOVEMENTS.contains(&movement_type) {
+        let on_hand = quantity_on_hand(&item_movements(&data.item_id)?);
+        if (data.quantity as i64) > on_hand {
+            return Err(format!(
+                "Only {} of '{}' in stock; cannot take out {}",
+                on_hand.max(0),
+                item.name,
+                data.quantity
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Stock movements can never be deleted.
+pub fn validate_stock_movement_delete() -> Result<(), String> {
+    Err("AUDIT: Stock movements cannot be deleted".to_string())
+}
+
+/// Called from the `stock_movements` on-set hook: posts a sale onto the student's fee
+/// assignment under the item's fee type.
+pub fn on_stock_movement_saved(key: &str, movement: &StockMovementData) -> Result<(), String> {
+    if movement.movement_type != "sale" {
+        return Ok(());
+    }
+    let (Some(fee_assignment_id), Some(unit_price)) = (movement.fee_assignment_id.as_ref(), movement.unit_price) else {
+        return Ok(());
+    };
+    let (_, item) = get_doc_data::<StockItemData>(STOCK_ITEMS_COLLECTION, &movement.item_id)?
+        .ok_or_else(|| format!("Stock item '{}' not found", movement.item_id))?;
+    let (doc, mut assignment) = get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", fee_assignment_id)?
+        .ok_or_else(|| format!("Fee assignment '{}' not found", fee_assignment_id))?;
+
+    let item_id = format!("stock:{}", key);
+    if assignment.fee_items.iter().any(|i| i.category_id == item_id) {
+        return Ok(());
+    }
+
+    let amount = Money::from_kobo(unit_price.kobo() * movement.quantity as i64);
+    let name = match item.size {
+        Some(ref size) if !size.trim().is_empty() => format!("{} ({}) x{}", item.name, size, movement.quantity),
+        _ => format!("{} x{}", item.name, movement.quantity),
+    };
+    assignment.fee_items.push(FeeItemData {
+        category_id: item_id,
+        category_name: name,
+        fee_type: item.item_type.clone(),
+        amount,
+        amount_paid: Money::ZERO,
+        balance: amount,
+        is_mandatory: true,
+        is_optional: Some(false),
+        is_selected: None,
+        extra: serde_json::Map::from_iter([(
+            "stockItemId".to_string(),
+            serde_json::Value::String(movement.item_id.clone()),
+        )]),
+    });
+
+    assignment.total_amount += amount;
+    if let Some(original) = assignment.original_amount {
+        assignment.original_amount = Some(original + amount);
+    }
+    assignment.balance = assignment.total_amount - assignment.amount_paid;
+    assignment.status = fee_assignment_status(assignment.amount_paid, assignment.balance).to_string();
+
+    set_doc_data("student_fee_assignments", fee_assignment_id, &assignment, doc.description.clone(), doc.version)?;
+    Ok(())
+}
+
+/// Quantity on hand of every stock item (store and finance staff).
+pub fn get_stock_levels() -> Result<Vec<StockLevel>, String> {
+    if !caller_has_any_role(&caller(), &STORE_ROLES) {
+        return Err("SECURITY: Not authorised to view stock levels".to_string());
+    }
+
+    let mut on_hand: HashMap<String, i64> = HashMap::new();
+    for (_, _, movement) in list_doc_data::<StockMovementData>(STOCK_MOVEMENTS_COLLECTION, None)? {
+        *on_hand.entry(movement.item_id.clone()).or_default() += signed_quantity(&movement);
+    }
+
+    let mut levels: Vec<StockLevel> = list_doc_data::<StockItemData>(STOCK_ITEMS_COLLECTION, None)?
+        .into_iter()
+        .map(|(key, _, item)| {
+            let quantity = on_hand.get(&key).copied().unwrap_or(0);
+            StockLevel {
+                quantity_on_hand: quantity,
+                below_reorder_level: quantity <= item.reorder_level as i64,
+                stock_value: Money::from_kobo(item.unit_price.kobo() * quantity.max(0)).naira(),
+                item_id: key,
+                name: item.name,
+                sku: item.sku,
+                item_type: item.item_type,
+                size: item.size,
+                reorder_level: item.reorder_level,
+            }
+        })
+        .collect();
+    levels.sort_by(|a, b| a.item_type.cmp(&b.item_type).then_with(|| a.name.cmp(&b.name)));
+    Ok(levels)
+}
+
+fn validate_sale(data: &StockMovementData, item: &StockItemData) -> Result<(), String> {
+    let student_id = data
+        .student_id
+        .as_ref()
+        .filter(|s| !s.trim().is_empty())
+        .ok_or("Sales must record the student the items were sold to")?;
+    let fee_assignment_id = data
+        .fee_assignment_id
+        .as_ref()
+        .filter(|f| !f.trim().is_empty())
+        .ok_or("Sales must record the fee assignment the sale is charged to")?;
+    if data.unit_price != Some(item.unit_price) {
+        return Err(format!("'{}' sells at ₦{}", item.name, item.unit_price));
+    }
+
+    let (_, assignment) = get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", fee_assignment_id)?
+        .ok_or_else(|| format!("Fee assignment '{}' not found", fee_assignment_id))?;
+    if &assignment.student_id != student_id {
+        return Err("Fee assignment does not belong to the student the items were sold to".to_string());
+    }
+    Ok(())
+}
+
+fn item_movements(item_id: &str) -> Result<Vec<StockMovementData>, String> {
+    Ok(list_doc_data::<StockMovementData>(STOCK_MOVEMENTS_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, m)| m.item_id == item_id)
+        .map(|(_, _, m)| m)
+        .collect())
+}
+
+fn quantity_on_hand(movements: &[StockMovementData]) -> i64 {
+    movements.iter().map(signed_quantity).sum()
+}
+
+fn signed_quantity(movement: &StockMovementData) -> i64 {
+    if OUTBOUND_MOVEMENTS.contains(&movement.movement_type.as_str()) {
+        -(movement.quantity as i64)
+    } else {
+        movement.quantity as i64
+    }
+}
@@ -0,0 +1,322 @@
+//! Investments Module - Fixed Deposits, Treasury Bills and Interest Income
+//!
+//! Endowment and surplus funds placed with banks (`investments`) are tracked from the
+//! placement to the withdrawal of principal and interest. This module enforces:
+//! - Each placement is funded by a bank debit of the principal, and each withdrawal is
+//!   received as a bank credit of the amount withdrawn
+//! - Terms (principal, rate, dates) are fixed once placed
+//! - Accrued interest and maturity are maintained only by the satellite's daily job,
+//!   which posts monthly interest accruals to the ledger and flags maturities
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::banking::BankTransactionData;
+use super::ledger::{find_account, journal_line, post_journal_entry};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::scan::scan_collection;
+use super::utils::validation_utils::*;
+
+pub const INVESTMENTS_COLLECTION: &str = "investments";
+
+// Bank postings are matched to placement/withdrawal dates within this many days
+const BANK_MATCH_WINDOW_DAYS: i64 = 3;
+
+// Accrued interest is receivable (Dr) against other income (Cr)
+const INTEREST_RECEIVABLE_ACCOUNT_CODE: &str = "1130";
+const INTEREST_INCOME_ACCOUNT_CODE: &str = "4300";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvestmentData {
+    pub investment_type: String,
+    pub institution: String,
+    pub reference: String,
+    pub principal: Money,
+    pub interest_rate: f64,
+    pub start_date: String,
+    pub maturity_date: String,
+    pub bank_account_id: String,
+    pub funding_transaction_id: String,
+    pub status: String,
+    #[serde(default)]
+    pub accrued_interest: Money,
+    pub last_accrual_date: Option<String>,
+    pub withdrawal_transaction_id: Option<String>,
+    pub withdrawn_amount: Option<Money>,
+    pub withdrawal_date: Option<String>,
+    pub notes: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Investment Validation
+///
+/// Checks:
+/// - Known instrument type, positive principal, sensible rate, maturity after start
+/// - Placement matched to a bank debit of the principal; withdrawal to a bank credit
+/// - Terms are immutable; accruals and maturity are set by the satellite only
+/// - Status workflow active → matured → withdrawn (early withdrawal allowed)
+pub fn validate_investment_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: InvestmentData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid investment data format: {}", e))?;
+
+    let valid_types = ["fixed_deposit", "treasury_bill"];
+    if !valid_types.contains(&data.investment_type.as_str()) {
+        return Err(format!(
+            "Invalid investment type '{}'. Must be one of: {}",
+            data.investment_type,
+            valid_types.join(", ")
+        ));
+    }
+    if data.institution.trim().is_empty() || data.reference.trim().is_empty() {
+        return Err("Institution and certificate reference are required".to_string());
+    }
+    if !data.principal.is_positive() {
+        return Err("Principal must be greater than 0".to_string());
+    }
+    if data.interest_rate <= 0.0 || data.interest_rate > 100.0 {
+        return Err("interestRate must be an annual percentage between 0 and 100".to_string());
+    }
+    if !is_valid_date_format(&data.start_date) || !is_valid_date_format(&data.maturity_date) {
+        return Err("Invalid start or maturity date format. Must be YYYY-MM-DD".to_string());
+    }
+    if data.maturity_date <= data.start_date {
+        return Err("maturityDate must be after startDate".to_string());
+    }
+
+    match context.data.data.current {
+        Some(ref before_doc) => {
+            let before: InvestmentData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous investment data: {}", e))?;
+            validate_investment_update(context, &before, &data)?;
+        }
+        None => {
+            if data.status != "active" {
+                return Err("New investments must have status 'active'".to_string());
+            }
+            if !data.accrued_interest.is_zero() || data.last_accrual_date.is_some() {
+                return Err("Interest is accrued by the satellite; new investments start with none".to_string());
+            }
+            if data.recorded_by != context.caller.to_text() {
+                return Err("recordedBy must be the principal recording the investment".to_string());
+            }
+            validate_bank_posting(
+                context,
+                &data,
+                &data.funding_transaction_id,
+                data.principal,
+                &data.start_date,
+                true,
+            )?;
+        }
+    }
+
+    if data.status == "withdrawn" {
+        let transaction_id = data
+            .withdrawal_transaction_id
+            .as_ref()
+            .filter(|id| !id.trim().is_empty())
+            .ok_or("Withdrawn investments must reference the bank credit that received the funds")?;
+        let amount = data.withdrawn_amount.ok_or("withdrawnAmount is required when withdrawing")?;
+        let date = data
+            .withdrawal_date
+            .as_ref()
+            .filter(|d| is_valid_date_format(d))
+            .ok_or("Withdrawn investments must have a valid withdrawalDate (YYYY-MM-DD)")?;
+        if !amount.is_positive() {
+            return Err("withdrawnAmount must be greater than 0".to_string());
+        }
+        if date < &data.start_date {
+            return Err("withdrawalDate cannot be before startDate".to_string());
+        }
+        validate_bank_posting(context, &data, transaction_id, amount, date, false)?;
+    } else if data.withdrawal_transaction_id.is_some() || data.withdrawn_amount.is_some() {
+        return Err("Withdrawal details can only be recorded when the investment is withdrawn".to_string());
+    }
+
+    Ok(())
+}
+
+fn validate_investment_update(
+    context: &AssertSetDocContext,
+    before: &InvestmentData,
+    data: &InvestmentData,
+) -> Result<(), String> {
+    if before.investment_type != data.investment_type
+        || before.principal != data.principal
+        || before.interest_rate != data.interest_rate
+        || before.start_date != data.start_date
+        || before.maturity_date != data.maturity_date
+        || before.bank_account_id != data.bank_account_id
+        || before.funding_transaction_id != data.funding_transaction_id
+        || before.reference != data.reference
+    {
+        return Err("AUDIT: Investment terms cannot be changed once placed".to_string());
+    }
+
+    let satellite = is_satellite_caller(&context.caller);
+    if !satellite && (before.accrued_interest != data.accrued_interest || before.last_accrual_date != data.last_accrual_date) {
+        return Err("SECURITY: Accrued interest is maintained by the satellite".to_string());
+    }
+
+    if before.status == data.status {
+        if before.status == "withdrawn" && !satellite {
+            return Err("AUDIT: Withdrawn investments cannot be modified".to_string());
+        }
+        return Ok(());
+    }
+
+    let valid_transitions = HashMap::from([
+        ("active", vec!["matured", "withdrawn"]),
+        ("matured", vec!["withdrawn"]),
+        ("withdrawn", vec![]),
+    ]);
+    let allowed = valid_transitions.get(before.status.as_str()).cloned().unwrap_or_default();
+    if !allowed.contains(&data.status.as_str()) {
+        return Err(format!(
+            "Invalid investment status transition from '{}' to '{}'. Allowed: [{}]",
+            before.status,
+            data.status,
+            allowed.join(", ")
+        ));
+    }
+    if data.status == "matured" && !satellite {
+        return Err("SECURITY: Maturity is recorded by the satellite on the maturity date".to_string());
+    }
+
+    Ok(())
+}
+
+/// The placement must be a debit (funds leaving the bank) and the withdrawal a credit
+/// (funds returning) on the investment's bank account, for the exact amount, near the
+/// date, and not already matched to another investment.
+fn validate_bank_posting(
+    context: &AssertSetDocContext,
+    data: &InvestmentData,
+    transaction_id: &str,
+    amount: Money,
+    date: &str,
+    is_placement: bool,
+) -> Result<(), String> {
+    let label = if is_placement { "placement" } else { "withdrawal" };
+    if transaction_id.trim().is_empty() {
+        return Err(format!("Investment {} must reference its bank transaction", label));
+    }
+
+    let (_, transaction) = get_doc_data::<BankTransactionData>("bank_transactions", transaction_id)?
+        .ok_or_else(|| format!("Bank transaction '{}' not found", transaction_id))?;
+
+    if transaction.bank_account_id != data.bank_account_id {
+        return Err(format!("Bank transaction for the {} is on a different bank account", label));
+    }
+
+    let posted = if is_placement { transaction.debit_amount } else { transaction.credit_amount };
+    if !posted.is_positive() {
+        return Err(format!(
+            "Bank transaction for the {} must be a {}",
+            label,
+            if is_placement { "debit" } else { "credit" }
+        ));
+    }
+    if posted != amount {
+        return Err(format!(
+            "Investment {} of ₦{} does not match the bank transaction of ₦{}",
+            label, amount, posted
+        ));
+    }
+
+    let transaction_date = transaction.transaction_date.get(0..10).unwrap_or_default();
+    let gap = days_between(transaction_date, date).ok_or("Referenced bank transaction has no valid transaction date")?;
+    if gap.abs() > BANK_MATCH_WINDOW_DAYS {
+        return Err(format!(
+            "Bank transaction on {} is more than {} days from the {} date {}",
+            transaction_date, BANK_MATCH_WINDOW_DAYS, label, date
+        ));
+    }
+
+    let investments = list_doc_data::<InvestmentData>(INVESTMENTS_COLLECTION, None)?;
+    if let Some((_, _, other)) = investments.iter().find(|(key, _, i)| {
+        key != &context.data.key
+            && (i.funding_transaction_id == transaction_id || i.withdrawal_transaction_id.as_deref() == Some(transaction_id))
+    }) {
+        return Err(format!(
+            "Bank transaction '{}' is already matched to investment {}",
+            transaction_id, other.reference
+        ));
+    }
+
+    Ok(())
+}
+
+/// Daily job: accrue interest on active investments up to the end of the last complete
+/// month (or maturity), posting each accrual to the ledger, and mark investments whose
+/// maturity date has been reached as matured. Scans from `cursor` and returns the cursor
+/// to continue from when the scan stopped at the instruction budget.
+pub fn run_investment_accruals(cursor: Option<String>) -> Result<Option<String>, String> {
+    let today = current_date();
+    let month_end = previous_month_end(&today).ok_or("Could not determine the accrual date")?;
+
+    let progress = scan_collection::<InvestmentData>(INVESTMENTS_COLLECTION, cursor, |key, doc, mut investment| {
+        if investment.status != "active" {
+            return Ok(());
+        }
+
+        let matured = investment.maturity_date <= today;
+        let accrue_to = if matured { investment.maturity_date.clone() } else { month_end.clone() };
+        let accrued_from = investment.last_accrual_date.clone().unwrap_or_else(|| investment.start_date.clone());
+        let days = days_between(&accrued_from, &accrue_to).unwrap_or(0);
+
+        if days <= 0 && !matured {
+            return Ok(());
+        }
+
+        if days > 0 {
+            let interest = investment.principal.percent(investment.interest_rate * days as f64 / 365.0);
+            post_interest_accrual(&key, &investment, &accrue_to, interest)?;
+            investment.accrued_interest += interest;
+            investment.last_accrual_date = Some(accrue_to);
+        }
+        if matured {
+            investment.status = "matured".to_string();
+        }
+        investment.updated_at = ic_cdk::api::time();
+
+        set_doc_data(INVESTMENTS_COLLECTION, &key, &investment, doc.description.clone(), doc.version)?;
+        Ok(())
+    })?;
+
+    Ok(progress.next_cursor)
+}
+
+fn post_interest_accrual(key: &str, investment: &InvestmentData, accrue_to: &str, interest: Money) -> Result<(), String> {
+    if !interest.is_positive() {
+        return Ok(());
+    }
+
+    let receivable = find_account(INTEREST_RECEIVABLE_ACCOUNT_CODE)?;
+    let income = find_account(INTEREST_INCOME_ACCOUNT_CODE)?;
+    let description = format!("Interest accrued on {} {} to {}", investment.institution, investment.reference, accrue_to);
+
+    post_journal_entry(
+        &format!("investment-accrual-{}-{}", key, accrue_to),
+        &format!("JE-INT-{}-{}", investment.reference, accrue_to.replace('-', "")),
+        accrue_to,
+        &description,
+        "other",
+        Some(key.to_string()),
+        vec![
+            journal_line(&receivable, interest, Money::ZERO, &description),
+            journal_line(&income, Money::ZERO, interest, &description),
+        ],
+    )?;
+
+    Ok(())
+}
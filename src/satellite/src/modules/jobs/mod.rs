@@ -0,0 +1,145 @@
+//! Jobs Module - Scheduled Background Work
+//!
+//! Timers do not survive upgrades, so they are (re)started from the satellite's
+//! `on_init` and `on_post_upgrade` hooks. Each job runs independently; a failing job is
+//! logged and does not stop the others.
+//!
+//! Jobs that walk a whole collection scan it in chunks (see [`super::utils::scan`]). A
+//! job that stops at the instruction budget returns a cursor and is continued from it in
+//! a fresh message, until it reaches the end of the collection.
+//!
+//! Replication to an analytics canister (see [`super::replication`]) is pushed every few
+//! minutes, and queued notifications (see [`super::notifications::delivery`]) are sent
+//! every minute. Exchange rates (see [`super::fx`]) are refreshed daily.
+//!
+//! The last run of each job (start, finish and error) is kept in memory for the health
+//! check; it starts empty after an upgrade, like the timers.
+
+use candid::CandidType;
+use junobuild_satellite::error;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::fx::refresh_exchange_rates;
+use super::investments::run_investment_accruals;
+use super::late_fees::apply_late_fees;
+use super::ledger::prepayments::run_prepayment_amortization;
+use super::ledger::recurring::run_recurring_journals;
+use super::notifications::delivery::send_queued_notifications;
+use super::replication::push_replication_batch;
+use super::reports::refresh_term_rollups;
+use super::utils::idempotency::prune_idempotency_keys;
+
+const DAILY: Duration = Duration::from_secs(24 * 60 * 60);
+const REPLICATION_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const NOTIFICATION_INTERVAL: Duration = Duration::from_secs(60);
+
+// A job takes the cursor to resume from and returns the next one, or None when done
+type Job = (&'static str, fn(Option<String>) -> Result<Option<String>, String>);
+
+#[derive(CandidType, Deserialize, Serialize, Clone)]
+pub struct JobStatus {
+    pub job: String,
+    pub last_started_at: Option<u64>,
+    pub last_finished_at: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+thread_local! {
+    // Last run of each job since the canister was installed or upgraded
+    static JOB_RUNS: RefCell<BTreeMap<&'static str, JobStatus>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Start the recurring job timers.
+pub fn schedule_jobs() {
+    ic_cdk_timers::set_timer_interval(DAILY, run_daily_jobs);
+    ic_cdk_timers::set_timer_interval(REPLICATION_INTERVAL, || ic_cdk::futures::spawn(run_replication()));
+    ic_cdk_timers::set_timer_interval(NOTIFICATION_INTERVAL, || ic_cdk::futures::spawn(run_notifications()));
+    ic_cdk_timers::set_timer_interval(DAILY, || ic_cdk::futures::spawn(run_exchange_rates()));
+}
+
+/// The last run of every job that has run since the canister was installed or upgraded.
+pub fn job_statuses() -> Vec<JobStatus> {
+    JOB_RUNS.with(|runs| runs.borrow().values().cloned().collect())
+}
+
+async fn run_replication() {
+    job_started("replication");
+    let result = push_replication_batch().await;
+    if let Err(ref e) = result {
+        let _ = error(format!("Replication push failed: {}", e));
+    }
+    job_finished("replication", result.err());
+}
+
+async fn run_notifications() {
+    job_started("notifications");
+    let result = send_queued_notifications().await;
+    if let Err(ref e) = result {
+        let _ = error(format!("Notification delivery failed: {}", e));
+    }
+    job_finished("notifications", result.err());
+}
+
+async fn run_exchange_rates() {
+    job_started("exchange rates");
+    let result = refresh_exchange_rates().await;
+    if let Err(ref e) = result {
+        let _ = error(format!("Exchange rate refresh failed: {}", e));
+    }
+    job_finished("exchange rates", result.err());
+}
+
+fn run_daily_jobs() {
+    let jobs: [Job; 6] = [
+        ("investment accruals", run_investment_accruals),
+        ("recurring journals", run_recurring_journals),
+        ("prepayment amortization", run_prepayment_amortization),
+        ("late fees", apply_late_fees),
+        ("report rollups", refresh_term_rollups),
+        ("idempotency pruning", prune_idempotency_keys),
+    ];
+
+    for job in jobs {
+        job_started(job.0);
+        run_job(job, None);
+    }
+}
+
+fn run_job((name, job): Job, cursor: Option<String>) {
+    match job(cursor) {
+        Ok(Some(next)) => {
+            ic_cdk_timers::set_timer(Duration::ZERO, move || run_job((name, job), Some(next)));
+        }
+        Ok(None) => job_finished(name, None),
+        Err(e) => {
+            let _ = error(format!("Daily job '{}' failed: {}", name, e));
+            job_finished(name, Some(e));
+        }
+    }
+}
+
+fn job_started(name: &'static str) {
+    JOB_RUNS.with(|runs| {
+        runs.borrow_mut()
+            .entry(name)
+            .or_insert_with(|| JobStatus {
+                job: name.to_string(),
+                last_started_at: None,
+                last_finished_at: None,
+                last_error: None,
+            })
+            .last_started_at = Some(ic_cdk::api::time());
+    });
+}
+
+fn job_finished(name: &'static str, failure: Option<String>) {
+    JOB_RUNS.with(|runs| {
+        if let Some(run) = runs.borrow_mut().get_mut(name) {
+            run.last_finished_at = Some(ic_cdk::api::time());
+            run.last_error = failure;
+        }
+    });
+}
@@ -0,0 +1,103 @@
+//! Late Fees Module - Overdue Fee Assignment Penalties
+//!
+//! A daily job charges the school's late fee (see [`super::settings`]) on every fee
+//! assignment still owing once its due date and grace period have passed. The fee is
+//! either a flat amount or a percentage of the outstanding balance, and is appended to
+//! the assignment as its own `late_fee` item, at most once per assignment. The item
+//! carries a note explaining how the fee was computed, and the change is recorded in the
+//! audit log.
+
+use super::audit::record_system_change;
+use super::fees::{FeeItemData, StudentFeeAssignmentData};
+use super::settings::{school_settings, SchoolSettingsData};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::scan::scan_collection;
+use super::utils::validation_utils::*;
+use super::verification::balance_after;
+
+pub const LATE_FEE_CATEGORY_ID: &str = "late_fee";
+
+/// Charge the late fee on overdue fee assignments, from `cursor` on. Runs daily; returns
+/// the cursor to continue from when the scan stopped at the instruction budget.
+pub fn apply_late_fees(cursor: Option<String>) -> Result<Option<String>, String> {
+    let settings = school_settings()?;
+    if settings.late_fee_type.is_none() {
+        return Ok(None);
+    }
+    let today = current_date();
+
+    let progress = scan_collection::<StudentFeeAssignmentData>(
+        "student_fee_assignments",
+        cursor,
+        |key, doc, mut assignment| {
+            let days_overdue = match assignment.due_date.as_deref().and_then(|due| days_between(due, &today)) {
+                Some(days) if days > settings.late_fee_grace_days as i64 => days,
+                _ => return Ok(()),
+            };
+            if !assignment.balance.is_positive()
+                || assignment.fee_items.iter().any(|item| item.category_id == LATE_FEE_CATEGORY_ID)
+            {
+                return Ok(());
+            }
+            let (amount, basis) = late_fee(&settings, assignment.balance);
+            if !amount.is_positive() {
+                return Ok(());
+            }
+
+            let note = format!(
+                "Late fee of ₦{} ({}) applied on {}, {} days after the due date {}",
+                amount,
+                basis,
+                today,
+                days_overdue,
+                assignment.due_date.as_deref().unwrap_or_default()
+            );
+            assignment.fee_items.push(FeeItemData {
+                category_id: LATE_FEE_CATEGORY_ID.to_string(),
+                category_name: "Late payment fee".to_string(),
+                fee_type: "other".to_string(),
+                amount,
+                amount_paid: Money::ZERO,
+                balance: amount,
+                is_mandatory: true,
+                is_optional: Some(false),
+                is_selected: None,
+                extra: serde_json::Map::from_iter([("note".to_string(), serde_json::Value::String(note))]),
+            });
+
+            let old_status = assignment.status.clone();
+            assignment.total_amount += amount;
+            if let Some(original) = assignment.original_amount {
+                assignment.original_amount = Some(original + amount);
+            }
+            let (balance, status) = balance_after(assignment.total_amount, assignment.amount_paid);
+            assignment.balance = balance;
+            assignment.status = status.to_string();
+
+            set_doc_data("student_fee_assignments", &key, &assignment, doc.description, doc.version)?;
+            record_system_change(
+                "student_fee_assignments",
+                &key,
+                "late_fee",
+                Some(old_status),
+                Some(assignment.status.clone()),
+            )?;
+            Ok(())
+        },
+    )?;
+
+    Ok(progress.next_cursor)
+}
+
+// The late fee on an outstanding balance, with how it was computed
+fn late_fee(settings: &SchoolSettingsData, balance: Money) -> (Money, String) {
+    match settings.late_fee_type.as_deref() {
+        Some("flat") => (settings.late_fee_amount, "flat fee".to_string()),
+        Some("percentage") => (
+            balance.percent(settings.late_fee_percent),
+            format!("{}% of ₦{} outstanding", settings.late_fee_percent, balance),
+        ),
+        _ => (Money::ZERO, String::new()),
+    }
+}
@@ -0,0 +1,170 @@
+//! Ledger Module - Server-side Journal Postings
+//!
+//! The general ledger lives in `journal_entries`, with accounts in `chart_of_accounts`,
+//! both maintained by the frontend's accounting service. Postings the satellite makes
+//! on its own (e.g. interest accruals) are written here as balanced, already-posted
+//! entries keyed deterministically so a job that runs twice does not post twice.
+//! Memorandum entries (status `memo`) record non-monetary events such as budget
+//! reallocations in the journal without affecting account balances. Month-end journals
+//! that repeat are posted from templates (see [`recurring`]), and expenses paid in
+//! advance are amortized over the months they cover (see [`prepayments`]).
+
+pub mod prepayments;
+pub mod recurring;
+
+use junobuild_satellite::id;
+use serde::{Deserialize, Serialize};
+
+use super::close::PERIOD_CLOSES_COLLECTION;
+use super::utils::cache::cached_list_doc_data;
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::period_end;
+
+pub const JOURNAL_ENTRIES_COLLECTION: &str = "journal_entries";
+pub const CHART_OF_ACCOUNTS_COLLECTION: &str = "chart_of_accounts";
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartOfAccountData {
+    pub account_code: String,
+    pub account_name: String,
+    pub account_type: String,
+    pub is_active: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalLineData {
+    pub account_id: String,
+    pub account_name: String,
+    pub account_code: String,
+    pub debit: Money,
+    pub credit: Money,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntryData {
+    pub entry_number: String,
+    pub entry_date: String,
+    pub description: String,
+    pub lines: Vec<JournalLineData>,
+    pub total_debit: Money,
+    pub total_credit: Money,
+    pub reference_type: String,
+    pub reference_id: Option<String>,
+    pub status: String,
+    pub posted_at: Option<u64>,
+    pub created_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Find an active account by its chart-of-accounts code.
+pub fn find_account(account_code: &str) -> Result<(String, ChartOfAccountData), String> {
+    cached_list_doc_data::<ChartOfAccountData>(CHART_OF_ACCOUNTS_COLLECTION)?
+        .into_iter()
+        .find(|(_, _, a)| a.account_code == account_code && a.is_active)
+        .map(|(key, _, account)| (key, account))
+        .ok_or_else(|| format!("Ledger account {} not found in the chart of accounts", account_code))
+}
+
+/// Journal line for an account.
+pub fn journal_line(account: &(String, ChartOfAccountData), debit: Money, credit: Money, description: &str) -> JournalLineData {
+    JournalLineData {
+        account_id: account.0.clone(),
+        account_name: account.1.account_name.clone(),
+        account_code: account.1.account_code.clone(),
+        debit,
+        credit,
+        description: Some(description.to_string()),
+    }
+}
+
+/// Post a balanced journal entry as the satellite. Returns `false` without writing when
+/// an entry with the same key has already been posted.
+pub fn post_journal_entry(
+    key: &str,
+    entry_number: &str,
+    entry_date: &str,
+    description: &str,
+    reference_type: &str,
+    reference_id: Option<String>,
+    lines: Vec<JournalLineData>,
+) -> Result<bool, String> {
+    write_journal_entry(key, entry_number, entry_date, description, reference_type, reference_id, lines, "posted")
+}
+
+/// Date a month's scheduled entry is posted on: the last day of the month, or today when
+/// the month was closed before the entry was posted, catching it up in the current month.
+/// Returns the date and whether the entry is a catch-up.
+pub fn month_end_posting_date(period: &str, today: &str) -> Result<(String, bool), String> {
+    if !doc_exists(PERIOD_CLOSES_COLLECTION, period)? {
+        return Ok((period_end(period).ok_or("Could not determine the period end")?, false));
+    }
+    if doc_exists(PERIOD_CLOSES_COLLECTION, &today[0..7])? {
+        return Err(format!("PERIOD_CLOSED: {} and the current month are closed", period));
+    }
+    Ok((today.to_string(), true))
+}
+
+/// Record a memorandum entry: kept in the journal for the audit trail, but excluded
+/// from balances, which only count `posted` entries.
+pub fn record_memo_entry(
+    key: &str,
+    entry_number: &str,
+    entry_date: &str,
+    description: &str,
+    reference_type: &str,
+    reference_id: Option<String>,
+    lines: Vec<JournalLineData>,
+) -> Result<bool, String> {
+    write_journal_entry(key, entry_number, entry_date, description, reference_type, reference_id, lines, "memo")
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_journal_entry(
+    key: &str,
+    entry_number: &str,
+    entry_date: &str,
+    description: &str,
+    reference_type: &str,
+    reference_id: Option<String>,
+    lines: Vec<JournalLineData>,
+    status: &str,
+) -> Result<bool, String> {
+    if doc_exists(JOURNAL_ENTRIES_COLLECTION, key)? {
+        return Ok(false);
+    }
+
+    let total_debit: Money = lines.iter().map(|l| l.debit).sum();
+    let total_credit: Money = lines.iter().map(|l| l.credit).sum();
+    if lines.len() < 2 || total_debit != total_credit {
+        return Err(format!(
+            "Journal entry '{}' does not balance: debits ₦{}, credits ₦{}",
+            entry_number, total_debit, total_credit
+        ));
+    }
+
+    let now = ic_cdk::api::time();
+    let entry = JournalEntryData {
+        entry_number: entry_number.to_string(),
+        entry_date: entry_date.to_string(),
+        description: description.to_string(),
+        lines,
+        total_debit,
+        total_credit,
+        reference_type: reference_type.to_string(),
+        reference_id,
+        status: status.to_string(),
+        posted_at: if status == "posted" { Some(now) } else { None },
+        created_by: id().to_text(),
+        created_at: now,
+        updated_at: now,
+    };
+
+    set_doc_data(JOURNAL_ENTRIES_COLLECTION, key, &entry, None, None)?;
+    Ok(true)
+}
@@ -0,0 +1,360 @@
+//! Prepayment amortization.
+//!
+//! An expense paid in advance for several months (annual insurance, a software licence,
+//! a year's rent) is recorded as a prepayment in `prepayments`, linked to the expense,
+//! with the months it covers. When the prepayment is recorded its amount is moved off
+//! the expense account onto the prepaid asset account (Prepaid Expenses, 1140, unless
+//! another is given). The daily job then charges one month's share back to the expense
+//! account at each month end, the last month taking any kobo left by rounding, so the
+//! cost reaches the income statement evenly over the cover.
+//!
+//! Prepayments are never edited or deleted. One that ends early (a cancelled policy) is
+//! cancelled, and whatever is still prepaid is charged to the expense account at once.
+//! `get_prepaid_balances` reports what remains prepaid on each.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::{find_account, journal_line, month_end_posting_date, post_journal_entry};
+use crate::modules::expenses::splits::expense_account_code;
+use crate::modules::expenses::ExpenseData;
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::utils::scan::scan_collection;
+use crate::modules::utils::validation_utils::*;
+
+pub const PREPAYMENTS_COLLECTION: &str = "prepayments";
+
+const PREPAID_ACCOUNT_CODE: &str = "1140";
+const MAX_COVERAGE_MONTHS: u32 = 60;
+const MIN_CANCEL_REASON_LENGTH: usize = 10;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrepaymentData {
+    pub expense_id: String,
+    pub description: String,
+    pub amount: Money,
+    // Months covered: the first (YYYY-MM) and how many
+    pub coverage_start: String,
+    pub coverage_months: u32,
+    // Prepaid asset account; Prepaid Expenses (1140) when not given
+    pub prepaid_account_code: Option<String>,
+    pub status: String,
+    // Progress, maintained by the satellite
+    #[serde(default)]
+    pub months_amortized: u32,
+    #[serde(default)]
+    pub amortized_amount: Money,
+    #[serde(default)]
+    pub last_amortized_period: Option<String>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    pub cancel_reason: Option<String>,
+    pub created_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct PrepaidBalance {
+    pub prepayment_id: String,
+    pub expense_id: String,
+    pub description: String,
+    pub coverage_start: String,
+    pub coverage_end: String,
+    pub coverage_months: u32,
+    pub months_amortized: u32,
+    pub amount: f64,
+    pub amortized_amount: f64,
+    pub remaining_balance: f64,
+    pub status: String,
+}
+
+/// Prepayment Validation
+///
+/// Checks:
+/// - Recorded by finance staff against an approved or paid, unsplit expense, once
+/// - Amount positive and no more than the expense; cover of 2-60 months starting no
+///   earlier than the month the expense was paid
+/// - Prepaid and expense accounts exist
+/// - Never edited: the only change is active → cancelled, with a reason
+pub fn validate_prepayment_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+        return Err("SECURITY: Only finance staff can record prepayments".to_string());
+    }
+
+    let data: PrepaymentData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid prepayment data format: {}", e))?;
+
+    if let Some(ref doc) = context.data.data.current {
+        let before: PrepaymentData =
+            decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous prepayment data: {}", e))?;
+        if before.status != "active" || data.status != "cancelled" {
+            return Err("AUDIT: Prepayments cannot be edited; an active prepayment can only be cancelled".to_string());
+        }
+        if data.expense_id != before.expense_id
+            || data.description != before.description
+            || data.amount != before.amount
+            || data.coverage_start != before.coverage_start
+            || data.coverage_months != before.coverage_months
+            || data.prepaid_account_code != before.prepaid_account_code
+            || data.months_amortized != before.months_amortized
+            || data.amortized_amount != before.amortized_amount
+            || data.last_amortized_period != before.last_amortized_period
+            || data.created_by != before.created_by
+        {
+            return Err("AUDIT: Prepayments cannot be edited; an active prepayment can only be cancelled".to_string());
+        }
+        if data.cancel_reason.as_ref().map(|r| r.trim().len() < MIN_CANCEL_REASON_LENGTH).unwrap_or(true) {
+            return Err(format!(
+                "Cancelling a prepayment requires a reason of at least {} characters",
+                MIN_CANCEL_REASON_LENGTH
+            ));
+        }
+        return Ok(());
+    }
+
+    if data.status != "active" {
+        return Err("New prepayments must be active".to_string());
+    }
+    if data.months_amortized != 0 || !data.amortized_amount.is_zero() || data.last_amortized_period.is_some() {
+        return Err("AUDIT: Amortization progress is recorded by the satellite".to_string());
+    }
+    if data.created_by != context.caller.to_text() {
+        return Err("createdBy must be the principal recording the prepayment".to_string());
+    }
+    if data.description.trim().is_empty() {
+        return Err("Prepayment description is required".to_string());
+    }
+
+    let (_, expense) = get_doc_data::<ExpenseData>("expenses", &data.expense_id)?
+        .ok_or_else(|| format!("Expense '{}' not found", data.expense_id))?;
+    if expense.status != "approved" && expense.status != "paid" {
+        return Err(format!("Only approved or paid expenses can be prepaid; this one is {}", expense.status));
+    }
+    if !expense.split_lines.is_empty() {
+        return Err("A split expense cannot be recorded as a prepayment".to_string());
+    }
+    let recorded = list_doc_data::<PrepaymentData>(PREPAYMENTS_COLLECTION, None)?
+        .into_iter()
+        .any(|(key, _, p)| key != context.data.key && p.expense_id == data.expense_id && p.status != "cancelled");
+    if recorded {
+        return Err(format!("Expense {} already has a prepayment", expense.reference));
+    }
+
+    if !data.amount.is_positive() || data.amount > expense.amount {
+        return Err(format!(
+            "Prepaid amount must be greater than 0 and no more than the expense amount of ₦{}",
+            expense.amount
+        ));
+    }
+    if data.coverage_months < 2 || data.coverage_months > MAX_COVERAGE_MONTHS {
+        return Err(format!("A prepayment covers 2-{} months", MAX_COVERAGE_MONTHS));
+    }
+    if !is_valid_period(&data.coverage_start) {
+        return Err("Coverage start must be in format YYYY-MM".to_string());
+    }
+    if expense.payment_date.get(0..7).map(|paid| data.coverage_start.as_str() < paid).unwrap_or(false) {
+        return Err("Cover cannot start before the month the expense was paid".to_string());
+    }
+
+    find_account(prepaid_account(&data))?;
+    find_account(&expense_account_code(&expense.category)?)?;
+
+    Ok(())
+}
+
+/// Prepayments are cancelled, never deleted.
+pub fn validate_prepayment_delete() -> Result<(), String> {
+    Err("AUDIT: Prepayments cannot be deleted; cancel the prepayment instead".to_string())
+}
+
+/// Called when a prepayment is saved: moves a new prepayment onto the prepaid account,
+/// and charges what remains prepaid to the expense account when one is cancelled.
+pub fn on_prepayment_saved(
+    key: &str,
+    before: Option<&PrepaymentData>,
+    prepayment: &PrepaymentData,
+) -> Result<(), String> {
+    let (_, expense) = get_doc_data::<ExpenseData>("expenses", &prepayment.expense_id)?
+        .ok_or_else(|| format!("Expense '{}' not found", prepayment.expense_id))?;
+    let prepaid = find_account(prepaid_account(prepayment))?;
+    let expense_account = find_account(&expense_account_code(&expense.category)?)?;
+    let today = current_date();
+
+    match before {
+        None => {
+            let description = format!("Prepayment: {} ({})", prepayment.description, expense.reference);
+            post_journal_entry(
+                &format!("prepayment-{}", key),
+                &format!("JE-PRE-{}", expense.reference),
+                &today,
+                &description,
+                "adjustment",
+                Some(key.to_string()),
+                vec![
+                    journal_line(&prepaid, prepayment.amount, Money::ZERO, &description),
+                    journal_line(&expense_account, Money::ZERO, prepayment.amount, &description),
+                ],
+            )?;
+        }
+        Some(before) if before.status == "active" && prepayment.status == "cancelled" => {
+            let remaining = prepayment.amount - prepayment.amortized_amount;
+            if !remaining.is_positive() {
+                return Ok(());
+            }
+            let description = format!("Prepayment cancelled: {} ({})", prepayment.description, expense.reference);
+            post_journal_entry(
+                &format!("prepayment-cancel-{}", key),
+                &format!("JE-PRE-{}-X", expense.reference),
+                &today,
+                &description,
+                "adjustment",
+                Some(key.to_string()),
+                vec![
+                    journal_line(&expense_account, remaining, Money::ZERO, &description),
+                    journal_line(&prepaid, Money::ZERO, remaining, &description),
+                ],
+            )?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Daily job: charges each active prepayment's share for the months that have ended
+/// since it was last amortized, completing it after the last month covered. A
+/// prepayment that cannot be amortized keeps the error in `lastError` and is retried the
+/// next day.
+pub fn run_prepayment_amortization(cursor: Option<String>) -> Result<Option<String>, String> {
+    let today = current_date();
+
+    let progress = scan_collection::<PrepaymentData>(PREPAYMENTS_COLLECTION, cursor, |key, doc, mut prepayment| {
+        if prepayment.status != "active" {
+            return Ok(());
+        }
+
+        let (amortized_before, error_before) = (prepayment.months_amortized, prepayment.last_error.clone());
+        prepayment.last_error = amortize_due_months(&key, &mut prepayment, &today).err();
+        if prepayment.months_amortized == amortized_before && prepayment.last_error == error_before {
+            return Ok(());
+        }
+        prepayment.updated_at = ic_cdk::api::time();
+
+        set_doc_data(PREPAYMENTS_COLLECTION, &key, &prepayment, doc.description.clone(), doc.version)?;
+        Ok(())
+    })?;
+
+    Ok(progress.next_cursor)
+}
+
+/// What remains prepaid on each prepayment, active ones first.
+pub fn get_prepaid_balances() -> Result<Vec<PrepaidBalance>, String> {
+    if !caller_has_any_role(&caller(), &[Role::SuperAdmin, Role::Bursar, Role::Accountant, Role::Auditor]) {
+        return Err("SECURITY: Not authorised to view prepaid balances".to_string());
+    }
+
+    let mut balances: Vec<PrepaidBalance> = list_doc_data::<PrepaymentData>(PREPAYMENTS_COLLECTION, None)?
+        .into_iter()
+        .map(|(key, _, p)| {
+            // Cancelled prepayments were charged off in full
+            let remaining = if p.status == "cancelled" { Money::ZERO } else { p.amount - p.amortized_amount };
+            PrepaidBalance {
+                coverage_end: coverage_end(&p).unwrap_or_default(),
+                prepayment_id: key,
+                expense_id: p.expense_id,
+                description: p.description,
+                coverage_start: p.coverage_start,
+                coverage_months: p.coverage_months,
+                months_amortized: p.months_amortized,
+                amount: p.amount.naira(),
+                amortized_amount: p.amortized_amount.naira(),
+                remaining_balance: remaining.naira(),
+                status: p.status,
+            }
+        })
+        .collect();
+    balances.sort_by(|a, b| {
+        (a.status != "active").cmp(&(b.status != "active")).then(a.coverage_start.cmp(&b.coverage_start))
+    });
+
+    Ok(balances)
+}
+
+fn amortize_due_months(key: &str, prepayment: &mut PrepaymentData, today: &str) -> Result<(), String> {
+    let (_, expense) = get_doc_data::<ExpenseData>("expenses", &prepayment.expense_id)?
+        .ok_or_else(|| format!("Expense '{}' not found", prepayment.expense_id))?;
+    let prepaid = find_account(prepaid_account(prepayment))?;
+    let expense_account = find_account(&expense_account_code(&expense.category)?)?;
+
+    while prepayment.months_amortized < prepayment.coverage_months {
+        let period = match prepayment.last_amortized_period {
+            Some(ref last) => next_period(last),
+            None => Some(prepayment.coverage_start.clone()),
+        }
+        .ok_or("Could not determine the next period")?;
+        let month_end = period_end(&period).ok_or("Could not determine the period end")?;
+        if month_end.as_str() > today {
+            return Ok(());
+        }
+
+        let share = monthly_share(prepayment);
+        let (entry_date, caught_up) = month_end_posting_date(&period, today)?;
+        let description = format!(
+            "Amortization of {} - {} ({} of {}){}",
+            prepayment.description,
+            period,
+            prepayment.months_amortized + 1,
+            prepayment.coverage_months,
+            if caught_up { format!(", caught up: {} is closed", period) } else { String::new() }
+        );
+        post_journal_entry(
+            &format!("prepayment-{}-{}", key, period),
+            &format!("JE-PRE-{}-{}", expense.reference, period.replace('-', "")),
+            &entry_date,
+            &description,
+            "adjustment",
+            Some(key.to_string()),
+            vec![
+                journal_line(&expense_account, share, Money::ZERO, &description),
+                journal_line(&prepaid, Money::ZERO, share, &description),
+            ],
+        )?;
+
+        prepayment.months_amortized += 1;
+        prepayment.amortized_amount += share;
+        prepayment.last_amortized_period = Some(period);
+    }
+
+    prepayment.status = "completed".to_string();
+    Ok(())
+}
+
+// An even share in whole kobo; the last month takes what is left
+fn monthly_share(prepayment: &PrepaymentData) -> Money {
+    if prepayment.months_amortized + 1 >= prepayment.coverage_months {
+        return prepayment.amount - prepayment.amortized_amount;
+    }
+    Money::from_kobo(prepayment.amount.kobo() / prepayment.coverage_months as i64)
+}
+
+fn prepaid_account(prepayment: &PrepaymentData) -> &str {
+    prepayment.prepaid_account_code.as_deref().filter(|c| !c.trim().is_empty()).unwrap_or(PREPAID_ACCOUNT_CODE)
+}
+
+// Last month covered (YYYY-MM)
+fn coverage_end(prepayment: &PrepaymentData) -> Option<String> {
+    let mut period = prepayment.coverage_start.clone();
+    for _ in 1..prepayment.coverage_months {
+        period = next_period(&period)?;
+    }
+    Some(period)
+}
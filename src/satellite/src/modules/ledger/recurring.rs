@@ -0,0 +1,281 @@
+//! Recurring journal entries.
+//!
+//! Month-end journals that repeat unchanged (amortising a rent prepayment, accruing
+//! insurance) are set up once in `recurring_journals` as a template: the lines posted
+//! each month, the first month, and for a prepayment the number of months it is spread
+//! over. The daily job posts each active template for every month that has ended since it
+//! last posted, dated on the month's last day and keyed by template and month, so a
+//! month is never posted twice.
+//!
+//! Templates must balance, and entries only go into open periods: a month that was
+//! closed before its entry was posted is caught up in the current month instead. A
+//! template's lines and schedule are fixed once it has posted; to change them, cancel it
+//! and set up a new one.
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{find_account, journal_line, month_end_posting_date, post_journal_entry};
+use crate::modules::close::PERIOD_CLOSES_COLLECTION;
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::utils::scan::scan_collection;
+use crate::modules::utils::validation_utils::*;
+
+pub const RECURRING_JOURNALS_COLLECTION: &str = "recurring_journals";
+
+const KINDS: [&str; 3] = ["prepayment", "accrual", "other"];
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecurringJournalLine {
+    pub account_code: String,
+    pub debit: Money,
+    pub credit: Money,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecurringJournalData {
+    pub name: String,
+    // `prepayment` (amortised over a fixed number of months), `accrual` or `other`
+    pub kind: String,
+    // Posted each month
+    pub lines: Vec<RecurringJournalLine>,
+    // First month posted (YYYY-MM) and how many months; open-ended when not set
+    pub start_period: String,
+    pub periods: Option<u32>,
+    pub status: String,
+    // Progress, maintained by the satellite
+    #[serde(default)]
+    pub posted_periods: u32,
+    #[serde(default)]
+    pub last_posted_period: Option<String>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    pub created_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Recurring Journal Validation
+///
+/// Checks:
+/// - Maintained by accountants and administrators; progress is recorded by the satellite
+/// - Name, kind and at least two lines on active accounts, each a debit or a credit
+/// - Debits equal credits
+/// - Start month is valid and open; prepayments run for at least two months
+/// - Status: active ⇄ paused → cancelled; completed is set by the satellite
+/// - Lines and schedule are fixed once an entry has been posted
+pub fn validate_recurring_journal_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Accountant]) {
+        return Err("SECURITY: Only accountants can maintain recurring journals".to_string());
+    }
+
+    let data: RecurringJournalData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid recurring journal data format: {}", e))?;
+
+    let before: RecurringJournalData = match context.data.data.current {
+        Some(ref doc) => {
+            decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous recurring journal data: {}", e))?
+        }
+        None => {
+            if data.status != "active" && data.status != "paused" {
+                return Err("New recurring journals must be active or paused".to_string());
+            }
+            if data.posted_periods != 0 || data.last_posted_period.is_some() || data.last_error.is_some() {
+                return Err("AUDIT: Posting progress is recorded by the satellite".to_string());
+            }
+            if data.created_by != context.caller.to_text() {
+                return Err("createdBy must be the principal setting up the recurring journal".to_string());
+            }
+            if doc_exists(PERIOD_CLOSES_COLLECTION, &data.start_period)? {
+                return Err(format!(
+                    "PERIOD_CLOSED: {} is closed; start the recurring journal in an open month",
+                    data.start_period
+                ));
+            }
+            return validate_template(&data);
+        }
+    };
+
+    if data.posted_periods != before.posted_periods
+        || data.last_posted_period != before.last_posted_period
+        || data.last_error != before.last_error
+        || data.created_by != before.created_by
+    {
+        return Err("AUDIT: Posting progress is recorded by the satellite".to_string());
+    }
+
+    if data.status != before.status {
+        let valid_transitions = HashMap::from([
+            ("active", vec!["paused", "cancelled"]),
+            ("paused", vec!["active", "cancelled"]),
+            ("completed", vec![]),
+            ("cancelled", vec![]),
+        ]);
+        let allowed = valid_transitions.get(before.status.as_str()).cloned().unwrap_or_default();
+        if !allowed.contains(&data.status.as_str()) {
+            return Err(format!(
+                "Invalid status transition from '{}' to '{}'. Allowed: {:?}",
+                before.status, data.status, allowed
+            ));
+        }
+    }
+
+    if before.posted_periods > 0
+        && (data.lines != before.lines
+            || data.kind != before.kind
+            || data.start_period != before.start_period
+            || data.periods != before.periods)
+    {
+        return Err(
+            "AUDIT: A recurring journal's lines and schedule cannot change once it has posted; cancel it and set up a new one"
+                .to_string(),
+        );
+    }
+
+    validate_template(&data)
+}
+
+/// A recurring journal that has posted cannot be deleted; it is cancelled instead.
+pub fn validate_recurring_journal_delete(key: &str) -> Result<(), String> {
+    match get_doc_data::<RecurringJournalData>(RECURRING_JOURNALS_COLLECTION, key)? {
+        Some((_, template)) if template.posted_periods > 0 => Err(format!(
+            "AUDIT: Recurring journal '{}' has posted {} entries; cancel it instead",
+            template.name, template.posted_periods
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Daily job: posts each active recurring journal for the months that have ended since it
+/// last posted, marking prepayments completed after their last month. A template that
+/// cannot post keeps the error in `lastError` and is retried the next day.
+pub fn run_recurring_journals(cursor: Option<String>) -> Result<Option<String>, String> {
+    let today = current_date();
+
+    let progress =
+        scan_collection::<RecurringJournalData>(RECURRING_JOURNALS_COLLECTION, cursor, |key, doc, mut template| {
+            if template.status != "active" {
+                return Ok(());
+            }
+
+            let (posted_before, status_before, error_before) =
+                (template.posted_periods, template.status.clone(), template.last_error.clone());
+            template.last_error = post_due_periods(&key, &mut template, &today).err();
+            if template.posted_periods == posted_before
+                && template.status == status_before
+                && template.last_error == error_before
+            {
+                return Ok(());
+            }
+            template.updated_at = ic_cdk::api::time();
+
+            set_doc_data(RECURRING_JOURNALS_COLLECTION, &key, &template, doc.description.clone(), doc.version)?;
+            Ok(())
+        })?;
+
+    Ok(progress.next_cursor)
+}
+
+fn validate_template(data: &RecurringJournalData) -> Result<(), String> {
+    let name = data.name.trim();
+    if name.len() < 3 || name.len() > 200 {
+        return Err("Recurring journal name must be 3-200 characters".to_string());
+    }
+    if !KINDS.contains(&data.kind.as_str()) {
+        return Err(format!("Invalid kind '{}'. Must be one of: {}", data.kind, KINDS.join(", ")));
+    }
+
+    if data.lines.len() < 2 {
+        return Err("A recurring journal needs at least two lines".to_string());
+    }
+    for line in data.lines.iter() {
+        find_account(&line.account_code)?;
+        if line.debit.is_negative()
+            || line.credit.is_negative()
+            || line.debit.is_positive() == line.credit.is_positive()
+        {
+            return Err(format!("Line for account {} must be either a debit or a credit", line.account_code));
+        }
+    }
+    let total_debit: Money = data.lines.iter().map(|l| l.debit).sum();
+    let total_credit: Money = data.lines.iter().map(|l| l.credit).sum();
+    if total_debit != total_credit {
+        return Err(format!("Recurring journal does not balance: debits ₦{}, credits ₦{}", total_debit, total_credit));
+    }
+
+    if !is_valid_period(&data.start_period) {
+        return Err("Start period must be in format YYYY-MM".to_string());
+    }
+    match data.periods {
+        Some(0) => return Err("Number of periods must be at least 1".to_string()),
+        Some(1) | None if data.kind == "prepayment" => {
+            return Err("A prepayment is amortised over at least two months (periods)".to_string());
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn post_due_periods(key: &str, template: &mut RecurringJournalData, today: &str) -> Result<(), String> {
+    loop {
+        if template.periods.is_some_and(|n| template.posted_periods >= n) {
+            template.status = "completed".to_string();
+            return Ok(());
+        }
+        let period = match template.last_posted_period {
+            Some(ref last) => next_period(last),
+            None => Some(template.start_period.clone()),
+        }
+        .ok_or("Could not determine the next period")?;
+        let month_end = period_end(&period).ok_or("Could not determine the period end")?;
+        if month_end.as_str() > today {
+            return Ok(());
+        }
+
+        post_period(key, template, &period, today)?;
+        template.posted_periods += 1;
+        template.last_posted_period = Some(period);
+    }
+}
+
+fn post_period(key: &str, template: &RecurringJournalData, period: &str, today: &str) -> Result<(), String> {
+    let (entry_date, caught_up) = month_end_posting_date(period, today)?;
+    let description = if caught_up {
+        format!("{} - {} (caught up: {} is closed)", template.name, period, period)
+    } else {
+        format!("{} - {}", template.name, period)
+    };
+
+    let mut lines = Vec::with_capacity(template.lines.len());
+    for line in template.lines.iter() {
+        lines.push(journal_line(
+            &find_account(&line.account_code)?,
+            line.debit,
+            line.credit,
+            line.description.as_deref().unwrap_or(&description),
+        ));
+    }
+
+    post_journal_entry(
+        &format!("recurring-{}-{}", key, period),
+        &format!("JE-REC-{}-{}", key, period.replace('-', "")),
+        &entry_date,
+        &description,
+        "adjustment",
+        Some(key.to_string()),
+        lines,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,235 @@
+//! Maintenance Module - Work Orders and Asset Maintenance Costing
+//!
+//! Repairs and maintenance are raised as work orders (`work_orders`) against an asset in
+//! the asset register (`fixed_assets`) and assigned to a vendor. This module enforces:
+//! - Work orders reference a registered asset and follow open → assigned → in_progress → completed
+//! - Maintenance and repair expenses reference a completed work order
+//! - Lifetime maintenance cost per asset is reported from the linked expenses
+
+use candid::CandidType;
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use super::expenses::ExpenseData;
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const WORK_ORDERS_COLLECTION: &str = "work_orders";
+
+/// Expense categories that must be backed by a completed work order
+pub const MAINTENANCE_EXPENSE_CATEGORIES: [&str; 2] = ["maintenance", "repairs"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkOrderData {
+    pub asset_id: String,
+    pub asset_name: String,
+    pub issue: String,
+    pub priority: String,
+    pub vendor_name: Option<String>,
+    pub vendor_contact: Option<String>,
+    pub estimated_cost: Option<Money>,
+    pub status: String,
+    pub reported_by: String,
+    pub reported_date: String,
+    pub completed_date: Option<String>,
+    pub completion_notes: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FixedAssetRef {
+    asset_name: String,
+    status: String,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct AssetMaintenanceCost {
+    pub asset_id: String,
+    pub asset_name: String,
+    pub work_order_count: u32,
+    pub expense_count: u32,
+    pub total_cost: f64,
+    pub last_completed_date: Option<String>,
+}
+
+/// Work Order Validation
+///
+/// Checks:
+/// - Asset exists in the asset register and has not been disposed
+/// - Asset cannot be swapped once the order is raised
+/// - Status workflow and vendor assignment before work starts
+/// - Completion records the date and what was done
+pub fn validate_work_order_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: WorkOrderData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid work order data format: {}", e))?;
+
+    if data.issue.trim().len() < 10 {
+        return Err("Issue description must be at least 10 characters".to_string());
+    }
+
+    let valid_priorities = ["low", "medium", "high", "urgent"];
+    if !valid_priorities.contains(&data.priority.as_str()) {
+        return Err(format!(
+            "Invalid priority '{}'. Must be one of: {}",
+            data.priority,
+            valid_priorities.join(", ")
+        ));
+    }
+
+    if !is_valid_date_format(&data.reported_date) {
+        return Err("Invalid reported date format. Must be YYYY-MM-DD".to_string());
+    }
+    if let Some(cost) = data.estimated_cost {
+        if cost.is_negative() {
+            return Err("Estimated cost cannot be negative".to_string());
+        }
+    }
+
+    let valid_transitions = HashMap::from([
+        ("open", vec!["assigned", "cancelled"]),
+        ("assigned", vec!["in_progress", "cancelled"]),
+        ("in_progress", vec!["completed", "cancelled"]),
+        ("completed", vec![]),
+        ("cancelled", vec![]),
+    ]);
+    if !valid_transitions.contains_key(data.status.as_str()) {
+        return Err(format!("Invalid work order status '{}'", data.status));
+    }
+
+    match context.data.data.current {
+        Some(ref before_doc) => {
+            let before: WorkOrderData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous work order data: {}", e))?;
+
+            if before.asset_id != data.asset_id {
+                return Err("A work order cannot be moved to a different asset".to_string());
+            }
+            if before.status == "completed" || before.status == "cancelled" {
+                if before.status != data.status {
+                    return Err(format!("Work order is {} and cannot change status", before.status));
+                }
+            } else if before.status != data.status {
+                let allowed = valid_transitions.get(before.status.as_str()).cloned().unwrap_or_default();
+                if !allowed.contains(&data.status.as_str()) {
+                    return Err(format!(
+                        "Invalid work order status transition from '{}' to '{}'. Allowed: [{}]",
+                        before.status,
+                        data.status,
+                        allowed.join(", ")
+                    ));
+                }
+            }
+        }
+        None => {
+            if data.status != "open" && data.status != "assigned" {
+                return Err("New work orders must be 'open' or 'assigned'".to_string());
+            }
+
+            let (_, asset) = get_doc_data::<FixedAssetRef>("fixed_assets", &data.asset_id)?
+                .ok_or_else(|| format!("Asset '{}' not found in the asset register", data.asset_id))?;
+            if asset.status == "disposed" {
+                return Err(format!("Asset '{}' has been disposed", asset.asset_name));
+            }
+        }
+    }
+
+    if data.status != "open" && data.status != "cancelled"
+        && data.vendor_name.as_ref().map(|v| v.trim().is_empty()).unwrap_or(true)
+    {
+        return Err("Work orders must be assigned to a vendor before work starts".to_string());
+    }
+
+    if data.status == "completed" {
+        let completed = data
+            .completed_date
+            .as_ref()
+            .filter(|d| is_valid_date_format(d))
+            .ok_or("Completed work orders must have a valid completedDate (YYYY-MM-DD)")?;
+        if completed < &data.reported_date {
+            return Err("completedDate cannot be before reportedDate".to_string());
+        }
+        if data.completion_notes.as_ref().map(|n| n.trim().len() < 10).unwrap_or(true) {
+            return Err("Completed work orders must describe the work done (at least 10 characters)".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Maintenance and repair expenses must reference a completed work order. Checked when
+/// the expense is recorded or its work order link changes.
+pub fn validate_expense_work_order(context: &AssertSetDocContext, expense: &ExpenseData) -> Result<(), String> {
+    if !MAINTENANCE_EXPENSE_CATEGORIES.contains(&expense.category.as_str()) {
+        return Ok(());
+    }
+
+    if let Some(ref before_doc) = context.data.data.current {
+        let before: ExpenseData = decode_doc_data(&before_doc.data)
+            .map_err(|e| format!("Invalid previous expense data: {}", e))?;
+        if before.work_order_id == expense.work_order_id && before.category == expense.category {
+            return Ok(());
+        }
+    }
+
+    let work_order_id = expense
+        .work_order_id
+        .as_ref()
+        .filter(|id| !id.trim().is_empty())
+        .ok_or("Maintenance and repair expenses must reference a completed work order")?;
+
+    let (_, order) = get_doc_data::<WorkOrderData>(WORK_ORDERS_COLLECTION, work_order_id)?
+        .ok_or_else(|| format!("Work order '{}' not found", work_order_id))?;
+
+    if order.status != "completed" {
+        return Err(format!(
+            "Work order for '{}' is {}; only completed work can be paid for",
+            order.asset_name, order.status
+        ));
+    }
+
+    Ok(())
+}
+
+/// Lifetime maintenance cost per asset from expenses linked to its work orders.
+pub fn get_asset_maintenance_costs(asset_id: Option<String>) -> Result<Vec<AssetMaintenanceCost>, String> {
+    let orders = list_doc_data::<WorkOrderData>(WORK_ORDERS_COLLECTION, None)?;
+    let expenses = list_doc_data::<ExpenseData>("expenses", None)?;
+
+    let mut costs: BTreeMap<String, AssetMaintenanceCost> = BTreeMap::new();
+    for (order_id, _, order) in orders.iter() {
+        if asset_id.as_ref().map(|a| a != &order.asset_id).unwrap_or(false) || order.status == "cancelled" {
+            continue;
+        }
+
+        let entry = costs.entry(order.asset_id.clone()).or_insert_with(|| AssetMaintenanceCost {
+            asset_id: order.asset_id.clone(),
+            asset_name: order.asset_name.clone(),
+            work_order_count: 0,
+            expense_count: 0,
+            total_cost: 0.0,
+            last_completed_date: None,
+        });
+
+        entry.work_order_count += 1;
+        if order.completed_date > entry.last_completed_date {
+            entry.last_completed_date = order.completed_date.clone();
+        }
+
+        for (_, _, expense) in expenses.iter().filter(|(_, _, e)| {
+            e.work_order_id.as_deref() == Some(order_id.as_str()) && (e.status == "approved" || e.status == "paid")
+        }) {
+            entry.expense_count += 1;
+            entry.total_cost = (Money::from_naira(entry.total_cost) + expense.amount).naira();
+        }
+    }
+
+    let mut report: Vec<AssetMaintenanceCost> = costs.into_values().collect();
+    report.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(report)
+}
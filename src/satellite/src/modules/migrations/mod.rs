@@ -0,0 +1,256 @@
+//! Migrations Module - Legacy Document Imports
+//!
+//! Documents carried over from a previous system often break rules added since: they are
+//! dated in closed periods, miss fields that are now required, or were entered outside
+//! working hours. `import_legacy_documents` writes such documents with named rule sets
+//! skipped, and records a bypass flag for every document it writes in
+//! `validation_bypasses` (key `<collection>:<document key>`): which rule sets were
+//! skipped, why, and under which migration.
+//!
+//! Rule sets are only skipped while an import is running, for the documents in it, and
+//! bypass flags can only be written by the import itself; they are never changed or
+//! deleted. `list_validation_bypasses` lists the flagged documents.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+use super::roles::{require_role, Role};
+use super::utils::doc_utils::*;
+
+pub const VALIDATION_BYPASSES_COLLECTION: &str = "validation_bypasses";
+
+// Rule sets an import can skip: the working-hours window, month and term locks, and the
+// collection's own document validation
+pub const BYPASS_RULE_SETS: [&str; 4] = ["write_window", "period_lock", "term_lock", "document"];
+
+const MAX_IMPORT_DOCUMENTS: usize = 100;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationBypassData {
+    pub collection: String,
+    pub doc_key: String,
+    pub rule_sets: Vec<String>,
+    pub reason: String,
+    pub migration_id: String,
+    pub imported_by: String,
+    pub imported_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct LegacyDocument {
+    pub key: String,
+    // Document data as JSON, in the collection's stored (camelCase) shape
+    pub data: String,
+    pub description: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ValidationBypass {
+    pub collection: String,
+    pub doc_key: String,
+    pub rule_sets: Vec<String>,
+    pub reason: String,
+    pub migration_id: String,
+    pub imported_by: String,
+    pub imported_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ValidationBypassPage {
+    pub bypasses: Vec<ValidationBypass>,
+    // Across all pages
+    pub total_bypasses: u32,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+// The import in progress: collection, rule sets skipped and the key being written
+struct ActiveImport {
+    collection: String,
+    rule_sets: Vec<String>,
+    doc_key: Option<String>,
+}
+
+thread_local! {
+    static ACTIVE_IMPORT: RefCell<Option<ActiveImport>> = const { RefCell::new(None) };
+}
+
+/// True when the write is part of a running import that skips `rule_set`.
+pub fn is_bypassed(context: &AssertSetDocContext, rule_set: &str) -> bool {
+    if !is_satellite_caller(&context.caller) {
+        return false;
+    }
+    ACTIVE_IMPORT.with(|active| {
+        active.borrow().as_ref().is_some_and(|import| {
+            import.collection == context.data.collection
+                && import.doc_key.as_deref() == Some(context.data.key.as_str())
+                && import.rule_sets.iter().any(|r| r == rule_set)
+        })
+    })
+}
+
+/// Validation Bypass Validation
+///
+/// Checks:
+/// - Bypass flags are written by `import_legacy_documents` only, for the document being
+///   imported
+/// - Flags are never modified
+pub fn validate_validation_bypass_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Validation bypass flags cannot be modified".to_string());
+    }
+    let data: ValidationBypassData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid validation bypass data format: {}", e))?;
+
+    let importing = is_satellite_caller(&context.caller)
+        && ACTIVE_IMPORT.with(|active| {
+            active.borrow().as_ref().is_some_and(|import| {
+                import.collection == data.collection && import.doc_key.as_deref() == Some(data.doc_key.as_str())
+            })
+        });
+    if !importing {
+        return Err("SECURITY: Validation bypass flags are recorded by the legacy import only".to_string());
+    }
+    Ok(())
+}
+
+/// Bypass flags are part of the audit trail and cannot be deleted.
+pub fn validate_validation_bypass_delete() -> Result<(), String> {
+    Err("AUDIT: Validation bypass flags cannot be deleted".to_string())
+}
+
+/// Write legacy documents into a collection with the given rule sets skipped, flagging
+/// each one. Returns the keys written.
+pub fn import_legacy_documents(
+    collection: String,
+    documents: Vec<LegacyDocument>,
+    rule_sets: Vec<String>,
+    migration_id: String,
+    reason: String,
+) -> Result<Vec<String>, String> {
+    let caller = caller();
+    require_role(&caller, Role::SuperAdmin)?;
+
+    if collection == VALIDATION_BYPASSES_COLLECTION {
+        return Err("Validation bypass flags cannot be imported".to_string());
+    }
+    if documents.is_empty() || documents.len() > MAX_IMPORT_DOCUMENTS {
+        return Err(format!("An import must contain between 1 and {} documents", MAX_IMPORT_DOCUMENTS));
+    }
+    if rule_sets.is_empty() {
+        return Err("An import must name the rule sets it skips".to_string());
+    }
+    for rule_set in rule_sets.iter() {
+        if !BYPASS_RULE_SETS.contains(&rule_set.as_str()) {
+            return Err(format!(
+                "Invalid rule set '{}'. Must be one of: {}",
+                rule_set,
+                BYPASS_RULE_SETS.join(", ")
+            ));
+        }
+    }
+    if migration_id.trim().is_empty() {
+        return Err("An import must carry a migration id".to_string());
+    }
+    if reason.trim().len() < 10 {
+        return Err("An import must include a reason of at least 10 characters".to_string());
+    }
+
+    ACTIVE_IMPORT.with(|active| {
+        *active.borrow_mut() = Some(ActiveImport {
+            collection: collection.clone(),
+            rule_sets: rule_sets.clone(),
+            doc_key: None,
+        })
+    });
+    let result = write_documents(&collection, documents, &rule_sets, &migration_id, &reason, &caller.to_text());
+    ACTIVE_IMPORT.with(|active| *active.borrow_mut() = None);
+    result
+}
+
+fn write_documents(
+    collection: &str,
+    documents: Vec<LegacyDocument>,
+    rule_sets: &[String],
+    migration_id: &str,
+    reason: &str,
+    imported_by: &str,
+) -> Result<Vec<String>, String> {
+    let mut keys = Vec::new();
+    for document in documents {
+        let data: serde_json::Value = serde_json::from_str(&document.data)
+            .map_err(|e| format!("Invalid JSON for document '{}': {}", document.key, e))?;
+        if doc_exists(collection, &document.key)? {
+            return Err(format!("Document '{}' already exists in {}", document.key, collection));
+        }
+
+        ACTIVE_IMPORT.with(|active| {
+            if let Some(import) = active.borrow_mut().as_mut() {
+                import.doc_key = Some(document.key.clone());
+            }
+        });
+        set_doc_data(collection, &document.key, &data, document.description, None)?;
+
+        let bypass = ValidationBypassData {
+            collection: collection.to_string(),
+            doc_key: document.key.clone(),
+            rule_sets: rule_sets.to_vec(),
+            reason: reason.to_string(),
+            migration_id: migration_id.to_string(),
+            imported_by: imported_by.to_string(),
+            imported_at: ic_cdk::api::time(),
+        };
+        set_doc_data(
+            VALIDATION_BYPASSES_COLLECTION,
+            &format!("{}:{}", collection, document.key),
+            &bypass,
+            Some(format!("collection={};migration_id={};", collection, migration_id)),
+            None,
+        )?;
+        keys.push(document.key);
+    }
+    Ok(keys)
+}
+
+/// Documents written with rule sets skipped, optionally for one collection, most recent
+/// first.
+pub fn list_validation_bypasses(collection: Option<String>, page: Pagination) -> Result<ValidationBypassPage, String> {
+    page.validate()?;
+
+    let mut bypasses: Vec<ValidationBypassData> =
+        list_doc_data::<ValidationBypassData>(VALIDATION_BYPASSES_COLLECTION, None)?
+            .into_iter()
+            .filter(|(_, _, b)| collection.as_ref().map(|c| c == &b.collection).unwrap_or(true))
+            .map(|(_, _, b)| b)
+            .collect();
+    bypasses.sort_by(|a, b| {
+        b.imported_at
+            .cmp(&a.imported_at)
+            .then(a.collection.cmp(&b.collection))
+            .then(a.doc_key.cmp(&b.doc_key))
+    });
+    let total_bypasses = bypasses.len() as u32;
+
+    Ok(ValidationBypassPage {
+        bypasses: page
+            .slice(bypasses)
+            .into_iter()
+            .map(|b| ValidationBypass {
+                collection: b.collection,
+                doc_key: b.doc_key,
+                rule_sets: b.rule_sets,
+                reason: b.reason,
+                migration_id: b.migration_id,
+                imported_by: b.imported_by,
+                imported_at: b.imported_at,
+            })
+            .collect(),
+        total_bypasses,
+        offset: page.offset,
+        limit: page.limit,
+    })
+}
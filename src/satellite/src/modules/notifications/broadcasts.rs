@@ -0,0 +1,135 @@
+//! Fee announcement broadcasts.
+//!
+//! `queue_broadcast` sends one message to every guardian with a child matching the
+//! filter: the child's current class, a band of outstanding fee balance, or subscription
+//! to school transport. Each matching guardian gets one SMS, plus an email when they have
+//! an address, queued for the notification relay under a shared `BC-` broadcast number.
+
+use candid::CandidType;
+use junobuild_satellite::caller;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::{guardian_addresses, queue_notification};
+use crate::modules::fees::StudentFeeAssignmentData;
+use crate::modules::guardians::{GuardianData, GUARDIANS_COLLECTION};
+use crate::modules::roles::{require_role, Role};
+use crate::modules::students::StudentData;
+use crate::modules::utils::counters::next_number;
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+
+// Three SMS pages
+pub const MAX_BROADCAST_MESSAGE_LENGTH: usize = 480;
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct BroadcastFilter {
+    pub class_id: Option<String>,
+    // Band of the student's total outstanding balance (naira), inclusive
+    pub min_balance: Option<f64>,
+    pub max_balance: Option<f64>,
+    // Only students with a selected transport item on a fee assignment
+    pub transport_subscribers: bool,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct BroadcastSummary {
+    pub broadcast_id: String,
+    pub students_matched: u32,
+    pub guardians_matched: u32,
+    pub notifications_queued: u32,
+}
+
+#[derive(Default)]
+struct StudentFees {
+    balance: Money,
+    transport: bool,
+}
+
+/// Queue a fee announcement for the guardians of the students matching the filter.
+pub fn queue_broadcast(filter: BroadcastFilter, message: String) -> Result<BroadcastSummary, String> {
+    let caller = caller();
+    require_role(&caller, Role::Bursar)?;
+
+    let message = message.trim().to_string();
+    if message.is_empty() {
+        return Err("Broadcast message cannot be empty".to_string());
+    }
+    if message.chars().count() > MAX_BROADCAST_MESSAGE_LENGTH {
+        return Err(format!(
+            "Broadcast message cannot exceed {} characters",
+            MAX_BROADCAST_MESSAGE_LENGTH
+        ));
+    }
+    let min_balance = filter.min_balance.map(Money::from_naira);
+    let max_balance = filter.max_balance.map(Money::from_naira);
+    if min_balance.is_some_and(|m| m.is_negative()) || max_balance.is_some_and(|m| m.is_negative()) {
+        return Err("Balance band cannot be negative".to_string());
+    }
+    if let (Some(min), Some(max)) = (min_balance, max_balance) {
+        if min > max {
+            return Err("Minimum balance cannot exceed the maximum balance".to_string());
+        }
+    }
+
+    let mut fees_by_student: HashMap<String, StudentFees> = HashMap::new();
+    for (_, _, assignment) in list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)? {
+        let fees = fees_by_student.entry(assignment.student_id.clone()).or_default();
+        fees.balance += assignment.balance;
+        fees.transport |= assignment
+            .fee_items
+            .iter()
+            .any(|i| i.fee_type == "transport" && i.amount.is_positive() && i.is_selected != Some(false));
+    }
+    let matched: HashSet<String> = list_doc_data::<StudentData>("students", None)?
+        .into_iter()
+        .filter(|(student_id, _, student)| {
+            let fees = fees_by_student.get(student_id);
+            let balance = fees.map(|f| f.balance).unwrap_or(Money::ZERO);
+            filter.class_id.as_ref().map(|c| student.class_id.as_ref() == Some(c)).unwrap_or(true)
+                && min_balance.map(|m| balance >= m).unwrap_or(true)
+                && max_balance.map(|m| balance <= m).unwrap_or(true)
+                && (!filter.transport_subscribers || fees.map(|f| f.transport).unwrap_or(false))
+        })
+        .map(|(student_id, _, _)| student_id)
+        .collect();
+    if matched.is_empty() {
+        return Err("No students match the broadcast filter".to_string());
+    }
+
+    let guardians: Vec<(String, GuardianData)> = list_doc_data::<GuardianData>(GUARDIANS_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, g)| g.student_ids.iter().any(|id| matched.contains(id)))
+        .map(|(key, _, g)| (key, g))
+        .collect();
+    if guardians.is_empty() {
+        return Err("None of the matching students have a guardian linked".to_string());
+    }
+
+    let broadcast_id = format!("BC-{:06}", next_number("broadcasts")?);
+    let queued_by = caller.to_text();
+    let mut notifications_queued = 0;
+    for (guardian_id, guardian) in guardians.iter() {
+        for (channel, address) in guardian_addresses(guardian) {
+            let key = format!("{}_{}_{}", broadcast_id, guardian_id, channel);
+            queue_notification(
+                &key,
+                guardian_id,
+                channel,
+                address,
+                Some("Fee announcement"),
+                &message,
+                Some(broadcast_id.clone()),
+                &queued_by,
+            )?;
+            notifications_queued += 1;
+        }
+    }
+
+    Ok(BroadcastSummary {
+        broadcast_id,
+        students_matched: matched.len() as u32,
+        guardians_matched: guardians.len() as u32,
+        notifications_queued,
+    })
+}
@@ -0,0 +1,275 @@
+//! Notification delivery through SMS and email providers.
+//!
+//! A channel is given a provider in `notification_settings`, keyed by channel (create the
+//! collection with controller-only read access: it holds API keys):
+//! - `sms`: Termii, e.g. `https://api.ng.termii.com/api/sms/send`
+//! - `email`: SendGrid, e.g. `https://api.sendgrid.com/v3/mail/send`
+//!
+//! Every minute the satellite takes a batch of due notifications on configured channels
+//! and posts each to its provider through an HTTPS outcall. A message the provider
+//! accepts is marked `sent`. A failed attempt is retried with exponential backoff (2, 4,
+//! 8 and 16 minutes), the last error kept in `failureReason`; after the fifth failed
+//! attempt the message is marked `failed`.
+//!
+//! Every replica makes the outcall, so requests carry the notification key as an
+//! `Idempotency-Key` header: `apiUrl` should reach the provider through a proxy that
+//! deduplicates on it, or parents may get a message more than once. Responses are reduced
+//! to the status and error so that the replicas agree on them.
+
+use ic_cdk::management_canister::{
+    http_request, transform_context_from_query, HttpHeader, HttpMethod, HttpRequestArgs, HttpRequestResult,
+    TransformArgs,
+};
+use junobuild_satellite::{error, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::cell::Cell;
+
+use super::{NotificationData, NOTIFICATION_QUEUE_COLLECTION};
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::validation_utils::is_valid_email;
+
+pub const NOTIFICATION_SETTINGS_COLLECTION: &str = "notification_settings";
+
+// Provider supported on each channel
+const PROVIDERS: [(&str, &str); 2] = [("sms", "termii"), ("email", "sendgrid")];
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_BASE_NANOS: u64 = 2 * 60 * 1_000_000_000;
+const BATCH_SIZE: usize = 20;
+const MAX_RESPONSE_BYTES: u64 = 4_000;
+const DEFAULT_SUBJECT: &str = "School notification";
+// Name of the query endpoint in lib.rs that strips provider responses to status and error
+const TRANSFORM_METHOD: &str = "transform_notification_response";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationSettingsData {
+    pub provider: String,
+    pub api_url: String,
+    pub api_key: String,
+    // Termii sender id, or the SendGrid from address
+    pub sender: String,
+    pub is_active: bool,
+    pub updated_by: String,
+    pub updated_at: u64,
+}
+
+// The provider response after the transform: identical on every replica
+#[derive(Deserialize, Serialize, Default)]
+struct ProviderAnswer {
+    error: Option<String>,
+}
+
+thread_local! {
+    // A batch is being sent; later ticks skip until it is done
+    static SEND_IN_FLIGHT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Notification Settings Validation
+///
+/// Checks:
+/// - Only administrators configure notification providers
+/// - The key is a channel and the provider is the one supported on it
+/// - The API URL is HTTPS and an API key is set
+/// - The sender is a 3-11 character alphanumeric sender id (SMS) or an email address
+pub fn validate_notification_settings_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only administrators can configure notification providers".to_string());
+    }
+
+    let data: NotificationSettingsData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid notification settings data format: {}", e))?;
+
+    let channel = context.data.key.as_str();
+    let provider = PROVIDERS
+        .iter()
+        .find(|(c, _)| *c == channel)
+        .map(|(_, p)| *p)
+        .ok_or_else(|| format!("Unknown notification channel '{}'. Must be one of: sms, email", channel))?;
+    if data.provider != provider {
+        return Err(format!("The {} channel is sent through {}", channel, provider));
+    }
+    if !data.api_url.starts_with("https://") {
+        return Err("Notification API URL must be an https:// URL".to_string());
+    }
+    if data.api_key.trim().is_empty() {
+        return Err("Notification provider API key is required".to_string());
+    }
+    let sender = data.sender.trim();
+    let valid_sender = match channel {
+        "sms" => (3..=11).contains(&sender.len()) && sender.chars().all(|c| c.is_ascii_alphanumeric()),
+        _ => is_valid_email(sender),
+    };
+    if !valid_sender {
+        return Err(format!(
+            "Invalid sender '{}': SMS needs a 3-11 character alphanumeric sender id, email a from address",
+            sender
+        ));
+    }
+    if data.updated_by != context.caller.to_text() {
+        return Err("updatedBy must be the principal configuring notifications".to_string());
+    }
+
+    Ok(())
+}
+
+/// Send the notifications that are due on channels with an active provider, recording
+/// each outcome. Run by the notification timer.
+pub async fn send_queued_notifications() -> Result<(), String> {
+    let settings: Vec<(String, NotificationSettingsData)> =
+        list_doc_data::<NotificationSettingsData>(NOTIFICATION_SETTINGS_COLLECTION, None)?
+            .into_iter()
+            .filter(|(_, _, s)| s.is_active)
+            .map(|(channel, _, s)| (channel, s))
+            .collect();
+    if settings.is_empty() {
+        return Ok(());
+    }
+    if SEND_IN_FLIGHT.with(|f| f.replace(true)) {
+        return Ok(());
+    }
+    let result = send_batch(&settings).await;
+    SEND_IN_FLIGHT.with(|f| f.set(false));
+    result
+}
+
+/// Transform for the send outcall: keeps the status and the provider's error message and
+/// drops the headers and message ids, so all replicas agree on the response.
+pub fn transform_response(args: TransformArgs) -> HttpRequestResult {
+    let provider = String::from_utf8(args.context).unwrap_or_default();
+    let answer = ProviderAnswer {
+        error: serde_json::from_slice::<serde_json::Value>(&args.response.body)
+            .ok()
+            .and_then(|body| provider_error(&provider, &body)),
+    };
+
+    HttpRequestResult {
+        status: args.response.status,
+        headers: Vec::new(),
+        body: serde_json::to_vec(&answer).unwrap_or_default(),
+    }
+}
+
+async fn send_batch(settings: &[(String, NotificationSettingsData)]) -> Result<(), String> {
+    let now = ic_cdk::api::time();
+    let due: Vec<(String, NotificationData, &NotificationSettingsData)> =
+        list_doc_data::<NotificationData>(NOTIFICATION_QUEUE_COLLECTION, None)?
+            .into_iter()
+            .filter(|(_, _, n)| n.status == "queued" && n.next_attempt_at.map(|at| at <= now).unwrap_or(true))
+            .filter_map(|(key, _, n)| {
+                let (_, provider) = settings.iter().find(|(channel, _)| *channel == n.channel)?;
+                Some((key, n, provider))
+            })
+            .take(BATCH_SIZE)
+            .collect();
+
+    for (key, notification, provider) in due {
+        let outcome = send(&key, &notification, provider).await;
+
+        // Re-read: the relay may have resolved the message while the outcall was made
+        let Some((doc, mut current)) = get_doc_data::<NotificationData>(NOTIFICATION_QUEUE_COLLECTION, &key)? else {
+            continue;
+        };
+        if current.status != "queued" {
+            continue;
+        }
+        current.attempts += 1;
+        current.provider = Some(provider.provider.clone());
+        match outcome {
+            Ok(()) => {
+                current.status = "sent".to_string();
+                current.sent_at = Some(ic_cdk::api::time());
+                current.failure_reason = None;
+                current.next_attempt_at = None;
+            }
+            Err(e) if current.attempts >= MAX_ATTEMPTS => {
+                let _ = error(format!("Notification '{}' failed after {} attempts: {}", key, current.attempts, e));
+                current.status = "failed".to_string();
+                current.failure_reason = Some(e);
+                current.next_attempt_at = None;
+            }
+            Err(e) => {
+                current.failure_reason = Some(e);
+                current.next_attempt_at = Some(ic_cdk::api::time() + (RETRY_BASE_NANOS << (current.attempts - 1)));
+            }
+        }
+        set_doc_data(NOTIFICATION_QUEUE_COLLECTION, &key, &current, doc.description, doc.version)?;
+    }
+
+    Ok(())
+}
+
+async fn send(key: &str, notification: &NotificationData, settings: &NotificationSettingsData) -> Result<(), String> {
+    let mut headers = vec![
+        HttpHeader { name: "Content-Type".to_string(), value: "application/json".to_string() },
+        HttpHeader { name: "Idempotency-Key".to_string(), value: key.to_string() },
+    ];
+    let body = match settings.provider.as_str() {
+        "termii" => json!({
+            "api_key": settings.api_key,
+            "to": international_number(&notification.address),
+            "from": settings.sender.trim(),
+            "sms": notification.message,
+            "type": "plain",
+            "channel": "generic",
+        }),
+        "sendgrid" => {
+            headers
+                .push(HttpHeader { name: "Authorization".to_string(), value: format!("Bearer {}", settings.api_key) });
+            json!({
+                "personalizations": [{ "to": [{ "email": notification.address }] }],
+                "from": { "email": settings.sender.trim() },
+                "subject": notification.subject.as_deref().unwrap_or(DEFAULT_SUBJECT),
+                "content": [{ "type": "text/plain", "value": notification.message }],
+            })
+        }
+        other => return Err(format!("Unknown notification provider '{}'", other)),
+    };
+
+    let request = HttpRequestArgs {
+        url: settings.api_url.clone(),
+        max_response_bytes: Some(MAX_RESPONSE_BYTES),
+        method: HttpMethod::POST,
+        headers,
+        body: Some(body.to_string().into_bytes()),
+        transform: Some(transform_context_from_query(
+            TRANSFORM_METHOD.to_string(),
+            settings.provider.as_bytes().to_vec(),
+        )),
+    };
+
+    let response =
+        http_request(&request).await.map_err(|e| format!("{} could not be reached: {:?}", settings.provider, e))?;
+    if response.status >= 200u32 && response.status < 300u32 {
+        return Ok(());
+    }
+    let answer: ProviderAnswer = serde_json::from_slice(&response.body).unwrap_or_default();
+    Err(format!(
+        "{} rejected the message ({}){}",
+        settings.provider,
+        response.status,
+        answer.error.map(|e| format!(": {}", e)).unwrap_or_default()
+    ))
+}
+
+// Termii takes numbers as 234XXXXXXXXXX
+fn international_number(phone: &str) -> String {
+    let cleaned = phone.replace(&[' ', '-', '+', '(', ')'][..], "");
+    match cleaned.strip_prefix('0') {
+        Some(rest) => format!("234{}", rest),
+        None => cleaned,
+    }
+}
+
+// Termii: { message: "..." }
+// SendGrid: { errors: [{ message: "..." }] }
+fn provider_error(provider: &str, body: &serde_json::Value) -> Option<String> {
+    let message = match provider {
+        "termii" => body.get("message")?,
+        "sendgrid" => body.get("errors")?.get(0)?.get("message")?,
+        _ => return None,
+    };
+    message.as_str().map(str::to_string)
+}
@@ -0,0 +1,56 @@
+//! Receipt and invoice messages to parents.
+//!
+//! When a payment is confirmed and its receipt issued, each guardian of the student is
+//! sent the receipt details; when a fee assignment is raised, they are sent the invoice:
+//! the term's fees, the balance and the due date. Messages go by SMS, plus email for
+//! guardians with an address, keyed by the receipt or fee assignment so each is queued
+//! once. Students without a linked guardian are skipped.
+
+use junobuild_satellite::id;
+
+use super::{guardian_addresses, queue_notification, student_guardians};
+use crate::modules::fees::StudentFeeAssignmentData;
+use crate::modules::receipts::ReceiptData;
+
+/// Queue the receipt for a confirmed payment to the student's guardians.
+pub fn queue_receipt_message(receipt: &ReceiptData) -> Result<(), String> {
+    let subject = format!("Payment receipt {}", receipt.receipt_number);
+    // Naira written as N: ₦ is outside the GSM alphabet and would make the SMS unicode
+    let message = format!(
+        "Payment of N{} received for {} on {} ({}). Receipt {}, ref {}. Thank you.",
+        receipt.amount,
+        receipt.student_name,
+        receipt.payment_date,
+        receipt.payment_method.replace('_', " "),
+        receipt.receipt_number,
+        receipt.reference
+    );
+    queue_for_guardians(&receipt.student_id, &format!("receipt_{}", receipt.receipt_number), &subject, &message)
+}
+
+/// Queue the invoice for a newly raised fee assignment to the student's guardians.
+pub fn queue_invoice_message(assignment_id: &str, assignment: &StudentFeeAssignmentData) -> Result<(), String> {
+    let subject = format!("School fees: {} term {}", assignment.term, assignment.academic_year);
+    let due = assignment.due_date.as_ref().map(|d| format!(", due {}", d)).unwrap_or_default();
+    let message = format!(
+        "Fees for {}, {} term {}: N{}. Balance N{}{}.",
+        assignment.student_name,
+        assignment.term,
+        assignment.academic_year,
+        assignment.total_amount,
+        assignment.balance,
+        due
+    );
+    queue_for_guardians(&assignment.student_id, &format!("invoice_{}", assignment_id), &subject, &message)
+}
+
+fn queue_for_guardians(student_id: &str, source: &str, subject: &str, message: &str) -> Result<(), String> {
+    let queued_by = id().to_text();
+    for (guardian_id, guardian) in student_guardians(student_id)?.iter() {
+        for (channel, address) in guardian_addresses(guardian) {
+            let key = format!("{}_{}_{}", source, guardian_id, channel);
+            queue_notification(&key, guardian_id, channel, address, Some(subject), message, None, &queued_by)?;
+        }
+    }
+    Ok(())
+}
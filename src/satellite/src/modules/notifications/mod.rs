@@ -0,0 +1,170 @@
+//! Notifications Module - Outgoing Message Queue
+//!
+//! Messages to guardians are queued in `notification_queue` by the satellite, one
+//! document per recipient and channel: fee broadcasts, payment receipts and fee invoices
+//! (see [`fee_messages`]). Channels with a provider configured in `notification_settings`
+//! are sent by the satellite itself, through HTTPS outcalls on a timer (see
+//! [`delivery`]), with retries. Other channels are left to the notification relay (an
+//! off-canister worker running as a controller), which reads the `queued` entries, sends
+//! them and records the outcome as `sent` or `failed`. Queued messages are never edited.
+
+pub mod broadcasts;
+pub mod delivery;
+pub mod fee_messages;
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::guardians::{GuardianData, GUARDIANS_COLLECTION};
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+
+pub const NOTIFICATION_QUEUE_COLLECTION: &str = "notification_queue";
+
+const CHANNELS: [&str; 2] = ["sms", "email"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationData {
+    // Guardian the message is addressed to
+    pub recipient_id: String,
+    pub channel: String,
+    // Phone number or email address, depending on the channel
+    pub address: String,
+    // Email subject; SMS have none
+    #[serde(default)]
+    pub subject: Option<String>,
+    pub message: String,
+    pub status: String,
+    pub broadcast_id: Option<String>,
+    pub queued_by: String,
+    pub created_at: u64,
+    pub sent_at: Option<u64>,
+    // Last error while the message is retried, or why it finally failed
+    pub failure_reason: Option<String>,
+    // Delivery attempts made by the satellite, and when the next one is due
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub next_attempt_at: Option<u64>,
+    // Provider the satellite sent the message through
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+/// Notification Queue Validation
+///
+/// Checks:
+/// - Entries are queued and delivered by the satellite
+/// - Only the relay (super admins) records other deliveries, once: queued → sent/failed
+/// - The recipient, address and message cannot be changed
+pub fn validate_notification_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: NotificationData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid notification data format: {}", e))?;
+
+    if is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+    let before: NotificationData = match context.data.data.current {
+        Some(ref doc) => decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous notification data: {}", e))?,
+        None => return Err("SECURITY: Notifications are queued by the satellite".to_string()),
+    };
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only the notification relay can record deliveries".to_string());
+    }
+    if before.status != "queued" {
+        return Err(format!("Notification is already {}", before.status));
+    }
+    if data.recipient_id != before.recipient_id
+        || data.channel != before.channel
+        || data.address != before.address
+        || data.subject != before.subject
+        || data.message != before.message
+        || data.broadcast_id != before.broadcast_id
+    {
+        return Err("AUDIT: Queued notifications cannot be edited".to_string());
+    }
+
+    match data.status.as_str() {
+        "sent" => {
+            if data.sent_at.is_none() {
+                return Err("Sent notifications must record sentAt".to_string());
+            }
+        }
+        "failed" => {
+            if data.failure_reason.as_ref().map(|r| r.trim().is_empty()).unwrap_or(true) {
+                return Err("Failed notifications must include the failure reason".to_string());
+            }
+        }
+        other => {
+            return Err(format!("Invalid status '{}'. Queued notifications resolve to: sent, failed", other));
+        }
+    }
+
+    Ok(())
+}
+
+/// Queue a message for delivery.
+#[allow(clippy::too_many_arguments)]
+pub fn queue_notification(
+    key: &str,
+    recipient_id: &str,
+    channel: &str,
+    address: &str,
+    subject: Option<&str>,
+    message: &str,
+    broadcast_id: Option<String>,
+    queued_by: &str,
+) -> Result<(), String> {
+    if !CHANNELS.contains(&channel) {
+        return Err(format!("Invalid channel '{}'. Must be one of: {}", channel, CHANNELS.join(", ")));
+    }
+    let notification = NotificationData {
+        recipient_id: recipient_id.to_string(),
+        channel: channel.to_string(),
+        address: address.to_string(),
+        subject: subject.map(str::to_string),
+        message: message.to_string(),
+        status: "queued".to_string(),
+        broadcast_id,
+        queued_by: queued_by.to_string(),
+        created_at: ic_cdk::api::time(),
+        sent_at: None,
+        failure_reason: None,
+        attempts: 0,
+        next_attempt_at: None,
+        provider: None,
+    };
+    set_doc_data(
+        NOTIFICATION_QUEUE_COLLECTION,
+        key,
+        &notification,
+        Some(format!("recipient_id={};", recipient_id)),
+        None,
+    )?;
+    Ok(())
+}
+
+/// Channels and addresses a guardian is reached on: SMS, plus email when they have an
+/// address.
+pub fn guardian_addresses(guardian: &GuardianData) -> Vec<(&'static str, &str)> {
+    let mut addresses = Vec::with_capacity(2);
+    if !guardian.phone.trim().is_empty() {
+        addresses.push(("sms", guardian.phone.as_str()));
+    }
+    if let Some(email) = guardian.email.as_deref().filter(|e| !e.trim().is_empty()) {
+        addresses.push(("email", email));
+    }
+    addresses
+}
+
+/// The guardians linked to a student.
+pub fn student_guardians(student_id: &str) -> Result<Vec<(String, GuardianData)>, String> {
+    Ok(list_doc_data::<GuardianData>(GUARDIANS_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, g)| g.student_ids.iter().any(|id| id == student_id))
+        .map(|(key, _, g)| (key, g))
+        .collect())
+}
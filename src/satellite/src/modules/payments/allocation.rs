@@ -0,0 +1,123 @@
+//! Automatic payment allocation.
+//!
+//! A payment recorded without fee allocations is allocated by the satellite when it is
+//! confirmed, following the school's `allocationPriority` setting (by default arrears,
+//! then tuition, then mandatory, then optional items). Under the `arrears` rule the
+//! student's unpaid assignments from earlier terms are settled, oldest first, before the
+//! payment's own assignment. Whatever is left once everything is paid stays on the
+//! payment's own assignment as an overpayment.
+//!
+//! The computed allocation is written back to the payment, so the split is visible on the
+//! payment itself.
+
+use super::{PaymentAllocation, PaymentData};
+use crate::modules::fees::{allocation_rank, StudentFeeAssignmentData};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+
+pub const OVERPAYMENT_CATEGORY_ID: &str = "overpayment";
+
+const TERMS: [&str; 3] = ["first", "second", "third"];
+
+// Fee assignment, the amount applied to it and the per-category split
+pub type AssignmentShare = (String, Money, Vec<(String, Money)>);
+
+/// Split a payment over the student's unpaid fee items in allocation priority order.
+pub fn auto_allocate(payment: &PaymentData, priority: &[String]) -> Result<Vec<PaymentAllocation>, String> {
+    allocate_for_student(&payment.student_id, &payment.fee_assignment_id, payment.amount_in_base(), priority)
+}
+
+/// Split an amount paid against one of the student's fee assignments over their unpaid
+/// fee items, as [`auto_allocate`] would for a payment of that amount.
+pub fn allocate_for_student(
+    student_id: &str,
+    fee_assignment_id: &str,
+    amount: Money,
+    priority: &[String],
+) -> Result<Vec<PaymentAllocation>, String> {
+    let period = |a: &StudentFeeAssignmentData| {
+        (a.academic_year.clone(), TERMS.iter().position(|t| *t == a.term).unwrap_or(0))
+    };
+    let (_, own) = get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", fee_assignment_id)?
+        .ok_or_else(|| format!("Fee assignment '{}' not found", fee_assignment_id))?;
+    let own_period = period(&own);
+
+    let mut assignments = vec![(None, own)];
+    if let Some(arrears_rank) = priority.iter().position(|rule| rule == "arrears") {
+        let mut arrears: Vec<(String, StudentFeeAssignmentData)> =
+            list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)?
+                .into_iter()
+                .filter(|(key, _, a)| {
+                    key != fee_assignment_id
+                        && a.student_id == student_id
+                        && a.balance.is_positive()
+                        && period(a) < own_period
+                })
+                .map(|(key, _, a)| (key, a))
+                .collect();
+        arrears.sort_by_key(|(_, a)| period(a));
+        assignments.extend(arrears.into_iter().map(|(key, a)| (Some((arrears_rank, key)), a)));
+    }
+
+    // (rule rank, assignment order, item rank) with the allocation target
+    let mut targets = Vec::new();
+    for (order, (arrears, assignment)) in assignments.iter().enumerate() {
+        for item in assignment.fee_items.iter().filter(|i| i.balance.is_positive()) {
+            let item_rank = allocation_rank(item, priority);
+            let (rank, assignment_id) = match arrears {
+                Some((arrears_rank, key)) => (*arrears_rank, Some(key.clone())),
+                None => (item_rank, None),
+            };
+            targets.push(((rank, order, item_rank), assignment_id, item));
+        }
+    }
+    targets.sort_by_key(|(key, _, _)| *key);
+
+    let mut remaining = amount;
+    let mut allocations = Vec::new();
+    for (_, assignment_id, item) in targets {
+        if !remaining.is_positive() {
+            break;
+        }
+        let share = remaining.min(item.balance);
+        allocations.push(PaymentAllocation {
+            category_id: item.category_id.clone(),
+            category_name: item.category_name.clone(),
+            fee_type: item.fee_type.clone(),
+            amount: share,
+            fee_assignment_id: assignment_id,
+        });
+        remaining -= share;
+    }
+    if remaining.is_positive() {
+        allocations.push(PaymentAllocation {
+            category_id: OVERPAYMENT_CATEGORY_ID.to_string(),
+            category_name: "Overpayment".to_string(),
+            fee_type: "other".to_string(),
+            amount: remaining,
+            fee_assignment_id: None,
+        });
+    }
+
+    Ok(allocations)
+}
+
+/// Allocations grouped by the fee assignment they settle (the payment's own assignment
+/// for allocations without one), in first-seen order.
+pub fn allocations_by_assignment(payment: &PaymentData) -> Vec<AssignmentShare> {
+    let mut grouped: Vec<AssignmentShare> = Vec::new();
+    for allocation in payment.fee_allocations.iter() {
+        let assignment_id = allocation.fee_assignment_id.as_ref().unwrap_or(&payment.fee_assignment_id);
+        let index = match grouped.iter().position(|(id, _, _)| id == assignment_id) {
+            Some(index) => index,
+            None => {
+                grouped.push((assignment_id.clone(), Money::ZERO, Vec::new()));
+                grouped.len() - 1
+            }
+        };
+        let (_, total, items) = &mut grouped[index];
+        *total += allocation.amount;
+        items.push((allocation.category_id.clone(), allocation.amount));
+    }
+    grouped
+}
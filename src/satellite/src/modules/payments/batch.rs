@@ -0,0 +1,108 @@
+//! Batch payment recording.
+//!
+//! Bursars upload bank schedules of a couple of hundred credits at a time, which time out
+//! as one write per payment. `record_payments_batch` records them in one call. Each
+//! payment is written as the caller, so it passes the same checks as a payment saved from
+//! the app (collection permissions and `validate_payment_document`), and then gets the same
+//! follow-up as the `payments` on-set hook: allocation, receipt, audit trail. A payment
+//! that is rejected is reported with its error and the others are still recorded.
+//!
+//! With an idempotency key, a retried upload returns the first call's outcomes rather
+//! than reporting every payment as already recorded.
+
+use candid::{CandidType, Principal};
+use junobuild_satellite::{caller, set_doc_store, HookContext, OnSetDocContext, SetDoc};
+use junobuild_utils::encode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+use crate::modules::utils::doc_utils::doc_exists;
+use crate::modules::utils::idempotency;
+
+pub const MAX_BATCH_PAYMENTS: usize = 200;
+const OPERATION: &str = "record_payments_batch";
+
+#[derive(CandidType, Deserialize)]
+pub struct PaymentInput {
+    pub key: String,
+    // Payment data as JSON, in the stored (camelCase) shape
+    pub data: String,
+    pub description: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct BatchResult {
+    // Position of the payment in the batch
+    pub index: u32,
+    pub key: String,
+    pub recorded: bool,
+    // Why the payment was rejected, or what failed after it was recorded
+    pub error: Option<String>,
+}
+
+/// Record `payments` in order, returning each one's outcome. `on_saved` is the on-set
+/// hook, run for every payment written.
+pub async fn record_payments_batch<F, Fut>(
+    payments: Vec<PaymentInput>,
+    idempotency_key: Option<String>,
+    on_saved: F,
+) -> Result<Vec<BatchResult>, String>
+where
+    F: Fn(OnSetDocContext) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    if payments.is_empty() {
+        return Err("The batch has no payments".to_string());
+    }
+    if payments.len() > MAX_BATCH_PAYMENTS {
+        return Err(format!(
+            "A batch holds at most {} payments, got {}; split the upload",
+            MAX_BATCH_PAYMENTS,
+            payments.len()
+        ));
+    }
+
+    if let Some(ref key) = idempotency_key {
+        if let Some(results) = idempotency::begin(OPERATION, key, &payments)? {
+            return Ok(results);
+        }
+    }
+
+    let caller = caller();
+    let mut results = Vec::with_capacity(payments.len());
+    for (index, payment) in payments.into_iter().enumerate() {
+        let key = payment.key.clone();
+        let (recorded, error) = match write_payment(caller, payment) {
+            Ok(context) => (true, on_saved(context).await.err().map(|e| format!("Recorded, but not applied: {}", e))),
+            Err(e) => (false, Some(e)),
+        };
+        results.push(BatchResult { index: index as u32, key, recorded, error });
+    }
+
+    let results = Ok(results);
+    if let Some(ref key) = idempotency_key {
+        idempotency::finish(OPERATION, key, &results)?;
+    }
+    results
+}
+
+fn write_payment(caller: Principal, payment: PaymentInput) -> Result<OnSetDocContext, String> {
+    if payment.key.trim().is_empty() {
+        return Err("Payment key is required".to_string());
+    }
+    // New payments only: a re-uploaded schedule does not record a payment twice
+    if doc_exists("payments", &payment.key)? {
+        return Err(format!("Payment '{}' is already recorded", payment.key));
+    }
+    let data: serde_json::Value = serde_json::from_str(&payment.data)
+        .map_err(|e| format!("Invalid JSON for payment '{}': {}", payment.key, e))?;
+
+    let doc = set_doc_store(
+        caller,
+        "payments".to_string(),
+        payment.key,
+        SetDoc { data: encode_doc_data(&data)?, description: payment.description, version: None },
+    )?;
+
+    Ok(HookContext { caller, data: doc })
+}
@@ -1,7 +1,25 @@
+pub mod allocation;
+pub mod batch;
+pub mod teller;
+pub mod unapplied;
+
 use junobuild_satellite::{AssertSetDocContext, list_docs};
 use junobuild_shared::types::list::{ListParams, ListMatcher};
 use junobuild_utils::decode_doc_data;
 use serde::{Deserialize, Serialize};
+use super::devices::validate_cash_entry_device;
+use super::fees::{apply_payment_to_assignment, StudentFeeAssignmentData};
+use super::fx::{in_base_currency, validate_foreign_amount};
+use super::gateway::validate_online_payment_confirmation;
+use super::icrc_payments::{validate_crypto_payment, CryptoTransfer, VerifiedBlock};
+use super::receipts::issue_receipt;
+use super::roles::limits::validate_role_write_limit;
+use super::roles::{require_role, Role};
+use super::settings::school_settings;
+use super::utils::cache::cached_doc_exists;
+use super::utils::doc_utils::{doc_exists, get_doc_data, is_satellite_caller, set_doc_data};
+use super::utils::identity::validate_caller_identity;
+use super::utils::money::Money;
 use super::utils::validation_utils::*;
 use std::collections::HashMap;
 
@@ -13,7 +31,7 @@ pub struct PaymentData {
     pub class_id: String,
     pub class_name: String,
     pub fee_assignment_id: String,
-    pub amount: f64,
+    pub amount: Money,
     pub payment_method: String,
     pub payment_date: String,
     pub fee_allocations: Vec<PaymentAllocation>,
@@ -24,30 +42,126 @@ pub struct PaymentData {
     pub notes: Option<String>,
     pub receipt_url: Option<String>,
     pub recorded_by: String,
+    // Registered cashier terminal the payment was entered at (required for cash)
+    #[serde(default)]
+    pub device_id: Option<String>,
+    // Gateway an online payment went through; the first active gateway when not set
+    #[serde(default)]
+    pub gateway: Option<String>,
+    // Allocations were computed by the satellite (the payment arrived without any)
+    #[serde(default)]
+    pub auto_allocated: bool,
+    // Why an `unapplied` payment could not be allocated
+    #[serde(default)]
+    pub unapplied_reason: Option<String>,
+    // Ledger transfer a `crypto` payment was made by, and the block the satellite verified
+    #[serde(default)]
+    pub crypto_transfer: Option<CryptoTransfer>,
+    #[serde(default)]
+    pub verified_block: Option<VerifiedBlock>,
+    // Currency paid in when not the base currency, with the rate and the amount in the
+    // base currency (see `fx`); allocations are in the base currency
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub fx_rate: Option<f64>,
+    #[serde(default)]
+    pub base_amount: Option<Money>,
     pub created_at: u64,
     pub updated_at: u64,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Deserialize, Serialize)]
+impl PaymentData {
+    /// The amount in the base currency, as it is applied to fees and reported.
+    pub fn amount_in_base(&self) -> Money {
+        in_base_currency(self.amount, self.base_amount)
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct PaymentAllocation {
     pub category_id: String,
     pub category_name: String,
     pub fee_type: String,
-    pub amount: f64,
+    pub amount: Money,
+    // Another of the student's fee assignments (arrears) settled by the payment; set by
+    // the automatic allocation only
+    #[serde(default)]
+    pub fee_assignment_id: Option<String>,
+}
+
+/// Called from the `payments` on-set hook: when a payment becomes confirmed, its
+/// allocations are applied to the fee assignments they settle and a receipt is issued.
+/// A payment without allocations is first allocated automatically (see [`allocation`])
+/// and saved with the computed split. A payment whose fee assignment is missing or
+/// belongs to another student is held as `unapplied` instead (see [`unapplied`]).
+pub fn on_payment_saved(key: &str, before: Option<&PaymentData>, payment: &PaymentData) -> Result<(), String> {
+    let was_confirmed = before.map(|b| b.status == "confirmed").unwrap_or(false);
+    if payment.status != "confirmed" || was_confirmed {
+        return Ok(());
+    }
+
+    if let Some(reason) = unapplied::unapplied_reason(payment)? {
+        return unapplied::hold_payment(key, reason);
+    }
+    if payment.fee_allocations.is_empty() {
+        let (doc, mut allocated) = get_doc_data::<PaymentData>("payments", key)?
+            .ok_or_else(|| format!("Payment '{}' not found", key))?;
+        allocated.fee_allocations = allocation::auto_allocate(payment, &school_settings()?.allocation_priority)?;
+        allocated.auto_allocated = true;
+        set_doc_data("payments", key, &allocated, doc.description, doc.version)?;
+        return apply_and_receipt(key, &allocated);
+    }
+    apply_and_receipt(key, payment)
+}
+
+fn apply_and_receipt(key: &str, payment: &PaymentData) -> Result<(), String> {
+    for (assignment_id, amount, allocations) in allocation::allocations_by_assignment(payment) {
+        apply_payment_to_assignment(&assignment_id, key, amount, &allocations)?;
+    }
+    issue_receipt(key, payment)
 }
 
  pub fn validate_payment_document(context: &AssertSetDocContext) -> Result<(), String> {
         let payment_data: PaymentData = decode_doc_data(&context.data.data.proposed.data)
             .map_err(|e| format!("Invalid payment data format: {}", e))?;
 
+        // recordedBy must be the caller
+        validate_caller_identity(context)?;
+
         // Core payment validation (minimal on server)
         validate_payment_core_fields(&payment_data)?;
         validate_payment_dates(&payment_data)?;
+        validate_foreign_amount(
+            payment_data.currency.as_deref(),
+            payment_data.amount,
+            payment_data.fx_rate,
+            payment_data.base_amount,
+            &payment_data.payment_date,
+        )?;
         validate_payment_method_constraints(&payment_data)?;
+        validate_payment_device(context, &payment_data)?;
         validate_payment_status_transitions(context, &payment_data)?;
-        validate_payment_allocations(&payment_data)?;
+        validate_payment_confirmation_role(context, &payment_data)?;
+        validate_online_payment_verified(context, &payment_data)?;
+        validate_crypto_payment(context, &payment_data)?;
+        validate_payment_allocations(context, &payment_data)?;
+        validate_payment_references(context, &payment_data)?;
+        validate_allocations_within_balance(context, &payment_data)?;
         validate_payment_reference_uniqueness(context, &payment_data)?;
+
+        // Amount within the recording user's role limit
+        let before_amount = match context.data.data.current {
+            Some(ref doc) => Some(decode_doc_data::<PaymentData>(&doc.data)
+                .map_err(|e| format!("Invalid previous payment data: {}", e))?
+                .amount_in_base()),
+            None => None,
+        };
+        validate_role_write_limit(context, payment_data.amount_in_base(), before_amount)?;
         
         Ok(())
     }
@@ -55,7 +169,7 @@ pub struct PaymentAllocation {
     // Core payment field validation
     fn validate_payment_core_fields(payment: &PaymentData) -> Result<(), String> {
         // Minimal checks - empty field validation moved to frontend
-        if payment.amount <= 0.0 {
+        if !payment.amount.is_positive() {
             return Err("Payment amount must be greater than zero".to_string());
         }
         Ok(())
@@ -73,7 +187,7 @@ pub struct PaymentAllocation {
 
     fn validate_payment_method_constraints(payment: &PaymentData) -> Result<(), String> {
         // Only enforce allowed enum on server
-        let valid_methods = ["cash", "bank_transfer", "pos", "online", "cheque"];
+        let valid_methods = ["cash", "bank_transfer", "pos", "online", "cheque", "crypto"];
         if !valid_methods.contains(&payment.payment_method.as_str()) {
             return Err(format!(
                 "Invalid payment method '{}'. Must be one of: {}",
@@ -84,12 +198,27 @@ pub struct PaymentAllocation {
         Ok(())
     }
 
+    // Cash is entered at registered cashier terminals only
+    fn validate_payment_device(context: &AssertSetDocContext, payment: &PaymentData) -> Result<(), String> {
+        if payment.payment_method != "cash" || is_satellite_caller(&context.caller) {
+            return Ok(());
+        }
+        if let Some(ref before_doc) = context.data.data.current {
+            let before_payment: PaymentData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous payment data: {}", e))?;
+            if before_payment.payment_method == payment.payment_method && before_payment.device_id == payment.device_id {
+                return Ok(());
+            }
+        }
+        validate_cash_entry_device(payment.device_id.as_deref())
+    }
+
     // Payment status transitions
     fn validate_payment_status_transitions(
         context: &AssertSetDocContext,
         payment: &PaymentData
     ) -> Result<(), String> {
-        let valid_statuses = ["pending", "confirmed", "cancelled", "refunded"];
+        let valid_statuses = ["pending", "confirmed", "unapplied", "cancelled", "refunded"];
         if !valid_statuses.contains(&payment.status.as_str()) {
             return Err(format!(
                 "Invalid payment status '{}'. Must be one of: {}",
@@ -104,8 +233,9 @@ pub struct PaymentAllocation {
                 .map_err(|e| format!("Invalid previous payment data: {}", e))?;
             
             let valid_transitions = HashMap::from([
-                ("pending", vec!["confirmed", "cancelled"]),
+                ("pending", vec!["confirmed", "unapplied", "cancelled"]),
                 ("confirmed", vec!["refunded"]),
+                ("unapplied", vec!["confirmed", "cancelled", "refunded"]),
                 ("cancelled", vec![]), // No transitions from cancelled
                 ("refunded", vec![]),  // No transitions from refunded
             ]);
@@ -113,7 +243,12 @@ pub struct PaymentAllocation {
             let current_status = &before_payment.status;
             let new_status = &payment.status;
             
-            if current_status != new_status {
+            // The confirmation hook holds payments it cannot allocate
+            let held_by_satellite = is_satellite_caller(&context.caller)
+                && current_status == "confirmed"
+                && new_status == "unapplied";
+
+            if current_status != new_status && !held_by_satellite {
                 if let Some(allowed_next_states) = valid_transitions.get(current_status.as_str()) {
                     if !allowed_next_states.contains(&new_status.as_str()) {
                         return Err(format!(
@@ -142,18 +277,69 @@ pub struct PaymentAllocation {
             }
         } else {
             // New payments should typically start as "pending" but can be "confirmed" for immediate confirmation
-            if !vec!["pending", "confirmed"].contains(&payment.status.as_str()) {
-                return Err("New payments must have status 'pending' or 'confirmed'".to_string());
+            if !["pending", "confirmed", "unapplied"].contains(&payment.status.as_str()) {
+                return Err("New payments must have status 'pending', 'confirmed' or 'unapplied'".to_string());
             }
         }
+
+        if payment.status == "unapplied" && payment.unapplied_reason.as_ref().map(|r| r.trim().is_empty()).unwrap_or(true) {
+            return Err("Unapplied payments must include the reason they could not be allocated".to_string());
+        }
         
         Ok(())
     }
 
-    // Fee allocation validation
-    fn validate_payment_allocations(payment: &PaymentData) -> Result<(), String> {
+    // Only bursars confirm payments, whether created confirmed or confirmed later
+    fn validate_payment_confirmation_role(context: &AssertSetDocContext, payment: &PaymentData) -> Result<(), String> {
+        if payment.status != "confirmed" {
+            return Ok(());
+        }
+        if let Some(ref before_doc) = context.data.data.current {
+            let before_payment: PaymentData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous payment data: {}", e))?;
+            if before_payment.status == "confirmed" {
+                return Ok(());
+            }
+        }
+        require_role(&context.caller, Role::Bursar)
+    }
+
+    // Online payments are confirmed only once the gateway has verified the transaction
+    fn validate_online_payment_verified(context: &AssertSetDocContext, payment: &PaymentData) -> Result<(), String> {
+        if payment.payment_method != "online" || payment.status != "confirmed" {
+            return Ok(());
+        }
+        if let Some(ref before_doc) = context.data.data.current {
+            let before_payment: PaymentData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous payment data: {}", e))?;
+            if before_payment.status == "confirmed" {
+                return Ok(());
+            }
+        }
+        validate_online_payment_confirmation(&context.data.key, payment)
+    }
+
+    // Fee allocation validation; payments without allocations are allocated automatically
+    // on confirmation
+    fn validate_payment_allocations(context: &AssertSetDocContext, payment: &PaymentData) -> Result<(), String> {
+        let before_payment: Option<PaymentData> = match context.data.data.current {
+            Some(ref doc) => Some(decode_doc_data(&doc.data)
+                .map_err(|e| format!("Invalid previous payment data: {}", e))?),
+            None => None,
+        };
+        let unchanged = before_payment
+            .as_ref()
+            .map(|b| b.fee_allocations == payment.fee_allocations && b.auto_allocated == payment.auto_allocated)
+            .unwrap_or(false);
+        if !is_satellite_caller(&context.caller)
+            && !unchanged
+            && (payment.auto_allocated || payment.fee_allocations.iter().any(|a| a.fee_assignment_id.is_some()))
+        {
+            return Err("Automatic allocations are computed by the satellite".to_string());
+        }
+
         if payment.fee_allocations.is_empty() {
-            return Err("Payment must have at least one fee allocation".to_string());
+            return Ok(());
         }
         
         if payment.fee_allocations.len() > 20 {
@@ -161,14 +347,14 @@ pub struct PaymentAllocation {
         }
         
         // Validate total allocation matches payment amount
-        let total_allocated: f64 = payment.fee_allocations.iter()
+        let total_allocated: Money = payment.fee_allocations.iter()
             .map(|alloc| alloc.amount)
             .sum();
         
-        if (payment.amount - total_allocated).abs() > 0.01 {
+        if payment.amount_in_base() != total_allocated {
             return Err(format!(
-                "Payment amount (₦{:.2}) must match sum of fee allocations (₦{:.2})",
-                payment.amount, total_allocated
+                "Payment amount (₦{}) must match sum of fee allocations (₦{})",
+                payment.amount_in_base(), total_allocated
             ));
         }
         
@@ -208,6 +394,95 @@ pub struct PaymentAllocation {
         Ok(())
     }
 
+    // The student, class and fee assignment a payment is recorded against must exist, and
+    // the assignment must be the student's. Payments recorded as `unapplied` are the queue
+    // for unidentified payments and are checked once the bursar corrects them.
+    fn validate_payment_references(context: &AssertSetDocContext, payment: &PaymentData) -> Result<(), String> {
+        if payment.status == "unapplied" {
+            return Ok(());
+        }
+        if let Some(ref before_doc) = context.data.data.current {
+            let before_payment: PaymentData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous payment data: {}", e))?;
+            let unchanged = before_payment.student_id == payment.student_id
+                && before_payment.class_id == payment.class_id
+                && before_payment.fee_assignment_id == payment.fee_assignment_id;
+            if unchanged && before_payment.status != "unapplied" {
+                return Ok(());
+            }
+        }
+
+        if !doc_exists("students", &payment.student_id)? {
+            return Err(format!("Student '{}' not found", payment.student_id));
+        }
+        if !cached_doc_exists("classes", &payment.class_id)? {
+            return Err(format!("Class '{}' not found", payment.class_id));
+        }
+        let (_, assignment) = get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", &payment.fee_assignment_id)?
+            .ok_or_else(|| format!("Fee assignment '{}' not found", payment.fee_assignment_id))?;
+        if assignment.student_id != payment.student_id {
+            return Err(format!(
+                "Fee assignment '{}' belongs to another student",
+                payment.fee_assignment_id
+            ));
+        }
+        Ok(())
+    }
+
+    // Allocations entered by a user cannot exceed what is still owed on the fee assignment,
+    // per fee item and in total. Automatic allocations (including overpayments) are computed
+    // by the satellite against the same balances.
+    fn validate_allocations_within_balance(context: &AssertSetDocContext, payment: &PaymentData) -> Result<(), String> {
+        if payment.fee_allocations.is_empty() || payment.status == "unapplied" || is_satellite_caller(&context.caller) {
+            return Ok(());
+        }
+        if let Some(ref before_doc) = context.data.data.current {
+            let before_payment: PaymentData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous payment data: {}", e))?;
+            if before_payment.fee_allocations == payment.fee_allocations
+                && before_payment.fee_assignment_id == payment.fee_assignment_id
+            {
+                return Ok(());
+            }
+        }
+
+        let Some((_, assignment)) = get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", &payment.fee_assignment_id)? else {
+            return Ok(());
+        };
+        // Once applied, the payment is already reflected in the balances
+        if assignment.applied_payment_ids.iter().any(|id| id == &context.data.key) {
+            return Ok(());
+        }
+
+        let mut by_category: HashMap<&str, Money> = HashMap::new();
+        for allocation in payment.fee_allocations.iter() {
+            *by_category.entry(allocation.category_id.as_str()).or_insert(Money::ZERO) += allocation.amount;
+        }
+        for (category_id, allocated) in by_category.iter() {
+            let item = assignment
+                .fee_items
+                .iter()
+                .find(|i| i.category_id == *category_id)
+                .ok_or_else(|| format!(
+                    "Fee category '{}' is not on fee assignment '{}'",
+                    category_id, payment.fee_assignment_id
+                ))?;
+            if *allocated > item.balance {
+                return Err(format!(
+                    "Allocation of ₦{} to '{}' exceeds the ₦{} still owed",
+                    allocated, item.category_name, item.balance
+                ));
+            }
+        }
+        if payment.amount_in_base() > assignment.balance {
+            return Err(format!(
+                "Payment of ₦{} exceeds the ₦{} still owed on fee assignment '{}'",
+                payment.amount_in_base(), assignment.balance, payment.fee_assignment_id
+            ));
+        }
+        Ok(())
+    }
+
     // Payment reference uniqueness (core)
     fn validate_payment_reference_uniqueness(
         context: &AssertSetDocContext,
@@ -0,0 +1,111 @@
+//! Bank teller pre-fill.
+//!
+//! Guardians paying at a bank branch fill in a teller slip. `get_teller_prefill` gives
+//! them what to write: the school's fee collection account (see the
+//! `feeCollectionAccountId` setting), the student's payment reference code to quote in
+//! the narration, and how the amount will be split over what the student owes, the same
+//! way the satellite allocates a payment that arrives without allocations. Quoting the
+//! code lets reconciliation match the credit to the student.
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use super::allocation::{allocate_for_student, OVERPAYMENT_CATEGORY_ID};
+use crate::modules::banking::BankAccountData;
+use crate::modules::fees::{current_fee_assignment, StudentFeeAssignmentData};
+use crate::modules::settings::school_settings;
+use crate::modules::students::{payment_reference_code, StudentData};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct TellerSplitLine {
+    pub fee_assignment_id: String,
+    pub academic_year: String,
+    pub term: String,
+    pub category_id: String,
+    pub category_name: String,
+    pub amount: f64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct TellerPrefill {
+    pub student_id: String,
+    pub admission_number: Option<String>,
+    // To quote in the teller narration
+    pub reference_code: String,
+    pub bank_name: String,
+    pub account_number: String,
+    pub amount: f64,
+    // Across all of the student's fee assignments, arrears included
+    pub outstanding: f64,
+    pub split: Vec<TellerSplitLine>,
+    // Part of the amount beyond what is owed, kept as an overpayment
+    pub overpayment: f64,
+}
+
+/// Teller slip details for paying `amount` (naira) towards a student's fees.
+pub fn get_teller_prefill(student_id: &str, amount: f64) -> Result<TellerPrefill, String> {
+    let amount = Money::from_naira(amount);
+    if !amount.is_positive() {
+        return Err("Amount must be greater than zero".to_string());
+    }
+
+    let (_, student) = get_doc_data::<StudentData>("students", student_id)?
+        .ok_or_else(|| format!("Student '{}' not found", student_id))?;
+
+    let settings = school_settings()?;
+    let account_id = settings
+        .fee_collection_account_id
+        .as_ref()
+        .ok_or("No fee collection bank account is set in the school settings")?;
+    let (_, account) = get_doc_data::<BankAccountData>("bank_accounts", account_id)?
+        .ok_or_else(|| format!("Fee collection bank account '{}' not found", account_id))?;
+
+    let (current_id, _) = current_fee_assignment(student_id)?
+        .ok_or_else(|| format!("Student '{}' has no current fee assignment", student_id))?;
+    let assignments: Vec<(String, StudentFeeAssignmentData)> =
+        list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)?
+            .into_iter()
+            .filter(|(_, _, a)| a.student_id == student_id)
+            .map(|(key, _, a)| (key, a))
+            .collect();
+    let outstanding: Money = assignments
+        .iter()
+        .map(|(_, a)| a.balance)
+        .filter(|balance| balance.is_positive())
+        .sum();
+
+    let mut split = Vec::new();
+    let mut overpayment = Money::ZERO;
+    for allocation in allocate_for_student(student_id, &current_id, amount, &settings.allocation_priority)? {
+        if allocation.category_id == OVERPAYMENT_CATEGORY_ID {
+            overpayment += allocation.amount;
+            continue;
+        }
+        let assignment_id = allocation.fee_assignment_id.as_ref().unwrap_or(&current_id);
+        let Some((_, assignment)) = assignments.iter().find(|(key, _)| key == assignment_id) else {
+            continue;
+        };
+        split.push(TellerSplitLine {
+            fee_assignment_id: assignment_id.clone(),
+            academic_year: assignment.academic_year.clone(),
+            term: assignment.term.clone(),
+            category_id: allocation.category_id,
+            category_name: allocation.category_name,
+            amount: allocation.amount.naira(),
+        });
+    }
+
+    Ok(TellerPrefill {
+        student_id: student_id.to_string(),
+        reference_code: payment_reference_code(student_id, &student),
+        admission_number: student.admission_number,
+        bank_name: account.bank_name,
+        account_number: account.account_number,
+        amount: amount.naira(),
+        outstanding: outstanding.naira(),
+        split,
+        overpayment: overpayment.naira(),
+    })
+}
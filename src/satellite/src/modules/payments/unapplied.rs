@@ -0,0 +1,108 @@
+//! Unapplied payments.
+//!
+//! A payment that cannot be allocated when it is confirmed (no student identified, the
+//! student or fee assignment is missing, or the assignment belongs to another student) is
+//! held as `unapplied` with the reason, without touching any fee balance or issuing a
+//! receipt. The bursar works through the queue from `list_unapplied_payments`, corrects
+//! the student or fee assignment and confirms the payment again, which allocates it as
+//! usual, or cancels or refunds it.
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use super::PaymentData;
+use crate::modules::fees::StudentFeeAssignmentData;
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct UnappliedPayment {
+    pub payment_id: String,
+    pub reference: String,
+    pub student_id: String,
+    pub student_name: String,
+    pub fee_assignment_id: String,
+    pub amount: f64,
+    pub payment_method: String,
+    pub payment_date: String,
+    pub reason: String,
+    pub recorded_by: String,
+    pub created_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct UnappliedPaymentsPage {
+    pub payments: Vec<UnappliedPayment>,
+    // Across all pages
+    pub total_payments: u32,
+    pub total_amount: f64,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+/// Why the payment cannot be allocated, or `None` when it can.
+pub fn unapplied_reason(payment: &PaymentData) -> Result<Option<String>, String> {
+    if payment.student_id.trim().is_empty() {
+        return Ok(Some("No student identified".to_string()));
+    }
+    if !doc_exists("students", &payment.student_id)? {
+        return Ok(Some(format!("Student '{}' not found", payment.student_id)));
+    }
+    match get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", &payment.fee_assignment_id)? {
+        None => Ok(Some(format!("Fee assignment '{}' not found", payment.fee_assignment_id))),
+        Some((_, assignment)) if assignment.student_id != payment.student_id => Ok(Some(format!(
+            "Fee assignment '{}' belongs to another student",
+            payment.fee_assignment_id
+        ))),
+        Some(_) => Ok(None),
+    }
+}
+
+/// Move a just-confirmed payment to `unapplied` with the reason it could not be allocated.
+pub fn hold_payment(key: &str, reason: String) -> Result<(), String> {
+    let (doc, mut payment) = get_doc_data::<PaymentData>("payments", key)?
+        .ok_or_else(|| format!("Payment '{}' not found", key))?;
+    payment.status = "unapplied".to_string();
+    payment.unapplied_reason = Some(reason);
+    payment.updated_at = ic_cdk::api::time();
+    set_doc_data("payments", key, &payment, doc.description, doc.version)?;
+    Ok(())
+}
+
+/// Unapplied payments awaiting the bursar, oldest first.
+pub fn list_unapplied_payments(page: Pagination) -> Result<UnappliedPaymentsPage, String> {
+    page.validate()?;
+
+    let mut held: Vec<(String, PaymentData)> = list_doc_data::<PaymentData>("payments", None)?
+        .into_iter()
+        .filter(|(_, _, p)| p.status == "unapplied")
+        .map(|(key, _, p)| (key, p))
+        .collect();
+    held.sort_by(|(a_key, a), (b_key, b)| a.created_at.cmp(&b.created_at).then(a_key.cmp(b_key)));
+    let total_payments = held.len() as u32;
+    let total_amount: Money = held.iter().map(|(_, p)| p.amount_in_base()).sum();
+
+    Ok(UnappliedPaymentsPage {
+        payments: page
+            .slice(held)
+            .into_iter()
+            .map(|(payment_id, p)| UnappliedPayment {
+                payment_id,
+                reference: p.reference,
+                student_id: p.student_id,
+                student_name: p.student_name,
+                fee_assignment_id: p.fee_assignment_id,
+                amount: p.amount.naira(),
+                payment_method: p.payment_method,
+                payment_date: p.payment_date,
+                reason: p.unapplied_reason.unwrap_or_default(),
+                recorded_by: p.recorded_by,
+                created_at: p.created_at,
+            })
+            .collect(),
+        total_payments,
+        total_amount: total_amount.naira(),
+        offset: page.offset,
+        limit: page.limit,
+    })
+}
@@ -0,0 +1,225 @@
+//! Payroll Module - Payroll Runs
+//!
+//! `create_payroll_run` builds a whole period's payroll on the server. For every active
+//! member of staff it computes gross pay (basic salary, allowances and approved duty
+//! claims not yet paid), the deductions due (PAYE, pension, NHF and court orders) and
+//! the net pay, and creates a pending `salary_payments` document. The run itself is
+//! recorded in `payroll_runs` (one run per period) with its totals, the salary payments
+//! created and the staff members skipped with the reason.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::audit::record_system_change;
+use super::audit::sequences::void_number;
+use super::duty_claims::{list_payable_claims, sync_claims_with_salary_payment};
+use super::garnishments::due_court_order_deductions;
+use super::roles::{require_role, Role};
+use super::staff::loans::due_loan_deductions;
+use super::staff::tax::statutory_deduction_items;
+use super::staff::{PaymentAllowanceItem, SalaryPaymentData, StaffMemberData};
+use super::utils::counters::next_number;
+use super::utils::doc_utils::*;
+use super::utils::idempotency::idempotent;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+use super::verification::{gross_salary, net_salary};
+
+pub const PAYROLL_RUNS_COLLECTION: &str = "payroll_runs";
+
+#[derive(Deserialize, Serialize, CandidType, Clone)]
+pub struct PayrollSkip {
+    pub staff_id: String,
+    pub staff_name: String,
+    pub reason: String,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayrollRunData {
+    pub period_start: String,
+    pub period_end: String,
+    pub status: String,
+    pub salary_payment_ids: Vec<String>,
+    pub skipped: Vec<PayrollSkip>,
+    pub total_gross: Money,
+    pub total_deductions: Money,
+    pub total_net: Money,
+    pub created_by: String,
+    pub created_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct PayrollRunSummary {
+    pub run_id: String,
+    pub salary_payment_ids: Vec<String>,
+    pub skipped: Vec<PayrollSkip>,
+    pub total_gross: f64,
+    pub total_deductions: f64,
+    pub total_net: f64,
+}
+
+/// Payroll runs are recorded by the satellite only.
+pub fn validate_payroll_run_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Payroll runs are created through the create_payroll_run endpoint".to_string());
+    }
+    Ok(())
+}
+
+/// Create pending salary payments for every active member of staff for the period. A
+/// retry with the same idempotency key returns the first run's summary.
+pub fn run_payroll(
+    period_start: String,
+    period_end: String,
+    idempotency_key: Option<String>,
+) -> Result<PayrollRunSummary, String> {
+    let args = (period_start.clone(), period_end.clone());
+    idempotent("create_payroll_run", idempotency_key, &args, || create_run(period_start, period_end))
+}
+
+fn create_run(period_start: String, period_end: String) -> Result<PayrollRunSummary, String> {
+    let caller = caller();
+    require_role(&caller, Role::Hr)?;
+
+    if !is_valid_date_format(&period_start) || !is_valid_date_format(&period_end) {
+        return Err("Payroll period start and end must be valid dates (YYYY-MM-DD)".to_string());
+    }
+    if period_end < period_start {
+        return Err("Payroll period end cannot be before start".to_string());
+    }
+
+    let run_id = format!("{}_{}", period_start, period_end);
+    if doc_exists(PAYROLL_RUNS_COLLECTION, &run_id)? {
+        return Err(format!("A payroll run for {} to {} already exists", period_start, period_end));
+    }
+
+    let salaries = list_doc_data::<SalaryPaymentData>("salary_payments", None)?;
+    let mut references: HashSet<String> = salaries.iter().map(|(_, _, s)| s.reference.clone()).collect();
+    // Staff already paid (or being paid) for an overlapping period
+    let already_paid: HashSet<String> = salaries
+        .iter()
+        .filter(|(_, _, s)| {
+            s.status != "failed" && s.payment_period_start <= period_end && s.payment_period_end >= period_start
+        })
+        .map(|(_, _, s)| s.staff_id.clone())
+        .collect();
+
+    let now = ic_cdk::api::time();
+    let mut run = PayrollRunData {
+        period_start: period_start.clone(),
+        period_end: period_end.clone(),
+        status: "draft".to_string(),
+        salary_payment_ids: Vec::new(),
+        skipped: Vec::new(),
+        total_gross: Money::ZERO,
+        total_deductions: Money::ZERO,
+        total_net: Money::ZERO,
+        created_by: caller.to_text(),
+        created_at: now,
+    };
+
+    let mut staff = list_doc_data::<StaffMemberData>("staff", None)?;
+    staff.sort_by(|a, b| a.2.staff_number.cmp(&b.2.staff_number));
+
+    for (staff_id, _, member) in staff.into_iter().filter(|(_, _, m)| m.is_active) {
+        let staff_name = format!("{} {}", member.firstname, member.surname);
+        let skip_reason = if already_paid.contains(&staff_id) {
+            Some("Already has a salary payment for this period")
+        } else if member.salary_hold.as_ref().map(|h| h.is_active()).unwrap_or(false) {
+            Some("Salary is on hold")
+        } else {
+            None
+        };
+        if let Some(reason) = skip_reason {
+            run.skipped.push(PayrollSkip { staff_id, staff_name, reason: reason.to_string() });
+            continue;
+        }
+
+        let reference = next_salary_reference(&period_start, &mut references)?;
+        let salary_key = format!("{}_{}", run_id, staff_id);
+
+        let mut salary = SalaryPaymentData {
+            staff_id: staff_id.clone(),
+            staff_name,
+            staff_number: member.staff_number.clone(),
+            payment_date: period_end.clone(),
+            payment_period_start: period_start.clone(),
+            payment_period_end: period_end.clone(),
+            basic_salary: member.basic_salary,
+            allowances: member
+                .allowances
+                .unwrap_or_default()
+                .into_iter()
+                .map(|a| PaymentAllowanceItem { name: a.name, amount: a.amount, is_taxable: a.is_taxable, claim_id: None })
+                .collect(),
+            deductions: Vec::new(),
+            net_salary: Money::ZERO,
+            payment_method: if member.account_number.is_some() { "bank_transfer" } else { "cash" }.to_string(),
+            reference,
+            status: "pending".to_string(),
+            notes: Some(format!("Payroll run {}", run_id)),
+            processed_by: caller.to_text(),
+            processed_at: now,
+            bank_reference: None,
+            failure_reason: None,
+            salary_discrepancy: None,
+            created_at: now,
+            updated_at: now,
+            extra: serde_json::Map::new(),
+        };
+        // Approved duty claims not yet paid are paid with this salary
+        salary.allowances.extend(list_payable_claims(&staff_id)?.into_iter().map(|claim| PaymentAllowanceItem {
+            name: format!("Duty: {} ({})", claim.duty_type.replace('_', " "), claim.duty_date),
+            amount: Money::from_naira(claim.amount),
+            // Duty pay is an emolument like any other allowance
+            is_taxable: true,
+            claim_id: Some(claim.claim_id),
+        }));
+        salary.deductions = statutory_deduction_items(&salary);
+        salary.deductions.extend(due_court_order_deductions(&salary_key, &salary)?);
+        salary.deductions.extend(due_loan_deductions(&salary_key, &salary)?);
+
+        let allowances: Vec<Money> = salary.allowances.iter().map(|a| a.amount).collect();
+        let deductions: Vec<Money> = salary.deductions.iter().map(|d| d.amount).collect();
+        let gross = gross_salary(salary.basic_salary, &allowances);
+        salary.net_salary = net_salary(salary.basic_salary, &allowances, &deductions);
+
+        set_doc_data("salary_payments", &salary_key, &salary, None, None)?;
+        // Satellite writes skip the on-set hook, so claims are linked and the creation
+        // audited here
+        sync_claims_with_salary_payment(&salary_key, &salary)?;
+        record_system_change("salary_payments", &salary_key, "create", None, Some(salary.status.clone()))?;
+
+        run.total_gross += gross;
+        run.total_deductions += gross - salary.net_salary;
+        run.total_net += salary.net_salary;
+        run.salary_payment_ids.push(salary_key);
+    }
+
+    set_doc_data(PAYROLL_RUNS_COLLECTION, &run_id, &run, None, None)?;
+
+    Ok(PayrollRunSummary {
+        run_id,
+        salary_payment_ids: run.salary_payment_ids,
+        skipped: run.skipped,
+        total_gross: run.total_gross.naira(),
+        total_deductions: run.total_deductions.naira(),
+        total_net: run.total_net.naira(),
+    })
+}
+
+// SAL-YYYY-MM-NNNNNN from the month's counter, skipping references already in use
+fn next_salary_reference(period_start: &str, references: &mut HashSet<String>) -> Result<String, String> {
+    let month = &period_start[0..7];
+    loop {
+        let number = next_number(&format!("salary-{}", month))?;
+        let reference = format!("SAL-{}-{:06}", month, number);
+        if references.insert(reference.clone()) {
+            return Ok(reference);
+        }
+        void_number("salary", month, number, "Reference already used by a recorded salary payment")?;
+    }
+}
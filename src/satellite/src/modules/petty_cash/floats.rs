@@ -0,0 +1,272 @@
+//! Imprest floats.
+//!
+//! A petty cash float (`petty_cash_floats`) is a fixed imprest amount held in cash by a
+//! custodian. Spending from it is recorded as vouchers (see [`super::vouchers`]), and
+//! what is left of the float is the imprest amount less the vouchers not yet retired.
+//!
+//! `retire_float` reconciles the float: every approved voucher is retired, and the total
+//! is recorded as an expense per expense category. Those expenses are the top-up that
+//! restores the float to its imprest amount, so the spending reaches the expense books
+//! and budgets when the cash is replaced rather than voucher by voucher.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::vouchers::{PettyCashVoucherData, PETTY_CASH_VOUCHERS_COLLECTION};
+use crate::modules::audit::sequences::void_number;
+use crate::modules::budgets::record_budget_spending;
+use crate::modules::category_usage::record_expense_usage;
+use crate::modules::expenses::{ExpenseCategoryData, ExpenseData};
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::cache::cached_doc_data;
+use crate::modules::utils::counters::next_number;
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::utils::validation_utils::current_date;
+
+pub const PETTY_CASH_FLOATS_COLLECTION: &str = "petty_cash_floats";
+
+// Vouchers still drawing on the float's cash
+const OUTSTANDING_VOUCHER_STATUSES: [&str; 2] = ["pending", "approved"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PettyCashFloatData {
+    pub name: String,
+    // Principal of the staff member holding the cash
+    pub custodian: String,
+    pub imprest_amount: Money,
+    pub status: String,
+    pub opened_by: String,
+    #[serde(default)]
+    pub last_retired_at: Option<u64>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct FloatRetirement {
+    pub float_id: String,
+    pub vouchers_retired: u32,
+    pub amount: f64,
+    // Top-up expenses recorded, one per expense category
+    pub expense_ids: Vec<String>,
+}
+
+/// Petty Cash Float Validation
+///
+/// Checks:
+/// - Opened and maintained by a bursar or administrator
+/// - Name and custodian set, imprest amount positive
+/// - The imprest amount cannot drop below the vouchers outstanding against it
+/// - open → closed, only once every voucher is retired or rejected
+pub fn validate_petty_cash_float_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: PettyCashFloatData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid petty cash float data format: {}", e))?;
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can manage petty cash floats".to_string());
+    }
+    if data.name.trim().is_empty() || data.custodian.trim().is_empty() {
+        return Err("Float name and custodian are required".to_string());
+    }
+    if !data.imprest_amount.is_positive() {
+        return Err("Imprest amount must be greater than 0".to_string());
+    }
+
+    let before_doc = match context.data.data.current {
+        Some(ref doc) => doc,
+        None => {
+            if data.status != "open" {
+                return Err("New petty cash floats must have status 'open'".to_string());
+            }
+            if data.opened_by != context.caller.to_text() {
+                return Err("openedBy must be the principal opening the float".to_string());
+            }
+            return Ok(());
+        }
+    };
+
+    let before: PettyCashFloatData = decode_doc_data(&before_doc.data)
+        .map_err(|e| format!("Invalid previous float data: {}", e))?;
+    if before.status == "closed" {
+        return Err("AUDIT: Closed petty cash floats cannot be modified".to_string());
+    }
+    if before.opened_by != data.opened_by {
+        return Err("openedBy cannot be changed".to_string());
+    }
+
+    let outstanding = outstanding_vouchers(&context.data.key, None)?;
+    match data.status.as_str() {
+        "open" if data.imprest_amount < outstanding => Err(format!(
+            "Imprest amount cannot be reduced below the ₦{} of vouchers outstanding",
+            outstanding
+        )),
+        "open" => Ok(()),
+        "closed" if outstanding.is_positive() => Err(format!(
+            "Retire or reject the ₦{} of outstanding vouchers before closing the float",
+            outstanding
+        )),
+        "closed" => Ok(()),
+        other => Err(format!("Invalid float status '{}'. Must be one of: open, closed", other)),
+    }
+}
+
+/// Cash left in a float: its imprest amount less the vouchers outstanding against it,
+/// leaving out `exclude_key` (the voucher being validated).
+pub fn float_balance(float_id: &str, float: &PettyCashFloatData, exclude_key: Option<&str>) -> Result<Money, String> {
+    Ok(float.imprest_amount - outstanding_vouchers(float_id, exclude_key)?)
+}
+
+/// Retire a float's approved vouchers and record the top-up expenses (bursar or
+/// administrator). Pending vouchers must be approved or rejected first.
+pub fn retire_float(float_id: &str) -> Result<FloatRetirement, String> {
+    let caller = caller();
+    if !caller_has_any_role(&caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can retire petty cash floats".to_string());
+    }
+
+    let (float_doc, mut float) = get_doc_data::<PettyCashFloatData>(PETTY_CASH_FLOATS_COLLECTION, float_id)?
+        .ok_or_else(|| format!("Petty cash float '{}' not found", float_id))?;
+    if float.status != "open" {
+        return Err(format!("Petty cash float '{}' is {}", float.name, float.status));
+    }
+
+    let vouchers: Vec<_> = list_doc_data::<PettyCashVoucherData>(PETTY_CASH_VOUCHERS_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, v)| v.float_id == float_id)
+        .collect();
+    let pending = vouchers.iter().filter(|(_, _, v)| v.status == "pending").count();
+    if pending > 0 {
+        return Err(format!("{} voucher(s) on this float are still pending approval", pending));
+    }
+    let approved: Vec<_> = vouchers.into_iter().filter(|(_, _, v)| v.status == "approved").collect();
+    if approved.is_empty() {
+        return Err("No approved vouchers to retire".to_string());
+    }
+
+    let mut by_category: BTreeMap<String, Money> = BTreeMap::new();
+    for (_, _, voucher) in approved.iter() {
+        *by_category.entry(voucher.category_id.clone()).or_default() += voucher.amount;
+    }
+
+    let recorded_by = caller.to_text();
+    let mut expense_ids = Vec::new();
+    for (category_id, amount) in by_category.iter() {
+        let expense_id = record_topup_expense(float_id, &float, category_id, *amount, &recorded_by)?;
+        expense_ids.push(expense_id);
+    }
+
+    let vouchers_retired = approved.len() as u32;
+    let now = ic_cdk::api::time();
+    for (key, doc, mut voucher) in approved {
+        voucher.status = "retired".to_string();
+        voucher.retired_at = Some(now);
+        voucher.updated_at = now;
+        set_doc_data(PETTY_CASH_VOUCHERS_COLLECTION, &key, &voucher, doc.description.clone(), doc.version)?;
+    }
+
+    float.last_retired_at = Some(now);
+    float.updated_at = now;
+    set_doc_data(PETTY_CASH_FLOATS_COLLECTION, float_id, &float, float_doc.description.clone(), float_doc.version)?;
+
+    Ok(FloatRetirement {
+        float_id: float_id.to_string(),
+        vouchers_retired,
+        amount: by_category.values().copied().sum::<Money>().naira(),
+        expense_ids,
+    })
+}
+
+fn outstanding_vouchers(float_id: &str, exclude_key: Option<&str>) -> Result<Money, String> {
+    Ok(list_doc_data::<PettyCashVoucherData>(PETTY_CASH_VOUCHERS_COLLECTION, None)?
+        .iter()
+        .filter(|(key, _, v)| {
+            v.float_id == float_id
+                && OUTSTANDING_VOUCHER_STATUSES.contains(&v.status.as_str())
+                && Some(key.as_str()) != exclude_key
+        })
+        .map(|(_, _, v)| v.amount)
+        .sum())
+}
+
+// A cash expense for the vouchers of one category, approved by the officer retiring the
+// float since each voucher was approved already
+fn record_topup_expense(
+    float_id: &str,
+    float: &PettyCashFloatData,
+    category_id: &str,
+    amount: Money,
+    recorded_by: &str,
+) -> Result<String, String> {
+    let (_, category) = cached_doc_data::<ExpenseCategoryData>("expense_categories", category_id)?
+        .ok_or_else(|| format!("Expense category '{}' not found", category_id))?;
+    let date = current_date();
+    let reference = next_expense_reference(&date)?;
+    let now = ic_cdk::api::time();
+
+    let expense = ExpenseData {
+        category_id: category_id.to_string(),
+        category_name: category.name,
+        category: category.category,
+        amount,
+        description: format!("Petty cash top-up: {} float", float.name),
+        purpose: Some(format!("Retirement of petty cash vouchers on float {}", float_id)),
+        payment_method: "cash".to_string(),
+        payment_date: date,
+        vendor_name: None,
+        vendor_contact: None,
+        reference: reference.clone(),
+        invoice_url: None,
+        status: "approved".to_string(),
+        approved_by: Some(recorded_by.to_string()),
+        // Approval must postdate creation
+        approved_at: Some(now + 1),
+        notes: None,
+        recorded_by: recorded_by.to_string(),
+        created_at: now,
+        updated_at: now,
+        vendor_bank_name: None,
+        vendor_account_number: None,
+        payment_batch: None,
+        bank_reference: None,
+        failure_reason: None,
+        insurance_claim_id: None,
+        work_order_id: None,
+        fund: None,
+        cheque_account_id: None,
+        encumbrance_id: None,
+        vendor_id: None,
+        purchase_order_id: None,
+        split_lines: Vec::new(),
+        void_reason: None,
+        voided_by: None,
+        voided_at: None,
+        currency: None,
+        fx_rate: None,
+        base_amount: None,
+        extra: serde_json::Map::new(),
+    };
+    let key = format!("petty_cash_{}", reference);
+    set_doc_data("expenses", &key, &expense, Some(format!("reference={};", reference)), None)?;
+    // Satellite writes skip the on-set hook, so usage and budget spending are recorded here
+    record_expense_usage(None, Some(&expense))?;
+    record_budget_spending(None, Some(&expense))?;
+    Ok(key)
+}
+
+// EXP-YYYY-PCNNNNNN from the year's counter, skipping references already in use
+fn next_expense_reference(date: &str) -> Result<String, String> {
+    let year = &date[0..4];
+    loop {
+        let number = next_number(&format!("petty-cash-{}", year))?;
+        let reference = format!("EXP-{}-PC{:06}", year, number);
+        if list_doc_data::<ExpenseData>("expenses", Some(format!("reference={};", reference)))?.is_empty() {
+            return Ok(reference);
+        }
+        void_number("petty_cash", year, number, "Reference already used by a recorded expense")?;
+    }
+}
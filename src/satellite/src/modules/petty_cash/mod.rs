@@ -0,0 +1,188 @@
+//! Petty Cash Module - Float Top-Up Validation
+//!
+//! A petty cash float is replenished with cash drawn from a school bank account. To stop
+//! floats being "topped up" on paper without cash actually leaving the bank, every top-up
+//! (`petty_cash_topups`) must be backed by a bank withdrawal:
+//! - The referenced `bank_transactions` entry is a debit of exactly the top-up amount
+//! - The withdrawal falls within a few days of the top-up date
+//! - A withdrawal backs at most one top-up
+//! - Approval is by a bursar or administrator other than the recorder
+//!
+//! Imprest floats and the vouchers paid out of them are kept in [`floats`] and
+//! [`vouchers`].
+
+pub mod floats;
+pub mod vouchers;
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::banking::BankTransactionData;
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const PETTY_CASH_TOPUPS_COLLECTION: &str = "petty_cash_topups";
+
+// Cash is usually withdrawn the same day; allow for weekends and late posting
+const TOPUP_MATCH_WINDOW_DAYS: i64 = 3;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PettyCashTopUpData {
+    pub float_name: String,
+    pub custodian: String,
+    pub amount: Money,
+    pub topup_date: String,
+    pub bank_transaction_id: String,
+    pub status: String,
+    pub approved_by: Option<String>,
+    pub approved_at: Option<u64>,
+    pub notes: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Petty Cash Top-Up Validation
+///
+/// Checks:
+/// - Matching bank withdrawal (same amount, within the date window, not reused)
+/// - Amount, date and bank linkage are fixed once recorded
+/// - pending → approved/rejected, approved by a bursar/admin who did not record it
+pub fn validate_petty_cash_topup_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: PettyCashTopUpData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid petty cash top-up data format: {}", e))?;
+
+    if data.float_name.trim().is_empty() || data.custodian.trim().is_empty() {
+        return Err("Float name and custodian are required".to_string());
+    }
+    if !data.amount.is_positive() {
+        return Err("Top-up amount must be greater than 0".to_string());
+    }
+    if !is_valid_date_format(&data.topup_date) {
+        return Err("Invalid top-up date format. Must be YYYY-MM-DD".to_string());
+    }
+
+    match context.data.data.current {
+        Some(ref before_doc) => {
+            let before: PettyCashTopUpData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous top-up data: {}", e))?;
+
+            if before.amount != data.amount
+                || before.topup_date != data.topup_date
+                || before.bank_transaction_id != data.bank_transaction_id
+                || before.recorded_by != data.recorded_by
+            {
+                return Err("AUDIT: Top-up amount, date and bank withdrawal cannot be changed once recorded".to_string());
+            }
+
+            validate_topup_status_transition(context, &before, &data)?;
+        }
+        None => {
+            if data.status != "pending" {
+                return Err("New petty cash top-ups must have status 'pending'".to_string());
+            }
+            if data.recorded_by != context.caller.to_text() {
+                return Err("recordedBy must be the principal recording the top-up".to_string());
+            }
+        }
+    }
+
+    if data.status != "rejected" {
+        validate_matching_withdrawal(context, &data)?;
+    }
+
+    Ok(())
+}
+
+fn validate_topup_status_transition(
+    context: &AssertSetDocContext,
+    before: &PettyCashTopUpData,
+    data: &PettyCashTopUpData,
+) -> Result<(), String> {
+    if before.status == data.status {
+        return Ok(());
+    }
+
+    let valid_transitions = HashMap::from([
+        ("pending", vec!["approved", "rejected"]),
+        ("approved", vec![]),
+        ("rejected", vec![]),
+    ]);
+
+    let allowed = valid_transitions.get(before.status.as_str()).cloned().unwrap_or_default();
+    if !allowed.contains(&data.status.as_str()) {
+        return Err(format!(
+            "Invalid top-up status transition from '{}' to '{}'. Allowed: [{}]",
+            before.status,
+            data.status,
+            allowed.join(", ")
+        ));
+    }
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can approve or reject petty cash top-ups".to_string());
+    }
+    if data.recorded_by == context.caller.to_text() {
+        return Err("Users cannot approve or reject top-ups they recorded".to_string());
+    }
+
+    match data.status.as_str() {
+        "approved"
+            if data.approved_by.as_deref() != Some(context.caller.to_text().as_str())
+                || data.approved_at.is_none() =>
+        {
+            Err("Approved top-ups must record approvedBy (the approver) and approvedAt".to_string())
+        }
+        "rejected" if data.notes.as_ref().map(|n| n.trim().len() < 10).unwrap_or(true) => {
+            Err("Rejected top-ups must include a reason of at least 10 characters in notes".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_matching_withdrawal(context: &AssertSetDocContext, data: &PettyCashTopUpData) -> Result<(), String> {
+    if data.bank_transaction_id.trim().is_empty() {
+        return Err("Petty cash top-ups must reference the bank withdrawal that funded them".to_string());
+    }
+
+    let (_, withdrawal) = get_doc_data::<BankTransactionData>("bank_transactions", &data.bank_transaction_id)?
+        .ok_or_else(|| format!("Bank transaction '{}' not found", data.bank_transaction_id))?;
+
+    if !withdrawal.debit_amount.is_positive() {
+        return Err("Referenced bank transaction is not a debit; cash did not leave the bank".to_string());
+    }
+    if withdrawal.debit_amount != data.amount {
+        return Err(format!(
+            "Top-up amount ₦{} does not match the bank withdrawal of ₦{}",
+            data.amount, withdrawal.debit_amount
+        ));
+    }
+
+    let withdrawal_date = withdrawal.transaction_date.get(0..10).unwrap_or_default();
+    let gap = days_between(withdrawal_date, &data.topup_date)
+        .ok_or("Referenced bank transaction has no valid transaction date")?;
+    if gap.abs() > TOPUP_MATCH_WINDOW_DAYS {
+        return Err(format!(
+            "Bank withdrawal on {} is more than {} days from the top-up date {}",
+            withdrawal_date, TOPUP_MATCH_WINDOW_DAYS, data.topup_date
+        ));
+    }
+
+    // One withdrawal funds one top-up
+    let topups = list_doc_data::<PettyCashTopUpData>(PETTY_CASH_TOPUPS_COLLECTION, None)?;
+    if let Some((_, _, other)) = topups.iter().find(|(key, _, t)| {
+        key != &context.data.key && t.bank_transaction_id == data.bank_transaction_id && t.status != "rejected"
+    }) {
+        return Err(format!(
+            "Bank withdrawal '{}' already funds a top-up of the {} float",
+            data.bank_transaction_id, other.float_name
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,184 @@
+//! Petty cash vouchers.
+//!
+//! Each payment out of a float is a voucher (`petty_cash_vouchers`) recorded by the
+//! custodian or finance staff and approved by a bursar or administrator. A voucher draws
+//! on the float's cash from the moment it is recorded, so it cannot exceed what is left
+//! of the float. Vouchers become `retired` only through `retire_float`.
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::floats::{float_balance, PettyCashFloatData, PETTY_CASH_FLOATS_COLLECTION};
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::cache::cached_doc_exists;
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::identity::{validate_caller_identity, validate_not_self_approval};
+use crate::modules::utils::money::Money;
+use crate::modules::utils::validation_utils::*;
+
+pub const PETTY_CASH_VOUCHERS_COLLECTION: &str = "petty_cash_vouchers";
+
+// Vouchers above ₦5,000 must have a receipt attached
+const RECEIPT_THRESHOLD: Money = Money::from_kobo(500_000);
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PettyCashVoucherData {
+    pub float_id: String,
+    pub voucher_number: String,
+    pub amount: Money,
+    pub payee: String,
+    pub description: String,
+    pub category_id: String,
+    pub voucher_date: String,
+    pub receipt_url: Option<String>,
+    pub status: String,
+    pub recorded_by: String,
+    pub approved_by: Option<String>,
+    pub approved_at: Option<u64>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub retired_at: Option<u64>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Petty Cash Voucher Validation
+///
+/// Checks:
+/// - References an open float and is recorded by its custodian or finance staff
+/// - Amount within the float's remaining balance, receipt attached above ₦5,000
+/// - Payee, description, an existing expense category and a valid date
+/// - pending → approved/rejected by a bursar or administrator other than the recorder;
+///   approved → retired by the satellite only
+/// - Amount, float and category are fixed once approved
+pub fn validate_petty_cash_voucher_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: PettyCashVoucherData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid petty cash voucher data format: {}", e))?;
+
+    // Recorder and approver must be the callers that set them
+    validate_caller_identity(context)?;
+
+    if data.voucher_number.trim().is_empty() || data.payee.trim().is_empty() || data.description.trim().is_empty() {
+        return Err("Voucher number, payee and description are required".to_string());
+    }
+    if !data.amount.is_positive() {
+        return Err("Voucher amount must be greater than 0".to_string());
+    }
+    if !is_valid_date_format(&data.voucher_date) {
+        return Err("Invalid voucher date format. Must be YYYY-MM-DD".to_string());
+    }
+    if !cached_doc_exists("expense_categories", &data.category_id)? {
+        return Err(format!("Expense category '{}' not found", data.category_id));
+    }
+    let has_receipt = data.receipt_url.as_ref().map(|u| !u.trim().is_empty()).unwrap_or(false);
+    if data.amount > RECEIPT_THRESHOLD && !has_receipt {
+        return Err(format!("Vouchers above ₦{} must have a receipt attached", RECEIPT_THRESHOLD));
+    }
+
+    let before: Option<PettyCashVoucherData> = match context.data.data.current {
+        Some(ref doc) => Some(
+            decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous voucher data: {}", e))?,
+        ),
+        None => None,
+    };
+
+    match before {
+        None => {
+            if data.status != "pending" {
+                return Err("New petty cash vouchers must have status 'pending'".to_string());
+            }
+        }
+        Some(ref before) => validate_voucher_status_transition(context, before, &data)?,
+    }
+
+    // Retirement is the satellite's; the float may be closed by then
+    if data.status == "retired" {
+        return Ok(());
+    }
+
+    let (_, float) = get_doc_data::<PettyCashFloatData>(PETTY_CASH_FLOATS_COLLECTION, &data.float_id)?
+        .ok_or_else(|| format!("Petty cash float '{}' not found", data.float_id))?;
+    if float.status != "open" {
+        return Err(format!("Petty cash float '{}' is {}", float.name, float.status));
+    }
+    if before.is_none()
+        && float.custodian != context.caller.to_text()
+        && !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar, Role::Accountant])
+    {
+        return Err("SECURITY: Only the float's custodian or finance staff can record vouchers".to_string());
+    }
+
+    if data.status != "rejected" {
+        let remaining = float_balance(&data.float_id, &float, Some(&context.data.key))?;
+        if data.amount > remaining {
+            return Err(format!(
+                "Voucher of ₦{} exceeds the ₦{} left on the {} float",
+                data.amount,
+                remaining.max(Money::ZERO),
+                float.name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_voucher_status_transition(
+    context: &AssertSetDocContext,
+    before: &PettyCashVoucherData,
+    data: &PettyCashVoucherData,
+) -> Result<(), String> {
+    if before.status == "retired" || before.status == "rejected" {
+        return Err(format!("AUDIT: {} vouchers cannot be modified", before.status));
+    }
+    if before.recorded_by != data.recorded_by || before.voucher_number != data.voucher_number {
+        return Err("recordedBy and voucherNumber cannot be changed".to_string());
+    }
+    if before.status == "approved"
+        && (before.amount != data.amount || before.float_id != data.float_id || before.category_id != data.category_id)
+    {
+        return Err("AUDIT: Amount, float and category of an approved voucher cannot be changed".to_string());
+    }
+    if before.status == data.status {
+        return Ok(());
+    }
+
+    let valid_transitions = HashMap::from([
+        ("pending", vec!["approved", "rejected"]),
+        ("approved", vec!["retired"]),
+    ]);
+    let allowed = valid_transitions.get(before.status.as_str()).cloned().unwrap_or_default();
+    if !allowed.contains(&data.status.as_str()) {
+        return Err(format!(
+            "Invalid voucher status transition from '{}' to '{}'. Allowed: [{}]",
+            before.status,
+            data.status,
+            allowed.join(", ")
+        ));
+    }
+
+    match data.status.as_str() {
+        "retired" => {
+            if !is_satellite_caller(&context.caller) {
+                return Err("SECURITY: Vouchers are retired through retire_float".to_string());
+            }
+            Ok(())
+        }
+        status => {
+            if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+                return Err("SECURITY: Only a bursar or administrator can approve or reject vouchers".to_string());
+            }
+            validate_not_self_approval(context, Some("recordedBy"), "petty cash vouchers")?;
+            if status == "approved" && (data.approved_by.is_none() || data.approved_at.is_none()) {
+                return Err("Approved vouchers must record approvedBy and approvedAt".to_string());
+            }
+            if status == "rejected" && data.notes.as_ref().map(|n| n.trim().len() < 10).unwrap_or(true) {
+                return Err("Rejected vouchers must include a reason of at least 10 characters in notes".to_string());
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! Procurement Module - Purchase Orders and Vendors
+//!
+//! Goods and services are bought against purchase orders (`purchase_orders`) raised to a
+//! registered vendor (see [`vendors`]). This module enforces:
+//! - draft → approved → delivered → invoiced → paid, with drafts and undelivered orders
+//!   cancellable; approval by a bursar or administrator other than the raiser
+//! - Vendor, category, lines and total are fixed once the order is approved
+//! - Expenses referencing a PO are for the PO's vendor and category, recorded once it is
+//!   invoiced, and cannot take the PO's payments past its total
+//! - The PO becomes `paid` when paid expenses against it cover its total; the satellite
+//!   marks it, since bank-transfer expenses are marked paid by the satellite as well
+
+pub mod vendors;
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::expenses::ExpenseData;
+use super::roles::{caller_has_any_role, Role};
+use super::utils::cache::cached_doc_exists;
+use super::utils::doc_utils::*;
+use super::utils::identity::validate_not_self_approval;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+use vendors::{VendorData, VENDORS_COLLECTION};
+
+pub const PURCHASE_ORDERS_COLLECTION: &str = "purchase_orders";
+
+// Expense statuses that draw on a purchase order
+const COMMITTED_EXPENSE_STATUSES: [&str; 2] = ["approved", "paid"];
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseOrderItem {
+    pub description: String,
+    pub quantity: u32,
+    pub unit_price: Money,
+    pub amount: Money,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseOrderData {
+    pub po_number: String,
+    pub vendor_id: String,
+    pub vendor_name: String,
+    pub category_id: String,
+    pub description: String,
+    pub items: Vec<PurchaseOrderItem>,
+    pub total_amount: Money,
+    pub status: String,
+    pub raised_by: String,
+    pub approved_by: Option<String>,
+    pub approved_at: Option<u64>,
+    pub delivered_date: Option<String>,
+    pub invoice_number: Option<String>,
+    pub invoice_date: Option<String>,
+    #[serde(default)]
+    pub paid_at: Option<u64>,
+    pub notes: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Purchase Order Validation
+///
+/// Checks:
+/// - Raised as a draft by finance staff to an active registered vendor, in an existing
+///   expense category, with a unique PO number
+/// - Each line's amount is quantity × unit price and the lines add up to the total
+/// - Status workflow; approval by a bursar or administrator other than the raiser,
+///   delivery and invoice details recorded, paid by the satellite only
+/// - Vendor, category, lines and total are fixed once approved
+pub fn validate_purchase_order_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: PurchaseOrderData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid purchase order data format: {}", e))?;
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+        return Err("SECURITY: Only finance staff can manage purchase orders".to_string());
+    }
+
+    if data.po_number.trim().is_empty() || data.description.trim().is_empty() {
+        return Err("PO number and description are required".to_string());
+    }
+    validate_purchase_order_lines(&data)?;
+
+    let before: Option<PurchaseOrderData> = match context.data.data.current {
+        Some(ref doc) => Some(
+            decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous purchase order data: {}", e))?,
+        ),
+        None => None,
+    };
+
+    match before {
+        None => {
+            if data.status != "draft" {
+                return Err("New purchase orders must have status 'draft'".to_string());
+            }
+            if data.raised_by != context.caller.to_text() {
+                return Err("raisedBy must be the principal raising the purchase order".to_string());
+            }
+            let duplicate = list_doc_data::<PurchaseOrderData>(PURCHASE_ORDERS_COLLECTION, None)?
+                .into_iter()
+                .any(|(key, _, po)| key != context.data.key && po.po_number == data.po_number);
+            if duplicate {
+                return Err(format!("PO number '{}' already exists", data.po_number));
+            }
+        }
+        Some(ref before) => validate_purchase_order_status_transition(context, before, &data)?,
+    }
+
+    if data.status == "draft" || data.status == "approved" {
+        let (_, vendor) = get_doc_data::<VendorData>(VENDORS_COLLECTION, &data.vendor_id)?
+            .ok_or_else(|| format!("Vendor '{}' not found", data.vendor_id))?;
+        if !vendor.is_active {
+            return Err(format!("Vendor '{}' is inactive", vendor.name));
+        }
+        if data.vendor_name != vendor.name {
+            return Err(format!("vendorName must be the registered vendor's name '{}'", vendor.name));
+        }
+        if !cached_doc_exists("expense_categories", &data.category_id)? {
+            return Err(format!("Expense category '{}' not found", data.category_id));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_purchase_order_lines(data: &PurchaseOrderData) -> Result<(), String> {
+    if data.items.is_empty() {
+        return Err("A purchase order must have at least one line".to_string());
+    }
+    for item in data.items.iter() {
+        if item.description.trim().is_empty() || item.quantity == 0 || !item.unit_price.is_positive() {
+            return Err("Each line needs a description, a quantity and a unit price greater than 0".to_string());
+        }
+        if item.amount != Money::from_kobo(item.unit_price.kobo() * item.quantity as i64) {
+            return Err(format!(
+                "Line '{}': amount ₦{} is not {} × ₦{}",
+                item.description, item.amount, item.quantity, item.unit_price
+            ));
+        }
+    }
+    let lines_total: Money = data.items.iter().map(|i| i.amount).sum();
+    if data.total_amount != lines_total {
+        return Err(format!(
+            "Total ₦{} does not match the sum of the lines (₦{})",
+            data.total_amount, lines_total
+        ));
+    }
+    Ok(())
+}
+
+fn validate_purchase_order_status_transition(
+    context: &AssertSetDocContext,
+    before: &PurchaseOrderData,
+    data: &PurchaseOrderData,
+) -> Result<(), String> {
+    if before.status == "paid" || before.status == "cancelled" {
+        return Err(format!("AUDIT: {} purchase orders cannot be modified", before.status));
+    }
+    if before.po_number != data.po_number || before.raised_by != data.raised_by {
+        return Err("poNumber and raisedBy cannot be changed".to_string());
+    }
+    if before.status != "draft"
+        && (before.vendor_id != data.vendor_id
+            || before.category_id != data.category_id
+            || before.items != data.items
+            || before.total_amount != data.total_amount)
+    {
+        return Err("AUDIT: Vendor, category, lines and total of an approved purchase order cannot be changed".to_string());
+    }
+    if before.status == data.status {
+        return Ok(());
+    }
+
+    let valid_transitions = HashMap::from([
+        ("draft", vec!["approved", "cancelled"]),
+        ("approved", vec!["delivered", "cancelled"]),
+        ("delivered", vec!["invoiced"]),
+        ("invoiced", vec!["paid"]),
+    ]);
+    let allowed = valid_transitions.get(before.status.as_str()).cloned().unwrap_or_default();
+    if !allowed.contains(&data.status.as_str()) {
+        return Err(format!(
+            "Invalid purchase order status transition from '{}' to '{}'. Allowed: [{}]",
+            before.status,
+            data.status,
+            allowed.join(", ")
+        ));
+    }
+
+    match data.status.as_str() {
+        "approved" => {
+            if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+                return Err("SECURITY: Only a bursar or administrator can approve purchase orders".to_string());
+            }
+            validate_not_self_approval(context, Some("raisedBy"), "purchase orders")?;
+            if data.approved_by.as_deref() != Some(context.caller.to_text().as_str()) || data.approved_at.is_none() {
+                return Err("Approved purchase orders must record approvedBy (the approver) and approvedAt".to_string());
+            }
+        }
+        "delivered" => {
+            let delivered = data
+                .delivered_date
+                .as_ref()
+                .filter(|d| is_valid_date_format(d))
+                .ok_or("Delivered purchase orders must have a valid deliveredDate (YYYY-MM-DD)")?;
+            if delivered.as_str() > current_date().as_str() {
+                return Err("deliveredDate cannot be in the future".to_string());
+            }
+        }
+        "invoiced" => {
+            if data.invoice_number.as_ref().map(|n| n.trim().is_empty()).unwrap_or(true) {
+                return Err("Invoiced purchase orders must record the vendor's invoiceNumber".to_string());
+            }
+            if !data.invoice_date.as_ref().map(|d| is_valid_date_format(d)).unwrap_or(false) {
+                return Err("Invoiced purchase orders must have a valid invoiceDate (YYYY-MM-DD)".to_string());
+            }
+        }
+        "paid" if !is_satellite_caller(&context.caller) => {
+            return Err("SECURITY: Purchase orders are marked paid when expenses against them are paid".to_string());
+        }
+        "cancelled" if data.notes.as_ref().map(|n| n.trim().len() < 10).unwrap_or(true) => {
+            return Err("Cancelled purchase orders must include a reason of at least 10 characters in notes".to_string());
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Expenses referencing a purchase order must be for its vendor and category, recorded
+/// once the PO is invoiced, and within what is left of its total. Checked when the
+/// expense is recorded or its PO, vendor or amount changes.
+pub fn validate_expense_purchase_order(context: &AssertSetDocContext, expense: &ExpenseData) -> Result<(), String> {
+    let po_id = match expense.purchase_order_id {
+        Some(ref id) if !id.trim().is_empty() => id,
+        _ => return Ok(()),
+    };
+    if !COMMITTED_EXPENSE_STATUSES.contains(&expense.status.as_str()) {
+        return Ok(());
+    }
+
+    if let Some(ref before_doc) = context.data.data.current {
+        let before: ExpenseData = decode_doc_data(&before_doc.data)
+            .map_err(|e| format!("Invalid previous expense data: {}", e))?;
+        if before.purchase_order_id == expense.purchase_order_id
+            && before.vendor_id == expense.vendor_id
+            && before.amount == expense.amount
+            && COMMITTED_EXPENSE_STATUSES.contains(&before.status.as_str())
+        {
+            return Ok(());
+        }
+    }
+
+    let (_, po) = get_doc_data::<PurchaseOrderData>(PURCHASE_ORDERS_COLLECTION, po_id)?
+        .ok_or_else(|| format!("Purchase order '{}' not found", po_id))?;
+    if po.status != "invoiced" {
+        return Err(format!(
+            "Purchase order {} is {}; expenses are recorded against it once the vendor has invoiced",
+            po.po_number, po.status
+        ));
+    }
+    if expense.vendor_id.as_deref() != Some(po.vendor_id.as_str()) {
+        return Err(format!("Expenses against purchase order {} must be paid to {}", po.po_number, po.vendor_name));
+    }
+    if expense.category_id != po.category_id {
+        return Err(format!("Expenses against purchase order {} must be in its expense category", po.po_number));
+    }
+
+    let committed = committed_against(po_id, Some(&context.data.key))?;
+    let remaining = po.total_amount - committed;
+    if expense.amount > remaining {
+        return Err(format!(
+            "Expense of ₦{} exceeds the ₦{} left on purchase order {} (total ₦{})",
+            expense.amount,
+            remaining.max(Money::ZERO),
+            po.po_number,
+            po.total_amount
+        ));
+    }
+
+    Ok(())
+}
+
+/// Called when an expense is saved, by the on-set hook or directly by satellite writes
+/// that mark expenses paid. Marks the expense's purchase order paid once paid expenses
+/// against it cover its total.
+pub fn record_purchase_order_payment(before: Option<&ExpenseData>, expense: &ExpenseData) -> Result<(), String> {
+    let po_id = match expense.purchase_order_id {
+        Some(ref id) if !id.trim().is_empty() => id,
+        _ => return Ok(()),
+    };
+    if expense.status != "paid" || before.map(|b| b.status == "paid").unwrap_or(false) {
+        return Ok(());
+    }
+
+    let (doc, mut po) = match get_doc_data::<PurchaseOrderData>(PURCHASE_ORDERS_COLLECTION, po_id)? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+    if po.status != "invoiced" {
+        return Ok(());
+    }
+
+    let paid: Money = list_doc_data::<ExpenseData>("expenses", None)?
+        .into_iter()
+        .filter(|(_, _, e)| e.purchase_order_id.as_deref() == Some(po_id.as_str()) && e.status == "paid")
+        .map(|(_, _, e)| e.amount)
+        .sum();
+    if paid < po.total_amount {
+        return Ok(());
+    }
+
+    let now = ic_cdk::api::time();
+    po.status = "paid".to_string();
+    po.paid_at = Some(now);
+    po.updated_at = now;
+    set_doc_data(PURCHASE_ORDERS_COLLECTION, po_id, &po, doc.description.clone(), doc.version)?;
+    Ok(())
+}
+
+// Approved and paid expenses against a PO, leaving out `exclude_key` (the expense being
+// validated)
+fn committed_against(po_id: &str, exclude_key: Option<&str>) -> Result<Money, String> {
+    Ok(list_doc_data::<ExpenseData>("expenses", None)?
+        .iter()
+        .filter(|(key, _, e)| {
+            e.purchase_order_id.as_deref() == Some(po_id)
+                && COMMITTED_EXPENSE_STATUSES.contains(&e.status.as_str())
+                && Some(key.as_str()) != exclude_key
+        })
+        .map(|(_, _, e)| e.amount)
+        .sum())
+}
@@ -0,0 +1,120 @@
+//! Vendor register.
+//!
+//! Suppliers are registered once in `vendors` by finance staff. Purchase orders and
+//! expenses reference a vendor by key (`vendorId`) and carry its name as a snapshot, so
+//! the same supplier is not spelt three different ways across the books. A vendor that
+//! is no longer used is deactivated rather than deleted.
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::PURCHASE_ORDERS_COLLECTION;
+use crate::modules::expenses::ExpenseData;
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::validation_utils::is_valid_account_number;
+
+pub const VENDORS_COLLECTION: &str = "vendors";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VendorData {
+    pub name: String,
+    pub contact_name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub bank_name: Option<String>,
+    pub account_number: Option<String>,
+    pub tax_id: Option<String>,
+    pub is_active: bool,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Vendor Validation
+///
+/// Checks:
+/// - Maintained by a bursar, accountant or administrator
+/// - Name of 2-200 characters, unique regardless of case
+/// - Bank details complete (bank name with a 10-digit account number) when given
+pub fn validate_vendor_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: VendorData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid vendor data format: {}", e))?;
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+        return Err("SECURITY: Only finance staff can maintain the vendor register".to_string());
+    }
+
+    let name = data.name.trim();
+    if name.len() < 2 || name.len() > 200 {
+        return Err("Vendor name must be 2-200 characters".to_string());
+    }
+    let taken = list_doc_data::<VendorData>(VENDORS_COLLECTION, None)?
+        .into_iter()
+        .any(|(key, _, v)| key != context.data.key && v.name.trim().eq_ignore_ascii_case(name));
+    if taken {
+        return Err(format!("Vendor '{}' is already registered", name));
+    }
+
+    let bank_name = data.bank_name.as_deref().map(str::trim).unwrap_or("");
+    let account_number = data.account_number.as_deref().map(str::trim).unwrap_or("");
+    if bank_name.is_empty() != account_number.is_empty() {
+        return Err("Vendor bank name and account number must be given together".to_string());
+    }
+    if !account_number.is_empty() && !is_valid_account_number(account_number) {
+        return Err("Vendor account number must be 10 digits".to_string());
+    }
+
+    Ok(())
+}
+
+/// A vendor cannot be deleted while purchase orders or expenses reference it.
+pub fn validate_vendor_delete(key: &str) -> Result<(), String> {
+    deny_if_referenced(
+        "vendor",
+        key,
+        &[
+            (PURCHASE_ORDERS_COLLECTION, referencing_keys(PURCHASE_ORDERS_COLLECTION, "vendorId", key)?),
+            ("expenses", referencing_keys("expenses", "vendorId", key)?),
+        ],
+    )
+}
+
+/// Vendor names on expenses resolve to the vendor register. Checked when the expense is
+/// recorded or its vendor changes, so expenses recorded before the register are left
+/// alone; the satellite's own expenses (petty cash top-ups) have no vendor.
+pub fn validate_expense_vendor(context: &AssertSetDocContext, expense: &ExpenseData) -> Result<(), String> {
+    if is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+    if let Some(ref before_doc) = context.data.data.current {
+        let before: ExpenseData = decode_doc_data(&before_doc.data)
+            .map_err(|e| format!("Invalid previous expense data: {}", e))?;
+        if before.vendor_id == expense.vendor_id && before.vendor_name == expense.vendor_name {
+            return Ok(());
+        }
+    }
+
+    let vendor_id = match expense.vendor_id {
+        Some(ref id) if !id.trim().is_empty() => id,
+        _ => {
+            if expense.vendor_name.as_ref().map(|n| !n.trim().is_empty()).unwrap_or(false) {
+                return Err("Expenses paid to a vendor must reference a registered vendor (vendorId)".to_string());
+            }
+            return Ok(());
+        }
+    };
+
+    let (_, vendor) = get_doc_data::<VendorData>(VENDORS_COLLECTION, vendor_id)?
+        .ok_or_else(|| format!("Vendor '{}' not found", vendor_id))?;
+    if !vendor.is_active {
+        return Err(format!("Vendor '{}' is inactive", vendor.name));
+    }
+    if expense.vendor_name.as_deref() != Some(vendor.name.as_str()) {
+        return Err(format!("vendorName must be the registered vendor's name '{}'", vendor.name));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! PTA Module - PTA Levy Fund
+//!
+//! PTA levies are held as a separate fund from school money. Fees of type `pta` are PTA
+//! collections, and expenses tagged with `fund: "pta"` are PTA spend. The PTA's own
+//! approval signatories are configured in `fund_settings` (document key `pta`).
+//! This module enforces:
+//! - PTA expenses are approved by a signatory holding the `pta_chair` role
+//! - An expense cannot move between the PTA and school funds once recorded
+//! - PTA collections and spend are reported separately from school funds
+
+use candid::{CandidType, Principal};
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::expenses::ExpenseData;
+use super::payments::PaymentData;
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const FUND_SETTINGS_COLLECTION: &str = "fund_settings";
+pub const PTA_FUND: &str = "pta";
+pub const SCHOOL_FUND: &str = "school";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FundSettingsData {
+    pub fund_name: String,
+    pub signatories: Vec<String>,
+    pub is_active: bool,
+    pub updated_by: String,
+}
+
+#[derive(CandidType, Deserialize, Serialize, Default)]
+pub struct FundSummary {
+    pub collections: f64,
+    pub spend: f64,
+    pub balance: f64,
+    pub payment_count: u32,
+    pub expense_count: u32,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct PtaFundReport {
+    pub period: Option<String>,
+    pub pta: FundSummary,
+    pub school: FundSummary,
+}
+
+/// Fund Settings Validation
+///
+/// Checks:
+/// - Only super admins configure funds; updatedBy is the caller
+/// - Only the PTA fund is configurable
+/// - At least one signatory, each a distinct principal
+pub fn validate_fund_settings_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: FundSettingsData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid fund settings data format: {}", e))?;
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only super admins can configure fund signatories".to_string());
+    }
+    if context.data.key != PTA_FUND {
+        return Err(format!("Unknown fund '{}'. Only '{}' can be configured", context.data.key, PTA_FUND));
+    }
+    if data.fund_name.trim().is_empty() {
+        return Err("Fund name is required".to_string());
+    }
+    if data.updated_by != context.caller.to_text() {
+        return Err("updatedBy must be the principal updating the settings".to_string());
+    }
+
+    if data.signatories.is_empty() {
+        return Err("At least one signatory is required".to_string());
+    }
+    let mut seen = HashSet::new();
+    for signatory in data.signatories.iter() {
+        Principal::from_text(signatory).map_err(|_| format!("Signatory '{}' is not a valid principal", signatory))?;
+        if !seen.insert(signatory.as_str()) {
+            return Err(format!("Signatory '{}' is listed more than once", signatory));
+        }
+    }
+
+    Ok(())
+}
+
+/// PTA expenses must be approved by a configured PTA signatory holding the `pta_chair`
+/// role. Checked when the expense is recorded, approved, or its approver changes.
+pub fn validate_expense_fund(context: &AssertSetDocContext, expense: &ExpenseData) -> Result<(), String> {
+    let fund = expense.fund.as_deref().unwrap_or(SCHOOL_FUND);
+    if fund != SCHOOL_FUND && fund != PTA_FUND {
+        return Err(format!("Invalid fund '{}'. Must be '{}' or '{}'", fund, SCHOOL_FUND, PTA_FUND));
+    }
+
+    let needs_approval = match context.data.data.current {
+        Some(ref before_doc) => {
+            let before: ExpenseData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous expense data: {}", e))?;
+            if before.fund.as_deref().unwrap_or(SCHOOL_FUND) != fund {
+                return Err("AUDIT: An expense cannot be moved between the school and PTA funds".to_string());
+            }
+            (before.status != "approved" && expense.status == "approved") || before.approved_by != expense.approved_by
+        }
+        None => true,
+    };
+
+    if fund != PTA_FUND || !needs_approval || is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+
+    if !caller_has_any_role(&context.caller, &[Role::PtaChair]) {
+        return Err("SECURITY: PTA expenses must be approved by the PTA chair".to_string());
+    }
+
+    let caller = context.caller.to_text();
+    let (_, settings) = get_doc_data::<FundSettingsData>(FUND_SETTINGS_COLLECTION, PTA_FUND)?
+        .ok_or("PTA signatories have not been configured")?;
+    if !settings.is_active {
+        return Err("The PTA fund is not active".to_string());
+    }
+    if !settings.signatories.contains(&caller) {
+        return Err("SECURITY: Caller is not a configured PTA signatory".to_string());
+    }
+    if expense.approved_by.as_deref() != Some(caller.as_str()) {
+        return Err("approvedBy must be the PTA signatory approving the expense".to_string());
+    }
+
+    Ok(())
+}
+
+/// PTA collections (confirmed payments allocated to `pta` fees) and PTA spend against
+/// the same figures for school funds, optionally for one month (YYYY-MM).
+pub fn get_pta_report(period: Option<String>) -> Result<PtaFundReport, String> {
+    if let Some(ref p) = period {
+        if !is_valid_period(p) {
+            return Err("Period must be in format YYYY-MM".to_string());
+        }
+    }
+    let in_period = |date: &str| period.as_ref().map(|p| date.starts_with(p.as_str())).unwrap_or(true);
+
+    let mut pta = FundSummary::default();
+    let mut school = FundSummary::default();
+    // (collections, spend) summed exactly, converted for the report at the end
+    let mut pta_totals = (Money::ZERO, Money::ZERO);
+    let mut school_totals = (Money::ZERO, Money::ZERO);
+
+    for (_, _, payment) in list_doc_data::<PaymentData>("payments", None)?.iter() {
+        if payment.status != "confirmed" || !in_period(&payment.payment_date) {
+            continue;
+        }
+
+        let pta_amount: Money = payment
+            .fee_allocations
+            .iter()
+            .filter(|a| a.fee_type == PTA_FUND)
+            .map(|a| a.amount)
+            .sum();
+        if pta_amount.is_positive() {
+            pta_totals.0 += pta_amount;
+            pta.payment_count += 1;
+        }
+        if (payment.amount - pta_amount).is_positive() {
+            school_totals.0 += payment.amount - pta_amount;
+            school.payment_count += 1;
+        }
+    }
+
+    for (_, _, expense) in list_doc_data::<ExpenseData>("expenses", None)?.iter() {
+        if (expense.status != "approved" && expense.status != "paid") || !in_period(&expense.payment_date) {
+            continue;
+        }
+
+        let (summary, totals) = if expense.fund.as_deref() == Some(PTA_FUND) {
+            (&mut pta, &mut pta_totals)
+        } else {
+            (&mut school, &mut school_totals)
+        };
+        totals.1 += expense.amount;
+        summary.expense_count += 1;
+    }
+
+    for (summary, (collections, spend)) in [(&mut pta, pta_totals), (&mut school, school_totals)] {
+        summary.collections = collections.naira();
+        summary.spend = spend.naira();
+        summary.balance = (collections - spend).naira();
+    }
+
+    Ok(PtaFundReport { period, pta, school })
+}
@@ -0,0 +1,157 @@
+//! Receipts Module - Payment Receipts
+//!
+//! When a payment is confirmed the satellite issues a receipt in `receipts`, numbered
+//! `RCP-YYYY-NNNNNN` from a yearly counter (document key = receipt number). Receipts are
+//! written once and can never be deleted, so a printed receipt can always be checked
+//! against the record. Each receipt is also rendered as a PDF (see [`super::documents`]).
+//!
+//! The one change allowed is voiding: a bursar or administrator other than the cashier
+//! who recorded the payment marks a spoilt or reversed receipt `voided` with a reason.
+//! The receipt keeps its number, so the numbering audit reports it as voided rather than
+//! as a gap, and a voided receipt no longer stops its payment being receipted again.
+//!
+//! Issued receipts are also queued to the student's guardians by SMS and email (see
+//! [`super::notifications::fee_messages`]).
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::documents::store_receipt_pdf;
+use super::notifications::fee_messages::queue_receipt_message;
+use super::payments::PaymentData;
+use super::roles::{caller_has_any_role, Role};
+use super::utils::counters::next_number;
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::current_date;
+
+pub const RECEIPTS_COLLECTION: &str = "receipts";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptData {
+    pub receipt_number: String,
+    pub payment_id: String,
+    pub student_id: String,
+    pub student_name: String,
+    pub amount: Money,
+    pub payment_method: String,
+    pub payment_date: String,
+    pub reference: String,
+    pub issued_at: u64,
+    #[serde(default = "default_status")]
+    pub status: String,
+    #[serde(default)]
+    pub void_reason: Option<String>,
+    #[serde(default)]
+    pub voided_by: Option<String>,
+    #[serde(default)]
+    pub voided_at: Option<u64>,
+}
+
+fn default_status() -> String {
+    "issued".to_string()
+}
+
+/// Receipt Validation
+///
+/// Checks:
+/// - Issued by the satellite only
+/// - The only change is issued → voided, by a bursar or administrator who did not record
+///   the payment, with a reason of at least 10 characters
+/// - Voided is terminal; nothing else on the receipt changes
+pub fn validate_receipt_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let before_doc = match context.data.data.current {
+        Some(ref doc) => doc,
+        None => {
+            if !is_satellite_caller(&context.caller) {
+                return Err("SECURITY: Receipts are issued by the satellite only".to_string());
+            }
+            return Ok(());
+        }
+    };
+
+    let data: ReceiptData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid receipt data format: {}", e))?;
+    let before: ReceiptData = decode_doc_data(&before_doc.data)
+        .map_err(|e| format!("Invalid previous receipt data: {}", e))?;
+
+    if before.status == "voided" {
+        return Err("AUDIT: Voided receipts cannot be modified".to_string());
+    }
+    if data.status != "voided" {
+        return Err("AUDIT: Issued receipts cannot be modified, only voided".to_string());
+    }
+    if data.receipt_number != before.receipt_number
+        || data.payment_id != before.payment_id
+        || data.student_id != before.student_id
+        || data.student_name != before.student_name
+        || data.amount != before.amount
+        || data.payment_method != before.payment_method
+        || data.payment_date != before.payment_date
+        || data.reference != before.reference
+        || data.issued_at != before.issued_at
+    {
+        return Err("AUDIT: Voiding a receipt cannot change its details".to_string());
+    }
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can void receipts".to_string());
+    }
+    if data.voided_by.as_deref() != Some(context.caller.to_text().as_str()) || data.voided_at.is_none() {
+        return Err("Voided receipts must record voidedBy (the approving caller) and voidedAt".to_string());
+    }
+    if data.void_reason.as_ref().map(|r| r.trim().len() < 10).unwrap_or(true) {
+        return Err("Voiding a receipt requires a reason of at least 10 characters".to_string());
+    }
+    if let Some((_, payment)) = get_doc_data::<PaymentData>("payments", &data.payment_id)? {
+        if payment.recorded_by == context.caller.to_text() {
+            return Err("SECURITY: A receipt cannot be voided by the cashier who recorded the payment".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Issued receipts can never be deleted.
+pub fn validate_receipt_delete() -> Result<(), String> {
+    Err("AUDIT: Issued receipts cannot be deleted".to_string())
+}
+
+/// Issue the receipt for a newly confirmed payment. A payment gets one receipt only,
+/// unless its receipts have been voided.
+pub fn issue_receipt(payment_id: &str, payment: &PaymentData) -> Result<(), String> {
+    let description = format!("payment_id={};", payment_id);
+    let receipted = list_doc_data::<ReceiptData>(RECEIPTS_COLLECTION, Some(description.clone()))?
+        .iter()
+        .any(|(_, _, r)| r.status != "voided");
+    if receipted {
+        return Ok(());
+    }
+
+    let year = current_date()[0..4].to_string();
+    let number = next_number(&format!("receipts-{}", year))?;
+    let receipt_number = format!("RCP-{}-{:06}", year, number);
+
+    let receipt = ReceiptData {
+        receipt_number: receipt_number.clone(),
+        payment_id: payment_id.to_string(),
+        student_id: payment.student_id.clone(),
+        student_name: payment.student_name.clone(),
+        amount: payment.amount,
+        payment_method: payment.payment_method.clone(),
+        payment_date: payment.payment_date.clone(),
+        reference: payment.reference.clone(),
+        issued_at: ic_cdk::api::time(),
+        status: default_status(),
+        void_reason: None,
+        voided_by: None,
+        voided_at: None,
+    };
+    set_doc_data(RECEIPTS_COLLECTION, &receipt_number, &receipt, Some(description), None)?;
+    store_receipt_pdf(&receipt, payment);
+    queue_receipt_message(&receipt)?;
+
+    Ok(())
+}
@@ -0,0 +1,273 @@
+//! Matching statement credits by student reference code.
+//!
+//! Guardians paying at a bank branch quote the student's reference code
+//! (`STU-<admission number>-<check digit>`, see the teller pre-fill) in the narration. A
+//! statement credit that matches no recorded transaction is searched for such a code,
+//! whatever the bank did to its separators and case, and scored:
+//! - 100: the code names a student and its check digit is right
+//! - 70: the code names a student but the check digit is wrong or missing
+//! - 50: one character off a single student's code, with that student's check digit
+//!
+//! An exact match becomes a confirmed payment against the student's current fee
+//! assignment, allocated and receipted as if it had been recorded by hand. Anything less,
+//! or an exact match that cannot be recorded, goes to suspense with the suggested student
+//! and score; `get_reference_match_reviews` is the queue of those suggestions for a
+//! finance officer to confirm or reject.
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::suspense::{SuspenseItemData, SUSPENSE_ITEMS_COLLECTION};
+use super::StatementRow;
+use crate::modules::audit::sequences::void_number;
+use crate::modules::classes::ClassData;
+use crate::modules::fees::current_fee_assignment;
+use crate::modules::payments::{on_payment_saved, PaymentData};
+use crate::modules::students::{reference_check_digit, reference_code_body, StudentData};
+use crate::modules::utils::cache::cached_doc_data;
+use crate::modules::utils::counters::next_number;
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+
+pub const EXACT_MATCH_CONFIDENCE: u32 = 100;
+const CHECK_DIGIT_MISMATCH_CONFIDENCE: u32 = 70;
+const NEAR_CODE_CONFIDENCE: u32 = 50;
+
+const CODE_PREFIX: &str = "STU";
+
+/// A student suggested for a statement credit by the code quoted in its narration.
+#[derive(Clone)]
+pub struct CodeMatch {
+    // As quoted, normalised: body and check digit without separators
+    pub code: String,
+    pub student_id: String,
+    pub confidence: u32,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ReferenceMatchReview {
+    pub item_id: String,
+    pub account_id: String,
+    pub date: String,
+    pub description: String,
+    pub reference: Option<String>,
+    pub amount: f64,
+    pub quoted_code: String,
+    pub suggested_student_id: String,
+    pub confidence: u32,
+}
+
+/// Reference code body → student, for every student.
+pub fn student_code_index() -> Result<HashMap<String, String>, String> {
+    Ok(list_doc_data::<StudentData>("students", None)?
+        .into_iter()
+        .map(|(key, _, student)| (reference_code_body(&key, &student), key))
+        .collect())
+}
+
+/// The best-scoring student for the codes quoted in a statement row, if any. Two
+/// different students scoring the same are left for review.
+pub fn match_reference_code(row: &StatementRow, index: &HashMap<String, String>) -> Option<CodeMatch> {
+    let narration = format!("{} {}", row.description, row.reference.as_deref().unwrap_or(""));
+    let mut best: Option<CodeMatch> = None;
+    let mut tied = false;
+
+    for (body, digit) in quoted_codes(&narration) {
+        let Some(candidate) = score_code(&body, digit, index) else {
+            continue;
+        };
+        match best {
+            Some(ref current) if candidate.confidence < current.confidence => {}
+            Some(ref current) if candidate.confidence == current.confidence => {
+                tied |= candidate.student_id != current.student_id;
+            }
+            _ => {
+                tied = false;
+                best = Some(candidate);
+            }
+        }
+    }
+
+    best.map(|mut m| {
+        if tied {
+            m.confidence = m.confidence.min(CHECK_DIGIT_MISMATCH_CONFIDENCE);
+        }
+        m
+    })
+}
+
+/// Record an exactly matched credit as a confirmed bank transfer payment against the
+/// student's current fee assignment; returns the payment key.
+pub fn record_code_payment(
+    report_id: &str,
+    row_index: usize,
+    row: &StatementRow,
+    code_match: &CodeMatch,
+    recorded_by: &str,
+) -> Result<String, String> {
+    let (assignment_id, assignment) = current_fee_assignment(&code_match.student_id)?
+        .ok_or_else(|| format!("Student '{}' has no current fee assignment", code_match.student_id))?;
+    let class_name = cached_doc_data::<ClassData>("classes", &assignment.class_id)?
+        .map(|(_, class)| class.name)
+        .unwrap_or_default();
+    let reference = next_payment_reference(&row.date)?;
+    let now = ic_cdk::api::time();
+
+    let payment = PaymentData {
+        student_id: code_match.student_id.clone(),
+        student_name: assignment.student_name,
+        class_id: assignment.class_id,
+        class_name,
+        fee_assignment_id: assignment_id,
+        amount: Money::from_naira(row.credit),
+        payment_method: "bank_transfer".to_string(),
+        payment_date: row.date.clone(),
+        fee_allocations: Vec::new(),
+        reference: reference.clone(),
+        transaction_id: row.reference.clone(),
+        paid_by: None,
+        status: "confirmed".to_string(),
+        notes: Some(format!("Matched from bank statement by reference code {}", code_match.code)),
+        receipt_url: None,
+        recorded_by: recorded_by.to_string(),
+        device_id: None,
+        gateway: None,
+        auto_allocated: false,
+        unapplied_reason: None,
+        crypto_transfer: None,
+        verified_block: None,
+        currency: None,
+        fx_rate: None,
+        base_amount: None,
+        created_at: now,
+        updated_at: now,
+        extra: serde_json::Map::new(),
+    };
+    let key = format!("{}_{}", report_id, row_index);
+    set_doc_data("payments", &key, &payment, Some(format!("reference={};", reference)), None)?;
+    // Satellite writes skip the on-set hook, so the payment is allocated and receipted here
+    on_payment_saved(&key, None, &payment)?;
+    Ok(key)
+}
+
+/// Open suspense items with a suggested student, most confident first.
+pub fn get_reference_match_reviews() -> Result<Vec<ReferenceMatchReview>, String> {
+    let mut reviews: Vec<ReferenceMatchReview> = list_doc_data::<SuspenseItemData>(SUSPENSE_ITEMS_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, item)| item.status == "open")
+        .filter_map(|(item_id, _, item)| {
+            Some(ReferenceMatchReview {
+                quoted_code: item.quoted_code?,
+                suggested_student_id: item.suggested_student_id?,
+                confidence: item.match_confidence.unwrap_or(0),
+                item_id,
+                account_id: item.account_id,
+                date: item.date,
+                description: item.description,
+                reference: item.reference,
+                amount: item.amount.naira(),
+            })
+        })
+        .collect();
+    reviews.sort_by(|a, b| b.confidence.cmp(&a.confidence).then(a.date.cmp(&b.date)));
+    Ok(reviews)
+}
+
+// Codes quoted in a narration as (body, check digit). Separators may be dashes or
+// slashes, or dropped altogether, in which case a trailing digit is read as the check
+// digit.
+fn quoted_codes(narration: &str) -> Vec<(String, Option<u32>)> {
+    let text = narration.to_ascii_uppercase();
+    let mut codes = Vec::new();
+
+    for (start, _) in text.match_indices(CODE_PREFIX) {
+        let preceded_by_word = text[..start].chars().last().is_some_and(|c| c.is_ascii_alphanumeric());
+        if preceded_by_word {
+            continue;
+        }
+        let parts: Vec<&str> = text[start + CODE_PREFIX.len()..]
+            .trim_start_matches(['-', '/', ' '])
+            .split(|c: char| !c.is_ascii_alphanumeric() && c != '-' && c != '/')
+            .next()
+            .unwrap_or("")
+            .split(['-', '/'])
+            .filter(|part| !part.is_empty())
+            .collect();
+
+        let (body, digit) = match parts.as_slice() {
+            [] => continue,
+            [.., last] if parts.len() > 1 && last.len() == 1 && last.chars().all(|c| c.is_ascii_digit()) => {
+                (parts[..parts.len() - 1].concat(), last.chars().next().and_then(|c| c.to_digit(10)))
+            }
+            _ => {
+                let joined = parts.concat();
+                match joined.chars().last().and_then(|c| c.to_digit(10)) {
+                    Some(digit) if joined.len() > 1 => (joined[..joined.len() - 1].to_string(), Some(digit)),
+                    _ => (joined, None),
+                }
+            }
+        };
+        codes.push((body, digit));
+    }
+    codes
+}
+
+fn score_code(body: &str, digit: Option<u32>, index: &HashMap<String, String>) -> Option<CodeMatch> {
+    let code = format!("{}{}", body, digit.map(|d| d.to_string()).unwrap_or_default());
+
+    if let Some(student_id) = index.get(body) {
+        let confidence = if digit == Some(reference_check_digit(body)) {
+            EXACT_MATCH_CONFIDENCE
+        } else {
+            CHECK_DIGIT_MISMATCH_CONFIDENCE
+        };
+        return Some(CodeMatch { code, student_id: student_id.clone(), confidence });
+    }
+    // A trailing digit read as the check digit may belong to the body, with no check
+    // digit quoted
+    if let Some(student_id) = index.get(&code) {
+        return Some(CodeMatch { code, student_id: student_id.clone(), confidence: CHECK_DIGIT_MISMATCH_CONFIDENCE });
+    }
+
+    let digit = digit?;
+    let mut near = index
+        .iter()
+        .filter(|(known, _)| reference_check_digit(known) == digit && within_one_edit(body, known));
+    match (near.next(), near.next()) {
+        (Some((_, student_id)), None) => Some(CodeMatch {
+            code,
+            student_id: student_id.clone(),
+            confidence: NEAR_CODE_CONFIDENCE,
+        }),
+        _ => None,
+    }
+}
+
+// One character substituted, inserted or dropped
+fn within_one_edit(a: &str, b: &str) -> bool {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let (short, long) = if a.len() <= b.len() { (&a, &b) } else { (&b, &a) };
+    if long.len() - short.len() > 1 {
+        return false;
+    }
+    let prefix = short.iter().zip(long.iter()).take_while(|(x, y)| x == y).count();
+    if short.len() == long.len() {
+        prefix == short.len() || short[prefix + 1..] == long[prefix + 1..]
+    } else {
+        short[prefix..] == long[prefix + 1..]
+    }
+}
+
+// PAY-YYYY-BNNNNNNN from the year's counter, skipping references already in use
+fn next_payment_reference(date: &str) -> Result<String, String> {
+    let year = &date[0..4];
+    loop {
+        let number = next_number(&format!("bank-credit-{}", year))?;
+        let reference = format!("PAY-{}-B{:07}", year, number);
+        if list_doc_data::<PaymentData>("payments", Some(format!("reference={};", reference)))?.is_empty() {
+            return Ok(reference);
+        }
+        void_number("bank_credits", year, number, "Reference already used by a recorded payment")?;
+    }
+}
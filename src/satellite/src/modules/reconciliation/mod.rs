@@ -0,0 +1,231 @@
+//! Reconciliation Module - Bank Statement Import and Matching
+//!
+//! `import_bank_statement` takes the rows of a bank statement for one account and matches
+//! each against the account's unreconciled `bank_transactions`:
+//! - Same debit or credit amount, to the kobo
+//! - Transaction date within a few days of the statement date
+//! - A matching bank reference wins; otherwise the closest date
+//!
+//! Matched transactions are marked reconciled and linked to the statement. Each import
+//! writes a report to `reconciliation_reports` listing the statement rows nothing matched
+//! and the account's transactions in the statement period that the bank did not show.
+//! Unmatched credits quoting a student reference code are matched to the student (see
+//! [`codes`]): exact matches are recorded as confirmed payments, the rest go to
+//! [`suspense`] with the suggested student. Other unmatched credits are money from
+//! unidentified payers and go to suspense as well.
+
+pub mod codes;
+pub mod suspense;
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext};
+use serde::{Deserialize, Serialize};
+
+use super::banking::{BankAccountData, BankTransactionData};
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+use codes::{match_reference_code, record_code_payment, student_code_index, EXACT_MATCH_CONFIDENCE};
+use suspense::post_to_suspense;
+
+pub const RECONCILIATION_REPORTS_COLLECTION: &str = "reconciliation_reports";
+
+const MAX_STATEMENT_ROWS: usize = 1_000;
+// Cheques and transfers often clear a few days after they are recorded
+const MATCH_WINDOW_DAYS: i64 = 5;
+
+#[derive(CandidType, Deserialize, Serialize, Clone)]
+pub struct StatementRow {
+    pub date: String,
+    pub description: String,
+    pub reference: Option<String>,
+    pub debit: f64,
+    pub credit: f64,
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone)]
+pub struct StatementMatch {
+    pub row: u32,
+    pub transaction_id: String,
+}
+
+// A statement credit recorded as a student payment from its reference code
+#[derive(CandidType, Deserialize, Serialize, Clone)]
+pub struct StatementPayment {
+    pub row: u32,
+    pub payment_id: String,
+    pub student_id: String,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconciliationReportData {
+    pub account_id: String,
+    pub statement_from: String,
+    pub statement_to: String,
+    pub rows_imported: u32,
+    pub matched: Vec<StatementMatch>,
+    #[serde(default)]
+    pub payments: Vec<StatementPayment>,
+    pub unmatched_rows: Vec<StatementRow>,
+    pub unmatched_transactions: Vec<String>,
+    pub imported_by: String,
+    pub imported_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ReconciliationSummary {
+    pub report_id: String,
+    pub matched: Vec<StatementMatch>,
+    pub payments: Vec<StatementPayment>,
+    pub unmatched_rows: Vec<StatementRow>,
+    pub unmatched_transactions: Vec<String>,
+}
+
+/// Reconciliation reports are written by `import_bank_statement` only.
+pub fn validate_reconciliation_report_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Reconciliation reports are produced by the statement import".to_string());
+    }
+    Ok(())
+}
+
+/// Import a bank statement for an account and reconcile it against the recorded
+/// transactions.
+pub fn import_bank_statement(account_id: String, rows: Vec<StatementRow>) -> Result<ReconciliationSummary, String> {
+    let caller = caller();
+    if !caller_has_any_role(&caller, &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+        return Err("SECURITY: Only finance officers can import bank statements".to_string());
+    }
+    if get_doc_data::<BankAccountData>("bank_accounts", &account_id)?.is_none() {
+        return Err(format!("Bank account '{}' not found", account_id));
+    }
+    validate_statement_rows(&rows)?;
+
+    let statement_from = rows.iter().map(|r| r.date.clone()).min().unwrap_or_default();
+    let statement_to = rows.iter().map(|r| r.date.clone()).max().unwrap_or_default();
+    let now = ic_cdk::api::time();
+    let report_id = format!("{}_{}", account_id, now);
+
+    let mut candidates: Vec<(String, Option<u64>, BankTransactionData)> =
+        list_doc_data::<BankTransactionData>("bank_transactions", None)?
+            .into_iter()
+            .filter(|(_, _, t)| t.bank_account_id == account_id && t.status != "reconciled")
+            .map(|(key, doc, t)| (key, doc.version, t))
+            .collect();
+
+    let student_codes = student_code_index()?;
+
+    let mut matched = Vec::new();
+    let mut payments = Vec::new();
+    let mut unmatched_rows = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        match best_match(row, &candidates) {
+            Some(position) => {
+                let (key, version, mut transaction) = candidates.swap_remove(position);
+                transaction.status = "reconciled".to_string();
+                transaction.is_reconciled = Some(true);
+                transaction.statement_id = Some(report_id.clone());
+                transaction.reconciled_by = Some(caller.to_text());
+                transaction.reconciled_at = Some(now);
+                set_doc_data("bank_transactions", &key, &transaction, None, version)?;
+                matched.push(StatementMatch { row: index as u32, transaction_id: key });
+            }
+            None if row.credit > 0.0 => {
+                let suggestion = match_reference_code(row, &student_codes);
+                if let Some(ref code_match) = suggestion {
+                    if code_match.confidence == EXACT_MATCH_CONFIDENCE {
+                        // Falls back to suspense when the payment cannot be recorded
+                        if let Ok(payment_id) = record_code_payment(&report_id, index, row, code_match, &caller.to_text()) {
+                            payments.push(StatementPayment {
+                                row: index as u32,
+                                payment_id,
+                                student_id: code_match.student_id.clone(),
+                            });
+                            continue;
+                        }
+                    }
+                }
+                post_to_suspense(&account_id, &report_id, index, row, suggestion.as_ref())?;
+                unmatched_rows.push(row.clone());
+            }
+            None => unmatched_rows.push(row.clone()),
+        }
+    }
+
+    // Recorded in the statement period but not shown by the bank
+    let mut unmatched_transactions: Vec<String> = candidates
+        .into_iter()
+        .filter(|(_, _, t)| t.transaction_date >= statement_from && t.transaction_date <= statement_to)
+        .map(|(key, _, _)| key)
+        .collect();
+    unmatched_transactions.sort();
+
+    let report = ReconciliationReportData {
+        account_id: account_id.clone(),
+        statement_from,
+        statement_to,
+        rows_imported: rows.len() as u32,
+        matched: matched.clone(),
+        payments: payments.clone(),
+        unmatched_rows: unmatched_rows.clone(),
+        unmatched_transactions: unmatched_transactions.clone(),
+        imported_by: caller.to_text(),
+        imported_at: now,
+    };
+    set_doc_data(
+        RECONCILIATION_REPORTS_COLLECTION,
+        &report_id,
+        &report,
+        Some(format!("account_id={};", account_id)),
+        None,
+    )?;
+
+    Ok(ReconciliationSummary {
+        report_id,
+        matched,
+        payments,
+        unmatched_rows,
+        unmatched_transactions,
+    })
+}
+
+fn validate_statement_rows(rows: &[StatementRow]) -> Result<(), String> {
+    if rows.is_empty() || rows.len() > MAX_STATEMENT_ROWS {
+        return Err(format!("A statement import must have 1-{} rows", MAX_STATEMENT_ROWS));
+    }
+    for (index, row) in rows.iter().enumerate() {
+        if !is_valid_date_format(&row.date) {
+            return Err(format!("Row {}: date must be in format YYYY-MM-DD", index + 1));
+        }
+        if row.debit < 0.0 || row.credit < 0.0 || (row.debit > 0.0) == (row.credit > 0.0) {
+            return Err(format!("Row {}: exactly one of debit or credit must be greater than 0", index + 1));
+        }
+    }
+    Ok(())
+}
+
+// Index of the candidate that best matches the row: same amount and within the window,
+// a matching reference first, then the closest date
+fn best_match(row: &StatementRow, candidates: &[(String, Option<u64>, BankTransactionData)]) -> Option<usize> {
+    let debit = Money::from_naira(row.debit);
+    let credit = Money::from_naira(row.credit);
+    let row_reference = row.reference.as_deref().map(|r| r.trim().to_lowercase()).filter(|r| !r.is_empty());
+
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, _, t))| t.debit_amount == debit && t.credit_amount == credit)
+        .filter_map(|(index, (_, _, t))| {
+            let days = days_between(&t.transaction_date, &row.date)?.abs();
+            if days > MATCH_WINDOW_DAYS {
+                return None;
+            }
+            let same_reference = row_reference.is_some()
+                && t.reference.as_deref().map(|r| r.trim().to_lowercase()) == row_reference;
+            Some((index, !same_reference, days))
+        })
+        .min_by_key(|(_, different_reference, days)| (*different_reference, *days))
+        .map(|(index, _, _)| index)
+}
@@ -0,0 +1,240 @@
+//! Suspense items for unidentified receipts.
+//!
+//! A statement credit that matches no recorded transaction is money received from an
+//! unknown payer. The statement import posts each one to `suspense_items` as `open`.
+//! An open item is resolved by a finance officer either as `matched`, linked to the
+//! confirmed student payment of the same amount, or as `refunded` with the refund's bank
+//! reference. Open items are aged in the suspense report and block the period close.
+//! A credit quoting a student reference code that could not be matched exactly carries
+//! the suggested student and the match confidence (see [`super::codes`]).
+
+use candid::CandidType;
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::codes::CodeMatch;
+use super::StatementRow;
+use crate::modules::payments::PaymentData;
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::utils::validation_utils::*;
+
+pub const SUSPENSE_ITEMS_COLLECTION: &str = "suspense_items";
+
+// Upper bounds (days) of the aging buckets; older items fall in the last bucket
+const AGING_BUCKETS: [(i64, &str); 3] = [(30, "0-30 days"), (60, "31-60 days"), (90, "61-90 days")];
+const OLDEST_BUCKET: &str = "Over 90 days";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuspenseItemData {
+    pub account_id: String,
+    pub date: String,
+    pub description: String,
+    pub reference: Option<String>,
+    pub amount: Money,
+    pub report_id: String,
+    pub status: String,
+    pub payment_id: Option<String>,
+    pub refund_reference: Option<String>,
+    pub resolution_notes: Option<String>,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<u64>,
+    pub created_at: u64,
+    // Student reference code quoted in the narration, the student it suggests and the
+    // match confidence (0-100)
+    #[serde(default)]
+    pub quoted_code: Option<String>,
+    #[serde(default)]
+    pub suggested_student_id: Option<String>,
+    #[serde(default)]
+    pub match_confidence: Option<u32>,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct OutstandingSuspenseItem {
+    pub item_id: String,
+    pub account_id: String,
+    pub date: String,
+    pub description: String,
+    pub reference: Option<String>,
+    pub amount: f64,
+    pub age_days: i64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct SuspenseAgingBucket {
+    pub label: String,
+    pub count: u32,
+    pub amount: f64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct SuspenseReport {
+    pub items: Vec<OutstandingSuspenseItem>,
+    pub buckets: Vec<SuspenseAgingBucket>,
+    pub total_outstanding: f64,
+}
+
+/// Suspense Item Validation
+///
+/// Checks:
+/// - Items are posted by the statement import only
+/// - Only finance officers resolve items, once: open → matched/refunded
+/// - Matched items link a confirmed payment of the same amount not used by another item
+/// - Refunded items carry the refund's bank reference and notes
+pub fn validate_suspense_item_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: SuspenseItemData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid suspense item data format: {}", e))?;
+
+    let before: SuspenseItemData = match context.data.data.current {
+        Some(ref doc) => decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous suspense item data: {}", e))?,
+        None if is_satellite_caller(&context.caller) => return Ok(()),
+        None => return Err("SECURITY: Suspense items are posted by the bank statement import".to_string()),
+    };
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+        return Err("SECURITY: Only finance officers can resolve suspense items".to_string());
+    }
+    if before.status != "open" {
+        return Err(format!("Suspense item is already {}", before.status));
+    }
+    if data.account_id != before.account_id
+        || data.date != before.date
+        || data.amount != before.amount
+        || data.reference != before.reference
+        || data.report_id != before.report_id
+        || data.quoted_code != before.quoted_code
+        || data.suggested_student_id != before.suggested_student_id
+        || data.match_confidence != before.match_confidence
+    {
+        return Err("Suspense item details cannot be changed".to_string());
+    }
+    if data.resolved_by.as_deref() != Some(context.caller.to_text().as_str()) || data.resolved_at.is_none() {
+        return Err("resolvedBy and resolvedAt must record the principal resolving the item".to_string());
+    }
+
+    match data.status.as_str() {
+        "matched" => {
+            let payment_id = data.payment_id.as_deref().ok_or("Matched suspense items must link the student payment")?;
+            let (_, payment) = get_doc_data::<PaymentData>("payments", payment_id)?
+                .ok_or_else(|| format!("Payment '{}' not found", payment_id))?;
+            if payment.status != "confirmed" || payment.amount != data.amount {
+                return Err(format!(
+                    "Payment '{}' must be a confirmed payment of ₦{}",
+                    payment_id, data.amount
+                ));
+            }
+            let already_used = list_doc_data::<SuspenseItemData>(SUSPENSE_ITEMS_COLLECTION, None)?
+                .iter()
+                .any(|(key, _, item)| key != &context.data.key && item.payment_id.as_deref() == Some(payment_id));
+            if already_used {
+                return Err(format!("Payment '{}' already resolves another suspense item", payment_id));
+            }
+        }
+        "refunded" => {
+            if data.refund_reference.as_ref().map(|r| r.trim().is_empty()).unwrap_or(true) {
+                return Err("Refunded suspense items must include the refund bank reference".to_string());
+            }
+            if data.resolution_notes.as_ref().map(|n| n.trim().is_empty()).unwrap_or(true) {
+                return Err("Refunded suspense items must include notes".to_string());
+            }
+        }
+        other => {
+            return Err(format!("Invalid status '{}'. Open suspense items resolve to: matched, refunded", other));
+        }
+    }
+
+    Ok(())
+}
+
+/// Post an unidentified statement credit to suspense, with the student its quoted
+/// reference code suggests, if any.
+pub fn post_to_suspense(
+    account_id: &str,
+    report_id: &str,
+    row_index: usize,
+    row: &StatementRow,
+    suggestion: Option<&CodeMatch>,
+) -> Result<(), String> {
+    let item = SuspenseItemData {
+        account_id: account_id.to_string(),
+        date: row.date.clone(),
+        description: row.description.clone(),
+        reference: row.reference.clone(),
+        amount: Money::from_naira(row.credit),
+        report_id: report_id.to_string(),
+        status: "open".to_string(),
+        payment_id: None,
+        refund_reference: None,
+        resolution_notes: None,
+        resolved_by: None,
+        resolved_at: None,
+        created_at: ic_cdk::api::time(),
+        quoted_code: suggestion.map(|m| m.code.clone()),
+        suggested_student_id: suggestion.map(|m| m.student_id.clone()),
+        match_confidence: suggestion.map(|m| m.confidence),
+    };
+    let key = format!("{}_{}", report_id, row_index);
+    set_doc_data(SUSPENSE_ITEMS_COLLECTION, &key, &item, None, None)?;
+    Ok(())
+}
+
+/// Open suspense items dated on or before the given date.
+pub fn open_suspense_items(up_to: &str) -> Result<Vec<(String, SuspenseItemData)>, String> {
+    Ok(list_doc_data::<SuspenseItemData>(SUSPENSE_ITEMS_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, item)| item.status == "open" && item.date.as_str() <= up_to)
+        .map(|(key, _, item)| (key, item))
+        .collect())
+}
+
+/// Outstanding suspense items, oldest first, with an aging summary.
+pub fn get_suspense_report() -> Result<SuspenseReport, String> {
+    let today = current_date();
+
+    let mut items: Vec<OutstandingSuspenseItem> = open_suspense_items(&today)?
+        .into_iter()
+        .map(|(item_id, item)| OutstandingSuspenseItem {
+            age_days: days_between(&item.date, &today).unwrap_or(0),
+            item_id,
+            account_id: item.account_id,
+            date: item.date,
+            description: item.description,
+            reference: item.reference,
+            amount: item.amount.naira(),
+        })
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.age_days));
+
+    let mut totals: HashMap<&str, (u32, Money)> = HashMap::new();
+    for item in items.iter() {
+        let label = AGING_BUCKETS
+            .iter()
+            .find(|(max_days, _)| item.age_days <= *max_days)
+            .map(|(_, label)| *label)
+            .unwrap_or(OLDEST_BUCKET);
+        let total = totals.entry(label).or_insert((0, Money::ZERO));
+        total.0 += 1;
+        total.1 += Money::from_naira(item.amount);
+    }
+
+    let buckets = AGING_BUCKETS
+        .iter()
+        .map(|(_, label)| *label)
+        .chain(std::iter::once(OLDEST_BUCKET))
+        .map(|label| {
+            let (count, amount) = totals.get(label).copied().unwrap_or((0, Money::ZERO));
+            SuspenseAgingBucket { label: label.to_string(), count, amount: amount.naira() }
+        })
+        .collect();
+
+    Ok(SuspenseReport {
+        total_outstanding: items.iter().map(|i| Money::from_naira(i.amount)).sum::<Money>().naira(),
+        items,
+        buckets,
+    })
+}
@@ -0,0 +1,298 @@
+//! Remittances Module - Third-Party Deduction Tracking
+//!
+//! Union dues, cooperative contributions and similar payroll deductions are held by the
+//! school until remitted to the receiving body. This module:
+//! - Registers deduction bodies (`deduction_bodies`) and the deduction name they collect
+//! - Validates remittance records (`deduction_remittances`) against what was actually deducted
+//! - Aggregates deductions per period per body for the remittance schedule
+//! - Reports deducted-but-unremitted balances
+
+use candid::CandidType;
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::staff::SalaryPaymentData;
+use super::utils::doc_utils::*;
+use super::utils::identity::validate_caller_identity;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const DEDUCTION_BODIES_COLLECTION: &str = "deduction_bodies";
+pub const DEDUCTION_REMITTANCES_COLLECTION: &str = "deduction_remittances";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeductionBodyData {
+    pub name: String,
+    pub body_type: String,
+    pub deduction_name: String,
+    pub remittance_day: u32,
+    pub bank_name: Option<String>,
+    pub account_number: Option<String>,
+    pub is_active: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeductionRemittanceData {
+    pub body_id: String,
+    pub period: String,
+    pub amount: Money,
+    pub remittance_date: String,
+    pub reference: String,
+    pub payment_method: String,
+    pub notes: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct RemittanceScheduleItem {
+    pub body_id: String,
+    pub body_name: String,
+    pub body_type: String,
+    pub period: String,
+    pub due_date: String,
+    pub amount_deducted: f64,
+    pub amount_remitted: f64,
+    pub outstanding: f64,
+}
+
+/// Deduction Body Validation
+///
+/// Checks:
+/// - Known body type and remittance day (1-28 so every month has it)
+/// - Deduction name is unique across bodies (aggregation key)
+/// - Bank account format when provided
+pub fn validate_deduction_body_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: DeductionBodyData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid deduction body data format: {}", e))?;
+
+    if data.name.trim().is_empty() {
+        return Err("Deduction body name is required".to_string());
+    }
+
+    let valid_body_types = ["union", "cooperative", "pension", "tax", "other"];
+    if !valid_body_types.contains(&data.body_type.as_str()) {
+        return Err(format!(
+            "Invalid body type '{}'. Must be one of: {}",
+            data.body_type,
+            valid_body_types.join(", ")
+        ));
+    }
+
+    if data.deduction_name.trim().is_empty() {
+        return Err("deductionName is required to match salary deductions".to_string());
+    }
+
+    if !(1..=28).contains(&data.remittance_day) {
+        return Err("remittanceDay must be between 1 and 28".to_string());
+    }
+
+    if let Some(ref account) = data.account_number {
+        if !account.trim().is_empty() && !is_valid_account_number(account) {
+            return Err("Account number must be 10 digits".to_string());
+        }
+    }
+
+    // Deduction name is the aggregation key, so it must map to exactly one body
+    let duplicate = list_doc_data::<DeductionBodyData>(DEDUCTION_BODIES_COLLECTION, None)?
+        .into_iter()
+        .any(|(key, _, body)| {
+            key != context.data.key && body.deduction_name.eq_ignore_ascii_case(&data.deduction_name)
+        });
+    if duplicate {
+        return Err(format!("Deduction '{}' is already remitted to another body", data.deduction_name));
+    }
+
+    Ok(())
+}
+
+/// Deduction Remittance Validation
+///
+/// Checks:
+/// - Body exists and period is YYYY-MM
+/// - Remittances are immutable once recorded (corrections are new entries)
+/// - Total remitted for the body/period never exceeds what was deducted
+pub fn validate_deduction_remittance_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: DeductionRemittanceData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid remittance data format: {}", e))?;
+
+    // The remittance is recorded under the caller's own identity
+    validate_caller_identity(context)?;
+
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Remittance records cannot be modified once recorded".to_string());
+    }
+
+    if !data.amount.is_positive() {
+        return Err("Remittance amount must be greater than zero".to_string());
+    }
+
+    if !is_valid_period(&data.period) {
+        return Err("Remittance period must be in format YYYY-MM".to_string());
+    }
+
+    if !is_valid_date_format(&data.remittance_date) {
+        return Err("Invalid remittance date format. Must be YYYY-MM-DD".to_string());
+    }
+
+    if data.reference.trim().is_empty() {
+        return Err("Remittance reference (bank teller/transfer reference) is required".to_string());
+    }
+
+    let valid_methods = ["bank_transfer", "cash", "cheque"];
+    if !valid_methods.contains(&data.payment_method.as_str()) {
+        return Err(format!(
+            "Invalid payment method '{}'. Must be one of: {}",
+            data.payment_method,
+            valid_methods.join(", ")
+        ));
+    }
+
+    let (_, body) = get_doc_data::<DeductionBodyData>(DEDUCTION_BODIES_COLLECTION, &data.body_id)?
+        .ok_or_else(|| format!("Deduction body '{}' not found", data.body_id))?;
+
+    let deducted = total_deducted(&body.deduction_name, &data.period)?;
+    let already_remitted = total_remitted(&data.body_id, &data.period)?;
+
+    if already_remitted + data.amount > deducted {
+        return Err(format!(
+            "Remittance of ₦{} to {} exceeds unremitted deductions for {} (deducted ₦{}, already remitted ₦{})",
+            data.amount, body.name, data.period, deducted, already_remitted
+        ));
+    }
+
+    Ok(())
+}
+
+/// Remittance schedule for one period (YYYY-MM): per body deducted, remitted and due date.
+pub fn get_remittance_schedule(period: &str) -> Result<Vec<RemittanceScheduleItem>, String> {
+    if !is_valid_period(period) {
+        return Err("Period must be in format YYYY-MM".to_string());
+    }
+
+    let bodies = list_doc_data::<DeductionBodyData>(DEDUCTION_BODIES_COLLECTION, None)?;
+    let deducted = deductions_by_period()?;
+    let remitted = remittances_by_period()?;
+
+    let mut schedule = Vec::new();
+    for (body_id, _, body) in bodies.into_iter().filter(|(_, _, b)| b.is_active) {
+        let key = (body.deduction_name.to_lowercase(), period.to_string());
+        let amount_deducted = deducted.get(&key).copied().unwrap_or_default();
+        let amount_remitted = remitted.get(&(body_id.clone(), period.to_string())).copied().unwrap_or_default();
+
+        schedule.push(RemittanceScheduleItem {
+            due_date: remittance_due_date(period, body.remittance_day),
+            body_id,
+            body_name: body.name,
+            body_type: body.body_type,
+            period: period.to_string(),
+            amount_deducted: amount_deducted.naira(),
+            amount_remitted: amount_remitted.naira(),
+            outstanding: (amount_deducted - amount_remitted).naira(),
+        });
+    }
+
+    Ok(schedule)
+}
+
+/// All body/period combinations where deductions exceed remittances, oldest first.
+pub fn get_unremitted_deductions() -> Result<Vec<RemittanceScheduleItem>, String> {
+    let bodies = list_doc_data::<DeductionBodyData>(DEDUCTION_BODIES_COLLECTION, None)?;
+    let deducted = deductions_by_period()?;
+    let remitted = remittances_by_period()?;
+
+    let mut report = Vec::new();
+    for (body_id, _, body) in bodies.iter() {
+        let deduction_key = body.deduction_name.to_lowercase();
+
+        for ((name, period), amount_deducted) in deducted.iter() {
+            if name != &deduction_key {
+                continue;
+            }
+
+            let amount_remitted = remitted.get(&(body_id.clone(), period.clone())).copied().unwrap_or_default();
+            let outstanding = *amount_deducted - amount_remitted;
+            if !outstanding.is_positive() {
+                continue;
+            }
+
+            report.push(RemittanceScheduleItem {
+                body_id: body_id.clone(),
+                body_name: body.name.clone(),
+                body_type: body.body_type.clone(),
+                period: period.clone(),
+                due_date: remittance_due_date(period, body.remittance_day),
+                amount_deducted: amount_deducted.naira(),
+                amount_remitted: amount_remitted.naira(),
+                outstanding: outstanding.naira(),
+            });
+        }
+    }
+
+    report.sort_by(|a, b| a.period.cmp(&b.period).then(a.body_name.cmp(&b.body_name)));
+    Ok(report)
+}
+
+fn total_deducted(deduction_name: &str, period: &str) -> Result<Money, String> {
+    let deducted = deductions_by_period()?;
+    Ok(deducted
+        .get(&(deduction_name.to_lowercase(), period.to_string()))
+        .copied()
+        .unwrap_or_default())
+}
+
+fn total_remitted(body_id: &str, period: &str) -> Result<Money, String> {
+    let remitted = remittances_by_period()?;
+    Ok(remitted
+        .get(&(body_id.to_string(), period.to_string()))
+        .copied()
+        .unwrap_or_default())
+}
+
+/// Sum of paid salary deductions keyed by (lowercased deduction name, YYYY-MM of the pay period).
+fn deductions_by_period() -> Result<BTreeMap<(String, String), Money>, String> {
+    let salaries = list_doc_data::<SalaryPaymentData>("salary_payments", None)?;
+
+    let mut totals: BTreeMap<(String, String), Money> = BTreeMap::new();
+    for (_, _, salary) in salaries.iter().filter(|(_, _, s)| s.status == "paid") {
+        let period = match salary.payment_period_start.get(0..7) {
+            Some(period) => period.to_string(),
+            None => continue,
+        };
+
+        for deduction in salary.deductions.iter() {
+            *totals
+                .entry((deduction.name.to_lowercase(), period.clone()))
+                .or_default() += deduction.amount;
+        }
+    }
+
+    Ok(totals)
+}
+
+/// Sum of recorded remittances keyed by (body id, period).
+fn remittances_by_period() -> Result<BTreeMap<(String, String), Money>, String> {
+    let remittances = list_doc_data::<DeductionRemittanceData>(DEDUCTION_REMITTANCES_COLLECTION, None)?;
+
+    let mut totals: BTreeMap<(String, String), Money> = BTreeMap::new();
+    for (_, _, remittance) in remittances.iter() {
+        *totals
+            .entry((remittance.body_id.clone(), remittance.period.clone()))
+            .or_default() += remittance.amount;
+    }
+
+    Ok(totals)
+}
+
+/// Remittances fall due in the month after the deduction period; no due date for a
+/// malformed period.
+fn remittance_due_date(period: &str, day: u32) -> String {
+    next_period(period)
+        .map(|due| format!("{}-{:02}", due, day))
+        .unwrap_or_default()
+}
@@ -0,0 +1,345 @@
+//! Replication Module - Event Outbox for an Analytics Satellite
+//!
+//! Schools that run heavy reporting on a companion canister can turn on replication in
+//! `replication_settings` (key `default`). While it is active, every write or delete in a
+//! replicated collection is recorded in `replication_outbox` with a sequence number, and
+//! the outbox is pushed to the companion canister in order, in batches, through an
+//! inter-canister call to its ingest method:
+//!
+//! `ingest_events : (ReplicationBatch) -> (variant { Ok : nat64; Err : text })`
+//!
+//! The companion answers with the highest sequence it has stored. Entries up to that
+//! sequence are removed from the outbox; anything not acknowledged stays and is pushed
+//! again on the next run, so delivery is at least once and the companion must ignore
+//! sequences it has already stored. Progress and the last error are kept in
+//! `replication_state`.
+
+use candid::{CandidType, Principal};
+use ic_cdk::call::Call;
+use junobuild_satellite::{caller, id, AssertSetDocContext, Doc};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+use super::roles::{caller_has_any_role, require_role, Role};
+use super::utils::counters::next_number;
+use super::utils::doc_utils::*;
+
+pub const REPLICATION_SETTINGS_COLLECTION: &str = "replication_settings";
+pub const REPLICATION_OUTBOX_COLLECTION: &str = "replication_outbox";
+pub const REPLICATION_STATE_COLLECTION: &str = "replication_state";
+
+// Collections whose writes can be replicated; each must be in the satellite's
+// on_set_doc and on_delete_doc hook lists
+pub const REPLICABLE_COLLECTIONS: [&str; 5] =
+    ["expenses", "payments", "salary_payments", "student_fee_assignments", "students"];
+
+const SETTINGS_KEY: &str = "default";
+const STATE_KEY: &str = "default";
+const DEFAULT_INGEST_METHOD: &str = "ingest_events";
+const MAX_BATCH_EVENTS: usize = 100;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationSettingsData {
+    pub target_canister: String,
+    pub ingest_method: Option<String>,
+    pub collections: Vec<String>,
+    pub is_active: bool,
+    pub updated_by: String,
+    pub updated_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxEntryData {
+    pub sequence: u64,
+    pub collection: String,
+    pub doc_key: String,
+    // set | delete
+    pub action: String,
+    // Document data as JSON; None for deletes
+    pub data: Option<String>,
+    pub recorded_at: u64,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationStateData {
+    pub acknowledged_sequence: u64,
+    pub last_pushed_at: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ReplicationEvent {
+    pub sequence: u64,
+    pub collection: String,
+    pub doc_key: String,
+    pub action: String,
+    pub data: Option<String>,
+    pub recorded_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ReplicationBatch {
+    pub source: Principal,
+    pub events: Vec<ReplicationEvent>,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ReplicationStatus {
+    pub is_active: bool,
+    pub target_canister: Option<String>,
+    pub acknowledged_sequence: u64,
+    pub pending_events: u32,
+    pub last_pushed_at: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+thread_local! {
+    // A push is awaiting the companion's answer
+    static PUSH_IN_FLIGHT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Replication Settings Validation
+///
+/// Checks:
+/// - Only administrators configure replication, under the `default` key
+/// - Target is a valid canister principal other than this satellite
+/// - Collections are replicable and listed once
+pub fn validate_replication_settings_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only administrators can configure replication".to_string());
+    }
+    if context.data.key != SETTINGS_KEY {
+        return Err(format!("Replication settings must use the key '{}'", SETTINGS_KEY));
+    }
+
+    let data: ReplicationSettingsData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid replication settings data format: {}", e))?;
+
+    let target = Principal::from_text(&data.target_canister)
+        .map_err(|_| format!("Invalid target canister '{}'", data.target_canister))?;
+    if target == id() {
+        return Err("The replication target cannot be this satellite".to_string());
+    }
+    if data.ingest_method.as_ref().is_some_and(|m| m.trim().is_empty()) {
+        return Err("ingestMethod cannot be empty".to_string());
+    }
+    if data.collections.is_empty() {
+        return Err("At least one collection must be replicated".to_string());
+    }
+    for (i, collection) in data.collections.iter().enumerate() {
+        if !REPLICABLE_COLLECTIONS.contains(&collection.as_str()) {
+            return Err(format!(
+                "Collection '{}' cannot be replicated. Must be one of: {}",
+                collection,
+                REPLICABLE_COLLECTIONS.join(", ")
+            ));
+        }
+        if data.collections[..i].contains(collection) {
+            return Err(format!("Collection '{}' is listed twice", collection));
+        }
+    }
+    if data.updated_by != context.caller.to_text() {
+        return Err("updatedBy must be the principal configuring replication".to_string());
+    }
+
+    Ok(())
+}
+
+/// Outbox entries and replication state are written by the satellite only, and outbox
+/// entries never change.
+pub fn validate_replication_outbox_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: The replication outbox is maintained by the satellite".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Replication outbox entries cannot be modified".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_replication_state_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Replication state is maintained by the satellite".to_string());
+    }
+    Ok(())
+}
+
+/// Outbox entries leave the outbox only once acknowledged, removed by the satellite.
+pub fn validate_replication_outbox_delete(caller: &Principal) -> Result<(), String> {
+    if !is_satellite_caller(caller) {
+        return Err("SECURITY: Replication outbox entries are removed by the satellite once acknowledged".to_string());
+    }
+    Ok(())
+}
+
+/// Record a write (`doc` present) or delete in the outbox when its collection is being
+/// replicated.
+pub fn record_replication_event(collection: &str, key: &str, doc: Option<&Doc>) -> Result<(), String> {
+    let replicated = active_settings()?.is_some_and(|settings| settings.collections.iter().any(|c| c == collection));
+    if !replicated {
+        return Ok(());
+    }
+
+    let data = match doc {
+        Some(doc) => Some(
+            decode_doc_data::<serde_json::Value>(&doc.data)
+                .map_err(|e| format!("Invalid {} data for '{}': {}", collection, key, e))?
+                .to_string(),
+        ),
+        None => None,
+    };
+    let sequence = next_number(REPLICATION_OUTBOX_COLLECTION)?;
+    let entry = OutboxEntryData {
+        sequence,
+        collection: collection.to_string(),
+        doc_key: key.to_string(),
+        action: if doc.is_some() { "set" } else { "delete" }.to_string(),
+        data,
+        recorded_at: ic_cdk::api::time(),
+    };
+    // Zero-padded so keys sort in sequence order
+    set_doc_data(
+        REPLICATION_OUTBOX_COLLECTION,
+        &format!("{:020}", sequence),
+        &entry,
+        Some(format!("collection={};", collection)),
+        None,
+    )?;
+    Ok(())
+}
+
+/// Push the oldest pending outbox entries to the companion canister and drop those it
+/// acknowledges. Runs on a timer; failures are kept in the replication state and retried
+/// on the next run.
+pub async fn push_replication_batch() -> Result<(), String> {
+    let Some(settings) = active_settings()? else {
+        return Ok(());
+    };
+    if PUSH_IN_FLIGHT.with(|f| f.replace(true)) {
+        return Ok(());
+    }
+    let result = push_batch(&settings).await;
+    PUSH_IN_FLIGHT.with(|f| f.set(false));
+
+    let (doc, mut state) = replication_state()?;
+    state.last_pushed_at = Some(ic_cdk::api::time());
+    match result {
+        Ok(Some(acknowledged)) => {
+            state.acknowledged_sequence = state.acknowledged_sequence.max(acknowledged);
+            state.last_error = None;
+        }
+        Ok(None) => state.last_error = None,
+        Err(ref e) => state.last_error = Some(e.clone()),
+    }
+    set_doc_data(
+        REPLICATION_STATE_COLLECTION,
+        STATE_KEY,
+        &state,
+        None,
+        doc.and_then(|d| d.version),
+    )?;
+
+    drop_acknowledged(state.acknowledged_sequence)?;
+    result.map(|_| ())
+}
+
+/// Push pending entries now, for administrators, and report where replication stands.
+pub async fn sync_replication() -> Result<ReplicationStatus, String> {
+    require_role(&caller(), Role::SuperAdmin)?;
+    if active_settings()?.is_none() {
+        return Err("Replication is not active".to_string());
+    }
+    // A failed push is reported in the status' last error
+    let _ = push_replication_batch().await;
+    get_replication_status()
+}
+
+/// Where replication stands: the last acknowledged sequence and what is still pending.
+pub fn get_replication_status() -> Result<ReplicationStatus, String> {
+    let settings = get_doc_data::<ReplicationSettingsData>(REPLICATION_SETTINGS_COLLECTION, SETTINGS_KEY)?;
+    let (_, state) = replication_state()?;
+    let pending_events = list_doc_data::<OutboxEntryData>(REPLICATION_OUTBOX_COLLECTION, None)?
+        .iter()
+        .filter(|(_, _, entry)| entry.sequence > state.acknowledged_sequence)
+        .count() as u32;
+
+    Ok(ReplicationStatus {
+        is_active: settings.as_ref().is_some_and(|(_, s)| s.is_active),
+        target_canister: settings.map(|(_, s)| s.target_canister),
+        acknowledged_sequence: state.acknowledged_sequence,
+        pending_events,
+        last_pushed_at: state.last_pushed_at,
+        last_error: state.last_error,
+    })
+}
+
+// Send the next batch; the companion's acknowledged sequence, or None when nothing was
+// pending
+async fn push_batch(settings: &ReplicationSettingsData) -> Result<Option<u64>, String> {
+    let (_, state) = replication_state()?;
+    let mut entries: Vec<OutboxEntryData> = list_doc_data::<OutboxEntryData>(REPLICATION_OUTBOX_COLLECTION, None)?
+        .into_iter()
+        .map(|(_, _, entry)| entry)
+        .filter(|entry| entry.sequence > state.acknowledged_sequence)
+        .collect();
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    entries.sort_by_key(|entry| entry.sequence);
+    entries.truncate(MAX_BATCH_EVENTS);
+
+    let target = Principal::from_text(&settings.target_canister)
+        .map_err(|_| format!("Invalid target canister '{}'", settings.target_canister))?;
+    let method = settings.ingest_method.as_deref().unwrap_or(DEFAULT_INGEST_METHOD);
+    let batch = ReplicationBatch {
+        source: id(),
+        events: entries
+            .into_iter()
+            .map(|entry| ReplicationEvent {
+                sequence: entry.sequence,
+                collection: entry.collection,
+                doc_key: entry.doc_key,
+                action: entry.action,
+                data: entry.data,
+                recorded_at: entry.recorded_at,
+            })
+            .collect(),
+    };
+
+    let response = Call::unbounded_wait(target, method)
+        .with_arg(batch)
+        .await
+        .map_err(|e| format!("Replication call to {} failed: {}", settings.target_canister, e))?;
+    let acknowledged = response
+        .candid::<Result<u64, String>>()
+        .map_err(|e| format!("Invalid replication response: {}", e))?
+        .map_err(|e| format!("Replication target rejected the batch: {}", e))?;
+    Ok(Some(acknowledged))
+}
+
+// Remove outbox entries the companion has stored
+fn drop_acknowledged(acknowledged: u64) -> Result<(), String> {
+    for (key, doc, entry) in list_doc_data::<OutboxEntryData>(REPLICATION_OUTBOX_COLLECTION, None)? {
+        if entry.sequence <= acknowledged {
+            delete_doc_data(REPLICATION_OUTBOX_COLLECTION, &key, doc.version)?;
+        }
+    }
+    Ok(())
+}
+
+fn active_settings() -> Result<Option<ReplicationSettingsData>, String> {
+    Ok(get_doc_data::<ReplicationSettingsData>(REPLICATION_SETTINGS_COLLECTION, SETTINGS_KEY)?
+        .map(|(_, settings)| settings)
+        .filter(|settings| settings.is_active))
+}
+
+fn replication_state() -> Result<(Option<Doc>, ReplicationStateData), String> {
+    Ok(match get_doc_data::<ReplicationStateData>(REPLICATION_STATE_COLLECTION, STATE_KEY)? {
+        Some((doc, state)) => (Some(doc), state),
+        None => (None, ReplicationStateData::default()),
+    })
+}
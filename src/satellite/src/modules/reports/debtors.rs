@@ -0,0 +1,108 @@
+//! Outstanding debtors.
+//!
+//! `list_debtors` groups the fee assignments still owing by student, optionally for one
+//! class, and pages through them largest balance first.
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::modules::fees::StudentFeeAssignmentData;
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct Debtor {
+    pub student_id: String,
+    pub student_name: String,
+    pub class_id: String,
+    pub assignments_owing: u32,
+    pub total_billed: f64,
+    pub amount_paid: f64,
+    pub balance: f64,
+    // Earliest due date among the assignments owing, when any has one
+    pub oldest_due_date: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct DebtorsPage {
+    pub debtors: Vec<Debtor>,
+    // Across all pages
+    pub total_debtors: u32,
+    pub total_balance: f64,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+struct Owing {
+    student_name: String,
+    class_id: String,
+    assignments_owing: u32,
+    total_billed: Money,
+    amount_paid: Money,
+    balance: Money,
+    oldest_due_date: Option<String>,
+}
+
+/// Students owing at least `min_balance` (naira), optionally in one class, largest balance
+/// first.
+pub fn list_debtors(class_id: Option<String>, min_balance: f64, page: Pagination) -> Result<DebtorsPage, String> {
+    page.validate()?;
+    if min_balance < 0.0 {
+        return Err("Minimum balance cannot be negative".to_string());
+    }
+    let min_balance = Money::from_naira(min_balance);
+
+    let mut by_student: HashMap<String, Owing> = HashMap::new();
+    for (_, _, assignment) in list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)? {
+        if !assignment.balance.is_positive() || class_id.as_ref().is_some_and(|c| c != &assignment.class_id) {
+            continue;
+        }
+        let owing = by_student.entry(assignment.student_id.clone()).or_insert_with(|| Owing {
+            student_name: assignment.student_name.clone(),
+            class_id: assignment.class_id.clone(),
+            assignments_owing: 0,
+            total_billed: Money::ZERO,
+            amount_paid: Money::ZERO,
+            balance: Money::ZERO,
+            oldest_due_date: None,
+        });
+        owing.assignments_owing += 1;
+        owing.total_billed += assignment.total_amount;
+        owing.amount_paid += assignment.amount_paid;
+        owing.balance += assignment.balance;
+        if let Some(due) = assignment.due_date {
+            if owing.oldest_due_date.as_ref().map(|d| &due < d).unwrap_or(true) {
+                owing.oldest_due_date = Some(due);
+            }
+        }
+    }
+
+    let mut debtors: Vec<(String, Owing)> = by_student.into_iter().filter(|(_, o)| o.balance >= min_balance).collect();
+    debtors.sort_by(|(a_id, a), (b_id, b)| {
+        b.balance.cmp(&a.balance).then(a.student_name.cmp(&b.student_name)).then(a_id.cmp(b_id))
+    });
+    let total_debtors = debtors.len() as u32;
+    let total_balance: Money = debtors.iter().map(|(_, o)| o.balance).sum();
+
+    Ok(DebtorsPage {
+        debtors: page
+            .slice(debtors)
+            .into_iter()
+            .map(|(student_id, o)| Debtor {
+                student_id,
+                student_name: o.student_name,
+                class_id: o.class_id,
+                assignments_owing: o.assignments_owing,
+                total_billed: o.total_billed.naira(),
+                amount_paid: o.amount_paid.naira(),
+                balance: o.balance.naira(),
+                oldest_due_date: o.oldest_due_date,
+            })
+            .collect(),
+        total_debtors,
+        total_balance: total_balance.naira(),
+        offset: page.offset,
+        limit: page.limit,
+    })
+}
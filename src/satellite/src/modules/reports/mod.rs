@@ -0,0 +1,253 @@
+//! Reports Module - Term Rollups and Year-over-Year Comparatives
+//!
+//! A daily job rolls confirmed collections, approved/paid expenses (by category) and paid
+//! payroll up per academic year and term into `report_rollups`. Rollups of the current
+//! academic year are refreshed on every run; once a year is over its rollups are archived
+//! and no longer recomputed, so board figures do not shift when old records are touched.
+//! Comparatives across years are read from these rollups only. The dashboard's live
+//! totals come from [`summary`], per-student fee statements from [`statement`], the
+//! debtors list from [`debtors`] and data completeness scores from [`quality`].
+
+pub mod debtors;
+pub mod quality;
+pub mod statement;
+pub mod summary;
+
+use candid::CandidType;
+use junobuild_satellite::AssertSetDocContext;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+use super::expenses::ExpenseData;
+use super::payments::PaymentData;
+use super::staff::SalaryPaymentData;
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::scan::{cursor_collection, scan_collection};
+use super::utils::validation_utils::*;
+
+pub const REPORT_ROLLUPS_COLLECTION: &str = "report_rollups";
+
+const TERMS: [&str; 3] = ["first", "second", "third"];
+const METRICS: [&str; 3] = ["collections", "expenses_by_category", "payroll_cost"];
+const MAX_COMPARATIVE_PERIODS: usize = 10;
+
+// Collections rolled up, scanned in this order
+const ROLLUP_SOURCES: [&str; 3] = ["payments", "expenses", "salary_payments"];
+
+#[derive(Deserialize, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TermRollupData {
+    pub academic_year: String,
+    pub term: String,
+    pub collections: Money,
+    pub expenses_by_category: BTreeMap<String, Money>,
+    pub payroll_cost: Money,
+    pub computed_at: u64,
+}
+
+// A rollup refresh in progress, kept between the messages of one daily run
+struct RollupRun {
+    current_year: String,
+    // Rollups stored when the run started, with their versions
+    existing: HashMap<String, Option<u64>>,
+    rollups: BTreeMap<String, TermRollupData>,
+}
+
+thread_local! {
+    static ROLLUP_RUN: RefCell<Option<RollupRun>> = const { RefCell::new(None) };
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ComparativeRow {
+    pub term: String,
+    pub category: Option<String>,
+    // One value per requested period, in the same order; None when no rollup exists
+    pub values: Vec<Option<f64>>,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ComparativeReport {
+    pub metric: String,
+    pub periods: Vec<String>,
+    pub rows: Vec<ComparativeRow>,
+}
+
+/// Rollups are written by the satellite's daily job only.
+pub fn validate_report_rollup_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Report rollups are maintained by the satellite".to_string());
+    }
+    Ok(())
+}
+
+/// Daily job: recompute the current academic year's term rollups and archive rollups for
+/// any earlier term that does not have one yet. Payments, expenses and salary payments
+/// are scanned in turn from `cursor`; the totals so far are held in memory between
+/// messages and written once the last collection has been scanned.
+pub fn refresh_term_rollups(cursor: Option<String>) -> Result<Option<String>, String> {
+    if cursor.is_none() {
+        let current_year =
+            academic_year_for_date(&current_date()).ok_or("Could not determine the current academic year")?;
+        let existing = list_doc_data::<TermRollupData>(REPORT_ROLLUPS_COLLECTION, None)?
+            .into_iter()
+            .map(|(key, doc, _)| (key, doc.version))
+            .collect();
+        ROLLUP_RUN.with(|run| {
+            *run.borrow_mut() = Some(RollupRun { current_year, existing, rollups: BTreeMap::new() });
+        });
+    }
+
+    let next_cursor = ROLLUP_RUN.with(|run| match run.borrow_mut().as_mut() {
+        Some(run) => scan_rollup_sources(run, cursor),
+        // The totals were lost with an upgrade; the next daily run starts over
+        None => Err("The report rollup run was interrupted; it restarts with the next daily run".to_string()),
+    })?;
+    if next_cursor.is_some() {
+        return Ok(next_cursor);
+    }
+
+    let run = ROLLUP_RUN.with(|run| run.borrow_mut().take()).ok_or("The report rollup run was interrupted")?;
+    let now = ic_cdk::api::time();
+    for (key, mut rollup) in run.rollups.into_iter() {
+        rollup.computed_at = now;
+        let version = run.existing.get(&key).copied().flatten();
+        set_doc_data(REPORT_ROLLUPS_COLLECTION, &key, &rollup, None, version)?;
+    }
+
+    Ok(None)
+}
+
+// Add the source collections to the run's totals from `cursor` on; the cursor to resume
+// from if the instruction budget ran out first
+fn scan_rollup_sources(run: &mut RollupRun, cursor: Option<String>) -> Result<Option<String>, String> {
+    let start = cursor
+        .as_deref()
+        .and_then(cursor_collection)
+        .and_then(|collection| ROLLUP_SOURCES.iter().position(|source| *source == collection))
+        .unwrap_or(0);
+    let mut cursor = cursor;
+
+    if start == 0 {
+        let progress = scan_collection::<PaymentData>(ROLLUP_SOURCES[0], cursor.take(), |_, _, payment| {
+            if payment.status == "confirmed" {
+                if let Some(rollup) = run.rollup_for(&payment.payment_date) {
+                    rollup.collections += payment.amount;
+                }
+            }
+            Ok(())
+        })?;
+        if progress.next_cursor.is_some() {
+            return Ok(progress.next_cursor);
+        }
+    }
+
+    if start <= 1 {
+        let progress = scan_collection::<ExpenseData>(ROLLUP_SOURCES[1], cursor.take(), |_, _, expense| {
+            if expense.status == "approved" || expense.status == "paid" {
+                if let Some(rollup) = run.rollup_for(&expense.payment_date) {
+                    *rollup.expenses_by_category.entry(expense.category_name.clone()).or_default() += expense.amount;
+                }
+            }
+            Ok(())
+        })?;
+        if progress.next_cursor.is_some() {
+            return Ok(progress.next_cursor);
+        }
+    }
+
+    let progress = scan_collection::<SalaryPaymentData>(ROLLUP_SOURCES[2], cursor.take(), |_, _, salary| {
+        if salary.status == "paid" {
+            if let Some(rollup) = run.rollup_for(&salary.payment_date) {
+                // Payroll cost to the school is gross pay: basic salary plus allowances
+                rollup.payroll_cost += salary.basic_salary + salary.allowances.iter().map(|a| a.amount).sum();
+            }
+        }
+        Ok(())
+    })?;
+    Ok(progress.next_cursor)
+}
+
+/// The same metric across several academic years, aligned by term. `expenses_by_category`
+/// returns one row per term and category.
+pub fn get_comparative_report(metric: String, periods: Vec<String>) -> Result<ComparativeReport, String> {
+    if !METRICS.contains(&metric.as_str()) {
+        return Err(format!("Invalid metric '{}'. Must be one of: {}", metric, METRICS.join(", ")));
+    }
+    if periods.is_empty() || periods.len() > MAX_COMPARATIVE_PERIODS {
+        return Err(format!("Between 1 and {} academic years can be compared", MAX_COMPARATIVE_PERIODS));
+    }
+    if let Some(invalid) = periods.iter().find(|p| !is_valid_academic_year(p)) {
+        return Err(format!("Invalid academic year '{}'. Must be in format YYYY/YYYY", invalid));
+    }
+
+    let rollups: HashMap<String, TermRollupData> = list_doc_data::<TermRollupData>(REPORT_ROLLUPS_COLLECTION, None)?
+        .into_iter()
+        .map(|(key, _, rollup)| (key, rollup))
+        .collect();
+    let rollup = |year: &str, term: &str| rollups.get(&rollup_key(year, term));
+
+    let mut rows = Vec::new();
+    for term in TERMS {
+        if metric == "expenses_by_category" {
+            let mut categories: Vec<&String> = periods
+                .iter()
+                .filter_map(|year| rollup(year, term))
+                .flat_map(|r| r.expenses_by_category.keys())
+                .collect();
+            categories.sort();
+            categories.dedup();
+
+            for category in categories {
+                rows.push(ComparativeRow {
+                    term: term.to_string(),
+                    category: Some(category.clone()),
+                    values: periods
+                        .iter()
+                        .map(|year| {
+                            rollup(year, term)
+                                .map(|r| r.expenses_by_category.get(category).copied().unwrap_or_default().naira())
+                        })
+                        .collect(),
+                });
+            }
+        } else {
+            rows.push(ComparativeRow {
+                term: term.to_string(),
+                category: None,
+                values: periods
+                    .iter()
+                    .map(|year| {
+                        rollup(year, term).map(|r| {
+                            if metric == "collections" { r.collections.naira() } else { r.payroll_cost.naira() }
+                        })
+                    })
+                    .collect(),
+            });
+        }
+    }
+
+    Ok(ComparativeReport { metric, periods, rows })
+}
+
+impl RollupRun {
+    /// Rollup being rebuilt for the term a date falls in; `None` for archived terms.
+    fn rollup_for(&mut self, date: &str) -> Option<&mut TermRollupData> {
+        let academic_year = academic_year_for_date(date)?;
+        let term = term_for_date(date)?;
+        let key = rollup_key(&academic_year, term);
+        if academic_year != self.current_year && self.existing.contains_key(&key) {
+            return None;
+        }
+        Some(self.rollups.entry(key).or_insert_with(|| TermRollupData {
+            academic_year,
+            term: term.to_string(),
+            ..Default::default()
+        }))
+    }
+}
+
+fn rollup_key(academic_year: &str, term: &str) -> String {
+    format!("{}-{}", academic_year.replace('/', "-"), term)
+}
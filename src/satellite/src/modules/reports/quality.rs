@@ -0,0 +1,148 @@
+//! Data quality scores.
+//!
+//! `get_data_quality` scores how completely a collection fills the optional fields that
+//! other features rely on: guardian contacts for reminders and broadcasts, staff bank
+//! details for payroll files, and vendor details (TIN included) for expense payments and
+//! tax returns. Each document scores the share of its tracked fields that are filled; the
+//! collection scores the average, and the least complete documents are listed first so
+//! the office can clean them up.
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::modules::utils::doc_utils::*;
+
+// Worst offenders listed in a report
+const MAX_OFFENDERS: usize = 20;
+
+struct QualityRule {
+    collection: &'static str,
+    // Tracked fields, camelCase as stored
+    fields: &'static [&'static str],
+    // Only documents with this field filled are scored
+    applies_when: Option<&'static str>,
+}
+
+const QUALITY_RULES: [QualityRule; 4] = [
+    QualityRule {
+        collection: "students",
+        fields: &["guardianPhone", "guardianEmail", "guardianAddress"],
+        applies_when: None,
+    },
+    QualityRule {
+        collection: "guardians",
+        fields: &["phone", "email"],
+        applies_when: None,
+    },
+    QualityRule {
+        collection: "staff",
+        fields: &["bankName", "accountNumber", "phone", "email"],
+        applies_when: None,
+    },
+    QualityRule {
+        collection: "expenses",
+        fields: &["vendorContact", "vendorTin", "vendorBankName", "vendorAccountNumber"],
+        applies_when: Some("vendorName"),
+    },
+];
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct FieldCompleteness {
+    pub field: String,
+    pub filled: u32,
+    pub missing: u32,
+    // Percentage of scored documents with the field filled
+    pub completeness: f64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct DataQualityOffender {
+    pub doc_key: String,
+    pub score: f64,
+    pub missing_fields: Vec<String>,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct DataQualityReport {
+    pub collection: String,
+    pub documents_scored: u32,
+    // Average document score, 0-100
+    pub score: f64,
+    pub fields: Vec<FieldCompleteness>,
+    // Least complete documents first
+    pub worst_offenders: Vec<DataQualityOffender>,
+}
+
+/// Completeness of the tracked fields of one collection.
+pub fn get_data_quality(collection: String) -> Result<DataQualityReport, String> {
+    let rule = QUALITY_RULES.iter().find(|r| r.collection == collection).ok_or_else(|| {
+        format!(
+            "Data quality is not tracked for '{}'. Must be one of: {}",
+            collection,
+            QUALITY_RULES.iter().map(|r| r.collection).collect::<Vec<_>>().join(", ")
+        )
+    })?;
+
+    let mut filled_counts = vec![0u32; rule.fields.len()];
+    let mut offenders = Vec::new();
+    let mut documents_scored = 0u32;
+    let mut score_total = 0.0;
+    for (key, _, data) in list_doc_data::<serde_json::Value>(rule.collection, None)? {
+        if rule.applies_when.is_some_and(|field| !is_filled(&data, field)) {
+            continue;
+        }
+        let mut missing_fields = Vec::new();
+        for (i, field) in rule.fields.iter().enumerate() {
+            if is_filled(&data, field) {
+                filled_counts[i] += 1;
+            } else {
+                missing_fields.push(field.to_string());
+            }
+        }
+        let score = percentage(rule.fields.len() - missing_fields.len(), rule.fields.len());
+        documents_scored += 1;
+        score_total += score;
+        if !missing_fields.is_empty() {
+            offenders.push(DataQualityOffender { doc_key: key, score, missing_fields });
+        }
+    }
+    offenders.sort_by(|a, b| a.score.total_cmp(&b.score).then(a.doc_key.cmp(&b.doc_key)));
+    offenders.truncate(MAX_OFFENDERS);
+
+    let fields = rule
+        .fields
+        .iter()
+        .zip(filled_counts)
+        .map(|(field, filled)| FieldCompleteness {
+            field: field.to_string(),
+            filled,
+            missing: documents_scored - filled,
+            completeness: percentage(filled as usize, documents_scored as usize),
+        })
+        .collect();
+
+    Ok(DataQualityReport {
+        collection,
+        documents_scored,
+        score: if documents_scored == 0 { 100.0 } else { (score_total * 10.0 / documents_scored as f64).round() / 10.0 },
+        fields,
+        worst_offenders: offenders,
+    })
+}
+
+// A field is filled when present with a value other than null or a blank string
+fn is_filled(data: &serde_json::Value, field: &str) -> bool {
+    match data.get(field) {
+        None | Some(serde_json::Value::Null) => false,
+        Some(serde_json::Value::String(s)) => !s.trim().is_empty(),
+        Some(_) => true,
+    }
+}
+
+// Share as a percentage rounded to one decimal; an empty set counts as complete
+fn percentage(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 100.0;
+    }
+    (part as f64 * 1000.0 / whole as f64).round() / 10.0
+}
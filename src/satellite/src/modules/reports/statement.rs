@@ -0,0 +1,212 @@
+//! Student fee statement.
+//!
+//! `get_student_statement` returns a student's account as one ledger, oldest first, with a
+//! running balance:
+//! - Fees billed per assignment (debit), dated when the assignment was created
+//! - Posted charges such as ID card reprints (debit)
+//! - Scholarship discounts (credit)
+//! - Confirmed payments (credit)
+//! - Amounts paid through a sponsor or family invoice (credit, one line per assignment)
+//!
+//! The closing balance equals the sum of the student's fee assignment balances.
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::modules::charges::StudentChargeData;
+use crate::modules::fees::StudentFeeAssignmentData;
+use crate::modules::payments::{allocation::allocations_by_assignment, PaymentData};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::utils::validation_utils::*;
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct StudentStatementEntry {
+    pub date: String,
+    // fee, charge, scholarship, payment or account_payment
+    pub entry_type: String,
+    pub description: String,
+    pub reference: Option<String>,
+    pub academic_year: String,
+    pub term: String,
+    pub debit: f64,
+    pub credit: f64,
+    pub balance: f64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct StudentStatement {
+    pub student_id: String,
+    pub student_name: String,
+    pub entries: Vec<StudentStatementEntry>,
+    pub total_billed: f64,
+    pub total_discounts: f64,
+    pub total_paid: f64,
+    pub closing_balance: f64,
+}
+
+struct Line {
+    date: String,
+    // Same-day ordering: bills before the credits against them
+    rank: u8,
+    entry_type: &'static str,
+    description: String,
+    reference: Option<String>,
+    academic_year: String,
+    term: String,
+    debit: Money,
+    credit: Money,
+}
+
+/// A student's fee assignments, charges, scholarships and payments as one ledger with a
+/// running balance.
+pub fn get_student_statement(student_id: String) -> Result<StudentStatement, String> {
+    if !doc_exists("students", &student_id)? {
+        return Err(format!("Student '{}' not found", student_id));
+    }
+
+    let assignments: Vec<(String, u64, StudentFeeAssignmentData)> =
+        list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)?
+            .into_iter()
+            .filter(|(_, _, a)| a.student_id == student_id)
+            .map(|(key, doc, a)| (key, doc.created_at, a))
+            .collect();
+    let charges: Vec<(String, StudentChargeData)> = list_doc_data::<StudentChargeData>("student_charges", None)?
+        .into_iter()
+        .filter(|(_, _, c)| c.student_id == student_id && c.status == "posted")
+        .map(|(key, _, c)| (key, c))
+        .collect();
+    let payments: Vec<(String, PaymentData)> = list_doc_data::<PaymentData>("payments", None)?
+        .into_iter()
+        .filter(|(_, _, p)| p.student_id == student_id && p.status == "confirmed")
+        .map(|(key, _, p)| (key, p))
+        .collect();
+
+    let mut lines: Vec<Line> = Vec::new();
+    for (assignment_id, created_at, assignment) in assignments.iter() {
+        let billed_on = date_from_timestamp(*created_at);
+        let discount = assignment.discount_amount.unwrap_or(Money::ZERO);
+        let assignment_charges: Vec<&(String, StudentChargeData)> =
+            charges.iter().filter(|(_, c)| &c.fee_assignment_id == assignment_id).collect();
+        let charged: Money = assignment_charges.iter().map(|(_, c)| c.amount).sum();
+
+        lines.push(Line {
+            date: billed_on.clone(),
+            rank: 0,
+            entry_type: "fee",
+            description: format!("Fees for {} term {}", assignment.term, assignment.academic_year),
+            reference: Some(assignment_id.clone()),
+            academic_year: assignment.academic_year.clone(),
+            term: assignment.term.clone(),
+            debit: assignment.total_amount + discount - charged,
+            credit: Money::ZERO,
+        });
+        for (charge_id, charge) in assignment_charges {
+            lines.push(Line {
+                date: charge.charge_date.clone(),
+                rank: 1,
+                entry_type: "charge",
+                description: charge.description.clone(),
+                reference: Some(charge_id.clone()),
+                academic_year: assignment.academic_year.clone(),
+                term: assignment.term.clone(),
+                debit: charge.amount,
+                credit: Money::ZERO,
+            });
+        }
+        if discount.is_positive() {
+            lines.push(Line {
+                date: billed_on.clone(),
+                rank: 2,
+                entry_type: "scholarship",
+                description: assignment.scholarship_name.clone().unwrap_or_else(|| "Scholarship".to_string()),
+                reference: assignment.scholarship_id.clone(),
+                academic_year: assignment.academic_year.clone(),
+                term: assignment.term.clone(),
+                debit: Money::ZERO,
+                credit: discount,
+            });
+        }
+
+        // Whatever was applied to the assignment without a payment of the student's own
+        // came through a sponsor or family invoice
+        let paid_directly: Money = payments
+            .iter()
+            .flat_map(|(_, p)| allocations_by_assignment(p))
+            .filter(|(id, _, _)| id == assignment_id)
+            .map(|(_, amount, _)| amount)
+            .sum();
+        let paid_by_account = assignment.amount_paid - paid_directly;
+        if paid_by_account.is_positive() {
+            lines.push(Line {
+                date: billed_on,
+                rank: 3,
+                entry_type: "account_payment",
+                description: "Paid through a sponsor or family invoice".to_string(),
+                reference: None,
+                academic_year: assignment.academic_year.clone(),
+                term: assignment.term.clone(),
+                debit: Money::ZERO,
+                credit: paid_by_account,
+            });
+        }
+    }
+    for (payment_id, payment) in payments.iter() {
+        let period = assignments.iter().find(|(key, _, _)| key == &payment.fee_assignment_id);
+        lines.push(Line {
+            date: payment.payment_date.clone(),
+            rank: 3,
+            entry_type: "payment",
+            description: format!("Payment by {}", payment.payment_method.replace('_', " ")),
+            reference: Some(payment.reference.clone()).filter(|r| !r.is_empty()).or(Some(payment_id.clone())),
+            academic_year: period.map(|(_, _, a)| a.academic_year.clone()).unwrap_or_default(),
+            term: period.map(|(_, _, a)| a.term.clone()).unwrap_or_default(),
+            debit: Money::ZERO,
+            credit: payment.amount,
+        });
+    }
+    lines.sort_by(|a, b| a.date.cmp(&b.date).then(a.rank.cmp(&b.rank)));
+
+    let mut balance = Money::ZERO;
+    let (mut total_billed, mut total_discounts, mut total_paid) = (Money::ZERO, Money::ZERO, Money::ZERO);
+    let entries = lines
+        .into_iter()
+        .map(|line| {
+            balance += line.debit;
+            balance -= line.credit;
+            total_billed += line.debit;
+            if line.entry_type == "scholarship" {
+                total_discounts += line.credit;
+            } else {
+                total_paid += line.credit;
+            }
+            StudentStatementEntry {
+                date: line.date,
+                entry_type: line.entry_type.to_string(),
+                description: line.description,
+                reference: line.reference,
+                academic_year: line.academic_year,
+                term: line.term,
+                debit: line.debit.naira(),
+                credit: line.credit.naira(),
+                balance: balance.naira(),
+            }
+        })
+        .collect();
+
+    let student_name = assignments
+        .first()
+        .map(|(_, _, a)| a.student_name.clone())
+        .or_else(|| payments.first().map(|(_, p)| p.student_name.clone()))
+        .unwrap_or_default();
+
+    Ok(StudentStatement {
+        student_id,
+        student_name,
+        entries,
+        total_billed: total_billed.naira(),
+        total_discounts: total_discounts.naira(),
+        total_paid: total_paid.naira(),
+        closing_balance: balance.naira(),
+    })
+}
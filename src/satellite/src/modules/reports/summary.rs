@@ -0,0 +1,109 @@
+//! Dashboard financial summary.
+//!
+//! `get_financial_summary` builds the dashboard's headline figures in one call instead of
+//! the frontend paging through every collection:
+//! - Confirmed payments, paid expenses and paid salaries (net pay) dated in the range
+//! - Outstanding fee balances and bank balances as they stand now
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::modules::banking::BankAccountData;
+use crate::modules::expenses::ExpenseData;
+use crate::modules::fees::StudentFeeAssignmentData;
+use crate::modules::payments::PaymentData;
+use crate::modules::staff::SalaryPaymentData;
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::utils::validation_utils::*;
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct BankBalance {
+    pub account_id: String,
+    pub bank_name: String,
+    pub account_number: String,
+    pub balance: f64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct FinancialSummary {
+    pub from: String,
+    pub to: String,
+    pub confirmed_payments: f64,
+    pub payment_count: u32,
+    pub paid_expenses: f64,
+    pub expense_count: u32,
+    pub paid_salaries: f64,
+    pub salary_count: u32,
+    // Collections less expenses and salaries paid in the range
+    pub net_cash_flow: f64,
+    pub outstanding_fee_balances: f64,
+    pub students_owing: u32,
+    pub bank_balance_total: f64,
+    pub bank_balances: Vec<BankBalance>,
+}
+
+/// Headline totals for the dashboard between two dates (inclusive).
+pub fn get_financial_summary(from: String, to: String) -> Result<FinancialSummary, String> {
+    if !is_valid_date_format(&from) || !is_valid_date_format(&to) {
+        return Err("From and to dates must be in format YYYY-MM-DD".to_string());
+    }
+    if to < from {
+        return Err("The to date cannot be before the from date".to_string());
+    }
+    let in_range = |date: &str| date >= from.as_str() && date <= to.as_str();
+
+    let (confirmed_payments, payment_count) = list_doc_data::<PaymentData>("payments", None)?
+        .iter()
+        .filter(|(_, _, p)| p.status == "confirmed" && in_range(&p.payment_date))
+        .fold((Money::ZERO, 0u32), |(total, count), (_, _, p)| (total + p.amount, count + 1));
+
+    let (paid_expenses, expense_count) = list_doc_data::<ExpenseData>("expenses", None)?
+        .iter()
+        .filter(|(_, _, e)| e.status == "paid" && in_range(&e.payment_date))
+        .fold((Money::ZERO, 0u32), |(total, count), (_, _, e)| (total + e.amount, count + 1));
+
+    let (paid_salaries, salary_count) = list_doc_data::<SalaryPaymentData>("salary_payments", None)?
+        .iter()
+        .filter(|(_, _, s)| s.status == "paid" && in_range(&s.payment_date))
+        .fold((Money::ZERO, 0u32), |(total, count), (_, _, s)| (total + s.net_salary, count + 1));
+
+    let owing: Vec<StudentFeeAssignmentData> =
+        list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)?
+            .into_iter()
+            .filter(|(_, _, a)| a.balance.is_positive())
+            .map(|(_, _, a)| a)
+            .collect();
+    let outstanding_fee_balances: Money = owing.iter().map(|a| a.balance).sum();
+    let students_owing = owing.iter().map(|a| a.student_id.as_str()).collect::<HashSet<_>>().len() as u32;
+
+    let accounts = list_doc_data::<BankAccountData>("bank_accounts", None)?;
+    let bank_balance_total: Money = accounts.iter().map(|(_, _, account)| account.balance).sum();
+    let mut bank_balances: Vec<BankBalance> = accounts
+        .into_iter()
+        .map(|(account_id, _, account)| BankBalance {
+            account_id,
+            bank_name: account.bank_name,
+            account_number: account.account_number,
+            balance: account.balance.naira(),
+        })
+        .collect();
+    bank_balances.sort_by(|a, b| a.bank_name.cmp(&b.bank_name).then(a.account_number.cmp(&b.account_number)));
+
+    Ok(FinancialSummary {
+        from,
+        to,
+        confirmed_payments: confirmed_payments.naira(),
+        payment_count,
+        paid_expenses: paid_expenses.naira(),
+        expense_count,
+        paid_salaries: paid_salaries.naira(),
+        salary_count,
+        net_cash_flow: (confirmed_payments - paid_expenses - paid_salaries).naira(),
+        outstanding_fee_balances: outstanding_fee_balances.naira(),
+        students_owing,
+        bank_balance_total: bank_balance_total.naira(),
+        bank_balances,
+    })
+}
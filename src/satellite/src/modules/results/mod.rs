@@ -0,0 +1,167 @@
+//! Results Module - Exam Entry and Result Release
+//!
+//! Schools bar students from exams and withhold results until enough of the term's fees
+//! are paid. `check_exam_entry` and `check_result_release` tell the exam office and the
+//! results portal whether a student is cleared for a term: the share of the term's fees
+//! paid must reach the threshold of the [`policies`] clearance policy covering the
+//! student's class, or, without one, the school-wide threshold (80% unless configured in
+//! `result_release_settings`, document `default`). Students exempted by the policy are
+//! always cleared. When a student is not cleared, the blocking balance is what they must
+//! still pay to reach the threshold.
+
+pub mod policies;
+
+use candid::CandidType;
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::fees::StudentFeeAssignmentData;
+use super::roles::{caller_has_any_role, Role};
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use policies::policy_for;
+
+pub const RESULT_RELEASE_SETTINGS_COLLECTION: &str = "result_release_settings";
+
+const SETTINGS_KEY: &str = "default";
+const DEFAULT_MIN_PAID_PERCENT: f64 = 80.0;
+const TERMS: [&str; 3] = ["first", "second", "third"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultReleaseSettingsData {
+    pub min_paid_percent: f64,
+    pub updated_by: String,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct ClearanceStatus {
+    pub student_id: String,
+    pub academic_year: Option<String>,
+    pub term: String,
+    pub total_billed: f64,
+    pub amount_paid: f64,
+    pub paid_percent: f64,
+    pub threshold_percent: f64,
+    pub cleared: bool,
+    // Still to pay before the student is cleared (0 when cleared)
+    pub blocking_balance: f64,
+    pub outstanding_balance: f64,
+    pub policy_id: Option<String>,
+    pub exemption: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Clearance {
+    ExamEntry,
+    ResultRelease,
+}
+
+/// Result Release Settings Validation
+///
+/// Checks:
+/// - Only administrators change the clearance threshold
+/// - Settings live in the `default` document
+/// - The threshold is a percentage between 0 and 100
+pub fn validate_result_release_settings_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only administrators can change the result release threshold".to_string());
+    }
+    if context.data.key != SETTINGS_KEY {
+        return Err(format!("Result release settings must use the key '{}'", SETTINGS_KEY));
+    }
+
+    let data: ResultReleaseSettingsData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid result release settings data format: {}", e))?;
+
+    if !(0.0..=100.0).contains(&data.min_paid_percent) {
+        return Err("Minimum paid percentage must be between 0 and 100".to_string());
+    }
+    if data.updated_by != context.caller.to_text() {
+        return Err("updatedBy must be the principal changing the settings".to_string());
+    }
+
+    Ok(())
+}
+
+/// Whether a student may sit the exams of a term (first, second or third, in the latest
+/// academic year the student was billed for it).
+pub fn check_exam_entry(student_id: String, term: String) -> Result<ClearanceStatus, String> {
+    clearance_status(student_id, term, Clearance::ExamEntry)
+}
+
+/// Whether a student's results for a term (first, second or third, in the latest
+/// academic year the student was billed for it) can be released.
+pub fn check_result_release(student_id: String, term: String) -> Result<ClearanceStatus, String> {
+    clearance_status(student_id, term, Clearance::ResultRelease)
+}
+
+fn clearance_status(student_id: String, term: String, clearance: Clearance) -> Result<ClearanceStatus, String> {
+    if !TERMS.contains(&term.as_str()) {
+        return Err(format!("Invalid term '{}'. Must be one of: {}", term, TERMS.join(", ")));
+    }
+    if !doc_exists("students", &student_id)? {
+        return Err(format!("Student '{}' not found", student_id));
+    }
+
+    let assignments: Vec<StudentFeeAssignmentData> =
+        list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)?
+            .into_iter()
+            .filter(|(_, _, a)| a.student_id == student_id && a.term == term)
+            .map(|(_, _, a)| a)
+            .collect();
+    let academic_year = assignments.iter().map(|a| a.academic_year.clone()).max();
+    let for_year: Vec<&StudentFeeAssignmentData> =
+        assignments.iter().filter(|a| Some(&a.academic_year) == academic_year.as_ref()).collect();
+
+    let (total_billed, amount_paid) = for_year.iter().fold((Money::ZERO, Money::ZERO), |(billed, paid), a| {
+        (billed + a.total_amount, paid + a.amount_paid)
+    });
+
+    let class_id = for_year.first().map(|a| a.class_id.as_str());
+    let policy = policy_for(class_id, academic_year.as_deref(), &term)?;
+    let threshold_percent = match policy {
+        Some((_, ref p)) if clearance == Clearance::ExamEntry => p.exam_entry_percent,
+        Some((_, ref p)) => p.result_release_percent,
+        None => get_doc_data::<ResultReleaseSettingsData>(RESULT_RELEASE_SETTINGS_COLLECTION, SETTINGS_KEY)?
+            .map(|(_, settings)| settings.min_paid_percent)
+            .unwrap_or(DEFAULT_MIN_PAID_PERCENT),
+    };
+    let exemption = match policy {
+        Some((_, ref p)) if p.exempt_student_ids.contains(&student_id) => Some("Exempted by policy".to_string()),
+        Some((_, ref p)) if p.scholarship_exempt && for_year.iter().any(|a| a.scholarship_id.is_some()) => {
+            Some("Scholarship student".to_string())
+        }
+        _ => None,
+    };
+
+    // Nothing billed for the term: nothing to withhold clearance for
+    let paid_percent = if total_billed.is_positive() {
+        amount_paid.naira() / total_billed.naira() * 100.0
+    } else {
+        100.0
+    };
+    let required = total_billed.percent(threshold_percent);
+    let blocking_balance = if exemption.is_some() || amount_paid >= required {
+        Money::ZERO
+    } else {
+        required - amount_paid
+    };
+
+    Ok(ClearanceStatus {
+        student_id,
+        academic_year,
+        term,
+        total_billed: total_billed.naira(),
+        amount_paid: amount_paid.naira(),
+        paid_percent,
+        threshold_percent,
+        cleared: blocking_balance.is_zero(),
+        blocking_balance: blocking_balance.naira(),
+        outstanding_balance: (total_billed - amount_paid).naira(),
+        policy_id: policy.map(|(key, _)| key),
+        exemption,
+    })
+}
@@ -0,0 +1,187 @@
+//! Fee clearance policies.
+//!
+//! A policy in `clearance_policies` sets the share of a term's fees a student must have
+//! paid before sitting exams and before results are released. A policy can apply to the
+//! whole school or be narrowed to a class, an academic year and/or a term; the most
+//! specific active policy wins. Scholarship students can be cleared automatically, and
+//! individual students exempted by name.
+//!
+//! Policies are edited by administrators and never deleted (deactivate instead). Every
+//! saved version is copied to `clearance_policy_history`.
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::cache::cached_doc_exists;
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::validation_utils::*;
+
+pub const CLEARANCE_POLICIES_COLLECTION: &str = "clearance_policies";
+pub const CLEARANCE_POLICY_HISTORY_COLLECTION: &str = "clearance_policy_history";
+
+const TERMS: [&str; 3] = ["first", "second", "third"];
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearancePolicyData {
+    pub name: String,
+    pub class_id: Option<String>,
+    pub academic_year: Option<String>,
+    pub term: Option<String>,
+    pub exam_entry_percent: f64,
+    pub result_release_percent: f64,
+    pub scholarship_exempt: bool,
+    #[serde(default)]
+    pub exempt_student_ids: Vec<String>,
+    pub is_active: bool,
+    pub updated_by: String,
+    pub updated_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearancePolicyHistoryData {
+    pub policy_id: String,
+    pub action: String,
+    pub policy: ClearancePolicyData,
+    pub changed_by: String,
+    pub changed_at: u64,
+}
+
+/// Clearance Policy Validation
+///
+/// Checks:
+/// - Only administrators edit policies
+/// - Name is set; class exists, academic year and term are valid when given
+/// - Thresholds are percentages (0-100)
+/// - No other active policy has the same scope
+/// - Exempted students exist
+pub fn validate_clearance_policy_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only administrators can edit clearance policies".to_string());
+    }
+
+    let data: ClearancePolicyData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid clearance policy data format: {}", e))?;
+
+    if data.name.trim().len() < 3 {
+        return Err("Policy name must be at least 3 characters".to_string());
+    }
+    if let Some(ref class_id) = data.class_id {
+        if !cached_doc_exists("classes", class_id)? {
+            return Err(format!("Class '{}' not found", class_id));
+        }
+    }
+    if let Some(ref year) = data.academic_year {
+        if !is_valid_academic_year(year) {
+            return Err("Invalid academic year. Must be in format YYYY/YYYY".to_string());
+        }
+    }
+    if let Some(ref term) = data.term {
+        if !TERMS.contains(&term.as_str()) {
+            return Err(format!("Invalid term '{}'. Must be one of: {}", term, TERMS.join(", ")));
+        }
+    }
+    for (name, percent) in [("Exam entry", data.exam_entry_percent), ("Result release", data.result_release_percent)] {
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(format!("{} threshold must be between 0 and 100", name));
+        }
+    }
+    for student_id in data.exempt_student_ids.iter() {
+        if !doc_exists("students", student_id)? {
+            return Err(format!("Exempted student '{}' not found", student_id));
+        }
+    }
+    if data.updated_by != context.caller.to_text() {
+        return Err("updatedBy must be the principal editing the policy".to_string());
+    }
+
+    if data.is_active {
+        let same_scope = list_doc_data::<ClearancePolicyData>(CLEARANCE_POLICIES_COLLECTION, None)?
+            .into_iter()
+            .find(|(key, _, other)| {
+                key != &context.data.key
+                    && other.is_active
+                    && other.class_id == data.class_id
+                    && other.academic_year == data.academic_year
+                    && other.term == data.term
+            });
+        if let Some((_, _, other)) = same_scope {
+            return Err(format!("Active policy '{}' already covers the same class and term", other.name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Policies keep their history; deactivate a policy instead of deleting it.
+pub fn validate_clearance_policy_delete() -> Result<(), String> {
+    Err("AUDIT: Clearance policies cannot be deleted; deactivate the policy instead".to_string())
+}
+
+/// History entries are written by the satellite only and never changed.
+pub fn validate_clearance_policy_history_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Clearance policy history is recorded by the satellite".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Clearance policy history cannot be modified".to_string());
+    }
+    Ok(())
+}
+
+/// Called from the `clearance_policies` on-set hook: records the saved version.
+pub fn record_clearance_policy_change(
+    policy_id: &str,
+    created: bool,
+    policy: &ClearancePolicyData,
+    caller: &str,
+) -> Result<(), String> {
+    let now = ic_cdk::api::time();
+    let entry = ClearancePolicyHistoryData {
+        policy_id: policy_id.to_string(),
+        action: if created { "create" } else { "update" }.to_string(),
+        policy: policy.clone(),
+        changed_by: caller.to_string(),
+        changed_at: now,
+    };
+    set_doc_data(
+        CLEARANCE_POLICY_HISTORY_COLLECTION,
+        &format!("{}_{}", policy_id, now),
+        &entry,
+        Some(format!("policy_id={};", policy_id)),
+        None,
+    )?;
+    Ok(())
+}
+
+/// The most specific active policy covering a class in a term, with its key.
+pub fn policy_for(
+    class_id: Option<&str>,
+    academic_year: Option<&str>,
+    term: &str,
+) -> Result<Option<(String, ClearancePolicyData)>, String> {
+    let applies = |scope: &Option<String>, value: Option<&str>| match scope {
+        Some(scope) => Some(scope.as_str()) == value,
+        None => true,
+    };
+
+    Ok(list_doc_data::<ClearancePolicyData>(CLEARANCE_POLICIES_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, p)| {
+            p.is_active
+                && applies(&p.class_id, class_id)
+                && applies(&p.academic_year, academic_year)
+                && applies(&p.term, Some(term))
+        })
+        .max_by_key(|(_, _, p)| {
+            let specificity = [p.class_id.is_some(), p.academic_year.is_some(), p.term.is_some()]
+                .iter()
+                .filter(|s| **s)
+                .count();
+            (specificity, p.updated_at)
+        })
+        .map(|(key, _, p)| (key, p)))
+}
@@ -0,0 +1,65 @@
+//! Per-role transaction amount limits.
+//!
+//! Each role may record payments and expenses up to its limit; bursars and
+//! administrators are unlimited. Callers without an application role are held to the
+//! clerk (`data_entry`) limit. A violation is rejected with the `ROLE_LIMIT` error code;
+//! the frontend reports it through `report_rejected_write` so it reaches the audit trail.
+
+use junobuild_satellite::AssertSetDocContext;
+
+use super::{get_caller_role, Role};
+use crate::modules::audit::record_rejected_write;
+use crate::modules::utils::doc_utils::is_satellite_caller;
+use crate::modules::utils::money::Money;
+
+pub const ROLE_LIMIT_ERROR: &str = "ROLE_LIMIT";
+
+/// Collections whose amounts are held to the role limits.
+pub const ROLE_LIMITED_COLLECTIONS: [&str; 2] = ["expenses", "payments"];
+
+/// Largest single payment or expense the role may record; `None` is unlimited.
+pub fn role_write_limit(role: Option<Role>) -> Option<Money> {
+    match role {
+        Some(Role::SuperAdmin) | Some(Role::Bursar) => None,
+        Some(Role::Accountant) => Some(Money::from_kobo(500_000_000)), // ₦5M
+        Some(Role::PtaChair) => Some(Money::from_kobo(100_000_000)),   // ₦1M
+        Some(Role::DataEntry) | Some(Role::Hr) | None => Some(Money::from_kobo(50_000_000)), // ₦500k
+        Some(Role::Auditor) => Some(Money::ZERO),
+    }
+}
+
+/// Reject a write whose amount exceeds the caller's role limit. Only new documents and
+/// amount changes are checked.
+pub fn validate_role_write_limit(
+    context: &AssertSetDocContext,
+    amount: Money,
+    before_amount: Option<Money>,
+) -> Result<(), String> {
+    if is_satellite_caller(&context.caller) || before_amount == Some(amount) {
+        return Ok(());
+    }
+
+    let role = get_caller_role(&context.caller);
+    if let Some(message) = role_limit_violation(role, amount) {
+        record_rejected_write(&context.caller.to_text(), &context.data.collection, &context.data.key, &message)?;
+        return Err(message);
+    }
+
+    Ok(())
+}
+
+/// The `ROLE_LIMIT` rejection for an amount over the role's limit, if it is over.
+pub fn role_limit_violation(role: Option<Role>, amount: Money) -> Option<String> {
+    let limit = role_write_limit(role)?;
+    if amount <= limit {
+        return None;
+    }
+
+    Some(format!(
+        "{}: A {} may record up to ₦{}; ₦{} requires a bursar",
+        ROLE_LIMIT_ERROR,
+        role.map(|r| r.as_str()).unwrap_or("user without a role"),
+        limit,
+        amount
+    ))
+}
@@ -0,0 +1,125 @@
+//! Roles Module - Caller Role Resolution
+//!
+//! Maps caller principals to application roles stored in `user_roles`
+//! (document key = principal text). Controllers of the satellite and the
+//! satellite itself are treated as `super_admin`. Assert hooks call `require_role`
+//! before accepting privileged changes such as confirming a payment.
+
+pub mod limits;
+pub mod rejections;
+pub mod working_hours;
+
+use candid::Principal;
+use junobuild_satellite::{get_controllers, AssertSetDocContext};
+use junobuild_shared::controllers::is_controller;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::utils::doc_utils::*;
+
+pub const USER_ROLES_COLLECTION: &str = "user_roles";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    SuperAdmin,
+    Bursar,
+    Accountant,
+    Auditor,
+    DataEntry,
+    PtaChair,
+    Hr,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::SuperAdmin => "super_admin",
+            Role::Bursar => "bursar",
+            Role::Accountant => "accountant",
+            Role::Auditor => "auditor",
+            Role::DataEntry => "data_entry",
+            Role::PtaChair => "pta_chair",
+            Role::Hr => "hr",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Role> {
+        match role {
+            "super_admin" => Some(Role::SuperAdmin),
+            "bursar" => Some(Role::Bursar),
+            "accountant" => Some(Role::Accountant),
+            "auditor" => Some(Role::Auditor),
+            "data_entry" => Some(Role::DataEntry),
+            "pta_chair" => Some(Role::PtaChair),
+            "hr" => Some(Role::Hr),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserRoleData {
+    pub role: String,
+    pub is_active: bool,
+    pub assigned_by: String,
+}
+
+/// Resolve the role of a caller. Returns `None` for principals without an active role.
+pub fn get_caller_role(caller: &Principal) -> Option<Role> {
+    if is_satellite_caller(caller) || is_controller(*caller, &get_controllers()) {
+        return Some(Role::SuperAdmin);
+    }
+
+    match get_doc_data::<UserRoleData>(USER_ROLES_COLLECTION, &caller.to_text()) {
+        Ok(Some((_, data))) if data.is_active => Role::parse(&data.role),
+        _ => None,
+    }
+}
+
+/// True when the caller holds one of the given roles.
+pub fn caller_has_any_role(caller: &Principal, roles: &[Role]) -> bool {
+    get_caller_role(caller)
+        .map(|role| roles.contains(&role))
+        .unwrap_or(false)
+}
+
+/// Require the caller to hold `role`. Super admins (controllers and the satellite
+/// included) hold every role.
+pub fn require_role(caller: &Principal, role: Role) -> Result<(), String> {
+    if caller_has_any_role(caller, &[Role::SuperAdmin, role]) {
+        return Ok(());
+    }
+    Err(format!("SECURITY: This action requires the '{}' role", role.as_str()))
+}
+
+/// User Role Validation
+///
+/// Security Checks:
+/// - Only super admins (or controllers) can grant or change roles
+/// - Document key must be the principal the role applies to
+/// - Role must be one of the application roles
+pub fn validate_user_role_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: UserRoleData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid user role data format: {}", e))?;
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only super admins can assign user roles".to_string());
+    }
+
+    Principal::from_text(&context.data.key)
+        .map_err(|_| "User role key must be the user's principal".to_string())?;
+
+    if Role::parse(&data.role).is_none() {
+        return Err(format!(
+            "Invalid role '{}'. Must be one of: super_admin, bursar, accountant, auditor, data_entry, pta_chair, hr",
+            data.role
+        ));
+    }
+
+    if data.assigned_by != context.caller.to_text() {
+        return Err("assignedBy must be the principal assigning the role".to_string());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Rejected-write reports.
+//!
+//! A policy check that refuses a client `set_doc` traps, and the trap rolls back the
+//! audit entry recorded during the call together with the write itself. The frontend
+//! therefore reports the rejection afterwards through `report_rejected_write`. The
+//! satellite re-runs the policy for the caller and records the `rejected` entry only
+//! when the policy still refuses the write, so made-up rejections never reach the trail.
+
+use candid::Principal;
+use junobuild_satellite::caller;
+
+use super::limits::{role_limit_violation, ROLE_LIMITED_COLLECTIONS};
+use super::working_hours::write_window_violation;
+use super::{get_caller_role, Role};
+use crate::modules::audit::{record_rejected_write, AUDITED_COLLECTIONS};
+use crate::modules::utils::doc_utils::is_satellite_caller;
+use crate::modules::utils::money::Money;
+
+/// Record a client write the satellite rejected for working hours or a role limit.
+/// `amount` is the amount the caller tried to record, in naira. Returns the reason recorded.
+pub fn report_rejected_write(collection: String, key: String, amount: Option<f64>) -> Result<String, String> {
+    let caller = caller();
+    if caller == Principal::anonymous() || is_satellite_caller(&caller) {
+        return Err("Rejected writes are reported by the signed-in user who made them".to_string());
+    }
+    if key.trim().is_empty() {
+        return Err("Document key is required".to_string());
+    }
+
+    let role = get_caller_role(&caller);
+    // Financial writes are checked against the working hours first, as in the assert hook
+    let outside_window = if AUDITED_COLLECTIONS.contains(&collection.as_str()) {
+        write_window_violation(role, ic_cdk::api::time())?
+    } else {
+        None
+    };
+    let reason = outside_window
+        .or_else(|| rejection_reason(&collection, role, amount.map(Money::from_naira)))
+        .ok_or("No policy rejects this write for the caller; nothing was recorded")?;
+    record_rejected_write(&caller.to_text(), &collection, &key, &reason)?;
+    Ok(reason)
+}
+
+// The role limit rejection the write would meet again, if any
+fn rejection_reason(collection: &str, role: Option<Role>, amount: Option<Money>) -> Option<String> {
+    if !ROLE_LIMITED_COLLECTIONS.contains(&collection) {
+        return None;
+    }
+    role_limit_violation(role, amount?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::audit::rejected_entry;
+    use crate::modules::roles::limits::ROLE_LIMIT_ERROR;
+
+    #[test]
+    fn over_limit_report_leaves_a_rejected_entry() {
+        let reason = rejection_reason("payments", Some(Role::Accountant), Some(Money::from_naira(6_000_000.0)))
+            .expect("₦6M is over the accountant limit");
+        assert!(reason.starts_with(ROLE_LIMIT_ERROR));
+
+        let entry = rejected_entry("clerk", "payments", "pay_1", &reason, 42);
+        assert_eq!(entry.action, "rejected");
+        assert_eq!(entry.collection, "payments");
+        assert_eq!(entry.doc_key, "pay_1");
+        assert_eq!(entry.reason.as_deref(), Some(reason.as_str()));
+    }
+
+    #[test]
+    fn reports_the_policy_would_accept_are_refused() {
+        let amount = Some(Money::from_naira(400_000.0));
+        assert_eq!(rejection_reason("expenses", Some(Role::DataEntry), amount), None);
+        assert_eq!(rejection_reason("expenses", Some(Role::Bursar), Some(Money::from_naira(9e7))), None);
+        assert_eq!(rejection_reason("expenses", None, None), None);
+        assert_eq!(rejection_reason("students", None, Some(Money::from_naira(9e7))), None);
+        assert!(rejection_reason("expenses", None, Some(Money::from_naira(500_000.01))).is_some());
+    }
+}
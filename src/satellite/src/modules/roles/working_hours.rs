@@ -0,0 +1,197 @@
+//! Working-hours write window.
+//!
+//! An optional policy in `working_hours`, keyed by role name (or `default` for roles
+//! without their own), restricts financial writes to set days and hours of the school's
+//! local time. Entries recorded at night or on weekends are a common sign of ghost
+//! transactions. Controllers and super admins are exempt. An attempt outside the window
+//! is rejected with the `WORKING_HOURS` error code; the frontend reports it through
+//! `report_rejected_write` so it reaches the audit trail.
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::{caller_has_any_role, get_caller_role, Role};
+use crate::modules::audit::record_rejected_write;
+use crate::modules::utils::doc_utils::*;
+
+pub const WORKING_HOURS_COLLECTION: &str = "working_hours";
+
+pub const WORKING_HOURS_ERROR: &str = "WORKING_HOURS";
+
+// Key of the policy that applies to roles without their own
+const DEFAULT_POLICY_KEY: &str = "default";
+
+// West Africa Time (UTC+1)
+const DEFAULT_UTC_OFFSET_MINUTES: i32 = 60;
+
+const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+fn default_utc_offset() -> i32 {
+    DEFAULT_UTC_OFFSET_MINUTES
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkingHoursData {
+    // ISO weekdays, 1 = Monday ... 7 = Sunday
+    pub days: Vec<u8>,
+    // HH:MM local time; the window includes start and excludes end
+    pub start_time: String,
+    pub end_time: String,
+    #[serde(default = "default_utc_offset")]
+    pub utc_offset_minutes: i32,
+    pub is_active: bool,
+    pub updated_by: String,
+}
+
+/// Working Hours Validation
+///
+/// Checks:
+/// - Maintained by super admins only
+/// - Keyed by an application role, or `default`
+/// - At least one weekday (1-7), HH:MM times with start before end, offset within ±14h
+pub fn validate_working_hours_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: WorkingHoursData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid working hours data format: {}", e))?;
+
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only super admins can change working hours".to_string());
+    }
+    if data.updated_by != context.caller.to_text() {
+        return Err("updatedBy must be the principal changing the working hours".to_string());
+    }
+
+    if context.data.key != DEFAULT_POLICY_KEY && Role::parse(&context.data.key).is_none() {
+        return Err(format!(
+            "Working hours are keyed by role name (or '{}'); unknown role '{}'",
+            DEFAULT_POLICY_KEY, context.data.key
+        ));
+    }
+
+    if data.days.is_empty() || data.days.iter().any(|d| !(1..=7).contains(d)) {
+        return Err("days must list at least one weekday between 1 (Monday) and 7 (Sunday)".to_string());
+    }
+    let start = parse_time(&data.start_time).ok_or("startTime must be in format HH:MM")?;
+    let end = parse_time(&data.end_time).ok_or("endTime must be in format HH:MM")?;
+    if start >= end {
+        return Err("startTime must be before endTime".to_string());
+    }
+    if data.utc_offset_minutes.abs() > 14 * 60 {
+        return Err("utcOffsetMinutes must be within ±14 hours".to_string());
+    }
+
+    Ok(())
+}
+
+/// Reject a financial write made outside the caller's working hours.
+pub fn validate_write_window(context: &AssertSetDocContext) -> Result<(), String> {
+    if let Some(message) = write_window_violation(get_caller_role(&context.caller), ic_cdk::api::time())? {
+        record_rejected_write(&context.caller.to_text(), &context.data.collection, &context.data.key, &message)?;
+        return Err(message);
+    }
+    Ok(())
+}
+
+/// The `WORKING_HOURS` rejection for a financial write by the role at `now`, if any.
+pub fn write_window_violation(role: Option<Role>, now: u64) -> Result<Option<String>, String> {
+    if role == Some(Role::SuperAdmin) {
+        // Controllers and the satellite resolve to super admin
+        return Ok(None);
+    }
+
+    Ok(applicable_policy(role)?.and_then(|policy| window_violation(role, &policy, now)))
+}
+
+// The rejection for a write at `now` (nanoseconds) outside the policy's window
+fn window_violation(role: Option<Role>, policy: &WorkingHoursData, now: u64) -> Option<String> {
+    let local_minutes = (now / 60_000_000_000) as i64 + policy.utc_offset_minutes as i64;
+    let days = local_minutes.div_euclid(24 * 60);
+    let minute_of_day = local_minutes.rem_euclid(24 * 60) as u32;
+    // 1970-01-01 was a Thursday (ISO weekday 4)
+    let weekday = ((days + 3).rem_euclid(7) + 1) as u8;
+
+    let start = parse_time(&policy.start_time).unwrap_or(0);
+    let end = parse_time(&policy.end_time).unwrap_or(24 * 60);
+    if policy.days.contains(&weekday) && minute_of_day >= start && minute_of_day < end {
+        return None;
+    }
+
+    Some(format!(
+        "{}: {} writes are allowed on {} between {} and {} only",
+        WORKING_HOURS_ERROR,
+        role.map(|r| r.as_str()).unwrap_or("these"),
+        policy
+            .days
+            .iter()
+            .filter_map(|d| DAY_NAMES.get((*d as usize).wrapping_sub(1)))
+            .copied()
+            .collect::<Vec<_>>()
+            .join(", "),
+        policy.start_time,
+        policy.end_time
+    ))
+}
+
+/// The role's own active policy, else the active default policy.
+fn applicable_policy(role: Option<Role>) -> Result<Option<WorkingHoursData>, String> {
+    let keys = match role {
+        Some(role) => vec![role.as_str(), DEFAULT_POLICY_KEY],
+        None => vec![DEFAULT_POLICY_KEY],
+    };
+    for key in keys {
+        if let Some((_, policy)) = get_doc_data::<WorkingHoursData>(WORKING_HOURS_COLLECTION, key)? {
+            if policy.is_active {
+                return Ok(Some(policy));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Minutes since midnight for an HH:MM time
+fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return None;
+    }
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some(hours * 60 + minutes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Monday to Friday, 08:00-16:00 West Africa Time
+    fn school_hours() -> WorkingHoursData {
+        WorkingHoursData {
+            days: vec![1, 2, 3, 4, 5],
+            start_time: "08:00".to_string(),
+            end_time: "16:00".to_string(),
+            utc_offset_minutes: DEFAULT_UTC_OFFSET_MINUTES,
+            is_active: true,
+            updated_by: "admin".to_string(),
+        }
+    }
+
+    #[test]
+    fn writes_inside_the_window_pass() {
+        // Wednesday 2024-03-06 09:30 WAT (08:30 UTC)
+        assert_eq!(window_violation(Some(Role::Accountant), &school_hours(), 1_709_713_800_000_000_000), None);
+    }
+
+    #[test]
+    fn night_and_weekend_writes_are_rejected() {
+        // Wednesday 2024-03-06 16:00 WAT, the end of the window is excluded
+        let evening = window_violation(Some(Role::Accountant), &school_hours(), 1_709_737_200_000_000_000);
+        assert!(evening.expect("16:00 is outside the window").starts_with(WORKING_HOURS_ERROR));
+        // Saturday 2024-03-09 10:00 WAT
+        assert!(window_violation(None, &school_hours(), 1_709_974_800_000_000_000).is_some());
+        // Friday 2024-03-08 15:30 UTC is 16:30 local time
+        assert!(window_violation(None, &school_hours(), 1_709_911_800_000_000_000).is_some());
+    }
+}
@@ -0,0 +1,145 @@
+//! Rules Module - Shadow-Mode Rollout of Validation Rules
+//!
+//! Tightening a rule retroactively breaks clients that still send the old shape, so new
+//! rules are registered in [`RULE_FLAGS`] and checked through [`check_rule`] instead of
+//! failing the write directly. Each rule runs in one of three modes, set per rule in the
+//! school settings' `ruleModes` (the rule's default otherwise):
+//! - `warn`: violations are recorded in `rule_violations` and the write goes through
+//! - `enforce`: violations reject the write
+//! - `off`: the rule is not checked
+//!
+//! `get_rule_violation_metrics` counts the recorded violations per rule, so a rule can be
+//! switched to `enforce` once clients stop tripping it.
+
+use candid::CandidType;
+use junobuild_satellite::AssertSetDocContext;
+use serde::{Deserialize, Serialize};
+
+use super::settings::school_settings;
+use super::utils::doc_utils::*;
+
+pub const RULE_VIOLATIONS_COLLECTION: &str = "rule_violations";
+
+pub const RULE_MODES: [&str; 3] = ["off", "warn", "enforce"];
+
+/// Rules under rollout, with their default mode.
+pub const RULE_FLAGS: [(&str, &str); 3] = [
+    // A fee assignment's scholarship is active, current and covers the student or class
+    ("scholarship_applicability", "warn"),
+    // A fee assignment's items follow its fee structure
+    ("fee_structure_match", "warn"),
+    // Active staff have a bank name and a valid account number for payroll files
+    ("staff_bank_details", "warn"),
+];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleViolationData {
+    pub rule_id: String,
+    pub collection: String,
+    pub doc_key: String,
+    pub caller: String,
+    pub message: String,
+    pub recorded_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct RuleMetric {
+    pub rule_id: String,
+    pub mode: String,
+    pub violations: u32,
+    // Distinct documents with a violation
+    pub documents: u32,
+    pub last_violation_at: Option<u64>,
+}
+
+/// Rule Violation Validation
+///
+/// Checks:
+/// - Violations are recorded by the satellite only and never modified
+pub fn validate_rule_violation_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Rule violations are recorded by the satellite".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Rule violations cannot be modified".to_string());
+    }
+    Ok(())
+}
+
+/// The mode a registered rule runs in.
+pub fn rule_mode(rule_id: &str) -> Result<String, String> {
+    let default_mode = RULE_FLAGS
+        .iter()
+        .find(|(id, _)| *id == rule_id)
+        .map(|(_, mode)| *mode)
+        .ok_or_else(|| format!("Unknown validation rule '{}'", rule_id))?;
+    Ok(school_settings()?
+        .rule_modes
+        .get(rule_id)
+        .cloned()
+        .unwrap_or_else(|| default_mode.to_string()))
+}
+
+/// Run a registered rule against the write in `context` in the rule's mode.
+pub fn check_rule(
+    context: &AssertSetDocContext,
+    rule_id: &str,
+    check: impl FnOnce() -> Result<(), String>,
+) -> Result<(), String> {
+    let mode = rule_mode(rule_id)?;
+    if mode == "off" {
+        return Ok(());
+    }
+    let message = match check() {
+        Ok(()) => return Ok(()),
+        Err(message) => message,
+    };
+    if mode == "enforce" {
+        return Err(message);
+    }
+
+    let now = ic_cdk::api::time();
+    let violation = RuleViolationData {
+        rule_id: rule_id.to_string(),
+        collection: context.data.collection.clone(),
+        doc_key: context.data.key.clone(),
+        caller: context.caller.to_text(),
+        message,
+        recorded_at: now,
+    };
+    // Time first so keys sort chronologically
+    let key = format!("{}_{}_{}_{}", now, rule_id, context.data.collection, context.data.key);
+    set_doc_data(
+        RULE_VIOLATIONS_COLLECTION,
+        &key,
+        &violation,
+        Some(format!("rule_id={};collection={};", rule_id, context.data.collection)),
+        None,
+    )?;
+    Ok(())
+}
+
+/// Violations recorded per registered rule, with the mode it currently runs in.
+pub fn get_rule_violation_metrics() -> Result<Vec<RuleMetric>, String> {
+    let violations = list_doc_data::<RuleViolationData>(RULE_VIOLATIONS_COLLECTION, None)?;
+
+    RULE_FLAGS
+        .iter()
+        .map(|(rule_id, _)| {
+            let recorded: Vec<&RuleViolationData> =
+                violations.iter().map(|(_, _, v)| v).filter(|v| v.rule_id == *rule_id).collect();
+            let mut documents: Vec<(&str, &str)> =
+                recorded.iter().map(|v| (v.collection.as_str(), v.doc_key.as_str())).collect();
+            documents.sort();
+            documents.dedup();
+            Ok(RuleMetric {
+                rule_id: rule_id.to_string(),
+                mode: rule_mode(rule_id)?,
+                violations: recorded.len() as u32,
+                documents: documents.len() as u32,
+                last_violation_at: recorded.iter().map(|v| v.recorded_at).max(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,247 @@
+//! Settings Module - School Configuration
+//!
+//! Each school tunes its limits in the `school_settings` collection (document `default`)
+//! instead of waiting for a canister upgrade:
+//! - Current academic year and active term
+//! - Base currency, and the foreign currencies whose exchange rates are kept
+//! - Approval thresholds for bank transfers and expenses
+//! - Fee due-date policy for new fee assignments
+//! - Priority for allocating payments that arrive without fee allocations
+//! - Late fee charged on overdue fee assignments
+//! - Whether unpaid optional fee items are carried forward or dropped at term rollover
+//! - Bank account guardians pay fees into at bank branches
+//! - Whether users may approve what they initiated (development and testing only)
+//! - Mode of each validation rule under rollout (see [`super::rules`])
+//!
+//! Validators read the settings through [`school_settings`]; any field not yet saved
+//! falls back to the default below, so a school without settings behaves as before.
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::fees::{ALLOCATION_RULES, VALID_FEE_TYPES};
+use super::roles::{caller_has_any_role, Role};
+use super::rules::{RULE_FLAGS, RULE_MODES};
+use super::utils::cache::cached_doc_data;
+use super::utils::doc_utils::doc_exists;
+use super::utils::money::Money;
+use super::utils::validation_utils::is_valid_academic_year;
+
+pub const SCHOOL_SETTINGS_COLLECTION: &str = "school_settings";
+
+const SETTINGS_KEY: &str = "default";
+const TERMS: [&str; 3] = ["first", "second", "third"];
+const MAX_FEE_DUE_DAYS: u32 = 365;
+const LATE_FEE_TYPES: [&str; 2] = ["flat", "percentage"];
+const UNPAID_OPTIONAL_FEE_POLICIES: [&str; 2] = ["carry_forward", "drop"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SchoolSettingsData {
+    pub current_academic_year: Option<String>,
+    pub active_term: Option<String>,
+    pub currency: String,
+    // Foreign currencies the satellite keeps daily exchange rates for (see `fx`)
+    pub fx_currencies: Vec<String>,
+    // Bank transfers above this need an approval before completion
+    pub transfer_approval_threshold: Money,
+    // Bank transfers above this need two signatories
+    pub transfer_dual_signatory_threshold: Money,
+    // Expenses above this are approved by an administrator
+    pub expense_approval_threshold: Money,
+    // New fee assignments must carry a due date...
+    pub fee_due_date_required: bool,
+    // ...no later than this many days after they are raised
+    pub fee_due_days: u32,
+    // Order in which unallocated payments settle fees: `arrears` (earlier terms), a fee
+    // type, `mandatory` or `optional` items
+    pub allocation_priority: Vec<String>,
+    // Late fee charged once on fee assignments still owing after their due date: a
+    // `flat` amount or a `percentage` of the balance; no late fees when not set
+    pub late_fee_type: Option<String>,
+    pub late_fee_amount: Money,
+    pub late_fee_percent: f64,
+    // Days after the due date before the late fee applies
+    pub late_fee_grace_days: u32,
+    // What closing a term does with unpaid optional fee items: `carry_forward` (owed as
+    // arrears) or `drop` (written off the assignment)
+    pub unpaid_optional_fees: String,
+    // Mode (`off`, `warn`, `enforce`) per rule under rollout; unlisted rules run in
+    // their default mode
+    pub rule_modes: BTreeMap<String, String>,
+    // Bank account (in `bank_accounts`) quoted on teller slips for fees paid at a branch
+    pub fee_collection_account_id: Option<String>,
+    // Lets a user approve an expense, salary payment or transfer they initiated; for
+    // development and testing only
+    pub allow_self_approval: bool,
+    pub updated_by: String,
+    pub updated_at: u64,
+}
+
+impl Default for SchoolSettingsData {
+    fn default() -> Self {
+        SchoolSettingsData {
+            current_academic_year: None,
+            active_term: None,
+            currency: "NGN".to_string(),
+            fx_currencies: ["USD", "GBP", "EUR"].map(String::from).to_vec(),
+            transfer_approval_threshold: Money::from_kobo(500_000_000),         // ₦5M
+            transfer_dual_signatory_threshold: Money::from_kobo(2_000_000_000), // ₦20M
+            expense_approval_threshold: Money::from_kobo(100_000_000),          // ₦1M
+            fee_due_date_required: false,
+            fee_due_days: 90,
+            allocation_priority: ["arrears", "tuition", "mandatory", "optional"].map(String::from).to_vec(),
+            late_fee_type: None,
+            late_fee_amount: Money::ZERO,
+            late_fee_percent: 0.0,
+            late_fee_grace_days: 0,
+            unpaid_optional_fees: "carry_forward".to_string(),
+            rule_modes: BTreeMap::new(),
+            fee_collection_account_id: None,
+            allow_self_approval: false,
+            updated_by: String::new(),
+            updated_at: 0,
+        }
+    }
+}
+
+/// School Settings Validation
+///
+/// Checks:
+/// - Only administrators change settings, kept in the `default` document
+/// - Academic year is `YYYY/YYYY` (consecutive years) and the term is first/second/third
+/// - Currency is a three-letter ISO code, and the foreign currencies are other codes,
+///   each listed once
+/// - Thresholds are positive, and the dual-signatory threshold is not below the
+///   approval threshold
+/// - The fee due period is 1-365 days
+/// - Allocation priority rules are known fee types or rules, each listed once
+/// - A late fee is a positive flat amount or a percentage up to 100, with a grace period
+///   of at most 365 days
+/// - Unpaid optional fees are carried forward or dropped
+/// - Rule modes name registered rules and known modes
+/// - The fee collection account is an existing bank account
+pub fn validate_school_settings_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only administrators can change school settings".to_string());
+    }
+    if context.data.key != SETTINGS_KEY {
+        return Err(format!("School settings must use the key '{}'", SETTINGS_KEY));
+    }
+
+    let data: SchoolSettingsData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid school settings data format: {}", e))?;
+
+    if let Some(ref year) = data.current_academic_year {
+        if !is_valid_academic_year(year) {
+            return Err("Academic year must be in format YYYY/YYYY, e.g. 2024/2025".to_string());
+        }
+    }
+    if let Some(ref term) = data.active_term {
+        if !TERMS.contains(&term.as_str()) {
+            return Err(format!("Invalid active term '{}'. Must be one of: {}", term, TERMS.join(", ")));
+        }
+    }
+    if data.currency.len() != 3 || !data.currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err("Currency must be a three-letter ISO code, e.g. NGN".to_string());
+    }
+    for (i, currency) in data.fx_currencies.iter().enumerate() {
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) || *currency == data.currency {
+            return Err(format!("Invalid foreign currency '{}'. Must be a three-letter ISO code other than {}", currency, data.currency));
+        }
+        if data.fx_currencies[..i].contains(currency) {
+            return Err(format!("Foreign currency '{}' is listed twice", currency));
+        }
+    }
+
+    for (name, threshold) in [
+        ("Transfer approval threshold", data.transfer_approval_threshold),
+        ("Transfer dual-signatory threshold", data.transfer_dual_signatory_threshold),
+        ("Expense approval threshold", data.expense_approval_threshold),
+    ] {
+        if !threshold.is_positive() {
+            return Err(format!("{} must be greater than 0", name));
+        }
+    }
+    if data.transfer_dual_signatory_threshold < data.transfer_approval_threshold {
+        return Err("The dual-signatory threshold cannot be below the transfer approval threshold".to_string());
+    }
+    if data.fee_due_days == 0 || data.fee_due_days > MAX_FEE_DUE_DAYS {
+        return Err(format!("Fee due days must be between 1 and {}", MAX_FEE_DUE_DAYS));
+    }
+    if data.allocation_priority.is_empty() {
+        return Err("Allocation priority must list at least one rule".to_string());
+    }
+    for (i, rule) in data.allocation_priority.iter().enumerate() {
+        if !ALLOCATION_RULES.contains(&rule.as_str()) && !VALID_FEE_TYPES.contains(&rule.as_str()) {
+            return Err(format!(
+                "Invalid allocation rule '{}'. Must be a fee type or one of: {}",
+                rule,
+                ALLOCATION_RULES.join(", ")
+            ));
+        }
+        if data.allocation_priority[..i].contains(rule) {
+            return Err(format!("Allocation rule '{}' is listed twice", rule));
+        }
+    }
+    if let Some(ref late_fee_type) = data.late_fee_type {
+        match late_fee_type.as_str() {
+            "flat" if !data.late_fee_amount.is_positive() => {
+                return Err("A flat late fee amount must be greater than 0".to_string());
+            }
+            "percentage" if data.late_fee_percent <= 0.0 || data.late_fee_percent > 100.0 => {
+                return Err("A percentage late fee must be greater than 0 and at most 100".to_string());
+            }
+            "flat" | "percentage" => {}
+            other => {
+                return Err(format!(
+                    "Invalid late fee type '{}'. Must be one of: {}",
+                    other,
+                    LATE_FEE_TYPES.join(", ")
+                ));
+            }
+        }
+    }
+    if data.late_fee_grace_days > MAX_FEE_DUE_DAYS {
+        return Err(format!("Late fee grace days cannot exceed {}", MAX_FEE_DUE_DAYS));
+    }
+    if !UNPAID_OPTIONAL_FEE_POLICIES.contains(&data.unpaid_optional_fees.as_str()) {
+        return Err(format!(
+            "Invalid unpaid optional fee policy '{}'. Must be one of: {}",
+            data.unpaid_optional_fees,
+            UNPAID_OPTIONAL_FEE_POLICIES.join(", ")
+        ));
+    }
+    for (rule_id, mode) in data.rule_modes.iter() {
+        if !RULE_FLAGS.iter().any(|(id, _)| id == rule_id) {
+            return Err(format!("Unknown validation rule '{}'", rule_id));
+        }
+        if !RULE_MODES.contains(&mode.as_str()) {
+            return Err(format!(
+                "Invalid mode '{}' for rule '{}'. Must be one of: {}",
+                mode,
+                rule_id,
+                RULE_MODES.join(", ")
+            ));
+        }
+    }
+    if let Some(ref account_id) = data.fee_collection_account_id {
+        if !doc_exists("bank_accounts", account_id)? {
+            return Err(format!("Fee collection bank account '{}' not found", account_id));
+        }
+    }
+    if data.updated_by != context.caller.to_text() {
+        return Err("updatedBy must be the principal changing the settings".to_string());
+    }
+
+    Ok(())
+}
+
+/// The school's settings, or the defaults where none are saved.
+pub fn school_settings() -> Result<SchoolSettingsData, String> {
+    Ok(cached_doc_data::<SchoolSettingsData>(SCHOOL_SETTINGS_COLLECTION, SETTINGS_KEY)?
+        .map(|(_, settings)| settings)
+        .unwrap_or_default())
+}
@@ -0,0 +1,120 @@
+//! Simulation Module - Dry Runs of Document Writes
+//!
+//! `simulate_transition` runs the rules `assert_set_doc` applies to a write against a
+//! current and proposed document supplied by the caller, and reports each rule set (the
+//! names used by legacy imports, see [`super::migrations::BYPASS_RULE_SETS`], and
+//! `archival`, which is never bypassed) as passed or failed with its message. Unlike a real write it does not stop at the first failure, so
+//! every reason a document would be rejected shows at once.
+//!
+//! Rules run as the caller, so role checks answer for them. Nothing is written: the
+//! simulation is a query, and migration bypasses never apply to it.
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext, Doc, DocAssertSet, DocContext, HookContext, SetDoc};
+use junobuild_utils::encode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::archival::validate_archival;
+use super::audit::AUDITED_COLLECTIONS;
+use super::close::validate_period_open;
+use super::roles::{caller_has_any_role, working_hours::validate_write_window, Role};
+use super::terms::validate_term_open;
+
+#[derive(CandidType, Deserialize)]
+pub struct SimulatedDocument {
+    pub key: String,
+    // Document data as JSON, in the collection's stored (camelCase) shape
+    pub data: String,
+    pub description: Option<String>,
+}
+
+#[derive(CandidType, Serialize)]
+pub struct RuleCheck {
+    // `write_window`, `period_lock`, `term_lock`, `archival` or `document`
+    pub rule: String,
+    pub passed: bool,
+    pub message: Option<String>,
+}
+
+#[derive(CandidType, Serialize)]
+pub struct TransitionSimulation {
+    pub collection: String,
+    pub key: String,
+    pub accepted: bool,
+    pub checks: Vec<RuleCheck>,
+}
+
+/// Run the write rules for `collection` on a change from `current_doc` (none for a new
+/// document) to `proposed_doc`, reporting each rule's outcome. `validate_document` is the
+/// collection's own validation, as dispatched by `assert_set_doc`.
+pub fn simulate_transition(
+    collection: String,
+    current_doc: Option<SimulatedDocument>,
+    proposed_doc: SimulatedDocument,
+    validate_document: fn(&AssertSetDocContext) -> Result<(), String>,
+) -> Result<TransitionSimulation, String> {
+    let caller = caller();
+    if !caller_has_any_role(
+        &caller,
+        &[Role::SuperAdmin, Role::Bursar, Role::Accountant, Role::Auditor, Role::DataEntry, Role::PtaChair, Role::Hr],
+    ) {
+        return Err("SECURITY: Only staff can simulate document writes".to_string());
+    }
+    if let Some(ref current) = current_doc {
+        if current.key != proposed_doc.key {
+            return Err(format!(
+                "Current document key '{}' does not match the proposed key '{}'",
+                current.key, proposed_doc.key
+            ));
+        }
+    }
+
+    let now = ic_cdk::api::time();
+    let current = match current_doc {
+        Some(current) => Some(Doc {
+            owner: caller,
+            data: document_data(&current)?,
+            description: current.description,
+            created_at: now,
+            updated_at: now,
+            version: Some(1),
+        }),
+        None => None,
+    };
+    let context = HookContext {
+        caller,
+        data: DocContext {
+            collection: collection.clone(),
+            key: proposed_doc.key.clone(),
+            data: DocAssertSet {
+                proposed: SetDoc {
+                    data: document_data(&proposed_doc)?,
+                    description: proposed_doc.description,
+                    version: current.as_ref().and_then(|doc| doc.version),
+                },
+                current,
+            },
+        },
+    };
+
+    let mut checks = Vec::new();
+    if AUDITED_COLLECTIONS.contains(&collection.as_str()) {
+        checks.push(check("write_window", validate_write_window(&context)));
+    }
+    checks.push(check("period_lock", validate_period_open(&context)));
+    checks.push(check("term_lock", validate_term_open(&context)));
+    checks.push(check("archival", validate_archival(&context)));
+    checks.push(check("document", validate_document(&context)));
+
+    Ok(TransitionSimulation { collection, key: proposed_doc.key, accepted: checks.iter().all(|c| c.passed), checks })
+}
+
+fn document_data(document: &SimulatedDocument) -> Result<Vec<u8>, String> {
+    let data: serde_json::Value = serde_json::from_str(&document.data)
+        .map_err(|e| format!("Invalid JSON for document '{}': {}", document.key, e))?;
+    encode_doc_data(&data)
+}
+
+fn check(rule: &str, result: Result<(), String>) -> RuleCheck {
+    RuleCheck { rule: rule.to_string(), passed: result.is_ok(), message: result.err() }
+}
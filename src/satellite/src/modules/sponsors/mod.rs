@@ -0,0 +1,487 @@
+//! Sponsors Module - Corporate and NGO Billing Accounts
+//!
+//! Some students' fees are paid by a company, NGO or government agency. A sponsor in
+//! `sponsors` lists the students it covers; a student is covered by one active sponsor
+//! at most.
+//! - `create_sponsor_invoice` bills the sponsor for the outstanding fees of all its
+//!   students for a term, as one invoice in `sponsor_invoices`
+//! - Sponsor payments (`sponsor_payments`) are recorded against an invoice. When one is
+//!   confirmed, the on-set hook fans it out over the invoice's students in invoice order,
+//!   applying each share to the student's fee assignment like a payment of their own
+//! - `get_sponsor_statement` lists a sponsor's invoices and payments with the balance
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::fees::{allocate_to_fee_items, apply_payment_to_assignment, StudentFeeAssignmentData};
+use super::roles::{caller_has_any_role, require_role, Role};
+use super::settings::school_settings;
+use super::utils::counters::next_number;
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const SPONSORS_COLLECTION: &str = "sponsors";
+pub const SPONSOR_INVOICES_COLLECTION: &str = "sponsor_invoices";
+pub const SPONSOR_PAYMENTS_COLLECTION: &str = "sponsor_payments";
+
+const SPONSOR_TYPES: [&str; 4] = ["company", "ngo", "government", "individual"];
+const PAYMENT_METHODS: [&str; 3] = ["bank_transfer", "cheque", "online"];
+const TERMS: [&str; 3] = ["first", "second", "third"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorData {
+    pub name: String,
+    pub sponsor_type: String,
+    pub contact_email: Option<String>,
+    pub contact_phone: Option<String>,
+    pub student_ids: Vec<String>,
+    pub is_active: bool,
+    pub created_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorInvoiceLine {
+    pub student_id: String,
+    pub student_name: String,
+    pub fee_assignment_id: String,
+    pub amount: Money,
+    pub amount_paid: Money,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorInvoiceData {
+    pub sponsor_id: String,
+    pub invoice_number: String,
+    pub academic_year: String,
+    pub term: String,
+    pub lines: Vec<SponsorInvoiceLine>,
+    pub total: Money,
+    pub amount_paid: Money,
+    // Paid by the sponsor but no longer owed by any student on the invoice
+    pub unapplied: Money,
+    pub status: String,
+    pub issued_by: String,
+    pub issued_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorPaymentData {
+    pub sponsor_id: String,
+    pub invoice_id: String,
+    pub amount: Money,
+    pub payment_method: String,
+    pub payment_date: String,
+    pub reference: String,
+    pub status: String,
+    pub notes: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct SponsorInvoiceSummary {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    pub students: u32,
+    pub total: f64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct SponsorStatementInvoice {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    pub academic_year: String,
+    pub term: String,
+    pub issued_on: String,
+    pub total: f64,
+    pub amount_paid: f64,
+    pub balance: f64,
+    pub status: String,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct SponsorStatementPayment {
+    pub payment_id: String,
+    pub invoice_id: String,
+    pub payment_date: String,
+    pub reference: String,
+    pub amount: f64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct SponsorStatement {
+    pub sponsor_id: String,
+    pub sponsor_name: String,
+    pub invoices: Vec<SponsorStatementInvoice>,
+    pub payments: Vec<SponsorStatementPayment>,
+    pub total_invoiced: f64,
+    pub total_paid: f64,
+    pub balance: f64,
+}
+
+/// Sponsor Validation
+///
+/// Checks:
+/// - Only bursars and administrators manage sponsors
+/// - Name, sponsor type and contact details are valid
+/// - Covered students exist, are listed once, and have no other active sponsor
+pub fn validate_sponsor_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can manage sponsors".to_string());
+    }
+
+    let data: SponsorData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid sponsor data format: {}", e))?;
+
+    if data.name.trim().len() < 3 {
+        return Err("Sponsor name must be at least 3 characters".to_string());
+    }
+    if !SPONSOR_TYPES.contains(&data.sponsor_type.as_str()) {
+        return Err(format!(
+            "Invalid sponsor type '{}'. Must be one of: {}",
+            data.sponsor_type,
+            SPONSOR_TYPES.join(", ")
+        ));
+    }
+    if let Some(ref email) = data.contact_email {
+        if !is_valid_email(email) {
+            return Err("Invalid sponsor contact email".to_string());
+        }
+    }
+    if let Some(ref phone) = data.contact_phone {
+        if !is_valid_phone_number(phone) {
+            return Err("Invalid sponsor contact phone number".to_string());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for student_id in data.student_ids.iter() {
+        if !seen.insert(student_id) {
+            return Err(format!("Student '{}' is listed more than once", student_id));
+        }
+        if !doc_exists("students", student_id)? {
+            return Err(format!("Student '{}' not found", student_id));
+        }
+    }
+
+    if data.is_active {
+        for (key, _, other) in list_doc_data::<SponsorData>(SPONSORS_COLLECTION, None)? {
+            if key == context.data.key || !other.is_active {
+                continue;
+            }
+            if let Some(student_id) = data.student_ids.iter().find(|id| other.student_ids.contains(id)) {
+                return Err(format!("Student '{}' is already sponsored by {}", student_id, other.name));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sponsor invoices are issued by `create_sponsor_invoice` and updated by the payment
+/// fan-out only.
+pub fn validate_sponsor_invoice_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Sponsor invoices are issued through the create_sponsor_invoice endpoint".to_string());
+    }
+    Ok(())
+}
+
+/// Sponsor Payment Validation
+///
+/// Checks:
+/// - The sponsor and invoice exist and belong together
+/// - Amount is positive and within the invoice balance; valid method, date and reference
+/// - New payments are pending or confirmed; pending → confirmed/cancelled only
+/// - Only bursars confirm; confirmed payments cannot be changed
+pub fn validate_sponsor_payment_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: SponsorPaymentData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid sponsor payment data format: {}", e))?;
+
+    let before: Option<SponsorPaymentData> = match context.data.data.current {
+        Some(ref doc) => Some(
+            decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous sponsor payment data: {}", e))?,
+        ),
+        None => None,
+    };
+
+    match before {
+        None => {
+            if data.status != "pending" && data.status != "confirmed" {
+                return Err("New sponsor payments must have status 'pending' or 'confirmed'".to_string());
+            }
+            if data.recorded_by != context.caller.to_text() {
+                return Err("recordedBy must be the principal recording the payment".to_string());
+            }
+        }
+        Some(ref before) => {
+            if before.status != "pending" {
+                return Err(format!("AUDIT: A {} sponsor payment cannot be changed", before.status));
+            }
+            if before.sponsor_id != data.sponsor_id
+                || before.invoice_id != data.invoice_id
+                || before.recorded_by != data.recorded_by
+            {
+                return Err("Sponsor, invoice and recorder of a payment cannot be changed".to_string());
+            }
+            if data.status != "pending" && data.status != "confirmed" && data.status != "cancelled" {
+                return Err(format!(
+                    "Invalid status transition from 'pending' to '{}'. Allowed: [confirmed, cancelled]",
+                    data.status
+                ));
+            }
+            if data.status == "cancelled" && data.notes.as_ref().map(|n| n.trim().is_empty()).unwrap_or(true) {
+                return Err("Cancelled sponsor payments must include the reason in notes".to_string());
+            }
+        }
+    }
+
+    if data.status == "confirmed" {
+        require_role(&context.caller, Role::Bursar)?;
+    }
+    if !data.amount.is_positive() {
+        return Err("Sponsor payment amount must be greater than zero".to_string());
+    }
+    if !PAYMENT_METHODS.contains(&data.payment_method.as_str()) {
+        return Err(format!(
+            "Invalid payment method '{}'. Must be one of: {}",
+            data.payment_method,
+            PAYMENT_METHODS.join(", ")
+        ));
+    }
+    if !is_valid_date_format(&data.payment_date) {
+        return Err("Invalid payment date format. Must be YYYY-MM-DD".to_string());
+    }
+    if data.reference.trim().is_empty() {
+        return Err("Sponsor payments must include the bank or cheque reference".to_string());
+    }
+
+    let (_, invoice) = get_doc_data::<SponsorInvoiceData>(SPONSOR_INVOICES_COLLECTION, &data.invoice_id)?
+        .ok_or_else(|| format!("Sponsor invoice '{}' not found", data.invoice_id))?;
+    if invoice.sponsor_id != data.sponsor_id {
+        return Err("The invoice was not issued to this sponsor".to_string());
+    }
+    let outstanding = invoice.total - invoice.amount_paid;
+    if data.status != "cancelled" && data.amount > outstanding {
+        return Err(format!(
+            "Payment of ₦{} exceeds the invoice's outstanding balance of ₦{}",
+            data.amount, outstanding
+        ));
+    }
+
+    Ok(())
+}
+
+/// Bill a sponsor for the outstanding fees of its students for a term.
+pub fn create_sponsor_invoice(
+    sponsor_id: String,
+    academic_year: String,
+    term: String,
+) -> Result<SponsorInvoiceSummary, String> {
+    let caller = caller();
+    if !caller_has_any_role(&caller, &[Role::SuperAdmin, Role::Bursar]) {
+        return Err("SECURITY: Only a bursar or administrator can invoice sponsors".to_string());
+    }
+    if !is_valid_academic_year(&academic_year) {
+        return Err("Invalid academic year. Must be in format YYYY/YYYY".to_string());
+    }
+    if !TERMS.contains(&term.as_str()) {
+        return Err(format!("Invalid term '{}'. Must be one of: {}", term, TERMS.join(", ")));
+    }
+
+    let (_, sponsor) = get_doc_data::<SponsorData>(SPONSORS_COLLECTION, &sponsor_id)?
+        .ok_or_else(|| format!("Sponsor '{}' not found", sponsor_id))?;
+    if !sponsor.is_active {
+        return Err(format!("Sponsor {} is inactive", sponsor.name));
+    }
+
+    let open_invoice = list_doc_data::<SponsorInvoiceData>(SPONSOR_INVOICES_COLLECTION, None)?
+        .into_iter()
+        .any(|(_, _, i)| {
+            i.sponsor_id == sponsor_id && i.academic_year == academic_year && i.term == term && i.status != "paid"
+        });
+    if open_invoice {
+        return Err(format!(
+            "{} already has an unpaid invoice for the {} {} term",
+            sponsor.name, academic_year, term
+        ));
+    }
+
+    // Invoice lines follow the sponsor's student order
+    let assignments = list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)?;
+    let (year_ref, term_ref) = (&academic_year, &term);
+    let lines: Vec<SponsorInvoiceLine> = sponsor
+        .student_ids
+        .iter()
+        .flat_map(|student_id| {
+            assignments.iter().filter(move |(_, _, a)| {
+                &a.student_id == student_id
+                    && &a.academic_year == year_ref
+                    && &a.term == term_ref
+                    && a.balance.is_positive()
+            })
+        })
+        .map(|(key, _, a)| SponsorInvoiceLine {
+            student_id: a.student_id.clone(),
+            student_name: a.student_name.clone(),
+            fee_assignment_id: key.clone(),
+            amount: a.balance,
+            amount_paid: Money::ZERO,
+        })
+        .collect();
+    if lines.is_empty() {
+        return Err(format!(
+            "None of {}'s students owe fees for the {} {} term",
+            sponsor.name, academic_year, term
+        ));
+    }
+
+    let year = &current_date()[0..4];
+    let invoice_number = format!("SPI-{}-{:06}", year, next_number(&format!("sponsor-invoices-{}", year))?);
+    let invoice = SponsorInvoiceData {
+        sponsor_id: sponsor_id.clone(),
+        invoice_number: invoice_number.clone(),
+        academic_year,
+        term,
+        total: lines.iter().map(|l| l.amount).sum(),
+        lines,
+        amount_paid: Money::ZERO,
+        unapplied: Money::ZERO,
+        status: "issued".to_string(),
+        issued_by: caller.to_text(),
+        issued_at: ic_cdk::api::time(),
+    };
+    set_doc_data(
+        SPONSOR_INVOICES_COLLECTION,
+        &invoice_number,
+        &invoice,
+        Some(format!("sponsor_id={};", sponsor_id)),
+        None,
+    )?;
+
+    Ok(SponsorInvoiceSummary {
+        invoice_id: invoice_number.clone(),
+        invoice_number,
+        students: invoice.lines.len() as u32,
+        total: invoice.total.naira(),
+    })
+}
+
+/// Called from the `sponsor_payments` on-set hook: when a sponsor payment is confirmed,
+/// spread it over the invoice's students and apply each share to their fee assignment.
+pub fn on_sponsor_payment_saved(
+    key: &str,
+    before: Option<&SponsorPaymentData>,
+    payment: &SponsorPaymentData,
+) -> Result<(), String> {
+    let was_confirmed = before.map(|b| b.status == "confirmed").unwrap_or(false);
+    if payment.status != "confirmed" || was_confirmed {
+        return Ok(());
+    }
+
+    let (doc, mut invoice) = get_doc_data::<SponsorInvoiceData>(SPONSOR_INVOICES_COLLECTION, &payment.invoice_id)?
+        .ok_or_else(|| format!("Sponsor invoice '{}' not found", payment.invoice_id))?;
+
+    let priority = school_settings()?.allocation_priority;
+    let mut remaining = payment.amount;
+    for line in invoice.lines.iter_mut() {
+        if !remaining.is_positive() {
+            break;
+        }
+        let (_, assignment) =
+            match get_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", &line.fee_assignment_id)? {
+                Some(found) => found,
+                None => continue,
+            };
+        let due = (line.amount - line.amount_paid).min(assignment.balance);
+        if !due.is_positive() {
+            continue;
+        }
+
+        let share = remaining.min(due);
+        let allocations = allocate_to_fee_items(&assignment, share, &priority);
+        apply_payment_to_assignment(
+            &line.fee_assignment_id,
+            &format!("{}:{}", key, line.student_id),
+            share,
+            &allocations,
+        )?;
+        line.amount_paid += share;
+        remaining -= share;
+    }
+
+    invoice.amount_paid += payment.amount;
+    invoice.unapplied += remaining;
+    invoice.status = if invoice.amount_paid >= invoice.total { "paid" } else { "partial" }.to_string();
+    set_doc_data(SPONSOR_INVOICES_COLLECTION, &payment.invoice_id, &invoice, doc.description, doc.version)?;
+
+    Ok(())
+}
+
+/// A sponsor's invoices and confirmed payments, oldest first, with the balance owed.
+pub fn get_sponsor_statement(sponsor_id: String) -> Result<SponsorStatement, String> {
+    let (_, sponsor) = get_doc_data::<SponsorData>(SPONSORS_COLLECTION, &sponsor_id)?
+        .ok_or_else(|| format!("Sponsor '{}' not found", sponsor_id))?;
+
+    let mut invoices: Vec<(u64, SponsorStatementInvoice)> =
+        list_doc_data::<SponsorInvoiceData>(SPONSOR_INVOICES_COLLECTION, None)?
+            .into_iter()
+            .filter(|(_, _, i)| i.sponsor_id == sponsor_id)
+            .map(|(key, _, i)| {
+                (
+                    i.issued_at,
+                    SponsorStatementInvoice {
+                        invoice_id: key,
+                        invoice_number: i.invoice_number,
+                        academic_year: i.academic_year,
+                        term: i.term,
+                        issued_on: date_from_timestamp(i.issued_at),
+                        total: i.total.naira(),
+                        amount_paid: i.amount_paid.naira(),
+                        balance: (i.total - i.amount_paid).naira(),
+                        status: i.status,
+                    },
+                )
+            })
+            .collect();
+    invoices.sort_by_key(|(issued_at, _)| *issued_at);
+    let invoices: Vec<SponsorStatementInvoice> = invoices.into_iter().map(|(_, i)| i).collect();
+
+    let mut payments: Vec<SponsorStatementPayment> =
+        list_doc_data::<SponsorPaymentData>(SPONSOR_PAYMENTS_COLLECTION, None)?
+            .into_iter()
+            .filter(|(_, _, p)| p.sponsor_id == sponsor_id && p.status == "confirmed")
+            .map(|(key, _, p)| SponsorStatementPayment {
+                payment_id: key,
+                invoice_id: p.invoice_id,
+                payment_date: p.payment_date,
+                reference: p.reference,
+                amount: p.amount.naira(),
+            })
+            .collect();
+    payments.sort_by(|a, b| a.payment_date.cmp(&b.payment_date));
+
+    let total_invoiced: Money = invoices.iter().map(|i| Money::from_naira(i.total)).sum();
+    let total_paid: Money = payments.iter().map(|p| Money::from_naira(p.amount)).sum();
+
+    Ok(SponsorStatement {
+        sponsor_id,
+        sponsor_name: sponsor.name,
+        invoices,
+        payments,
+        total_invoiced: total_invoiced.naira(),
+        total_paid: total_paid.naira(),
+        balance: (total_invoiced - total_paid).naira(),
+    })
+}
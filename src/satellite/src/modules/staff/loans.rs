@@ -0,0 +1,337 @@
+//! Staff loans and salary advances, repaid through payroll deductions.
+//!
+//! A loan (`staff_loans`) moves through requested → approved → disbursed → repaying →
+//! closed. Once disbursed, every salary payment for a period from the repayment start
+//! must carry the loan's deduction (named by `deductionName`) of one installment, or what
+//! remains of the principal when less. The amount repaid and the outstanding balance
+//! are maintained by the satellite from paid salaries: the loan moves to repaying with the
+//! first repayment and closes when nothing is outstanding.
+
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::{PaymentDeductionItem, SalaryPaymentData, StaffMemberData};
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::utils::validation_utils::*;
+
+pub const STAFF_LOANS_COLLECTION: &str = "staff_loans";
+
+const LOAN_TYPES: [&str; 2] = ["loan", "advance"];
+const LOAN_STATUSES: [&str; 5] = ["requested", "approved", "disbursed", "repaying", "closed"];
+// Statuses in which installments are deducted
+const IN_REPAYMENT: [&str; 2] = ["disbursed", "repaying"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StaffLoanData {
+    pub staff_id: String,
+    #[serde(rename = "type")]
+    pub loan_type: String,
+    pub principal: Money,
+    pub installment_amount: Money,
+    pub number_of_installments: u32,
+    // First payroll period (YYYY-MM) the installment is deducted
+    pub repayment_start_period: String,
+    pub deduction_name: String,
+    #[serde(default)]
+    pub amount_repaid: Money,
+    pub outstanding_balance: Money,
+    pub status: String,
+    pub purpose: Option<String>,
+    pub requested_by: String,
+    #[serde(default)]
+    pub approved_by: Option<String>,
+    #[serde(default)]
+    pub disbursement_reference: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Staff Loan Validation
+///
+/// Checks:
+/// - Staff member exists and is active when the loan is requested
+/// - Positive principal and installment; the schedule repays the principal without
+///   surplus installments
+/// - Outstanding balance is the principal less the amount repaid, which only the
+///   satellite moves
+/// - Status follows requested → approved → disbursed → repaying → closed; approval and
+///   disbursement by the bursar, never by the requester
+/// - Terms are fixed once approved
+pub fn validate_staff_loan_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: StaffLoanData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid staff loan data format: {}", e))?;
+
+    if !LOAN_TYPES.contains(&data.loan_type.as_str()) {
+        return Err(format!("Invalid loan type '{}'. Must be one of: {}", data.loan_type, LOAN_TYPES.join(", ")));
+    }
+    if !data.principal.is_positive() {
+        return Err("Loan principal must be greater than zero".to_string());
+    }
+    if !data.installment_amount.is_positive() || data.installment_amount > data.principal {
+        return Err("installmentAmount must be greater than zero and not exceed the principal".to_string());
+    }
+    if data.number_of_installments == 0 {
+        return Err("numberOfInstallments must be at least 1".to_string());
+    }
+    let scheduled = Money::from_kobo(data.installment_amount.kobo() * data.number_of_installments as i64);
+    let before_last = scheduled - data.installment_amount;
+    if scheduled < data.principal || before_last >= data.principal {
+        return Err(format!(
+            "{} installments of ₦{} do not repay a principal of ₦{}",
+            data.number_of_installments, data.installment_amount, data.principal
+        ));
+    }
+    if !is_valid_period(&data.repayment_start_period) {
+        return Err("repaymentStartPeriod must be in format YYYY-MM".to_string());
+    }
+    if data.deduction_name.trim().is_empty() {
+        return Err("deductionName is required to match salary deductions".to_string());
+    }
+    if data.amount_repaid.is_negative() || data.amount_repaid > data.principal {
+        return Err("amountRepaid must be between 0 and the principal".to_string());
+    }
+    if data.outstanding_balance != data.principal - data.amount_repaid {
+        return Err(format!(
+            "outstandingBalance (₦{}) must equal principal less amount repaid (₦{})",
+            data.outstanding_balance,
+            data.principal - data.amount_repaid
+        ));
+    }
+    if !LOAN_STATUSES.contains(&data.status.as_str()) {
+        return Err(format!("Invalid loan status '{}'. Must be one of: {}", data.status, LOAN_STATUSES.join(", ")));
+    }
+    if data.status == "closed" && !data.outstanding_balance.is_zero() {
+        return Err("A loan can only be closed once nothing is outstanding".to_string());
+    }
+
+    let is_satellite = is_satellite_caller(&context.caller);
+    let caller = context.caller.to_text();
+    match context.data.data.current {
+        Some(ref before_doc) => {
+            let before: StaffLoanData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous staff loan data: {}", e))?;
+
+            if !is_satellite && before.amount_repaid != data.amount_repaid {
+                return Err("AUDIT: amountRepaid is maintained automatically from paid salaries".to_string());
+            }
+            let terms_changed = before.staff_id != data.staff_id
+                || before.loan_type != data.loan_type
+                || before.principal != data.principal
+                || before.installment_amount != data.installment_amount
+                || before.number_of_installments != data.number_of_installments
+                || before.repayment_start_period != data.repayment_start_period
+                || before.deduction_name != data.deduction_name;
+            if terms_changed && before.status != "requested" {
+                return Err("Loan terms cannot be changed once approved".to_string());
+            }
+
+            if before.status != data.status {
+                let allowed = match (before.status.as_str(), data.status.as_str()) {
+                    ("requested", "approved") | ("approved", "disbursed") => !is_satellite,
+                    ("disbursed", "repaying") | ("repaying", "closed") | ("disbursed", "closed") => is_satellite,
+                    _ => false,
+                };
+                if !allowed {
+                    return Err(format!(
+                        "Invalid loan status transition from '{}' to '{}'",
+                        before.status, data.status
+                    ));
+                }
+            }
+            if !is_satellite && before.status == "requested" && data.status == "approved" {
+                if !caller_has_any_role(&context.caller, &[Role::Bursar]) {
+                    return Err("SECURITY: Only the bursar can approve staff loans".to_string());
+                }
+                if data.requested_by == caller {
+                    return Err("SECURITY: A loan cannot be approved by the person who requested it".to_string());
+                }
+                if data.approved_by.as_deref() != Some(caller.as_str()) {
+                    return Err("approvedBy must be the principal approving the loan".to_string());
+                }
+            }
+            if !is_satellite && before.status == "approved" && data.status == "disbursed" {
+                if !caller_has_any_role(&context.caller, &[Role::Bursar]) {
+                    return Err("SECURITY: Only the bursar can disburse staff loans".to_string());
+                }
+                if data.disbursement_reference.as_ref().map(|r| r.trim().is_empty()).unwrap_or(true) {
+                    return Err("Disbursed loans must include the disbursement reference".to_string());
+                }
+            }
+        }
+        None => {
+            if data.status != "requested" {
+                return Err("New staff loans must have status 'requested'".to_string());
+            }
+            if !data.amount_repaid.is_zero() {
+                return Err("New staff loans must start with amountRepaid of 0".to_string());
+            }
+            if data.requested_by != caller {
+                return Err("requestedBy must be the principal requesting the loan".to_string());
+            }
+            let (_, staff) = get_doc_data::<StaffMemberData>("staff", &data.staff_id)?
+                .ok_or_else(|| format!("Staff member '{}' not found", data.staff_id))?;
+            if !staff.is_active {
+                return Err("Loans can only be requested for active staff".to_string());
+            }
+            let open_loan = list_doc_data::<StaffLoanData>(STAFF_LOANS_COLLECTION, None)?
+                .into_iter()
+                .any(|(key, _, other)| {
+                    key != context.data.key
+                        && other.staff_id == data.staff_id
+                        && other.status != "closed"
+                        && other.deduction_name.eq_ignore_ascii_case(&data.deduction_name)
+                });
+            if open_loan {
+                return Err(format!(
+                    "Staff member already has an open loan deducted as '{}'",
+                    data.deduction_name
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Loans can be withdrawn while requested; approved loans stay on record.
+pub fn validate_staff_loan_delete(key: &str) -> Result<(), String> {
+    match get_doc_data::<StaffLoanData>(STAFF_LOANS_COLLECTION, key)? {
+        Some((_, loan)) if loan.status != "requested" => {
+            Err(format!("AUDIT: A {} loan cannot be deleted", loan.status))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Every loan in repayment for the staff member must appear in the salary deductions with
+/// its installment (or the remainder of the principal), and loans not in repayment must
+/// not be deducted.
+pub fn validate_salary_loan_deductions(salary_key: &str, salary: &SalaryPaymentData) -> Result<(), String> {
+    let loans = list_doc_data::<StaffLoanData>(STAFF_LOANS_COLLECTION, None)?;
+
+    for (_, _, loan) in loans.iter().filter(|(_, _, l)| l.staff_id == salary.staff_id) {
+        let applied = salary
+            .deductions
+            .iter()
+            .find(|d| d.name.eq_ignore_ascii_case(&loan.deduction_name));
+        let expected = expected_installment(salary_key, salary, loan)?;
+
+        match applied {
+            Some(deduction) if !expected.is_positive() => {
+                // A closed loan's last installment stays on the salary that repaid it
+                if loan.status == "closed" && salary.status == "paid" {
+                    continue;
+                }
+                return Err(format!(
+                    "Deduction '{}' must not be applied: the {} is {}",
+                    deduction.name, loan.loan_type, loan.status
+                ));
+            }
+            Some(deduction) if deduction.amount != expected => {
+                return Err(format!(
+                    "Staff {} repayment '{}' must be exactly ₦{}, found ₦{}",
+                    loan.loan_type, loan.deduction_name, expected, deduction.amount
+                ));
+            }
+            None if expected.is_positive() => {
+                return Err(format!(
+                    "Staff {} repayment '{}' of ₦{} is missing from the deductions",
+                    loan.loan_type, loan.deduction_name, expected
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Loan repayments a salary payment must carry, for building salaries server-side.
+pub fn due_loan_deductions(salary_key: &str, salary: &SalaryPaymentData) -> Result<Vec<PaymentDeductionItem>, String> {
+    let loans = list_doc_data::<StaffLoanData>(STAFF_LOANS_COLLECTION, None)?;
+
+    let mut deductions = Vec::new();
+    for (_, _, loan) in loans.iter().filter(|(_, _, l)| l.staff_id == salary.staff_id) {
+        let amount = expected_installment(salary_key, salary, loan)?;
+        if amount.is_positive() {
+            deductions.push(PaymentDeductionItem {
+                name: loan.deduction_name.clone(),
+                amount,
+                is_statutory: false,
+            });
+        }
+    }
+    Ok(deductions)
+}
+
+/// Called once a salary is paid: recomputes the amount repaid on each of the staff
+/// member's loans in repayment, moving them to repaying and closing them when repaid.
+pub fn sync_loans_with_salary_payment(salary: &SalaryPaymentData) -> Result<(), String> {
+    if salary.status != "paid" {
+        return Ok(());
+    }
+
+    let loans = list_doc_data::<StaffLoanData>(STAFF_LOANS_COLLECTION, None)?;
+    let salaries = list_doc_data::<SalaryPaymentData>("salary_payments", None)?;
+
+    for (key, doc, mut loan) in loans
+        .into_iter()
+        .filter(|(_, _, l)| l.staff_id == salary.staff_id && IN_REPAYMENT.contains(&l.status.as_str()))
+    {
+        let repaid: Money = salaries
+            .iter()
+            .filter(|(_, _, s)| s.staff_id == loan.staff_id && s.status == "paid")
+            .filter(|(_, _, s)| salary_period(s) >= loan.repayment_start_period.as_str())
+            .flat_map(|(_, _, s)| s.deductions.iter())
+            .filter(|d| d.name.eq_ignore_ascii_case(&loan.deduction_name))
+            .map(|d| d.amount)
+            .sum();
+        let repaid = repaid.min(loan.principal);
+        if repaid == loan.amount_repaid {
+            continue;
+        }
+
+        loan.amount_repaid = repaid;
+        loan.outstanding_balance = loan.principal - repaid;
+        loan.status = if loan.outstanding_balance.is_zero() { "closed" } else { "repaying" }.to_string();
+        loan.updated_at = ic_cdk::api::time();
+
+        set_doc_data(
+            STAFF_LOANS_COLLECTION,
+            &key,
+            &loan,
+            Some(format!("staff_id={};status={};", loan.staff_id, loan.status)),
+            doc.version,
+        )?;
+    }
+
+    Ok(())
+}
+
+// Installment due on this salary, or what remains of the principal
+fn expected_installment(salary_key: &str, salary: &SalaryPaymentData, loan: &StaffLoanData) -> Result<Money, String> {
+    if !IN_REPAYMENT.contains(&loan.status.as_str()) || salary_period(salary) < loan.repayment_start_period.as_str() {
+        return Ok(Money::ZERO);
+    }
+    let repaid_elsewhere: Money = list_doc_data::<SalaryPaymentData>("salary_payments", None)?
+        .iter()
+        .filter(|(key, _, s)| key != salary_key && s.staff_id == salary.staff_id && s.status != "failed")
+        .filter(|(_, _, s)| salary_period(s) >= loan.repayment_start_period.as_str())
+        .flat_map(|(_, _, s)| s.deductions.iter())
+        .filter(|d| d.name.eq_ignore_ascii_case(&loan.deduction_name))
+        .map(|d| d.amount)
+        .sum();
+    Ok((loan.principal - repaid_elsewhere).clamp(Money::ZERO, loan.installment_amount))
+}
+
+// Payroll period (YYYY-MM) of a salary payment
+fn salary_period(salary: &SalaryPaymentData) -> &str {
+    salary.payment_period_start.get(..7).unwrap_or(&salary.payment_period_start)
+}
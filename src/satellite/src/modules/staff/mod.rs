@@ -1,10 +1,27 @@
-use junobuild_satellite::{AssertSetDocContext, list_docs};
+pub mod loans;
+pub mod payslips;
+pub mod tax;
+
+use junobuild_satellite::{info_with_data, AssertSetDocContext, list_docs};
 use junobuild_shared::types::list::{ListParams, ListMatcher};
 use junobuild_utils::decode_doc_data;
 use serde::{Deserialize, Serialize};
+use super::documents::store_payslip_pdf;
+use super::duty_claims::{sync_claims_with_salary_payment, validate_salary_claim_allowances};
+use super::garnishments::{sync_court_orders_with_salary_payment, validate_salary_court_order_deductions};
+use loans::{sync_loans_with_salary_payment, validate_salary_loan_deductions};
+use super::roles::{caller_has_any_role, require_role, Role};
+use super::rules::check_rule;
+use super::utils::doc_utils::{deny_if_referenced, get_doc_data, is_satellite_caller, referencing_keys};
+use super::utils::identity::{validate_caller_identity, validate_not_self_approval};
+use super::utils::money::Money;
 use super::utils::validation_utils::*;
+use super::verification::net_salary;
 use std::collections::HashMap;
 
+// Rounding differences allowed between a salary payment and the staff record
+const BASIC_SALARY_TOLERANCE: Money = Money::from_kobo(100);
+
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StaffMemberData {
@@ -19,20 +36,45 @@ pub struct StaffMemberData {
     pub department: Option<String>,
     pub employment_type: String,
     pub employment_date: String,
-    pub basic_salary: f64,
+    pub basic_salary: Money,
     pub allowances: Option<Vec<StaffAllowance>>,
     pub bank_name: Option<String>,
     pub account_number: Option<String>,
     pub is_active: bool,
+    #[serde(default)]
+    pub salary_hold: Option<SalaryHold>,
     pub created_at: u64,
     pub updated_at: u64,
 }
 
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SalaryHold {
+    pub reason: String,
+    pub placed_by: String,
+    pub placed_at: u64,
+    pub released_by: Option<String>,
+    pub released_at: Option<u64>,
+    pub release_notes: Option<String>,
+}
+
+impl SalaryHold {
+    pub fn is_active(&self) -> bool {
+        self.released_at.is_none()
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StaffAllowance {
     pub name: String,
-    pub amount: f64,
+    pub amount: Money,
+    #[serde(default = "default_taxable")]
+    pub is_taxable: bool,
+}
+
+fn default_taxable() -> bool {
+    true
 }
 
 #[derive(Deserialize, Serialize)]
@@ -44,33 +86,55 @@ pub struct SalaryPaymentData {
     pub payment_date: String,
     pub payment_period_start: String,
     pub payment_period_end: String,
-    pub basic_salary: f64,
+    pub basic_salary: Money,
     pub allowances: Vec<PaymentAllowanceItem>,
     pub deductions: Vec<PaymentDeductionItem>,
-    pub net_salary: f64,
+    pub net_salary: Money,
     pub payment_method: String,
     pub reference: String,
     pub status: String,
     pub notes: Option<String>,
     pub processed_by: String,
     pub processed_at: u64,
+    #[serde(default)]
+    pub bank_reference: Option<String>,
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+    // Basic salary differs from the staff record; must be approved before payment
+    #[serde(default)]
+    pub salary_discrepancy: Option<SalaryDiscrepancy>,
     pub created_at: u64,
     pub updated_at: u64,
+    // Fields owned by the frontend, preserved when the satellite rewrites the document
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SalaryDiscrepancy {
+    pub reason: String,
+    #[serde(default)]
+    pub approved_by: Option<String>,
+    #[serde(default)]
+    pub approved_at: Option<u64>,
 }
 
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PaymentAllowanceItem {
     pub name: String,
-    pub amount: f64,
+    pub amount: Money,
     pub is_taxable: bool,
+    #[serde(default)]
+    pub claim_id: Option<String>,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct PaymentDeductionItem {
     pub name: String,
-    pub amount: f64,
+    pub amount: Money,
     pub is_statutory: bool,
 }
 
@@ -84,9 +148,10 @@ pub struct PaymentDeductionItem {
         validate_staff_employment_details(&staff_data)?;
         validate_staff_salary_and_allowances(&staff_data)?;
         validate_staff_contact_information(&staff_data)?;
-        validate_staff_banking_details(&staff_data)?;
+        validate_staff_banking_details(context, &staff_data)?;
         validate_staff_number_uniqueness(context, &staff_data)?;
         validate_staff_business_rules(&staff_data)?;
+        validate_staff_salary_hold(context, &staff_data)?;
         
         Ok(())
     }
@@ -95,14 +160,27 @@ pub struct PaymentDeductionItem {
         let salary_data: SalaryPaymentData = decode_doc_data(&context.data.data.proposed.data)
             .map_err(|e| format!("Invalid salary payment data format: {}", e))?;
 
+        // processedBy must be the caller
+        validate_caller_identity(context)?;
+
         // Core salary payment validation
         validate_salary_core_fields(&salary_data)?;
+        if context.data.data.current.is_none() {
+            // Only HR creates salary payments
+            require_role(&context.caller, Role::Hr)?;
+        }
         validate_salary_amounts_and_calculations(&salary_data)?;
+        validate_salary_statutory_deductions(context, &salary_data)?;
         validate_salary_payment_period(&salary_data)?;
         validate_salary_payment_method(&salary_data)?;
         validate_salary_status_transitions(context, &salary_data)?;
+        validate_salary_not_on_hold(&salary_data)?;
+        validate_salary_staff_record(context, &salary_data)?;
         validate_salary_reference_uniqueness(context, &salary_data)?;
         validate_salary_business_rules(context, &salary_data)?;
+        validate_salary_claim_allowances(&context.data.key, &salary_data)?;
+        validate_salary_court_order_deductions(context, &salary_data)?;
+        validate_salary_loan_deductions(&context.data.key, &salary_data)?;
         
         Ok(())
     }
@@ -110,7 +188,7 @@ pub struct PaymentDeductionItem {
     // Staff core field validation
     fn validate_staff_core_fields(staff: &StaffMemberData) -> Result<(), String> {
         // Minimal core validation - field-level checks moved to frontend
-        if staff.basic_salary <= 0.0 {
+        if !staff.basic_salary.is_positive() {
             return Err("Basic salary must be greater than zero".to_string());
         }
         Ok(())
@@ -184,9 +262,20 @@ pub struct PaymentDeductionItem {
     }
 
     // Banking details validation
-    fn validate_staff_banking_details(_staff: &StaffMemberData) -> Result<(), String> {
-        // Moved to frontend
-        Ok(())
+    // Active staff need bank details for payroll files (rolled out as `staff_bank_details`)
+    fn validate_staff_banking_details(context: &AssertSetDocContext, staff: &StaffMemberData) -> Result<(), String> {
+        if !staff.is_active {
+            return Ok(());
+        }
+        check_rule(context, "staff_bank_details", || {
+            if staff.bank_name.as_ref().map(|b| b.trim().is_empty()).unwrap_or(true) {
+                return Err("Active staff must have a bank name".to_string());
+            }
+            match staff.account_number {
+                Some(ref account) if is_valid_account_number(account) => Ok(()),
+                _ => Err("Active staff must have a 10-digit bank account number".to_string()),
+            }
+        })
     }
 
     // Staff number uniqueness validation
@@ -224,10 +313,164 @@ pub struct PaymentDeductionItem {
         Ok(())
     }
 
+    // Salary hold validation: placing requires a payroll role, releasing requires bursar/admin
+    fn validate_staff_salary_hold(
+        context: &AssertSetDocContext,
+        staff: &StaffMemberData
+    ) -> Result<(), String> {
+        let before_hold = match context.data.data.current {
+            Some(ref before_doc) => {
+                let before_staff: StaffMemberData = decode_doc_data(&before_doc.data)
+                    .map_err(|e| format!("Invalid previous staff data: {}", e))?;
+                before_staff.salary_hold
+            }
+            None => None,
+        };
+
+        if before_hold == staff.salary_hold {
+            return Ok(());
+        }
+
+        let caller = context.caller.to_text();
+
+        match (&before_hold, &staff.salary_hold) {
+            (Some(before), None) if before.is_active() => {
+                Err("An active salary hold must be released, not removed".to_string())
+            }
+            (_, None) => Ok(()),
+            (Some(before), Some(after)) if before.placed_at == after.placed_at => {
+                // Same hold: only a release is allowed
+                if after.reason != before.reason || after.placed_by != before.placed_by {
+                    return Err("Salary hold reason and placedBy cannot be changed".to_string());
+                }
+                if !before.is_active() {
+                    return Err("A released salary hold cannot be modified".to_string());
+                }
+                if after.is_active() {
+                    return Ok(());
+                }
+                if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar]) {
+                    return Err("SECURITY: Only a bursar or administrator can release a salary hold".to_string());
+                }
+                if after.released_by.as_deref() != Some(caller.as_str()) {
+                    return Err("releasedBy must be the principal releasing the hold".to_string());
+                }
+                if after.release_notes.as_ref().map(|n| n.trim().is_empty()).unwrap_or(true) {
+                    return Err("Releasing a salary hold requires release notes".to_string());
+                }
+                Ok(())
+            }
+            (before, Some(after)) => {
+                // A new hold is being placed
+                if before.as_ref().map(|b| b.is_active()).unwrap_or(false) {
+                    return Err("Staff member already has an active salary hold".to_string());
+                }
+                if !caller_has_any_role(&context.caller, &[Role::SuperAdmin, Role::Bursar, Role::Accountant]) {
+                    return Err("SECURITY: Only payroll officers can place a salary hold".to_string());
+                }
+                if after.reason.trim().len() < 10 {
+                    return Err("Salary hold reason must be at least 10 characters".to_string());
+                }
+                if after.placed_by != caller {
+                    return Err("placedBy must be the principal placing the hold".to_string());
+                }
+                if !after.is_active() || after.released_by.is_some() {
+                    return Err("A new salary hold cannot already be released".to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn validate_salary_not_on_hold(salary: &SalaryPaymentData) -> Result<(), String> {
+        if salary.status != "approved" && salary.status != "paid" {
+            return Ok(());
+        }
+
+        if let Some((_, staff)) = get_doc_data::<StaffMemberData>("staff", &salary.staff_id)? {
+            if let Some(ref hold) = staff.salary_hold {
+                if hold.is_active() {
+                    return Err(format!(
+                        "Salary for staff {} is on hold ({}). The hold must be released before approval",
+                        salary.staff_number, hold.reason
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // The payment is for an existing staff member, active when the salary is raised and
+    // approved, at the basic salary on record unless the difference is flagged and approved
+    fn validate_salary_staff_record(context: &AssertSetDocContext, salary: &SalaryPaymentData) -> Result<(), String> {
+        let (_, staff) = get_doc_data::<StaffMemberData>("staff", &salary.staff_id)?
+            .ok_or_else(|| format!("Staff member '{}' not found", salary.staff_id))?;
+        if staff.staff_number != salary.staff_number {
+            return Err(format!(
+                "Staff number '{}' does not match the staff record ({})",
+                salary.staff_number, staff.staff_number
+            ));
+        }
+
+        let before: Option<SalaryPaymentData> = match context.data.data.current {
+            Some(ref doc) => Some(
+                decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous salary data: {}", e))?,
+            ),
+            None => None,
+        };
+        let approving = salary.status == "approved" && before.as_ref().map(|b| b.status != "approved").unwrap_or(true);
+        if (before.is_none() || approving) && !staff.is_active {
+            return Err(format!("Staff {} is not active", salary.staff_number));
+        }
+
+        let difference = salary.basic_salary - staff.basic_salary;
+        let discrepancy = difference > BASIC_SALARY_TOLERANCE || -difference > BASIC_SALARY_TOLERANCE;
+        let Some(ref flag) = salary.salary_discrepancy else {
+            if discrepancy {
+                return Err(format!(
+                    "Basic salary ₦{} differs from the staff record (₦{}). Flag the discrepancy with a reason for approval",
+                    salary.basic_salary, staff.basic_salary
+                ));
+            }
+            return Ok(());
+        };
+        if flag.reason.trim().len() < 10 {
+            return Err("Salary discrepancy reason must be at least 10 characters".to_string());
+        }
+
+        let before_flag = before.as_ref().and_then(|b| b.salary_discrepancy.as_ref());
+        if flag.approved_by.is_some() && before_flag.and_then(|f| f.approved_by.as_ref()).is_none() {
+            let caller = context.caller.to_text();
+            if !caller_has_any_role(&context.caller, &[Role::Bursar]) {
+                return Err("SECURITY: Only the bursar can approve salary discrepancies".to_string());
+            }
+            if flag.approved_by.as_deref() != Some(caller.as_str()) {
+                return Err("Salary discrepancy approvedBy must be the principal approving it".to_string());
+            }
+            if salary.processed_by == caller {
+                return Err("SECURITY: A salary discrepancy cannot be approved by the person who processed the salary".to_string());
+            }
+            if flag.approved_at.is_none() {
+                return Err("Salary discrepancy approval must include approvedAt".to_string());
+            }
+        } else if before_flag.is_some_and(|f| f.approved_by.is_some()) && before_flag != Some(flag) {
+            return Err("AUDIT: An approved salary discrepancy cannot be changed".to_string());
+        }
+        if discrepancy && salary.status != "pending" && flag.approved_by.is_none() {
+            return Err(format!(
+                "Basic salary ₦{} differs from the staff record (₦{}); the discrepancy must be approved first",
+                salary.basic_salary, staff.basic_salary
+            ));
+        }
+
+        Ok(())
+    }
+
     // Salary payment validation functions
     fn validate_salary_core_fields(salary: &SalaryPaymentData) -> Result<(), String> {
         // Minimal validation - field checks moved to frontend
-        if salary.basic_salary <= 0.0 {
+        if !salary.basic_salary.is_positive() {
             return Err("Basic salary must be greater than zero".to_string());
         }
         Ok(())
@@ -235,7 +478,7 @@ pub struct PaymentDeductionItem {
 
     fn validate_salary_amounts_and_calculations(salary: &SalaryPaymentData) -> Result<(), String> {
         // Core calculation validation
-        let mut calculated_allowances_total = 0.0;
+        let mut calculated_allowances_total = Money::ZERO;
         let mut allowance_names = std::collections::HashSet::new();
         
         for allowance in salary.allowances.iter() {
@@ -247,7 +490,7 @@ pub struct PaymentDeductionItem {
             calculated_allowances_total += allowance.amount;
         }
         
-        let mut calculated_deductions_total = 0.0;
+        let mut calculated_deductions_total = Money::ZERO;
         let mut deduction_names = std::collections::HashSet::new();
         
         for deduction in salary.deductions.iter() {
@@ -260,11 +503,14 @@ pub struct PaymentDeductionItem {
         }
         
         // Core: validate calculation correctness
-        let expected_gross = salary.basic_salary + calculated_allowances_total;
-        let expected_net = expected_gross - calculated_deductions_total;
-        if (salary.net_salary - expected_net).abs() > 0.01 {
+        let expected_net = net_salary(
+            salary.basic_salary,
+            &[calculated_allowances_total],
+            &[calculated_deductions_total],
+        );
+        if salary.net_salary != expected_net {
             return Err(format!(
-                "Net salary (₦{:.2}) doesn't match basic + allowances - deductions (₦{:.2})",
+                "Net salary (₦{}) doesn't match basic + allowances - deductions (₦{})",
                 salary.net_salary, expected_net
             ));
         }
@@ -272,6 +518,25 @@ pub struct PaymentDeductionItem {
         Ok(())
     }
 
+    // PAYE, pension and NHF are checked when a salary is created or its pay changes
+    fn validate_salary_statutory_deductions(context: &AssertSetDocContext, salary: &SalaryPaymentData) -> Result<(), String> {
+        if let Some(ref before_doc) = context.data.data.current {
+            let before: SalaryPaymentData = decode_doc_data(&before_doc.data)
+                .map_err(|e| format!("Invalid previous salary data: {}", e))?;
+            let pay = |s: &SalaryPaymentData| {
+                (
+                    s.basic_salary,
+                    s.allowances.iter().map(|a| a.amount).sum::<Money>(),
+                    s.deductions.iter().map(|d| (d.name.clone(), d.amount)).collect::<Vec<_>>(),
+                )
+            };
+            if pay(&before) == pay(salary) {
+                return Ok(());
+            }
+        }
+        tax::validate_statutory_deductions(salary)
+    }
+
     fn validate_salary_payment_period(salary: &SalaryPaymentData) -> Result<(), String> {
         // Validate payment date
         if !is_valid_date_format(&salary.payment_date) {
@@ -285,18 +550,15 @@ pub struct PaymentDeductionItem {
         if !is_valid_date_format(&salary.payment_period_start) || !is_valid_date_format(&salary.payment_period_end) {
             return Err("Payment period start and end must be valid dates (YYYY-MM-DD)".to_string());
         }
-        let (sy, sm, sd) = parse_date(&salary.payment_period_start).map_err(|_| "Invalid payment_period_start".to_string())?;
-        let (ey, em, ed) = parse_date(&salary.payment_period_end).map_err(|_| "Invalid payment_period_end".to_string())?;
-        let start_ts = date_to_timestamp(sy, sm, sd);
-        let end_ts = date_to_timestamp(ey, em, ed);
-        if end_ts < start_ts {
+        let start = calendar_date(&salary.payment_period_start).ok_or("Invalid payment_period_start".to_string())?;
+        let end = calendar_date(&salary.payment_period_end).ok_or("Invalid payment_period_end".to_string())?;
+        if end < start {
             return Err("Payment period end cannot be before start".to_string());
         }
         
         // Ensure payment date falls within or after period start (soft check)
-        let (py, pm, pd) = parse_date(&salary.payment_date).map_err(|_| "Invalid payment_date".to_string())?;
-        let pay_ts = date_to_timestamp(py, pm, pd);
-        if pay_ts < start_ts {
+        let pay = calendar_date(&salary.payment_date).ok_or("Invalid payment_date".to_string())?;
+        if pay < start {
             return Err("Payment date cannot be before the period start".to_string());
         }
         
@@ -320,7 +582,7 @@ pub struct PaymentDeductionItem {
         context: &AssertSetDocContext,
         salary: &SalaryPaymentData
     ) -> Result<(), String> {
-        let valid_statuses = ["pending", "approved", "paid"];
+        let valid_statuses = ["pending", "approved", "paid", "failed"];
         if !valid_statuses.contains(&salary.status.as_str()) {
             return Err(format!(
                 "Invalid salary status '{}'. Must be one of: {}",
@@ -336,8 +598,9 @@ pub struct PaymentDeductionItem {
             
             let valid_transitions = HashMap::from([
                 ("pending", vec!["approved"]),
-                ("approved", vec!["paid"]),
+                ("approved", vec!["paid", "failed"]),
                 ("paid", vec![]), // No transitions from paid
+                ("failed", vec!["paid"]), // Settled by a successful retry from the retry queue
             ]);
             
             let current_status = &before_salary.status;
@@ -360,6 +623,23 @@ pub struct PaymentDeductionItem {
             if new_status == "approved" && salary.processed_by.trim().is_empty() {
                 return Err("Approved salary payments must have processed_by set".to_string());
             }
+            // No self-approval: the approving caller cannot be the processor
+            if new_status == "approved" && current_status != new_status {
+                validate_not_self_approval(context, Some("processedBy"), "salary payments")?;
+            }
+            
+            // Bank outcomes come only from acknowledgment imports
+            if current_status == "failed" && new_status == "paid" && !is_satellite_caller(&context.caller) {
+                return Err("Failed salary payments are settled only through the retry queue".to_string());
+            }
+            if new_status == "failed" && current_status != new_status {
+                if !is_satellite_caller(&context.caller) {
+                    return Err("Salary payments are marked failed only by bank acknowledgment import".to_string());
+                }
+                if salary.failure_reason.as_ref().map(|r| r.trim().is_empty()).unwrap_or(true) {
+                    return Err("Failed salary payments must include the bank failure reason".to_string());
+                }
+            }
         } else {
             // New salary payments must start as pending
             if salary.status != "pending" {
@@ -370,6 +650,42 @@ pub struct PaymentDeductionItem {
         Ok(())
     }
 
+    /// Side effects of a saved salary payment (claims linkage, court-order and loan running
+    /// totals, the payslip PDF once paid). Runs from the `salary_payments` on-set hook and
+    /// after server-side status updates, which do not trigger hooks.
+    pub fn on_salary_payment_saved(salary_key: &str, salary: &SalaryPaymentData) -> Result<(), String> {
+        store_payslip_pdf(salary_key, salary);
+        sync_claims_with_salary_payment(salary_key, salary)?;
+        sync_court_orders_with_salary_payment(salary)?;
+        sync_loans_with_salary_payment(salary)
+    }
+
+    /// Called from the `staff` on-set hook: writes hold placements and releases to the
+    /// satellite log so they can be audited.
+    pub fn log_salary_hold_changes(
+        staff_key: &str,
+        before: Option<&StaffMemberData>,
+        after: &StaffMemberData
+    ) -> Result<(), String> {
+        let before_hold = before.and_then(|b| b.salary_hold.as_ref());
+        let after_hold = after.salary_hold.as_ref();
+
+        if before_hold == after_hold {
+            return Ok(());
+        }
+
+        if let Some(hold) = after_hold {
+            let was_active = before_hold.map(|h| h.is_active() && h.placed_at == hold.placed_at).unwrap_or(false);
+            if hold.is_active() && !was_active {
+                info_with_data(format!("Salary hold placed on staff {} ({})", after.staff_number, staff_key), hold)?;
+            } else if !hold.is_active() && was_active {
+                info_with_data(format!("Salary hold released for staff {} ({})", after.staff_number, staff_key), hold)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn validate_salary_reference_uniqueness(
         context: &AssertSetDocContext,
         salary: &SalaryPaymentData
@@ -442,3 +758,15 @@ pub struct PaymentDeductionItem {
         
         Ok(())
     }
+
+/// A staff member cannot be deleted while salary payments or loans reference them.
+pub fn validate_staff_delete(key: &str) -> Result<(), String> {
+    deny_if_referenced(
+        "staff member",
+        key,
+        &[
+            ("salary_payments", referencing_keys("salary_payments", "staffId", key)?),
+            ("staff_loans", referencing_keys("staff_loans", "staffId", key)?),
+        ],
+    )
+}
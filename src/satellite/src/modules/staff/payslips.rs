@@ -0,0 +1,94 @@
+//! Payslip PDFs in storage.
+//!
+//! Payslips are stored as assets in the `payslips` storage collection, one folder per
+//! salary payment: `salary_payments/{key}/{name}`. Payslips carry pay and deduction
+//! details, so only payroll officers (bursars, accountants and administrators) can upload
+//! them. Once the payment is paid its payslip is part of the payroll record and can no
+//! longer be deleted.
+
+use candid::Principal;
+use junobuild_satellite::{AssertDeleteAssetContext, AssertUploadAssetContext};
+
+use super::SalaryPaymentData;
+use crate::modules::roles::{caller_has_any_role, Role};
+use crate::modules::utils::doc_utils::get_doc_data;
+
+pub const PAYSLIPS_COLLECTION: &str = "payslips";
+
+const PAYROLL_ROLES: [Role; 3] = [Role::SuperAdmin, Role::Bursar, Role::Accountant];
+
+/// Payslip Upload Validation
+///
+/// Checks:
+/// - Uploaded by a payroll officer
+/// - Path is `salary_payments/{key}/{name}` for an existing salary payment
+/// - Content type is PDF
+pub fn validate_payslip_upload(context: &AssertUploadAssetContext) -> Result<(), String> {
+    require_payroll_officer(&context.caller)?;
+
+    let (_, payment_id) = payslip_payment(&context.data.batch.key.full_path)?;
+    if get_doc_data::<SalaryPaymentData>("salary_payments", payment_id)?.is_none() {
+        return Err(format!("Salary payment '{}' not found", payment_id));
+    }
+
+    let content_type = context
+        .data
+        .commit_batch
+        .headers
+        .iter()
+        .find(|header| header.0.eq_ignore_ascii_case("content-type"))
+        .map(|header| header.1.to_ascii_lowercase())
+        .unwrap_or_default();
+    if content_type != "application/pdf" {
+        return Err(format!("Payslips must be PDF files (got '{}')", content_type));
+    }
+
+    Ok(())
+}
+
+/// Payslip Deletion Validation
+///
+/// Checks:
+/// - Deleted by a payroll officer
+/// - The salary payment has not been paid
+pub fn validate_payslip_delete(context: &AssertDeleteAssetContext) -> Result<(), String> {
+    require_payroll_officer(&context.caller)?;
+
+    let (full_path, payment_id) = payslip_payment(&context.data.key.full_path)?;
+    if let Some((_, payment)) = get_doc_data::<SalaryPaymentData>("salary_payments", payment_id)? {
+        if payment.status == "paid" {
+            return Err(format!(
+                "AUDIT: Payslip '{}' belongs to a paid salary payment and cannot be deleted",
+                full_path
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn require_payroll_officer(caller: &Principal) -> Result<(), String> {
+    if !caller_has_any_role(caller, &PAYROLL_ROLES) {
+        return Err("SECURITY: Only payroll officers can manage payslips".to_string());
+    }
+    Ok(())
+}
+
+// The salary payment key from a payslip's full path, which must be
+// `/payslips/salary_payments/{key}/{name}`
+fn payslip_payment(full_path: &str) -> Result<(&str, &str), String> {
+    let path = full_path
+        .trim_start_matches('/')
+        .strip_prefix(PAYSLIPS_COLLECTION)
+        .map(|rest| rest.trim_start_matches('/'))
+        .unwrap_or(full_path);
+
+    match path.split('/').collect::<Vec<&str>>().as_slice() {
+        ["salary_payments", payment_id, name] if !payment_id.is_empty() && !name.trim().is_empty() => {
+            Ok((full_path, payment_id))
+        }
+        _ => Err(format!(
+            "Payslips must be stored at salary_payments/{{key}}/{{name}}, not '{}'",
+            full_path
+        )),
+    }
+}
@@ -0,0 +1,125 @@
+//! Nigerian statutory deductions: PAYE, employee pension and NHF.
+//!
+//! Computed monthly from the salary payment, the same way the payroll screens do:
+//! - PAYE: annualised gross less the consolidated relief allowance (the higher of ₦200,000
+//!   or 1% of gross, plus 20% of gross), taxed on the PITA bands, divided by 12
+//! - Pension: 8% of monthly emoluments (basic salary plus allowances)
+//! - NHF: 2.5% of basic salary, for basic salaries of ₦30,000 and above
+//!
+//! Amounts are rounded to the naira. Statutory deductions on a salary payment must match
+//! the computed values within a naira.
+
+use super::{PaymentDeductionItem, SalaryPaymentData};
+use crate::modules::utils::money::Money;
+
+pub const PAYE_DEDUCTION: &str = "PAYE (Pay As You Earn Tax)";
+pub const PENSION_DEDUCTION: &str = "Pension - Employee Contribution";
+pub const NHF_DEDUCTION: &str = "NHF (National Housing Fund)";
+
+const PENSION_EMPLOYEE_RATE: f64 = 0.08;
+const NHF_RATE: f64 = 0.025;
+const NHF_MIN_BASIC_SALARY: f64 = 30_000.0;
+const MIN_CRA: f64 = 200_000.0;
+const CRA_RATE: f64 = 0.21;
+// Rounding differences allowed between the frontend's figures and the canister's
+const TOLERANCE: Money = Money::from_kobo(100);
+
+// Annual PITA bands: (band width, rate); the last band is unbounded
+const TAX_BANDS: [(f64, f64); 6] = [
+    (300_000.0, 0.07),
+    (300_000.0, 0.11),
+    (500_000.0, 0.15),
+    (500_000.0, 0.19),
+    (1_600_000.0, 0.21),
+    (f64::INFINITY, 0.24),
+];
+
+#[derive(Clone, Copy)]
+pub struct StatutoryDeductions {
+    pub paye: Money,
+    pub pension: Money,
+    pub nhf: Money,
+}
+
+/// Statutory deductions due on a salary payment.
+pub fn compute_statutory_deductions(salary: &SalaryPaymentData) -> StatutoryDeductions {
+    let basic = salary.basic_salary.naira();
+    let emoluments = (salary.basic_salary + salary.allowances.iter().map(|a| a.amount).sum()).naira();
+
+    StatutoryDeductions {
+        paye: Money::from_naira(monthly_paye(emoluments).round()),
+        pension: Money::from_naira((emoluments * PENSION_EMPLOYEE_RATE).round()),
+        nhf: if basic >= NHF_MIN_BASIC_SALARY {
+            Money::from_naira((basic * NHF_RATE).round())
+        } else {
+            Money::ZERO
+        },
+    }
+}
+
+/// Deduction items for the amounts due, for building salaries server-side.
+pub fn statutory_deduction_items(salary: &SalaryPaymentData) -> Vec<PaymentDeductionItem> {
+    let due = compute_statutory_deductions(salary);
+    [(NHF_DEDUCTION, due.nhf), (PENSION_DEDUCTION, due.pension), (PAYE_DEDUCTION, due.paye)]
+        .into_iter()
+        .filter(|(_, amount)| amount.is_positive())
+        .map(|(name, amount)| PaymentDeductionItem { name: name.to_string(), amount, is_statutory: true })
+        .collect()
+}
+
+/// PAYE, pension and NHF must each be deducted when due, marked statutory, and within a
+/// naira of the computed amount. Other statutory deductions (e.g. court orders) are
+/// checked elsewhere.
+pub fn validate_statutory_deductions(salary: &SalaryPaymentData) -> Result<(), String> {
+    let due = compute_statutory_deductions(salary);
+
+    for (kind, name, expected) in [
+        ("paye", PAYE_DEDUCTION, due.paye),
+        ("pension", PENSION_DEDUCTION, due.pension),
+        ("nhf", NHF_DEDUCTION, due.nhf),
+    ] {
+        let applied: Vec<&PaymentDeductionItem> =
+            salary.deductions.iter().filter(|d| d.name.to_lowercase().contains(kind)).collect();
+
+        match applied.as_slice() {
+            [] if expected.is_positive() => {
+                return Err(format!("Statutory deduction '{}' of ₦{} is missing", name, expected));
+            }
+            [] => {}
+            [deduction] => {
+                if !deduction.is_statutory {
+                    return Err(format!("Deduction '{}' must be marked as statutory", deduction.name));
+                }
+                let difference = deduction.amount - expected;
+                if difference > TOLERANCE || -difference > TOLERANCE {
+                    return Err(format!(
+                        "Statutory deduction '{}' should be ₦{}, found ₦{}",
+                        deduction.name, expected, deduction.amount
+                    ));
+                }
+            }
+            _ => return Err(format!("Statutory deduction '{}' is included more than once", name)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Monthly PAYE on a monthly gross, in naira before rounding.
+pub fn monthly_paye(monthly_gross: f64) -> f64 {
+    let annual_gross = monthly_gross * 12.0;
+    let relief = MIN_CRA.max(annual_gross * CRA_RATE);
+    let mut remaining = (annual_gross - relief).max(0.0);
+
+    let mut annual_tax = 0.0;
+    for (width, rate) in TAX_BANDS {
+        if remaining <= 0.0 {
+            break;
+        }
+        let taxed = remaining.min(width);
+        annual_tax += taxed * rate;
+        remaining -= taxed;
+    }
+
+    annual_tax / 12.0
+}
@@ -0,0 +1,89 @@
+//! Student photos and documents in storage.
+//!
+//! Student files are uploaded to the `student_assets` storage collection under the
+//! student's key:
+//! - `student/{key}/photo`: the passport photo, a JPEG or PNG in a single upload chunk.
+//!   The path is fixed, so a new photo replaces the old one and a student has one photo
+//!   at most.
+//! - `student/{key}/documents/{name}`: supporting documents (PDF, JPEG or PNG).
+//!
+//! Uploads are only accepted for existing students who have not been withdrawn, so
+//! storage holds no files for unknown students and report cards always find the photo
+//! where they expect it.
+
+use junobuild_satellite::AssertUploadAssetContext;
+
+use super::StudentData;
+use crate::modules::utils::doc_utils::get_doc_data;
+
+pub const STUDENT_ASSETS_COLLECTION: &str = "student_assets";
+
+const PHOTO_CONTENT_TYPES: [&str; 2] = ["image/jpeg", "image/png"];
+const DOCUMENT_CONTENT_TYPES: [&str; 3] = ["application/pdf", "image/jpeg", "image/png"];
+
+// Files are uploaded in chunks of at most ~1.9 MB: photos must fit one chunk, documents
+// five (~9.5 MB)
+const MAX_PHOTO_CHUNKS: usize = 1;
+const MAX_DOCUMENT_CHUNKS: usize = 5;
+
+/// Student Asset Upload Validation
+///
+/// Checks:
+/// - Path is `student/{key}/photo` or `student/{key}/documents/{name}`
+/// - The student exists and has not been withdrawn
+/// - Content type is JPEG or PNG for photos, and also PDF for documents
+/// - Size is within one upload chunk for photos and five for documents
+pub fn validate_student_asset_upload(context: &AssertUploadAssetContext) -> Result<(), String> {
+    let key = &context.data.batch.key;
+    let path = key
+        .full_path
+        .trim_start_matches('/')
+        .strip_prefix(STUDENT_ASSETS_COLLECTION)
+        .map(|rest| rest.trim_start_matches('/'))
+        .unwrap_or(&key.full_path);
+
+    let segments: Vec<&str> = path.split('/').collect();
+    let (student_id, is_photo) = match segments.as_slice() {
+        ["student", student_id, "photo"] => (*student_id, true),
+        ["student", student_id, "documents", name] if !name.trim().is_empty() => (*student_id, false),
+        _ => {
+            return Err(format!(
+                "Student files must be stored at student/{{key}}/photo or student/{{key}}/documents/{{name}}, not '{}'",
+                key.full_path
+            ));
+        }
+    };
+
+    let (_, student) = get_doc_data::<StudentData>("students", student_id)?
+        .ok_or_else(|| format!("Student '{}' not found", student_id))?;
+    if student.is_active == Some(false) {
+        return Err(format!("Student '{}' has been withdrawn; files can no longer be uploaded", student_id));
+    }
+
+    let content_type = context
+        .data
+        .commit_batch
+        .headers
+        .iter()
+        .find(|header| header.0.eq_ignore_ascii_case("content-type"))
+        .map(|header| header.1.to_ascii_lowercase())
+        .unwrap_or_default();
+    let (allowed, max_chunks, kind) = if is_photo {
+        (&PHOTO_CONTENT_TYPES[..], MAX_PHOTO_CHUNKS, "Passport photos")
+    } else {
+        (&DOCUMENT_CONTENT_TYPES[..], MAX_DOCUMENT_CHUNKS, "Student documents")
+    };
+    if !allowed.contains(&content_type.as_str()) {
+        return Err(format!(
+            "{} must be one of: {} (got '{}')",
+            kind,
+            allowed.join(", "),
+            content_type
+        ));
+    }
+    if context.data.commit_batch.chunk_ids.len() > max_chunks {
+        return Err(format!("{} are too large: at most {} upload chunk(s) of ~1.9 MB", kind, max_chunks));
+    }
+
+    Ok(())
+}
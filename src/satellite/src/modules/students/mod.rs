@@ -1,7 +1,11 @@
+pub mod assets;
+
 use junobuild_satellite::{AssertSetDocContext, list_docs};
 use junobuild_shared::types::list::{ListParams, ListMatcher};
 use junobuild_utils::decode_doc_data;
 use serde::{Deserialize, Serialize};
+use super::utils::doc_utils::{deny_if_referenced, referencing_keys};
+use super::utils::cache::cached_doc_exists;
 
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -10,6 +14,8 @@ pub struct StudentData {
     pub admission_number: Option<String>,
     #[serde(default)]
     pub class_id: Option<String>,
+    #[serde(default)]
+    pub is_active: Option<bool>,
     // Allow other fields to be present but ignored
     #[serde(flatten)]
     pub _extra: std::collections::HashMap<String, serde_cbor::Value>,
@@ -47,22 +53,56 @@ pub fn validate_student_document(context: &AssertSetDocContext) -> Result<(), St
 
     // Referential integrity: classId must reference an existing class if provided
     if let Some(ref class_id) = student_data.class_id {
-        if !class_id.trim().is_empty() {
-            let classes = list_docs(
-                String::from("classes"),
-                ListParams {
-                    matcher: Some(ListMatcher {
-                        key: Some(class_id.to_string()),
-                        ..Default::default()
-                    }),
-                    ..Default::default()
-                },
-            );
-            if classes.items.is_empty() {
-                return Err(format!("Class '{}' not found", class_id));
-            }
+        if !class_id.trim().is_empty() && !cached_doc_exists("classes", class_id)? {
+            return Err(format!("Class '{}' not found", class_id));
         }
     }
 
     Ok(())
 }
+
+/// A student cannot be deleted while payments or fee assignments reference them.
+pub fn validate_student_delete(key: &str) -> Result<(), String> {
+    deny_if_referenced(
+        "student",
+        key,
+        &[
+            ("payments", referencing_keys("payments", "studentId", key)?),
+            ("student_fee_assignments", referencing_keys("student_fee_assignments", "studentId", key)?),
+        ],
+    )
+}
+
+/// Reference code guardians quote when paying at a bank branch:
+/// `STU-<admission number>-<check digit>`, with separators dropped from the admission
+/// number (the student key is used when there is none). The check digit lets
+/// reconciliation tell a mistyped code from another student's.
+pub fn payment_reference_code(student_id: &str, student: &StudentData) -> String {
+    let body = reference_code_body(student_id, student);
+    format!("STU-{}-{}", body, reference_check_digit(&body))
+}
+
+/// Body of a student's reference code: the admission number (or student key), upper case
+/// and without separators.
+pub fn reference_code_body(student_id: &str, student: &StudentData) -> String {
+    let source = student
+        .admission_number
+        .as_deref()
+        .filter(|adm| !adm.trim().is_empty())
+        .unwrap_or(student_id);
+    source
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Check digit of a reference code body: position-weighted sum of the characters
+/// (digits 0-9, letters 10-35) modulo 10.
+pub fn reference_check_digit(body: &str) -> u32 {
+    body.chars()
+        .enumerate()
+        .map(|(i, c)| (i as u32 + 1) * c.to_digit(36).unwrap_or(0))
+        .sum::<u32>()
+        % 10
+}
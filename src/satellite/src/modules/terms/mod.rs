@@ -0,0 +1,290 @@
+//! Terms Module - Academic Terms and Term Rollover
+//!
+//! Each term of the school year is an `academic_terms` document with its date range.
+//! Terms never overlap and at most one is active at a time: the running term. Rolling
+//! over is done through `close_term`, which:
+//! - Marks the term closed and inactive, so the next term can be activated
+//! - Carries forward or drops unpaid optional fee items, as the school's settings say
+//!   (see [`optional_fees`])
+//! - Snapshots fees billed and collected per class into `term_summaries`
+//! - Locks payments and expenses dated inside the term against any further change
+
+pub mod optional_fees;
+
+use candid::CandidType;
+use junobuild_satellite::{caller, AssertDeleteDocContext, AssertSetDocContext};
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::fees::StudentFeeAssignmentData;
+use super::payments::PaymentData;
+use super::roles::{caller_has_any_role, require_role, Role};
+use super::settings::school_settings;
+use super::utils::doc_utils::*;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const ACADEMIC_TERMS_COLLECTION: &str = "academic_terms";
+pub const TERM_SUMMARIES_COLLECTION: &str = "term_summaries";
+
+const TERMS: [&str; 3] = ["first", "second", "third"];
+
+// Collections locked by a closed term and the date field that places a document in it
+const LOCKED_DATE_FIELDS: [(&str, &str); 2] = [("expenses", "paymentDate"), ("payments", "paymentDate")];
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcademicTermData {
+    pub academic_year: String,
+    pub term: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub is_active: bool,
+    pub status: String,
+    pub closed_by: Option<String>,
+    pub closed_at: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermSummaryData {
+    pub term_id: String,
+    pub academic_year: String,
+    pub term: String,
+    pub class_id: String,
+    pub class_name: String,
+    pub students_billed: u32,
+    pub total_billed: Money,
+    pub total_collected: Money,
+    pub outstanding: Money,
+    pub payment_count: u32,
+    pub created_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct TermClassSummary {
+    pub class_id: String,
+    pub class_name: String,
+    pub students_billed: u32,
+    pub total_billed: f64,
+    pub total_collected: f64,
+    pub outstanding: f64,
+    pub payment_count: u32,
+}
+
+/// Academic Term Validation
+///
+/// Checks:
+/// - Only administrators set up terms; only `close_term` closes them
+/// - Valid academic year, term and date range
+/// - No overlap with another term's dates, and one term per year and term name
+/// - At most one active term; closed terms cannot be reactivated or changed
+pub fn validate_academic_term_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: AcademicTermData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid academic term data format: {}", e))?;
+
+    if is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+    if !caller_has_any_role(&context.caller, &[Role::SuperAdmin]) {
+        return Err("SECURITY: Only administrators can set up academic terms".to_string());
+    }
+
+    if let Some(ref before_doc) = context.data.data.current {
+        let before: AcademicTermData = decode_doc_data(&before_doc.data)
+            .map_err(|e| format!("Invalid previous academic term data: {}", e))?;
+        if before.status == "closed" {
+            return Err(format!("AUDIT: {} {} term is closed and cannot be changed", before.academic_year, before.term));
+        }
+    }
+    if data.status != "open" {
+        return Err("Terms are closed through the close_term endpoint".to_string());
+    }
+
+    if !is_valid_academic_year(&data.academic_year) {
+        return Err("Invalid academic year. Must be in format YYYY/YYYY".to_string());
+    }
+    if !TERMS.contains(&data.term.as_str()) {
+        return Err(format!("Invalid term '{}'. Must be one of: {}", data.term, TERMS.join(", ")));
+    }
+    if !is_valid_date_format(&data.start_date) || !is_valid_date_format(&data.end_date) {
+        return Err("Term start and end dates must be in format YYYY-MM-DD".to_string());
+    }
+    if data.end_date <= data.start_date {
+        return Err("Term end date must be after its start date".to_string());
+    }
+
+    for (key, _, other) in list_doc_data::<AcademicTermData>(ACADEMIC_TERMS_COLLECTION, None)? {
+        if key == context.data.key {
+            continue;
+        }
+        if other.academic_year == data.academic_year && other.term == data.term {
+            return Err(format!("The {} {} term already exists", data.academic_year, data.term));
+        }
+        if other.start_date <= data.end_date && other.end_date >= data.start_date {
+            return Err(format!(
+                "Term dates overlap the {} {} term ({} to {})",
+                other.academic_year, other.term, other.start_date, other.end_date
+            ));
+        }
+        if data.is_active && other.is_active {
+            return Err(format!(
+                "The {} {} term is still active; close it before activating another term",
+                other.academic_year, other.term
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Terms carry the lock on their payments and expenses, so they cannot be deleted
+/// once closed.
+pub fn validate_academic_term_delete(context: &AssertDeleteDocContext) -> Result<(), String> {
+    if let Some(ref doc) = context.data.data.current {
+        let term: AcademicTermData = decode_doc_data(&doc.data)?;
+        if term.status == "closed" {
+            return Err("AUDIT: A closed term cannot be deleted".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Term summaries are written by `close_term` only and never changed.
+pub fn validate_term_summary_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Term summaries are produced by close_term".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Term summaries cannot be modified".to_string());
+    }
+    Ok(())
+}
+
+/// Close a term: settle its unpaid optional fees, lock its payments and expenses and
+/// snapshot per-class collections.
+pub fn close_term(term_id: String) -> Result<Vec<TermClassSummary>, String> {
+    let caller = caller();
+    require_role(&caller, Role::Bursar)?;
+
+    let (doc, mut term) = get_doc_data::<AcademicTermData>(ACADEMIC_TERMS_COLLECTION, &term_id)?
+        .ok_or_else(|| format!("Academic term '{}' not found", term_id))?;
+    if term.status == "closed" {
+        return Err(format!("The {} {} term is already closed", term.academic_year, term.term));
+    }
+
+    let now = ic_cdk::api::time();
+    let policy = school_settings()?.unpaid_optional_fees;
+    optional_fees::roll_over_optional_fees(&term_id, &term, &policy, &caller.to_text(), now)?;
+
+    let mut by_class: BTreeMap<String, TermSummaryData> = BTreeMap::new();
+    let new_summary = |class_id: &str| TermSummaryData {
+        term_id: term_id.clone(),
+        academic_year: term.academic_year.clone(),
+        term: term.term.clone(),
+        class_id: class_id.to_string(),
+        class_name: String::new(),
+        students_billed: 0,
+        total_billed: Money::ZERO,
+        total_collected: Money::ZERO,
+        outstanding: Money::ZERO,
+        payment_count: 0,
+        created_at: now,
+    };
+
+    for (_, _, assignment) in list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)? {
+        if assignment.academic_year != term.academic_year || assignment.term != term.term {
+            continue;
+        }
+        let summary = by_class
+            .entry(assignment.class_id.clone())
+            .or_insert_with(|| new_summary(&assignment.class_id));
+        summary.students_billed += 1;
+        summary.total_billed += assignment.total_amount;
+        summary.outstanding += assignment.balance;
+    }
+    for (_, _, payment) in list_doc_data::<PaymentData>("payments", None)? {
+        if payment.status != "confirmed"
+            || payment.payment_date < term.start_date
+            || payment.payment_date > term.end_date
+        {
+            continue;
+        }
+        let summary = by_class.entry(payment.class_id.clone()).or_insert_with(|| new_summary(&payment.class_id));
+        if summary.class_name.is_empty() {
+            summary.class_name = payment.class_name.clone();
+        }
+        summary.total_collected += payment.amount_in_base();
+        summary.payment_count += 1;
+    }
+
+    let mut summaries = Vec::new();
+    for (class_id, summary) in by_class {
+        set_doc_data(
+            TERM_SUMMARIES_COLLECTION,
+            &format!("{}_{}", term_id, class_id),
+            &summary,
+            Some(format!("term_id={};", term_id)),
+            None,
+        )?;
+        summaries.push(TermClassSummary {
+            class_id,
+            class_name: summary.class_name,
+            students_billed: summary.students_billed,
+            total_billed: summary.total_billed.naira(),
+            total_collected: summary.total_collected.naira(),
+            outstanding: summary.outstanding.naira(),
+            payment_count: summary.payment_count,
+        });
+    }
+
+    term.status = "closed".to_string();
+    term.is_active = false;
+    term.closed_by = Some(caller.to_text());
+    term.closed_at = Some(now);
+    set_doc_data(ACADEMIC_TERMS_COLLECTION, &term_id, &term, doc.description, doc.version)?;
+
+    Ok(summaries)
+}
+
+/// Reject writes to payments and expenses dated inside a closed term, whether the
+/// document is being created there, changed there, or moved out of it.
+pub fn validate_term_open(context: &AssertSetDocContext) -> Result<(), String> {
+    if is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+    check_term_open(&context.data.collection, context.data.data.current.as_ref().map(|d| &d.data))?;
+    check_term_open(&context.data.collection, Some(&context.data.data.proposed.data))
+}
+
+/// Reject deletion of payments and expenses dated inside a closed term.
+pub fn validate_term_open_on_delete(context: &AssertDeleteDocContext) -> Result<(), String> {
+    check_term_open(&context.data.collection, context.data.data.current.as_ref().map(|d| &d.data))
+}
+
+fn check_term_open(collection: &str, data: Option<&Vec<u8>>) -> Result<(), String> {
+    let field = match LOCKED_DATE_FIELDS.iter().find(|(c, _)| *c == collection) {
+        Some((_, field)) => *field,
+        None => return Ok(()),
+    };
+    let date = match data.and_then(|d| decode_doc_data::<serde_json::Value>(d).ok()) {
+        Some(data) => data.get(field).and_then(|v| v.as_str()).map(str::to_string),
+        None => None,
+    };
+    let date = match date {
+        Some(date) => date,
+        None => return Ok(()),
+    };
+
+    let closed = list_doc_data::<AcademicTermData>(ACADEMIC_TERMS_COLLECTION, None)?
+        .into_iter()
+        .find(|(_, _, t)| t.status == "closed" && t.start_date <= date && t.end_date >= date);
+    match closed {
+        Some((_, _, term)) => Err(format!(
+            "TERM_CLOSED: The {} {} term is closed; entries dated in it cannot be changed",
+            term.academic_year, term.term
+        )),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,143 @@
+//! Unpaid optional fees at term rollover.
+//!
+//! When a term is closed, the optional items still owing on its fee assignments are
+//! settled by the school's `unpaidOptionalFees` setting, the same way for every student:
+//! - `carry_forward`: the items stay on the assignment and their balance is owed as
+//!   arrears, settled by later payments
+//! - `drop`: the unpaid part is written off the assignment. An item nothing was paid on
+//!   is removed, so it is not billed again when the student is promoted; a part-paid item
+//!   is reduced to what was paid.
+//!
+//! Each student with unpaid optional items gets an `optional_fee_rollovers` document
+//! recording the policy applied and the items it applied to. They are never changed.
+
+use junobuild_satellite::AssertSetDocContext;
+use serde::{Deserialize, Serialize};
+
+use super::AcademicTermData;
+use crate::modules::fees::StudentFeeAssignmentData;
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::money::Money;
+use crate::modules::verification::balance_after;
+
+pub const OPTIONAL_FEE_ROLLOVERS_COLLECTION: &str = "optional_fee_rollovers";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OptionalFeeRolloverData {
+    pub term_id: String,
+    pub academic_year: String,
+    pub term: String,
+    pub student_id: String,
+    pub student_name: String,
+    pub assignment_id: String,
+    // `carry_forward` or `drop`
+    pub policy: String,
+    pub items: Vec<RolledOverItem>,
+    pub total_unpaid: Money,
+    pub decided_by: String,
+    pub decided_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RolledOverItem {
+    pub category_id: String,
+    pub category_name: String,
+    pub amount_paid: Money,
+    pub unpaid: Money,
+}
+
+/// Optional Fee Rollover Validation
+///
+/// Checks:
+/// - Rollovers are recorded by `close_term` only and never modified
+pub fn validate_optional_fee_rollover_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Optional fee rollovers are recorded by close_term".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Optional fee rollovers cannot be modified".to_string());
+    }
+    Ok(())
+}
+
+/// Rollovers record what happened to students' fees and cannot be deleted.
+pub fn validate_optional_fee_rollover_delete() -> Result<(), String> {
+    Err("AUDIT: Optional fee rollovers cannot be deleted".to_string())
+}
+
+/// Apply `policy` to the unpaid optional items on the term's fee assignments, recording
+/// it for each student affected.
+pub fn roll_over_optional_fees(
+    term_id: &str,
+    term: &AcademicTermData,
+    policy: &str,
+    decided_by: &str,
+    now: u64,
+) -> Result<(), String> {
+    for (assignment_id, doc, mut assignment) in
+        list_doc_data::<StudentFeeAssignmentData>("student_fee_assignments", None)?
+    {
+        if assignment.academic_year != term.academic_year || assignment.term != term.term {
+            continue;
+        }
+        let items: Vec<RolledOverItem> = assignment
+            .fee_items
+            .iter()
+            .filter(|item| !item.is_mandatory && item.balance.is_positive())
+            .map(|item| RolledOverItem {
+                category_id: item.category_id.clone(),
+                category_name: item.category_name.clone(),
+                amount_paid: item.amount_paid,
+                unpaid: item.balance,
+            })
+            .collect();
+        if items.is_empty() {
+            continue;
+        }
+        let total_unpaid: Money = items.iter().map(|item| item.unpaid).sum();
+
+        if policy == "drop" {
+            assignment
+                .fee_items
+                .retain(|item| item.is_mandatory || !item.balance.is_positive() || !item.amount_paid.is_zero());
+            for item in assignment.fee_items.iter_mut().filter(|item| !item.is_mandatory && item.balance.is_positive())
+            {
+                item.amount = item.amount_paid;
+                item.balance = Money::ZERO;
+            }
+            // A discount may leave less owing on the assignment than on its items
+            let written_off = total_unpaid.min(assignment.balance.max(Money::ZERO));
+            assignment.total_amount -= written_off;
+            assignment.original_amount = assignment.original_amount.map(|amount| amount - written_off);
+            let (balance, status) = balance_after(assignment.total_amount, assignment.amount_paid);
+            assignment.balance = balance;
+            assignment.status = status.to_string();
+            set_doc_data("student_fee_assignments", &assignment_id, &assignment, doc.description, doc.version)?;
+        }
+
+        let rollover = OptionalFeeRolloverData {
+            term_id: term_id.to_string(),
+            academic_year: term.academic_year.clone(),
+            term: term.term.clone(),
+            student_id: assignment.student_id.clone(),
+            student_name: assignment.student_name.clone(),
+            assignment_id: assignment_id.clone(),
+            policy: policy.to_string(),
+            items,
+            total_unpaid,
+            decided_by: decided_by.to_string(),
+            decided_at: now,
+        };
+        set_doc_data(
+            OPTIONAL_FEE_ROLLOVERS_COLLECTION,
+            &format!("{}_{}", term_id, assignment_id),
+            &rollover,
+            Some(format!("student_id={};", assignment.student_id)),
+            None,
+        )?;
+    }
+
+    Ok(())
+}
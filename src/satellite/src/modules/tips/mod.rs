@@ -0,0 +1,155 @@
+//! Tips Module - Whistleblower Filings
+//!
+//! Any signed-in user can raise a concern about a transaction through `file_tip`. The
+//! satellite stores the filing in `tips` without the filer's identity, so the collection
+//! is write-only from the frontend (create it with private read permission). Filings are
+//! read through `list_tips`, open to the auditor role only: administrators are not
+//! given access because a concern may be about them.
+//!
+//! Each filing carries the hash of the previous one, so removing, reordering or editing
+//! a filing breaks the chain and shows up when the tips are listed.
+
+use candid::{CandidType, Principal};
+use junobuild_satellite::{caller, AssertSetDocContext};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::roles::{caller_has_any_role, Role};
+use super::utils::counters::next_number;
+use super::utils::doc_utils::*;
+
+pub const TIPS_COLLECTION: &str = "tips";
+
+const MAX_REFERENCE_LENGTH: usize = 100;
+const MIN_DESCRIPTION_LENGTH: usize = 20;
+const MAX_DESCRIPTION_LENGTH: usize = 5_000;
+
+// Previous hash of the first filing
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TipData {
+    pub number: u64,
+    pub reference: String,
+    pub description: String,
+    pub filed_at: u64,
+    pub previous_hash: String,
+    pub hash: String,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct TipRecord {
+    pub number: u64,
+    pub reference: String,
+    pub description: String,
+    pub filed_at: u64,
+    pub hash: String,
+    // False when this filing or the link to the one before it was tampered with
+    pub chain_valid: bool,
+}
+
+/// Tips are written by `file_tip` only and never modified.
+pub fn validate_tip_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Tips are filed through the file_tip endpoint".to_string());
+    }
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Filed tips cannot be modified".to_string());
+    }
+    Ok(())
+}
+
+/// Filed tips can never be deleted.
+pub fn validate_tip_delete() -> Result<(), String> {
+    Err("AUDIT: Filed tips cannot be deleted".to_string())
+}
+
+/// File a concern about a transaction. Returns the filing number.
+pub fn file_tip(reference: String, description: String) -> Result<u64, String> {
+    if caller() == Principal::anonymous() {
+        return Err("SECURITY: Sign in to file a tip".to_string());
+    }
+
+    let reference = reference.trim().to_string();
+    let description = description.trim().to_string();
+    if reference.is_empty() || reference.len() > MAX_REFERENCE_LENGTH {
+        return Err(format!("Reference must be 1-{} characters", MAX_REFERENCE_LENGTH));
+    }
+    if description.len() < MIN_DESCRIPTION_LENGTH || description.len() > MAX_DESCRIPTION_LENGTH {
+        return Err(format!(
+            "Description must be {}-{} characters",
+            MIN_DESCRIPTION_LENGTH, MAX_DESCRIPTION_LENGTH
+        ));
+    }
+
+    let number = next_number(TIPS_COLLECTION)?;
+    let previous_hash = match number {
+        1 => GENESIS_HASH.to_string(),
+        _ => get_doc_data::<TipData>(TIPS_COLLECTION, &tip_key(number - 1))?
+            .map(|(_, tip)| tip.hash)
+            .ok_or("The previous tip is missing; the tip chain is broken")?,
+    };
+
+    let mut tip = TipData {
+        number,
+        reference,
+        description,
+        filed_at: ic_cdk::api::time(),
+        previous_hash,
+        hash: String::new(),
+    };
+    tip.hash = tip_hash(&tip);
+    set_doc_data(TIPS_COLLECTION, &tip_key(number), &tip, None, None)?;
+
+    Ok(number)
+}
+
+/// All filings in order, each checked against the chain. Auditors only.
+pub fn list_tips() -> Result<Vec<TipRecord>, String> {
+    if !caller_has_any_role(&caller(), &[Role::Auditor]) {
+        return Err("SECURITY: Tips are available to auditors only".to_string());
+    }
+
+    let mut tips: Vec<TipData> = list_doc_data::<TipData>(TIPS_COLLECTION, None)?
+        .into_iter()
+        .map(|(_, _, tip)| tip)
+        .collect();
+    tips.sort_by_key(|tip| tip.number);
+
+    let mut expected_previous = GENESIS_HASH.to_string();
+    let mut expected_number = 1;
+    Ok(tips
+        .into_iter()
+        .map(|tip| {
+            let chain_valid =
+                tip.number == expected_number && tip.previous_hash == expected_previous && tip.hash == tip_hash(&tip);
+            expected_previous = tip.hash.clone();
+            expected_number = tip.number + 1;
+            TipRecord {
+                number: tip.number,
+                reference: tip.reference,
+                description: tip.description,
+                filed_at: tip.filed_at,
+                hash: tip.hash,
+                chain_valid,
+            }
+        })
+        .collect())
+}
+
+fn tip_key(number: u64) -> String {
+    format!("TIP-{:06}", number)
+}
+
+// SHA-256 over the filing's content and the previous hash, hex encoded
+fn tip_hash(tip: &TipData) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(tip.previous_hash.as_bytes());
+    hasher.update(tip.number.to_be_bytes());
+    hasher.update(tip.filed_at.to_be_bytes());
+    hasher.update(tip.reference.as_bytes());
+    hasher.update([0]);
+    hasher.update(tip.description.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
@@ -0,0 +1,245 @@
+//! Generator fuel logs and reconciliation.
+//!
+//! Fuel bought for generators (`fuel_logs` entries of type `purchase`) and fuel drawn
+//! (`consumption`, with the generator's hour-meter readings) are logged per generator
+//! (`generators`). Consumption is checked against fuel on hand, and the variance report
+//! compares drawn litres with what the recorded running hours should have burned.
+
+use candid::CandidType;
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::modules::expenses::ExpenseData;
+use crate::modules::utils::doc_utils::*;
+use crate::modules::utils::identity::validate_caller_identity;
+use crate::modules::utils::validation_utils::*;
+
+pub const GENERATORS_COLLECTION: &str = "generators";
+pub const FUEL_LOGS_COLLECTION: &str = "fuel_logs";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratorData {
+    pub name: String,
+    pub capacity_kva: f64,
+    pub litres_per_hour: f64,
+    pub variance_tolerance_percent: f64,
+    pub is_active: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FuelLogData {
+    pub generator_id: String,
+    pub entry_type: String,
+    pub log_date: String,
+    pub litres: f64,
+    pub expense_id: Option<String>,
+    pub hour_meter_start: Option<f64>,
+    pub hour_meter_end: Option<f64>,
+    pub notes: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct FuelVarianceItem {
+    pub generator_id: String,
+    pub generator_name: String,
+    pub period: String,
+    pub litres_purchased: f64,
+    pub litres_consumed: f64,
+    pub running_hours: f64,
+    pub expected_litres: f64,
+    pub variance_litres: f64,
+    pub variance_percent: f64,
+    pub is_flagged: bool,
+}
+
+/// Generator Validation
+///
+/// Checks:
+/// - Positive rated consumption (litres per hour) and capacity
+/// - Variance tolerance is a sensible percentage
+pub fn validate_generator_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: GeneratorData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid generator data format: {}", e))?;
+
+    if data.name.trim().is_empty() {
+        return Err("Generator name is required".to_string());
+    }
+    if data.capacity_kva <= 0.0 {
+        return Err("Generator capacity (kVA) must be greater than 0".to_string());
+    }
+    if data.litres_per_hour <= 0.0 {
+        return Err("Rated fuel consumption (litresPerHour) must be greater than 0".to_string());
+    }
+    if !(0.0..=100.0).contains(&data.variance_tolerance_percent) {
+        return Err("varianceTolerancePercent must be between 0 and 100".to_string());
+    }
+
+    Ok(())
+}
+
+/// Fuel Log Validation
+///
+/// Checks:
+/// - Generator exists; entries are immutable once logged
+/// - Purchases link to the fuel expense they were paid with
+/// - Consumption carries hour-meter readings that continue from the previous run
+/// - Fuel drawn never exceeds fuel purchased for the generator
+pub fn validate_fuel_log_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: FuelLogData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid fuel log data format: {}", e))?;
+
+    // recordedBy must be the caller
+    validate_caller_identity(context)?;
+
+    if context.data.data.current.is_some() {
+        return Err("AUDIT: Fuel log entries cannot be modified; record a correcting entry".to_string());
+    }
+    if !is_valid_date_format(&data.log_date) {
+        return Err("Invalid log date format. Must be YYYY-MM-DD".to_string());
+    }
+    if data.litres <= 0.0 {
+        return Err("Litres must be greater than 0".to_string());
+    }
+
+    let (_, generator) = get_doc_data::<GeneratorData>(GENERATORS_COLLECTION, &data.generator_id)?
+        .ok_or_else(|| format!("Generator '{}' not found", data.generator_id))?;
+    if !generator.is_active {
+        return Err(format!("Generator '{}' is inactive", generator.name));
+    }
+
+    let logs = generator_fuel_logs(&data.generator_id)?;
+
+    match data.entry_type.as_str() {
+        "purchase" => {
+            let expense_id = data
+                .expense_id
+                .as_ref()
+                .filter(|id| !id.trim().is_empty())
+                .ok_or("Fuel purchases must reference the expense that paid for them")?;
+
+            let (_, expense) = get_doc_data::<ExpenseData>("expenses", expense_id)?
+                .ok_or_else(|| format!("Expense '{}' not found", expense_id))?;
+            if expense.status == "rejected" {
+                return Err("Fuel purchases cannot reference a rejected expense".to_string());
+            }
+            if logs.iter().any(|(_, l)| l.expense_id.as_deref() == Some(expense_id.as_str())) {
+                return Err(format!("Expense '{}' is already logged as a fuel purchase", expense.reference));
+            }
+            if data.hour_meter_start.is_some() || data.hour_meter_end.is_some() {
+                return Err("Hour-meter readings belong on consumption entries".to_string());
+            }
+        }
+        "consumption" => {
+            let start = data.hour_meter_start.ok_or("hourMeterStart is required for consumption")?;
+            let end = data.hour_meter_end.ok_or("hourMeterEnd is required for consumption")?;
+            if end <= start {
+                return Err("hourMeterEnd must be greater than hourMeterStart".to_string());
+            }
+
+            let last_end = logs
+                .iter()
+                .filter_map(|(_, l)| l.hour_meter_end)
+                .fold(0.0_f64, f64::max);
+            if start + 0.001 < last_end {
+                return Err(format!(
+                    "hourMeterStart {} is below the last recorded reading of {}",
+                    start, last_end
+                ));
+            }
+
+            let (purchased, consumed) = fuel_totals(logs.iter().map(|(_, l)| l));
+            let on_hand = purchased - consumed;
+            if data.litres > on_hand + 0.001 {
+                return Err(format!(
+                    "Cannot draw {:.1} litres: only {:.1} litres purchased and not yet consumed",
+                    data.litres, on_hand
+                ));
+            }
+        }
+        other => {
+            return Err(format!("Invalid entry type '{}'. Must be purchase or consumption", other));
+        }
+    }
+
+    Ok(())
+}
+
+/// Per generator and month: fuel purchased and drawn against the litres the logged
+/// running hours should have consumed at the generator's rated consumption.
+pub fn get_fuel_variance_report(period: Option<String>) -> Result<Vec<FuelVarianceItem>, String> {
+    if let Some(ref p) = period {
+        if !is_valid_period(p) {
+            return Err("Period must be in format YYYY-MM".to_string());
+        }
+    }
+
+    let generators = list_doc_data::<GeneratorData>(GENERATORS_COLLECTION, None)?;
+    let logs = list_doc_data::<FuelLogData>(FUEL_LOGS_COLLECTION, None)?;
+
+    let mut report = Vec::new();
+    for (generator_id, _, generator) in generators.iter() {
+        let mut by_period: BTreeMap<String, Vec<&FuelLogData>> = BTreeMap::new();
+        for (_, _, log) in logs.iter().filter(|(_, _, l)| &l.generator_id == generator_id) {
+            let log_period = log.log_date.get(0..7).unwrap_or_default().to_string();
+            if period.as_ref().map(|p| p == &log_period).unwrap_or(true) {
+                by_period.entry(log_period).or_default().push(log);
+            }
+        }
+
+        for (log_period, entries) in by_period.into_iter() {
+            let (litres_purchased, litres_consumed) = fuel_totals(entries.iter().copied());
+            let running_hours: f64 = entries
+                .iter()
+                .filter_map(|l| Some(l.hour_meter_end? - l.hour_meter_start?))
+                .sum();
+            let expected_litres = running_hours * generator.litres_per_hour;
+            let variance_litres = litres_consumed - expected_litres;
+            let variance_percent = if expected_litres > 0.0 {
+                variance_litres / expected_litres * 100.0
+            } else if litres_consumed > 0.0 {
+                100.0
+            } else {
+                0.0
+            };
+
+            report.push(FuelVarianceItem {
+                generator_id: generator_id.clone(),
+                generator_name: generator.name.clone(),
+                period: log_period,
+                litres_purchased,
+                litres_consumed,
+                running_hours,
+                expected_litres,
+                variance_litres,
+                variance_percent,
+                is_flagged: variance_percent > generator.variance_tolerance_percent,
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+fn generator_fuel_logs(generator_id: &str) -> Result<Vec<(String, FuelLogData)>, String> {
+    Ok(list_doc_data::<FuelLogData>(FUEL_LOGS_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, l)| l.generator_id == generator_id)
+        .map(|(key, _, l)| (key, l))
+        .collect())
+}
+
+/// (litres purchased, litres consumed)
+fn fuel_totals<'a>(logs: impl Iterator<Item = &'a FuelLogData>) -> (f64, f64) {
+    logs.fold((0.0, 0.0), |(purchased, consumed), log| match log.entry_type.as_str() {
+        "purchase" => (purchased + log.litres, consumed),
+        "consumption" => (purchased, consumed + log.litres),
+        _ => (purchased, consumed),
+    })
+}
@@ -0,0 +1,269 @@
+//! Utilities Module - Meter Readings and Cost Anomalies
+//!
+//! Electricity, diesel and water meters (`utility_meters`) are read periodically
+//! (`meter_readings`), each reading optionally linked to the utility expense it pays for.
+//! This module enforces:
+//! - Readings on a meter increase monotonically with the reading date
+//! - Consumption and cost per unit are derived from the previous reading and the expense
+//! - Cost per unit above the meter's threshold versus its trailing average is reported
+//!
+//! Generator fuel purchases and consumption are reconciled in [`fuel`].
+
+pub mod fuel;
+
+use candid::CandidType;
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde::{Deserialize, Serialize};
+
+use super::expenses::ExpenseData;
+use super::utils::doc_utils::*;
+use super::utils::identity::validate_caller_identity;
+use super::utils::money::Money;
+use super::utils::validation_utils::*;
+
+pub const UTILITY_METERS_COLLECTION: &str = "utility_meters";
+pub const METER_READINGS_COLLECTION: &str = "meter_readings";
+
+// Number of earlier priced readings averaged when checking for a cost jump
+const TRAILING_READINGS: usize = 3;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UtilityMeterData {
+    pub name: String,
+    pub utility_type: String,
+    pub unit: String,
+    pub location: Option<String>,
+    pub anomaly_threshold_percent: f64,
+    pub is_active: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterReadingData {
+    pub meter_id: String,
+    pub reading_date: String,
+    pub reading: f64,
+    pub units_consumed: f64,
+    pub expense_id: Option<String>,
+    pub cost: Option<Money>,
+    pub cost_per_unit: Option<f64>,
+    pub notes: Option<String>,
+    pub recorded_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct UtilityCostAnomaly {
+    pub reading_id: String,
+    pub meter_id: String,
+    pub meter_name: String,
+    pub utility_type: String,
+    pub reading_date: String,
+    pub cost_per_unit: f64,
+    pub trailing_average: f64,
+    pub increase_percent: f64,
+    pub threshold_percent: f64,
+}
+
+/// Utility Meter Validation
+///
+/// Checks:
+/// - Known utility type and a unit of measure
+/// - Anomaly threshold is a positive percentage
+pub fn validate_utility_meter_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: UtilityMeterData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid utility meter data format: {}", e))?;
+
+    if data.name.trim().is_empty() {
+        return Err("Meter name is required".to_string());
+    }
+
+    let valid_types = ["electricity", "diesel", "water"];
+    if !valid_types.contains(&data.utility_type.as_str()) {
+        return Err(format!(
+            "Invalid utility type '{}'. Must be one of: {}",
+            data.utility_type,
+            valid_types.join(", ")
+        ));
+    }
+
+    if data.unit.trim().is_empty() {
+        return Err("Unit of measure (e.g. kWh, litres, m3) is required".to_string());
+    }
+    if data.anomaly_threshold_percent <= 0.0 || data.anomaly_threshold_percent > 1000.0 {
+        return Err("anomalyThresholdPercent must be between 0 and 1000".to_string());
+    }
+
+    if let Some(ref before_doc) = context.data.data.current {
+        let before: UtilityMeterData = decode_doc_data(&before_doc.data)
+            .map_err(|e| format!("Invalid previous meter data: {}", e))?;
+        if before.utility_type != data.utility_type || before.unit != data.unit {
+            return Err("Meter utility type and unit cannot be changed; register a new meter instead".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Meter Reading Validation
+///
+/// Checks:
+/// - Meter exists and is active
+/// - One reading per meter per date; readings increase with date
+/// - unitsConsumed equals the increase over the previous reading
+/// - Linked expense exists and its amount is the reading cost
+pub fn validate_meter_reading_document(context: &AssertSetDocContext) -> Result<(), String> {
+    let data: MeterReadingData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid meter reading data format: {}", e))?;
+
+    // Readings are recorded under the caller's own identity
+    validate_caller_identity(context)?;
+
+    if !is_valid_date_format(&data.reading_date) {
+        return Err("Invalid reading date format. Must be YYYY-MM-DD".to_string());
+    }
+    if data.reading < 0.0 {
+        return Err("Meter reading cannot be negative".to_string());
+    }
+
+    let (_, meter) = get_doc_data::<UtilityMeterData>(UTILITY_METERS_COLLECTION, &data.meter_id)?
+        .ok_or_else(|| format!("Utility meter '{}' not found", data.meter_id))?;
+    if !meter.is_active && context.data.data.current.is_none() {
+        return Err(format!("Utility meter '{}' is inactive", meter.name));
+    }
+
+    let readings = meter_readings(&data.meter_id, Some(&context.data.key))?;
+
+    if readings.iter().any(|(_, r)| r.reading_date == data.reading_date) {
+        return Err(format!(
+            "Meter '{}' already has a reading for {}",
+            meter.name, data.reading_date
+        ));
+    }
+
+    let previous = readings.iter().rev().find(|(_, r)| r.reading_date < data.reading_date);
+    let next = readings.iter().find(|(_, r)| r.reading_date > data.reading_date);
+
+    if let Some((_, prev)) = previous {
+        if data.reading <= prev.reading {
+            return Err(format!(
+                "Reading {} must be higher than the previous reading of {} on {}",
+                data.reading, prev.reading, prev.reading_date
+            ));
+        }
+    }
+    if let Some((_, nxt)) = next {
+        if data.reading >= nxt.reading {
+            return Err(format!(
+                "Reading {} must be lower than the later reading of {} on {}",
+                data.reading, nxt.reading, nxt.reading_date
+            ));
+        }
+    }
+
+    let expected_units = previous.map(|(_, p)| data.reading - p.reading).unwrap_or(0.0);
+    if (data.units_consumed - expected_units).abs() > 0.001 {
+        return Err(format!(
+            "unitsConsumed must be {:.3} (increase over the previous reading)",
+            expected_units
+        ));
+    }
+
+    validate_reading_cost(&data)?;
+
+    Ok(())
+}
+
+fn validate_reading_cost(data: &MeterReadingData) -> Result<(), String> {
+    let expense_id = match data.expense_id {
+        Some(ref id) if !id.trim().is_empty() => id,
+        _ => {
+            if data.cost.is_some() || data.cost_per_unit.is_some() {
+                return Err("Reading cost is taken from the linked utility expense".to_string());
+            }
+            return Ok(());
+        }
+    };
+
+    let (_, expense) = get_doc_data::<ExpenseData>("expenses", expense_id)?
+        .ok_or_else(|| format!("Expense '{}' not found", expense_id))?;
+
+    if expense.status == "rejected" {
+        return Err("Meter readings cannot be linked to a rejected expense".to_string());
+    }
+
+    let cost = data.cost.ok_or("cost is required when an expense is linked")?;
+    if cost != expense.amount {
+        return Err(format!(
+            "Reading cost ₦{} must equal the linked expense amount ₦{}",
+            cost, expense.amount
+        ));
+    }
+
+    let expected_rate = if data.units_consumed > 0.0 { Some(cost.naira() / data.units_consumed) } else { None };
+    match (data.cost_per_unit, expected_rate) {
+        (Some(rate), Some(expected)) if (rate - expected).abs() <= 0.01 => Ok(()),
+        (None, None) => Ok(()),
+        (_, Some(expected)) => Err(format!("costPerUnit must be {:.2} (cost / unitsConsumed)", expected)),
+        (Some(_), None) => Err("costPerUnit cannot be set without consumption since the previous reading".to_string()),
+    }
+}
+
+/// Readings whose cost per unit exceeds the trailing average of the meter's earlier
+/// priced readings by more than the meter's threshold.
+pub fn list_cost_anomalies() -> Result<Vec<UtilityCostAnomaly>, String> {
+    let meters = list_doc_data::<UtilityMeterData>(UTILITY_METERS_COLLECTION, None)?;
+
+    let mut anomalies = Vec::new();
+    for (meter_id, _, meter) in meters.iter() {
+        let priced: Vec<(String, MeterReadingData)> = meter_readings(meter_id, None)?
+            .into_iter()
+            .filter(|(_, r)| r.cost_per_unit.is_some())
+            .collect();
+
+        for (index, (reading_id, reading)) in priced.iter().enumerate().skip(1) {
+            let window = &priced[index.saturating_sub(TRAILING_READINGS)..index];
+            let trailing_average =
+                window.iter().filter_map(|(_, r)| r.cost_per_unit).sum::<f64>() / window.len() as f64;
+            let cost_per_unit = reading.cost_per_unit.unwrap_or(0.0);
+
+            if trailing_average <= 0.0 {
+                continue;
+            }
+
+            let increase_percent = (cost_per_unit - trailing_average) / trailing_average * 100.0;
+            if increase_percent > meter.anomaly_threshold_percent {
+                anomalies.push(UtilityCostAnomaly {
+                    reading_id: reading_id.clone(),
+                    meter_id: meter_id.clone(),
+                    meter_name: meter.name.clone(),
+                    utility_type: meter.utility_type.clone(),
+                    reading_date: reading.reading_date.clone(),
+                    cost_per_unit,
+                    trailing_average,
+                    increase_percent,
+                    threshold_percent: meter.anomaly_threshold_percent,
+                });
+            }
+        }
+    }
+
+    anomalies.sort_by(|a, b| b.reading_date.cmp(&a.reading_date));
+    Ok(anomalies)
+}
+
+/// Readings for a meter ordered by reading date, optionally excluding one document.
+fn meter_readings(meter_id: &str, exclude_key: Option<&str>) -> Result<Vec<(String, MeterReadingData)>, String> {
+    let mut readings: Vec<(String, MeterReadingData)> =
+        list_doc_data::<MeterReadingData>(METER_READINGS_COLLECTION, None)?
+            .into_iter()
+            .filter(|(key, _, r)| r.meter_id == meter_id && Some(key.as_str()) != exclude_key)
+            .map(|(key, _, r)| (key, r))
+            .collect();
+
+    readings.sort_by(|a, b| a.1.reading_date.cmp(&b.1.reading_date));
+    Ok(readings)
+}
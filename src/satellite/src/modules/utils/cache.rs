@@ -0,0 +1,80 @@
+//! Read-through cache of small, hot lookup collections
+//!
+//! Validators look up the same reference data (settings, categories, classes, the chart
+//! of accounts) on almost every write. The first read of a cached collection loads all
+//! its documents into canister memory; later reads are served from there until a write
+//! or delete to the collection invalidates it. Invalidation runs from the satellite's
+//! assert hooks once a write to the collection has been accepted, so the next read
+//! reloads the collection with the change. The cache is heap memory only and starts
+//! empty after an upgrade.
+
+use junobuild_satellite::{id, list_docs_store, Doc};
+use junobuild_shared::types::list::ListParams;
+use junobuild_utils::decode_doc_data;
+use serde::de::DeserializeOwned;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Lookup collections served from the cache
+pub const CACHED_COLLECTIONS: [&str; 5] =
+    ["chart_of_accounts", "classes", "expense_categories", "fee_categories", "school_settings"];
+
+type CachedDocs = Rc<HashMap<String, Doc>>;
+
+thread_local! {
+    static CACHE: RefCell<HashMap<&'static str, CachedDocs>> = RefCell::new(HashMap::new());
+}
+
+/// Drop the cached documents of a collection, if it is cached.
+pub fn invalidate(collection: &str) {
+    CACHE.with(|cache| cache.borrow_mut().retain(|cached, _| *cached != collection));
+}
+
+/// Load a document of a cached collection by key and decode its data.
+pub fn cached_doc_data<T: DeserializeOwned>(collection: &str, key: &str) -> Result<Option<(Doc, T)>, String> {
+    match cached_docs(collection)?.get(key) {
+        Some(doc) => {
+            let data: T = decode_doc_data(&doc.data)
+                .map_err(|e| format!("Invalid {} data for '{}': {}", collection, key, e))?;
+            Ok(Some((doc.clone(), data)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// True when a document with the given key exists in a cached collection.
+pub fn cached_doc_exists(collection: &str, key: &str) -> Result<bool, String> {
+    Ok(cached_docs(collection)?.contains_key(key))
+}
+
+/// All documents of a cached collection, decoded, in key order. Documents that cannot be
+/// decoded are skipped.
+pub fn cached_list_doc_data<T: DeserializeOwned>(collection: &str) -> Result<Vec<(String, Doc, T)>, String> {
+    let docs = cached_docs(collection)?;
+    let mut items: Vec<(String, Doc, T)> = docs
+        .iter()
+        .filter_map(|(key, doc)| decode_doc_data::<T>(&doc.data).ok().map(|data| (key.clone(), doc.clone(), data)))
+        .collect();
+    items.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(items)
+}
+
+fn cached_docs(collection: &str) -> Result<CachedDocs, String> {
+    let name = CACHED_COLLECTIONS
+        .iter()
+        .find(|c| **c == collection)
+        .ok_or_else(|| format!("Collection '{}' is not cached", collection))?;
+
+    if let Some(docs) = CACHE.with(|cache| cache.borrow().get(name).cloned()) {
+        return Ok(docs);
+    }
+    let docs: CachedDocs = Rc::new(
+        list_docs_store(id(), collection.to_string(), &ListParams::default())?
+            .items
+            .into_iter()
+            .collect(),
+    );
+    CACHE.with(|cache| cache.borrow_mut().insert(name, docs.clone()));
+    Ok(docs)
+}
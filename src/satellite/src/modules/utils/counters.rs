@@ -0,0 +1,41 @@
+//! Sequential counters kept in the `counters` collection.
+//!
+//! Each counter is a document holding the last number issued. Because it lives in the
+//! datastore it survives upgrades, and because updates carry the document version two
+//! writes can never issue the same number.
+
+use junobuild_satellite::AssertSetDocContext;
+use serde::{Deserialize, Serialize};
+
+use super::doc_utils::*;
+
+pub const COUNTERS_COLLECTION: &str = "counters";
+
+#[derive(Deserialize, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CounterData {
+    pub last_number: u64,
+    pub updated_at: u64,
+}
+
+/// Counters are advanced by the satellite only.
+pub fn validate_counter_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Counters are maintained by the satellite".to_string());
+    }
+    Ok(())
+}
+
+/// Advance the named counter and return the new number, starting at 1.
+pub fn next_number(name: &str) -> Result<u64, String> {
+    let (version, mut counter) = match get_doc_data::<CounterData>(COUNTERS_COLLECTION, name)? {
+        Some((doc, counter)) => (doc.version, counter),
+        None => (None, CounterData::default()),
+    };
+
+    counter.last_number += 1;
+    counter.updated_at = ic_cdk::api::time();
+    set_doc_data(COUNTERS_COLLECTION, name, &counter, None, version)?;
+
+    Ok(counter.last_number)
+}
@@ -0,0 +1,159 @@
+//! Helpers for reading and writing datastore documents from server-side code
+//! (hooks and custom endpoints) on behalf of the satellite itself.
+
+use junobuild_satellite::{delete_doc_store, get_doc_store, id, list_docs_store, set_doc_store, DelDoc, Doc, SetDoc};
+use junobuild_shared::types::list::{ListMatcher, ListParams};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+use candid::CandidType;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::modules::history::record_replaced_version;
+
+/// Load a document by key and decode its data.
+pub fn get_doc_data<T: DeserializeOwned>(collection: &str, key: &str) -> Result<Option<(Doc, T)>, String> {
+    let doc = get_doc_store(id(), collection.to_string(), key.to_string())?;
+
+    match doc {
+        Some(doc) => {
+            let data: T = decode_doc_data(&doc.data)
+                .map_err(|e| format!("Invalid {} data for '{}': {}", collection, key, e))?;
+            Ok(Some((doc, data)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// True when a document with the given key exists in the collection.
+pub fn doc_exists(collection: &str, key: &str) -> Result<bool, String> {
+    Ok(get_doc_store(id(), collection.to_string(), key.to_string())?.is_some())
+}
+
+/// List documents whose description matches the given pattern and decode their data.
+/// Documents that cannot be decoded are skipped.
+pub fn list_doc_data<T: DeserializeOwned>(collection: &str, description: Option<String>) -> Result<Vec<(String, Doc, T)>, String> {
+    let params = ListParams {
+        matcher: Some(ListMatcher {
+            description,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let results = list_docs_store(id(), collection.to_string(), &params)?;
+
+    Ok(results
+        .items
+        .into_iter()
+        .filter_map(|(key, doc)| {
+            decode_doc_data::<T>(&doc.data)
+                .ok()
+                .map(|data| (key, doc, data))
+        })
+        .collect())
+}
+
+/// Keys of the documents in `collection` whose `field` (camelCase, as stored) equals `value`.
+pub fn referencing_keys(collection: &str, field: &str, value: &str) -> Result<Vec<String>, String> {
+    Ok(list_doc_data::<serde_json::Value>(collection, None)?
+        .into_iter()
+        .filter(|(_, _, data)| data.get(field).and_then(|v| v.as_str()) == Some(value))
+        .map(|(key, _, _)| key)
+        .collect())
+}
+
+/// Refuse to delete a document that other documents still reference. `references` pairs
+/// each referencing collection with the keys of the blocking documents.
+pub fn deny_if_referenced(kind: &str, key: &str, references: &[(&str, Vec<String>)]) -> Result<(), String> {
+    const SHOWN_KEYS: usize = 5;
+
+    let blocking: Vec<String> = references
+        .iter()
+        .filter(|(_, keys)| !keys.is_empty())
+        .map(|(collection, keys)| {
+            let mut shown = keys.iter().take(SHOWN_KEYS).cloned().collect::<Vec<_>>().join(", ");
+            if keys.len() > SHOWN_KEYS {
+                shown.push_str(&format!(" and {} more", keys.len() - SHOWN_KEYS));
+            }
+            format!("{} {} [{}]", keys.len(), collection, shown)
+        })
+        .collect();
+
+    if blocking.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Cannot delete {} '{}': still referenced by {}",
+        kind,
+        key,
+        blocking.join("; ")
+    ))
+}
+
+/// Create or update a document as the satellite. `version` must be the version of the
+/// current document when updating, `None` when creating. Updates to collections with a
+/// history store keep the replaced version (see [`crate::modules::history`]).
+pub fn set_doc_data<T: Serialize>(
+    collection: &str,
+    key: &str,
+    data: &T,
+    description: Option<String>,
+    version: Option<u64>,
+) -> Result<Doc, String> {
+    let encoded = encode_doc_data(data)?;
+
+    let result = set_doc_store(
+        id(),
+        collection.to_string(),
+        key.to_string(),
+        SetDoc {
+            data: encoded,
+            description,
+            version,
+        },
+    )?;
+
+    // The satellite's writes skip the on-set hook, so the replaced version of a document
+    // with a history store is kept here
+    if let Some(ref before) = result.data.before {
+        record_replaced_version(collection, key, before)?;
+    }
+
+    Ok(result.data.after)
+}
+
+/// Delete a document as the satellite. `version` must be the version of the current
+/// document.
+pub fn delete_doc_data(collection: &str, key: &str, version: Option<u64>) -> Result<(), String> {
+    delete_doc_store(id(), collection.to_string(), key.to_string(), DelDoc { version })?;
+    Ok(())
+}
+
+/// True when the hook was triggered by the satellite's own server-side writes.
+pub fn is_satellite_caller(caller: &candid::Principal) -> bool {
+    *caller == id()
+}
+
+// Largest page a listing endpoint returns
+pub const MAX_PAGE_LIMIT: u32 = 200;
+
+/// Page of results requested from a listing endpoint.
+#[derive(CandidType, Deserialize, Serialize, Clone)]
+pub struct Pagination {
+    pub offset: u32,
+    pub limit: u32,
+}
+
+impl Pagination {
+    /// Reject empty or oversized pages.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.limit == 0 || self.limit > MAX_PAGE_LIMIT {
+            return Err(format!("Page limit must be between 1 and {}", MAX_PAGE_LIMIT));
+        }
+        Ok(())
+    }
+
+    /// The items of this page, from an already sorted list.
+    pub fn slice<T>(&self, items: Vec<T>) -> Vec<T> {
+        items.into_iter().skip(self.offset as usize).take(self.limit as usize).collect()
+    }
+}
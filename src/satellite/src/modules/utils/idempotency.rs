@@ -0,0 +1,172 @@
+//! Idempotency keys for update calls.
+//!
+//! A client that loses the reply to an update call (a network drop, a timeout) cannot tell
+//! whether it ran, and retrying may post it twice. Financial update calls therefore take
+//! an optional client-supplied idempotency key: the first call with a key runs and its
+//! result is kept in `idempotency_keys` (a stable-memory collection, so records survive
+//! upgrades); a retry with the same key returns that result instead of running again.
+//!
+//! Keys are scoped to the caller and the call, and tied to the request: reusing a key for
+//! different arguments is an error. A call that fails keeps nothing, so it can be retried
+//! with the same key. Records are pruned after seven days by a daily job.
+//!
+//! Documents the app writes itself (single payments, transfers) need no key: a retried
+//! write of the same document key is rejected by its version check.
+
+use candid::CandidType;
+use junobuild_satellite::AssertSetDocContext;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::doc_utils::*;
+use super::scan::scan_collection;
+
+pub const IDEMPOTENCY_KEYS_COLLECTION: &str = "idempotency_keys";
+
+const MAX_KEY_LENGTH: usize = 100;
+// A call still running after this long is taken to have failed, and may be retried
+const PENDING_TIMEOUT_NS: u64 = 10 * 60 * 1_000_000_000;
+const RETENTION_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdempotencyRecordData {
+    pub operation: String,
+    pub caller: String,
+    // SHA-256 of the Candid-encoded arguments
+    pub request_hash: String,
+    // `pending` while the call runs, then `completed`
+    pub status: String,
+    pub result: Option<serde_json::Value>,
+    pub created_at: u64,
+    pub completed_at: Option<u64>,
+}
+
+/// Idempotency records are kept by the satellite only.
+pub fn validate_idempotency_key_document(context: &AssertSetDocContext) -> Result<(), String> {
+    if !is_satellite_caller(&context.caller) {
+        return Err("SECURITY: Idempotency records are maintained by the satellite".to_string());
+    }
+    Ok(())
+}
+
+/// Idempotency records are pruned by the satellite only.
+pub fn validate_idempotency_key_delete(caller: &candid::Principal) -> Result<(), String> {
+    if !is_satellite_caller(caller) {
+        return Err("SECURITY: Idempotency records are pruned by the satellite".to_string());
+    }
+    Ok(())
+}
+
+/// Run `run` once per idempotency key: a retry returns the first call's result. Without a
+/// key the call simply runs.
+pub fn idempotent<A: CandidType, R: Serialize + DeserializeOwned>(
+    operation: &str,
+    key: Option<String>,
+    args: &A,
+    run: impl FnOnce() -> Result<R, String>,
+) -> Result<R, String> {
+    let key = match key {
+        Some(key) => key,
+        None => return run(),
+    };
+    if let Some(result) = begin(operation, &key, args)? {
+        return Ok(result);
+    }
+    let result = run();
+    finish(operation, &key, &result)?;
+    result
+}
+
+/// Claim `key` for a call to `operation` with `args`. Returns the stored result when the
+/// call already completed; otherwise the call should run and then `finish`.
+pub fn begin<A: CandidType, R: DeserializeOwned>(operation: &str, key: &str, args: &A) -> Result<Option<R>, String> {
+    if key.is_empty()
+        || key.len() > MAX_KEY_LENGTH
+        || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Idempotency key must be 1-{} letters, digits, hyphens or underscores", MAX_KEY_LENGTH));
+    }
+
+    let caller = junobuild_satellite::caller().to_text();
+    let request_hash = request_hash(args)?;
+    let now = ic_cdk::api::time();
+    let doc_key = record_key(operation, &caller, key);
+
+    let version = match get_doc_data::<IdempotencyRecordData>(IDEMPOTENCY_KEYS_COLLECTION, &doc_key)? {
+        Some((doc, record)) => {
+            if record.request_hash != request_hash {
+                return Err(format!("Idempotency key '{}' was already used for a different request", key));
+            }
+            match record.result {
+                Some(result) if record.status == "completed" => {
+                    let result = serde_json::from_value(result)
+                        .map_err(|e| format!("Stored result for idempotency key '{}' is unreadable: {}", key, e))?;
+                    return Ok(Some(result));
+                }
+                _ if now.saturating_sub(record.created_at) < PENDING_TIMEOUT_NS => {
+                    return Err(format!("A request with idempotency key '{}' is still being processed", key));
+                }
+                _ => doc.version,
+            }
+        }
+        None => None,
+    };
+
+    let record = IdempotencyRecordData {
+        operation: operation.to_string(),
+        caller,
+        request_hash,
+        status: "pending".to_string(),
+        result: None,
+        created_at: now,
+        completed_at: None,
+    };
+    set_doc_data(IDEMPOTENCY_KEYS_COLLECTION, &doc_key, &record, Some(format!("operation={};", operation)), version)?;
+
+    Ok(None)
+}
+
+/// Keep the result of a call claimed with `begin`. A failed call releases its key.
+pub fn finish<R: Serialize>(operation: &str, key: &str, result: &Result<R, String>) -> Result<(), String> {
+    let doc_key = record_key(operation, &junobuild_satellite::caller().to_text(), key);
+    let (doc, mut record) = match get_doc_data::<IdempotencyRecordData>(IDEMPOTENCY_KEYS_COLLECTION, &doc_key)? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+
+    match result {
+        Ok(result) => {
+            record.result = Some(serde_json::to_value(result).map_err(|e| format!("Could not store result: {}", e))?);
+            record.status = "completed".to_string();
+            record.completed_at = Some(ic_cdk::api::time());
+            set_doc_data(IDEMPOTENCY_KEYS_COLLECTION, &doc_key, &record, doc.description, doc.version)?;
+        }
+        Err(_) => delete_doc_data(IDEMPOTENCY_KEYS_COLLECTION, &doc_key, doc.version)?,
+    }
+
+    Ok(())
+}
+
+/// Daily job: deletes idempotency records older than the retention period.
+pub fn prune_idempotency_keys(cursor: Option<String>) -> Result<Option<String>, String> {
+    let now = ic_cdk::api::time();
+    let progress =
+        scan_collection::<IdempotencyRecordData>(IDEMPOTENCY_KEYS_COLLECTION, cursor, |key, doc, record| {
+            if now.saturating_sub(record.created_at) > RETENTION_NS {
+                delete_doc_data(IDEMPOTENCY_KEYS_COLLECTION, &key, doc.version)?;
+            }
+            Ok(())
+        })?;
+
+    Ok(progress.next_cursor)
+}
+
+fn record_key(operation: &str, caller: &str, key: &str) -> String {
+    format!("{}:{}:{}", operation, caller, key)
+}
+
+fn request_hash<A: CandidType>(args: &A) -> Result<String, String> {
+    let encoded = candid::encode_one(args).map_err(|e| format!("Could not encode request: {}", e))?;
+    Ok(format!("{:x}", Sha256::digest(&encoded)))
+}
@@ -0,0 +1,108 @@
+//! Caller identity on recorded documents
+//!
+//! Documents name who recorded, approved or processed them (`recordedBy`, `approvedBy`,
+//! `processedBy`). The frontend fills these in, so without a check a user could record an
+//! expense as another staff member. A value set or changed by a write must name the
+//! caller: either its principal or the key of its app-user profile in `users` (the
+//! profile whose `internetIdentityId` is the principal), which is what the forms send.
+//! Values carried over unchanged from the stored document are left alone,
+//! so later edits by other users keep the original recorder. Writes by the satellite
+//! itself (payroll runs, automatic allocation) are exempt.
+//!
+//! Approvals also go by the caller: whoever approves an expense, salary payment or
+//! transfer cannot be the user who initiated it, judged by the stored document's owner
+//! and initiator field rather than what the write claims. The `allowSelfApproval` school
+//! setting lifts this for development and testing.
+
+use candid::Principal;
+use junobuild_satellite::AssertSetDocContext;
+use junobuild_utils::decode_doc_data;
+use serde_json::Value;
+
+use super::doc_utils::{is_satellite_caller, list_doc_data};
+use crate::modules::settings::school_settings;
+
+pub const USERS_COLLECTION: &str = "users";
+
+// Identity fields, as stored (camelCase)
+pub const IDENTITY_FIELDS: [&str; 3] = ["recordedBy", "approvedBy", "processedBy"];
+
+/// Reject a write that sets any identity field to someone other than the caller.
+pub fn validate_caller_identity(context: &AssertSetDocContext) -> Result<(), String> {
+    if is_satellite_caller(&context.caller) {
+        return Ok(());
+    }
+
+    let proposed: Value = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid document data: {}", e))?;
+    let current: Option<Value> = match context.data.data.current {
+        Some(ref doc) => Some(decode_doc_data(&doc.data).map_err(|e| format!("Invalid previous document data: {}", e))?),
+        None => None,
+    };
+    let identities = caller_identities(&context.caller)?;
+
+    for field in IDENTITY_FIELDS {
+        let Some(value) = proposed.get(field).and_then(Value::as_str) else {
+            continue;
+        };
+        let before = current.as_ref().and_then(|c| c.get(field)).and_then(Value::as_str);
+        if before == Some(value) {
+            continue;
+        }
+        if !identities.iter().any(|id| id == value) {
+            return Err(format!(
+                "SECURITY: {} must be the caller's own identity ({}), not '{}'",
+                field,
+                context.caller.to_text(),
+                value
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The values that name the caller on a document: its principal, followed by the keys
+/// of its app-user profiles.
+pub fn caller_identities(caller: &Principal) -> Result<Vec<String>, String> {
+    let principal = caller.to_text();
+    let mut identities: Vec<String> = list_doc_data::<Value>(USERS_COLLECTION, None)?
+        .into_iter()
+        .filter(|(_, _, user)| user.get("internetIdentityId").and_then(Value::as_str) == Some(principal.as_str()))
+        .map(|(key, _, _)| key)
+        .collect();
+    identities.insert(0, principal);
+    Ok(identities)
+}
+
+/// Reject an approval by the user who initiated the document: its owner, or the user
+/// named in `initiator_field` of the stored document (by principal or app-user key). Approving in the same write that
+/// creates the document is always self-approval.
+pub fn validate_not_self_approval(
+    context: &AssertSetDocContext,
+    initiator_field: Option<&str>,
+    what: &str,
+) -> Result<(), String> {
+    if is_satellite_caller(&context.caller) || school_settings()?.allow_self_approval {
+        return Ok(());
+    }
+
+    let identities = caller_identities(&context.caller)?;
+    let self_approval = match context.data.data.current {
+        None => true,
+        Some(ref doc) => {
+            let initiator = match initiator_field {
+                Some(field) => {
+                    let stored: Value = decode_doc_data(&doc.data)
+                        .map_err(|e| format!("Invalid previous document data: {}", e))?;
+                    stored.get(field).and_then(Value::as_str).map(String::from)
+                }
+                None => None,
+            };
+            doc.owner == context.caller || initiator.is_some_and(|initiator| identities.contains(&initiator))
+        }
+    };
+    if self_approval {
+        return Err(format!("SECURITY: Users cannot approve {} they initiated", what));
+    }
+    Ok(())
+}
@@ -1,6 +1,14 @@
 //! Utility modules for the satellite crate
 
+pub mod cache;
+pub mod counters;
+pub mod doc_utils;
+pub mod idempotency;
+pub mod identity;
+pub mod money;
+pub mod scan;
 pub mod validation_utils;
 
 // Re-export commonly used utilities
+pub use doc_utils::*;
 pub use validation_utils::*;
@@ -0,0 +1,162 @@
+//! Exact monetary amounts in kobo (1/100 naira)
+//!
+//! Amounts are held as whole kobo so sums and differences (net salary, allocation totals,
+//! balances) are exact instead of compared within a floating-point tolerance.
+//!
+//! Migration window: documents written by the current frontend store naira as JSON
+//! numbers (e.g. `1500.5`), while migrated documents store kobo as a Juno bigint
+//! (`{"__bigint__": "150050"}`). Both are accepted. Amounts are still written back as
+//! naira numbers until every client reads the bigint encoding.
+
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub const fn from_kobo(kobo: i64) -> Money {
+        Money(kobo)
+    }
+
+    /// Convert a naira amount, rounding to the nearest kobo.
+    pub fn from_naira(naira: f64) -> Money {
+        Money((naira * 100.0).round() as i64)
+    }
+
+    pub fn kobo(self) -> i64 {
+        self.0
+    }
+
+    pub fn naira(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// `percent`% of the amount, rounded to the nearest kobo.
+    pub fn percent(self, percent: f64) -> Money {
+        Money((self.0 as f64 * percent / 100.0).round() as i64)
+    }
+}
+
+impl fmt::Display for Money {
+    // Plain `1234.56` so existing `₦{:.2}` messages read the same
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        write!(f, "{}{}.{:02}", sign, self.0.unsigned_abs() / 100, self.0.unsigned_abs() % 100)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+    fn add(self, rhs: Money) -> Money {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+    fn sub(self, rhs: Money) -> Money {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Money) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        Money(iter.map(|m| m.0).sum())
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        Money(iter.map(|m| m.0).sum())
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.naira())
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+struct MoneyVisitor;
+
+impl<'de> Visitor<'de> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a naira amount or an object with a key __bigint__ holding kobo")
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Money, E> {
+        if !value.is_finite() {
+            return Err(E::custom("amount must be a finite number"));
+        }
+        Ok(Money::from_naira(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Money, E> {
+        value.checked_mul(100).map(Money).ok_or_else(|| E::custom("amount out of range"))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Money, E> {
+        i64::try_from(value)
+            .ok()
+            .and_then(|v| v.checked_mul(100))
+            .map(Money)
+            .ok_or_else(|| E::custom("amount out of range"))
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Money, M::Error> {
+        let mut kobo: Option<i64> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key != "__bigint__" {
+                return Err(de::Error::unknown_field(&key, &["__bigint__"]));
+            }
+            let value: String = map.next_value()?;
+            kobo = Some(value.parse().map_err(|_| de::Error::custom("invalid __bigint__ kobo amount"))?);
+        }
+        kobo.map(Money).ok_or_else(|| de::Error::missing_field("__bigint__"))
+    }
+}
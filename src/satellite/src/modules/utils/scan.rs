@@ -0,0 +1,105 @@
+//! Chunked scans of whole collections, kept under the instruction limit
+//!
+//! A message that walks a large collection in one go can hit the canister's instruction
+//! limit and trap, losing its work. `scan_collection` reads the collection in key order,
+//! a chunk at a time, and checks the instruction counter after every document. Once the
+//! budget is spent it stops and returns a cursor token; passing the token back resumes
+//! after the last document visited.
+//!
+//! The daily jobs (investment accruals, recurring journals, prepayment amortization, late
+//! fees, report rollups, idempotency pruning) and `rebuild_category_usage` run on it and
+//! pick up where the previous message stopped.
+//!
+//! Not covered: report queries (`get_financial_summary`, `list_debtors`,
+//! `get_data_quality`, `audit_sequences`, the close checklist) and the interactive batch
+//! endpoints (`create_payroll_run`, `promote_students`, `assign_fees_to_class`). Those
+//! still read their collections with `list_doc_data` in a single message: a query cannot
+//! keep totals between messages, and the batches check every document before writing
+//! any, which a resumed call could not honour. Until they move to stored aggregates they
+//! are bounded only by the size of the collections they read.
+
+use junobuild_satellite::{id, list_docs_store, Doc};
+use junobuild_shared::types::list::{ListOrder, ListOrderField, ListPaginate, ListParams};
+use junobuild_utils::decode_doc_data;
+use serde::de::DeserializeOwned;
+
+// Documents read from the store at a time
+const SCAN_CHUNK_SIZE: usize = 100;
+
+// Instructions a scan may use before it stops and hands back a cursor. Well under the
+// query limit (5B), so the same scan is safe in queries, updates and timers.
+pub const SCAN_INSTRUCTION_BUDGET: u64 = 3_000_000_000;
+
+/// Where a scan stopped.
+pub struct ScanProgress {
+    pub visited: u32,
+    // Token to resume from; None once the collection has been scanned to the end
+    pub next_cursor: Option<String>,
+}
+
+/// Visit the documents of `collection` in key order, starting after `cursor`, until the
+/// end of the collection or the instruction budget. Documents that cannot be decoded are
+/// skipped.
+pub fn scan_collection<T: DeserializeOwned>(
+    collection: &str,
+    cursor: Option<String>,
+    mut visit: impl FnMut(String, Doc, T) -> Result<(), String>,
+) -> Result<ScanProgress, String> {
+    let mut last_key = match cursor {
+        Some(token) => Some(decode_cursor(collection, &token)?),
+        None => None,
+    };
+    let mut visited = 0u32;
+
+    loop {
+        let params = ListParams {
+            paginate: Some(ListPaginate {
+                start_after: last_key.clone(),
+                limit: Some(SCAN_CHUNK_SIZE),
+            }),
+            order: Some(ListOrder {
+                desc: false,
+                field: ListOrderField::Keys,
+            }),
+            ..Default::default()
+        };
+        let chunk = list_docs_store(id(), collection.to_string(), &params)?.items;
+        let chunk_len = chunk.len();
+
+        for (key, doc) in chunk {
+            last_key = Some(key.clone());
+            if let Ok(data) = decode_doc_data::<T>(&doc.data) {
+                visit(key, doc, data)?;
+                visited += 1;
+            }
+            if ic_cdk::api::instruction_counter() > SCAN_INSTRUCTION_BUDGET {
+                return Ok(ScanProgress {
+                    visited,
+                    next_cursor: last_key.map(|key| encode_cursor(collection, &key)),
+                });
+            }
+        }
+
+        if chunk_len < SCAN_CHUNK_SIZE {
+            return Ok(ScanProgress { visited, next_cursor: None });
+        }
+    }
+}
+
+/// The collection a cursor token resumes, for scans that walk several collections in turn.
+pub fn cursor_collection(token: &str) -> Option<&str> {
+    token.split_once(':').map(|(collection, _)| collection)
+}
+
+// A cursor names its collection so it cannot be replayed against another one
+fn encode_cursor(collection: &str, key: &str) -> String {
+    format!("{}:{}", collection, key)
+}
+
+fn decode_cursor(collection: &str, token: &str) -> Result<String, String> {
+    token
+        .strip_prefix(collection)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .map(|key| key.to_string())
+        .ok_or_else(|| format!("Invalid scan cursor for {}", collection))
+}
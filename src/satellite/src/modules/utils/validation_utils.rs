@@ -1,23 +1,31 @@
 //! Utility functions for validation across different modules
+//!
+//! Dates are YYYY-MM-DD strings on the proleptic Gregorian calendar (UTC), handled with
+//! the `time` crate: impossible dates such as 2023-02-29 or 2024-04-31 are rejected, and
+//! day arithmetic follows real month lengths and leap years.
 
 use serde::Deserialize;
+use time::{Date, Duration, Month, OffsetDateTime};
 
 // Helper functions that can be used across modules
 pub fn parse_date(date: &str) -> Result<(u32, u32, u32), ()> {
+    let date = calendar_date(date).ok_or(())?;
+    Ok((date.year() as u32, date.month() as u32, date.day() as u32))
+}
+
+// The calendar date of a YYYY-MM-DD string, if it exists
+pub fn calendar_date(date: &str) -> Option<Date> {
     let parts: Vec<&str> = date.split('-').collect();
-    if parts.len() != 3 { return Err(()); }
-    
-    let year = parts[0].parse::<u32>().map_err(|_| ())?;
-    let month = parts[1].parse::<u32>().map_err(|_| ())?;
-    let day = parts[2].parse::<u32>().map_err(|_| ())?;
-    
-    Ok((year, month, day))
+    if parts.len() != 3 { return None; }
+
+    let year = parts[0].parse::<i32>().ok()?;
+    let month = Month::try_from(parts[1].parse::<u8>().ok()?).ok()?;
+    let day = parts[2].parse::<u8>().ok()?;
+    Date::from_calendar_date(year, month, day).ok()
 }
 
-pub fn date_to_timestamp(year: u32, month: u32, day: u32) -> u64 {
-    // Simple timestamp calculation (approximate)
-    let days_since_1970 = (year - 1970) * 365 + (month - 1) * 30 + day;
-    days_since_1970 as u64 * 24 * 60 * 60 * 1_000_000_000 // Convert to nanoseconds
+fn format_date(date: Date) -> String {
+    format!("{:04}-{:02}-{:02}", date.year(), date.month() as u8, date.day())
 }
 
 // Email validation
@@ -44,90 +52,139 @@ pub fn is_valid_url(url: &str) -> bool {
     url.starts_with("http://") || url.starts_with("https://")
 }
 
-// Date format validation
+// Date format validation: YYYY-MM-DD and a date that exists
 pub fn is_valid_date_format(date: &str) -> bool {
-    if date.len() != 10 { return false; }
-    let parts: Vec<&str> = date.split('-').collect();
-    if parts.len() != 3 { return false; }
-    
+    let bytes = date.as_bytes();
+    if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' { return false; }
+    if !date.chars().enumerate().all(|(i, c)| i == 4 || i == 7 || c.is_ascii_digit()) { return false; }
+
+    calendar_date(date).is_some()
+}
+
+// Period format validation (YYYY-MM)
+pub fn is_valid_period(period: &str) -> bool {
+    if period.len() != 7 { return false; }
+    let parts: Vec<&str> = period.split('-').collect();
+    if parts.len() != 2 { return false; }
+
     if parts[0].len() != 4 || !parts[0].chars().all(|c| c.is_numeric()) { return false; }
     if parts[1].len() != 2 || !parts[1].chars().all(|c| c.is_numeric()) { return false; }
-    if parts[2].len() != 2 || !parts[2].chars().all(|c| c.is_numeric()) { return false; }
-    
+
     let month: u32 = parts[1].parse().unwrap_or(0);
-    let day: u32 = parts[2].parse().unwrap_or(0);
-    month >= 1 && month <= 12 && day >= 1 && day <= 31
+    (1..=12).contains(&month)
 }
 
-// Date validation functions
-pub fn is_date_in_future(date: &str) -> bool {
-    if let Ok(parsed_date) = parse_date(date) {
-        let current_time = ic_cdk::api::time();
-        let date_timestamp = date_to_timestamp(parsed_date.0, parsed_date.1, parsed_date.2);
-        date_timestamp > current_time
-    } else {
-        false
+// Academic/fiscal year validation (YYYY/YYYY, consecutive years)
+pub fn is_valid_academic_year(year: &str) -> bool {
+    let parts: Vec<&str> = year.split('/').collect();
+    if parts.len() != 2 { return false; }
+    if parts.iter().any(|p| p.len() != 4 || !p.chars().all(|c| c.is_ascii_digit())) { return false; }
+
+    let start: u32 = parts[0].parse().unwrap_or(0);
+    let end: u32 = parts[1].parse().unwrap_or(0);
+    end == start + 1
+}
+
+// Academic year (YYYY/YYYY) a date falls in; the school year starts in September
+pub fn academic_year_for_date(date: &str) -> Option<String> {
+    let (year, month, _) = parse_date(date).ok()?;
+    let start = if month >= 9 { year } else { year - 1 };
+    Some(format!("{}/{}", start, start + 1))
+}
+
+// Term a date falls in: first (Sep–Dec), second (Jan–Apr) or third (May–Aug)
+pub fn term_for_date(date: &str) -> Option<&'static str> {
+    let (_, month, _) = parse_date(date).ok()?;
+    Some(match month {
+        9..=12 => "first",
+        1..=4 => "second",
+        _ => "third",
+    })
+}
+
+// Whole days from `from` to `to` (both YYYY-MM-DD); negative when `to` is earlier
+pub fn days_between(from: &str, to: &str) -> Option<i64> {
+    Some((calendar_date(to)? - calendar_date(from)?).whole_days())
+}
+
+// Today's date (UTC) as YYYY-MM-DD
+pub fn current_date() -> String {
+    date_from_timestamp(ic_cdk::api::time())
+}
+
+// YYYY-MM-DD (UTC) of a nanosecond timestamp
+pub fn date_from_timestamp(timestamp: u64) -> String {
+    match OffsetDateTime::from_unix_timestamp_nanos(timestamp as i128) {
+        Ok(time) => format_date(time.date()),
+        Err(_) => String::new(),
     }
 }
 
+// Last day of the month before the given YYYY-MM-DD date
+pub fn previous_month_end(date: &str) -> Option<String> {
+    let date = calendar_date(date)?;
+    Some(format_date(date.replace_day(1).ok()?.previous_day()?))
+}
+
+// The month (YYYY-MM) after a YYYY-MM period
+pub fn next_period(period: &str) -> Option<String> {
+    let (year, month, _) = parse_date(&format!("{}-01", period)).ok()?;
+    Some(if month == 12 { format!("{:04}-01", year + 1) } else { format!("{:04}-{:02}", year, month + 1) })
+}
+
+// Last day (YYYY-MM-DD) of a YYYY-MM period
+pub fn period_end(period: &str) -> Option<String> {
+    previous_month_end(&format!("{}-01", next_period(period)?))
+}
+
+// Date validation functions: dates that do not exist are neither in the future nor old
+pub fn is_date_in_future(date: &str) -> bool {
+    is_more_than_days_after(date, &current_date(), 0)
+}
+
 pub fn is_date_too_far_in_future(date: &str) -> bool {
-    if let Ok(parsed_date) = parse_date(date) {
-        let current_time = ic_cdk::api::time();
-        let date_timestamp = date_to_timestamp(parsed_date.0, parsed_date.1, parsed_date.2);
-        let seven_days = 7 * 24 * 60 * 60 * 1_000_000_000u64; // 7 days in nanoseconds
-        
-        date_timestamp > current_time + seven_days
-    } else {
-        false
-    }
+    is_more_than_days_after(date, &current_date(), 7)
 }
 
 pub fn is_date_too_far_in_future_30_days(date: &str) -> bool {
-    if let Ok(parsed_date) = parse_date(date) {
-        let current_time = ic_cdk::api::time();
-        let date_timestamp = date_to_timestamp(parsed_date.0, parsed_date.1, parsed_date.2);
-        let thirty_days = 30 * 24 * 60 * 60 * 1_000_000_000u64; // 30 days in nanoseconds
-        
-        date_timestamp > current_time + thirty_days
-    } else {
-        false
-    }
+    is_more_than_days_after(date, &current_date(), 30)
 }
 
 pub fn is_date_too_old(date: &str, years: i32) -> bool {
-    if let Ok(parsed_date) = parse_date(date) {
-        let current_time = ic_cdk::api::time();
-        let date_timestamp = date_to_timestamp(parsed_date.0, parsed_date.1, parsed_date.2);
-        let years_ns = (years as u64) * 365 * 24 * 60 * 60 * 1_000_000_000u64; // years in nanoseconds
-        
-        date_timestamp < current_time - years_ns
-    } else {
-        false
-    }
+    is_more_than_years_before(date, &current_date(), years)
 }
 
 pub fn is_date_too_old_2_years(date: &str) -> bool {
-    if let Ok(parsed_date) = parse_date(date) {
-        let current_time = ic_cdk::api::time();
-        let date_timestamp = date_to_timestamp(parsed_date.0, parsed_date.1, parsed_date.2);
-        let two_years = 2 * 365 * 24 * 60 * 60 * 1_000_000_000u64; // 2 years in nanoseconds
-        
-        date_timestamp < current_time - two_years
-    } else {
-        false
-    }
+    is_more_than_years_before(date, &current_date(), 2)
 }
 
 // Staff-specific utility functions
 pub fn is_employment_date_too_old(date: &str) -> bool {
-    if let Ok(parsed_date) = parse_date(date) {
-        let current_time = ic_cdk::api::time();
-        let date_timestamp = date_to_timestamp(parsed_date.0, parsed_date.1, parsed_date.2);
-        let fifty_years = 50 * 365 * 24 * 60 * 60 * 1_000_000_000u64; // 50 years in nanoseconds
-        
-        date_timestamp < current_time - fifty_years
-    } else {
-        false
+    is_more_than_years_before(date, &current_date(), 50)
+}
+
+// `date` is more than `days` days after `today`
+fn is_more_than_days_after(date: &str, today: &str, days: i64) -> bool {
+    match (calendar_date(date), calendar_date(today)) {
+        (Some(date), Some(today)) => today.checked_add(Duration::days(days)).is_some_and(|limit| date > limit),
+        _ => false,
+    }
+}
+
+// `date` is before the same day `years` years before `today` (29 February counts back
+// to 28 February in a common year)
+fn is_more_than_years_before(date: &str, today: &str, years: i32) -> bool {
+    let (date, today) = match (calendar_date(date), calendar_date(today)) {
+        (Some(date), Some(today)) => (date, today),
+        _ => return false,
+    };
+    let year = today.year() - years;
+    let cutoff = today
+        .replace_year(year)
+        .or_else(|_| Date::from_calendar_date(year, today.month(), today.day() - 1));
+    match cutoff {
+        Ok(cutoff) => date < cutoff,
+        Err(_) => false,
     }
 }
 
@@ -251,3 +308,73 @@ where
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leap_days_exist_only_in_leap_years() {
+        assert!(is_valid_date_format("2024-02-29"));
+        assert!(is_valid_date_format("2000-02-29"));
+        assert!(!is_valid_date_format("2023-02-29"));
+        assert!(!is_valid_date_format("1900-02-29"));
+    }
+
+    #[test]
+    fn impossible_dates_are_rejected() {
+        assert!(!is_valid_date_format("2024-02-30"));
+        assert!(!is_valid_date_format("2024-04-31"));
+        assert!(!is_valid_date_format("2024-13-01"));
+        assert!(!is_valid_date_format("2024-00-10"));
+        assert!(!is_valid_date_format("2024-01-00"));
+        assert!(!is_valid_date_format("2024-1-05"));
+        assert!(is_valid_date_format("2024-12-31"));
+        assert!(parse_date("2023-02-29").is_err());
+        assert_eq!(parse_date("2024-02-29"), Ok((2024, 2, 29)));
+    }
+
+    #[test]
+    fn days_between_follows_month_lengths() {
+        assert_eq!(days_between("2024-02-01", "2024-03-01"), Some(29));
+        assert_eq!(days_between("2023-02-01", "2023-03-01"), Some(28));
+        assert_eq!(days_between("2024-01-01", "2025-01-01"), Some(366));
+        assert_eq!(days_between("2023-01-31", "2023-01-01"), Some(-30));
+        assert_eq!(days_between("2023-02-29", "2023-03-01"), None);
+    }
+
+    #[test]
+    fn month_ends_account_for_leap_years() {
+        assert_eq!(period_end("2024-02"), Some("2024-02-29".to_string()));
+        assert_eq!(period_end("2023-02"), Some("2023-02-28".to_string()));
+        assert_eq!(period_end("2024-12"), Some("2024-12-31".to_string()));
+        assert_eq!(previous_month_end("2024-03-15"), Some("2024-02-29".to_string()));
+        assert_eq!(previous_month_end("2024-01-10"), Some("2023-12-31".to_string()));
+        assert_eq!(next_period("2024-12"), Some("2025-01".to_string()));
+    }
+
+    #[test]
+    fn timestamps_convert_to_utc_dates() {
+        assert_eq!(date_from_timestamp(0), "1970-01-01");
+        // 2024-02-29T23:59:59Z
+        assert_eq!(date_from_timestamp(1_709_251_199_000_000_000), "2024-02-29");
+        // 2024-03-01T00:00:00Z
+        assert_eq!(date_from_timestamp(1_709_251_200_000_000_000), "2024-03-01");
+    }
+
+    #[test]
+    fn future_limits_count_calendar_days() {
+        assert!(!is_more_than_days_after("2024-03-01", "2024-01-31", 30));
+        assert!(is_more_than_days_after("2023-03-03", "2023-01-31", 30));
+        assert!(!is_more_than_days_after("2024-02-29", "2024-02-29", 0));
+        assert!(is_more_than_days_after("2024-03-01", "2024-02-29", 0));
+    }
+
+    #[test]
+    fn age_limits_clamp_leap_days() {
+        assert!(!is_more_than_years_before("2022-02-28", "2024-02-29", 2));
+        assert!(is_more_than_years_before("2022-02-27", "2024-02-29", 2));
+        assert!(!is_more_than_years_before("1974-03-01", "2024-03-01", 50));
+        assert!(is_more_than_years_before("1974-02-28", "2024-03-01", 50));
+    }
+}
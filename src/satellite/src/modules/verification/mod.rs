@@ -0,0 +1,321 @@
+//! Verification Module - Pure Monetary Arithmetic and On-Canister Self Test
+//!
+//! The money computations the validators and jobs rely on (net salary, allocation of a
+//! payment over fee items, balances and payment status, proration, PAYE) are kept here or
+//! re-exported here as pure functions, with no document reads or writes, so they can be
+//! checked in isolation.
+//!
+//! `self_test` runs randomized consistency checks of these functions on the canister:
+//! totals are preserved, shares stay within bounds, results move the right way when an
+//! input grows. Run it after an upgrade to catch arithmetic regressions before they reach
+//! stored documents. Each run reports its seed so a failure can be traced.
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use super::fees::{allocation_rank, fee_assignment_status, FeeItemData, ALLOCATION_RULES, VALID_FEE_TYPES};
+use super::staff::tax;
+use super::utils::money::Money;
+
+pub use super::fees::allocate_to_items;
+
+// Randomized cases per property
+const CASES_PER_CHECK: u32 = 200;
+
+// Upper bound for generated amounts (₦5M)
+const MAX_GENERATED_KOBO: u64 = 500_000_000;
+
+// Failures listed in a report
+const MAX_REPORTED_FAILURES: usize = 20;
+
+// A named randomized property check
+type Check = (&'static str, fn(&mut Rng) -> Result<(), String>);
+
+#[derive(CandidType, Deserialize, Serialize)]
+pub struct SelfTestReport {
+    pub seed: u64,
+    pub checks: u32,
+    pub cases: u32,
+    pub passed: bool,
+    pub failures: Vec<String>,
+}
+
+/// Basic salary plus allowances.
+pub fn gross_salary(basic: Money, allowances: &[Money]) -> Money {
+    basic + allowances.iter().sum()
+}
+
+/// Basic salary plus allowances, less deductions.
+pub fn net_salary(basic: Money, allowances: &[Money], deductions: &[Money]) -> Money {
+    gross_salary(basic, allowances) - deductions.iter().sum()
+}
+
+/// Outstanding balance and payment status of an amount owed after payments.
+pub fn balance_after(total: Money, paid: Money) -> (Money, &'static str) {
+    let balance = total - paid;
+    (balance, fee_assignment_status(paid, balance))
+}
+
+/// `part` out of `whole` of an amount (e.g. days attended out of days in the term),
+/// rounded to the nearest kobo. A zero `whole` prorates to nothing.
+pub fn prorate(amount: Money, part: u32, whole: u32) -> Money {
+    if whole == 0 {
+        return Money::ZERO;
+    }
+    let scaled = amount.kobo() as i128 * part as i128;
+    let whole = whole as i128;
+    // Round half away from zero
+    let rounded = if scaled >= 0 { (scaled + whole / 2) / whole } else { (scaled - whole / 2) / whole };
+    Money::from_kobo(rounded as i64)
+}
+
+/// Monthly PAYE on a monthly gross, rounded to the naira as on salary payments.
+pub fn monthly_paye(monthly_gross: Money) -> Money {
+    Money::from_naira(tax::monthly_paye(monthly_gross.naira()).round())
+}
+
+/// Run randomized consistency checks of the monetary functions.
+pub fn self_test() -> SelfTestReport {
+    let seed = ic_cdk::api::time() | 1;
+    let mut rng = Rng(seed);
+    let mut failures = Vec::new();
+
+    let checks: [Check; 6] = [
+        ("money", check_money),
+        ("net_salary", check_net_salary),
+        ("balance", check_balance),
+        ("allocation", check_allocation),
+        ("proration", check_proration),
+        ("paye", check_paye),
+    ];
+    for (name, check) in checks.iter() {
+        for case in 0..CASES_PER_CHECK {
+            if let Err(message) = check(&mut rng) {
+                failures.push(format!("{} case {}: {}", name, case, message));
+                break;
+            }
+        }
+    }
+    failures.truncate(MAX_REPORTED_FAILURES);
+
+    SelfTestReport {
+        seed,
+        checks: checks.len() as u32,
+        cases: checks.len() as u32 * CASES_PER_CHECK,
+        passed: failures.is_empty(),
+        failures,
+    }
+}
+
+// Naira round trips, percentages of 0 and 100, and sums that match their parts
+fn check_money(rng: &mut Rng) -> Result<(), String> {
+    let a = rng.money();
+    let b = rng.money();
+    if Money::from_naira(a.naira()) != a {
+        return Err(format!("₦{} does not round-trip through naira", a));
+    }
+    if a.percent(100.0) != a || !a.percent(0.0).is_zero() {
+        return Err(format!("0% or 100% of ₦{} is wrong", a));
+    }
+    if (a + b) - b != a || [a, b].iter().sum::<Money>() != a + b {
+        return Err(format!("₦{} + ₦{} does not add up", a, b));
+    }
+    Ok(())
+}
+
+// Net plus deductions is the gross, and one more allowance raises net pay by exactly it
+fn check_net_salary(rng: &mut Rng) -> Result<(), String> {
+    let basic = rng.money();
+    let allowances = rng.amounts(5);
+    let deductions = rng.amounts(5);
+    let net = net_salary(basic, &allowances, &deductions);
+    let gross = gross_salary(basic, &allowances);
+    if net + deductions.iter().sum() != gross {
+        return Err(format!("net ₦{} plus deductions is not the gross ₦{}", net, gross));
+    }
+
+    let extra = rng.money();
+    let mut raised = allowances.clone();
+    raised.push(extra);
+    if net_salary(basic, &raised, &deductions) - net != extra {
+        return Err(format!("an allowance of ₦{} did not raise net pay by the same amount", extra));
+    }
+    Ok(())
+}
+
+// Balance is what is owed less what was paid, the status follows it, and paying in two
+// parts lands on the same balance as paying at once
+fn check_balance(rng: &mut Rng) -> Result<(), String> {
+    let total = rng.money();
+    let first = rng.money();
+    let second = rng.money();
+    let (balance, status) = balance_after(total, first + second);
+    if balance != total - first - second {
+        return Err(format!("balance ₦{} on ₦{} owed is wrong", balance, total));
+    }
+    let expected = if (first + second).is_zero() {
+        "unpaid"
+    } else if balance.is_negative() {
+        "overpaid"
+    } else if balance.is_zero() {
+        "paid"
+    } else {
+        "partial"
+    };
+    if status != expected {
+        return Err(format!("status '{}' for balance ₦{}, expected '{}'", status, balance, expected));
+    }
+
+    let (after_first, _) = balance_after(total, first);
+    let (after_second, _) = balance_after(after_first, second);
+    if after_second != balance {
+        return Err(format!("paying in two parts leaves ₦{}, at once ₦{}", after_second, balance));
+    }
+    let (exact, exact_status) = balance_after(total, total);
+    if total.is_positive() && (!exact.is_zero() || exact_status != "paid") {
+        return Err(format!("paying ₦{} in full does not settle it", total));
+    }
+    Ok(())
+}
+
+// Allocations never exceed the payment or an item's balance, cover the payment until the
+// items are settled, name each item once, and follow the priority
+fn check_allocation(rng: &mut Rng) -> Result<(), String> {
+    let items: Vec<FeeItemData> = (0..rng.below(8) + 1).map(|i| rng.fee_item(i)).collect();
+    let amount = rng.money();
+    let mut priority: Vec<String> = Vec::new();
+    for _ in 0..rng.below(4) {
+        priority.push(rng.pick(&ALLOCATION_RULES).to_string());
+        priority.push(rng.pick(&VALID_FEE_TYPES).to_string());
+    }
+
+    let allocations = allocate_to_items(&items, amount, &priority);
+    let allocated: Money = allocations.iter().map(|(_, share)| *share).sum();
+    let outstanding: Money = items.iter().map(|i| i.balance).filter(|b| b.is_positive()).sum();
+    if allocated != amount.min(outstanding) {
+        return Err(format!(
+            "₦{} allocated of a ₦{} payment over ₦{} outstanding",
+            allocated, amount, outstanding
+        ));
+    }
+
+    let mut last_rank = 0;
+    for (i, (category_id, share)) in allocations.iter().enumerate() {
+        let item = items
+            .iter()
+            .find(|item| &item.category_id == category_id)
+            .ok_or_else(|| format!("allocation to unknown item '{}'", category_id))?;
+        if !share.is_positive() || *share > item.balance {
+            return Err(format!("₦{} allocated to '{}' with ₦{} owing", share, category_id, item.balance));
+        }
+        if allocations[..i].iter().any(|(other, _)| other == category_id) {
+            return Err(format!("item '{}' allocated twice", category_id));
+        }
+        let rank = allocation_rank(item, &priority);
+        if rank < last_rank {
+            return Err(format!("item '{}' allocated out of priority order", category_id));
+        }
+        last_rank = rank;
+    }
+    Ok(())
+}
+
+// A share stays within the amount, the whole is the amount, and complementary shares
+// add back up within a kobo
+fn check_proration(rng: &mut Rng) -> Result<(), String> {
+    let amount = rng.money();
+    let whole = rng.below(365) as u32 + 1;
+    let part = rng.below(whole as u64 + 1) as u32;
+    let share = prorate(amount, part, whole);
+    if share.is_negative() || share > amount {
+        return Err(format!("{}/{} of ₦{} is ₦{}", part, whole, amount, share));
+    }
+    if prorate(amount, whole, whole) != amount || !prorate(amount, 0, whole).is_zero() {
+        return Err(format!("none or all of ₦{} does not prorate exactly", amount));
+    }
+    let rest = prorate(amount, whole - part, whole);
+    let difference = (share + rest - amount).kobo();
+    if difference.abs() > 1 {
+        return Err(format!(
+            "{}/{} and {}/{} of ₦{} add up to ₦{}",
+            part,
+            whole,
+            whole - part,
+            whole,
+            amount,
+            share + rest
+        ));
+    }
+    Ok(())
+}
+
+// PAYE is never negative, never more than the top band rate, nothing on a gross within
+// the minimum relief, and does not fall when pay rises
+fn check_paye(rng: &mut Rng) -> Result<(), String> {
+    let gross = rng.money();
+    let raise = rng.money();
+    let paye = monthly_paye(gross);
+    if paye.is_negative() || paye > gross.percent(24.0) + Money::from_naira(1.0) {
+        return Err(format!("PAYE ₦{} on a monthly gross of ₦{}", paye, gross));
+    }
+    if gross.naira() * 12.0 <= 200_000.0 && !paye.is_zero() {
+        return Err(format!("PAYE ₦{} on a monthly gross of ₦{} within relief", paye, gross));
+    }
+    if monthly_paye(gross + raise) < paye {
+        return Err(format!("PAYE fell when a monthly gross of ₦{} rose by ₦{}", gross, raise));
+    }
+    Ok(())
+}
+
+// xorshift64*: enough spread for test inputs, no entropy source needed in a query
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    // Mostly whole naira, sometimes with kobo, sometimes zero
+    fn money(&mut self) -> Money {
+        let kobo = match self.below(10) {
+            0 => 0,
+            1..=4 => self.below(MAX_GENERATED_KOBO / 100 + 1) * 100,
+            _ => self.below(MAX_GENERATED_KOBO + 1),
+        };
+        Money::from_kobo(kobo as i64)
+    }
+
+    fn amounts(&mut self, max_len: u64) -> Vec<Money> {
+        (0..self.below(max_len + 1)).map(|_| self.money()).collect()
+    }
+
+    fn pick<'a>(&mut self, values: &[&'a str]) -> &'a str {
+        values[self.below(values.len() as u64) as usize]
+    }
+
+    // An item part-paid, settled or overpaid
+    fn fee_item(&mut self, index: u64) -> FeeItemData {
+        let amount = self.money();
+        let amount_paid = self.money().min(amount + Money::from_naira(1_000.0));
+        let is_mandatory = self.below(2) == 0;
+        FeeItemData {
+            category_id: format!("category_{}", index),
+            category_name: format!("Category {}", index),
+            fee_type: self.pick(&VALID_FEE_TYPES).to_string(),
+            amount,
+            amount_paid,
+            balance: amount - amount_paid,
+            is_mandatory,
+            is_optional: Some(!is_mandatory),
+            is_selected: None,
+            extra: serde_json::Map::new(),
+        }
+    }
+}
@@ -105,6 +105,12 @@ export class ChartOfAccountsService extends BaseDataService<ChartOfAccounts> {
         accountType: "asset" as const,
         description: "Student fees receivable",
       },
+      {
+        accountCode: "1140",
+        accountName: "Prepaid Expenses",
+        accountType: "asset" as const,
+        description: "Expenses paid in advance, amortized monthly",
+      },
       {
         accountCode: "1200",
         accountName: "Fixed Assets",
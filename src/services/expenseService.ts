@@ -347,21 +347,25 @@ export class ExpenseService extends BaseDataService<Expense> {
     // Use the original pending expense ID to maintain consistency
     const approvedExpense = await this.createWithId(expenseId, expenseData);
 
-    // Auto-post journal entry for the approved expense
+    // Auto-post journal entry for the approved expense (the satellite posts split
+    // expenses line by line)
     try {
-      await autoPostingService.postExpense(
-        pendingExpense.amount,
-        pendingExpense.categoryName,
-        pendingExpense.paymentMethod,
-        pendingExpense.vendorName || "Vendor",
-        {
-          description: `${pendingExpense.categoryName} - ${pendingExpense.description}`,
-          reference: pendingExpense.reference,
-          transactionDate: pendingExpense.paymentDate,
-          createdBy: approvedBy,
-          autoPost: true,
-        },
-      );
+      const isSplit = Array.isArray(pendingExpense.splitLines) && pendingExpense.splitLines.length > 0;
+      if (!isSplit) {
+        await autoPostingService.postExpense(
+          pendingExpense.amount,
+          pendingExpense.categoryName,
+          pendingExpense.paymentMethod,
+          pendingExpense.vendorName || "Vendor",
+          {
+            description: `${pendingExpense.categoryName} - ${pendingExpense.description}`,
+            reference: pendingExpense.reference,
+            transactionDate: pendingExpense.paymentDate,
+            createdBy: approvedBy,
+            autoPost: true,
+          },
+        );
+      }
     } catch (error) {
       console.error("Failed to auto-post expense journal entry:", error);
       // Don't fail the approval if journal entry fails
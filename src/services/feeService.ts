@@ -526,6 +526,7 @@ export class StudentFeeAssignmentService extends BaseDataService<StudentFeeAssig
 
   /**
    * Record payment against fee assignment
+   * @deprecated The satellite applies confirmed payments to the assignment
    */
   async recordPayment(
     assignmentId: string,
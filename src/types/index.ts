@@ -519,11 +519,17 @@ export interface Payment {
   className: string;
   feeAssignmentId: string;
   amount: number;
-  paymentMethod: "cash" | "bank_transfer" | "pos" | "online" | "cheque";
+  paymentMethod: "cash" | "bank_transfer" | "pos" | "online" | "cheque" | "crypto";
   paymentDate: string;
   feeAllocations: PaymentAllocation[];
   reference: string;
   transactionId?: string;
+  cryptoTransfer?: CryptoTransfer;
+  verifiedBlock?: VerifiedBlock;
+  // Set when paid in a currency other than the school's base currency
+  currency?: string;
+  fxRate?: number;
+  baseAmount?: number;
   paidBy?: string;
   status: "pending" | "confirmed" | "cancelled" | "refunded";
   notes?: string;
@@ -534,6 +540,26 @@ export interface Payment {
   [key: string]: unknown;
 }
 
+// Ledger transfer a crypto payment was made by; amount in the token's smallest unit
+export interface CryptoTransfer {
+  token: "ICP" | "ckBTC";
+  blockIndex: number;
+  amount: number;
+  nairaRate: number;
+}
+
+// Set by the satellite once the block has been read from the ledger
+export interface VerifiedBlock {
+  token: string;
+  ledgerId: string;
+  blockIndex: number;
+  from: string;
+  to: string;
+  amount: number;
+  timestamp: number;
+  verifiedAt: number;
+}
+
 export interface PaymentAllocation {
   categoryId: string;
   categoryName: string;
@@ -616,6 +642,10 @@ export interface Expense {
   approvedAt?: bigint;
   notes?: string;
   recordedBy: string;
+  // Set when paid in a currency other than the school's base currency
+  currency?: string;
+  fxRate?: number;
+  baseAmount?: number;
   createdAt: bigint;
   updatedAt: bigint;
   [key: string]: unknown;
@@ -677,6 +707,7 @@ export interface StaffMember {
 export interface StaffAllowance {
   name: string;
   amount: number;
+  isTaxable?: boolean;
 }
 
 export interface SalaryPayment {